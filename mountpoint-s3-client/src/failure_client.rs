@@ -7,6 +7,7 @@ use std::fmt::Debug;
 use std::pin::Pin;
 use std::sync::Mutex;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::Stream;
@@ -14,12 +15,16 @@ use mountpoint_s3_crt::s3::client::BufferPoolUsageStats;
 use pin_project::pin_project;
 
 use crate::object_client::{
-    Checksum, CopyObjectError, CopyObjectParams, CopyObjectResult, DeleteObjectError, DeleteObjectResult, GetBodyPart,
-    GetObjectAttributesError, GetObjectAttributesResult, GetObjectError, GetObjectParams, GetObjectResponse,
-    HeadObjectError, HeadObjectParams, HeadObjectResult, ListObjectsError, ListObjectsResult, ObjectAttribute,
-    ObjectChecksumError, ObjectClient, ObjectClientError, ObjectClientResult, ObjectMetadata, PutObjectError,
-    PutObjectParams, PutObjectRequest, PutObjectResult, PutObjectSingleParams, RenameObjectError, RenameObjectParams,
-    RenameObjectResult, UploadReview,
+    AbortMultipartUploadResult, Checksum, CompleteMultipartUploadParams, CompletedPart, CopyObjectError,
+    CopyObjectParams, CopyObjectResult, CreateMultipartUploadParams, CreateMultipartUploadResult, DeleteObjectError,
+    DeleteObjectResult, DeleteObjectsResult, GetBodyPart, GetObjectAttributesError, GetObjectAttributesResult,
+    GetObjectError, GetObjectParams, GetObjectResponse, GetObjectTaggingError, GetObjectTaggingResult, HeadObjectError,
+    HeadObjectParams, HeadObjectResult, ListObjectVersionsError, ListObjectVersionsResult, ListObjectsError,
+    ListObjectsResult, MultipartUploadError, ObjectAttribute, ObjectChecksumError, ObjectClient, ObjectClientError,
+    ObjectClientResult, ObjectMetadata, ObjectTags, PresignGetObjectError, PresignGetObjectParams,
+    PresignGetObjectResult, PutObjectError, PutObjectParams, PutObjectRequest, PutObjectResult, PutObjectSingleParams,
+    PutObjectTaggingError, PutObjectTaggingResult, RenameObjectError, RenameObjectParams, RenameObjectResult,
+    UploadPartCopyParams, UploadPartParams, UploadPartResult, UploadReview,
 };
 
 // Wrapper for injecting failures into a get stream or a put request
@@ -98,6 +103,14 @@ where
         self.client.delete_object(bucket, key).await
     }
 
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, Self::ClientError> {
+        self.client.delete_objects(bucket, keys).await
+    }
+
     async fn copy_object(
         &self,
         source_bucket: &str,
@@ -152,6 +165,21 @@ where
             .await
     }
 
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, Self::ClientError> {
+        // TODO failure hook for list_object_versions
+        self.client
+            .list_object_versions(bucket, key_marker, version_id_marker, delimiter, max_keys, prefix)
+            .await
+    }
+
     async fn head_object(
         &self,
         bucket: &str,
@@ -212,6 +240,95 @@ where
         // TODO failure hook for rename_object
         self.client.rename_object(bucket, src_key, dst_key, params).await
     }
+
+    async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, Self::ClientError> {
+        // TODO failure hook for get_object_tagging
+        self.client.get_object_tagging(bucket, key).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, Self::ClientError> {
+        // TODO failure hook for put_object_tagging
+        self.client.put_object_tagging(bucket, key, tags).await
+    }
+
+    async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, Self::ClientError> {
+        // TODO failure hook for presign_get_object
+        self.client.presign_get_object(bucket, key, expires_in, params).await
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        // TODO failure hook for multipart uploads
+        self.client.create_multipart_upload(bucket, key, params).await
+    }
+
+    async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.client
+            .upload_part(bucket, key, upload_id, part_number, params, contents)
+            .await
+    }
+
+    async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.client
+            .upload_part_copy(bucket, key, upload_id, part_number, params)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, Self::ClientError> {
+        self.client
+            .complete_multipart_upload(bucket, key, upload_id, parts, params)
+            .await
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        self.client.abort_multipart_upload(bucket, key, upload_id).await
+    }
 }
 
 #[pin_project]
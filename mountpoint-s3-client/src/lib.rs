@@ -90,12 +90,16 @@ pub mod config {
 /// Types used by all object clients
 pub mod types {
     pub use super::object_client::{
-        Checksum, ChecksumAlgorithm, ChecksumMode, ClientBackpressureHandle, CopyObjectParams, CopyObjectResult,
-        DeleteObjectResult, ETag, GetBodyPart, GetObjectAttributesParts, GetObjectAttributesResult, GetObjectParams,
-        GetObjectResponse, HeadObjectParams, HeadObjectResult, ListObjectsResult, ObjectAttribute, ObjectClientResult,
-        ObjectInfo, ObjectPart, PutObjectParams, PutObjectResult, PutObjectSingleParams, PutObjectTrailingChecksums,
-        RenameObjectParams, RenameObjectResult, RenamePreconditionTypes, RestoreStatus, UploadChecksum, UploadReview,
-        UploadReviewPart,
+        AbortMultipartUploadResult, Checksum, ChecksumAlgorithm, ChecksumMode, ClientBackpressureHandle,
+        CompleteMultipartUploadParams, CompletedPart, CopyObjectParams, CopyObjectResult, CreateMultipartUploadParams,
+        CreateMultipartUploadResult, DeleteMarkerInfo, DeleteObjectResult, DeleteObjectsFailure, DeleteObjectsResult,
+        ETag, GetBodyPart, GetObjectAttributesParts, GetObjectAttributesResult, GetObjectParams, GetObjectResponse,
+        GetObjectTaggingResult, HeadObjectParams, HeadObjectResult, ListObjectVersionsResult, ListObjectsResult,
+        MAX_PRESIGNED_URL_EXPIRATION, ObjectAttribute, ObjectClientResult, ObjectInfo, ObjectMetadata, ObjectPart,
+        ObjectTags, ObjectVersionInfo, PresignGetObjectParams, PresignGetObjectResult, PutObjectParams,
+        PutObjectResult, PutObjectSingleParams, PutObjectTaggingResult, PutObjectTrailingChecksums, RenameObjectParams,
+        RenameObjectResult, RenamePreconditionTypes, RestoreStatus, UploadChecksum, UploadPartCopyParams,
+        UploadPartParams, UploadPartResult, UploadReview, UploadReviewPart,
     };
 }
 
@@ -106,8 +110,9 @@ pub mod types {
 /// client errors. See its documentation for more details.
 pub mod error {
     pub use super::object_client::{
-        CopyObjectError, DeleteObjectError, GetObjectAttributesError, GetObjectError, HeadObjectError,
-        ListObjectsError, ObjectClientError, PutObjectError, RenameObjectError,
+        CopyObjectError, DeleteObjectError, GetObjectAttributesError, GetObjectError, GetObjectTaggingError,
+        HeadObjectError, ListObjectVersionsError, ListObjectsError, MultipartUploadError, ObjectClientError,
+        PresignGetObjectError, PutObjectError, PutObjectTaggingError, RenameObjectError,
     };
     #[doc(hidden)]
     pub use super::s3_crt_client::CrtError;
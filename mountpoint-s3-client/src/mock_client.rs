@@ -28,14 +28,19 @@ use crate::checksums::{
 };
 use crate::error_metadata::{ClientErrorMetadata, ProvideErrorMetadata};
 use crate::object_client::{
-    Checksum, ChecksumAlgorithm, ChecksumMode, ClientBackpressureHandle, CopyObjectError, CopyObjectParams,
-    CopyObjectResult, DeleteObjectError, DeleteObjectResult, ETag, GetBodyPart, GetObjectAttributesError,
-    GetObjectAttributesParts, GetObjectAttributesResult, GetObjectError, GetObjectParams, GetObjectResponse,
-    HeadObjectError, HeadObjectParams, HeadObjectResult, ListObjectsError, ListObjectsResult, ObjectAttribute,
+    AbortMultipartUploadResult, Checksum, ChecksumAlgorithm, ChecksumMode, ClientBackpressureHandle,
+    CompleteMultipartUploadParams, CompletedPart, CopyObjectError, CopyObjectParams, CopyObjectResult,
+    CreateMultipartUploadParams, CreateMultipartUploadResult, DeleteMarkerInfo, DeleteObjectError, DeleteObjectResult,
+    DeleteObjectsResult, ETag, GetBodyPart, GetObjectAttributesError, GetObjectAttributesParts,
+    GetObjectAttributesResult, GetObjectError, GetObjectParams, GetObjectResponse, GetObjectTaggingError,
+    GetObjectTaggingResult, HeadObjectError, HeadObjectParams, HeadObjectResult, ListObjectVersionsError,
+    ListObjectVersionsResult, ListObjectsError, ListObjectsResult, MultipartUploadError, ObjectAttribute,
     ObjectChecksumError, ObjectClient, ObjectClientError, ObjectClientResult, ObjectInfo, ObjectMetadata, ObjectPart,
-    PutObjectError, PutObjectParams, PutObjectRequest, PutObjectResult, PutObjectSingleParams,
-    PutObjectTrailingChecksums, RenameObjectError, RenameObjectParams, RenameObjectResult, RenamePreconditionTypes,
-    RestoreStatus, UploadChecksum, UploadReview, UploadReviewPart,
+    ObjectTags, ObjectVersionInfo, PresignGetObjectError, PresignGetObjectParams, PresignGetObjectResult,
+    PutObjectError, PutObjectParams, PutObjectRequest, PutObjectResult, PutObjectSingleParams, PutObjectTaggingError,
+    PutObjectTaggingResult, PutObjectTrailingChecksums, RenameObjectError, RenameObjectParams, RenameObjectResult,
+    RenamePreconditionTypes, RestoreStatus, UploadChecksum, UploadPartCopyParams, UploadPartParams, UploadPartResult,
+    UploadReview, UploadReviewPart,
 };
 
 mod leaky_bucket;
@@ -128,11 +133,27 @@ impl MockClientConfig {
 pub struct MockClient {
     config: MockClientConfig,
     objects: Arc<RwLock<BTreeMap<String, MockObject>>>,
+    /// Noncurrent versions of objects and delete markers, by key.
+    noncurrent_versions: Arc<RwLock<BTreeMap<String, Vec<MockVersion>>>>,
     in_progress_uploads: Arc<RwLock<BTreeSet<String>>>,
+    /// Multipart uploads created with [ObjectClient::create_multipart_upload], by upload ID.
+    multipart_uploads: Arc<RwLock<HashMap<String, MockMultipartUpload>>>,
     operation_counts: Arc<RwLock<HashMap<Operation, u64>>>,
     read_window_increment_failed: Arc<AtomicBool>,
 }
 
+/// Minimum size of the parts of a multipart upload other than the last one.
+const MIN_MULTIPART_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// A multipart upload in progress in the mock bucket.
+#[derive(Debug)]
+struct MockMultipartUpload {
+    key: String,
+    params: CreateMultipartUploadParams,
+    /// The ETag and the content of the uploaded parts, by part number.
+    parts: BTreeMap<usize, (ETag, Box<[u8]>)>,
+}
+
 fn add_object(objects: &Arc<RwLock<BTreeMap<String, MockObject>>>, key: &str, value: MockObject) {
     objects.write().unwrap().insert(key.to_owned(), value);
 }
@@ -144,7 +165,9 @@ impl MockClient {
         Self {
             config,
             objects: Default::default(),
+            noncurrent_versions: Default::default(),
             in_progress_uploads: Default::default(),
+            multipart_uploads: Default::default(),
             operation_counts: Default::default(),
             read_window_increment_failed,
         }
@@ -160,6 +183,59 @@ impl MockClient {
         add_object(&self.objects, key, value);
     }
 
+    /// Add a noncurrent version of an object to this mock client's bucket. The version should have a version ID. The
+    /// current version of the object is the one added with [MockClient::add_object], unless a version or delete
+    /// marker was last modified after it.
+    pub fn add_object_version(&self, key: &str, version: MockObject) {
+        assert!(version.version_id.is_some(), "versions should have a version ID");
+        self.add_version(key, MockVersion::Object(version.into()));
+    }
+
+    /// Add a delete marker of an object to this mock client's bucket.
+    pub fn add_delete_marker(&self, key: &str, version_id: &str, last_modified: OffsetDateTime) {
+        let delete_marker = MockVersion::DeleteMarker {
+            version_id: version_id.to_owned(),
+            last_modified,
+        };
+        self.add_version(key, delete_marker);
+    }
+
+    fn add_version(&self, key: &str, version: MockVersion) {
+        let mut noncurrent_versions = self.noncurrent_versions.write().unwrap();
+        noncurrent_versions.entry(key.to_owned()).or_default().push(version);
+    }
+
+    /// All the versions of the objects under a prefix, ordered by key and then from the newest to the oldest.
+    fn object_versions(&self, prefix: &str) -> Vec<(String, MockVersion)> {
+        let objects = self.objects.read().unwrap();
+        let noncurrent_versions = self.noncurrent_versions.read().unwrap();
+        let keys: BTreeSet<&String> = objects
+            .keys()
+            .chain(noncurrent_versions.keys())
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+        let mut result = Vec::new();
+        for key in keys {
+            let mut versions: Vec<MockVersion> = objects
+                .get(key)
+                .map(|object| MockVersion::Object(object.clone().into()))
+                .into_iter()
+                .chain(noncurrent_versions.get(key).into_iter().flatten().cloned())
+                .collect();
+            versions.sort_by_key(|version| std::cmp::Reverse(version.last_modified()));
+            result.extend(versions.into_iter().map(|version| (key.clone(), version)));
+        }
+        result
+    }
+
+    /// A version of an object, if it exists.
+    fn object_version(&self, key: &str, version_id: &str) -> Option<MockVersion> {
+        self.object_versions(key)
+            .into_iter()
+            .find(|(version_key, version)| version_key == key && version.version_id() == version_id)
+            .map(|(_, version)| version)
+    }
+
     /// Remove object for the mock client's bucket
     pub fn remove_object(&self, key: &str) {
         self.objects.write().unwrap().remove(key);
@@ -191,6 +267,30 @@ impl MockClient {
         self.in_progress_uploads.read().unwrap().contains(key)
     }
 
+    /// Returns the number of multipart uploads created with [ObjectClient::create_multipart_upload] which were
+    /// neither completed nor aborted
+    pub fn multipart_upload_count(&self) -> usize {
+        self.multipart_uploads.read().unwrap().len()
+    }
+
+    /// Add an uploaded part to a multipart upload.
+    fn add_multipart_upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        contents: Box<[u8]>,
+    ) -> Result<ETag, MultipartUploadError> {
+        let mut uploads = self.multipart_uploads.write().unwrap();
+        let upload = uploads
+            .get_mut(upload_id)
+            .filter(|upload| upload.key == key)
+            .ok_or(MultipartUploadError::NoSuchUpload)?;
+        let etag = ETag::from_object_bytes(&contents);
+        upload.parts.insert(part_number, (etag.clone(), contents));
+        Ok(etag)
+    }
+
     /// Returns the objects storage class
     pub fn get_object_storage_class(&self, key: &str) -> Result<Option<String>, MockClientError> {
         if let Some(mock_object) = self.objects.read().unwrap().get(key) {
@@ -248,11 +348,19 @@ impl MockClient {
             return self.append_object(key, offset, params, contents);
         }
 
+        if let Some(etag) = &params.if_match
+            && self.objects.read().unwrap().get(key).map(|object| &object.etag) != Some(etag)
+        {
+            return Err(ObjectClientError::ServiceError(PutObjectError::PreconditionFailed));
+        }
+
         let checksum = validate_checksum(contents.as_ref(), params.checksum.as_ref())?;
 
         let mut object: MockObject = contents.into();
         object.set_storage_class(params.storage_class.clone());
         object.set_object_metadata(params.object_metadata.clone());
+        object.set_tags(params.object_tags.clone());
+        object.set_content_type(params.content_type.clone());
         object.set_checksum(checksum);
 
         let etag = object.etag.clone();
@@ -270,6 +378,96 @@ impl MockClient {
         op_counts.entry(operation).and_modify(|count| *count += 1).or_insert(1);
     }
 
+    /// List object versions implementation
+    fn list_object_versions_ordered(
+        &self,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ListObjectVersionsResult {
+        let delimiter = (!delimiter.is_empty()).then_some(delimiter);
+        let mut result = ListObjectVersionsResult {
+            versions: Vec::new(),
+            delete_markers: Vec::new(),
+            common_prefixes: Vec::new(),
+            next_key_marker: None,
+            next_version_id_marker: None,
+        };
+        let mut count = 0;
+        // Key and version ID of the last entry returned, from which the next page starts.
+        let mut last: Option<(String, Option<String>)> = None;
+        let mut skipping = key_marker.is_some();
+        let mut previous_key = None;
+
+        for (key, version) in self.object_versions(prefix) {
+            // Versions are ordered from the newest, which is the current one.
+            let is_latest = previous_key.as_ref() != Some(&key);
+            previous_key = Some(key.clone());
+
+            // Skip the versions up to and including the one of the markers.
+            if let Some(marker) = key_marker.filter(|_| skipping) {
+                let under_marker = delimiter.is_some_and(|d| marker.ends_with(d)) && key.starts_with(marker);
+                if key.as_str() < marker || under_marker {
+                    continue;
+                }
+                if key == marker {
+                    if version_id_marker == Some(version.version_id()) {
+                        skipping = false;
+                    }
+                    continue;
+                }
+                skipping = false;
+            }
+
+            let common_prefix = delimiter.and_then(|d| {
+                key[prefix.len()..]
+                    .split_once(d)
+                    .map(|(pre, _)| format!("{prefix}{pre}{d}"))
+            });
+            if common_prefix.is_some() && result.common_prefixes.last() == common_prefix.as_ref() {
+                continue;
+            }
+            if count == max_keys {
+                let (next_key_marker, next_version_id_marker) = last.unzip();
+                result.next_key_marker = next_key_marker;
+                result.next_version_id_marker = next_version_id_marker.flatten();
+                break;
+            }
+            count += 1;
+            if let Some(common_prefix) = common_prefix {
+                result.common_prefixes.push(common_prefix.clone());
+                last = Some((common_prefix, None));
+                continue;
+            }
+
+            last = Some((key.clone(), Some(version.version_id().to_owned())));
+            match version {
+                MockVersion::Object(object) => result.versions.push(ObjectVersionInfo {
+                    version_id: object.version_id.clone().unwrap_or_else(|| NULL_VERSION_ID.to_owned()),
+                    key,
+                    is_latest,
+                    size: object.len() as u64,
+                    last_modified: object.last_modified,
+                    storage_class: object.storage_class.clone(),
+                    restore_status: object.restore_status,
+                    etag: object.etag.as_str().to_owned(),
+                }),
+                MockVersion::DeleteMarker {
+                    version_id,
+                    last_modified,
+                } => result.delete_markers.push(DeleteMarkerInfo {
+                    key,
+                    version_id,
+                    is_latest,
+                    last_modified,
+                }),
+            }
+        }
+        result
+    }
+
     /// Ordered list implementation
     fn list_objects_ordered(
         &self,
@@ -455,6 +653,8 @@ impl MockClient {
                 let mut object = MockObject::from(contents);
                 object.set_storage_class(params.storage_class.clone());
                 object.set_object_metadata(params.object_metadata.clone());
+                object.set_tags(params.object_tags.clone());
+                object.set_content_type(params.content_type.clone());
                 object.set_checksum(checksum);
                 objects.insert(key.to_owned(), object);
                 objects.get_mut(key).unwrap()
@@ -505,14 +705,23 @@ impl MockClient {
 #[derive(Debug, Eq, Hash, PartialEq)]
 pub enum Operation {
     DeleteObject,
+    DeleteObjects,
     HeadObject,
     GetObject,
     GetObjectAttributes,
     ListObjectsV2,
+    ListObjectVersions,
     PutObject,
     RenameObject,
     CopyObject,
     PutObjectSingle,
+    GetObjectTagging,
+    PutObjectTagging,
+    CreateMultipartUpload,
+    UploadPart,
+    UploadPartCopy,
+    CompleteMultipartUpload,
+    AbortMultipartUpload,
 }
 
 /// Counter for a specific client [Operation].
@@ -535,6 +744,35 @@ impl OperationCounter<'_> {
     }
 }
 
+/// Version ID of the objects created before versioning was enabled on their bucket.
+const NULL_VERSION_ID: &str = "null";
+
+/// A version of an object in the mock bucket, or a delete marker.
+#[derive(Debug, Clone)]
+enum MockVersion {
+    Object(Box<MockObject>),
+    DeleteMarker {
+        version_id: String,
+        last_modified: OffsetDateTime,
+    },
+}
+
+impl MockVersion {
+    fn version_id(&self) -> &str {
+        match self {
+            MockVersion::Object(object) => object.version_id.as_deref().unwrap_or(NULL_VERSION_ID),
+            MockVersion::DeleteMarker { version_id, .. } => version_id,
+        }
+    }
+
+    fn last_modified(&self) -> OffsetDateTime {
+        match self {
+            MockVersion::Object(object) => object.last_modified,
+            MockVersion::DeleteMarker { last_modified, .. } => *last_modified,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MockObject {
     generator: Arc<dyn Fn(u64, usize) -> Box<[u8]> + Send + Sync>,
@@ -545,6 +783,14 @@ pub struct MockObject {
     etag: ETag,
     parts: Option<MockObjectParts>,
     object_metadata: HashMap<String, String>,
+    version_id: Option<String>,
+    content_encoding: Option<String>,
+    content_type: Option<String>,
+    cache_control: Option<String>,
+    content_disposition: Option<String>,
+    sse_type: Option<String>,
+    sse_kms_key_id: Option<String>,
+    tags: ObjectTags,
     /// S3 checksums associated with the object.
     ///
     /// Typically, at most one of the checksums should be set.
@@ -568,6 +814,14 @@ impl MockObject {
             etag,
             parts: None,
             object_metadata: HashMap::new(),
+            version_id: None,
+            content_encoding: None,
+            content_type: None,
+            cache_control: None,
+            content_disposition: None,
+            sse_type: None,
+            sse_kms_key_id: None,
+            tags: ObjectTags::new(),
             checksum: Checksum::empty(),
         }
     }
@@ -582,6 +836,14 @@ impl MockObject {
             etag,
             parts: None,
             object_metadata: HashMap::new(),
+            version_id: None,
+            content_encoding: None,
+            content_type: None,
+            cache_control: None,
+            content_disposition: None,
+            sse_type: None,
+            sse_kms_key_id: None,
+            tags: ObjectTags::new(),
             checksum: Checksum::empty(),
         }
     }
@@ -606,6 +868,14 @@ impl MockObject {
             etag,
             parts: None,
             object_metadata: HashMap::new(),
+            version_id: None,
+            content_encoding: None,
+            content_type: None,
+            cache_control: None,
+            content_disposition: None,
+            sse_type: None,
+            sse_kms_key_id: None,
+            tags: ObjectTags::new(),
             checksum: Checksum::empty(),
         }
     }
@@ -648,6 +918,43 @@ impl MockObject {
         self.object_metadata = object_metadata;
     }
 
+    pub fn set_tags(&mut self, tags: ObjectTags) {
+        self.tags = tags;
+    }
+
+    pub fn tags(&self) -> &ObjectTags {
+        &self.tags
+    }
+
+    pub fn set_version_id(&mut self, version_id: Option<String>) {
+        self.version_id = version_id;
+    }
+
+    pub fn set_content_encoding(&mut self, content_encoding: Option<String>) {
+        self.content_encoding = content_encoding;
+    }
+
+    pub fn set_content_type(&mut self, content_type: Option<String>) {
+        self.content_type = content_type;
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn set_cache_control(&mut self, cache_control: Option<String>) {
+        self.cache_control = cache_control;
+    }
+
+    pub fn set_content_disposition(&mut self, content_disposition: Option<String>) {
+        self.content_disposition = content_disposition;
+    }
+
+    pub fn set_server_side_encryption(&mut self, sse_type: Option<String>, sse_kms_key_id: Option<String>) {
+        self.sse_type = sse_type;
+        self.sse_kms_key_id = sse_kms_key_id;
+    }
+
     pub fn set_restored(&mut self, restore_status: Option<RestoreStatus>) {
         self.restore_status = restore_status;
     }
@@ -717,6 +1024,18 @@ fn compute_checksum(content: &[u8], algorithms: &[ChecksumAlgorithm]) -> Checksu
     checksum
 }
 
+/// The field of a [Checksum] holding the checksum of an algorithm.
+fn checksum_field<'a>(checksum: &'a mut Checksum, algorithm: &ChecksumAlgorithm) -> &'a mut Option<String> {
+    match algorithm {
+        ChecksumAlgorithm::Crc64nvme => &mut checksum.checksum_crc64nvme,
+        ChecksumAlgorithm::Crc32 => &mut checksum.checksum_crc32,
+        ChecksumAlgorithm::Crc32c => &mut checksum.checksum_crc32c,
+        ChecksumAlgorithm::Sha1 => &mut checksum.checksum_sha1,
+        ChecksumAlgorithm::Sha256 => &mut checksum.checksum_sha256,
+        algorithm => unimplemented!("unknown checksum algorithm: {:?}", algorithm),
+    }
+}
+
 /// Validate data against the [UploadChecksum] and return the [Checksum] to be stored.
 fn validate_checksum(
     contents: &[u8],
@@ -911,21 +1230,62 @@ impl ObjectClient for MockClient {
         Ok(DeleteObjectResult {})
     }
 
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, Self::ClientError> {
+        trace!(bucket, keys = keys.len(), "DeleteObjects");
+        self.inc_op_count(Operation::DeleteObjects);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(DeleteObjectError::NoSuchBucket));
+        }
+        if keys.len() > 1000 {
+            return Err(ObjectClientError::ClientError(MockClientError(
+                "DeleteObjects accepts at most 1000 keys".into(),
+            )));
+        }
+
+        let mut objects = self.objects.write().unwrap();
+        for key in keys {
+            objects.remove(key);
+        }
+
+        Ok(DeleteObjectsResult::default())
+    }
+
     async fn copy_object(
         &self,
         source_bucket: &str,
         source_key: &str,
         destination_bucket: &str,
         destination_key: &str,
-        _params: &CopyObjectParams,
+        params: &CopyObjectParams,
     ) -> ObjectClientResult<CopyObjectResult, CopyObjectError, Self::ClientError> {
         if destination_bucket != self.config.bucket && source_bucket != self.config.bucket {
             return Err(ObjectClientError::ServiceError(CopyObjectError::NotFound));
         }
 
         let mut objects = self.objects.write().unwrap();
+        if params.if_none_match.as_deref() == Some("*") && objects.contains_key(destination_key) {
+            return Err(ObjectClientError::ServiceError(CopyObjectError::PreconditionFailed));
+        }
         if let Some(object) = objects.get(source_key) {
-            let cloned_object = object.clone();
+            let mut cloned_object = object.clone();
+            if let Some(object_metadata) = &params.object_metadata {
+                cloned_object.set_object_metadata(object_metadata.clone());
+                cloned_object.set_content_type(params.content_type.clone());
+                cloned_object.set_content_encoding(params.content_encoding.clone());
+                cloned_object.set_cache_control(params.cache_control.clone());
+                cloned_object.set_content_disposition(params.content_disposition.clone());
+            }
+            // Like S3, encrypt the copy as requested rather than like the source object.
+            cloned_object
+                .set_server_side_encryption(params.server_side_encryption.clone(), params.ssekms_key_id.clone());
+            if params.storage_class.is_some() {
+                cloned_object.set_storage_class(params.storage_class.clone());
+            }
             objects.insert(destination_key.to_owned(), cloned_object);
             Ok(CopyObjectResult {})
         } else {
@@ -939,7 +1299,7 @@ impl ObjectClient for MockClient {
         key: &str,
         params: &GetObjectParams,
     ) -> ObjectClientResult<Self::GetObjectResponse, GetObjectError, Self::ClientError> {
-        trace!(bucket, key, ?params.range, ?params.if_match, "GetObject");
+        trace!(bucket, key, ?params.range, ?params.if_match, ?params.version_id, "GetObject");
         self.inc_op_count(Operation::GetObject);
 
         if bucket != self.config.bucket {
@@ -948,9 +1308,16 @@ impl ObjectClient for MockClient {
             )));
         }
 
-        let objects = self.objects.read().unwrap();
+        let object = match params.version_id.as_deref() {
+            Some(version_id) => match self.object_version(key, version_id) {
+                Some(MockVersion::Object(object)) => Some(*object),
+                // S3 rejects GetObject on delete markers with a MethodNotAllowed error, reported as a missing key here.
+                Some(MockVersion::DeleteMarker { .. }) | None => None,
+            },
+            None => self.objects.read().unwrap().get(key).cloned(),
+        };
 
-        if let Some(object) = objects.get(key) {
+        if let Some(object) = object {
             if let Some(etag_match) = params.if_match.as_ref()
                 && etag_match != &object.etag
             {
@@ -967,6 +1334,7 @@ impl ObjectClient for MockClient {
             } else {
                 (0, object.len())
             };
+            let part_size = params.part_size.unwrap_or(self.config.part_size);
 
             let backpressure_handle = if self.config.enable_backpressure {
                 let read_window_end_offset = Arc::new(AtomicU64::new(
@@ -975,7 +1343,7 @@ impl ObjectClient for MockClient {
                 Some(MockBackpressureHandle {
                     read_window_end_offset,
                     request_range: next_offset..next_offset + length as u64,
-                    part_size: self.read_part_size() as u64,
+                    part_size: part_size as u64,
                     read_window_increment_failed: self.read_window_increment_failed.clone(),
                     fail_on_non_aligned_read_window: self.config.fail_on_non_aligned_read_window,
                 })
@@ -983,10 +1351,10 @@ impl ObjectClient for MockClient {
                 None
             };
             Ok(MockGetObjectResponse {
-                object: object.clone(),
+                object,
                 next_offset,
                 length,
-                part_size: self.config.part_size,
+                part_size,
                 backpressure_handle,
             })
         } else {
@@ -1024,8 +1392,14 @@ impl ObjectClient for MockClient {
                 storage_class: object.storage_class.clone(),
                 restore_status: object.restore_status,
                 checksum,
-                sse_type: None,
-                sse_kms_key_id: None,
+                sse_type: object.sse_type.clone(),
+                sse_kms_key_id: object.sse_kms_key_id.clone(),
+                object_metadata: object.object_metadata.clone(),
+                version_id: object.version_id.clone(),
+                content_encoding: object.content_encoding.clone(),
+                content_type: object.content_type.clone(),
+                cache_control: object.cache_control.clone(),
+                content_disposition: object.content_disposition.clone(),
             })
         } else {
             Err(ObjectClientError::ServiceError(HeadObjectError::NotFound))
@@ -1054,6 +1428,33 @@ impl ObjectClient for MockClient {
         }
     }
 
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, Self::ClientError> {
+        trace!(
+            bucket,
+            ?key_marker,
+            ?version_id_marker,
+            delimiter,
+            max_keys,
+            prefix,
+            "ListObjectVersions"
+        );
+        self.inc_op_count(Operation::ListObjectVersions);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(ListObjectVersionsError::NoSuchBucket));
+        }
+
+        Ok(self.list_object_versions_ordered(key_marker, version_id_marker, delimiter, max_keys, prefix))
+    }
+
     async fn put_object(
         &self,
         bucket: &str,
@@ -1069,7 +1470,7 @@ impl ObjectClient for MockClient {
 
         let put_request = MockPutObjectRequest::new(
             key,
-            self.config.part_size,
+            params.part_size.unwrap_or(self.config.part_size),
             params,
             &self.objects,
             &self.in_progress_uploads,
@@ -1117,6 +1518,8 @@ impl ObjectClient for MockClient {
                     ObjectAttribute::ETag => result.etag = Some(object.etag.as_str().to_owned()),
                     ObjectAttribute::Checksum => result.checksum = Some(object.checksum.clone()),
                     ObjectAttribute::ObjectParts => {
+                        // The parts were uploaded with the algorithm of the checksum of the object.
+                        let part_checksum_algorithm = object.checksum.algorithms().into_iter().next();
                         let parts = match &object.parts {
                             Some(MockObjectParts::Count(num_parts)) => Some(GetObjectAttributesParts {
                                 is_truncated: None,
@@ -1136,13 +1539,14 @@ impl ObjectClient for MockClient {
                                         .iter()
                                         .enumerate()
                                         .map(|(i, part)| ObjectPart {
-                                            checksum: Some(Checksum {
-                                                checksum_crc64nvme: None,
-                                                checksum_crc32: None,
-                                                checksum_crc32c: part.checksum.clone(),
-                                                checksum_sha1: None,
-                                                checksum_sha256: None,
-                                            }),
+                                            checksum: Some(part_checksum_algorithm.as_ref().map_or_else(
+                                                Checksum::empty,
+                                                |algorithm| {
+                                                    let mut checksum = Checksum::empty();
+                                                    *checksum_field(&mut checksum, algorithm) = part.checksum.clone();
+                                                    checksum
+                                                },
+                                            )),
                                             // Part numbers start at 1
                                             part_number: i + 1,
                                             size: part.size,
@@ -1215,6 +1619,259 @@ impl ObjectClient for MockClient {
         trace!("renamed in bucket");
         Ok(RenameObjectResult {})
     }
+
+    async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, Self::ClientError> {
+        trace!(bucket, key, "GetObjectTagging");
+        self.inc_op_count(Operation::GetObjectTagging);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(GetObjectTaggingError::NoSuchBucket));
+        }
+
+        match self.objects.read().unwrap().get(key) {
+            Some(object) => Ok(GetObjectTaggingResult {
+                tags: object.tags.clone(),
+            }),
+            None => Err(ObjectClientError::ServiceError(GetObjectTaggingError::NoSuchKey)),
+        }
+    }
+
+    async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, Self::ClientError> {
+        trace!(bucket, key, "PutObjectTagging");
+        self.inc_op_count(Operation::PutObjectTagging);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(PutObjectTaggingError::NoSuchBucket));
+        }
+        // S3 allows up to 10 tags per object.
+        if tags.len() > 10 {
+            return Err(ObjectClientError::ServiceError(PutObjectTaggingError::InvalidTag));
+        }
+
+        match self.objects.write().unwrap().get_mut(key) {
+            Some(object) => {
+                object.tags = tags.clone();
+                Ok(PutObjectTaggingResult {})
+            }
+            None => Err(ObjectClientError::ServiceError(PutObjectTaggingError::NoSuchKey)),
+        }
+    }
+
+    async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, Self::ClientError> {
+        trace!(bucket, key, "PresignGetObject");
+
+        // Like S3, pre-signing doesn't look up the object, and the URL is never made by the mock client.
+        PresignGetObjectError::check_expiration(expires_in).map_err(ObjectClientError::ServiceError)?;
+        let mut url = format!(
+            "https://{bucket}.s3.mock.amazonaws.com/{key}?X-Amz-Expires={}",
+            expires_in.as_secs()
+        );
+        if let Some(version_id) = &params.version_id {
+            url.push_str(&format!("&versionId={version_id}"));
+        }
+        Ok(PresignGetObjectResult { url })
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        trace!(bucket, key, "CreateMultipartUpload");
+        self.inc_op_count(Operation::CreateMultipartUpload);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchBucket));
+        }
+
+        let upload_id = format!("upload-{}", rand::random::<u64>());
+        let upload = MockMultipartUpload {
+            key: key.to_owned(),
+            params: params.clone(),
+            parts: BTreeMap::new(),
+        };
+        self.multipart_uploads
+            .write()
+            .unwrap()
+            .insert(upload_id.clone(), upload);
+        Ok(CreateMultipartUploadResult { upload_id })
+    }
+
+    async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        trace!(bucket, key, upload_id, part_number, "UploadPart");
+        self.inc_op_count(Operation::UploadPart);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchBucket));
+        }
+        if validate_checksum(contents.as_ref(), params.checksum.as_ref()).is_err() {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::BadChecksum));
+        }
+
+        let etag = self
+            .add_multipart_upload_part(key, upload_id, part_number, contents.as_ref().into())
+            .map_err(ObjectClientError::ServiceError)?;
+        Ok(UploadPartResult {
+            etag,
+            checksum: params.checksum.clone(),
+        })
+    }
+
+    async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        trace!(bucket, key, upload_id, part_number, source_key = params.source_key, ?params.source_range, "UploadPartCopy");
+        self.inc_op_count(Operation::UploadPartCopy);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchBucket));
+        }
+
+        let contents = {
+            let objects = self.objects.read().unwrap();
+            let Some(object) = objects.get(&params.source_key) else {
+                return Err(ObjectClientError::ServiceError(MultipartUploadError::SourceNotFound));
+            };
+            if params.source_if_match.as_ref().is_some_and(|etag| *etag != object.etag) {
+                return Err(ObjectClientError::ServiceError(
+                    MultipartUploadError::PreconditionFailed,
+                ));
+            }
+            let range = params.source_range.clone().unwrap_or(0..object.size as u64);
+            if range.end > object.size as u64 || range.start >= range.end {
+                return Err(ObjectClientError::ClientError(MockClientError(
+                    "invalid source range for UploadPartCopy".into(),
+                )));
+            }
+            object.read(range.start, (range.end - range.start) as usize)
+        };
+
+        let etag = self
+            .add_multipart_upload_part(key, upload_id, part_number, contents)
+            .map_err(ObjectClientError::ServiceError)?;
+        Ok(UploadPartResult { etag, checksum: None })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, Self::ClientError> {
+        trace!(bucket, key, upload_id, parts = parts.len(), "CompleteMultipartUpload");
+        self.inc_op_count(Operation::CompleteMultipartUpload);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchBucket));
+        }
+
+        let mut uploads = self.multipart_uploads.write().unwrap();
+        let upload = uploads
+            .get(upload_id)
+            .filter(|upload| upload.key == key)
+            .ok_or(ObjectClientError::ServiceError(MultipartUploadError::NoSuchUpload))?;
+        if parts.is_empty() || !parts.is_sorted_by(|a, b| a.part_number < b.part_number) {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::InvalidPart));
+        }
+        let mut buffer = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            let Some((etag, contents)) = upload
+                .parts
+                .get(&part.part_number)
+                .filter(|(etag, _)| *etag == part.etag)
+            else {
+                return Err(ObjectClientError::ServiceError(MultipartUploadError::InvalidPart));
+            };
+            if i + 1 < parts.len() && contents.len() < MIN_MULTIPART_PART_SIZE {
+                return Err(ObjectClientError::ServiceError(MultipartUploadError::EntityTooSmall));
+            }
+            trace!(
+                part_number = part.part_number,
+                ?etag,
+                size = contents.len(),
+                "completing part"
+            );
+            buffer.extend_from_slice(contents);
+        }
+
+        if let Some(etag) = &params.if_match
+            && self.objects.read().unwrap().get(key).map(|object| &object.etag) != Some(etag)
+        {
+            return Err(ObjectClientError::ServiceError(
+                MultipartUploadError::PreconditionFailed,
+            ));
+        }
+
+        let upload = uploads.remove(upload_id).expect("upload should exist");
+        let mut object: MockObject = buffer.into();
+        object.set_storage_class(upload.params.storage_class);
+        object.set_object_metadata(upload.params.object_metadata);
+        object.set_tags(upload.params.object_tags);
+        object.set_content_type(upload.params.content_type);
+        object.parts = Some(MockObjectParts::Count(parts.len()));
+
+        let etag = object.etag();
+        add_object(&self.objects, key, object);
+        Ok(PutObjectResult {
+            etag,
+            sse_type: None,
+            sse_kms_key_id: None,
+        })
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        trace!(bucket, key, upload_id, "AbortMultipartUpload");
+        self.inc_op_count(Operation::AbortMultipartUpload);
+
+        if bucket != self.config.bucket {
+            return Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchBucket));
+        }
+
+        let mut uploads = self.multipart_uploads.write().unwrap();
+        match uploads.get(upload_id) {
+            Some(upload) if upload.key == key => {
+                uploads.remove(upload_id);
+                Ok(AbortMultipartUploadResult {})
+            }
+            _ => Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchUpload)),
+        }
+    }
 }
 
 /// Mock implementation of a meta [PutObjectRequest], created by [MockClient]'s [ObjectClient::put_object].
@@ -1249,17 +1906,29 @@ impl MockPutObjectRequest {
         }
     }
 
+    /// Algorithm of the checksums of the parts, which are only sent to S3 with [PutObjectTrailingChecksums::Enabled].
+    fn checksum_algorithm(&self) -> Option<ChecksumAlgorithm> {
+        match self.params.trailing_checksums {
+            PutObjectTrailingChecksums::Enabled => Some(
+                self.params
+                    .trailing_checksum_algorithm
+                    .clone()
+                    .unwrap_or(ChecksumAlgorithm::Crc32c),
+            ),
+            PutObjectTrailingChecksums::ReviewOnly => Some(ChecksumAlgorithm::Crc32c),
+            PutObjectTrailingChecksums::Disabled => None,
+        }
+    }
+
     fn parts(&self) -> Vec<MockObjectPartAttributes> {
+        let checksum_algorithm = self.checksum_algorithm();
         self.buffer
             .chunks(self.part_size)
             .map(|part| {
                 let size = part.len();
-                let checksum = if self.params.trailing_checksums != PutObjectTrailingChecksums::Disabled {
-                    let checksum = crc32c::checksum(part);
-                    Some(crc32c_to_base64(&checksum))
-                } else {
-                    None
-                };
+                let checksum = checksum_algorithm.as_ref().and_then(|algorithm| {
+                    checksum_field(&mut compute_checksum(part, std::slice::from_ref(algorithm)), algorithm).take()
+                });
                 MockObjectPartAttributes { size, checksum }
             })
             .collect()
@@ -1273,17 +1942,24 @@ impl MockPutObjectRequest {
         let mut object: MockObject = buffer.into();
         object.set_storage_class(self.params.storage_class.clone());
         object.set_object_metadata(self.params.object_metadata.clone());
+        object.set_tags(self.params.object_tags.clone());
+        object.set_content_type(self.params.content_type.clone());
 
         // For S3 Standard, part attributes are only available when additional checksums are used
         if self.params.trailing_checksums == PutObjectTrailingChecksums::Enabled {
             let whole_obj_checksum = {
-                let mut whole_obj_checksum = Checksum::empty();
                 let part_checksums = parts
                     .iter()
                     .map(|part| part.checksum.clone())
                     .map(|checksum| checksum.expect("checksum must be set when using trailing checksums"));
-                whole_obj_checksum.checksum_crc32c = Some(compute_crc32c_of_crc32c_checksums(part_checksums));
-                whole_obj_checksum
+                match self.checksum_algorithm() {
+                    Some(ChecksumAlgorithm::Crc32c) | None => {
+                        let mut whole_obj_checksum = Checksum::empty();
+                        whole_obj_checksum.checksum_crc32c = Some(compute_crc32c_of_crc32c_checksums(part_checksums));
+                        whole_obj_checksum
+                    }
+                    Some(algorithm) => compute_checksum_of_checksums(&algorithm, part_checksums),
+                }
             };
             object.set_checksum(whole_obj_checksum);
             object.parts = Some(MockObjectParts::Parts(parts));
@@ -1314,6 +1990,24 @@ fn compute_crc32c_of_crc32c_checksums(individual_checksums: impl IntoIterator<It
     checksum
 }
 
+/// Compute a checksum of checksums with any algorithm, like [compute_crc32c_of_crc32c_checksums].
+fn compute_checksum_of_checksums(
+    algorithm: &ChecksumAlgorithm,
+    individual_checksums: impl IntoIterator<Item = String>,
+) -> Checksum {
+    let mut content = Vec::new();
+    let mut count = 0;
+    for individual_checksum in individual_checksums {
+        count += 1;
+        content.extend_from_slice(individual_checksum.as_bytes());
+    }
+    let mut checksum = compute_checksum(&content, std::slice::from_ref(algorithm));
+    if let Some(value) = checksum_field(&mut checksum, algorithm) {
+        write!(value, "-{count}").expect("should be able to append to String");
+    }
+    checksum
+}
+
 impl Drop for MockPutObjectRequest {
     fn drop(&mut self) {
         self.in_progress_uploads.write().unwrap().remove(&self.key);
@@ -1338,11 +2032,7 @@ impl PutObjectRequest for MockPutObjectRequest {
         self,
         review_callback: impl FnOnce(UploadReview) -> bool + Send + 'static,
     ) -> ObjectClientResult<PutObjectResult, PutObjectError, Self::ClientError> {
-        let checksum_algorithm = if self.params.trailing_checksums != PutObjectTrailingChecksums::Disabled {
-            Some(ChecksumAlgorithm::Crc32c)
-        } else {
-            None
-        };
+        let checksum_algorithm = self.checksum_algorithm();
         let parts = self.parts();
         let review_parts = parts
             .iter()
@@ -1385,6 +2075,7 @@ mod tests {
     use test_case::test_case;
 
     use super::*;
+    use crate::object_client::MAX_PRESIGNED_URL_EXPIRATION;
 
     macro_rules! assert_client_error {
         ($e:expr, $err:expr) => {
@@ -1616,6 +2307,39 @@ mod tests {
             .get_object(bucket, dst_key, &GetObjectParams::new())
             .await
             .expect("get_object should succeed");
+
+        let params = CopyObjectParams::new().if_none_match(Some("*".to_owned()));
+        assert!(matches!(
+            client.copy_object(bucket, src_key, bucket, dst_key, &params).await,
+            Err(ObjectClientError::ServiceError(CopyObjectError::PreconditionFailed))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_replace_metadata() {
+        let bucket = "test_bucket";
+        let key = "copy_key";
+        let client = MockClient::config().bucket(bucket).part_size(1024).build();
+
+        let mut object: MockObject = "test_body".into();
+        object.set_object_metadata(HashMap::from([("source".to_owned(), "test".to_owned())]));
+        client.add_object(key, object);
+
+        let object_metadata = HashMap::from([("mtime".to_owned(), "1700000000".to_owned())]);
+        let params = CopyObjectParams::new()
+            .object_metadata(object_metadata.clone())
+            .storage_class("STANDARD_IA".to_owned());
+        client
+            .copy_object(bucket, key, bucket, key, &params)
+            .await
+            .expect("Should not fail");
+
+        let head = client
+            .head_object(bucket, key, &HeadObjectParams::new())
+            .await
+            .expect("head_object should succeed");
+        assert_eq!(head.object_metadata, object_metadata);
+        assert_eq!(head.storage_class.as_deref(), Some("STANDARD_IA"));
     }
 
     #[tokio::test]
@@ -1637,6 +2361,161 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_presign_get_object() {
+        let bucket = "test_bucket";
+        let client = MockClient::config().bucket(bucket).part_size(1024).build();
+
+        let result = client
+            .presign_get_object(bucket, "key", Duration::from_secs(60), &Default::default())
+            .await
+            .expect("presign_get_object should succeed");
+        assert_eq!(
+            result.url,
+            "https://test_bucket.s3.mock.amazonaws.com/key?X-Amz-Expires=60"
+        );
+
+        let params = PresignGetObjectParams::new().version_id(Some("v1".to_owned()));
+        let result = client
+            .presign_get_object(bucket, "key", MAX_PRESIGNED_URL_EXPIRATION, &params)
+            .await
+            .expect("presign_get_object should succeed");
+        assert!(result.url.ends_with("X-Amz-Expires=604800&versionId=v1"));
+
+        for expires_in in [Duration::ZERO, MAX_PRESIGNED_URL_EXPIRATION + Duration::from_secs(1)] {
+            assert!(matches!(
+                client.presign_get_object(bucket, "key", expires_in, &params).await,
+                Err(ObjectClientError::ServiceError(
+                    PresignGetObjectError::InvalidExpiration
+                ))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload() {
+        let bucket = "test_bucket";
+        let key = "mpu_key";
+        let client = MockClient::config().bucket(bucket).part_size(1024).build();
+
+        let existing = vec![1u8; MIN_MULTIPART_PART_SIZE];
+        let existing_object: MockObject = existing.clone().into();
+        let existing_etag = existing_object.etag();
+        client.add_object(key, existing_object);
+
+        let params = CreateMultipartUploadParams::new().storage_class("STANDARD_IA".to_owned());
+        let upload_id = client
+            .create_multipart_upload(bucket, key, &params)
+            .await
+            .expect("create_multipart_upload should succeed")
+            .upload_id;
+        assert_eq!(client.multipart_upload_count(), 1);
+
+        let result = client
+            .upload_part_copy(
+                bucket,
+                key,
+                &upload_id,
+                1,
+                &UploadPartCopyParams::new(key.to_owned()).source_if_match(Some("\"wrong\"".into())),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ObjectClientError::ServiceError(
+                MultipartUploadError::PreconditionFailed
+            ))
+        ));
+
+        let copy_params = UploadPartCopyParams::new(key.to_owned()).source_if_match(Some(existing_etag.clone()));
+        let first = client
+            .upload_part_copy(bucket, key, &upload_id, 1, &copy_params)
+            .await
+            .expect("upload_part_copy should succeed");
+        let second = client
+            .upload_part(bucket, key, &upload_id, 2, &UploadPartParams::new(), b"appended")
+            .await
+            .expect("upload_part should succeed");
+
+        // The object is only replaced once the upload completes, and only if it still has the given ETag.
+        assert_eq!(client.objects.read().unwrap()[key].size, existing.len());
+        let parts = [first.into_completed_part(1), second.into_completed_part(2)];
+        let result = client
+            .complete_multipart_upload(
+                bucket,
+                key,
+                &upload_id,
+                &parts,
+                &CompleteMultipartUploadParams::new().if_match(Some("\"wrong\"".into())),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ObjectClientError::ServiceError(
+                MultipartUploadError::PreconditionFailed
+            ))
+        ));
+        client
+            .complete_multipart_upload(
+                bucket,
+                key,
+                &upload_id,
+                &parts,
+                &CompleteMultipartUploadParams::new().if_match(Some(existing_etag)),
+            )
+            .await
+            .expect("complete_multipart_upload should succeed");
+        assert_eq!(client.multipart_upload_count(), 0);
+
+        let object = client.objects.read().unwrap()[key].clone();
+        let mut expected = existing;
+        expected.extend_from_slice(b"appended");
+        assert_eq!(&object.read(0, object.size)[..], &expected[..]);
+        assert_eq!(object.storage_class.as_deref(), Some("STANDARD_IA"));
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_too_small_and_abort() {
+        let bucket = "test_bucket";
+        let key = "mpu_key";
+        let client = MockClient::config().bucket(bucket).part_size(1024).build();
+
+        let upload_id = client
+            .create_multipart_upload(bucket, key, &CreateMultipartUploadParams::new())
+            .await
+            .expect("create_multipart_upload should succeed")
+            .upload_id;
+        let mut parts = Vec::new();
+        for part_number in 1..=2 {
+            let part = client
+                .upload_part(bucket, key, &upload_id, part_number, &UploadPartParams::new(), b"small")
+                .await
+                .expect("upload_part should succeed");
+            parts.push(part.into_completed_part(part_number));
+        }
+        let result = client
+            .complete_multipart_upload(bucket, key, &upload_id, &parts, &CompleteMultipartUploadParams::new())
+            .await;
+        assert!(matches!(
+            result,
+            Err(ObjectClientError::ServiceError(MultipartUploadError::EntityTooSmall))
+        ));
+
+        client
+            .abort_multipart_upload(bucket, key, &upload_id)
+            .await
+            .expect("abort_multipart_upload should succeed");
+        assert_eq!(client.multipart_upload_count(), 0);
+        assert!(!client.contains_key(key));
+        let result = client
+            .complete_multipart_upload(bucket, key, &upload_id, &parts, &CompleteMultipartUploadParams::new())
+            .await;
+        assert!(matches!(
+            result,
+            Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchUpload))
+        ));
+    }
+
     #[tokio::test]
     async fn list_object_dirs() {
         let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
@@ -1989,6 +2868,116 @@ mod tests {
         assert_eq!(result.objects[1].checksum_algorithms, vec![ChecksumAlgorithm::Sha1]);
     }
 
+    #[tokio::test]
+    async fn list_object_versions() {
+        let client = MockClient::config().bucket("test_bucket").build();
+        let time = |seconds| OffsetDateTime::from_unix_timestamp(seconds).unwrap();
+        let version = |value: u8, version_id: &str, seconds| {
+            let mut object = MockObject::constant(value, 5, ETag::from_object_bytes(&[value]));
+            object.set_version_id(Some(version_id.to_owned()));
+            object.set_last_modified(time(seconds));
+            object
+        };
+
+        client.add_object("dir/a.txt", version(2, "a2", 20));
+        client.add_object_version("dir/a.txt", version(1, "a1", 10));
+        client.add_object_version("dir/b.txt", version(3, "b1", 10));
+        client.add_delete_marker("dir/b.txt", "b2", time(20));
+        client.add_object("dir/sub/c.txt", version(4, "c1", 10));
+
+        let list = |key_marker: Option<String>, version_id_marker: Option<String>, delimiter, max_keys| {
+            let client = &client;
+            async move {
+                client
+                    .list_object_versions(
+                        "test_bucket",
+                        key_marker.as_deref(),
+                        version_id_marker.as_deref(),
+                        delimiter,
+                        max_keys,
+                        "dir/",
+                    )
+                    .await
+                    .expect("should not fail")
+            }
+        };
+
+        let result = list(None, None, "/", 1000).await;
+        let versions: Vec<_> = result
+            .versions
+            .iter()
+            .map(|version| (version.key.as_str(), version.version_id.as_str(), version.is_latest))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                ("dir/a.txt", "a2", true),
+                ("dir/a.txt", "a1", false),
+                ("dir/b.txt", "b1", false)
+            ]
+        );
+        let delete_markers: Vec<_> = result
+            .delete_markers
+            .iter()
+            .map(|marker| (marker.key.as_str(), marker.version_id.as_str(), marker.is_latest))
+            .collect();
+        assert_eq!(delete_markers, [("dir/b.txt", "b2", true)]);
+        assert_eq!(result.common_prefixes, ["dir/sub/"]);
+        assert!(result.next_key_marker.is_none());
+
+        // Page through the versions one at a time.
+        let mut listed = Vec::new();
+        let (mut key_marker, mut version_id_marker) = (None, None);
+        loop {
+            let result = list(key_marker, version_id_marker, "", 1).await;
+            listed.extend(result.versions.into_iter().map(|version| version.version_id));
+            listed.extend(result.delete_markers.into_iter().map(|marker| marker.version_id));
+            if result.next_key_marker.is_none() {
+                break;
+            }
+            key_marker = result.next_key_marker;
+            version_id_marker = result.next_version_id_marker;
+        }
+        assert_eq!(listed, ["a2", "a1", "b2", "b1", "c1"]);
+    }
+
+    #[tokio::test]
+    async fn get_object_version() {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+        let mut current = MockObject::from_bytes(b"current", ETag::for_tests());
+        current.set_version_id(Some("v2".to_owned()));
+        current.set_last_modified(OffsetDateTime::from_unix_timestamp(20).unwrap());
+        client.add_object("key", current);
+        let mut noncurrent = MockObject::from_bytes(b"noncurrent", ETag::for_tests());
+        noncurrent.set_version_id(Some("v1".to_owned()));
+        noncurrent.set_last_modified(OffsetDateTime::from_unix_timestamp(10).unwrap());
+        client.add_object_version("key", noncurrent);
+        client.add_delete_marker("key", "v0", OffsetDateTime::from_unix_timestamp(5).unwrap());
+
+        for (version_id, expected) in [
+            (None, &b"current"[..]),
+            (Some("v2"), b"current"),
+            (Some("v1"), b"noncurrent"),
+        ] {
+            let params = GetObjectParams::new().version_id(version_id.map(str::to_owned));
+            let mut response = client.get_object("test_bucket", "key", &params).await.unwrap();
+            let mut body = Vec::new();
+            while let Some(part) = response.next().await {
+                body.extend_from_slice(&part.unwrap().data);
+            }
+            assert_eq!(body, expected);
+        }
+
+        for version_id in ["v0", "v3"] {
+            let params = GetObjectParams::new().version_id(Some(version_id.to_owned()));
+            let result = client.get_object("test_bucket", "key", &params).await;
+            assert!(matches!(
+                result,
+                Err(ObjectClientError::ServiceError(GetObjectError::NoSuchKey(_)))
+            ));
+        }
+    }
+
     #[tokio::test]
     async fn test_put_object() {
         let mut rng = SmallRng::seed_from_u64(0x12345678);
@@ -2063,6 +3052,58 @@ mod tests {
         assert_eq!(&content, &*actual);
     }
 
+    #[tokio::test]
+    async fn test_put_object_tags() {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+        let tags = ObjectTags::from([("team".to_owned(), "data".to_owned())]);
+
+        let put_params = PutObjectSingleParams::new().object_tags(tags.clone());
+        client
+            .put_object_single("test_bucket", "single", &put_params, b"content")
+            .await
+            .expect("put_object_single failed");
+
+        let put_params = PutObjectParams::new().object_tags(tags.clone());
+        let mut put_request = client
+            .put_object("test_bucket", "multipart", &put_params)
+            .await
+            .expect("put_object failed");
+        put_request.write(b"content").await.unwrap();
+        put_request.complete().await.unwrap();
+
+        for key in ["single", "multipart"] {
+            let result = client.get_object_tagging("test_bucket", key).await.unwrap();
+            assert_eq!(result.tags, tags, "tags of {key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_object_content_type() {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+
+        let put_params = PutObjectSingleParams::new().content_type("text/csv".to_owned());
+        client
+            .put_object_single("test_bucket", "single", &put_params, b"a,b")
+            .await
+            .expect("put_object_single failed");
+
+        let put_params = PutObjectParams::new().content_type("text/csv".to_owned());
+        let mut put_request = client
+            .put_object("test_bucket", "multipart", &put_params)
+            .await
+            .expect("put_object failed");
+        put_request.write(b"a,b").await.unwrap();
+        put_request.complete().await.unwrap();
+
+        for key in ["single", "multipart"] {
+            let head = client
+                .head_object("test_bucket", key, &HeadObjectParams::new())
+                .await
+                .unwrap();
+            assert_eq!(head.content_type.as_deref(), Some("text/csv"), "content type of {key}");
+        }
+    }
+
     #[tokio::test]
     async fn test_checksums_set_after_single_put() {
         let client = MockClient::config().bucket("test_bucket").build();
@@ -2166,6 +3207,51 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_trailing_checksum_algorithm() {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+
+        let body = vec![0xaa; 2500];
+        let put_object_params = PutObjectParams::new()
+            .trailing_checksums(PutObjectTrailingChecksums::Enabled)
+            .trailing_checksum_algorithm(ChecksumAlgorithm::Sha256);
+        let mut put_request = client
+            .put_object("test_bucket", "key1", &put_object_params)
+            .await
+            .expect("should be able to initiate meta put_object");
+        put_request.write(&body).await.unwrap();
+        let expected = sha256_to_base64(&sha256::checksum(&body[..1024]).unwrap());
+        put_request
+            .review_and_complete(move |review| {
+                assert_eq!(review.checksum_algorithm, Some(ChecksumAlgorithm::Sha256));
+                assert_eq!(review.parts[0].checksum.as_deref(), Some(expected.as_str()));
+                true
+            })
+            .await
+            .expect("should be able to complete meta put_object");
+
+        let attributes = client
+            .get_object_attributes(
+                "test_bucket",
+                "key1",
+                None,
+                None,
+                &[ObjectAttribute::ObjectParts, ObjectAttribute::Checksum],
+            )
+            .await
+            .unwrap();
+        let checksum = attributes.checksum.unwrap();
+        assert_eq!(checksum.algorithms(), vec![ChecksumAlgorithm::Sha256]);
+        assert!(checksum.checksum_sha256.unwrap().ends_with("-3"));
+        let parts = attributes.object_parts.unwrap().parts.unwrap();
+        assert_eq!(parts.len(), 3);
+        assert!(
+            parts
+                .iter()
+                .all(|part| part.checksum.as_ref().unwrap().algorithms() == vec![ChecksumAlgorithm::Sha256])
+        );
+    }
+
     #[tokio::test]
     async fn rename_object_without_override() {
         let client = MockClient::config().bucket("test_bucket").enable_rename(true).build();
@@ -13,11 +13,16 @@ use crate::mock_client::{
     MockClient, MockClientConfig, MockClientError, MockGetObjectResponse, MockObject, MockPutObjectRequest,
 };
 use crate::object_client::{
-    Checksum, CopyObjectError, CopyObjectParams, CopyObjectResult, DeleteObjectError, DeleteObjectResult, GetBodyPart,
-    GetObjectAttributesError, GetObjectAttributesResult, GetObjectError, GetObjectParams, GetObjectResponse,
-    HeadObjectError, HeadObjectParams, HeadObjectResult, ListObjectsError, ListObjectsResult, ObjectAttribute,
-    ObjectChecksumError, ObjectClient, ObjectClientResult, ObjectMetadata, PutObjectError, PutObjectParams,
-    PutObjectResult, PutObjectSingleParams, RenameObjectError, RenameObjectParams, RenameObjectResult,
+    AbortMultipartUploadResult, Checksum, CompleteMultipartUploadParams, CompletedPart, CopyObjectError,
+    CopyObjectParams, CopyObjectResult, CreateMultipartUploadParams, CreateMultipartUploadResult, DeleteObjectError,
+    DeleteObjectResult, DeleteObjectsResult, GetBodyPart, GetObjectAttributesError, GetObjectAttributesResult,
+    GetObjectError, GetObjectParams, GetObjectResponse, GetObjectTaggingError, GetObjectTaggingResult, HeadObjectError,
+    HeadObjectParams, HeadObjectResult, ListObjectVersionsError, ListObjectVersionsResult, ListObjectsError,
+    ListObjectsResult, MultipartUploadError, ObjectAttribute, ObjectChecksumError, ObjectClient, ObjectClientResult,
+    ObjectMetadata, ObjectTags, PresignGetObjectError, PresignGetObjectParams, PresignGetObjectResult, PutObjectError,
+    PutObjectParams, PutObjectResult, PutObjectSingleParams, PutObjectTaggingError, PutObjectTaggingResult,
+    RenameObjectError, RenameObjectParams, RenameObjectResult, UploadPartCopyParams, UploadPartParams,
+    UploadPartResult,
 };
 
 use super::MockBackpressureHandle;
@@ -147,6 +152,14 @@ impl ObjectClient for ThroughputMockClient {
         self.inner.delete_object(bucket, key).await
     }
 
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, Self::ClientError> {
+        self.inner.delete_objects(bucket, keys).await
+    }
+
     async fn copy_object(
         &self,
         source_bucket: &str,
@@ -184,6 +197,20 @@ impl ObjectClient for ThroughputMockClient {
             .await
     }
 
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, Self::ClientError> {
+        self.inner
+            .list_object_versions(bucket, key_marker, version_id_marker, delimiter, max_keys, prefix)
+            .await
+    }
+
     async fn head_object(
         &self,
         bucket: &str,
@@ -234,6 +261,91 @@ impl ObjectClient for ThroughputMockClient {
     ) -> ObjectClientResult<RenameObjectResult, RenameObjectError, Self::ClientError> {
         self.inner.rename_object(bucket, src_key, dst_key, params).await
     }
+
+    async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, Self::ClientError> {
+        self.inner.get_object_tagging(bucket, key).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, Self::ClientError> {
+        self.inner.put_object_tagging(bucket, key, tags).await
+    }
+
+    async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, Self::ClientError> {
+        self.inner.presign_get_object(bucket, key, expires_in, params).await
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        self.inner.create_multipart_upload(bucket, key, params).await
+    }
+
+    async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.inner
+            .upload_part(bucket, key, upload_id, part_number, params, contents)
+            .await
+    }
+
+    async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.inner
+            .upload_part_copy(bucket, key, upload_id, part_number, params)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, Self::ClientError> {
+        self.inner
+            .complete_multipart_upload(bucket, key, upload_id, parts, params)
+            .await
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        self.inner.abort_multipart_upload(bucket, key, upload_id).await
+    }
 }
 
 #[cfg(test)]
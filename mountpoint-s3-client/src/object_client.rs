@@ -1,6 +1,6 @@
 use std::fmt::{self, Debug};
 use std::ops::Range;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use auto_impl::auto_impl;
@@ -56,6 +56,16 @@ pub trait ObjectClient {
         key: &str,
     ) -> ObjectClientResult<DeleteObjectResult, DeleteObjectError, Self::ClientError>;
 
+    /// Delete up to 1000 objects from the object store in a single DeleteObjects request.
+    ///
+    /// Like [`delete_object`](Self::delete_object), deleting an object that does not exist succeeds. The request can
+    /// succeed while some of the objects are not deleted, which are reported in the result.
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, Self::ClientError>;
+
     /// Create a copy of an existing object. Currently, this functionality has the following limitations:
     /// - Supported only for copying between matching bucket types:
     ///     - Standard S3 to Standard S3 buckets.
@@ -91,6 +101,18 @@ pub trait ObjectClient {
         prefix: &str,
     ) -> ObjectClientResult<ListObjectsResult, ListObjectsError, Self::ClientError>;
 
+    /// List the versions of the objects in a bucket under a given prefix, and their delete markers. Listings resume
+    /// after the given key and version ID markers, from a previous result.
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, Self::ClientError>;
+
     /// Retrieve object metadata without retrieving the object contents
     async fn head_object(
         &self,
@@ -135,6 +157,83 @@ pub trait ObjectClient {
         dest_key: &str,
         params: &RenameObjectParams,
     ) -> ObjectClientResult<RenameObjectResult, RenameObjectError, Self::ClientError>;
+
+    /// Get the tags of an object.
+    async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, Self::ClientError>;
+
+    /// Replace the tags of an object.
+    async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, Self::ClientError>;
+
+    /// Create a pre-signed URL for a GetObject request of an object, which can be used to get the object without
+    /// credentials until it expires, after `expires_in`. The object is not looked up, so the URL is created even if
+    /// the key doesn't exist.
+    async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, Self::ClientError>;
+
+    /// Start a multipart upload of an object, whose parts are then uploaded with
+    /// [`upload_part`](Self::upload_part) or copied from an existing object with
+    /// [`upload_part_copy`](Self::upload_part_copy). The object is only created once the upload is
+    /// completed with [`complete_multipart_upload`](Self::complete_multipart_upload).
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, Self::ClientError>;
+
+    /// Upload a part of a multipart upload. Parts are numbered from 1, and all of them but the last
+    /// must be at least 5 MiB.
+    async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError>;
+
+    /// Copy a range of an existing object in the same bucket as a part of a multipart upload.
+    async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError>;
+
+    /// Complete a multipart upload, creating the object from the given parts in order, or replacing the existing one.
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, Self::ClientError>;
+
+    /// Abort a multipart upload, discarding the parts uploaded so far.
+    async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, Self::ClientError>;
 }
 
 /// The top-level error type returned by calls to an [`ObjectClient`].
@@ -192,6 +291,12 @@ impl ProvideErrorMetadata for ListObjectsError {
     }
 }
 
+impl ProvideErrorMetadata for ListObjectVersionsError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
 impl ProvideErrorMetadata for HeadObjectError {
     fn meta(&self) -> ClientErrorMetadata {
         Default::default()
@@ -204,6 +309,22 @@ impl ProvideErrorMetadata for DeleteObjectError {
     }
 }
 
+impl ProvideErrorMetadata for DeleteObjectsFailure {
+    fn meta(&self) -> ClientErrorMetadata {
+        ClientErrorMetadata {
+            http_code: None,
+            error_code: Some(self.code.clone()),
+            error_message: Some(self.message.clone()),
+        }
+    }
+}
+
+impl ProvideErrorMetadata for CopyObjectError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
 impl ProvideErrorMetadata for RenameObjectError {
     fn meta(&self) -> ClientErrorMetadata {
         Default::default()
@@ -216,6 +337,30 @@ impl ProvideErrorMetadata for PutObjectError {
     }
 }
 
+impl ProvideErrorMetadata for GetObjectTaggingError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
+impl ProvideErrorMetadata for PutObjectTaggingError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
+impl ProvideErrorMetadata for PresignGetObjectError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
+impl ProvideErrorMetadata for MultipartUploadError {
+    fn meta(&self) -> ClientErrorMetadata {
+        Default::default()
+    }
+}
+
 /// Shorthand type for the result of an object client request
 pub type ObjectClientResult<T, S, C> = Result<T, ObjectClientError<S, C>>;
 
@@ -243,6 +388,10 @@ pub struct GetObjectParams {
     /// An optional caller-supplied identifier passed through to the memory pool on buffer
     /// allocations for this request. Not related to the S3 request ID returned by the service.
     pub custom_id: Option<u64>,
+    /// Part size of this request, overriding the client's read part size.
+    pub part_size: Option<usize>,
+    /// Version of the object to get, rather than its current version.
+    pub version_id: Option<String>,
 }
 
 impl GetObjectParams {
@@ -275,6 +424,18 @@ impl GetObjectParams {
         self.custom_id = value;
         self
     }
+
+    /// Set the part size of this request, overriding the client's read part size
+    pub fn part_size(mut self, value: Option<usize>) -> Self {
+        self.part_size = value;
+        self
+    }
+
+    /// Set the version of the object to get, rather than its current version
+    pub fn version_id(mut self, value: Option<String>) -> Self {
+        self.version_id = value;
+        self
+    }
 }
 
 /// Result of a [`list_objects`](ObjectClient::list_objects) request
@@ -300,6 +461,35 @@ pub enum ListObjectsError {
     NoSuchBucket,
 }
 
+/// Result of a [`list_object_versions`](ObjectClient::list_object_versions) request
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ListObjectVersionsResult {
+    /// The list of versions of objects.
+    pub versions: Vec<ObjectVersionInfo>,
+
+    /// The list of delete markers.
+    pub delete_markers: Vec<DeleteMarkerInfo>,
+
+    /// The list of common prefixes. This rolls up all of the versions with a common prefix up to
+    /// the next instance of the delimiter.
+    pub common_prefixes: Vec<String>,
+
+    /// If present, the key marker to use to query more results.
+    pub next_key_marker: Option<String>,
+
+    /// If present, the version ID marker to use along with the key marker to query more results.
+    pub next_version_id_marker: Option<String>,
+}
+
+/// Errors returned by a [`list_object_versions`](ObjectClient::list_object_versions) request
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ListObjectVersionsError {
+    #[error("The bucket does not exist")]
+    NoSuchBucket,
+}
+
 /// Parameters to a [`head_object`](ObjectClient::head_object) request
 #[derive(Debug, Default, Clone)]
 #[non_exhaustive]
@@ -366,6 +556,24 @@ pub struct HeadObjectResult {
 
     /// Server-side encryption KMS key ID that was used to store the object.
     pub sse_kms_key_id: Option<String>,
+
+    /// User-defined metadata of the object, from the `x-amz-meta-*` headers.
+    pub object_metadata: ObjectMetadata,
+
+    /// Version ID of the object, if versioning is enabled on the bucket.
+    pub version_id: Option<String>,
+
+    /// Content encoding of the object, from the `Content-Encoding` header, such as `gzip`.
+    pub content_encoding: Option<String>,
+
+    /// Content type of the object, from the `Content-Type` header, such as `text/csv`.
+    pub content_type: Option<String>,
+
+    /// Caching directives of the object, from the `Cache-Control` header, such as `max-age=3600`.
+    pub cache_control: Option<String>,
+
+    /// Presentation of the object, from the `Content-Disposition` header, such as `attachment`.
+    pub content_disposition: Option<String>,
 }
 
 /// Errors returned by a [`head_object`](ObjectClient::head_object) request
@@ -393,6 +601,27 @@ pub enum DeleteObjectError {
     NoSuchBucket,
 }
 
+/// Result of a [`delete_objects`](ObjectClient::delete_objects) request. The objects not listed in
+/// [`errors`](Self::errors) were deleted.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct DeleteObjectsResult {
+    /// The objects that could not be deleted.
+    pub errors: Vec<DeleteObjectsFailure>,
+}
+
+/// An object that a [`delete_objects`](ObjectClient::delete_objects) request could not delete.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("The object {key:?} could not be deleted: {code}: {message}")]
+pub struct DeleteObjectsFailure {
+    /// Key of the object.
+    pub key: String,
+    /// S3 error code, such as `AccessDenied`.
+    pub code: String,
+    /// Description of the error.
+    pub message: String,
+}
+
 /// Result of a [`copy_object`](ObjectClient::copy_object) request
 #[derive(Debug)]
 #[non_exhaustive]
@@ -410,13 +639,36 @@ pub enum CopyObjectError {
 
     #[error("The source object of the COPY action is not in the active tier and is only stored in Amazon S3 Glacier.")]
     ObjectNotInActiveTierError,
+
+    #[error("The destination object did not match the precondition of the copy")]
+    PreconditionFailed,
 }
 
 /// Parameters to a [`copy_object`](ObjectClient::copy_object) request
 #[derive(Debug, Default, Clone)]
 #[non_exhaustive]
 pub struct CopyObjectParams {
-    // TODO: Populate this struct with fields as and when required to satisfy various use cases.
+    /// User-defined metadata replacing the metadata of the source object, which is copied when unset
+    pub object_metadata: Option<ObjectMetadata>,
+    /// Storage class of the copy, which is the default storage class of the bucket when unset
+    pub storage_class: Option<String>,
+    /// Content type of the copy, only used when the user-defined metadata is replaced, in which case the content type
+    /// of the source object is not copied either
+    pub content_type: Option<String>,
+    /// Content encoding of the copy, only used when the user-defined metadata is replaced, like the content type
+    pub content_encoding: Option<String>,
+    /// `Cache-Control` of the copy, only used when the user-defined metadata is replaced, like the content type
+    pub cache_control: Option<String>,
+    /// `Content-Disposition` of the copy, only used when the user-defined metadata is replaced, like the content type
+    pub content_disposition: Option<String>,
+    /// The server-side encryption algorithm of the copy (for example, AES256, aws:kms, aws:kms:dsse), which is the
+    /// default encryption of the bucket when unset, whatever the encryption of the source object
+    pub server_side_encryption: Option<String>,
+    /// If `server_side_encryption` has a valid value of aws:kms or aws:kms:dsse, this value may be used to specify AWS KMS key ID to be used
+    /// when encrypting the copy. When not specified, the default AWS managed key is used.
+    pub ssekms_key_id: Option<String>,
+    /// Can be set to * to only copy the object if the destination doesn't exist
+    pub if_none_match: Option<String>,
 }
 
 impl CopyObjectParams {
@@ -424,6 +676,60 @@ impl CopyObjectParams {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Replace the user-defined metadata of the source object.
+    pub fn object_metadata(mut self, value: ObjectMetadata) -> Self {
+        self.object_metadata = Some(value);
+        self
+    }
+
+    /// Set the storage class.
+    pub fn storage_class(mut self, value: String) -> Self {
+        self.storage_class = Some(value);
+        self
+    }
+
+    /// Set the content type, when replacing the user-defined metadata.
+    pub fn content_type(mut self, value: String) -> Self {
+        self.content_type = Some(value);
+        self
+    }
+
+    /// Set the content encoding, when replacing the user-defined metadata.
+    pub fn content_encoding(mut self, value: String) -> Self {
+        self.content_encoding = Some(value);
+        self
+    }
+
+    /// Set the `Cache-Control` header, when replacing the user-defined metadata.
+    pub fn cache_control(mut self, value: String) -> Self {
+        self.cache_control = Some(value);
+        self
+    }
+
+    /// Set the `Content-Disposition` header, when replacing the user-defined metadata.
+    pub fn content_disposition(mut self, value: String) -> Self {
+        self.content_disposition = Some(value);
+        self
+    }
+
+    /// Set server-side encryption type.
+    pub fn server_side_encryption(mut self, value: Option<String>) -> Self {
+        self.server_side_encryption = value;
+        self
+    }
+
+    /// Set KMS key ID to be used for server-side encryption.
+    pub fn ssekms_key_id(mut self, value: Option<String>) -> Self {
+        self.ssekms_key_id = value;
+        self
+    }
+
+    /// Set if-none-match header
+    pub fn if_none_match(mut self, value: Option<String>) -> Self {
+        self.if_none_match = value;
+        self
+    }
 }
 
 /// Result of a [`get_object_attributes`](ObjectClient::get_object_attributes) request
@@ -544,16 +850,337 @@ pub enum RenameObjectError {
     BadRequest,
 }
 
+/// Tags of an object, as key-value pairs.
+pub type ObjectTags = HashMap<String, String>;
+
+/// Result of a [`get_object_tagging`](ObjectClient::get_object_tagging) request
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct GetObjectTaggingResult {
+    /// Tags of the object.
+    pub tags: ObjectTags,
+}
+
+/// Errors returned by a [`get_object_tagging`](ObjectClient::get_object_tagging) request
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GetObjectTaggingError {
+    #[error("The bucket does not exist")]
+    NoSuchBucket,
+
+    #[error("The key does not exist")]
+    NoSuchKey,
+}
+
+/// The longest time a pre-signed URL can be used for, 7 days, see
+/// [`presign_get_object`](ObjectClient::presign_get_object).
+pub const MAX_PRESIGNED_URL_EXPIRATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Parameters to a [`presign_get_object`](ObjectClient::presign_get_object) request
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct PresignGetObjectParams {
+    /// Version of the object the URL gets, rather than its current version.
+    pub version_id: Option<String>,
+}
+
+impl PresignGetObjectParams {
+    /// Create a default [PresignGetObjectParams].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the version of the object the URL gets, rather than its current version
+    pub fn version_id(mut self, value: Option<String>) -> Self {
+        self.version_id = value;
+        self
+    }
+}
+
+/// Result of a [`presign_get_object`](ObjectClient::presign_get_object) request
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PresignGetObjectResult {
+    /// The pre-signed URL.
+    pub url: String,
+}
+
+/// Errors returned by a [`presign_get_object`](ObjectClient::presign_get_object) request
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PresignGetObjectError {
+    #[error("The expiration must be between 1 second and 7 days")]
+    InvalidExpiration,
+}
+
+impl PresignGetObjectError {
+    /// Check that a pre-signed URL can expire after `expires_in`.
+    pub(crate) fn check_expiration(expires_in: Duration) -> Result<(), Self> {
+        if expires_in < Duration::from_secs(1) || expires_in > MAX_PRESIGNED_URL_EXPIRATION {
+            return Err(Self::InvalidExpiration);
+        }
+        Ok(())
+    }
+}
+
+/// Result of a [`put_object_tagging`](ObjectClient::put_object_tagging) request
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct PutObjectTaggingResult {}
+
+/// Errors returned by a [`put_object_tagging`](ObjectClient::put_object_tagging) request
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PutObjectTaggingError {
+    #[error("The bucket does not exist")]
+    NoSuchBucket,
+
+    #[error("The key does not exist")]
+    NoSuchKey,
+
+    #[error("The tags are invalid")]
+    InvalidTag,
+}
+
+/// Parameters to a [`create_multipart_upload`](ObjectClient::create_multipart_upload) request
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct CreateMultipartUploadParams {
+    /// Algorithm of the checksums of the parts, which must then be given for every uploaded part
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Storage class of the object
+    pub storage_class: Option<String>,
+    /// Content type of the object, from the `Content-Type` header
+    pub content_type: Option<String>,
+    /// The server-side encryption algorithm to be used for this object in Amazon S3 (for example, AES256, aws:kms, aws:kms:dsse)
+    pub server_side_encryption: Option<String>,
+    /// If `server_side_encryption` has a valid value of aws:kms or aws:kms:dsse, this value may be used to specify AWS KMS key ID to be used
+    /// when creating new objects. When not specified, the default AWS managed key is used.
+    pub ssekms_key_id: Option<String>,
+    /// User-defined object metadata
+    pub object_metadata: ObjectMetadata,
+    /// Tags of the object
+    pub object_tags: ObjectTags,
+}
+
+impl CreateMultipartUploadParams {
+    /// Create a default [CreateMultipartUploadParams].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the checksum algorithm of the parts.
+    pub fn checksum_algorithm(mut self, value: Option<ChecksumAlgorithm>) -> Self {
+        self.checksum_algorithm = value;
+        self
+    }
+
+    /// Set the storage class.
+    pub fn storage_class(mut self, value: String) -> Self {
+        self.storage_class = Some(value);
+        self
+    }
+
+    /// Set the content type.
+    pub fn content_type(mut self, value: String) -> Self {
+        self.content_type = Some(value);
+        self
+    }
+
+    /// Set server-side encryption type.
+    pub fn server_side_encryption(mut self, value: Option<String>) -> Self {
+        self.server_side_encryption = value;
+        self
+    }
+
+    /// Set KMS key ID to be used for server-side encryption.
+    pub fn ssekms_key_id(mut self, value: Option<String>) -> Self {
+        self.ssekms_key_id = value;
+        self
+    }
+
+    /// Set user defined object metadata.
+    pub fn object_metadata(mut self, value: ObjectMetadata) -> Self {
+        self.object_metadata = value;
+        self
+    }
+
+    /// Set the tags of the object.
+    pub fn object_tags(mut self, value: ObjectTags) -> Self {
+        self.object_tags = value;
+        self
+    }
+}
+
+/// Result of a [`create_multipart_upload`](ObjectClient::create_multipart_upload) request
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CreateMultipartUploadResult {
+    /// ID of the upload, which its other requests refer to
+    pub upload_id: String,
+}
+
+/// Parameters to an [`upload_part`](ObjectClient::upload_part) request
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct UploadPartParams {
+    /// Checksum of the part, required when the upload was created with a checksum algorithm
+    pub checksum: Option<UploadChecksum>,
+}
+
+impl UploadPartParams {
+    /// Create a default [UploadPartParams].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the checksum of the part.
+    pub fn checksum(mut self, value: Option<UploadChecksum>) -> Self {
+        self.checksum = value;
+        self
+    }
+}
+
+/// Parameters to an [`upload_part_copy`](ObjectClient::upload_part_copy) request
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct UploadPartCopyParams {
+    /// Key of the object to copy the part from
+    pub source_key: String,
+    /// Range of the source object to copy, which is copied whole when unset
+    pub source_range: Option<Range<u64>>,
+    /// Only copy the part if the source object has this ETag
+    pub source_if_match: Option<ETag>,
+}
+
+impl UploadPartCopyParams {
+    /// Create [UploadPartCopyParams] copying the whole object with the given key.
+    pub fn new(source_key: String) -> Self {
+        Self {
+            source_key,
+            source_range: None,
+            source_if_match: None,
+        }
+    }
+
+    /// Set the range of the source object to copy.
+    pub fn source_range(mut self, value: Option<Range<u64>>) -> Self {
+        self.source_range = value;
+        self
+    }
+
+    /// Only copy the part if the source object has the given ETag.
+    pub fn source_if_match(mut self, value: Option<ETag>) -> Self {
+        self.source_if_match = value;
+        self
+    }
+}
+
+/// Result of an [`upload_part`](ObjectClient::upload_part) or
+/// [`upload_part_copy`](ObjectClient::upload_part_copy) request
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct UploadPartResult {
+    /// ETag of the part
+    pub etag: ETag,
+    /// Checksum of the part, if the upload was created with a checksum algorithm
+    pub checksum: Option<UploadChecksum>,
+}
+
+impl UploadPartResult {
+    /// The part with the given number this result is for, to complete the upload with.
+    pub fn into_completed_part(self, part_number: usize) -> CompletedPart {
+        CompletedPart {
+            part_number,
+            etag: self.etag,
+            checksum: self.checksum,
+        }
+    }
+}
+
+/// Parameters to a [`complete_multipart_upload`](ObjectClient::complete_multipart_upload) request
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct CompleteMultipartUploadParams {
+    /// Only complete the upload if the object it replaces has this ETag
+    pub if_match: Option<ETag>,
+}
+
+impl CompleteMultipartUploadParams {
+    /// Create a default [CompleteMultipartUploadParams].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only complete the upload if the object it replaces has the given ETag.
+    pub fn if_match(mut self, value: Option<ETag>) -> Self {
+        self.if_match = value;
+        self
+    }
+}
+
+/// A part of a multipart upload, to give to a
+/// [`complete_multipart_upload`](ObjectClient::complete_multipart_upload) request
+#[derive(Debug, Clone)]
+pub struct CompletedPart {
+    /// Number of the part
+    pub part_number: usize,
+    /// ETag of the part
+    pub etag: ETag,
+    /// Checksum of the part, if the upload was created with a checksum algorithm
+    pub checksum: Option<UploadChecksum>,
+}
+
+/// Result of an [`abort_multipart_upload`](ObjectClient::abort_multipart_upload) request
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AbortMultipartUploadResult {}
+
+/// Errors returned by the requests of multipart uploads, such as
+/// [`upload_part_copy`](ObjectClient::upload_part_copy)
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MultipartUploadError {
+    #[error("The bucket does not exist")]
+    NoSuchBucket,
+
+    #[error("The upload does not exist")]
+    NoSuchUpload,
+
+    #[error("The source object of the copy was not found")]
+    SourceNotFound,
+
+    #[error("The source object of the copy, or the object replaced by the upload, did not match the given ETag")]
+    PreconditionFailed,
+
+    #[error("The source object of the copy is not in the active tier and is only stored in Amazon S3 Glacier")]
+    ObjectNotInActiveTierError,
+
+    #[error("A part other than the last one was smaller than the minimum part size")]
+    EntityTooSmall,
+
+    #[error("A part was not found or did not match its ETag")]
+    InvalidPart,
+
+    #[error("The checksum of the part did not match its content")]
+    BadChecksum,
+}
+
 pub type ObjectMetadata = HashMap<String, String>;
 
 /// Parameters to a [`put_object`](ObjectClient::put_object) request
 #[derive(Debug, Default, Clone)]
 #[non_exhaustive]
 pub struct PutObjectParams {
-    /// Enable Crc32c trailing checksums.
+    /// Enable trailing checksums.
     pub trailing_checksums: PutObjectTrailingChecksums,
+    /// Algorithm of the trailing checksums sent to S3, CRC32C if not set. Checksums only computed for upload review
+    /// always use CRC32C.
+    pub trailing_checksum_algorithm: Option<ChecksumAlgorithm>,
     /// Storage class to be used when creating new S3 object
     pub storage_class: Option<String>,
+    /// Content type of the object, from the `Content-Type` header
+    pub content_type: Option<String>,
     /// The server-side encryption algorithm to be used for this object in Amazon S3 (for example, AES256, aws:kms, aws:kms:dsse)
     pub server_side_encryption: Option<String>,
     /// If `server_side_encryption` has a valid value of aws:kms or aws:kms:dsse, this value may be used to specify AWS KMS key ID to be used
@@ -563,9 +1190,16 @@ pub struct PutObjectParams {
     pub custom_headers: Vec<(String, String)>,
     /// User-defined object metadata
     pub object_metadata: ObjectMetadata,
+    /// Tags of the object
+    pub object_tags: ObjectTags,
     /// An optional caller-supplied identifier passed through to the memory pool on buffer
     /// allocations for this request. Not related to the S3 request ID returned by the service.
     pub custom_id: Option<u64>,
+    /// Part size of this request, overriding the client's write part size.
+    pub part_size: Option<usize>,
+    /// Maximum number of parts of this request uploaded at once, so that a large upload doesn't use all the
+    /// connections of the client.
+    pub max_concurrent_parts: Option<u32>,
 }
 
 impl PutObjectParams {
@@ -574,18 +1208,30 @@ impl PutObjectParams {
         Self::default()
     }
 
-    /// Set Crc32c trailing checksums.
+    /// Set trailing checksums.
     pub fn trailing_checksums(mut self, value: PutObjectTrailingChecksums) -> Self {
         self.trailing_checksums = value;
         self
     }
 
+    /// Set the algorithm of the trailing checksums sent to S3.
+    pub fn trailing_checksum_algorithm(mut self, value: ChecksumAlgorithm) -> Self {
+        self.trailing_checksum_algorithm = Some(value);
+        self
+    }
+
     /// Set the storage class.
     pub fn storage_class(mut self, value: String) -> Self {
         self.storage_class = Some(value);
         self
     }
 
+    /// Set the content type.
+    pub fn content_type(mut self, value: String) -> Self {
+        self.content_type = Some(value);
+        self
+    }
+
     /// Set server-side encryption type.
     pub fn server_side_encryption(mut self, value: Option<String>) -> Self {
         self.server_side_encryption = value;
@@ -610,15 +1256,33 @@ impl PutObjectParams {
         self
     }
 
+    /// Set the tags of the object.
+    pub fn object_tags(mut self, value: ObjectTags) -> Self {
+        self.object_tags = value;
+        self
+    }
+
     /// Set an optional caller-supplied identifier passed through to the memory pool on buffer
     /// allocations for this request. Not related to the S3 request ID returned by the service.
     pub fn custom_id(mut self, value: Option<u64>) -> Self {
         self.custom_id = value;
         self
     }
+
+    /// Set the part size of this request, overriding the client's write part size
+    pub fn part_size(mut self, value: Option<usize>) -> Self {
+        self.part_size = value;
+        self
+    }
+
+    /// Set the maximum number of parts of this request uploaded at once
+    pub fn max_concurrent_parts(mut self, value: Option<u32>) -> Self {
+        self.max_concurrent_parts = value;
+        self
+    }
 }
 
-/// How CRC32c checksums are used for parts of a multi-part PutObject request
+/// How checksums are used for parts of a multi-part PutObject request
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum PutObjectTrailingChecksums {
     /// Checksums are computed, passed to upload review, and also sent to S3
@@ -647,6 +1311,8 @@ pub struct PutObjectSingleParams {
     pub checksum: Option<UploadChecksum>,
     /// Storage class to be used when creating new S3 object
     pub storage_class: Option<String>,
+    /// Content type of the object, from the `Content-Type` header
+    pub content_type: Option<String>,
     /// The server-side encryption algorithm to be used for this object in Amazon S3 (for example, AES256, aws:kms, aws:kms:dsse)
     pub server_side_encryption: Option<String>,
     /// If `server_side_encryption` has a valid value of aws:kms or aws:kms:dsse, this value may be used to specify AWS KMS key ID to be used
@@ -660,6 +1326,8 @@ pub struct PutObjectSingleParams {
     pub custom_headers: Vec<(String, String)>,
     /// User-defined object metadata
     pub object_metadata: ObjectMetadata,
+    /// Tags of the object
+    pub object_tags: ObjectTags,
 }
 
 impl PutObjectSingleParams {
@@ -685,6 +1353,12 @@ impl PutObjectSingleParams {
         self
     }
 
+    /// Set the content type.
+    pub fn content_type(mut self, value: String) -> Self {
+        self.content_type = Some(value);
+        self
+    }
+
     /// Set server-side encryption type.
     pub fn server_side_encryption(mut self, value: Option<String>) -> Self {
         self.server_side_encryption = value;
@@ -720,6 +1394,12 @@ impl PutObjectSingleParams {
         self.object_metadata = value;
         self
     }
+
+    /// Set the tags of the object.
+    pub fn object_tags(mut self, value: ObjectTags) -> Self {
+        self.object_tags = value;
+        self
+    }
 }
 
 /// A checksum used by the object client for integrity checks on uploads.
@@ -939,6 +1619,54 @@ pub struct ObjectInfo {
     pub checksum_algorithms: Vec<ChecksumAlgorithm>,
 }
 
+/// Metadata about a single version of an object, as returned by
+/// [`list_object_versions`](ObjectClient::list_object_versions).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ObjectVersionInfo {
+    /// Key for this object.
+    pub key: String,
+
+    /// Version ID of this version, `null` for objects created before versioning was enabled.
+    pub version_id: String,
+
+    /// Whether this is the current version of the object.
+    pub is_latest: bool,
+
+    /// Size of this version in bytes.
+    pub size: u64,
+
+    /// The time this version was created.
+    pub last_modified: OffsetDateTime,
+
+    /// Storage class for this version.
+    pub storage_class: Option<String>,
+
+    /// Objects in flexible retrieval storage classes (such as GLACIER and DEEP_ARCHIVE) are only
+    /// accessible after restoration
+    pub restore_status: Option<RestoreStatus>,
+
+    /// Entity tag of this version.
+    pub etag: String,
+}
+
+/// A delete marker, as returned by [`list_object_versions`](ObjectClient::list_object_versions).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DeleteMarkerInfo {
+    /// Key of the deleted object.
+    pub key: String,
+
+    /// Version ID of the delete marker.
+    pub version_id: String,
+
+    /// Whether the delete marker is the current version of the object.
+    pub is_latest: bool,
+
+    /// The time the object was deleted.
+    pub last_modified: OffsetDateTime,
+}
+
 /// All possible object attributes that can be retrived from [ObjectClient::get_object_attributes].
 /// Fields that you do not specify are not returned.
 #[derive(Debug)]
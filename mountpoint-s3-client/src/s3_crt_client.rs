@@ -72,7 +72,11 @@ pub(crate) use get_object::S3GetObjectResponse;
 pub(crate) mod get_object_attributes;
 
 pub(crate) mod head_object;
+pub(crate) mod list_object_versions;
 pub(crate) mod list_objects;
+pub(crate) mod multipart_upload;
+pub(crate) mod object_tagging;
+pub(crate) mod presign;
 
 pub(crate) mod rename_object;
 
@@ -489,6 +493,28 @@ impl S3CrtClientInner {
     /// response should be XML; this header should be overwritten for requests like GET that return
     /// object data.
     fn new_request_template(&self, method: &str, bucket: &str) -> Result<S3Message<'_>, ConstructionError> {
+        let mut message = self.new_message_template(method, bucket)?;
+        message.inner.add_header(&Header::new("accept", "application/xml"))?;
+        message
+            .inner
+            .add_header(&Header::new("User-Agent", &self.user_agent_header))?;
+
+        if let Some(ref payer) = self.request_payer {
+            message.inner.add_header(&Header::new("x-amz-request-payer", payer))?;
+        }
+
+        if let Some(ref owner) = self.bucket_owner {
+            message
+                .inner
+                .add_header(&Header::new("x-amz-expected-bucket-owner", owner))?;
+        }
+
+        Ok(message)
+    }
+
+    /// Create a new HTTP message for the given HTTP method and S3 bucket name, with only the "Host" header, and the
+    /// signing config for the endpoint of the bucket.
+    fn new_message_template<'a>(&self, method: &str, bucket: &str) -> Result<S3Message<'a>, ConstructionError> {
         let endpoint = self.endpoint_config.resolve_for_bucket(bucket)?;
         let uri = endpoint.uri()?;
         trace!(?uri, "resolved endpoint");
@@ -528,16 +554,6 @@ impl S3CrtClientInner {
         let mut message = Message::new_request(&self.allocator)?;
         message.set_request_method(method)?;
         message.add_header(&Header::new("Host", hostname_header))?;
-        message.add_header(&Header::new("accept", "application/xml"))?;
-        message.add_header(&Header::new("User-Agent", &self.user_agent_header))?;
-
-        if let Some(ref payer) = self.request_payer {
-            message.add_header(&Header::new("x-amz-request-payer", payer))?;
-        }
-
-        if let Some(ref owner) = self.bucket_owner {
-            message.add_header(&Header::new("x-amz-expected-bucket-owner", owner))?;
-        }
 
         Ok(S3Message {
             inner: message,
@@ -890,14 +906,23 @@ enum ResponseHeadersError {
 #[derive(Debug, Clone, Copy)]
 enum S3Operation {
     DeleteObject,
+    DeleteObjects,
     GetObject,
     GetObjectAttributes,
     HeadBucket,
     HeadObject,
     ListObjects,
+    ListObjectVersions,
     PutObject,
     CopyObject,
     PutObjectSingle,
+    GetObjectTagging,
+    PutObjectTagging,
+    CreateMultipartUpload,
+    UploadPart,
+    UploadPartCopy,
+    CompleteMultipartUpload,
+    AbortMultipartUpload,
 }
 
 impl S3Operation {
@@ -916,14 +941,23 @@ impl S3Operation {
     fn operation_name(&self) -> Option<&'static str> {
         match self {
             S3Operation::DeleteObject => Some("DeleteObject"),
+            S3Operation::DeleteObjects => Some("DeleteObjects"),
             S3Operation::GetObject => None,
             S3Operation::GetObjectAttributes => Some("GetObjectAttributes"),
             S3Operation::HeadBucket => Some("HeadBucket"),
             S3Operation::HeadObject => Some("HeadObject"),
             S3Operation::ListObjects => Some("ListObjectsV2"),
+            S3Operation::ListObjectVersions => Some("ListObjectVersions"),
             S3Operation::PutObject => None,
             S3Operation::CopyObject => None,
             S3Operation::PutObjectSingle => Some("PutObject"),
+            S3Operation::GetObjectTagging => Some("GetObjectTagging"),
+            S3Operation::PutObjectTagging => Some("PutObjectTagging"),
+            S3Operation::CreateMultipartUpload => Some("CreateMultipartUpload"),
+            S3Operation::UploadPart => Some("UploadPart"),
+            S3Operation::UploadPartCopy => Some("UploadPartCopy"),
+            S3Operation::CompleteMultipartUpload => Some("CompleteMultipartUpload"),
+            S3Operation::AbortMultipartUpload => Some("AbortMultipartUpload"),
         }
     }
 }
@@ -950,6 +984,13 @@ fn write_encoded_fragment(s: &mut OsString, piece: impl AsRef<OsStr>, encoding:
     s.extend(iter.map(|s| OsStr::from_bytes(s.as_bytes())));
 }
 
+/// The value of the `x-amz-copy-source` header of a copy from the given object, which S3 requires to be URL-encoded.
+fn copy_source(bucket: &str, key: &str) -> OsString {
+    let mut source = OsString::from(format!("/{bucket}/"));
+    write_encoded_fragment(&mut source, key, URLENCODE_PATH_FRAGMENT);
+    source
+}
+
 #[derive(Debug, Default)]
 enum QueryFragment<'a, P: AsRef<OsStr>> {
     #[default]
@@ -1286,6 +1327,7 @@ fn operation_name_to_static_metrics_string(operation_name: Option<&str>) -> &'st
         "PutObject",
         "ListObjectsV2",
         "DeleteObject",
+        "DeleteObjects",
         "GetObjectAttributes",
         "HeadBucket",
         "RenameObject",
@@ -1490,6 +1532,14 @@ impl ObjectClient for S3CrtClient {
         self.delete_object(bucket, key).await
     }
 
+    async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, Self::ClientError> {
+        self.delete_objects(bucket, keys).await
+    }
+
     async fn copy_object(
         &self,
         source_bucket: &str,
@@ -1523,6 +1573,19 @@ impl ObjectClient for S3CrtClient {
             .await
     }
 
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, Self::ClientError> {
+        self.list_object_versions(bucket, key_marker, version_id_marker, delimiter, max_keys, prefix)
+            .await
+    }
+
     async fn head_object(
         &self,
         bucket: &str,
@@ -1572,6 +1635,87 @@ impl ObjectClient for S3CrtClient {
     ) -> ObjectClientResult<RenameObjectResult, RenameObjectError, Self::ClientError> {
         self.rename_object(bucket, src_key, dst_key, params).await
     }
+
+    async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, Self::ClientError> {
+        self.get_object_tagging(bucket, key).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, Self::ClientError> {
+        self.put_object_tagging(bucket, key, tags).await
+    }
+
+    async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, Self::ClientError> {
+        self.presign_get_object(bucket, key, expires_in, params).await
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        self.create_multipart_upload(bucket, key, params).await
+    }
+
+    async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.upload_part(bucket, key, upload_id, part_number, params, contents)
+            .await
+    }
+
+    async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, Self::ClientError> {
+        self.upload_part_copy(bucket, key, upload_id, part_number, params).await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, Self::ClientError> {
+        self.complete_multipart_upload(bucket, key, upload_id, parts, params)
+            .await
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, Self::ClientError> {
+        self.abort_multipart_upload(bucket, key, upload_id).await
+    }
 }
 
 /// Custom handling of telemetry events
@@ -1744,6 +1888,13 @@ mod tests {
         );
     }
 
+    #[test_case("dir/file.txt" => "/bucket/dir/file.txt")]
+    #[test_case("a b+c%d?e#f" => "/bucket/a%20b%2Bc%25d%3Fe%23f")]
+    #[test_case("données/€" => "/bucket/donn%C3%A9es/%E2%82%AC")]
+    fn test_copy_source(key: &str) -> String {
+        copy_source("bucket", key).into_string().unwrap()
+    }
+
     #[test_case("bytes 200-1000/67589" => Some(200..1001))]
     #[test_case("bytes 200-1000/*" => Some(200..1001))]
     #[test_case("bytes 200-1000" => None)]
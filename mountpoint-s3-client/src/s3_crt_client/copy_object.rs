@@ -6,7 +6,7 @@ use tracing::trace;
 
 use crate::object_client::{CopyObjectError, CopyObjectParams, CopyObjectResult, ObjectClientResult};
 
-use super::{S3CrtClient, S3Operation, S3RequestError};
+use super::{S3CrtClient, S3Operation, S3RequestError, URLENCODE_PATH_FRAGMENT, copy_source};
 
 impl S3CrtClient {
     /// Create and begin a new CopyObject request.
@@ -16,7 +16,7 @@ impl S3CrtClient {
         source_key: &str,
         destination_bucket: &str,
         destination_key: &str,
-        _params: &CopyObjectParams,
+        params: &CopyObjectParams,
     ) -> ObjectClientResult<CopyObjectResult, CopyObjectError, S3RequestError> {
         let request = {
             let mut message = self
@@ -29,9 +29,59 @@ impl S3CrtClient {
             message
                 .set_header(&Header::new(
                     "x-amz-copy-source",
-                    format!("/{source_bucket}/{source_key}"),
+                    copy_source(source_bucket, source_key),
                 ))
                 .map_err(S3RequestError::construction_failure)?;
+            if let Some(object_metadata) = &params.object_metadata {
+                message
+                    .set_header(&Header::new("x-amz-metadata-directive", "REPLACE"))
+                    .map_err(S3RequestError::construction_failure)?;
+                for (name, value) in object_metadata {
+                    message
+                        .set_header(&Header::new(format!("x-amz-meta-{name}"), value))
+                        .map_err(S3RequestError::construction_failure)?;
+                }
+                if let Some(content_type) = &params.content_type {
+                    message
+                        .set_header(&Header::new("Content-Type", content_type))
+                        .map_err(S3RequestError::construction_failure)?;
+                }
+                if let Some(content_encoding) = &params.content_encoding {
+                    message
+                        .set_header(&Header::new("Content-Encoding", content_encoding))
+                        .map_err(S3RequestError::construction_failure)?;
+                }
+                if let Some(cache_control) = &params.cache_control {
+                    message
+                        .set_header(&Header::new("Cache-Control", cache_control))
+                        .map_err(S3RequestError::construction_failure)?;
+                }
+                if let Some(content_disposition) = &params.content_disposition {
+                    message
+                        .set_header(&Header::new("Content-Disposition", content_disposition))
+                        .map_err(S3RequestError::construction_failure)?;
+                }
+            }
+            if let Some(sse_type) = &params.server_side_encryption {
+                message
+                    .set_header(&Header::new("x-amz-server-side-encryption", sse_type))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(key_id) = &params.ssekms_key_id {
+                message
+                    .set_header(&Header::new("x-amz-server-side-encryption-aws-kms-key-id", key_id))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(storage_class) = &params.storage_class {
+                message
+                    .set_header(&Header::new("x-amz-storage-class", storage_class))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(if_none_match) = &params.if_none_match {
+                message
+                    .set_header(&Header::new("If-None-Match", if_none_match))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
 
             let span = request_span!(
                 self.inner,
@@ -50,7 +100,11 @@ impl S3CrtClient {
                 .map_err(S3RequestError::construction_failure)?
                 .uri()
                 .map_err(S3RequestError::construction_failure)?;
-            let source_uri = format!("{}/{source_key}", uri.as_os_str().to_string_lossy());
+            let source_uri = format!(
+                "{}/{}",
+                uri.as_os_str().to_string_lossy(),
+                percent_encode(source_key.as_bytes(), URLENCODE_PATH_FRAGMENT)
+            );
             trace!(source_uri, "resolved source uri");
             options.copy_source_uri(source_uri);
             self.inner
@@ -76,6 +130,7 @@ fn parse_copy_object_error(result: &MetaRequestResult) -> Option<CopyObjectError
             }
         }
         404 => Some(CopyObjectError::NotFound),
+        412 => Some(CopyObjectError::PreconditionFailed),
         _ => None,
     }
 }
@@ -107,4 +162,11 @@ mod tests {
         let result = parse_copy_object_error(&result);
         assert_eq!(result, Some(CopyObjectError::NotFound));
     }
+    #[test]
+    fn parse_412_error() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>PreconditionFailed</Code><Message>At least one of the pre-conditions you specified did not hold</Message><Condition>If-None-Match</Condition><RequestId>BHCQ0FTYY0HKMV43</RequestId><HostId>ntCK1jQfPxY7sSNL/GB13RttgJLjSETfIuOiuRnwImO0dQP2ttj2Qqpn5S/jSLt3Ql0TgHWuYF0=</HostId></Error>"#;
+        let result = make_result(412, OsStr::from_bytes(&body[..]));
+        let result = parse_copy_object_error(&result);
+        assert_eq!(result, Some(CopyObjectError::PreconditionFailed));
+    }
 }
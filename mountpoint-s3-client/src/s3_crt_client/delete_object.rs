@@ -1,11 +1,32 @@
 use std::ops::Deref;
 use std::os::unix::prelude::OsStrExt;
 
+use mountpoint_s3_crt::http::request_response::Header;
+use mountpoint_s3_crt::io::stream::InputStream;
 use mountpoint_s3_crt::s3::client::MetaRequestResult;
+use thiserror::Error;
+use xmltree::{Element, XMLNode};
 
-use crate::object_client::{DeleteObjectError, DeleteObjectResult, ObjectClientResult};
+use crate::checksums::crc32c;
+use crate::object_client::{
+    DeleteObjectError, DeleteObjectResult, DeleteObjectsFailure, DeleteObjectsResult, ObjectClientResult,
+    UploadChecksum,
+};
 
-use super::{S3CrtClient, S3Operation, S3RequestError};
+use super::{QueryFragment, S3CrtClient, S3Operation, S3RequestError};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    #[error("XML parsing error: {0:?}")]
+    Xml(#[from] xmltree::ParseError),
+
+    #[error("XML writing error: {0:?}")]
+    XmlWrite(#[from] xmltree::Error),
+
+    #[error("Missing field {1} from XML element {0:?}")]
+    MissingField(Box<Element>, String),
+}
 
 impl S3CrtClient {
     /// Create and begin a new DeleteObject request.
@@ -37,6 +58,94 @@ impl S3CrtClient {
 
         Ok(DeleteObjectResult {})
     }
+
+    /// Create and begin a new DeleteObjects request, in quiet mode so that only the objects that could not be deleted
+    /// are returned.
+    pub(super) async fn delete_objects(
+        &self,
+        bucket: &str,
+        keys: &[String],
+    ) -> ObjectClientResult<DeleteObjectsResult, DeleteObjectError, S3RequestError> {
+        let request_body = delete_objects_body(keys).map_err(S3RequestError::internal_failure)?;
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("POST", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_request_path_and_query("/", QueryFragment::Action("delete"))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_content_length_header(request_body.len())
+                .map_err(S3RequestError::construction_failure)?;
+            // DeleteObjects requires an integrity check of the body.
+            message
+                .set_checksum_header(&UploadChecksum::Crc32c(crc32c::checksum(&request_body)))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_header(&Header::new("x-amz-sdk-checksum-algorithm", "CRC32C"))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let body_input_stream =
+                InputStream::new_from_slice(&self.inner.allocator, &request_body).map_err(S3RequestError::CrtError)?;
+            message.set_body_stream(Some(body_input_stream));
+
+            let span = request_span!(self.inner, "delete_objects", bucket, keys = keys.len());
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::DeleteObjects),
+                span,
+                parse_delete_object_error,
+            )?
+        };
+
+        let body = body.await?;
+        DeleteObjectsResult::parse_from_bytes(&body).map_err(|e| S3RequestError::internal_failure(e).into())
+    }
+}
+
+impl DeleteObjectsResult {
+    fn parse_from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut root = Element::parse(bytes)?;
+        let mut errors = Vec::new();
+        while let Some(error) = root.take_child("Error") {
+            errors.push(DeleteObjectsFailure {
+                key: get_field(&error, "Key")?,
+                code: get_field(&error, "Code")?,
+                message: get_field(&error, "Message").unwrap_or_default(),
+            });
+        }
+        Ok(Self { errors })
+    }
+}
+
+fn delete_objects_body(keys: &[String]) -> Result<Vec<u8>, ParseError> {
+    let text_element = |name: &str, text: String| {
+        let mut element = Element::new(name);
+        element.children.push(XMLNode::Text(text));
+        XMLNode::Element(element)
+    };
+
+    let mut delete = Element::new("Delete");
+    delete.children.push(text_element("Quiet", "true".to_owned()));
+    for key in keys {
+        let mut object = Element::new("Object");
+        object.children.push(text_element("Key", key.clone()));
+        delete.children.push(XMLNode::Element(object));
+    }
+
+    let mut body = Vec::new();
+    delete.write(&mut body)?;
+    Ok(body)
+}
+
+/// Copy text out of a child of an XML element, with the right error type.
+fn get_field(element: &Element, name: &str) -> Result<String, ParseError> {
+    element
+        .get_child(name)
+        .and_then(|child| child.get_text())
+        .map(|text| text.into_owned())
+        .ok_or_else(|| ParseError::MissingField(element.clone().into(), name.to_owned()))
 }
 
 fn parse_delete_object_error(result: &MetaRequestResult) -> Option<DeleteObjectError> {
@@ -79,4 +188,39 @@ mod tests {
         let result = parse_delete_object_error(&result);
         assert_eq!(result, Some(DeleteObjectError::NoSuchBucket));
     }
+
+    #[test]
+    fn parse_delete_objects_result() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><DeleteResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Error><Key>dir/a.txt</Key><Code>AccessDenied</Code><Message>Access Denied</Message></Error><Error><Key>dir/b&amp;c.txt</Key><Code>InternalError</Code><Message>We encountered an internal error. Please try again.</Message></Error></DeleteResult>"#;
+        let result = DeleteObjectsResult::parse_from_bytes(&body[..]).unwrap();
+        let errors: Vec<_> = result
+            .errors
+            .iter()
+            .map(|error| (error.key.as_str(), error.code.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [("dir/a.txt", "AccessDenied"), ("dir/b&c.txt", "InternalError")]
+        );
+
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><DeleteResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"></DeleteResult>"#;
+        let result = DeleteObjectsResult::parse_from_bytes(&body[..]).unwrap();
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn delete_objects_body_round_trip() {
+        let keys = ["dir/a.txt".to_owned(), "dir/<b>&c.txt".to_owned()];
+        let body = delete_objects_body(&keys).unwrap();
+        let root = Element::parse(&body[..]).unwrap();
+        assert_eq!(get_field(&root, "Quiet").unwrap(), "true");
+        let parsed: Vec<_> = root
+            .children
+            .iter()
+            .filter_map(|node| node.as_element())
+            .filter(|element| element.name == "Object")
+            .map(|object| get_field(object, "Key").unwrap())
+            .collect();
+        assert_eq!(parsed, keys);
+    }
 }
@@ -21,7 +21,10 @@ use crate::object_client::{
     ObjectChecksumError, ObjectClientError, ObjectClientResult, ObjectMetadata,
 };
 
-use super::{CancellingMetaRequest, ResponseHeadersError, S3CrtClient, S3Operation, S3RequestError, parse_checksum};
+use super::{
+    CancellingMetaRequest, QueryFragment, ResponseHeadersError, S3CrtClient, S3Operation, S3RequestError,
+    parse_checksum,
+};
 
 impl S3CrtClient {
     /// Create and begin a new GetObject request. The returned [S3GetObjectResponse] is a [Stream] of
@@ -36,8 +39,7 @@ impl S3CrtClient {
         let next_offset = params.range.as_ref().map(|r| r.start).unwrap_or(0);
         let (event_sender, mut event_receiver) = futures::channel::mpsc::unbounded();
         let meta_request = {
-            let span =
-                request_span!(self.inner, "get_object", bucket, key, range=?params.range, if_match=?params.if_match);
+            let span = request_span!(self.inner, "get_object", bucket, key, range=?params.range, if_match=?params.if_match, version_id=?params.version_id);
 
             let mut message = self
                 .inner
@@ -72,12 +74,16 @@ impl S3CrtClient {
             }
 
             let key = format!("/{key}");
-            message
-                .set_request_path(key)
-                .map_err(S3RequestError::construction_failure)?;
+            match params.version_id.as_deref() {
+                Some(version_id) => {
+                    message.set_request_path_and_query(key, QueryFragment::Query(&[("versionId", version_id)]))
+                }
+                None => message.set_request_path(key),
+            }
+            .map_err(S3RequestError::construction_failure)?;
 
             let mut options = message.into_options(S3Operation::GetObject);
-            options.part_size(self.inner.read_part_size as u64);
+            options.part_size(params.part_size.unwrap_or(self.inner.read_part_size) as u64);
             if let Some(id) = params.custom_id {
                 options.custom_id(id);
             }
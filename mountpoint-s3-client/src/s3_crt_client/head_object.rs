@@ -74,7 +74,20 @@ impl HeadObjectResult {
         let restore_status = Self::parse_restore_status(headers)?;
         let sse_type = headers.get_as_optional_string("x-amz-server-side-encryption")?;
         let sse_kms_key_id = headers.get_as_optional_string("x-amz-server-side-encryption-aws-kms-key-id")?;
+        let version_id = headers.get_as_optional_string("x-amz-version-id")?;
+        let content_encoding = headers.get_as_optional_string("Content-Encoding")?;
+        let content_type = headers.get_as_optional_string("Content-Type")?;
+        let cache_control = headers.get_as_optional_string("Cache-Control")?;
+        let content_disposition = headers.get_as_optional_string("Content-Disposition")?;
         let checksum = parse_checksum(headers)?;
+        let object_metadata = headers
+            .iter()
+            .filter_map(|(key, value)| {
+                let metadata_header = key.to_str()?.strip_prefix("x-amz-meta-")?;
+                let value = value.to_str()?;
+                Some((metadata_header.to_string(), value.to_string()))
+            })
+            .collect();
         let result = HeadObjectResult {
             size,
             last_modified,
@@ -84,6 +97,12 @@ impl HeadObjectResult {
             checksum,
             sse_type,
             sse_kms_key_id,
+            object_metadata,
+            version_id,
+            content_encoding,
+            content_type,
+            cache_control,
+            content_disposition,
         };
         Ok(result)
     }
@@ -0,0 +1,230 @@
+use std::ops::Deref;
+use std::os::unix::prelude::OsStrExt;
+use std::str::FromStr;
+
+use mountpoint_s3_crt::s3::client::MetaRequestResult;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::object_client::{
+    DeleteMarkerInfo, ListObjectVersionsError, ListObjectVersionsResult, ObjectClientError, ObjectClientResult,
+    ObjectVersionInfo,
+};
+
+use super::list_objects::{ParseError, get_field, get_text, parse_restore_status};
+use super::{QueryFragment, S3CrtClient, S3Operation, S3RequestError};
+
+fn parse_result_from_bytes(bytes: &[u8]) -> Result<ListObjectVersionsResult, ParseError> {
+    parse_result_from_xml(&mut xmltree::Element::parse(bytes)?)
+}
+
+fn parse_result_from_xml(element: &mut xmltree::Element) -> Result<ListObjectVersionsResult, ParseError> {
+    let mut versions = Vec::new();
+    while let Some(version) = element.take_child("Version") {
+        versions.push(parse_version_from_xml(&version)?);
+    }
+
+    let mut delete_markers = Vec::new();
+    while let Some(delete_marker) = element.take_child("DeleteMarker") {
+        delete_markers.push(parse_delete_marker_from_xml(&delete_marker)?);
+    }
+
+    let mut common_prefixes = Vec::new();
+    while let Some(common_prefix) = element.take_child("CommonPrefixes") {
+        common_prefixes.push(get_field(&common_prefix, "Prefix")?);
+    }
+
+    let next_key_marker = element.get_child("NextKeyMarker").map(get_text).transpose()?;
+    // The version ID marker is empty when the listing ends between keys.
+    let next_version_id_marker = element
+        .get_child("NextVersionIdMarker")
+        .and_then(|elem| elem.get_text())
+        .map(|text| text.into_owned());
+
+    let is_truncated = get_field(element, "IsTruncated")?;
+    let is_truncated = bool::from_str(&is_truncated).map_err(|e| ParseError::Bool(e, "IsTruncated".to_string()))?;
+
+    if is_truncated != next_key_marker.is_some() {
+        return Err(ParseError::InvalidResponse(
+            element.clone().into(),
+            "IsTruncated doesn't match NextKeyMarker".to_string(),
+        ));
+    }
+
+    Ok(ListObjectVersionsResult {
+        versions,
+        delete_markers,
+        common_prefixes,
+        next_key_marker,
+        next_version_id_marker,
+    })
+}
+
+fn parse_is_latest(element: &xmltree::Element) -> Result<bool, ParseError> {
+    bool::from_str(&get_field(element, "IsLatest")?).map_err(|e| ParseError::Bool(e, "IsLatest".to_string()))
+}
+
+fn parse_last_modified(element: &xmltree::Element) -> Result<OffsetDateTime, ParseError> {
+    OffsetDateTime::parse(&get_field(element, "LastModified")?, &Rfc3339)
+        .map_err(|e| ParseError::OffsetDateTime(e, "LastModified".to_string()))
+}
+
+fn parse_version_from_xml(element: &xmltree::Element) -> Result<ObjectVersionInfo, ParseError> {
+    let size = get_field(element, "Size")?;
+    let size = u64::from_str(&size).map_err(|e| ParseError::Int(e, "Size".to_string()))?;
+
+    Ok(ObjectVersionInfo {
+        key: get_field(element, "Key")?,
+        version_id: get_field(element, "VersionId")?,
+        is_latest: parse_is_latest(element)?,
+        size,
+        last_modified: parse_last_modified(element)?,
+        storage_class: get_field(element, "StorageClass").ok(),
+        restore_status: parse_restore_status(element)?,
+        etag: get_field(element, "ETag")?,
+    })
+}
+
+fn parse_delete_marker_from_xml(element: &xmltree::Element) -> Result<DeleteMarkerInfo, ParseError> {
+    Ok(DeleteMarkerInfo {
+        key: get_field(element, "Key")?,
+        version_id: get_field(element, "VersionId")?,
+        is_latest: parse_is_latest(element)?,
+        last_modified: parse_last_modified(element)?,
+    })
+}
+
+impl S3CrtClient {
+    pub(super) async fn list_object_versions(
+        &self,
+        bucket: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        delimiter: &str,
+        max_keys: usize,
+        prefix: &str,
+    ) -> ObjectClientResult<ListObjectVersionsResult, ListObjectVersionsError, S3RequestError> {
+        // Scope the endpoint, message, etc. since otherwise rustc thinks we use Message across the await.
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("GET", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let max_keys = format!("{max_keys}");
+            let mut query = vec![
+                ("versions", ""),
+                ("delimiter", delimiter),
+                ("max-keys", &max_keys),
+                ("prefix", prefix),
+            ];
+            if let Some(key_marker) = key_marker {
+                query.push(("key-marker", key_marker));
+            }
+            if let Some(version_id_marker) = version_id_marker {
+                query.push(("version-id-marker", version_id_marker));
+            }
+
+            message
+                .set_request_path_and_query("/", QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let span = request_span!(
+                self.inner,
+                "list_object_versions",
+                bucket,
+                continued = key_marker.is_some(),
+                delimiter,
+                max_keys,
+                prefix
+            );
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::ListObjectVersions),
+                span,
+                parse_list_object_versions_error,
+            )?
+        };
+
+        let body = body.await?;
+
+        parse_result_from_bytes(&body)
+            .map_err(|e| ObjectClientError::ClientError(S3RequestError::InternalError(e.into())))
+    }
+}
+
+fn parse_list_object_versions_error(result: &MetaRequestResult) -> Option<ListObjectVersionsError> {
+    match result.response_status {
+        404 => {
+            let body = result.error_response_body.as_ref()?;
+            let root = xmltree::Element::parse(body.as_bytes()).ok()?;
+            let error_code = root.get_child("Code")?;
+            let error_str = error_code.get_text()?;
+            match error_str.deref() {
+                "NoSuchBucket" => Some(ListObjectVersionsError::NoSuchBucket),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+
+    use super::*;
+
+    fn make_result(response_status: i32, body: impl Into<OsString>) -> MetaRequestResult {
+        MetaRequestResult {
+            response_status,
+            crt_error: 1i32.into(),
+            error_response_headers: None,
+            error_response_body: Some(body.into()),
+        }
+    }
+
+    #[test]
+    fn parse_versions() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><ListVersionsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Name>amzn-s3-demo-bucket</Name><Prefix>dir/</Prefix><KeyMarker></KeyMarker><VersionIdMarker></VersionIdMarker><NextKeyMarker>dir/b</NextKeyMarker><NextVersionIdMarker>v3</NextVersionIdMarker><MaxKeys>3</MaxKeys><Delimiter>/</Delimiter><IsTruncated>true</IsTruncated><Version><Key>dir/a</Key><VersionId>v1</VersionId><IsLatest>true</IsLatest><LastModified>2024-01-02T00:00:00.000Z</LastModified><ETag>"etag1"</ETag><Size>10</Size><Owner><ID>owner</ID></Owner><StorageClass>STANDARD</StorageClass></Version><DeleteMarker><Key>dir/b</Key><VersionId>v3</VersionId><IsLatest>true</IsLatest><LastModified>2024-01-03T00:00:00.000Z</LastModified></DeleteMarker><Version><Key>dir/b</Key><VersionId>v2</VersionId><IsLatest>false</IsLatest><LastModified>2024-01-01T00:00:00.000Z</LastModified><ETag>"etag2"</ETag><Size>20</Size><StorageClass>STANDARD</StorageClass></Version><CommonPrefixes><Prefix>dir/c/</Prefix></CommonPrefixes></ListVersionsResult>"#;
+        let result = parse_result_from_bytes(&body[..]).unwrap();
+        let versions: Vec<_> = result
+            .versions
+            .iter()
+            .map(|version| {
+                (
+                    version.key.as_str(),
+                    version.version_id.as_str(),
+                    version.is_latest,
+                    version.size,
+                )
+            })
+            .collect();
+        assert_eq!(versions, [("dir/a", "v1", true, 10), ("dir/b", "v2", false, 20)]);
+        assert_eq!(result.versions[1].etag, "\"etag2\"");
+        assert_eq!(result.delete_markers.len(), 1);
+        assert_eq!(result.delete_markers[0].key, "dir/b");
+        assert_eq!(
+            result.delete_markers[0].last_modified,
+            OffsetDateTime::parse("2024-01-03T00:00:00Z", &Rfc3339).unwrap()
+        );
+        assert_eq!(result.common_prefixes, ["dir/c/"]);
+        assert_eq!(result.next_key_marker.as_deref(), Some("dir/b"));
+        assert_eq!(result.next_version_id_marker.as_deref(), Some("v3"));
+    }
+
+    #[test]
+    fn parse_last_page() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><ListVersionsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Name>amzn-s3-demo-bucket</Name><Prefix></Prefix><KeyMarker>dir/b</KeyMarker><VersionIdMarker>v3</VersionIdMarker><MaxKeys>1000</MaxKeys><IsTruncated>false</IsTruncated></ListVersionsResult>"#;
+        let result = parse_result_from_bytes(&body[..]).unwrap();
+        assert!(result.versions.is_empty());
+        assert!(result.next_key_marker.is_none());
+    }
+
+    #[test]
+    fn parse_404_no_such_bucket() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>NoSuchBucket</Code><Message>The specified bucket does not exist</Message><BucketName>amzn-s3-demo-bucket</BucketName><RequestId>4YAYHJ0E82DDDNF0</RequestId><HostId>Ajn9+i3d3VWQi339YrGqBbJqQlj5HaX2vplXp9IlDPAxsJ4vsIAsje0P2gJ0of/mTKKz/fv9pNy9RqhbLUBc/g==</HostId></Error>"#;
+        let result = make_result(404, OsStr::from_bytes(&body[..]));
+        let result = parse_list_object_versions_error(&result);
+        assert_eq!(result, Some(ListObjectVersionsError::NoSuchBucket));
+    }
+}
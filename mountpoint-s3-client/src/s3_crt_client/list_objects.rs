@@ -38,7 +38,7 @@ pub enum ParseError {
 }
 
 /// Copy text out of an XML element, with the right error type.
-fn get_text(element: &xmltree::Element) -> Result<String, ParseError> {
+pub(super) fn get_text(element: &xmltree::Element) -> Result<String, ParseError> {
     Ok(element
         .get_text()
         .ok_or_else(|| ParseError::InvalidResponse(element.clone().into(), "field has no text".to_string()))?
@@ -53,7 +53,7 @@ fn get_child<'a>(element: &'a xmltree::Element, name: &str) -> Result<&'a xmltre
 }
 
 /// Get the text out of a child node, with the right error type.
-fn get_field(element: &xmltree::Element, name: &str) -> Result<String, ParseError> {
+pub(super) fn get_field(element: &xmltree::Element, name: &str) -> Result<String, ParseError> {
     get_text(get_child(element, name)?)
 }
 
@@ -97,7 +97,7 @@ fn parse_result_from_xml(element: &mut xmltree::Element) -> Result<ListObjectsRe
     })
 }
 
-fn parse_restore_status(element: &xmltree::Element) -> Result<Option<RestoreStatus>, ParseError> {
+pub(super) fn parse_restore_status(element: &xmltree::Element) -> Result<Option<RestoreStatus>, ParseError> {
     let Some(restore_status) = element.get_child("RestoreStatus") else {
         return Ok(None);
     };
@@ -0,0 +1,507 @@
+use std::ops::Deref;
+use std::os::unix::prelude::OsStrExt;
+
+use mountpoint_s3_crt::http::request_response::Header;
+use mountpoint_s3_crt::io::stream::InputStream;
+use mountpoint_s3_crt::s3::client::MetaRequestResult;
+use thiserror::Error;
+use xmltree::{Element, XMLNode};
+
+use crate::checksums::{
+    self, crc32_from_base64, crc32_to_base64, crc32c_from_base64, crc32c_to_base64, crc64nvme_from_base64,
+    crc64nvme_to_base64, sha1_to_base64, sha256_to_base64,
+};
+use crate::object_client::{
+    AbortMultipartUploadResult, CompleteMultipartUploadParams, CompletedPart, CreateMultipartUploadParams,
+    CreateMultipartUploadResult, ETag, MultipartUploadError, ObjectClientError, ObjectClientResult, PutObjectResult,
+    UploadChecksum, UploadPartCopyParams, UploadPartParams, UploadPartResult,
+};
+
+use super::object_tagging::tagging_header;
+use super::{QueryFragment, S3CrtClient, S3Operation, S3RequestError, copy_source};
+
+const SSE_TYPE_HEADER_NAME: &str = "x-amz-server-side-encryption";
+const SSE_KEY_ID_HEADER_NAME: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    #[error("XML parsing error: {0:?}")]
+    Xml(#[from] xmltree::ParseError),
+
+    #[error("XML writing error: {0:?}")]
+    XmlWrite(#[from] xmltree::Error),
+
+    #[error("Missing field {1} from XML element {0:?}")]
+    MissingField(Box<Element>, String),
+
+    #[error("Invalid checksum in field {0}: {1}")]
+    Checksum(String, #[source] checksums::ParseError),
+
+    #[error("Error response {0:?}")]
+    ErrorResponse(String),
+}
+
+impl S3CrtClient {
+    pub(super) async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        params: &CreateMultipartUploadParams,
+    ) -> ObjectClientResult<CreateMultipartUploadResult, MultipartUploadError, S3RequestError> {
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("POST", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Action("uploads"))
+                .map_err(S3RequestError::construction_failure)?;
+            if let Some(checksum_algorithm) = &params.checksum_algorithm {
+                message
+                    .set_header(&Header::new("x-amz-checksum-algorithm", checksum_algorithm.to_string()))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(storage_class) = &params.storage_class {
+                message
+                    .set_header(&Header::new("x-amz-storage-class", storage_class))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(sse) = &params.server_side_encryption {
+                message
+                    .set_header(&Header::new(SSE_TYPE_HEADER_NAME, sse))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(key_id) = &params.ssekms_key_id {
+                message
+                    .set_header(&Header::new(SSE_KEY_ID_HEADER_NAME, key_id))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            for (name, value) in &params.object_metadata {
+                message
+                    .set_header(&Header::new(format!("x-amz-meta-{name}"), value))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if !params.object_tags.is_empty() {
+                message
+                    .set_header(&Header::new("x-amz-tagging", tagging_header(&params.object_tags)))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(content_type) = &params.content_type {
+                message
+                    .set_header(&Header::new("Content-Type", content_type))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+
+            let span = request_span!(self.inner, "create_multipart_upload", bucket, key);
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::CreateMultipartUpload),
+                span,
+                parse_multipart_upload_error,
+            )?
+        };
+
+        let body = body.await?;
+
+        let root = Element::parse(&body[..]).map_err(|e| S3RequestError::internal_failure(ParseError::from(e)))?;
+        let upload_id = get_field(&root, "UploadId").map_err(S3RequestError::internal_failure)?;
+        Ok(CreateMultipartUploadResult { upload_id })
+    }
+
+    pub(super) async fn upload_part<'a>(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartParams,
+        contents: impl AsRef<[u8]> + Send + 'a,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, S3RequestError> {
+        let slice = contents.as_ref();
+        let request = {
+            let mut message = self
+                .inner
+                .new_request_template("PUT", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let query = [
+                ("partNumber".to_owned(), part_number.to_string()),
+                ("uploadId".to_owned(), upload_id.to_owned()),
+            ];
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_content_length_header(slice.len())
+                .map_err(S3RequestError::construction_failure)?;
+            if let Some(checksum) = &params.checksum {
+                message
+                    .set_checksum_header(checksum)
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+
+            let body_input_stream =
+                InputStream::new_from_slice(&self.inner.allocator, slice).map_err(S3RequestError::CrtError)?;
+            message.set_body_stream(Some(body_input_stream));
+
+            let span = request_span!(self.inner, "upload_part", bucket, key, part_number);
+
+            self.inner.meta_request_with_headers_payload(
+                message.into_options(S3Operation::UploadPart),
+                span,
+                parse_multipart_upload_error,
+            )?
+        };
+
+        let headers = request.await?;
+
+        let etag = headers
+            .get_as_string("ETag")
+            .map_err(|e| S3RequestError::InternalError(Box::new(e)))?;
+        // S3 validated the part against its checksum, so it is the checksum of the part.
+        Ok(UploadPartResult {
+            etag: etag.into(),
+            checksum: params.checksum.clone(),
+        })
+    }
+
+    pub(super) async fn upload_part_copy(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        params: &UploadPartCopyParams,
+    ) -> ObjectClientResult<UploadPartResult, MultipartUploadError, S3RequestError> {
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("PUT", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let query = [
+                ("partNumber".to_owned(), part_number.to_string()),
+                ("uploadId".to_owned(), upload_id.to_owned()),
+            ];
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_header(&Header::new(
+                    "x-amz-copy-source",
+                    copy_source(bucket, &params.source_key),
+                ))
+                .map_err(S3RequestError::construction_failure)?;
+            if let Some(range) = &params.source_range {
+                // The range of the header is inclusive.
+                let range = format!("bytes={}-{}", range.start, range.end.saturating_sub(1));
+                message
+                    .set_header(&Header::new("x-amz-copy-source-range", range))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(etag) = &params.source_if_match {
+                message
+                    .set_header(&Header::new("x-amz-copy-source-if-match", etag.as_str()))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+
+            let span = request_span!(self.inner, "upload_part_copy", bucket, key, part_number);
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::UploadPartCopy),
+                span,
+                parse_multipart_upload_error,
+            )?
+        };
+
+        let body = body.await?;
+
+        UploadPartResult::parse_copy_part_result(&body).map_err(|e| S3RequestError::internal_failure(e).into())
+    }
+
+    pub(super) async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        params: &CompleteMultipartUploadParams,
+    ) -> ObjectClientResult<PutObjectResult, MultipartUploadError, S3RequestError> {
+        let request_body = complete_multipart_upload_body(parts).map_err(S3RequestError::internal_failure)?;
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("POST", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let query = [("uploadId", upload_id)];
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_content_length_header(request_body.len())
+                .map_err(S3RequestError::construction_failure)?;
+            if let Some(etag) = &params.if_match {
+                message
+                    .set_header(&Header::new("If-Match", etag.as_str()))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+
+            let body_input_stream =
+                InputStream::new_from_slice(&self.inner.allocator, &request_body).map_err(S3RequestError::CrtError)?;
+            message.set_body_stream(Some(body_input_stream));
+
+            let span = request_span!(self.inner, "complete_multipart_upload", bucket, key);
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::CompleteMultipartUpload),
+                span,
+                parse_multipart_upload_error,
+            )?
+        };
+
+        let body = body.await?;
+
+        // CompleteMultipartUpload can fail after responding with a 200 status, in which case the body is an error.
+        let root = Element::parse(&body[..]).map_err(|e| S3RequestError::internal_failure(ParseError::from(e)))?;
+        if root.name == "Error" {
+            let code = get_field(&root, "Code").map_err(S3RequestError::internal_failure)?;
+            return Err(match multipart_upload_error_for_code(&code) {
+                Some(error) => ObjectClientError::ServiceError(error),
+                None => S3RequestError::internal_failure(ParseError::ErrorResponse(code)).into(),
+            });
+        }
+        let etag = get_field(&root, "ETag").map_err(S3RequestError::internal_failure)?;
+        Ok(PutObjectResult {
+            etag: etag.into(),
+            sse_type: None,
+            sse_kms_key_id: None,
+        })
+    }
+
+    pub(super) async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> ObjectClientResult<AbortMultipartUploadResult, MultipartUploadError, S3RequestError> {
+        let request = {
+            let mut message = self
+                .inner
+                .new_request_template("DELETE", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let query = [("uploadId", upload_id)];
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let span = request_span!(self.inner, "abort_multipart_upload", bucket, key);
+
+            self.inner.meta_request_without_payload(
+                message.into_options(S3Operation::AbortMultipartUpload),
+                span,
+                parse_multipart_upload_error,
+            )?
+        };
+
+        request.await?;
+        Ok(AbortMultipartUploadResult {})
+    }
+}
+
+impl UploadPartResult {
+    fn parse_copy_part_result(bytes: &[u8]) -> Result<Self, ParseError> {
+        let root = Element::parse(bytes)?;
+        let etag = get_field(&root, "ETag")?;
+        let checksum = parse_checksum(&root)?;
+        Ok(Self {
+            etag: ETag::from(etag),
+            checksum,
+        })
+    }
+}
+
+/// The checksum of a part in a CopyPartResult. SHA checksums can't be decoded, so they are ignored.
+fn parse_checksum(element: &Element) -> Result<Option<UploadChecksum>, ParseError> {
+    let field = |name: &str| element.get_child(name).and_then(|child| child.get_text());
+    let checksum_error = |name: &'static str| move |e| ParseError::Checksum(name.to_owned(), e);
+    if let Some(crc) = field("ChecksumCRC32C") {
+        let crc = crc32c_from_base64(&crc).map_err(checksum_error("ChecksumCRC32C"))?;
+        return Ok(Some(UploadChecksum::Crc32c(crc)));
+    }
+    if let Some(crc) = field("ChecksumCRC32") {
+        let crc = crc32_from_base64(&crc).map_err(checksum_error("ChecksumCRC32"))?;
+        return Ok(Some(UploadChecksum::Crc32(crc)));
+    }
+    if let Some(crc) = field("ChecksumCRC64NVME") {
+        let crc = crc64nvme_from_base64(&crc).map_err(checksum_error("ChecksumCRC64NVME"))?;
+        return Ok(Some(UploadChecksum::Crc64nvme(crc)));
+    }
+    Ok(None)
+}
+
+/// Write the body of a CompleteMultipartUpload request.
+fn complete_multipart_upload_body(parts: &[CompletedPart]) -> Result<Vec<u8>, ParseError> {
+    let text_element = |name: &str, text: String| {
+        let mut element = Element::new(name);
+        element.children.push(XMLNode::Text(text));
+        XMLNode::Element(element)
+    };
+
+    let mut complete = Element::new("CompleteMultipartUpload");
+    for part in parts {
+        let mut element = Element::new("Part");
+        element
+            .children
+            .push(text_element("PartNumber", part.part_number.to_string()));
+        element
+            .children
+            .push(text_element("ETag", part.etag.as_str().to_owned()));
+        if let Some(checksum) = &part.checksum {
+            let (name, value) = match checksum {
+                UploadChecksum::Crc64nvme(crc) => ("ChecksumCRC64NVME", crc64nvme_to_base64(crc)),
+                UploadChecksum::Crc32c(crc) => ("ChecksumCRC32C", crc32c_to_base64(crc)),
+                UploadChecksum::Crc32(crc) => ("ChecksumCRC32", crc32_to_base64(crc)),
+                UploadChecksum::Sha1(sha) => ("ChecksumSHA1", sha1_to_base64(sha)),
+                UploadChecksum::Sha256(sha) => ("ChecksumSHA256", sha256_to_base64(sha)),
+            };
+            element.children.push(text_element(name, value));
+        }
+        complete.children.push(XMLNode::Element(element));
+    }
+
+    let mut body = Vec::new();
+    complete.write(&mut body)?;
+    Ok(body)
+}
+
+/// Copy text out of a child of an XML element, with the right error type.
+fn get_field(element: &Element, name: &str) -> Result<String, ParseError> {
+    element
+        .get_child(name)
+        .and_then(|child| child.get_text())
+        .map(|text| text.into_owned())
+        .ok_or_else(|| ParseError::MissingField(element.clone().into(), name.to_owned()))
+}
+
+fn multipart_upload_error_for_code(code: &str) -> Option<MultipartUploadError> {
+    match code {
+        "NoSuchBucket" => Some(MultipartUploadError::NoSuchBucket),
+        "NoSuchUpload" => Some(MultipartUploadError::NoSuchUpload),
+        "NoSuchKey" => Some(MultipartUploadError::SourceNotFound),
+        "PreconditionFailed" => Some(MultipartUploadError::PreconditionFailed),
+        "ObjectNotInActiveTierError" => Some(MultipartUploadError::ObjectNotInActiveTierError),
+        "EntityTooSmall" => Some(MultipartUploadError::EntityTooSmall),
+        "InvalidPart" | "InvalidPartOrder" => Some(MultipartUploadError::InvalidPart),
+        "BadDigest" => Some(MultipartUploadError::BadChecksum),
+        _ => None,
+    }
+}
+
+fn parse_multipart_upload_error(result: &MetaRequestResult) -> Option<MultipartUploadError> {
+    match result.response_status {
+        400 | 403 | 404 | 412 => {
+            let body = result.error_response_body.as_ref()?;
+            let root = Element::parse(body.as_bytes()).ok()?;
+            let code = root.get_child("Code")?.get_text()?;
+            match multipart_upload_error_for_code(code.deref()) {
+                Some(error) => Some(error),
+                None if result.response_status == 412 => Some(MultipartUploadError::PreconditionFailed),
+                None => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+
+    use super::*;
+
+    fn make_result(response_status: i32, body: impl Into<OsString>) -> MetaRequestResult {
+        MetaRequestResult {
+            response_status,
+            crt_error: 1i32.into(),
+            error_response_headers: None,
+            error_response_body: Some(body.into()),
+        }
+    }
+
+    #[test]
+    fn parse_copy_part_result() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><CopyPartResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><LastModified>2024-01-01T00:00:00.000Z</LastModified><ETag>"b54357faf0632cce46e942fa68356b38"</ETag><ChecksumCRC32C>yZRlqg==</ChecksumCRC32C></CopyPartResult>"#;
+        let result = UploadPartResult::parse_copy_part_result(&body[..]).unwrap();
+        assert_eq!(result.etag.as_str(), "\"b54357faf0632cce46e942fa68356b38\"");
+        let Some(UploadChecksum::Crc32c(crc)) = result.checksum else {
+            panic!("expected a CRC32C checksum");
+        };
+        assert_eq!(crc32c_to_base64(&crc), "yZRlqg==");
+    }
+
+    #[test]
+    fn complete_multipart_upload_body_round_trip() {
+        let parts = [
+            CompletedPart {
+                part_number: 1,
+                etag: ETag::from("\"a\"".to_owned()),
+                checksum: Some(UploadChecksum::Crc32c(crc32c_from_base64("yZRlqg==").unwrap())),
+            },
+            CompletedPart {
+                part_number: 2,
+                etag: ETag::from("\"b\"".to_owned()),
+                checksum: None,
+            },
+        ];
+        let body = complete_multipart_upload_body(&parts).unwrap();
+        let root = Element::parse(&body[..]).unwrap();
+        let parsed: Vec<_> = root
+            .children
+            .iter()
+            .filter_map(|node| node.as_element())
+            .map(|part| {
+                (
+                    get_field(part, "PartNumber").unwrap(),
+                    get_field(part, "ETag").unwrap(),
+                    get_field(part, "ChecksumCRC32C").ok(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("1".to_owned(), "\"a\"".to_owned(), Some("yZRlqg==".to_owned())),
+                ("2".to_owned(), "\"b\"".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_404_no_such_upload() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>NoSuchUpload</Code><Message>The specified upload does not exist.</Message><UploadId>abc</UploadId></Error>"#;
+        let result = make_result(404, OsStr::from_bytes(&body[..]));
+        assert_eq!(
+            parse_multipart_upload_error(&result),
+            Some(MultipartUploadError::NoSuchUpload)
+        );
+    }
+
+    #[test]
+    fn parse_412_precondition_failed() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>PreconditionFailed</Code><Message>At least one of the pre-conditions you specified did not hold</Message><Condition>x-amz-copy-source-If-Match</Condition></Error>"#;
+        let result = make_result(412, OsStr::from_bytes(&body[..]));
+        assert_eq!(
+            parse_multipart_upload_error(&result),
+            Some(MultipartUploadError::PreconditionFailed)
+        );
+    }
+
+    #[test]
+    fn parse_400_entity_too_small() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>EntityTooSmall</Code><Message>Your proposed upload is smaller than the minimum allowed object size.</Message></Error>"#;
+        let result = make_result(400, OsStr::from_bytes(&body[..]));
+        assert_eq!(
+            parse_multipart_upload_error(&result),
+            Some(MultipartUploadError::EntityTooSmall)
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::ops::Deref;
+use std::os::unix::prelude::OsStrExt;
+
+use mountpoint_s3_crt::http::request_response::Header;
+use mountpoint_s3_crt::io::stream::InputStream;
+use mountpoint_s3_crt::s3::client::MetaRequestResult;
+use percent_encoding::percent_encode;
+use thiserror::Error;
+use xmltree::{Element, XMLNode};
+
+use crate::checksums::crc32c;
+use crate::object_client::{
+    GetObjectTaggingError, GetObjectTaggingResult, ObjectClientResult, ObjectTags, PutObjectTaggingError,
+    PutObjectTaggingResult, UploadChecksum,
+};
+
+use super::{QueryFragment, S3CrtClient, S3Operation, S3RequestError, URLENCODE_QUERY_FRAGMENT};
+
+const TAGGING_QUERY_PARAM: &str = "tagging";
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    #[error("XML parsing error: {0:?}")]
+    Xml(#[from] xmltree::ParseError),
+
+    #[error("XML writing error: {0:?}")]
+    XmlWrite(#[from] xmltree::Error),
+
+    #[error("Missing field {1} from XML element {0:?}")]
+    MissingField(Box<Element>, String),
+}
+
+impl GetObjectTaggingResult {
+    fn parse_from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let mut root = Element::parse(bytes)?;
+        let mut tags = ObjectTags::new();
+        let Some(mut tag_set) = root.take_child("TagSet") else {
+            return Ok(Self { tags });
+        };
+        while let Some(tag) = tag_set.take_child("Tag") {
+            let key = get_field(&tag, "Key")?;
+            // Tags may have an empty value, which has no text.
+            let value = tag
+                .get_child("Value")
+                .ok_or_else(|| ParseError::MissingField(tag.clone().into(), "Value".to_owned()))?
+                .get_text()
+                .unwrap_or_default()
+                .into_owned();
+            tags.insert(key, value);
+        }
+        Ok(Self { tags })
+    }
+}
+
+/// Write the body of a PutObjectTagging request.
+fn tagging_body(tags: &ObjectTags) -> Result<Vec<u8>, ParseError> {
+    let text_element = |name: &str, text: &str| {
+        let mut element = Element::new(name);
+        element.children.push(XMLNode::Text(text.to_owned()));
+        XMLNode::Element(element)
+    };
+
+    let mut tag_set = Element::new("TagSet");
+    for (key, value) in tags {
+        let mut tag = Element::new("Tag");
+        tag.children.push(text_element("Key", key));
+        tag.children.push(text_element("Value", value));
+        tag_set.children.push(XMLNode::Element(tag));
+    }
+    let mut tagging = Element::new("Tagging");
+    tagging.children.push(XMLNode::Element(tag_set));
+
+    let mut body = Vec::new();
+    tagging.write(&mut body)?;
+    Ok(body)
+}
+
+/// Encode tags as the value of the `x-amz-tagging` header of the requests creating objects, a URL query string.
+pub(super) fn tagging_header(tags: &ObjectTags) -> String {
+    let mut tags: Vec<_> = tags.iter().collect();
+    tags.sort();
+    let encode = |s: &str| percent_encode(s.as_bytes(), URLENCODE_QUERY_FRAGMENT).to_string();
+    tags.into_iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+impl S3CrtClient {
+    pub(super) async fn get_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> ObjectClientResult<GetObjectTaggingResult, GetObjectTaggingError, S3RequestError> {
+        let body = {
+            let mut message = self
+                .inner
+                .new_request_template("GET", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+
+            let path = format!("/{key}");
+            message
+                .set_request_path_and_query(path, QueryFragment::Action(TAGGING_QUERY_PARAM))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let span = request_span!(self.inner, "get_object_tagging", bucket, key);
+
+            self.inner.meta_request_with_body_payload(
+                message.into_options(S3Operation::GetObjectTagging),
+                span,
+                parse_get_object_tagging_error,
+            )?
+        };
+
+        let body = body.await?;
+
+        GetObjectTaggingResult::parse_from_bytes(&body).map_err(|e| S3RequestError::internal_failure(e).into())
+    }
+
+    pub(super) async fn put_object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+        tags: &ObjectTags,
+    ) -> ObjectClientResult<PutObjectTaggingResult, PutObjectTaggingError, S3RequestError> {
+        let body = tagging_body(tags).map_err(S3RequestError::internal_failure)?;
+        let request = {
+            let mut message = self
+                .inner
+                .new_request_template("PUT", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+
+            let path = format!("/{key}");
+            message
+                .set_request_path_and_query(path, QueryFragment::Action(TAGGING_QUERY_PARAM))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_content_length_header(body.len())
+                .map_err(S3RequestError::construction_failure)?;
+            // PutObjectTagging requires an integrity check of the body.
+            message
+                .set_checksum_header(&UploadChecksum::Crc32c(crc32c::checksum(&body)))
+                .map_err(S3RequestError::construction_failure)?;
+            message
+                .set_header(&Header::new("x-amz-sdk-checksum-algorithm", "CRC32C"))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let body_input_stream =
+                InputStream::new_from_slice(&self.inner.allocator, &body).map_err(S3RequestError::CrtError)?;
+            message.set_body_stream(Some(body_input_stream));
+
+            let span = request_span!(self.inner, "put_object_tagging", bucket, key);
+
+            self.inner.meta_request_without_payload(
+                message.into_options(S3Operation::PutObjectTagging),
+                span,
+                parse_put_object_tagging_error,
+            )?
+        };
+
+        request.await?;
+        Ok(PutObjectTaggingResult {})
+    }
+}
+
+/// Copy text out of a child of an XML element, with the right error type.
+fn get_field(element: &Element, name: &str) -> Result<String, ParseError> {
+    element
+        .get_child(name)
+        .and_then(|child| child.get_text())
+        .map(|text| text.into_owned())
+        .ok_or_else(|| ParseError::MissingField(element.clone().into(), name.to_owned()))
+}
+
+/// The error code of an S3 error response.
+fn error_code(result: &MetaRequestResult) -> Option<String> {
+    let body = result.error_response_body.as_ref()?;
+    let root = Element::parse(body.as_bytes()).ok()?;
+    Some(root.get_child("Code")?.get_text()?.into_owned())
+}
+
+fn parse_get_object_tagging_error(result: &MetaRequestResult) -> Option<GetObjectTaggingError> {
+    match result.response_status {
+        404 => match error_code(result)?.deref() {
+            "NoSuchBucket" => Some(GetObjectTaggingError::NoSuchBucket),
+            "NoSuchKey" => Some(GetObjectTaggingError::NoSuchKey),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn parse_put_object_tagging_error(result: &MetaRequestResult) -> Option<PutObjectTaggingError> {
+    match result.response_status {
+        400 => match error_code(result)?.deref() {
+            "InvalidTag" => Some(PutObjectTaggingError::InvalidTag),
+            _ => None,
+        },
+        404 => match error_code(result)?.deref() {
+            "NoSuchBucket" => Some(PutObjectTaggingError::NoSuchBucket),
+            "NoSuchKey" => Some(PutObjectTaggingError::NoSuchKey),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+
+    use super::*;
+
+    fn make_result(response_status: i32, body: impl Into<OsString>) -> MetaRequestResult {
+        MetaRequestResult {
+            response_status,
+            crt_error: 1i32.into(),
+            error_response_headers: None,
+            error_response_body: Some(body.into()),
+        }
+    }
+
+    #[test]
+    fn parse_tagging() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Tagging xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><TagSet><Tag><Key>project</Key><Value>mountpoint</Value></Tag><Tag><Key>empty</Key><Value></Value></Tag></TagSet></Tagging>"#;
+        let result = GetObjectTaggingResult::parse_from_bytes(&body[..]).unwrap();
+        assert_eq!(
+            result.tags,
+            ObjectTags::from([
+                ("project".to_owned(), "mountpoint".to_owned()),
+                ("empty".to_owned(), String::new()),
+            ])
+        );
+
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Tagging xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><TagSet></TagSet></Tagging>"#;
+        let result = GetObjectTaggingResult::parse_from_bytes(&body[..]).unwrap();
+        assert!(result.tags.is_empty());
+    }
+
+    #[test]
+    fn tagging_body_round_trip() {
+        let tags = ObjectTags::from([("a&b".to_owned(), "<value>".to_owned())]);
+        let body = tagging_body(&tags).unwrap();
+        let result = GetObjectTaggingResult::parse_from_bytes(&body).unwrap();
+        assert_eq!(result.tags, tags);
+    }
+
+    #[test]
+    fn tagging_header_encoding() {
+        let tags = ObjectTags::from([
+            ("team".to_owned(), "data platform".to_owned()),
+            ("a&b".to_owned(), "c=d".to_owned()),
+            ("empty".to_owned(), String::new()),
+        ]);
+        assert_eq!(tagging_header(&tags), "a%26b=c%3Dd&empty=&team=data%20platform");
+    }
+
+    #[test]
+    fn parse_404_no_such_key() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message><Key>not-a-real-key</Key><RequestId>NTKJWKHQBYNS73A9</RequestId></Error>"#;
+        let result = make_result(404, OsStr::from_bytes(&body[..]));
+        assert_eq!(
+            parse_get_object_tagging_error(&result),
+            Some(GetObjectTaggingError::NoSuchKey)
+        );
+        assert_eq!(
+            parse_put_object_tagging_error(&result),
+            Some(PutObjectTaggingError::NoSuchKey)
+        );
+    }
+
+    #[test]
+    fn parse_400_invalid_tag() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>InvalidTag</Code><Message>The TagKey you have provided is invalid</Message><RequestId>NTKJWKHQBYNS73A9</RequestId></Error>"#;
+        let result = make_result(400, OsStr::from_bytes(&body[..]));
+        assert_eq!(
+            parse_put_object_tagging_error(&result),
+            Some(PutObjectTaggingError::InvalidTag)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::os::unix::prelude::OsStrExt;
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use mountpoint_s3_crt::auth::signer::presign_request;
+
+use crate::object_client::{
+    ObjectClientError, ObjectClientResult, PresignGetObjectError, PresignGetObjectParams, PresignGetObjectResult,
+};
+
+use super::{QueryFragment, S3CrtClient, S3Message, S3RequestError};
+
+impl S3CrtClient {
+    pub(super) async fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+        params: &PresignGetObjectParams,
+    ) -> ObjectClientResult<PresignGetObjectResult, PresignGetObjectError, S3RequestError> {
+        PresignGetObjectError::check_expiration(expires_in).map_err(ObjectClientError::ServiceError)?;
+
+        // The message is not `Send`, so it must be handed to the signer before awaiting the signature.
+        let (origin, receiver) = {
+            // Only the host is signed, since the URL is used by other clients, which send their own headers. The
+            // headers of the requests of this client that change the request go in the query string instead.
+            let mut message = self
+                .inner
+                .new_message_template("GET", bucket)
+                .map_err(S3RequestError::construction_failure)?;
+            let mut query = Vec::new();
+            if let Some(version_id) = &params.version_id {
+                query.push(("versionId", version_id.as_str()));
+            }
+            if let Some(payer) = &self.inner.request_payer {
+                query.push(("x-amz-request-payer", payer.as_str()));
+            }
+            if let Some(owner) = &self.inner.bucket_owner {
+                query.push(("x-amz-expected-bucket-owner", owner.as_str()));
+            }
+            message
+                .set_request_path_and_query(format!("/{key}"), QueryFragment::Query(&query))
+                .map_err(S3RequestError::construction_failure)?;
+
+            let S3Message {
+                inner: message,
+                uri,
+                signing_config,
+                ..
+            } = message;
+            let port = match uri.host_port() {
+                0 => String::new(),
+                port => format!(":{port}"),
+            };
+            let origin = format!(
+                "{}://{}{}",
+                uri.scheme().to_string_lossy(),
+                uri.host_name().to_string_lossy(),
+                port
+            );
+
+            let Some(signing_config) = signing_config else {
+                // Anonymous clients don't sign their requests, so anyone can already make them.
+                let path = message.get_request_path().map_err(S3RequestError::CrtError)?;
+                let url = format!("{origin}{}", String::from_utf8_lossy(path.as_bytes()));
+                return Ok(PresignGetObjectResult { url });
+            };
+            let (sender, receiver) = oneshot::channel();
+            presign_request(
+                &self.inner.allocator,
+                message,
+                signing_config,
+                expires_in,
+                move |result| {
+                    let _ = sender.send(result.and_then(|message| message.get_request_path()));
+                },
+            )
+            .map_err(S3RequestError::CrtError)?;
+            (origin, receiver)
+        };
+
+        let path = receiver
+            .await
+            .expect("the signing callback is always invoked")
+            .map_err(S3RequestError::CrtError)?;
+        let url = format!("{origin}{}", String::from_utf8_lossy(path.as_bytes()));
+        Ok(PresignGetObjectResult { url })
+    }
+}
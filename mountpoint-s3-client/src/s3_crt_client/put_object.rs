@@ -14,9 +14,11 @@ use tracing::error;
 use xmltree::Element;
 
 use crate::object_client::{
-    ObjectClientResult, PutObjectError, PutObjectParams, PutObjectRequest, PutObjectResult, PutObjectSingleParams,
+    ObjectClientError, ObjectClientResult, PutObjectError, PutObjectParams, PutObjectRequest, PutObjectResult,
+    PutObjectSingleParams,
 };
 
+use super::object_tagging::tagging_header;
 use super::{
     ETag, PutObjectTrailingChecksums, S3CrtClient, S3Message, S3MetaRequest, S3Operation, S3RequestError,
     emit_throughput_metric,
@@ -49,7 +51,13 @@ impl S3CrtClient {
             )?;
 
             let checksum_config = match params.trailing_checksums {
-                PutObjectTrailingChecksums::Enabled => Some(ChecksumConfig::trailing_crc32c()),
+                PutObjectTrailingChecksums::Enabled => match &params.trailing_checksum_algorithm {
+                    Some(checksum_algorithm) => Some(
+                        ChecksumConfig::trailing(checksum_algorithm)
+                            .ok_or(ObjectClientError::ServiceError(PutObjectError::InvalidChecksumType))?,
+                    ),
+                    None => Some(ChecksumConfig::trailing_crc32c()),
+                },
                 PutObjectTrailingChecksums::ReviewOnly => Some(ChecksumConfig::upload_review_crc32c()),
                 PutObjectTrailingChecksums::Disabled => None,
             };
@@ -60,6 +68,16 @@ impl S3CrtClient {
                     .set_header(&Header::new(format!("x-amz-meta-{name}"), value))
                     .map_err(S3RequestError::construction_failure)?
             }
+            if !params.object_tags.is_empty() {
+                message
+                    .set_header(&Header::new("x-amz-tagging", tagging_header(&params.object_tags)))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(content_type) = &params.content_type {
+                message
+                    .set_header(&Header::new("Content-Type", content_type))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
             for (name, value) in &params.custom_headers {
                 message
                     .inner
@@ -72,10 +90,13 @@ impl S3CrtClient {
             let mut options = message.into_options(S3Operation::PutObject);
             options.send_using_async_writes(true);
             options.on_upload_review(move |review| callback.invoke(review));
-            options.part_size(self.inner.write_part_size as u64);
+            options.part_size(params.part_size.unwrap_or(self.inner.write_part_size) as u64);
             if let Some(id) = params.custom_id {
                 options.custom_id(id);
             }
+            if let Some(max_concurrent_parts) = params.max_concurrent_parts {
+                options.max_active_connections_override(max_concurrent_parts);
+            }
 
             let on_mpu_created_sender = Arc::new(Mutex::new(Some(mpu_created_sender)));
             let on_failure_sender = on_mpu_created_sender.clone();
@@ -169,6 +190,16 @@ impl S3CrtClient {
                     .set_header(&Header::new(format!("x-amz-meta-{name}"), value))
                     .map_err(S3RequestError::construction_failure)?;
             }
+            if !params.object_tags.is_empty() {
+                message
+                    .set_header(&Header::new("x-amz-tagging", tagging_header(&params.object_tags)))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
+            if let Some(content_type) = &params.content_type {
+                message
+                    .set_header(&Header::new("Content-Type", content_type))
+                    .map_err(S3RequestError::construction_failure)?;
+            }
             for (name, value) in &params.custom_headers {
                 message
                     .inner
@@ -7,7 +7,12 @@ use common::creds::{assert_no_permissions_error, get_no_permissions_provider};
 use common::*;
 use mountpoint_s3_client::config::{S3ClientAuthConfig, S3ClientConfig};
 use mountpoint_s3_client::error::{CopyObjectError, ObjectClientError};
+use mountpoint_s3_client::types::{
+    CompleteMultipartUploadParams, CopyObjectParams, CreateMultipartUploadParams, HeadObjectParams,
+    UploadPartCopyParams,
+};
 use mountpoint_s3_client::{ObjectClient, S3CrtClient};
+use test_case::test_case;
 
 #[tokio::test]
 async fn test_copy_objects() {
@@ -42,6 +47,170 @@ async fn test_copy_objects() {
         .expect("copied object should exist");
 }
 
+#[test_case("a b+c%d?e#f"; "reserved characters")]
+#[test_case("dir/données€"; "non-ascii")]
+#[tokio::test]
+async fn test_copy_object_encoded_key(source_name: &str) {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_copy_object_encoded_key");
+
+    let key = format!("{prefix}/{source_name}");
+    let body = b"hello world!";
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(Bytes::from_static(body)))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let copy_key = format!("{prefix}/copy");
+    client
+        .copy_object(&bucket, &key, &bucket, &copy_key, &Default::default())
+        .await
+        .expect("copy_object operation should succeed");
+
+    let part_copy_key = format!("{prefix}/part_copy");
+    let upload_id = client
+        .create_multipart_upload(&bucket, &part_copy_key, &CreateMultipartUploadParams::new())
+        .await
+        .expect("create_multipart_upload should succeed")
+        .upload_id;
+    let part = client
+        .upload_part_copy(&bucket, &part_copy_key, &upload_id, 1, &UploadPartCopyParams::new(key))
+        .await
+        .expect("upload_part_copy should succeed")
+        .into_completed_part(1);
+    client
+        .complete_multipart_upload(
+            &bucket,
+            &part_copy_key,
+            &upload_id,
+            &[part],
+            &CompleteMultipartUploadParams::new(),
+        )
+        .await
+        .expect("complete_multipart_upload should succeed");
+
+    for copied_key in [copy_key, part_copy_key] {
+        let object = sdk_client
+            .get_object()
+            .bucket(&bucket)
+            .key(&copied_key)
+            .send()
+            .await
+            .expect("copied object should exist");
+        let copied_body = object.body.collect().await.unwrap().into_bytes();
+        assert_eq!(&copied_body[..], body);
+    }
+}
+
+#[tokio::test]
+async fn test_copy_object_if_none_match() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_copy_object_if_none_match");
+
+    let key = format!("{prefix}/hello");
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let copy_key = format!("{prefix}/hello2");
+    let params = CopyObjectParams::new().if_none_match(Some("*".to_owned()));
+    client
+        .copy_object(&bucket, &key, &bucket, &copy_key, &params)
+        .await
+        .expect("copy_object to a new key should succeed");
+    let result = client.copy_object(&bucket, &key, &bucket, &copy_key, &params).await;
+    assert!(matches!(
+        result,
+        Err(ObjectClientError::ServiceError(CopyObjectError::PreconditionFailed))
+    ));
+}
+
+#[tokio::test]
+async fn test_copy_object_replace_metadata() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_copy_object_replace_metadata");
+
+    let key = format!("{prefix}/hello");
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .metadata("source", "test")
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    // Objects can only be copied onto themselves when their metadata is replaced.
+    let client: S3CrtClient = get_test_client();
+    let object_metadata = [("mtime".to_owned(), "1700000000".to_owned())].into();
+    let params = CopyObjectParams::new().object_metadata(object_metadata);
+    client
+        .copy_object(&bucket, &key, &bucket, &key, &params)
+        .await
+        .expect("copy_object operation should succeed");
+
+    let head = client
+        .head_object(&bucket, &key, &HeadObjectParams::new())
+        .await
+        .expect("copied object should exist");
+    assert_eq!(
+        head.object_metadata,
+        [("mtime".to_owned(), "1700000000".to_owned())].into()
+    );
+}
+
+#[tokio::test]
+async fn test_copy_object_replace_metadata_keeps_headers() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_copy_object_replace_metadata_keeps_headers");
+
+    let key = format!("{prefix}/hello");
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let object_metadata = [("mtime".to_owned(), "1700000000".to_owned())].into();
+    let params = CopyObjectParams::new()
+        .object_metadata(object_metadata)
+        .content_type("text/plain".to_owned())
+        .content_encoding("identity".to_owned())
+        .cache_control("max-age=3600".to_owned())
+        .content_disposition("attachment".to_owned())
+        .server_side_encryption(Some("AES256".to_owned()));
+    client
+        .copy_object(&bucket, &key, &bucket, &key, &params)
+        .await
+        .expect("copy_object operation should succeed");
+
+    let head = client
+        .head_object(&bucket, &key, &HeadObjectParams::new())
+        .await
+        .expect("copied object should exist");
+    assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(head.content_encoding.as_deref(), Some("identity"));
+    assert_eq!(head.cache_control.as_deref(), Some("max-age=3600"));
+    assert_eq!(head.content_disposition.as_deref(), Some("attachment"));
+    assert_eq!(head.sse_type.as_deref(), Some("AES256"));
+}
+
 #[tokio::test]
 async fn test_copy_object_no_permission() {
     let (bucket, prefix) = get_test_bucket_and_prefix("test_copy_object_no_permission");
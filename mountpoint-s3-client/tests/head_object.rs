@@ -2,6 +2,7 @@
 
 pub mod common;
 
+use std::collections::HashMap;
 #[cfg(not(feature = "s3express_tests"))]
 use std::time::{Duration, Instant};
 
@@ -49,6 +50,57 @@ async fn test_head_object() {
     );
 }
 
+#[tokio::test]
+async fn test_head_object_user_metadata() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_head_object_user_metadata");
+
+    let key = format!("{prefix}hello");
+    let metadata = HashMap::from([("foo".to_string(), "bar".to_string())]);
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .set_metadata(Some(metadata.clone()))
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let result = client
+        .head_object(&bucket, &key, &HeadObjectParams::new())
+        .await
+        .expect("head_object failed");
+
+    assert_eq!(result.object_metadata, metadata);
+}
+
+#[tokio::test]
+async fn test_head_object_content_encoding() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_head_object_content_encoding");
+
+    let key = format!("{prefix}hello");
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .content_encoding("gzip")
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let result = client
+        .head_object(&bucket, &key, &HeadObjectParams::new())
+        .await
+        .expect("head_object failed");
+
+    assert_eq!(result.content_encoding.as_deref(), Some("gzip"));
+}
+
 #[test_case(ChecksumAlgorithm::Crc64Nvme)]
 #[test_case(ChecksumAlgorithm::Crc32)]
 #[test_case(ChecksumAlgorithm::Crc32C)]
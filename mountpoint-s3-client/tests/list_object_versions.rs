@@ -0,0 +1,95 @@
+#![cfg(feature = "s3_tests")]
+// Directory buckets do not support versioning.
+#![cfg(not(feature = "s3express_tests"))]
+
+pub mod common;
+
+use common::*;
+use futures::StreamExt;
+use mountpoint_s3_client::error::{ListObjectVersionsError, ObjectClientError};
+use mountpoint_s3_client::types::GetObjectParams;
+use mountpoint_s3_client::{ObjectClient, S3CrtClient};
+
+#[tokio::test]
+async fn test_list_object_versions() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_list_object_versions");
+    create_objects_for_test(&sdk_client, &bucket, &prefix, &["hello", "dir/a", "dir/b"]).await;
+
+    let client: S3CrtClient = get_test_client();
+
+    let result = client
+        .list_object_versions(&bucket, None, None, "/", 1000, &prefix)
+        .await
+        .expect("ListObjectVersions failed");
+
+    assert!(result.next_key_marker.is_none());
+    assert!(result.delete_markers.is_empty());
+    assert_eq!(result.versions.len(), 1);
+    let version = &result.versions[0];
+    assert_eq!(version.key, format!("{prefix}hello"));
+    assert!(version.is_latest);
+    assert_eq!(result.common_prefixes, [format!("{prefix}dir/")]);
+
+    // Buckets without versioning report the `null` version, which can be read like the others.
+    let params = GetObjectParams::new().version_id(Some(version.version_id.clone()));
+    let mut response = client
+        .get_object(&bucket, &version.key, &params)
+        .await
+        .expect("GetObject failed");
+    let mut size = 0;
+    while let Some(part) = response.next().await {
+        size += part.expect("GetObject failed").data.len();
+    }
+    assert_eq!(size as u64, version.size);
+}
+
+#[tokio::test]
+async fn test_list_object_versions_key_marker() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_list_object_versions_key_marker");
+    let keys: Vec<String> = (0..5).map(|i| format!("object_{i}")).collect();
+    create_objects_for_test(&sdk_client, &bucket, &prefix, &keys[..]).await;
+
+    let client: S3CrtClient = get_test_client();
+
+    let mut listed = Vec::new();
+    let (mut key_marker, mut version_id_marker) = (None, None);
+    loop {
+        let result = client
+            .list_object_versions(
+                &bucket,
+                key_marker.as_deref(),
+                version_id_marker.as_deref(),
+                "",
+                2,
+                &prefix,
+            )
+            .await
+            .expect("ListObjectVersions failed");
+        assert!(result.versions.len() <= 2);
+        listed.extend(result.versions.into_iter().map(|version| version.key));
+        if result.next_key_marker.is_none() {
+            break;
+        }
+        key_marker = result.next_key_marker;
+        version_id_marker = result.next_version_id_marker;
+    }
+    let expected: Vec<_> = keys.iter().map(|key| format!("{prefix}{key}")).collect();
+    assert_eq!(listed, expected);
+}
+
+#[tokio::test]
+async fn test_list_object_versions_404_bucket() {
+    let (_bucket, prefix) = get_test_bucket_and_prefix("test_list_object_versions_404_bucket");
+
+    let client: S3CrtClient = get_test_client();
+
+    let result = client
+        .list_object_versions("amzn-s3-demo-bucket", None, None, "/", 1000, &prefix)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ObjectClientError::ServiceError(ListObjectVersionsError::NoSuchBucket))
+    ));
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "s3_tests")]
+// Directory buckets don't support object tagging.
+#![cfg(not(feature = "s3express_tests"))]
+
+pub mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::Bytes;
+use common::*;
+use mountpoint_s3_client::error::{GetObjectTaggingError, ObjectClientError, PutObjectTaggingError};
+use mountpoint_s3_client::types::ObjectTags;
+use mountpoint_s3_client::{ObjectClient, S3CrtClient};
+
+#[tokio::test]
+async fn test_object_tagging() {
+    let sdk_client = get_test_sdk_client().await;
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_object_tagging");
+
+    let key = format!("{prefix}hello");
+    sdk_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .tagging("project=mountpoint")
+        .body(ByteStream::from(Bytes::from_static(b"hello world!")))
+        .send()
+        .await
+        .unwrap();
+
+    let client: S3CrtClient = get_test_client();
+    let result = client
+        .get_object_tagging(&bucket, &key)
+        .await
+        .expect("get_object_tagging should succeed");
+    assert_eq!(
+        result.tags,
+        ObjectTags::from([("project".to_owned(), "mountpoint".to_owned())])
+    );
+
+    let tags = ObjectTags::from([
+        ("project".to_owned(), "mountpoint".to_owned()),
+        ("lifecycle".to_owned(), "archive & delete".to_owned()),
+    ]);
+    client
+        .put_object_tagging(&bucket, &key, &tags)
+        .await
+        .expect("put_object_tagging should succeed");
+    let result = client.get_object_tagging(&bucket, &key).await.unwrap();
+    assert_eq!(result.tags, tags);
+}
+
+#[tokio::test]
+async fn test_object_tagging_no_such_key() {
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_object_tagging_no_such_key");
+    let key = format!("{prefix}nonexistent");
+
+    let client: S3CrtClient = get_test_client();
+    let result = client.get_object_tagging(&bucket, &key).await;
+    assert!(matches!(
+        result,
+        Err(ObjectClientError::ServiceError(GetObjectTaggingError::NoSuchKey))
+    ));
+    let result = client.put_object_tagging(&bucket, &key, &ObjectTags::new()).await;
+    assert!(matches!(
+        result,
+        Err(ObjectClientError::ServiceError(PutObjectTaggingError::NoSuchKey))
+    ));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "s3_tests")]
+
+pub mod common;
+
+use std::time::Duration;
+
+use common::*;
+use mountpoint_s3_client::error::{ObjectClientError, PresignGetObjectError};
+use mountpoint_s3_client::types::PresignGetObjectParams;
+use mountpoint_s3_client::{ObjectClient, S3CrtClient};
+
+#[tokio::test]
+async fn test_presign_get_object() {
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_presign_get_object");
+    let key = format!("{prefix}hello");
+
+    // Pre-signing doesn't look up the object, so it doesn't need to exist.
+    let client: S3CrtClient = get_test_client();
+    let result = client
+        .presign_get_object(&bucket, &key, Duration::from_secs(900), &PresignGetObjectParams::new())
+        .await
+        .expect("presign_get_object should succeed");
+    let url = result.url;
+    assert!(url.starts_with("https://"), "{url}");
+    assert!(url.contains(&key), "{url}");
+    assert!(url.contains("X-Amz-Expires=900"), "{url}");
+    assert!(url.contains("X-Amz-Signature="), "{url}");
+
+    let params = PresignGetObjectParams::new().version_id(Some("version".to_owned()));
+    let result = client
+        .presign_get_object(&bucket, &key, Duration::from_secs(900), &params)
+        .await
+        .expect("presign_get_object should succeed");
+    assert!(result.url.contains("versionId=version"), "{}", result.url);
+}
+
+#[tokio::test]
+async fn test_presign_get_object_invalid_expiration() {
+    let (bucket, prefix) = get_test_bucket_and_prefix("test_presign_get_object_invalid_expiration");
+    let key = format!("{prefix}hello");
+
+    let client: S3CrtClient = get_test_client();
+    let result = client
+        .presign_get_object(
+            &bucket,
+            &key,
+            Duration::from_secs(8 * 24 * 60 * 60),
+            &Default::default(),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(ObjectClientError::ServiceError(
+            PresignGetObjectError::InvalidExpiration
+        ))
+    ));
+}
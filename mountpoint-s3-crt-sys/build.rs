@@ -29,6 +29,8 @@ const CRT_LIBRARIES: &[&str] = &[
 const CRT_HEADERS: &[&str] = &[
     "auth/credentials.h",
     "auth/aws_imds_client.h",
+    "auth/signable.h",
+    "auth/signing_result.h",
     "cal/hash.h",
     "checksums/crc.h",
     "common/atomics.h",
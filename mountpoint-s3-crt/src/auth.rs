@@ -10,6 +10,7 @@ use crate::common::allocator::Allocator;
 
 pub mod credentials;
 pub mod imds_client;
+pub mod signer;
 pub mod signing_config;
 
 static AUTH_LIBRARY_INIT: Once = Once::new();
@@ -0,0 +1,117 @@
+//! Signing HTTP requests to AWS APIs outside of the S3 client
+
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use mountpoint_s3_crt_sys::{
+    aws_allocator, aws_apply_signing_result_to_http_request, aws_sign_request_aws, aws_signable, aws_signable_destroy,
+    aws_signable_new_http_request, aws_signing_config_base, aws_signing_result,
+};
+
+use crate::CrtError as _;
+use crate::auth::auth_library_init;
+use crate::auth::signing_config::SigningConfig;
+use crate::common::allocator::Allocator;
+use crate::common::error::Error;
+use crate::http::request_response::Message;
+
+/// Pre-sign an HTTP request with the given [SigningConfig], so that it can be made by anyone without credentials
+/// until the given expiration. The signature is added to the query string of the request, which is given to the
+/// callback once signed. The credentials are fetched from the credentials provider of the config, so the callback
+/// may be invoked on another thread.
+pub fn presign_request<F>(
+    allocator: &Allocator,
+    request: Message<'static>,
+    mut signing_config: SigningConfig,
+    expiration: Duration,
+    callback: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(Result<Message<'static>, Error>) + Send + 'static,
+{
+    auth_library_init(allocator);
+    signing_config.inner_mut().presign(expiration);
+
+    // SAFETY: `request.inner` is a valid `aws_http_message`, which the signable only points to. The request is kept
+    // alive, along with the signable, until the signing completes.
+    let signable = unsafe { aws_signable_new_http_request(allocator.inner.as_ptr(), request.inner.as_ptr()) };
+    // SAFETY: no other CRT code ran since `aws_signable_new_http_request` returned.
+    let signable = unsafe { signable.ok_or_last_error()? };
+    let base_config = signing_config.to_inner_ptr() as *const aws_signing_config_base;
+    let context = Box::new(PresignContext {
+        allocator: allocator.inner,
+        request,
+        signable,
+        _signing_config: signing_config,
+        callback: Box::new(callback),
+    });
+    let context = Box::into_raw(context);
+
+    // SAFETY: the signable and the signing config are valid, and held by `context`, which is leaked by
+    // [Box::into_raw] and so lives until the `presign_request_complete` function is invoked.
+    let result = unsafe {
+        aws_sign_request_aws(
+            allocator.inner.as_ptr(),
+            signable.as_ptr(),
+            base_config,
+            Some(presign_request_complete),
+            context as *mut libc::c_void,
+        )
+        .ok_or_last_error()
+    };
+    if let Err(error) = result {
+        // SAFETY: the callback is not invoked when the signing fails to start, so `context` is still valid and owned
+        // here.
+        let context = unsafe { Box::from_raw(context) };
+        // SAFETY: the signable was created above and is not used anymore.
+        unsafe { aws_signable_destroy(context.signable.as_ptr()) };
+        return Err(error);
+    }
+    Ok(())
+}
+
+type OnPresigned = Box<dyn FnOnce(Result<Message<'static>, Error>) + Send>;
+
+/// The state of a signing, held until it completes.
+struct PresignContext {
+    allocator: NonNull<aws_allocator>,
+    request: Message<'static>,
+    signable: NonNull<aws_signable>,
+    /// The signing config must outlive the signing, since the CRT keeps pointers to it.
+    _signing_config: SigningConfig,
+    callback: OnPresigned,
+}
+
+/// Rust binding for CRT's callback function `aws_signing_complete_fn`.
+unsafe extern "C" fn presign_request_complete(
+    signing_result: *mut aws_signing_result,
+    error_code: i32,
+    user_data: *mut libc::c_void,
+) {
+    // SAFETY: `user_data` is a raw pointer to a `Box<PresignContext>` created and leaked at signing time. This
+    // function will be executed at most once, so the Box is still valid right now.
+    let context = unsafe { Box::from_raw(user_data as *mut PresignContext) };
+    let PresignContext {
+        allocator,
+        request,
+        signable,
+        _signing_config,
+        callback,
+    } = *context;
+
+    let result = if error_code != 0 {
+        Err(error_code.into())
+    } else {
+        // SAFETY: the CRT guarantees `signing_result` is valid if the signing succeeded, and `request` is a valid
+        // `aws_http_message` the result is copied into.
+        unsafe {
+            aws_apply_signing_result_to_http_request(request.inner.as_ptr(), allocator.as_ptr(), signing_result)
+                .ok_or_last_error()
+        }
+        .map(|()| request)
+    };
+    // SAFETY: the signable was created at signing time and is not used by the CRT anymore.
+    unsafe { aws_signable_destroy(signable.as_ptr()) };
+
+    callback(result)
+}
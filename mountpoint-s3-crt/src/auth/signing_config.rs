@@ -2,11 +2,15 @@
 
 use crate::ToAwsByteCursor;
 use crate::auth::credentials::CredentialsProvider;
-use mountpoint_s3_crt_sys::{aws_s3_init_default_signing_config, aws_signing_algorithm, aws_signing_config_aws};
+use mountpoint_s3_crt_sys::{
+    aws_date_time_init_now, aws_s3_init_default_signing_config, aws_signature_type, aws_signed_body_header_type,
+    aws_signing_algorithm, aws_signing_config_aws, g_aws_signed_body_value_unsigned_payload,
+};
 use std::ffi::OsString;
 use std::fmt::Debug;
 use std::marker::PhantomPinned;
 use std::pin::Pin;
+use std::time::Duration;
 
 pub(crate) struct SigningConfigInner {
     /// The raw `aws_signing_config` for this config
@@ -74,6 +78,18 @@ impl SigningConfigInner {
     pub fn algorithm(&mut self, algorithm: SigningAlgorithm) {
         self.inner.algorithm = algorithm.into();
     }
+
+    /// Sign in the query parameters of the request rather than in its headers, without signing its payload, so that
+    /// the request can be made by anyone without credentials until it expires. The signature is dated from now.
+    pub fn presign(&mut self, expiration: Duration) {
+        self.inner.signature_type = aws_signature_type::AWS_ST_HTTP_REQUEST_QUERY_PARAMS;
+        self.inner.expiration_in_seconds = expiration.as_secs();
+        // SAFETY: `g_aws_signed_body_value_unsigned_payload` is a constant of the CRT, valid for the whole program.
+        self.inner.signed_body_value = unsafe { g_aws_signed_body_value_unsigned_payload };
+        self.inner.signed_body_header = aws_signed_body_header_type::AWS_SBHT_NONE;
+        // SAFETY: `self.inner.date` is a valid `aws_date_time` to initialize.
+        unsafe { aws_date_time_init_now(&mut self.inner.date) };
+    }
 }
 
 /// Wrap the SigningConfigInner struct into a Pin<Box<_>>, so that it cannot be moved.
@@ -85,6 +101,12 @@ impl SigningConfig {
     pub(crate) fn to_inner_ptr(&self) -> *const aws_signing_config_aws {
         &self.0.as_ref().get_ref().inner
     }
+
+    /// Get a mutable reference to the inner config, to change its options.
+    pub(crate) fn inner_mut(&mut self) -> &mut SigningConfigInner {
+        // SAFETY: the options of the config are changed in place, so it is not moved.
+        unsafe { self.0.as_mut().get_unchecked_mut() }
+    }
 }
 
 /// The version of the AWS signing process.
@@ -357,6 +357,22 @@ impl<'a> Message<'a> {
         unsafe { aws_http_message_set_request_path(self.inner.as_ptr(), path.as_aws_byte_cursor()).ok_or_last_error() }
     }
 
+    /// Get the request path of this message, including its query string.
+    pub fn get_request_path(&self) -> Result<OsString, Error> {
+        // SAFETY: `self.inner` is a valid aws_http_message, and `aws_http_message_get_request_path` promises to
+        // initialize the output `struct aws_byte_cursor *out_path` on success.
+        let path = unsafe {
+            let mut path: MaybeUninit<aws_byte_cursor> = MaybeUninit::uninit();
+            aws_http_message_get_request_path(self.inner.as_ptr(), path.as_mut_ptr()).ok_or_last_error()?;
+            path.assume_init()
+        };
+
+        // SAFETY: `path` points to the path held by the message, and we immediately make a copy of it before the
+        // message can be modified.
+        let path = unsafe { OsStr::from_bytes(aws_byte_cursor_as_slice(&path)).to_owned() };
+        Ok(path)
+    }
+
     /// Set the request method for this message.
     pub fn set_request_method(&mut self, method: impl AsRef<OsStr>) -> Result<(), Error> {
         // SAFETY: `aws_http_message_set_request_method` makes a copy of `method`.
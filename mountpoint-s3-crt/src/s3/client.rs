@@ -492,6 +492,15 @@ impl<'a> MetaRequestOptions<'a> {
         self
     }
 
+    /// When set, cap the number of active connections of this request. Otherwise, the client shares its connections
+    /// between its requests.
+    pub fn max_active_connections_override(&mut self, max_active_connections_override: u32) -> &mut Self {
+        // SAFETY: we aren't moving out of the struct.
+        let options = unsafe { Pin::get_unchecked_mut(Pin::as_mut(&mut self.0)) };
+        options.inner.max_active_connections_override = max_active_connections_override;
+        self
+    }
+
     /// Set this to send request body data using the async [MetaRequest::write] function.
     /// This only works with [MetaRequestType::PutObject].
     pub fn send_using_async_writes(&mut self, send_using_async_writes: bool) -> &mut Self {
@@ -1656,6 +1665,18 @@ impl ChecksumConfig {
         }
     }
 
+    /// Create a [ChecksumConfig] enabling trailing checksums with the given algorithm in PUT requests. Returns `None`
+    /// for an [ChecksumAlgorithm::Unknown] algorithm.
+    pub fn trailing(checksum_algorithm: &ChecksumAlgorithm) -> Option<Self> {
+        Some(Self {
+            inner: aws_s3_checksum_config {
+                location: aws_s3_checksum_location::AWS_SCL_TRAILER,
+                checksum_algorithm: checksum_algorithm.to_aws_s3_checksum_algorithm()?,
+                ..Default::default()
+            },
+        })
+    }
+
     /// Create a [ChecksumConfig] enabling Crc32c trailing checksums only for upload review.
     pub fn upload_review_crc32c() -> Self {
         Self {
@@ -1706,6 +1727,17 @@ impl ChecksumAlgorithm {
             _ => unreachable!("unknown aws_s3_checksum_algorithm"),
         }
     }
+
+    fn to_aws_s3_checksum_algorithm(&self) -> Option<aws_s3_checksum_algorithm> {
+        match self {
+            ChecksumAlgorithm::Crc64nvme => Some(aws_s3_checksum_algorithm::AWS_SCA_CRC64NVME),
+            ChecksumAlgorithm::Crc32c => Some(aws_s3_checksum_algorithm::AWS_SCA_CRC32C),
+            ChecksumAlgorithm::Crc32 => Some(aws_s3_checksum_algorithm::AWS_SCA_CRC32),
+            ChecksumAlgorithm::Sha1 => Some(aws_s3_checksum_algorithm::AWS_SCA_SHA1),
+            ChecksumAlgorithm::Sha256 => Some(aws_s3_checksum_algorithm::AWS_SCA_SHA256),
+            ChecksumAlgorithm::Unknown(_) => None,
+        }
+    }
 }
 
 impl Display for ChecksumAlgorithm {
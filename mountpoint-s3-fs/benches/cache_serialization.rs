@@ -38,6 +38,15 @@ fn cache_read_benchmark(group: &mut BenchmarkGroup<'_, WallTime>, dir_path: &Pat
         cache_directory: dir_path.to_path_buf(),
         block_size: BLOCK_SIZE,
         limit: mountpoint_s3_fs::data_cache::CacheLimit::Unbounded,
+        eviction_policy: Default::default(),
+        shared: false,
+        persistent: false,
+        admission_policy: Default::default(),
+        pinning: None,
+        encryption_key: None,
+        compression_level: None,
+        io_backend: Default::default(),
+        account: None,
     };
     let pool = PagedPool::new_with_candidate_sizes([BLOCK_SIZE as usize]);
     let cache = DiskDataCache::new(config, pool);
@@ -12,7 +12,7 @@ use mountpoint_s3_fs::fuse::session::FuseSession;
 use mountpoint_s3_fs::memory::PagedPool;
 use mountpoint_s3_fs::prefetch::Prefetcher;
 use mountpoint_s3_fs::s3::{Bucket, S3Path};
-use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig, Superblock, SuperblockConfig};
+use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig};
 use tempfile::tempdir;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt::Subscriber;
@@ -168,15 +168,15 @@ fn mount_file_system(
         mountpoint.to_str().unwrap()
     );
     let prefetcher_builder = Prefetcher::default_builder(client.clone());
-    let superblock = Superblock::new(
-        client.clone(),
+    let fs = S3Filesystem::new(
+        client,
+        prefetcher_builder,
+        pool,
+        runtime,
         s3_path,
-        SuperblockConfig {
-            cache_config: filesystem_config.cache_config.clone(),
-            s3_personality: filesystem_config.s3_personality,
-        },
+        Default::default(),
+        filesystem_config,
     );
-    let fs = S3Filesystem::new(client, prefetcher_builder, pool, runtime, superblock, filesystem_config);
 
     let options = FuseOptions {
         read_only: true,
@@ -99,6 +99,7 @@ impl ConfigOptions {
             allow_root: self.allow_root,
             auto_unmount: self.auto_unmount.unwrap_or(false),
             clone_fd: false,
+            max_read: None,
         };
         FuseSessionConfig::new(mount_point, fuse_options, self.max_threads.unwrap_or(16))
     }
@@ -177,6 +178,15 @@ impl ConfigOptions {
             cache_directory: disk_cache.path.clone().into(),
             block_size: 1024 * 1024, // 1 MiB block size - default
             limit: cache_limit,
+            eviction_policy: Default::default(),
+            shared: false,
+            persistent: false,
+            admission_policy: Default::default(),
+            pinning: None,
+            encryption_key: None,
+            compression_level: None,
+            io_backend: Default::default(),
+            account: None,
         };
         Ok(Some(cache_config))
     }
@@ -193,7 +203,13 @@ impl ConfigOptions {
         }
         Ok(DataCacheConfig {
             disk_cache_config,
+            memory_cache_config: None,
+            scrub_interval: None,
             express_cache_config: None,
+            peer_cache_config: None,
+            prefix_metrics_config: None,
+            write_through_config: None,
+            pin_xattr: false,
         })
     }
 
@@ -320,7 +336,7 @@ fn mount_filesystem(
 
     // Create and run the FUSE session
     let mut fuse_session = mp_config
-        .create_fuse_session(metablock, client, runtime, pool)
+        .create_fuse_session_with_metablock(metablock, client, runtime, pool)
         .context("Failed to create FUSE session")?;
 
     if let Some(managed_cache_dir) = managed_cache_dir {
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use std::{fmt::Debug, future::Future};
 
 use async_channel::{Receiver, Sender};
@@ -94,6 +95,16 @@ impl<T, E> Drop for RemoteResult<T, E> {
     }
 }
 
+/// Wait for `duration` on a thread of its own, without blocking the executor.
+pub(crate) async fn sleep(duration: Duration) {
+    let (sender, receiver) = async_channel::bounded(1);
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let _ = sender.send_blocking(());
+    });
+    let _ = receiver.recv().await;
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -285,7 +285,7 @@ pub enum IntegrityError {
 /// A Crc32c checksum which can be (de)serialized to a base64 encoded string.
 ///
 /// TODO: there should be a single Crc32c type implementing serialization.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Crc32cBase64(Crc32c);
 
 impl Crc32cBase64 {
@@ -1,15 +1,24 @@
 use anyhow::Context as _;
 use futures::executor::block_on;
+use futures::task::SpawnExt as _;
 use mountpoint_s3_client::ObjectClient;
 
-use crate::data_cache::{DataCacheConfig, DiskDataCache, ExpressDataCache, MultilevelDataCache};
+use crate::data_cache::{
+    DataCache, DataCacheConfig, DiskDataCache, ExpressDataCache, MultilevelDataCache, PeerDataCache,
+    PrefixMetricsDataCache, TieredDataCache,
+};
+use crate::event_notifications::{self, EventNotificationConfig};
+use crate::fs::PinningCache;
 use crate::fuse::config::FuseSessionConfig;
 use crate::fuse::session::FuseSession;
-use crate::fuse::{ErrorLogger, S3FuseFilesystem};
+use crate::fuse::{ErrorLogger, S3FuseFilesystem, invalidate_kernel_caches};
 use crate::memory::PagedPool;
 use crate::metablock::Metablock;
 use crate::prefetch::{Prefetcher, PrefetcherBuilder};
+use crate::s3::S3Path;
+use crate::superblock::SuperblockConfig;
 use crate::sync::Arc;
+use crate::upload::WriteThroughCache;
 use crate::{Runtime, S3Filesystem, S3FilesystemConfig};
 
 /// Configuration for a Mountpoint session
@@ -19,6 +28,7 @@ pub struct MountpointConfig {
     data_cache_config: DataCacheConfig,
     filesystem_config: S3FilesystemConfig,
     error_logger: Option<Box<dyn ErrorLogger + Send + Sync>>,
+    event_notification_config: Option<EventNotificationConfig>,
 }
 
 impl MountpointConfig {
@@ -32,6 +42,7 @@ impl MountpointConfig {
             data_cache_config,
             filesystem_config,
             error_logger: None,
+            event_notification_config: None,
         }
     }
 
@@ -41,8 +52,44 @@ impl MountpointConfig {
         self
     }
 
-    /// Create a new FUSE session
+    /// Invalidate cached metadata from the S3 event notifications of the bucket, see [crate::event_notifications].
+    pub fn event_notifications(mut self, config: EventNotificationConfig) -> Self {
+        self.event_notification_config = Some(config);
+        self
+    }
+
+    /// Create a new FUSE session serving the objects under `s3_path`, see [S3Filesystem::new]
     pub fn create_fuse_session<Client>(
+        self,
+        s3_path: S3Path,
+        superblock_config: SuperblockConfig,
+        client: Client,
+        runtime: Runtime,
+        memory_pool: PagedPool,
+    ) -> anyhow::Result<FuseSession>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        self.create_fuse_session_with(
+            client,
+            runtime,
+            memory_pool,
+            |client, prefetcher_builder, pool, runtime, config| {
+                S3Filesystem::new(
+                    client,
+                    prefetcher_builder,
+                    pool,
+                    runtime,
+                    s3_path,
+                    superblock_config,
+                    config,
+                )
+            },
+        )
+    }
+
+    /// Create a new FUSE session serving the inodes of another [Metablock], see [S3Filesystem::with_metablock]
+    pub fn create_fuse_session_with_metablock<Client>(
         self,
         metablock: impl Metablock + 'static,
         client: Client,
@@ -52,37 +99,144 @@ impl MountpointConfig {
     where
         Client: ObjectClient + Clone + Send + Sync + 'static,
     {
-        let prefetcher_builder =
-            create_prefetcher_builder(self.data_cache_config, &client, &runtime, memory_pool.clone())?;
-        tracing::trace!(filesystem_config=?self.filesystem_config, "creating file system");
-        let fs = S3Filesystem::new(
+        self.create_fuse_session_with(
+            client,
+            runtime,
+            memory_pool,
+            |client, prefetcher_builder, pool, runtime, config| {
+                S3Filesystem::with_metablock(client, prefetcher_builder, pool, runtime, metablock, config)
+            },
+        )
+    }
+
+    fn create_fuse_session_with<Client>(
+        self,
+        client: Client,
+        runtime: Runtime,
+        memory_pool: PagedPool,
+        create_filesystem: impl FnOnce(
+            Client,
+            PrefetcherBuilder<Client>,
+            PagedPool,
+            Runtime,
+            S3FilesystemConfig,
+        ) -> S3Filesystem<Client>,
+    ) -> anyhow::Result<FuseSession>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        let (data_cache, write_through_cache, pinning_cache) =
+            create_data_caches(self.data_cache_config, &client, &runtime, memory_pool.clone())?;
+        let prefetcher_builder = match data_cache {
+            Some(cache) => Prefetcher::caching_builder(cache, client.clone()),
+            None => Prefetcher::default_builder(client.clone()),
+        };
+        let mut filesystem_config = self.filesystem_config;
+        if write_through_cache.is_some() {
+            filesystem_config.write_through_cache = write_through_cache;
+        }
+        if pinning_cache.is_some() {
+            filesystem_config.pinning_cache = pinning_cache;
+        }
+        tracing::trace!(?filesystem_config, "creating file system");
+        let fs = create_filesystem(
             client,
             prefetcher_builder,
             memory_pool,
-            runtime,
-            metablock,
-            self.filesystem_config,
+            runtime.clone(),
+            filesystem_config,
         );
+        let metablock = fs.metablock().clone();
+        let kernel_invalidations = fs.kernel_invalidations();
 
         let fuse_fs = S3FuseFilesystem::new(fs, self.error_logger);
-        let session = FuseSession::new(fuse_fs, self.fuse_session_config)?;
+        let mut session = FuseSession::new(fuse_fs, self.fuse_session_config)?;
+        let notifier = session.notifier();
+        let invalidator = runtime
+            .spawn_with_handle(async move {
+                while let Ok(invalidations) = kernel_invalidations.recv().await {
+                    invalidate_kernel_caches(&notifier, &invalidations);
+                }
+            })
+            .context("failed to start invalidating kernel caches")?;
+        session.run_on_close(Box::new(move || drop(invalidator)));
+        if let Some(config) = self.event_notification_config {
+            let notifier = session.notifier();
+            let listener = event_notifications::spawn_listener(config, metablock, &runtime, move |invalidations| {
+                invalidate_kernel_caches(&notifier, invalidations)
+            })
+            .context("failed to start consuming event notifications")?;
+            session.run_on_close(Box::new(move || drop(listener)));
+        }
         ctrlc::set_handler(session.shutdown_fn()).context("failed to set interrupt handler")?;
         Ok(session)
     }
 }
 
-fn create_prefetcher_builder<Client>(
+type SharedDataCache = Arc<dyn DataCache + Send + Sync>;
+
+/// Create the data cache described by `data_cache_config`, or `None` if no cache is configured.
+pub(crate) fn create_data_cache<Client>(
     data_cache_config: DataCacheConfig,
     client: &Client,
     runtime: &Runtime,
     memory_pool: PagedPool,
-) -> anyhow::Result<PrefetcherBuilder<Client>>
+) -> anyhow::Result<Option<SharedDataCache>>
 where
     Client: ObjectClient + Clone + Send + Sync + 'static,
 {
-    let disk_cache = data_cache_config
+    let (cache, _, _) = create_data_caches(data_cache_config, client, runtime, memory_pool)?;
+    Ok(cache)
+}
+
+/// Create the data cache described by `data_cache_config`, along with the cache the objects written through the
+/// mount are put to and the cache files are pinned in, if configured.
+fn create_data_caches<Client>(
+    data_cache_config: DataCacheConfig,
+    client: &Client,
+    runtime: &Runtime,
+    memory_pool: PagedPool,
+) -> anyhow::Result<(Option<SharedDataCache>, Option<WriteThroughCache>, Option<PinningCache>)>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    let disk_data_cache = data_cache_config
         .disk_cache_config
-        .map(|config| DiskDataCache::new(config, memory_pool));
+        .map(|config| Arc::new(DiskDataCache::new(config, memory_pool)));
+    let pinning_cache = match (&disk_data_cache, data_cache_config.pin_xattr) {
+        (Some(disk_cache), true) => Some(PinningCache::new(disk_cache.clone())),
+        (None, true) => {
+            tracing::warn!("pinning files in the cache requires a local disk cache");
+            None
+        }
+        (_, false) => None,
+    };
+    let disk_cache = disk_data_cache.map(|disk_cache| {
+        if let Some(interval) = data_cache_config.scrub_interval {
+            DiskDataCache::spawn_scrubber(&disk_cache, interval);
+        }
+        let cache: Arc<dyn DataCache + Send + Sync> = match data_cache_config.memory_cache_config {
+            Some(memory_config) => Arc::new(TieredDataCache::new(disk_cache, memory_config)),
+            None => disk_cache,
+        };
+        cache
+    });
+    let local_cache = disk_cache.clone();
+    let disk_cache = match (disk_cache, data_cache_config.peer_cache_config) {
+        (Some(disk_cache), Some(config)) => {
+            let address = config.address;
+            let peer_cache = PeerDataCache::new(config, disk_cache.clone())
+                .with_context(|| format!("failed to serve the local cache to the peers on {address}"))?;
+            let cache: SharedDataCache = Arc::new(MultilevelDataCache::new(disk_cache, peer_cache, runtime.clone()));
+            Some(cache)
+        }
+        (disk_cache, peer_cache_config) => {
+            if peer_cache_config.is_some() {
+                tracing::warn!("sharing the cache with peers requires a local disk cache");
+            }
+            disk_cache
+        }
+    };
     let express_cache = match data_cache_config.express_cache_config {
         None => None,
         Some(config) => {
@@ -93,15 +247,27 @@ where
             Some(express_cache)
         }
     };
-    let client = client.clone();
-    let builder = match (disk_cache, express_cache) {
-        (None, Some(express_cache)) => Prefetcher::caching_builder(express_cache, client),
-        (Some(disk_cache), None) => Prefetcher::caching_builder(disk_cache, client),
+    let cache: Arc<dyn DataCache + Send + Sync> = match (disk_cache, express_cache) {
+        (None, Some(express_cache)) => Arc::new(express_cache),
+        (Some(disk_cache), None) => disk_cache,
         (Some(disk_cache), Some(express_cache)) => {
-            let cache = MultilevelDataCache::new(Arc::new(disk_cache), express_cache, runtime.clone());
-            Prefetcher::caching_builder(cache, client)
+            Arc::new(MultilevelDataCache::new(disk_cache, express_cache, runtime.clone()))
+        }
+        (None, None) => return Ok((None, None, None)),
+    };
+    let cache = match data_cache_config.prefix_metrics_config {
+        Some(prefix_metrics_config) => Arc::new(PrefixMetricsDataCache::new(cache, prefix_metrics_config)),
+        None => cache,
+    };
+    let write_through_cache = match data_cache_config.write_through_config {
+        Some(config) if config.shared => Some(WriteThroughCache::new(cache.clone(), config.max_object_size)),
+        Some(config) => {
+            if local_cache.is_none() {
+                tracing::warn!("write-through caching to the local disk cache requires a local disk cache");
+            }
+            local_cache.map(|cache| WriteThroughCache::new(cache, config.max_object_size))
         }
-        _ => Prefetcher::default_builder(client),
+        None => None,
     };
-    Ok(builder)
+    Ok((Some(cache), write_through_cache, pinning_cache))
 }
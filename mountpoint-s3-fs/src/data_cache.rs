@@ -5,24 +5,37 @@
 //! Ultimately, this means reduced cost in terms of S3 billing as well as compute time.
 
 mod cache_directory;
+mod cache_warmer;
 mod disk_data_cache;
 mod express_data_cache;
 mod in_memory_data_cache;
 mod multilevel_cache;
+mod peer_data_cache;
+mod prefix_metrics;
+mod tiered_cache;
+
+use std::time::Duration;
 
 use async_trait::async_trait;
 use thiserror::Error;
 
 pub use crate::checksums::ChecksummedBytes;
 pub use crate::data_cache::cache_directory::ManagedCacheDir;
+pub use crate::data_cache::cache_warmer::{CacheWarmer, CacheWarmerConfig, CacheWarmingStats, WarmTarget};
 pub use crate::data_cache::disk_data_cache::{
-    CacheLimit, DEFAULT_CACHE_MIN_AVAILABLE_RATIO, DiskDataCache, DiskDataCacheConfig,
+    AdmissionPolicy, CacheAccount, CacheEncryptionKey, CacheEncryptionKeyError, CacheLimit, CachedBlockInfo,
+    DEFAULT_CACHE_COMPRESSION_LEVEL, DEFAULT_CACHE_MIN_AVAILABLE_RATIO, DiskDataCache, DiskDataCacheConfig,
+    DiskIoBackend, EvictionPolicy, PinningConfig, ScrubStats,
 };
 pub use crate::data_cache::express_data_cache::{ExpressDataCache, ExpressDataCacheConfig, build_prefix, get_s3_key};
 pub use crate::data_cache::in_memory_data_cache::InMemoryDataCache;
 pub use crate::data_cache::multilevel_cache::MultilevelDataCache;
+pub use crate::data_cache::peer_data_cache::{DEFAULT_PEER_TIMEOUT, PeerCacheConfig, PeerDataCache};
+pub use crate::data_cache::prefix_metrics::{PrefixMetricsConfig, PrefixMetricsDataCache};
+pub use crate::data_cache::tiered_cache::{MemoryCacheConfig, TieredDataCache};
 
 use crate::object::ObjectId;
+use crate::sync::Arc;
 
 /// Indexes blocks within a given object.
 pub type BlockIndex = u64;
@@ -77,6 +90,38 @@ pub trait DataCache {
     fn block_size(&self) -> u64;
 }
 
+#[async_trait]
+impl<Cache: DataCache + Send + Sync + ?Sized> DataCache for Arc<Cache> {
+    async fn get_block(
+        &self,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        object_size: usize,
+    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+        self.as_ref()
+            .get_block(cache_key, block_idx, block_offset, object_size)
+            .await
+    }
+
+    async fn put_block(
+        &self,
+        cache_key: ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        bytes: ChecksummedBytes,
+        object_size: usize,
+    ) -> DataCacheResult<()> {
+        self.as_ref()
+            .put_block(cache_key, block_idx, block_offset, bytes, object_size)
+            .await
+    }
+
+    fn block_size(&self) -> u64 {
+        self.as_ref().block_size()
+    }
+}
+
 /// Configuration for the data cache.
 ///
 /// Can be configured to enable a local disk cache, a shared cache
@@ -84,5 +129,29 @@ pub trait DataCache {
 #[derive(Debug, Default)]
 pub struct DataCacheConfig {
     pub disk_cache_config: Option<DiskDataCacheConfig>,
+    /// Memory tier in front of the local disk cache. Ignored without a local disk cache.
+    pub memory_cache_config: Option<MemoryCacheConfig>,
+    /// Interval at which the blocks of the local disk cache are verified in the background, see
+    /// [DiskDataCache::spawn_scrubber]. Ignored without a local disk cache.
+    pub scrub_interval: Option<Duration>,
     pub express_cache_config: Option<ExpressDataCacheConfig>,
+    /// Cache tier made of the local disk caches of other mounts, in front of the shared cache. Ignored without a local
+    /// disk cache.
+    pub peer_cache_config: Option<PeerCacheConfig>,
+    /// Report the hits and misses of the cache for each prefix of the object keys.
+    pub prefix_metrics_config: Option<PrefixMetricsConfig>,
+    /// Put the objects written through the mount to the cache.
+    pub write_through_config: Option<WriteThroughConfig>,
+    /// Let applications pin files in the local disk cache with the [crate::fs::PIN_XATTR] extended attribute. Ignored
+    /// without a local disk cache.
+    pub pin_xattr: bool,
+}
+
+/// Configuration for putting the objects written through the mount to the data cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteThroughConfig {
+    /// Maximum size of the objects put to the cache. The data written is held in memory until the upload completes.
+    pub max_object_size: usize,
+    /// Whether to also put the objects to the shared cache, rather than to the local disk cache only.
+    pub shared: bool,
 }
@@ -5,17 +5,24 @@
 //! to mitigate any impact from the user providing a directory that already contains data.
 //! Using a new sub-directory minimizes the interference with the existing directory structure,
 //! and limits the risk from deleting or overwriting data to files written within this sub-directory.
+//!
+//! A shared cache directory may be used by several Mountpoint processes at the same time. Each of
+//! them holds a shared lock on a lock file in the sub-directory while using it, and the contents
+//! are only cleaned up by the first process to use it and by the last one to exit.
 
 use sha2::{Digest, Sha256};
 use std::ffi::OsStr;
-use std::fs;
+use std::fs::{self, File, OpenOptions, TryLockError};
 use std::io;
 use std::os::unix::ffi::OsStrExt as _;
-use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
+/// Name of the lock file held by the processes using a shared cache directory.
+const LOCK_FILE_NAME: &str = "mountpoint.lock";
+
 /// Cache directory that will be created with appropriate permissions if it doesn't exist,
 /// and - where configured - emptied at creation and when dropped.
 ///
@@ -28,6 +35,8 @@ pub struct ManagedCacheDir {
     managed_cache_path: PathBuf,
     /// Indicates if directory should be removed before construction and when dropped.
     should_cleanup: bool,
+    /// Lock file of a shared directory, on which a shared lock is held while the directory is used.
+    lock_file: Option<File>,
 }
 
 #[derive(Debug, Error)]
@@ -36,9 +45,21 @@ pub enum ManagedCacheDirError {
     CreationFailure(#[source] io::Error),
     #[error("cleanup of cache sub-directory failed due to IO error: {0}")]
     CleanupFailure(#[source] io::Error),
+    #[error("locking of shared cache sub-directory failed due to IO error: {0}")]
+    LockError(#[source] io::Error),
 }
 
 impl ManagedCacheDir {
+    /// The directory managed by a [ManagedCacheDir] created inside the provided parent path with the given cache key,
+    /// without creating it.
+    pub fn managed_path(parent_path: impl AsRef<Path>, cache_key: Option<&OsStr>) -> PathBuf {
+        let mountpoint_cache_path = parent_path.as_ref().join("mountpoint-cache");
+        match cache_key {
+            None => mountpoint_cache_path,
+            Some(cache_key) => mountpoint_cache_path.join(hash_cache_key(cache_key.as_bytes())),
+        }
+    }
+
     /// Create a new directory inside the provided parent path.
     ///
     /// If `should_cleanup` is `true` and `<parent_path>/mountpoint-cache` already exists,
@@ -54,14 +75,12 @@ impl ManagedCacheDir {
         should_cleanup: bool,
     ) -> Result<Self, ManagedCacheDirError> {
         let mountpoint_cache_path = parent_path.as_ref().join("mountpoint-cache");
-        let managed_cache_path = match cache_key {
-            None => mountpoint_cache_path.clone(),
-            Some(cache_key) => mountpoint_cache_path.join(hash_cache_key(cache_key.as_bytes())),
-        };
+        let managed_cache_path = Self::managed_path(parent_path, cache_key);
         let managed_cache_dir = Self {
             mountpoint_cache_path,
             managed_cache_path,
             should_cleanup,
+            lock_file: None,
         };
 
         if should_cleanup {
@@ -74,6 +93,92 @@ impl ManagedCacheDir {
         Ok(managed_cache_dir)
     }
 
+    /// Create a new directory inside the provided parent path, which other processes may be using
+    /// at the same time through another shared [ManagedCacheDir].
+    ///
+    /// A shared lock is held on `<parent_path>/mountpoint-cache/mountpoint.lock` until the
+    /// returned directory is dropped. If `should_cleanup` is `true`, the contents of
+    /// `<parent_path>/mountpoint-cache` are only deleted if no other process holds the lock, both
+    /// here and when dropped. The lock file itself is never deleted, so that processes starting
+    /// while the directory is cleaned up wait for the cleanup to complete.
+    pub fn new_shared_from_parent_with_cache_key(
+        parent_path: impl AsRef<Path>,
+        cache_key: Option<&OsStr>,
+        should_cleanup: bool,
+    ) -> Result<Self, ManagedCacheDirError> {
+        let mountpoint_cache_path = parent_path.as_ref().join("mountpoint-cache");
+        let managed_cache_path = Self::managed_path(parent_path, cache_key);
+        Self::create_dir(&mountpoint_cache_path)?;
+        let lock_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(mountpoint_cache_path.join(LOCK_FILE_NAME))
+            .map_err(ManagedCacheDirError::LockError)?;
+        let managed_cache_dir = Self {
+            mountpoint_cache_path,
+            managed_cache_path,
+            should_cleanup,
+            lock_file: Some(lock_file),
+        };
+
+        if should_cleanup && managed_cache_dir.try_lock_exclusive()? {
+            managed_cache_dir.remove_contents()?;
+        }
+        // Downgrades the exclusive lock if we hold it, or waits for another process to complete
+        // its cleanup otherwise.
+        managed_cache_dir
+            .lock_file()
+            .lock_shared()
+            .map_err(ManagedCacheDirError::LockError)?;
+        if cache_key.is_some() {
+            Self::create_dir(&managed_cache_dir.managed_cache_path)?;
+        }
+        Ok(managed_cache_dir)
+    }
+
+    fn lock_file(&self) -> &File {
+        self.lock_file.as_ref().expect("shared directories have a lock file")
+    }
+
+    /// Try to lock a shared directory exclusively, which only succeeds if no other process uses it.
+    fn try_lock_exclusive(&self) -> Result<bool, ManagedCacheDirError> {
+        match self.lock_file().try_lock() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(err)) => Err(ManagedCacheDirError::LockError(err)),
+        }
+    }
+
+    /// Remove the contents of a shared cache sub-directory, except for its lock file, and reset
+    /// its permissions.
+    fn remove_contents(&self) -> Result<(), ManagedCacheDirError> {
+        tracing::debug!(cache_subdirectory = ?self.mountpoint_cache_path, "removing the contents of the shared cache sub-directory");
+        let entries = fs::read_dir(&self.mountpoint_cache_path).map_err(ManagedCacheDirError::CleanupFailure)?;
+        for entry in entries {
+            let entry = entry.map_err(ManagedCacheDirError::CleanupFailure)?;
+            if entry.file_name() == LOCK_FILE_NAME {
+                continue;
+            }
+            let path = entry.path();
+            let result = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+                Ok(_) => fs::remove_file(&path),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result
+                && err.kind() != io::ErrorKind::NotFound
+            {
+                return Err(ManagedCacheDirError::CleanupFailure(err));
+            }
+        }
+        fs::set_permissions(&self.mountpoint_cache_path, fs::Permissions::from_mode(0o700))
+            .map_err(ManagedCacheDirError::CleanupFailure)?;
+        Ok(())
+    }
+
     /// Remove the cache sub-directory, along with its contents if any
     fn remove(&self) -> Result<(), ManagedCacheDirError> {
         tracing::debug!(cache_subdirectory = ?self.mountpoint_cache_path, "removing the cache sub-directory and any contents");
@@ -116,9 +221,22 @@ impl ManagedCacheDir {
 
 impl Drop for ManagedCacheDir {
     fn drop(&mut self) {
-        if self.should_cleanup
-            && let Err(err) = self.remove()
-        {
+        if !self.should_cleanup {
+            return;
+        }
+        let result = if self.lock_file.is_some() {
+            match self.try_lock_exclusive() {
+                Ok(true) => self.remove_contents(),
+                Ok(false) => {
+                    tracing::debug!(cache_subdirectory = ?self.mountpoint_cache_path, "shared cache sub-directory still in use, not cleaning it up");
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            }
+        } else {
+            self.remove()
+        };
+        if let Err(err) = result {
             tracing::error!(cache_subdirectory = ?self.mountpoint_cache_path, "failed to remove cache sub-directory: {err}");
         }
     }
@@ -134,7 +252,7 @@ fn hash_cache_key(cache_key: &[u8]) -> String {
 mod tests {
     use test_case::test_matrix;
 
-    use super::{ManagedCacheDir, hash_cache_key};
+    use super::{LOCK_FILE_NAME, ManagedCacheDir, hash_cache_key};
 
     use std::ffi::OsStr;
     use std::fs;
@@ -298,4 +416,47 @@ mod tests {
 
         temp_dir.close().unwrap();
     }
+
+    #[test_matrix([SHOULD_CLEANUP, SHOULD_NOT_CLEANUP])]
+    fn test_shared(should_cleanup: bool) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let expected_path = temp_dir.path().join("mountpoint-cache");
+        fs::create_dir(&expected_path).unwrap();
+        fs::File::create(expected_path.join("stale.txt")).unwrap();
+
+        let first_dir = ManagedCacheDir::new_shared_from_parent_with_cache_key(temp_dir.path(), None, should_cleanup)
+            .expect("creating managed dir should succeed");
+        assert_eq!(
+            expected_path.join("stale.txt").exists(),
+            !should_cleanup,
+            "the first process cleans up the directory"
+        );
+        fs::File::create(expected_path.join("file.txt")).unwrap();
+
+        let second_dir = ManagedCacheDir::new_shared_from_parent_with_cache_key(temp_dir.path(), None, should_cleanup)
+            .expect("creating managed dir should succeed");
+        assert!(
+            expected_path.join("file.txt").exists(),
+            "the directory is not cleaned up while in use"
+        );
+
+        drop(first_dir);
+        assert!(
+            expected_path.join("file.txt").exists(),
+            "the directory is not cleaned up while in use"
+        );
+
+        drop(second_dir);
+        assert_eq!(
+            expected_path.join("file.txt").exists(),
+            !should_cleanup,
+            "the last process cleans up the directory"
+        );
+        assert!(
+            expected_path.join(LOCK_FILE_NAME).exists(),
+            "the lock file is never removed"
+        );
+
+        temp_dir.close().unwrap();
+    }
 }
@@ -0,0 +1,406 @@
+//! Pre-populating a data cache with the content of objects, before a workload reads them.
+//!
+//! A [CacheWarmer] reads each object to warm with a [Prefetcher], and writes its blocks to the
+//! cache through [DataCache::put_block], as the caching prefetcher of a mount would. Workloads
+//! reading the objects for the first time through a mount using the same cache then get cache
+//! hits rather than waiting on S3.
+
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+use anyhow::{Context as _, anyhow};
+use futures::StreamExt as _;
+use mountpoint_s3_client::ObjectClient;
+use mountpoint_s3_client::types::{ETag, HeadObjectParams};
+use tracing::{debug, warn};
+
+use crate::Runtime;
+use crate::config::create_data_cache;
+use crate::data_cache::{DataCache, DataCacheConfig};
+use crate::mem_limiter::MemoryLimiter;
+use crate::memory::PagedPool;
+use crate::object::ObjectId;
+use crate::prefetch::{HandleId, Prefetcher, PrefetcherConfig};
+use crate::s3::S3Path;
+use crate::sync::Arc;
+
+/// Maximum number of keys to request in each ListObjectsV2 request when listing a prefix.
+const LIST_MAX_KEYS: usize = 1000;
+
+/// Objects to warm, relative to the prefix of a [CacheWarmer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarmTarget {
+    /// A single object.
+    Object(String),
+    /// All the objects whose keys start with the prefix, which ends with `/`.
+    Prefix(String),
+}
+
+impl WarmTarget {
+    /// Parse a manifest of objects to warm, with one key per line. Lines ending with `/` are
+    /// prefixes, and empty lines or lines starting with `#` are ignored.
+    pub fn parse_manifest(reader: impl BufRead) -> io::Result<Vec<WarmTarget>> {
+        let mut targets = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let target = if line.ends_with('/') {
+                WarmTarget::Prefix(line.to_owned())
+            } else {
+                WarmTarget::Object(line.to_owned())
+            };
+            targets.push(target);
+        }
+        Ok(targets)
+    }
+}
+
+/// Configuration for a [CacheWarmer].
+#[derive(Debug, Clone)]
+pub struct CacheWarmerConfig {
+    /// Maximum number of objects warmed at the same time.
+    pub concurrency: usize,
+    pub prefetcher_config: PrefetcherConfig,
+    /// Memory limit for the prefetched data, in bytes.
+    pub mem_limit: u64,
+    /// Mount point of a running Mountpoint process for the same bucket and prefix. When set, the
+    /// warmed objects are also looked up through it, which populates its metadata cache.
+    pub metadata_mount_point: Option<PathBuf>,
+}
+
+impl Default for CacheWarmerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            prefetcher_config: Default::default(),
+            mem_limit: 512 * 1024 * 1024,
+            metadata_mount_point: None,
+        }
+    }
+}
+
+/// Outcome of [CacheWarmer::warm].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheWarmingStats {
+    /// Number of objects written to the cache.
+    pub objects: u64,
+    /// Number of bytes written to the cache.
+    pub bytes: u64,
+    /// Number of objects or prefixes that could not be warmed, or looked up through the mount point.
+    pub failures: u64,
+}
+
+/// An object to warm, as found by listing or looking it up.
+#[derive(Debug)]
+struct WarmObject {
+    /// Key relative to the prefix of the [CacheWarmer].
+    relative_key: String,
+    etag: ETag,
+    size: u64,
+}
+
+/// Writes the content of objects to a [DataCache].
+pub struct CacheWarmer<Client: ObjectClient> {
+    client: Client,
+    s3_path: S3Path,
+    cache: Arc<dyn DataCache + Send + Sync>,
+    prefetcher: Prefetcher<Client>,
+    config: CacheWarmerConfig,
+}
+
+impl<Client> CacheWarmer<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    /// Create a [CacheWarmer] for the objects of `s3_path`, writing to the caches configured in
+    /// `data_cache_config`.
+    pub fn new(
+        client: Client,
+        s3_path: S3Path,
+        data_cache_config: DataCacheConfig,
+        runtime: Runtime,
+        pool: PagedPool,
+        config: CacheWarmerConfig,
+    ) -> anyhow::Result<Self> {
+        let cache = create_data_cache(data_cache_config, &client, &runtime, pool.clone())?
+            .ok_or_else(|| anyhow!("no data cache is configured"))?;
+        let mem_limiter = Arc::new(MemoryLimiter::new(pool, config.mem_limit));
+        let prefetcher =
+            Prefetcher::default_builder(client.clone()).build(runtime, mem_limiter, config.prefetcher_config);
+        Ok(Self {
+            client,
+            s3_path,
+            cache,
+            prefetcher,
+            config,
+        })
+    }
+
+    /// Write the content of the objects in `targets` to the cache. Objects that can't be warmed
+    /// are logged and counted in [CacheWarmingStats::failures].
+    pub async fn warm(&self, targets: &[WarmTarget]) -> CacheWarmingStats {
+        let mut stats = CacheWarmingStats::default();
+        let mut objects = Vec::new();
+        for target in targets {
+            match self.resolve(target, &mut objects).await {
+                Ok(()) => {}
+                Err(error) => {
+                    warn!(?target, "unable to find the objects to warm: {error:#}");
+                    stats.failures += 1;
+                }
+            }
+        }
+
+        let results: Vec<_> = futures::stream::iter(objects.iter().enumerate())
+            .map(|(index, object)| async move {
+                let result = self.warm_object(HandleId::new(index as u64), object).await;
+                (object, result)
+            })
+            .buffer_unordered(self.config.concurrency.max(1))
+            .collect()
+            .await;
+        let mut warmed = Vec::new();
+        for (object, result) in results {
+            match result {
+                Ok(()) => {
+                    stats.objects += 1;
+                    stats.bytes += object.size;
+                    warmed.push(object.relative_key.as_str());
+                }
+                Err(error) => {
+                    warn!(key = object.relative_key, "unable to warm object: {error:#}");
+                    stats.failures += 1;
+                }
+            }
+        }
+
+        if let Some(mount_point) = &self.config.metadata_mount_point {
+            stats.failures += lookup_all(mount_point, &warmed, self.config.concurrency);
+        }
+        stats
+    }
+
+    /// Find the objects of a target, and add them to `objects`.
+    async fn resolve(&self, target: &WarmTarget, objects: &mut Vec<WarmObject>) -> anyhow::Result<()> {
+        let bucket = self.s3_path.bucket.as_str();
+        let prefix = self.s3_path.prefix.as_str();
+        match target {
+            WarmTarget::Object(relative_key) => {
+                let key = format!("{prefix}{relative_key}");
+                let object = self
+                    .client
+                    .head_object(bucket, &key, &HeadObjectParams::new())
+                    .await
+                    .context("HeadObject failed")?;
+                objects.push(WarmObject {
+                    relative_key: relative_key.clone(),
+                    etag: object.etag,
+                    size: object.size,
+                });
+            }
+            WarmTarget::Prefix(relative_prefix) => {
+                let list_prefix = format!("{prefix}{relative_prefix}");
+                let mut continuation_token = None;
+                loop {
+                    let result = self
+                        .client
+                        .list_objects(bucket, continuation_token.as_deref(), "", LIST_MAX_KEYS, &list_prefix)
+                        .await
+                        .context("ListObjectsV2 failed")?;
+                    objects.extend(result.objects.into_iter().map(|object| WarmObject {
+                        relative_key: object.key[prefix.len()..].to_owned(),
+                        etag: object.etag.into(),
+                        size: object.size,
+                    }));
+                    continuation_token = result.next_continuation_token;
+                    if continuation_token.is_none() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an object block by block, and write each block to the cache.
+    async fn warm_object(&self, handle_id: HandleId, object: &WarmObject) -> anyhow::Result<()> {
+        debug!(key = object.relative_key, size = object.size, "warming object");
+        let key = format!("{}{}", self.s3_path.prefix, object.relative_key);
+        let object_id = ObjectId::new(key, object.etag.clone());
+        let block_size = self.cache.block_size();
+        let mut request = self.prefetcher.prefetch(
+            self.s3_path.bucket.as_str().to_owned(),
+            object_id.clone(),
+            handle_id,
+            object.size,
+        );
+        let mut offset = 0;
+        let mut block_index = 0;
+        while offset < object.size {
+            let bytes = request.read(offset, block_size as usize).await?;
+            if bytes.is_empty() {
+                return Err(anyhow!("object ended at offset {offset}"));
+            }
+            let length = bytes.len() as u64;
+            self.cache
+                .put_block(object_id.clone(), block_index, offset, bytes, object.size as usize)
+                .await?;
+            offset += length;
+            block_index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Look up `keys` under `mount_point`, using up to `concurrency` threads. Returns the number of
+/// keys that could not be looked up.
+fn lookup_all(mount_point: &std::path::Path, keys: &[&str], concurrency: usize) -> u64 {
+    let chunk_size = keys.len().div_ceil(concurrency.max(1)).max(1);
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = keys
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut failures = 0;
+                    for key in chunk {
+                        let path = mount_point.join(key);
+                        if let Err(error) = std::fs::metadata(&path) {
+                            warn!(?path, ?error, "unable to look up object through the mount point");
+                            failures += 1;
+                        }
+                    }
+                    failures
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().expect("lookup threads don't panic"))
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::{ThreadPool, block_on};
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject};
+
+    use super::*;
+    use crate::data_cache::{CacheLimit, DiskDataCache, DiskDataCacheConfig};
+    use crate::s3::{Bucket, Prefix};
+
+    const BLOCK_SIZE: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = "# training data\ntrain/\n\n  a.txt  \nb/c.txt\n";
+        let targets = WarmTarget::parse_manifest(manifest.as_bytes()).unwrap();
+        assert_eq!(
+            targets,
+            [
+                WarmTarget::Prefix("train/".to_owned()),
+                WarmTarget::Object("a.txt".to_owned()),
+                WarmTarget::Object("b/c.txt".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warm() {
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(BLOCK_SIZE as usize)
+                .enable_backpressure(true)
+                .initial_read_window_size(BLOCK_SIZE as usize)
+                .build(),
+        );
+        client.add_object(
+            "data/a.txt",
+            MockObject::ramp(0x11, 3 * BLOCK_SIZE as usize / 2, ETag::for_tests()),
+        );
+        client.add_object("data/train/b.txt", MockObject::ramp(0x22, 10, ETag::for_tests()));
+        client.add_object("data/train/c/d.txt", MockObject::ramp(0x33, 100, ETag::for_tests()));
+        client.add_object("data/test/e.txt", MockObject::ramp(0x44, 100, ETag::for_tests()));
+
+        let cache_directory = tempfile::tempdir().unwrap();
+        let disk_cache_config = || DiskDataCacheConfig {
+            cache_directory: cache_directory.path().to_path_buf(),
+            block_size: BLOCK_SIZE,
+            limit: CacheLimit::Unbounded,
+            eviction_policy: Default::default(),
+            shared: false,
+            persistent: false,
+            admission_policy: Default::default(),
+            pinning: None,
+            encryption_key: None,
+            compression_level: None,
+            io_backend: Default::default(),
+            account: None,
+        };
+        let pool = PagedPool::new_with_candidate_sizes([BLOCK_SIZE as usize]);
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let s3_path = S3Path::new(Bucket::new("test-bucket").unwrap(), Prefix::new("data/").unwrap());
+        let warmer = CacheWarmer::new(
+            client,
+            s3_path,
+            DataCacheConfig {
+                disk_cache_config: Some(disk_cache_config()),
+                memory_cache_config: None,
+                scrub_interval: None,
+                express_cache_config: None,
+                peer_cache_config: None,
+                prefix_metrics_config: None,
+                write_through_config: None,
+                pin_xattr: false,
+            },
+            runtime,
+            pool.clone(),
+            Default::default(),
+        )
+        .unwrap();
+
+        let targets = [
+            WarmTarget::Object("a.txt".to_owned()),
+            WarmTarget::Prefix("train/".to_owned()),
+            WarmTarget::Object("missing.txt".to_owned()),
+        ];
+        let stats = block_on(warmer.warm(&targets));
+        assert_eq!(
+            stats,
+            CacheWarmingStats {
+                objects: 3,
+                bytes: 3 * BLOCK_SIZE / 2 + 110,
+                failures: 1,
+            }
+        );
+
+        let cache = DiskDataCache::new(disk_cache_config(), pool);
+        let is_cached = |key: &str, block_index: u64, size: usize| {
+            let object_id = ObjectId::new(key.to_owned(), ETag::for_tests());
+            block_on(cache.get_block(&object_id, block_index, block_index * BLOCK_SIZE, size))
+                .unwrap()
+                .is_some()
+        };
+        let a_size = 3 * BLOCK_SIZE as usize / 2;
+        assert!(is_cached("data/a.txt", 0, a_size));
+        assert!(is_cached("data/a.txt", 1, a_size));
+        assert!(is_cached("data/train/b.txt", 0, 10));
+        assert!(is_cached("data/train/c/d.txt", 0, 100));
+        assert!(
+            !is_cached("data/test/e.txt", 0, 100),
+            "objects outside the targets aren't warmed"
+        );
+    }
+
+    #[test]
+    fn test_warm_metadata() {
+        let mount_point = tempfile::tempdir().unwrap();
+        std::fs::write(mount_point.path().join("a.txt"), "a").unwrap();
+        assert_eq!(lookup_all(mount_point.path(), &["a.txt", "b.txt"], 4), 1);
+        assert_eq!(lookup_all(mount_point.path(), &[], 4), 0);
+    }
+}
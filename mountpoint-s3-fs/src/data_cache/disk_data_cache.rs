@@ -1,17 +1,18 @@
 //! Module for the on-disk data cache implementation.
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::{ErrorKind, Read, Write};
+use std::io::{self, ErrorKind, Read, Write};
+use std::ops::{Deref, DerefMut};
 use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use bincode::config::{Configuration, Fixint, Limit, LittleEndian};
 use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
 use bytes::Bytes;
-use linked_hash_map::LinkedHashMap;
 use mountpoint_s3_client::checksums::crc32c::{self, Crc32c};
 use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
@@ -22,50 +23,184 @@ use crate::checksums::IntegrityError;
 use crate::data_cache::DataCacheError;
 use crate::memory::{BufferKind, PagedPool};
 use crate::metrics::defs::{
-    ATTR_CACHE, CACHE_DISK, CACHE_EVICT_LATENCY, CACHE_GET_ERRORS, CACHE_GET_IO_SIZE, CACHE_GET_LATENCY,
-    CACHE_PUT_ERRORS, CACHE_PUT_IO_SIZE, CACHE_PUT_LATENCY, CACHE_TOTAL_SIZE,
+    ATTR_ACCOUNT, ATTR_CACHE, CACHE_ACCOUNT_EVICTIONS, CACHE_ACCOUNT_SIZE, CACHE_DISK, CACHE_EVICT_LATENCY,
+    CACHE_GET_ERRORS, CACHE_GET_IO_SIZE, CACHE_GET_LATENCY, CACHE_PUT_ERRORS, CACHE_PUT_IO_SIZE, CACHE_PUT_LATENCY,
+    CACHE_TOTAL_SIZE,
 };
 use crate::object::ObjectId;
-use crate::sync::Mutex;
+use crate::sync::{Mutex, MutexGuard};
 
 use super::{BlockIndex, ChecksummedBytes, DataCache, DataCacheResult};
 
+mod admission;
+mod encryption;
+mod inspect;
+mod scrubber;
+#[cfg(target_os = "linux")]
+mod uring;
+mod usage_journal;
+
+pub use encryption::{CacheEncryptionKey, CacheEncryptionKeyError};
+pub use inspect::CachedBlockInfo;
+pub use scrubber::ScrubStats;
+
+use admission::GhostFilter;
+use encryption::BlockCipher;
+#[cfg(target_os = "linux")]
+use uring::UringIo;
+use usage_journal::{UsageJournal, UsageRecord};
+
 /// Disk and file-layout versioning.
 const CACHE_VERSION: &str = "V2";
 
+/// Version written before encrypted blocks instead of [CACHE_VERSION], so that caches with and without encryption don't
+/// try to read each other's blocks.
+const ENCRYPTED_CACHE_VERSION: &str = "E2";
+
+/// Versions written before the blocks compressed with zstd instead of [CACHE_VERSION] and [ENCRYPTED_CACHE_VERSION], so
+/// that whether each block is compressed is known before reading it.
+const COMPRESSED_CACHE_VERSION: &str = "Z2";
+const ENCRYPTED_COMPRESSED_CACHE_VERSION: &str = "X2";
+
 /// Index where hashed directory names for the cache are split to avoid FS-specific limits.
 const HASHED_DIR_SPLIT_INDEX: usize = 2;
 
+/// Name of the file the eviction bookkeeping is saved to, in the directory of the blocks.
+const USAGE_STATE_FILE: &str = "usage-state";
+
 /// On-disk implementation of [DataCache].
 pub struct DiskDataCache {
     config: DiskDataCacheConfig,
     pool: PagedPool,
     /// Tracks blocks usage. `None` when no cache limit was set.
-    usage: Option<Mutex<UsageInfo<DiskBlockKey>>>,
+    usage: Option<Mutex<BlockUsage>>,
+    /// Blocks read once, for the [AdmissionPolicy::SecondRead] policy.
+    ghosts: Option<Mutex<GhostFilter>>,
+    /// Cipher the blocks are encrypted with, if any.
+    cipher: Option<BlockCipher>,
+    /// The io_uring instance the block files are read and written with, for [DiskIoBackend::IoUring].
+    #[cfg(target_os = "linux")]
+    uring: Option<UringIo>,
 }
 
 /// Configuration for a [DiskDataCache].
 #[derive(Debug)]
 pub struct DiskDataCacheConfig {
     pub cache_directory: PathBuf,
-    /// Size of data blocks.
+    /// Size of data blocks. Blocks of different sizes are kept in separate directories, each with its own usage
+    /// bookkeeping, so mounts with different block sizes can use the same cache directory.
     pub block_size: u64,
     /// How to limit the cache size.
     pub limit: CacheLimit,
+    /// Which blocks to evict first.
+    pub eviction_policy: EvictionPolicy,
+    /// Whether other processes may use the same cache directory at the same time. When set, the usage of the blocks
+    /// is shared with them, so that the cache limit applies to the blocks written by all of them.
+    pub shared: bool,
+    /// Whether the content of the cache directory is kept when Mountpoint restarts. When set, the blocks tracked by
+    /// the saved usage state are validated against it when first read, rather than when the cache is created.
+    pub persistent: bool,
+    /// Which blocks to write to the cache.
+    pub admission_policy: AdmissionPolicy,
+    /// Blocks to keep in the cache in preference to the others, if any.
+    pub pinning: Option<PinningConfig>,
+    /// Key to encrypt the blocks with, if any. Blocks written without encryption or with another key are not read.
+    pub encryption_key: Option<CacheEncryptionKey>,
+    /// Compress the blocks with zstd at this level before writing them, if set. Blocks that don't get smaller are
+    /// written uncompressed, and compressed blocks are read whether or not this is set.
+    pub compression_level: Option<i32>,
+    /// How to read and write the block files.
+    pub io_backend: DiskIoBackend,
+    /// Account the blocks written by this mount are charged to, in a cache directory shared with other mounts.
+    pub account: Option<CacheAccount>,
+}
+
+/// Accounting of the blocks written by the mounts sharing a cache directory, so that the usage of each of them can be
+/// reported and limited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheAccount {
+    /// Name of the account. Mounts with the same account share its usage and quota.
+    pub name: String,
+    /// Maximum total size of the blocks charged to the account. When exceeded, the blocks of the account are evicted
+    /// first, so that a mount over its quota evicts its own blocks rather than those of the other mounts.
+    pub quota: Option<usize>,
+}
+
+/// How a [DiskDataCache] reads and writes its block files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiskIoBackend {
+    /// Blocking reads and writes, on the threads getting and putting the blocks.
+    #[default]
+    Sync,
+    /// Asynchronous reads and writes with io_uring, bypassing the page cache with `O_DIRECT` if `direct` is set. Falls
+    /// back to [DiskIoBackend::Sync] where io_uring isn't available.
+    IoUring { direct: bool },
+}
+
+/// Blocks that a [DiskDataCache] evicts only after the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinningConfig {
+    /// Prefixes of the keys of the pinned blocks.
+    pub prefixes: Vec<String>,
+    /// Maximum fraction of the cache size, between 0 and 1, used by pinned blocks. When pinned blocks use more than
+    /// this, they are evicted like the other blocks.
+    pub max_ratio: f64,
 }
 
 /// Limit the cache size.
 #[derive(Debug)]
 pub enum CacheLimit {
     Unbounded,
-    TotalSize { max_size: usize },
-    AvailableSpace { min_ratio: f64 },
+    TotalSize {
+        max_size: usize,
+    },
+    AvailableSpace {
+        min_ratio: f64,
+    },
+    /// Keep the available space of the filesystem of the cache directory between two watermarks: once less than
+    /// `low_ratio` of its space is available, blocks are evicted until `high_ratio` of it is available again. The cache
+    /// then shrinks when other users of the filesystem need space, and grows back when they release it. When set, the
+    /// cache is also limited to `max_size`.
+    Watermarks {
+        low_ratio: f64,
+        high_ratio: f64,
+        max_size: Option<usize>,
+    },
+}
+
+/// How to pick the blocks to evict from the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used blocks first.
+    #[default]
+    Lru,
+    /// Evict the least frequently used blocks first, and the least recently used of them first.
+    Lfu,
+    /// Evict the oldest blocks first, however they are used.
+    Fifo,
+    /// Evict blocks once they are older than `ttl`, and the oldest blocks first when the cache
+    /// exceeds its limit.
+    Ttl { ttl: Duration },
+}
+
+/// How to pick the blocks to write to the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AdmissionPolicy {
+    /// Write all the blocks read from S3.
+    #[default]
+    All,
+    /// Only write a block when it is read from S3 for the second time within `window`, so that blocks read only once,
+    /// for example by a large scan, don't evict the blocks read repeatedly.
+    SecondRead { window: Duration },
 }
 
 /// Default minimum ratio of available space to preserve when using AvailableSpace cache limit.
 /// This preserves 5% of the filesystem's total space as available space.
 pub const DEFAULT_CACHE_MIN_AVAILABLE_RATIO: f64 = 0.05;
 
+/// Default zstd level to compress blocks at, favoring speed over compression ratio.
+pub const DEFAULT_CACHE_COMPRESSION_LEVEL: i32 = 3;
+
 impl Default for CacheLimit {
     fn default() -> Self {
         CacheLimit::AvailableSpace {
@@ -129,6 +264,10 @@ enum DiskBlockReadWriteError {
     EncodeError(EncodeError),
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    DecryptionError(#[from] encryption::DecryptionError),
+    #[error("Error decompressing the block: {0}")]
+    DecompressionError(std::io::Error),
 }
 
 impl DiskBlockHeader {
@@ -209,6 +348,16 @@ impl DiskBlockHeader {
     }
 }
 
+/// The data of a block read from the cache directory, along with the information to validate it against the usage
+/// state.
+struct CachedBlock {
+    bytes: ChecksummedBytes,
+    /// Size of the block file, excluding the cache version.
+    size: usize,
+    /// Checksum of the header of the block.
+    checksum: u32,
+}
+
 /// Represents a fixed-size chunk of data that can be serialized.
 #[derive(Debug)]
 struct DiskBlock {
@@ -257,8 +406,13 @@ impl DiskBlock {
         Ok(bytes)
     }
 
-    /// Deserialize an instance from `reader`.
-    fn read(reader: &mut impl Read, block_size: u64, pool: &PagedPool) -> Result<Self, DiskBlockReadWriteError> {
+    /// Deserialize an instance from `reader`, decompressing its data if `compressed`.
+    fn read(
+        reader: &mut impl Read,
+        block_size: u64,
+        compressed: bool,
+        pool: &PagedPool,
+    ) -> Result<Self, DiskBlockReadWriteError> {
         let header: DiskBlockHeader = bincode::decode_from_std_read(reader, BINCODE_CONFIG)?;
 
         if header.block_len > block_size {
@@ -266,18 +420,58 @@ impl DiskBlock {
         }
 
         let size = header.block_len as usize;
-        let mut buffer = pool.get_buffer_mut(size, BufferKind::DiskCache);
-        buffer.fill_from_reader(reader)?;
-        let data = buffer.into_bytes();
+        let data = if compressed {
+            let mut compressed_data = Vec::new();
+            reader.read_to_end(&mut compressed_data)?;
+            let data =
+                zstd::bulk::decompress(&compressed_data, size).map_err(DiskBlockReadWriteError::DecompressionError)?;
+            if data.len() != size {
+                return Err(DiskBlockReadWriteError::InvalidBlockLength(data.len() as u64));
+            }
+            data.into()
+        } else {
+            let mut buffer = pool.get_buffer_mut(size, BufferKind::DiskCache);
+            buffer.fill_from_reader(reader)?;
+            buffer.into_bytes()
+        };
+
+        Ok(Self { header, data })
+    }
+
+    /// Deserialize an instance from `content`, like [Self::read], without copying its data unless it is compressed.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn from_bytes(content: Bytes, block_size: u64, compressed: bool) -> Result<Self, DiskBlockReadWriteError> {
+        let (header, header_len): (DiskBlockHeader, usize) = bincode::decode_from_slice(&content, BINCODE_CONFIG)?;
+
+        if header.block_len > block_size {
+            return Err(DiskBlockReadWriteError::InvalidBlockLength(header.block_len));
+        }
+
+        let size = header.block_len as usize;
+        let data = if compressed {
+            let data = zstd::bulk::decompress(&content[header_len..], size)
+                .map_err(DiskBlockReadWriteError::DecompressionError)?;
+            if data.len() != size {
+                return Err(DiskBlockReadWriteError::InvalidBlockLength(data.len() as u64));
+            }
+            data.into()
+        } else {
+            if content.len() - header_len < size {
+                return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+            }
+            content.slice(header_len..header_len + size)
+        };
 
         Ok(Self { header, data })
     }
 
-    /// Serialize this instance to `writer` and return the number of bytes written on success.
-    fn write(&self, writer: &mut impl Write) -> Result<usize, DiskBlockReadWriteError> {
+    /// Serialize this instance to `writer`, with `compressed_data` in place of its data if given, and return the number
+    /// of bytes written on success.
+    fn write(&self, writer: &mut impl Write, compressed_data: Option<&[u8]>) -> Result<usize, DiskBlockReadWriteError> {
         let header_length = bincode::encode_into_std_write(&self.header, writer, BINCODE_CONFIG)?;
-        writer.write_all(&self.data)?;
-        Ok(header_length + self.data.len())
+        let data = compressed_data.unwrap_or(&self.data);
+        writer.write_all(data)?;
+        Ok(header_length + data.len())
     }
 }
 
@@ -316,17 +510,204 @@ impl From<DiskBlockReadWriteError> for DataCacheError {
 
 impl DiskDataCache {
     /// Create a new instance of an [DiskDataCache] with the specified configuration.
+    ///
+    /// The eviction bookkeeping saved in the cache directory when a previous instance was dropped
+    /// is restored, so that blocks it wrote keep their place in the eviction order. For a shared
+    /// cache, the bookkeeping of the other processes using the cache directory is also restored.
     pub fn new(config: DiskDataCacheConfig, pool: PagedPool) -> Self {
-        let usage = match &config.limit {
-            CacheLimit::Unbounded => None,
-            CacheLimit::TotalSize { .. } | CacheLimit::AvailableSpace { .. } => Some(Mutex::new(UsageInfo::new())),
+        let tracks_usage = match &config.limit {
+            CacheLimit::Unbounded => {
+                matches!(config.eviction_policy, EvictionPolicy::Ttl { .. }) || config.account.is_some()
+            }
+            CacheLimit::TotalSize { .. } | CacheLimit::AvailableSpace { .. } | CacheLimit::Watermarks { .. } => true,
+        };
+        let ghosts = match config.admission_policy {
+            AdmissionPolicy::All => None,
+            AdmissionPolicy::SecondRead { window } => Some(Mutex::new(GhostFilter::new(window))),
+        };
+        let cipher = config.encryption_key.as_ref().map(BlockCipher::new);
+        #[cfg(target_os = "linux")]
+        let uring = match config.io_backend {
+            DiskIoBackend::Sync => None,
+            DiskIoBackend::IoUring { direct } => UringIo::new(direct)
+                .inspect_err(|error| warn!(?error, "io_uring is not available, falling back to synchronous I/O"))
+                .ok(),
+        };
+        #[cfg(not(target_os = "linux"))]
+        if matches!(config.io_backend, DiskIoBackend::IoUring { .. }) {
+            warn!("io_uring is only available on Linux, falling back to synchronous I/O");
+        }
+        let mut cache = DiskDataCache {
+            config,
+            pool,
+            usage: None,
+            ghosts,
+            cipher,
+            #[cfg(target_os = "linux")]
+            uring,
+        };
+        if !tracks_usage {
+            return cache;
+        }
+        // Blocks of a persistent cache are validated lazily, as checking all of them could delay the mount.
+        let persistent = cache.config.persistent;
+        let exists = |key: &DiskBlockKey| persistent || cache.get_path_for_block_key(key).exists();
+        let mut usage = if cache.config.shared {
+            let directory = cache.blocks_directory();
+            match UsageJournal::open(&directory, cache.config.eviction_policy, exists) {
+                Ok((journal, info)) => BlockUsage::new(info, Some(journal)),
+                Err(error) => {
+                    warn!(
+                        ?directory,
+                        ?error,
+                        "unable to open the shared usage journal, not sharing usage"
+                    );
+                    BlockUsage::new(cache.load_usage(), None)
+                }
+            }
+        } else {
+            BlockUsage::new(cache.load_usage(), None)
+        };
+        if persistent {
+            usage.unvalidated = usage.info.entries.keys().copied().collect();
+        }
+        cache.usage = Some(Mutex::new(usage));
+        cache
+    }
+
+    /// The directory of the blocks of the configured size, within the versioned cache directory.
+    fn blocks_directory(&self) -> PathBuf {
+        let block_size = self.config.block_size;
+        self.config
+            .cache_directory
+            .join(CACHE_VERSION)
+            .join(format!("blocks-{block_size}"))
+    }
+
+    /// The version written at the start of the block files, depending on whether the block is compressed.
+    fn block_version(&self, compressed: bool) -> &'static str {
+        match (self.cipher.is_some(), compressed) {
+            (false, false) => CACHE_VERSION,
+            (true, false) => ENCRYPTED_CACHE_VERSION,
+            (false, true) => COMPRESSED_CACHE_VERSION,
+            (true, true) => ENCRYPTED_COMPRESSED_CACHE_VERSION,
+        }
+    }
+
+    /// Read the version at the start of a block file, and return whether the block is compressed, or `None` if the
+    /// block has a stale format or wasn't written with the encryption configuration of this cache.
+    fn read_block_version(&self, file: &mut fs::File) -> io::Result<Option<bool>> {
+        let mut block_version = [0; CACHE_VERSION.len()];
+        file.read_exact(&mut block_version)?;
+        Ok(self.compressed_version(&block_version))
+    }
+
+    /// Whether a block file starting with `block_version` is compressed, as for [Self::read_block_version].
+    fn compressed_version(&self, block_version: &[u8]) -> Option<bool> {
+        [false, true]
+            .into_iter()
+            .find(|&compressed| block_version == self.block_version(compressed).as_bytes())
+    }
+
+    /// Deserialize the block in `file`, after its version, decrypting it if the cache is encrypted and decompressing
+    /// it if `compressed`.
+    fn read_disk_block(
+        &self,
+        file: &mut fs::File,
+        block_key: &DiskBlockKey,
+        compressed: bool,
+    ) -> Result<DiskBlock, DiskBlockReadWriteError> {
+        let Some(cipher) = &self.cipher else {
+            return DiskBlock::read(file, self.config.block_size, compressed, &self.pool);
+        };
+        let mut sealed = Vec::new();
+        file.read_to_end(&mut sealed)?;
+        let mut data: &[u8] = cipher.open(block_key, &mut sealed)?;
+        DiskBlock::read(&mut data, self.config.block_size, compressed, &self.pool)
+    }
+
+    /// Deserialize the block in `content`, the content of a block file after its version, like [Self::read_disk_block].
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn parse_disk_block(
+        &self,
+        content: Bytes,
+        block_key: &DiskBlockKey,
+        compressed: bool,
+    ) -> Result<DiskBlock, DiskBlockReadWriteError> {
+        let Some(cipher) = &self.cipher else {
+            return DiskBlock::from_bytes(content, self.config.block_size, compressed);
+        };
+        let mut sealed = content.to_vec();
+        let data = cipher.open(block_key, &mut sealed)?;
+        DiskBlock::from_bytes(Bytes::copy_from_slice(data), self.config.block_size, compressed)
+    }
+
+    /// Compress the data of `block` if the cache is configured to, and if that makes it smaller.
+    fn compress(&self, block: &DiskBlock) -> Option<Vec<u8>> {
+        let level = self.config.compression_level?;
+        zstd::bulk::compress(&block.data, level)
+            .inspect_err(|error| warn!(?error, "unable to compress cache block"))
+            .ok()
+            .filter(|compressed| compressed.len() < block.data.len())
+    }
+
+    fn usage_state_path(&self) -> PathBuf {
+        self.blocks_directory().join(USAGE_STATE_FILE)
+    }
+
+    /// Load the eviction bookkeeping saved by a previous instance, if any. Blocks removed from the
+    /// cache directory since then are skipped.
+    fn load_usage(&self) -> UsageInfo<DiskBlockKey> {
+        let path = self.usage_state_path();
+        let Some(state) = read_usage_state(&path) else {
+            return UsageInfo::new(self.config.eviction_policy);
         };
-        DiskDataCache { config, pool, usage }
+        let usage = UsageInfo::from_state(self.config.eviction_policy, state, |key| {
+            self.config.persistent || self.get_path_for_block_key(key).exists()
+        });
+        trace!(
+            ?path,
+            entries = usage.entries.len(),
+            size = usage.size,
+            "restored cache usage state"
+        );
+        usage
+    }
+
+    /// Save the eviction bookkeeping to the cache directory, to be restored by [Self::load_usage].
+    fn save_usage(&self, usage: &mut BlockUsage) -> io::Result<()> {
+        if let Some(journal) = &mut usage.journal {
+            return journal.save(&mut usage.info);
+        }
+        let path = self.usage_state_path();
+        let directory = path.parent().expect("path should include the blocks directory");
+        if !directory.exists() {
+            // Nothing was ever written to the cache, or the cache directory was already cleaned up.
+            return Ok(());
+        }
+        write_usage_state(&path, &usage.info.to_state(0))?;
+        trace!(?path, entries = usage.info.entries.len(), "saved cache usage state");
+        Ok(())
+    }
+
+    /// Lock the usage bookkeeping, if the cache tracks it. For a shared cache, the changes made by
+    /// other processes are applied first, and the changes made through the returned guard are
+    /// shared with them when it is dropped.
+    fn lock_usage(&self) -> Option<UsageGuard<'_>> {
+        let mut usage = self.usage.as_ref()?.lock().unwrap();
+        let BlockUsage { info, journal, .. } = &mut *usage;
+        let synced = journal.as_mut().is_some_and(|journal| {
+            journal
+                .sync(info)
+                .inspect_err(|error| warn!(?error, "unable to read the shared usage journal"))
+                .is_ok()
+        });
+        Some(UsageGuard { usage, synced })
     }
 
     /// Get the relative path for the given block.
     fn get_path_for_block_key(&self, block_key: &DiskBlockKey) -> PathBuf {
-        let mut path = self.config.cache_directory.join(CACHE_VERSION);
+        let mut path = self.blocks_directory();
         block_key.append_to_path(&mut path);
         path
     }
@@ -337,7 +718,7 @@ impl DiskDataCache {
         cache_key: &ObjectId,
         block_idx: BlockIndex,
         block_offset: u64,
-    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+    ) -> DataCacheResult<Option<CachedBlock>> {
         trace!(
             key = ?cache_key.key(),
             offset = block_offset,
@@ -350,18 +731,46 @@ impl DiskDataCache {
             Err(err) => return Err(err.into()),
         };
 
-        let mut block_version = [0; CACHE_VERSION.len()];
-        file.read_exact(&mut block_version)?;
-        if block_version != CACHE_VERSION.as_bytes() {
-            warn!(
-                found_version = ?block_version, expected_version = ?CACHE_VERSION, path = ?path.as_ref(),
-                "stale block format found during reading"
-            );
+        let Some(compressed) = self.read_block_version(&mut file)? else {
+            warn!(path = ?path.as_ref(), "stale block format found during reading");
             return Err(DataCacheError::InvalidBlockContent);
-        }
+        };
 
-        let block = DiskBlock::read(&mut file, self.block_size(), &self.pool)
+        let size = file.metadata()?.len() as usize - CACHE_VERSION.len();
+        let block = self
+            .read_disk_block(&mut file, &DiskBlockKey::new(cache_key, block_idx), compressed)
             .inspect_err(|e| warn!(path = ?path.as_ref(), "block could not be deserialized: {:?}", e))?;
+        self.cached_block(block, size, cache_key, block_idx, block_offset)
+            .map(Some)
+    }
+
+    /// Read the block at `path` with the configured I/O backend.
+    async fn read_block_with_backend(
+        &self,
+        path: &Path,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+    ) -> DataCacheResult<Option<CachedBlock>> {
+        #[cfg(target_os = "linux")]
+        if let Some(uring) = &self.uring {
+            return self
+                .read_block_uring(uring, path, cache_key, block_idx, block_offset)
+                .await;
+        }
+        self.read_block(path, cache_key, block_idx, block_offset)
+    }
+
+    /// Validate a block read from a block file of `size` bytes, excluding its version, and extract its data.
+    fn cached_block(
+        &self,
+        block: DiskBlock,
+        size: usize,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+    ) -> DataCacheResult<CachedBlock> {
+        let checksum = block.header.header_checksum;
         let bytes = block
             .data(cache_key, block_idx, block_offset)
             .map_err(|err| match err {
@@ -370,10 +779,15 @@ impl DiskDataCache {
                 }
             })?;
 
-        Ok(Some(bytes))
+        Ok(CachedBlock { bytes, size, checksum })
     }
 
-    fn write_block(&self, path: impl AsRef<Path>, block: DiskBlock) -> DataCacheResult<(NamedTempFile, usize)> {
+    fn write_block(
+        &self,
+        path: impl AsRef<Path>,
+        block_key: &DiskBlockKey,
+        block: DiskBlock,
+    ) -> DataCacheResult<(NamedTempFile, usize)> {
         let path = path.as_ref();
         let cache_path_for_key = path.parent().expect("path should include cache key in directory name");
         fs::DirBuilder::new()
@@ -381,9 +795,7 @@ impl DiskDataCache {
             .recursive(true)
             .create(cache_path_for_key)?;
 
-        let mut temp_file = tempfile::Builder::new()
-            .permissions(fs::Permissions::from_mode(0o600))
-            .tempfile_in(cache_path_for_key)?;
+        let mut temp_file = self.create_temp_file(cache_path_for_key)?;
         trace!(
             key = block.header.s3_key,
             offset = block.header.block_offset,
@@ -391,57 +803,244 @@ impl DiskDataCache {
             temp_path = ?temp_file.path(),
             "writing cache block",
         );
-        temp_file.write_all(CACHE_VERSION.as_bytes())?;
-        let bytes_written = block.write(&mut temp_file)?;
+        let bytes_written = self.serialize_block(&mut temp_file, block_key, block)?;
         Ok((temp_file, bytes_written))
     }
 
-    fn is_limit_exceeded(&self, size: usize) -> bool {
+    /// Write `block` to a temporary file next to `path` with the configured I/O backend.
+    async fn write_block_with_backend(
+        &self,
+        path: &Path,
+        block_key: &DiskBlockKey,
+        block: DiskBlock,
+    ) -> DataCacheResult<(NamedTempFile, usize)> {
+        #[cfg(target_os = "linux")]
+        if let Some(uring) = &self.uring {
+            return self.write_block_uring(uring, path, block_key, block).await;
+        }
+        self.write_block(path, block_key, block)
+    }
+
+    /// Create the temporary file a block is written to in `directory`, before it is moved to its path.
+    fn create_temp_file(&self, directory: &Path) -> io::Result<NamedTempFile> {
+        tempfile::Builder::new()
+            .permissions(fs::Permissions::from_mode(0o600))
+            .tempfile_in(directory)
+    }
+
+    /// Write the version and content of the block file of `block` to `writer`, and return the size of the content,
+    /// excluding the version.
+    fn serialize_block(
+        &self,
+        writer: &mut impl Write,
+        block_key: &DiskBlockKey,
+        block: DiskBlock,
+    ) -> Result<usize, DiskBlockReadWriteError> {
+        let compressed_data = self.compress(&block);
+        writer.write_all(self.block_version(compressed_data.is_some()).as_bytes())?;
+        match &self.cipher {
+            Some(cipher) => {
+                let mut data = Vec::new();
+                block.write(&mut data, compressed_data.as_deref())?;
+                let sealed = cipher.seal(block_key, data);
+                writer.write_all(&sealed)?;
+                Ok(sealed.len())
+            }
+            None => block.write(writer, compressed_data.as_deref()),
+        }
+    }
+
+    /// Whether the cache of the given size exceeds its limit. While `evicting`, the cache is only back within a
+    /// [CacheLimit::Watermarks] limit once the high watermark is reached.
+    fn is_limit_exceeded(&self, size: usize, evicting: bool) -> bool {
         metrics::gauge!(CACHE_TOTAL_SIZE, ATTR_CACHE => CACHE_DISK).set(size as f64);
         match self.config.limit {
             CacheLimit::Unbounded => false,
             CacheLimit::TotalSize { max_size } => size > max_size,
-            CacheLimit::AvailableSpace { min_ratio } => {
-                let stats = match nix::sys::statvfs::statvfs(&self.config.cache_directory) {
-                    Ok(stats) if stats.blocks() == 0 => {
-                        warn!("unable to determine available space (0 blocks reported)");
-                        return false;
-                    }
-                    Ok(stats) => stats,
-                    Err(error) => {
-                        warn!(?error, "unable to determine available space");
-                        return false;
-                    }
-                };
-                (stats.blocks_available() as f64) < min_ratio * (stats.blocks() as f64)
+            CacheLimit::AvailableSpace { min_ratio } => self.available_ratio().is_some_and(|ratio| ratio < min_ratio),
+            CacheLimit::Watermarks {
+                low_ratio,
+                high_ratio,
+                max_size,
+            } => {
+                if max_size.is_some_and(|max_size| size > max_size) {
+                    return true;
+                }
+                let min_ratio = if evicting { high_ratio } else { low_ratio };
+                self.available_ratio().is_some_and(|ratio| ratio < min_ratio)
+            }
+        }
+    }
+
+    /// The ratio of the space of the filesystem of the cache directory that is available, if known.
+    fn available_ratio(&self) -> Option<f64> {
+        match nix::sys::statvfs::statvfs(&self.config.cache_directory) {
+            Ok(stats) if stats.blocks() == 0 => {
+                warn!("unable to determine available space (0 blocks reported)");
+                None
+            }
+            Ok(stats) => Some(stats.blocks_available() as f64 / stats.blocks() as f64),
+            Err(error) => {
+                warn!(?error, "unable to determine available space");
+                None
             }
         }
     }
 
     fn evict_if_needed(&self) -> DataCacheResult<()> {
-        let Some(usage) = &self.usage else {
+        if self.usage.is_none() {
             return Ok(());
-        };
+        }
 
+        let now = timestamp_millis();
+        let mut evicting = false;
         loop {
-            let mut usage = usage.lock().unwrap();
-            if !self.is_limit_exceeded(usage.size) {
-                break;
-            }
-            let Some(to_remove) = usage.evict_lru() else {
-                warn!("cache limit exceeded but nothing to evict");
-                return Err(DataCacheError::EvictionFailure);
+            let mut usage = self.lock_usage().expect("usage is tracked");
+            let to_remove = match usage.evict_expired(now).or_else(|| self.evict_over_quota(&mut usage)) {
+                Some(to_remove) => to_remove,
+                None => {
+                    if !self.is_limit_exceeded(usage.info.size, evicting) {
+                        break;
+                    }
+                    let max_pinned_size = self.max_pinned_size(usage.info.size);
+                    let Some(to_remove) = usage.evict(max_pinned_size) else {
+                        if evicting && !self.is_limit_exceeded(usage.info.size, false) {
+                            // The high watermark can't be reached, but the cache is back within the low one.
+                            break;
+                        }
+                        warn!("cache limit exceeded but nothing to evict");
+                        return Err(DataCacheError::EvictionFailure);
+                    };
+                    evicting = true;
+                    to_remove
+                }
             };
-            let path_to_remove = self.get_path_for_block_key(&to_remove);
-            trace!("evicting block at {}", path_to_remove.display());
-            if let Err(remove_err) = fs::remove_file(&path_to_remove)
-                && remove_err.kind() != ErrorKind::NotFound
-            {
-                warn!("unable to evict block: {:?}", remove_err);
-            }
+            self.remove_block(&to_remove);
         }
         Ok(())
     }
+
+    /// Evict the next block of the account of this mount if the account exceeds its quota. Also reports the usage of
+    /// the account.
+    fn evict_over_quota(&self, usage: &mut BlockUsage) -> Option<DiskBlockKey> {
+        let account = self.config.account.as_ref()?;
+        let size = usage.info.account_size(&account.name);
+        metrics::gauge!(CACHE_ACCOUNT_SIZE, ATTR_ACCOUNT => account.name.clone()).set(size as f64);
+        if account.quota.is_none_or(|quota| size <= quota) {
+            return None;
+        }
+        let key = usage.evict_account(&account.name)?;
+        trace!(account = account.name, ?key, "evicting block over the account quota");
+        metrics::counter!(CACHE_ACCOUNT_EVICTIONS, ATTR_ACCOUNT => account.name.clone()).increment(1);
+        Some(key)
+    }
+
+    /// The name of the account of this mount, if any.
+    fn account_name(&self) -> Option<&str> {
+        self.config.account.as_ref().map(|account| account.name.as_str())
+    }
+
+    /// Whether the blocks of the given object are pinned.
+    fn is_pinned(&self, cache_key: &ObjectId) -> bool {
+        self.config.pinning.as_ref().is_some_and(|pinning| {
+            pinning
+                .prefixes
+                .iter()
+                .any(|prefix| cache_key.key().starts_with(prefix.as_str()))
+        })
+    }
+
+    /// Keep the blocks of an object of the given size in the cache until [Self::unpin_object] is called for it: they
+    /// are written whatever the admission policy, and are neither evicted nor expired. Once the blocks of the pinned
+    /// objects fill the cache, no other blocks are written to it. Other processes sharing the cache directory don't
+    /// know about the pinned objects, and may still evict their blocks. Does nothing when the usage of the blocks isn't
+    /// tracked, since blocks are then never evicted.
+    pub fn pin_object(&self, cache_key: &ObjectId, object_size: u64) {
+        self.set_held(cache_key, object_size, true);
+    }
+
+    /// Let the blocks of an object pinned with [Self::pin_object] be evicted again.
+    pub fn unpin_object(&self, cache_key: &ObjectId, object_size: u64) {
+        self.set_held(cache_key, object_size, false);
+    }
+
+    fn set_held(&self, cache_key: &ObjectId, object_size: u64, held: bool) {
+        let Some(mut usage) = self.lock_usage() else {
+            return;
+        };
+        trace!(?cache_key, held, "updating the pinning of the object blocks");
+        for block_idx in 0..object_size.div_ceil(self.config.block_size) {
+            usage.info.set_held(&DiskBlockKey::new(cache_key, block_idx), held);
+        }
+    }
+
+    /// Whether the given block belongs to an object pinned with [Self::pin_object].
+    fn is_held(&self, block_key: &DiskBlockKey) -> bool {
+        self.lock_usage()
+            .is_some_and(|usage| usage.info.held.contains(block_key))
+    }
+
+    /// Maximum total size of the pinned blocks evicted only after the others, for a cache of the given size.
+    fn max_pinned_size(&self, size: usize) -> usize {
+        match &self.config.pinning {
+            Some(pinning) => (pinning.max_ratio * size as f64) as usize,
+            None => 0,
+        }
+    }
+
+    fn remove_block(&self, block_key: &DiskBlockKey) {
+        let path_to_remove = self.get_path_for_block_key(block_key);
+        trace!("evicting block at {}", path_to_remove.display());
+        if let Err(remove_err) = fs::remove_file(&path_to_remove)
+            && remove_err.kind() != ErrorKind::NotFound
+        {
+            warn!("unable to evict block: {:?}", remove_err);
+        }
+    }
+}
+
+impl Drop for DiskDataCache {
+    fn drop(&mut self) {
+        if let Some(Ok(mut usage)) = self.usage.as_ref().map(|usage| usage.lock())
+            && let Err(error) = self.save_usage(&mut usage)
+        {
+            warn!(?error, "unable to save the cache usage state");
+        }
+    }
+}
+
+/// Read a saved [UsageState], if any.
+fn read_usage_state(path: &Path) -> Option<UsageState<DiskBlockKey>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return None,
+        Err(error) => {
+            warn!(?path, ?error, "unable to read the cache usage state, ignoring it");
+            return None;
+        }
+    };
+    bincode::decode_from_std_read(&mut file, bincode::config::standard())
+        .inspect_err(|error| warn!(?path, ?error, "unable to decode the cache usage state, ignoring it"))
+        .ok()
+}
+
+/// Save a [UsageState], replacing any previous one atomically.
+fn write_usage_state(path: &Path, state: &UsageState<DiskBlockKey>) -> io::Result<()> {
+    let directory = path.parent().expect("path should include the cache version directory");
+    let mut temp_file = tempfile::Builder::new()
+        .permissions(fs::Permissions::from_mode(0o600))
+        .tempfile_in(directory)?;
+    bincode::encode_into_std_write(state, &mut temp_file, bincode::config::standard()).map_err(io::Error::other)?;
+    _ = temp_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Current time in milliseconds since the Unix epoch, which blocks expire from.
+fn timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
 }
 
 /// Hash the cache key using its fields as well as the [CACHE_VERSION].
@@ -470,19 +1069,47 @@ impl DataCache for DiskDataCache {
         }
         let start = Instant::now();
         let block_key = DiskBlockKey::new(cache_key, block_idx);
+        if let Some(mut usage) = self.lock_usage()
+            && usage.remove_if_expired(&block_key, timestamp_millis())
+        {
+            trace!(?cache_key, block_idx, "cache block expired");
+            self.remove_block(&block_key);
+            metrics::histogram!(CACHE_GET_LATENCY, ATTR_CACHE => CACHE_DISK).record(start.elapsed().as_micros() as f64);
+            return Ok(None);
+        }
         let path = self.get_path_for_block_key(&block_key);
-        let result = match self.read_block(&path, cache_key, block_idx, block_offset) {
+        let result = match self
+            .read_block_with_backend(&path, cache_key, block_idx, block_offset)
+            .await
+        {
             Ok(None) => {
                 // Cache miss.
+                if self.config.persistent
+                    && let Some(mut usage) = self.lock_usage()
+                    && usage.unvalidated.remove(&block_key)
+                {
+                    // The block was removed while the cache wasn't used.
+                    usage.remove(&block_key);
+                }
                 Ok(None)
             }
-            Ok(Some(bytes)) => {
-                // Cache hit.
-                metrics::histogram!(CACHE_GET_IO_SIZE, ATTR_CACHE => CACHE_DISK).record(bytes.len() as f64);
-                if let Some(usage) = &self.usage {
-                    usage.lock().unwrap().refresh(&block_key);
+            Ok(Some(block)) => {
+                if let Some(mut usage) = self.lock_usage()
+                    && !usage.validate(&block_key, &block, self.is_pinned(cache_key), self.account_name())
+                {
+                    warn!(
+                        ?cache_key,
+                        block_idx, "cache block doesn't match the saved usage state, removing it"
+                    );
+                    drop(usage);
+                    self.remove_block(&block_key);
+                    metrics::histogram!(CACHE_GET_LATENCY, ATTR_CACHE => CACHE_DISK)
+                        .record(start.elapsed().as_micros() as f64);
+                    return Ok(None);
                 }
-                Ok(Some(bytes))
+                // Cache hit.
+                metrics::histogram!(CACHE_GET_IO_SIZE, ATTR_CACHE => CACHE_DISK).record(block.bytes.len() as f64);
+                Ok(Some(block.bytes))
             }
             Err(err) => {
                 // Invalid block.
@@ -508,15 +1135,27 @@ impl DataCache for DiskDataCache {
         let start = Instant::now();
         let bytes_len = bytes.len();
         let block_key = DiskBlockKey::new(&cache_key, block_idx);
+        if let Some(ghosts) = &self.ghosts
+            && !self.is_held(&block_key)
+            && !ghosts.lock().unwrap().admit(&block_key, start)
+        {
+            trace!(
+                ?cache_key,
+                block_idx, "block read for the first time is not admitted to disk cache"
+            );
+            return Ok(());
+        }
+        let pinned = self.is_pinned(&cache_key);
         let path = self.get_path_for_block_key(&block_key);
         trace!(?cache_key, ?path, "new block will be created in disk cache");
 
         // Capture the put operation result separately from metrics recording
         // to ensure we can record both success and error metrics consistently
-        let put_result = (|| -> DataCacheResult<()> {
+        let put_result = async {
             let block = DiskBlock::new(cache_key, block_idx, block_offset, bytes).map_err(|err| match err {
                 DiskBlockCreationError::IntegrityError(_e) => DataCacheError::InvalidBlockContent,
             })?;
+            let checksum = block.header.header_checksum;
 
             {
                 let eviction_start = Instant::now();
@@ -526,19 +1165,26 @@ impl DataCache for DiskDataCache {
                 result
             }?;
 
-            let result = self.write_block(&path, block);
+            let result = self.write_block_with_backend(&path, &block_key, block).await;
             let (temp_file, size) = result?;
 
-            if let Some(usage) = &self.usage {
-                let mut usage = usage.lock().unwrap();
+            if let Some(mut usage) = self.lock_usage() {
                 _ = temp_file.persist(path).map_err(|e| e.error)?;
-                usage.add(block_key, size);
+                usage.add(
+                    block_key,
+                    size,
+                    checksum,
+                    pinned,
+                    self.account_name(),
+                    timestamp_millis(),
+                );
             } else {
                 _ = temp_file.persist(path).map_err(|e| e.error)?;
             }
 
-            Ok(())
-        })();
+            Ok::<_, DataCacheError>(())
+        }
+        .await;
 
         if put_result.is_ok() {
             metrics::histogram!(CACHE_PUT_IO_SIZE, ATTR_CACHE => CACHE_DISK).record(bytes_len as f64);
@@ -558,7 +1204,7 @@ impl DataCache for DiskDataCache {
 /// An S3 key may be up to 1024 UTF-8 bytes long, which exceeds the maximum UNIX file name length.
 /// Instead, this key contains a hash of the S3 key and ETag to avoid the limit when used in paths.
 /// The risk of collisions is mitigated as we ignore blocks read that contain the wrong S3 key, etc..
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Encode, Decode)]
 struct DiskBlockKey {
     hashed_key: [u8; 32],
     block_index: BlockIndex,
@@ -590,66 +1236,454 @@ impl DiskBlockKey {
     }
 }
 
-/// Keeps track of entries usage and total size.
+/// Keeps track of entries usage and total size, and picks the entries to evict according to an
+/// [EvictionPolicy].
 struct UsageInfo<K> {
-    entries: LinkedHashMap<K, usize>,
+    policy: EvictionPolicy,
+    entries: HashMap<K, UsageEntry>,
+    /// Keys of the unpinned entries by eviction rank, the first one being the next to evict.
+    eviction_order: BTreeMap<(u64, u64), K>,
+    /// Keys of the pinned entries by eviction rank.
+    pinned_order: BTreeMap<(u64, u64), K>,
+    size: usize,
+    /// Total size of the pinned entries.
+    pinned_size: usize,
+    /// Usage of the accounts the entries are charged to, indexed by [UsageEntry::account].
+    accounts: Vec<AccountUsage<K>>,
+    /// Keys never evicted nor expired, which may not have an entry yet, see [DiskDataCache::pin_object].
+    held: HashSet<K>,
+    /// Logical clock ordering the insertions and accesses of entries.
+    clock: u64,
+}
+
+/// Usage of the entries charged to an account.
+struct AccountUsage<K> {
+    name: String,
     size: usize,
+    /// Keys of the entries of the account by pinning and eviction rank, the first one being the next to evict.
+    eviction_order: BTreeMap<(bool, (u64, u64)), K>,
+}
+
+/// Usage of a single entry.
+#[derive(Debug, Clone, Encode, Decode)]
+struct UsageEntry {
+    size: usize,
+    /// Number of times the entry was accessed since it was added
+    hits: u64,
+    /// Logical time the entry was added
+    added: u64,
+    /// Logical time the entry was last added or accessed
+    accessed: u64,
+    /// Time the entry was added, in milliseconds since the Unix epoch
+    added_at: u64,
+    /// Checksum of the header of the block, which covers the key, ETag and data checksum of the block
+    checksum: u32,
+    /// Whether the entry is only evicted after the unpinned ones, see [PinningConfig]
+    pinned: bool,
+    /// Index of the account the entry is charged to, see [CacheAccount]
+    account: Option<u32>,
+}
+
+/// The bookkeeping of a [UsageInfo], as saved to disk. Entries are kept independently of the
+/// eviction policy, which may change between restarts.
+#[derive(Debug, Encode, Decode)]
+struct UsageState<K> {
+    /// Generation of the state, which a [UsageJournal] applies to.
+    generation: u64,
+    clock: u64,
+    /// Names of the accounts the entries are charged to.
+    accounts: Vec<String>,
+    entries: Vec<(K, UsageEntry)>,
 }
 
 impl<K> UsageInfo<K>
 where
-    K: std::hash::Hash + Eq + std::fmt::Debug,
+    K: std::hash::Hash + Eq + Clone + std::fmt::Debug,
 {
-    fn new() -> Self {
+    fn new(policy: EvictionPolicy) -> Self {
         Self {
-            entries: LinkedHashMap::new(),
+            policy,
+            entries: HashMap::new(),
+            eviction_order: BTreeMap::new(),
+            pinned_order: BTreeMap::new(),
             size: 0,
+            pinned_size: 0,
+            accounts: Vec::new(),
+            held: HashSet::new(),
+            clock: 0,
+        }
+    }
+
+    /// Restore the bookkeeping from a saved [UsageState], keeping only the entries for which
+    /// `exists` returns `true`.
+    fn from_state(policy: EvictionPolicy, state: UsageState<K>, exists: impl Fn(&K) -> bool) -> Self {
+        let mut usage = Self::new(policy);
+        usage.clock = state.clock;
+        for name in state.accounts {
+            usage.account_index(&name);
+        }
+        for (key, entry) in state.entries.into_iter().filter(|(key, _)| exists(key)) {
+            usage.insert(key, entry);
+        }
+        usage
+    }
+
+    fn to_state(&self, generation: u64) -> UsageState<K> {
+        UsageState {
+            generation,
+            clock: self.clock,
+            accounts: self.accounts.iter().map(|account| account.name.clone()).collect(),
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry.clone()))
+                .collect(),
+        }
+    }
+
+    /// Rank of an entry in the eviction order. Clock values are unique, so ranks are too.
+    fn rank(&self, entry: &UsageEntry) -> (u64, u64) {
+        match self.policy {
+            EvictionPolicy::Lru => (entry.accessed, 0),
+            EvictionPolicy::Lfu => (entry.hits, entry.accessed),
+            EvictionPolicy::Fifo | EvictionPolicy::Ttl { .. } => (entry.added, 0),
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Add or replace a key with the given entry, and update the total size.
+    fn insert(&mut self, key: K, entry: UsageEntry) {
+        self.remove(&key);
+        let rank = self.rank(&entry);
+        let held = self.held.contains(&key);
+        if held {
+            // Held entries are not in any eviction order.
+        } else if entry.pinned {
+            self.pinned_order.insert(rank, key.clone());
+            self.pinned_size = self.pinned_size.saturating_add(entry.size);
+        } else {
+            self.eviction_order.insert(rank, key.clone());
+        }
+        if let Some(account) = entry.account.and_then(|index| self.accounts.get_mut(index as usize)) {
+            if !held {
+                account.eviction_order.insert((entry.pinned, rank), key.clone());
+            }
+            account.size = account.size.saturating_add(entry.size);
+        }
+        self.size = self.size.saturating_add(entry.size);
+        self.entries.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<UsageEntry> {
+        let entry = self.entries.remove(key)?;
+        let rank = self.rank(&entry);
+        let held = self.held.contains(key);
+        if held {
+            // Held entries are not in any eviction order.
+        } else if entry.pinned {
+            self.pinned_order.remove(&rank);
+            self.pinned_size = self.pinned_size.saturating_sub(entry.size);
+        } else {
+            self.eviction_order.remove(&rank);
+        }
+        if let Some(account) = entry.account.and_then(|index| self.accounts.get_mut(index as usize)) {
+            if !held {
+                account.eviction_order.remove(&(entry.pinned, rank));
+            }
+            account.size = account.size.saturating_sub(entry.size);
         }
+        self.size = self.size.saturating_sub(entry.size);
+        Some(entry)
     }
 
-    /// Refresh the given key if present, marking it as the most recently used.
+    /// Refresh the given key if present, recording an access to it.
     /// Returns `false` if the key is not in the cache.
     fn refresh(&mut self, key: &K) -> bool {
-        self.entries.get_refresh(key).is_some()
+        let Some(mut entry) = self.remove(key) else {
+            return false;
+        };
+        entry.hits += 1;
+        entry.accessed = self.tick();
+        self.insert(key.clone(), entry);
+        true
+    }
+
+    /// Add or replace a key, charged to `account` and added at `now` (in milliseconds since the Unix epoch), and update
+    /// the total size.
+    fn add(&mut self, key: K, size: usize, checksum: u32, pinned: bool, account: Option<&str>, now: u64) {
+        let account = account.map(|name| self.account_index(name));
+        let clock = self.tick();
+        let entry = UsageEntry {
+            size,
+            hits: 0,
+            added: clock,
+            accessed: clock,
+            added_at: now,
+            checksum,
+            pinned,
+            account,
+        };
+        self.insert(key, entry);
     }
 
-    /// Add or replace a key and update the total size.
-    fn add(&mut self, key: K, size: usize) {
-        if let Some(previous_size) = self.entries.insert(key, size) {
-            self.size = self.size.saturating_sub(previous_size);
+    /// Exempt a key from eviction and expiry if `held` is set, whether or not it has an entry yet, or stop doing so.
+    fn set_held(&mut self, key: &K, held: bool) {
+        let entry = self.remove(key);
+        if held {
+            self.held.insert(key.clone());
+        } else {
+            self.held.remove(key);
         }
+        if let Some(entry) = entry {
+            self.insert(key.clone(), entry);
+        }
+    }
 
-        self.size = self.size.saturating_add(size);
+    /// The index of the account with the given name, added if needed.
+    fn account_index(&mut self, name: &str) -> u32 {
+        let index = match self.accounts.iter().position(|account| account.name == name) {
+            Some(index) => index,
+            None => {
+                self.accounts.push(AccountUsage {
+                    name: name.to_owned(),
+                    size: 0,
+                    eviction_order: BTreeMap::new(),
+                });
+                self.accounts.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    /// Total size of the entries charged to the account with the given name.
+    fn account_size(&self, name: &str) -> usize {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .map_or(0, |account| account.size)
     }
 
-    /// Remove the least recently used key and update the total size.
+    /// Remove the next key of the account with the given name to evict according to the policy, its unpinned keys
+    /// first. Return `None` if the account has no keys.
+    fn evict_account(&mut self, name: &str) -> Option<K> {
+        let account = self.accounts.iter().find(|account| account.name == name)?;
+        let key = account.eviction_order.values().next()?.clone();
+        self.remove(&key);
+        Some(key)
+    }
+
+    /// Remove the next key to evict according to the policy and update the total size. Pinned keys are only evicted
+    /// when there are no other keys, or when their total size exceeds `max_pinned_size`.
     /// Return `None` if empty.
-    fn evict_lru(&mut self) -> Option<K> {
-        let (key, size) = self.entries.pop_front()?;
-        self.size = self.size.saturating_sub(size);
+    fn evict(&mut self, max_pinned_size: usize) -> Option<K> {
+        let order = if self.pinned_size > max_pinned_size || self.eviction_order.is_empty() {
+            &mut self.pinned_order
+        } else {
+            &mut self.eviction_order
+        };
+        let (_, key) = order.pop_first()?;
+        self.remove(&key);
         Some(key)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
-    use std::{ffi::OsString, io::Cursor};
+    fn is_expired(&self, entry: &UsageEntry, now: u64) -> bool {
+        match self.policy {
+            EvictionPolicy::Ttl { ttl } => now >= entry.added_at.saturating_add(ttl.as_millis() as u64),
+            _ => false,
+        }
+    }
 
-    use super::*;
+    /// Remove the oldest key if it expired at `now`. Pinned keys expire too.
+    fn evict_expired(&mut self, now: u64) -> Option<K> {
+        let oldest = [&self.eviction_order, &self.pinned_order]
+            .into_iter()
+            .filter_map(|order| order.first_key_value())
+            .min_by_key(|(rank, _)| **rank)
+            .map(|(_, key)| key.clone())?;
+        self.remove_if_expired(&oldest, now).then_some(oldest)
+    }
 
-    use futures::StreamExt as _;
-    use futures::executor::{ThreadPool, block_on};
-    use futures::task::SpawnExt;
-    use mountpoint_s3_client::types::ETag;
-    use rand::rngs::SmallRng;
-    use rand::{RngExt, SeedableRng};
-    use test_case::test_case;
+    /// Remove the given key if it expired at `now`. Returns `true` if it was removed.
+    fn remove_if_expired(&mut self, key: &K, now: u64) -> bool {
+        let expired =
+            !self.held.contains(key) && self.entries.get(key).is_some_and(|entry| self.is_expired(entry, now));
+        if expired {
+            self.remove(key);
+        }
+        expired
+    }
+}
 
-    use crate::sync::Arc;
+/// Usage of the blocks in the cache, along with the journal sharing it with other processes when
+/// the cache is shared.
+struct BlockUsage {
+    info: UsageInfo<DiskBlockKey>,
+    journal: Option<UsageJournal>,
+    /// Changes not yet appended to the journal.
+    pending: Vec<UsageRecord>,
+    /// Blocks restored from the saved usage state of a persistent cache, which weren't read since.
+    unvalidated: HashSet<DiskBlockKey>,
+}
 
-    #[test]
-    fn test_block_format_version_requires_update() {
+impl BlockUsage {
+    fn new(info: UsageInfo<DiskBlockKey>, journal: Option<UsageJournal>) -> Self {
+        Self {
+            info,
+            journal,
+            pending: Vec::new(),
+            unvalidated: HashSet::new(),
+        }
+    }
+
+    /// Record an access to a block read from the cache directory. Returns `false` if the block was restored from the
+    /// saved usage state and doesn't match it, in which case it is removed from the usage.
+    ///
+    /// Blocks that aren't tracked, for example because a previous instance didn't save its usage state before exiting,
+    /// are added to the usage.
+    fn validate(&mut self, key: &DiskBlockKey, block: &CachedBlock, pinned: bool, account: Option<&str>) -> bool {
+        if self.unvalidated.remove(key)
+            && self
+                .info
+                .entries
+                .get(key)
+                .is_some_and(|entry| entry.checksum != block.checksum)
+        {
+            self.remove(key);
+            return false;
+        }
+        if !self.refresh(key) {
+            self.add(*key, block.size, block.checksum, pinned, account, timestamp_millis());
+        }
+        true
+    }
+
+    fn record(&mut self, record: UsageRecord) {
+        if self.journal.is_some() {
+            self.pending.push(record);
+        }
+    }
+
+    /// See [UsageInfo::refresh].
+    fn refresh(&mut self, key: &DiskBlockKey) -> bool {
+        let refreshed = self.info.refresh(key);
+        if refreshed {
+            self.record(UsageRecord::Access { key: *key });
+        }
+        refreshed
+    }
+
+    /// See [UsageInfo::add].
+    fn add(&mut self, key: DiskBlockKey, size: usize, checksum: u32, pinned: bool, account: Option<&str>, now: u64) {
+        self.info.add(key, size, checksum, pinned, account, now);
+        self.unvalidated.remove(&key);
+        self.record(UsageRecord::Add {
+            key,
+            size,
+            checksum,
+            pinned,
+            account: account.map(str::to_owned),
+            added_at: now,
+        });
+    }
+
+    /// See [UsageInfo::remove].
+    fn remove(&mut self, key: &DiskBlockKey) {
+        if self.info.remove(key).is_some() {
+            self.record(UsageRecord::Remove { key: *key });
+        }
+    }
+
+    /// See [UsageInfo::evict].
+    fn evict(&mut self, max_pinned_size: usize) -> Option<DiskBlockKey> {
+        let key = self.info.evict(max_pinned_size)?;
+        self.record(UsageRecord::Remove { key });
+        Some(key)
+    }
+
+    /// See [UsageInfo::evict_account].
+    fn evict_account(&mut self, name: &str) -> Option<DiskBlockKey> {
+        let key = self.info.evict_account(name)?;
+        self.record(UsageRecord::Remove { key });
+        Some(key)
+    }
+
+    /// See [UsageInfo::evict_expired].
+    fn evict_expired(&mut self, now: u64) -> Option<DiskBlockKey> {
+        let key = self.info.evict_expired(now)?;
+        self.record(UsageRecord::Remove { key });
+        Some(key)
+    }
+
+    /// See [UsageInfo::remove_if_expired].
+    fn remove_if_expired(&mut self, key: &DiskBlockKey, now: u64) -> bool {
+        let removed = self.info.remove_if_expired(key, now);
+        if removed {
+            self.record(UsageRecord::Remove { key: *key });
+        }
+        removed
+    }
+}
+
+/// Lock on the [BlockUsage] of a cache, returned by [DiskDataCache::lock_usage]. When dropped,
+/// the changes made through it are appended to the shared journal, if any.
+struct UsageGuard<'a> {
+    usage: MutexGuard<'a, BlockUsage>,
+    /// Whether the journal is locked and up to date.
+    synced: bool,
+}
+
+impl Deref for UsageGuard<'_> {
+    type Target = BlockUsage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.usage
+    }
+}
+
+impl DerefMut for UsageGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.usage
+    }
+}
+
+impl Drop for UsageGuard<'_> {
+    fn drop(&mut self) {
+        let BlockUsage {
+            info, journal, pending, ..
+        } = &mut *self.usage;
+        if self.synced
+            && let Some(journal) = journal
+            && let Err(error) = journal.finish(pending, info)
+        {
+            warn!(?error, "unable to write to the shared usage journal");
+        }
+        pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::{ffi::OsString, io::Cursor};
+
+    use super::*;
+
+    use futures::StreamExt as _;
+    use futures::executor::{ThreadPool, block_on};
+    use futures::task::SpawnExt;
+    use mountpoint_s3_client::types::ETag;
+    use rand::rngs::SmallRng;
+    use rand::{RngExt, SeedableRng};
+    use test_case::test_case;
+
+    use crate::sync::Arc;
+
+    #[test]
+    fn test_block_format_version_requires_update() {
         let cache_key = ObjectId::new("hello-world".to_string(), ETag::for_tests());
         let data = ChecksummedBytes::new("Foo".into());
         let block = DiskBlock::new(cache_key, 100, 100 * 10, data).expect("should succeed as data checksum is valid");
@@ -659,7 +1693,7 @@ mod tests {
             108, 100, 9, 85, 128, 46, 13, 202, 106, 46, 70, 111, 111,
         ];
         let mut serialized_bytes = Vec::new();
-        block.write(&mut serialized_bytes).unwrap();
+        block.write(&mut serialized_bytes, None).unwrap();
         assert_eq!(
             expected_bytes, serialized_bytes,
             "serialized disk format appears to have changed, version bump required"
@@ -685,6 +1719,15 @@ mod tests {
                 cache_directory: cache_dir,
                 block_size: 1024,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
@@ -699,6 +1742,7 @@ mod tests {
         let expected = vec![
             "mountpoint-cache",
             CACHE_VERSION,
+            "blocks-1024",
             split_hashed_key.0,
             split_hashed_key.1,
             "0000000005",
@@ -717,6 +1761,15 @@ mod tests {
                 cache_directory: cache_dir,
                 block_size: 1024,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
@@ -731,6 +1784,7 @@ mod tests {
         let expected = vec![
             "mountpoint-cache",
             CACHE_VERSION,
+            "blocks-1024",
             split_hashed_key.0,
             split_hashed_key.1,
             "1000000000000000",
@@ -740,6 +1794,282 @@ mod tests {
         assert_eq!(expected, results);
     }
 
+    #[tokio::test]
+    async fn test_second_read_admission() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let cache = DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.path().to_path_buf(),
+                block_size: 1024,
+                limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: AdmissionPolicy::SecondRead {
+                    window: Duration::from_secs(3600),
+                },
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            PagedPool::new_with_candidate_sizes([1024]),
+        );
+        let cache_key = ObjectId::new("a".into(), ETag::for_tests());
+        let data = ChecksummedBytes::new("Foo".into());
+
+        cache.put_block(cache_key.clone(), 0, 0, data.clone(), 3).await.unwrap();
+        assert_eq!(
+            cache.get_block(&cache_key, 0, 0, 3).await.unwrap(),
+            None,
+            "blocks read once are not written"
+        );
+        cache.put_block(cache_key.clone(), 0, 0, data.clone(), 3).await.unwrap();
+        assert_eq!(cache.get_block(&cache_key, 0, 0, 3).await.unwrap(), Some(data));
+    }
+
+    #[tokio::test]
+    async fn test_mixed_block_sizes() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let new_cache = |block_size| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size,
+                    limit: CacheLimit::TotalSize { max_size: 1024 * 1024 },
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: true,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            )
+        };
+        let cache_key = ObjectId::new("a".into(), ETag::for_tests());
+        let data = ChecksummedBytes::new("Foo".into());
+
+        let small_blocks = new_cache(4);
+        let large_blocks = new_cache(1024);
+        small_blocks
+            .put_block(cache_key.clone(), 0, 0, data.clone(), 8)
+            .await
+            .unwrap();
+        assert_eq!(
+            large_blocks.get_block(&cache_key, 0, 0, 8).await.unwrap(),
+            None,
+            "blocks of another size are not visible"
+        );
+        large_blocks
+            .put_block(cache_key.clone(), 0, 0, data.clone(), 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            small_blocks.get_block(&cache_key, 0, 0, 8).await.unwrap(),
+            Some(data.clone())
+        );
+        assert_eq!(large_blocks.get_block(&cache_key, 0, 0, 3).await.unwrap(), Some(data));
+    }
+
+    #[tokio::test]
+    async fn test_encryption() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let new_cache = |encryption_key| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::Unbounded,
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            )
+        };
+        let key = CacheEncryptionKey::ephemeral();
+        let cache = new_cache(Some(key.clone()));
+        let cache_key = ObjectId::new("secret/a".into(), ETag::for_tests());
+        let data = ChecksummedBytes::new("Foo".into());
+        cache.put_block(cache_key.clone(), 0, 0, data.clone(), 3).await.unwrap();
+        assert_eq!(cache.get_block(&cache_key, 0, 0, 3).await.unwrap(), Some(data));
+
+        let path = cache.get_path_for_block_key(&DiskBlockKey::new(&cache_key, 0));
+        let content = fs::read(&path).unwrap();
+        assert!(content.starts_with(ENCRYPTED_CACHE_VERSION.as_bytes()));
+        for plaintext in [b"Foo".as_slice(), b"secret/a"] {
+            assert!(
+                !content.windows(plaintext.len()).any(|window| window == plaintext),
+                "neither the data nor the key of the block are written unencrypted"
+            );
+        }
+
+        assert!(new_cache(Some(key)).get_block(&cache_key, 0, 0, 3).await.is_ok());
+        new_cache(Some(CacheEncryptionKey::ephemeral()))
+            .get_block(&cache_key, 0, 0, 3)
+            .await
+            .expect_err("blocks encrypted with another key are invalid");
+        new_cache(None)
+            .get_block(&cache_key, 0, 0, 3)
+            .await
+            .expect_err("encrypted blocks are invalid without encryption");
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let new_cache = |encryption_key, compression_level| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::Unbounded,
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key,
+                    compression_level,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            )
+        };
+        let cache = new_cache(None, Some(DEFAULT_CACHE_COMPRESSION_LEVEL));
+        let text = ObjectId::new("logs/a".into(), ETag::for_tests());
+        let text_data = ChecksummedBytes::new("{\"level\":\"info\"}\n".repeat(64).into_bytes()[..1024].to_vec().into());
+        let short = ObjectId::new("short".into(), ETag::for_tests());
+        let short_data = ChecksummedBytes::new("Foo".into());
+        cache
+            .put_block(text.clone(), 0, 0, text_data.clone(), 1024)
+            .await
+            .unwrap();
+        cache
+            .put_block(short.clone(), 0, 0, short_data.clone(), 3)
+            .await
+            .unwrap();
+
+        let text_content = fs::read(cache.get_path_for_block_key(&DiskBlockKey::new(&text, 0))).unwrap();
+        assert!(text_content.starts_with(COMPRESSED_CACHE_VERSION.as_bytes()));
+        assert!(text_content.len() < 512, "compressible blocks are smaller on disk");
+        let short_content = fs::read(cache.get_path_for_block_key(&DiskBlockKey::new(&short, 0))).unwrap();
+        assert!(
+            short_content.starts_with(CACHE_VERSION.as_bytes()),
+            "blocks that don't get smaller are written uncompressed"
+        );
+
+        for cache in [cache, new_cache(None, None)] {
+            assert_eq!(
+                cache.get_block(&text, 0, 0, 1024).await.unwrap(),
+                Some(text_data.clone()),
+                "compressed blocks are read whether or not compression is enabled"
+            );
+            assert_eq!(
+                cache.get_block(&short, 0, 0, 3).await.unwrap(),
+                Some(short_data.clone())
+            );
+        }
+
+        let key = CacheEncryptionKey::ephemeral();
+        let encrypted_cache = new_cache(Some(key), Some(DEFAULT_CACHE_COMPRESSION_LEVEL));
+        encrypted_cache
+            .put_block(text.clone(), 0, 0, text_data.clone(), 1024)
+            .await
+            .unwrap();
+        let content = fs::read(encrypted_cache.get_path_for_block_key(&DiskBlockKey::new(&text, 0))).unwrap();
+        assert!(content.starts_with(ENCRYPTED_COMPRESSED_CACHE_VERSION.as_bytes()));
+        assert_eq!(
+            encrypted_cache.get_block(&text, 0, 0, 1024).await.unwrap(),
+            Some(text_data)
+        );
+        new_cache(None, None)
+            .get_block(&text, 0, 0, 1024)
+            .await
+            .expect_err("compressed encrypted blocks are invalid without encryption");
+    }
+
+    #[test_case(false; "buffered")]
+    #[test_case(true; "direct")]
+    #[tokio::test]
+    async fn test_io_uring(direct: bool) {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let new_cache = |io_backend, encryption_key, compression_level| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::Unbounded,
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key,
+                    compression_level,
+                    io_backend,
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            )
+        };
+        let io_uring = DiskIoBackend::IoUring { direct };
+        let key = CacheEncryptionKey::ephemeral();
+        let data = ChecksummedBytes::new("Foo".repeat(300).into_bytes().into());
+        for (i, (encryption_key, compression_level)) in [
+            (None, None),
+            (None, Some(DEFAULT_CACHE_COMPRESSION_LEVEL)),
+            (Some(key.clone()), None),
+            (Some(key.clone()), Some(DEFAULT_CACHE_COMPRESSION_LEVEL)),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let cache_key = ObjectId::new(format!("key{i}"), ETag::for_tests());
+            let cache = new_cache(io_uring, encryption_key.clone(), compression_level);
+            cache
+                .put_block(cache_key.clone(), 0, 0, data.clone(), 900)
+                .await
+                .unwrap();
+            assert_eq!(
+                cache.get_block(&cache_key, 0, 0, 900).await.unwrap(),
+                Some(data.clone())
+            );
+            assert!(
+                cache.get_block(&cache_key, 1, 1024, 900).await.unwrap().is_none(),
+                "missing blocks are not found"
+            );
+
+            // Blocks are interchangeable between the I/O backends.
+            let sync_cache = new_cache(DiskIoBackend::Sync, encryption_key, compression_level);
+            assert_eq!(
+                sync_cache.get_block(&cache_key, 0, 0, 900).await.unwrap(),
+                Some(data.clone())
+            );
+            let other_key = ObjectId::new(format!("other{i}"), ETag::for_tests());
+            sync_cache
+                .put_block(other_key.clone(), 0, 0, data.clone(), 900)
+                .await
+                .unwrap();
+            assert_eq!(
+                cache.get_block(&other_key, 0, 0, 900).await.unwrap(),
+                Some(data.clone())
+            );
+        }
+    }
+
     #[test_case(8 * 1024 * 1024, 8 * 1024 * 1024; "matching block and pool buffer sizes")]
     #[test_case(1024 * 1024, 8 * 1024 * 1024; "block size smaller than pool buffer size")]
     #[test_case(8 * 1024 * 1024, 1024 * 1024; "block size larger than pool buffer size")]
@@ -759,6 +2089,15 @@ mod tests {
                 cache_directory: cache_directory.path().to_path_buf(),
                 block_size,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
@@ -846,6 +2185,15 @@ mod tests {
                 cache_directory: cache_directory.path().to_path_buf(),
                 block_size: 8 * 1024 * 1024,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
@@ -867,6 +2215,61 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_watermarks_limit() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let new_cache = |low_ratio, high_ratio, max_size| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::Watermarks {
+                        low_ratio,
+                        high_ratio,
+                        max_size,
+                    },
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            )
+        };
+
+        let cache = new_cache(0.0, 1.0, None);
+        assert!(
+            !cache.is_limit_exceeded(0, false),
+            "the cache grows while more space than the low watermark is available"
+        );
+        assert!(
+            cache.is_limit_exceeded(0, true),
+            "blocks are evicted until the high watermark is reached"
+        );
+        assert!(new_cache(1.0, 1.0, None).is_limit_exceeded(0, false));
+        let cache = new_cache(0.0, 0.0, Some(1024));
+        assert!(!cache.is_limit_exceeded(1024, false));
+        assert!(cache.is_limit_exceeded(1025, false));
+
+        // Eviction stops once the cache is empty, even if the high watermark isn't reached.
+        let cache = new_cache(0.0, 1.0, Some(1));
+        let keys: Vec<_> = ["a", "b"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+        let data = ChecksummedBytes::new("Foo".into());
+        for key in &keys {
+            cache.put_block(key.clone(), 0, 0, data.clone(), 3).await.unwrap();
+        }
+        assert_eq!(cache.get_block(&keys[0], 0, 0, 3).await.unwrap(), None);
+        assert_eq!(cache.get_block(&keys[1], 0, 0, 3).await.unwrap(), Some(data));
+    }
+
     #[tokio::test]
     async fn test_eviction() {
         const BLOCK_SIZE: usize = 100 * 1024;
@@ -928,6 +2331,15 @@ mod tests {
                 cache_directory: cache_directory.path().to_path_buf(),
                 block_size: BLOCK_SIZE as u64,
                 limit: CacheLimit::TotalSize { max_size: CACHE_LIMIT },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
@@ -984,6 +2396,544 @@ mod tests {
         );
     }
 
+    #[test_case(EvictionPolicy::Lru, &["b", "a", "c"]; "lru")]
+    #[test_case(EvictionPolicy::Lfu, &["b", "c", "a"]; "lfu")]
+    #[test_case(EvictionPolicy::Fifo, &["a", "b", "c"]; "fifo")]
+    fn test_eviction_order(policy: EvictionPolicy, expected: &[&str]) {
+        let mut usage = UsageInfo::new(policy);
+        for key in ["a", "b", "c"] {
+            usage.add(key, 10, 0, false, None, 0);
+        }
+        for key in ["a", "a", "c"] {
+            assert!(usage.refresh(&key));
+        }
+        assert!(!usage.refresh(&"d"));
+        assert_eq!(usage.size, 30);
+
+        let evicted: Vec<_> = std::iter::from_fn(|| usage.evict(0)).collect();
+        assert_eq!(evicted, expected);
+        assert_eq!(usage.size, 0);
+    }
+
+    #[test]
+    fn test_pinned_eviction_order() {
+        let mut usage = UsageInfo::new(EvictionPolicy::Lru);
+        usage.add("pinned-a", 10, 0, true, None, 0);
+        usage.add("pinned-b", 10, 0, true, None, 0);
+        usage.add("c", 10, 0, false, None, 0);
+        usage.add("d", 10, 0, false, None, 0);
+        assert_eq!(usage.pinned_size, 20);
+
+        assert_eq!(usage.evict(20), Some("c"), "unpinned keys are evicted first");
+        assert_eq!(
+            usage.evict(15),
+            Some("pinned-a"),
+            "pinned keys over the limit are evicted"
+        );
+        assert_eq!(usage.evict(15), Some("d"));
+        assert_eq!(
+            usage.evict(15),
+            Some("pinned-b"),
+            "pinned keys are evicted when no others are left"
+        );
+        assert_eq!(usage.evict(15), None);
+        assert_eq!((usage.size, usage.pinned_size), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_blocks() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let data = ChecksummedBytes::new("Foo".into());
+        let block_size = {
+            let probe = DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().join("probe"),
+                    block_size: 1024,
+                    limit: CacheLimit::TotalSize { max_size: 1024 * 1024 },
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                PagedPool::new_with_candidate_sizes([1024]),
+            );
+            let key = ObjectId::new("probe".into(), ETag::for_tests());
+            probe.put_block(key, 0, 0, data.clone(), 3).await.unwrap();
+            probe.usage.as_ref().unwrap().lock().unwrap().info.size
+        };
+        // Room for 3 blocks, of which at most half may be pinned.
+        let cache = DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.path().join("cache"),
+                block_size: 1024,
+                limit: CacheLimit::TotalSize {
+                    max_size: 3 * block_size,
+                },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: Some(PinningConfig {
+                    prefixes: vec!["reference/".to_owned()],
+                    max_ratio: 0.5,
+                }),
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            PagedPool::new_with_candidate_sizes([1024]),
+        );
+        let pinned = ObjectId::new("reference/a".into(), ETag::for_tests());
+        cache.put_block(pinned.clone(), 0, 0, data.clone(), 3).await.unwrap();
+        for index in 0..10 {
+            let key = ObjectId::new(format!("scan/{index}"), ETag::for_tests());
+            cache.put_block(key, 0, 0, data.clone(), 3).await.unwrap();
+        }
+        assert_eq!(
+            cache.get_block(&pinned, 0, 0, 3).await.unwrap(),
+            Some(data),
+            "pinned blocks are kept while other blocks are evicted"
+        );
+        let first_scanned = ObjectId::new("scan/0".into(), ETag::for_tests());
+        assert_eq!(cache.get_block(&first_scanned, 0, 0, 3).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_held_entries() {
+        let mut usage = UsageInfo::new(EvictionPolicy::Ttl {
+            ttl: Duration::from_secs(1),
+        });
+        usage.set_held(&"held-a", true);
+        usage.add("held-a", 10, 0, false, None, 0);
+        usage.add("held-b", 10, 0, true, None, 0);
+        usage.set_held(&"held-b", true);
+        usage.add("c", 10, 0, false, None, 0);
+        assert_eq!(usage.size, 30);
+
+        assert_eq!(usage.evict_expired(1000), Some("c"), "held keys don't expire");
+        assert_eq!(usage.evict_expired(1000), None);
+        assert!(!usage.remove_if_expired(&"held-a", 1000));
+        assert_eq!(usage.evict(0), None, "held keys are not evicted");
+        assert_eq!(usage.size, 20);
+
+        usage.set_held(&"held-b", false);
+        assert_eq!(usage.pinned_size, 10);
+        assert_eq!(usage.evict(0), Some("held-b"), "released keys are evicted again");
+        assert_eq!(usage.evict(0), None);
+    }
+
+    #[tokio::test]
+    async fn test_pin_object() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let block_size = 1024;
+        let object_size = 2 * block_size as usize;
+        let data = ChecksummedBytes::new(vec![0xaa; block_size as usize].into());
+        // Not quite room for 2 blocks with their headers, and blocks are only written when read twice.
+        let cache = DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.path().to_path_buf(),
+                block_size,
+                limit: CacheLimit::TotalSize {
+                    max_size: 2 * block_size as usize,
+                },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: AdmissionPolicy::SecondRead {
+                    window: Duration::from_secs(600),
+                },
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            PagedPool::new_with_candidate_sizes([block_size as usize]),
+        );
+        let pinned = ObjectId::new("pinned".into(), ETag::for_tests());
+        cache.pin_object(&pinned, object_size as u64);
+        for block_idx in 0..2 {
+            let offset = block_idx * block_size;
+            cache
+                .put_block(pinned.clone(), block_idx, offset, data.clone(), object_size)
+                .await
+                .unwrap();
+        }
+        let get_pinned = async |block_idx| {
+            cache
+                .get_block(&pinned, block_idx, block_idx * block_size, object_size)
+                .await
+                .unwrap()
+        };
+        assert!(get_pinned(1).await.is_some(), "blocks of pinned objects are admitted");
+
+        let other = ObjectId::new("other".into(), ETag::for_tests());
+        let put_other = async || {
+            cache
+                .put_block(other.clone(), 0, 0, data.clone(), block_size as usize)
+                .await
+        };
+        put_other().await.expect("blocks read once are not admitted");
+        put_other().await.expect_err("the cache is full of pinned blocks");
+        assert!(
+            get_pinned(0).await.is_some(),
+            "blocks of pinned objects are not evicted"
+        );
+        assert!(
+            get_pinned(1).await.is_some(),
+            "blocks of pinned objects are not evicted"
+        );
+
+        cache.unpin_object(&pinned, object_size as u64);
+        put_other().await.unwrap();
+        put_other().await.unwrap();
+        assert!(get_pinned(0).await.is_none(), "blocks of unpinned objects are evicted");
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let mut usage = UsageInfo::new(EvictionPolicy::Ttl {
+            ttl: Duration::from_secs(1),
+        });
+        usage.add("a", 10, 0, false, None, 0);
+        usage.add("b", 10, 0, false, None, 500);
+        assert!(usage.refresh(&"a"), "accesses don't extend the TTL");
+
+        assert_eq!(usage.evict_expired(999), None);
+        assert_eq!(usage.evict_expired(1000), Some("a"));
+        assert_eq!(usage.evict_expired(1000), None);
+        assert!(!usage.remove_if_expired(&"b", 1499));
+        assert!(usage.remove_if_expired(&"b", 1500));
+        assert_eq!(usage.size, 0);
+
+        let mut usage = UsageInfo::new(EvictionPolicy::Lru);
+        usage.add("a", 10, 0, false, None, 0);
+        assert_eq!(
+            usage.evict_expired(u64::MAX),
+            None,
+            "only the TTL policy expires entries"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_survives_restart() {
+        const BLOCK_SIZE: u64 = 1024;
+        let cache_directory = tempfile::tempdir().unwrap();
+        let pool = PagedPool::new_with_candidate_sizes([BLOCK_SIZE as usize]);
+        let new_cache = |eviction_policy| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: BLOCK_SIZE,
+                    limit: CacheLimit::TotalSize { max_size: 1024 * 1024 },
+                    eviction_policy,
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                pool.clone(),
+            )
+        };
+        let keys: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+
+        let cache = new_cache(EvictionPolicy::Lfu);
+        for key in &keys {
+            let bytes = ChecksummedBytes::new("Foo".into());
+            cache.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        for key in [&keys[0], &keys[0], &keys[2]] {
+            let _ = cache
+                .get_block(key, 0, 0, 3)
+                .await
+                .unwrap()
+                .expect("block is in the cache");
+        }
+        let size = cache.usage.as_ref().unwrap().lock().unwrap().info.size;
+        drop(cache);
+
+        let cache = new_cache(EvictionPolicy::Lfu);
+        let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+        assert_eq!(usage.info.size, size);
+        let eviction_order: Vec<_> = usage.info.eviction_order.values().copied().collect();
+        let expected: Vec<_> = [&keys[1], &keys[2], &keys[0]]
+            .into_iter()
+            .map(|key| DiskBlockKey::new(key, 0))
+            .collect();
+        assert_eq!(eviction_order, expected, "eviction order is restored");
+        drop(usage);
+        drop(cache);
+
+        // Blocks removed from the cache directory while it wasn't used are forgotten.
+        let cache = new_cache(EvictionPolicy::Lru);
+        let removed = DiskBlockKey::new(&keys[0], 0);
+        fs::remove_file(cache.get_path_for_block_key(&removed)).unwrap();
+        drop(cache);
+        let cache = new_cache(EvictionPolicy::Lru);
+        let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+        assert_eq!(usage.info.entries.len(), 2);
+        assert!(!usage.info.entries.contains_key(&removed));
+    }
+
+    #[tokio::test]
+    async fn test_persistent_cache() {
+        const BLOCK_SIZE: u64 = 1024;
+        let cache_directory = tempfile::tempdir().unwrap();
+        let pool = PagedPool::new_with_candidate_sizes([BLOCK_SIZE as usize]);
+        let new_cache = |limit| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: BLOCK_SIZE,
+                    limit,
+                    eviction_policy: EvictionPolicy::Lru,
+                    shared: false,
+                    persistent: true,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                pool.clone(),
+            )
+        };
+        let bounded = || CacheLimit::TotalSize { max_size: 1024 * 1024 };
+        let keys: Vec<_> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+        let block_keys: Vec<_> = keys.iter().map(|key| DiskBlockKey::new(key, 0)).collect();
+
+        let cache = new_cache(bounded());
+        for key in &keys[..3] {
+            let bytes = ChecksummedBytes::new("Foo".into());
+            cache.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        drop(cache);
+
+        // Change the cache directory while it isn't used: remove "b", replace "c", and add "d" without tracking it.
+        fs::remove_file(new_cache(bounded()).get_path_for_block_key(&block_keys[1])).unwrap();
+        let untracked = new_cache(CacheLimit::Unbounded);
+        for key in &keys[2..] {
+            let bytes = ChecksummedBytes::new("Bar".into());
+            untracked.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        drop(untracked);
+
+        let cache = new_cache(bounded());
+        {
+            let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+            assert_eq!(
+                usage.info.entries.len(),
+                3,
+                "blocks aren't checked when the cache is created"
+            );
+            assert_eq!(usage.unvalidated.len(), 3);
+        }
+
+        let get = |key| block_on(cache.get_block(key, 0, 0, 3)).unwrap();
+        assert_eq!(get(&keys[0]).unwrap(), ChecksummedBytes::new("Foo".into()));
+        assert!(get(&keys[1]).is_none(), "removed blocks are misses");
+        assert!(
+            get(&keys[2]).is_none(),
+            "blocks not matching the usage state are misses"
+        );
+        assert!(!cache.get_path_for_block_key(&block_keys[2]).exists());
+        assert_eq!(get(&keys[3]).unwrap(), ChecksummedBytes::new("Bar".into()));
+
+        let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+        assert!(usage.unvalidated.is_empty());
+        let mut tracked: Vec<_> = usage.info.entries.keys().copied().collect();
+        tracked.sort_by_key(|key| block_keys.iter().position(|block_key| block_key == key));
+        assert_eq!(
+            tracked,
+            [block_keys[0], block_keys[3]],
+            "untracked blocks are tracked once read"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_cache() {
+        let keys: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let new_cache = |cache_directory: &Path, max_size| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::TotalSize { max_size },
+                    eviction_policy: EvictionPolicy::Lru,
+                    shared: true,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                },
+                pool.clone(),
+            )
+        };
+
+        // Measure the size of a block on disk, to fit the cache limit to two blocks.
+        let probe_directory = tempfile::tempdir().unwrap();
+        let probe = new_cache(probe_directory.path(), usize::MAX);
+        let bytes = ChecksummedBytes::new("Foo".into());
+        probe.put_block(keys[0].clone(), 0, 0, bytes, 3).await.unwrap();
+        let block_size = probe.usage.as_ref().unwrap().lock().unwrap().info.size;
+
+        let cache_directory = tempfile::tempdir().unwrap();
+        let first = new_cache(cache_directory.path(), 2 * block_size - 1);
+        let second = new_cache(cache_directory.path(), 2 * block_size - 1);
+        for key in &keys[..2] {
+            let bytes = ChecksummedBytes::new("Foo".into());
+            first.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        let block = second.get_block(&keys[0], 0, 0, 3).await.unwrap();
+        assert!(block.is_some(), "blocks written by one cache are read by the other");
+
+        // The second cache evicts the block the first one wrote and nobody accessed since.
+        let bytes = ChecksummedBytes::new("Foo".into());
+        second.put_block(keys[2].clone(), 0, 0, bytes, 3).await.unwrap();
+        let cached: Vec<_> = futures::stream::iter(&keys)
+            .then(|key| async { first.get_block(key, 0, 0, 3).await.unwrap().is_some() })
+            .collect()
+            .await;
+        assert_eq!(cached, [true, false, true]);
+        for cache in [&first, &second] {
+            let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+            assert_eq!(usage.info.size, 2 * block_size, "both caches track all the blocks");
+        }
+
+        // The shared bookkeeping is restored by new caches.
+        drop(first);
+        let third = new_cache(cache_directory.path(), 2 * block_size - 1);
+        assert_eq!(third.usage.as_ref().unwrap().lock().unwrap().info.size, 2 * block_size);
+    }
+
+    #[tokio::test]
+    async fn test_account_quota() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let new_cache = |name: &str, quota| {
+            DiskDataCache::new(
+                DiskDataCacheConfig {
+                    cache_directory: cache_directory.path().to_path_buf(),
+                    block_size: 1024,
+                    limit: CacheLimit::Unbounded,
+                    eviction_policy: EvictionPolicy::Lru,
+                    shared: true,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: None,
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: Some(CacheAccount {
+                        name: name.to_owned(),
+                        quota,
+                    }),
+                },
+                pool.clone(),
+            )
+        };
+        let size_of =
+            |cache: &DiskDataCache, name| cache.usage.as_ref().unwrap().lock().unwrap().info.account_size(name);
+        let keys: Vec<_> = (0..4)
+            .map(|i| ObjectId::new(format!("key{i}"), ETag::for_tests()))
+            .collect();
+
+        let other = new_cache("other", None);
+        for key in &keys[..2] {
+            let bytes = ChecksummedBytes::new("Foo".into());
+            other.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        let block_size = size_of(&other, "other") / 2;
+
+        let limited = new_cache("limited", Some(block_size));
+        for key in &keys[2..] {
+            let bytes = ChecksummedBytes::new("Foo".into());
+            limited.put_block(key.clone(), 0, 0, bytes, 3).await.unwrap();
+        }
+        let bytes = ChecksummedBytes::new("Foo".into());
+        limited.put_block(keys[2].clone(), 1, 1024, bytes, 1027).await.unwrap();
+        let cached: Vec<_> = futures::stream::iter(&keys)
+            .then(|key| async { other.get_block(key, 0, 0, 3).await.unwrap().is_some() })
+            .collect()
+            .await;
+        assert_eq!(
+            cached,
+            [true, true, false, true],
+            "an account over its quota evicts its own blocks only"
+        );
+        assert_eq!(size_of(&limited, "other"), 2 * block_size);
+        assert_eq!(size_of(&limited, "limited"), 2 * block_size);
+        assert_eq!(size_of(&other, "limited"), 2 * block_size, "the accounts are shared");
+
+        // Blocks read by another account stay charged to the account that wrote them.
+        assert!(limited.get_block(&keys[0], 0, 0, 3).await.unwrap().is_some());
+        assert_eq!(size_of(&limited, "other"), 2 * block_size);
+
+        // The accounts are restored by new caches.
+        drop(other);
+        let restored = new_cache("other", None);
+        assert_eq!(size_of(&restored, "other"), 2 * block_size);
+        assert_eq!(size_of(&restored, "limited"), 2 * block_size);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_eviction_policy() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let cache = DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.path().to_path_buf(),
+                block_size: 1024,
+                limit: CacheLimit::Unbounded,
+                eviction_policy: EvictionPolicy::Ttl { ttl: Duration::ZERO },
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            pool,
+        );
+        let cache_key = ObjectId::new("a".into(), ETag::for_tests());
+        cache
+            .put_block(cache_key.clone(), 0, 0, ChecksummedBytes::new("Foo".into()), 3)
+            .await
+            .unwrap();
+        let path = cache.get_path_for_block_key(&DiskBlockKey::new(&cache_key, 0));
+        assert!(path.exists());
+
+        let block = cache.get_block(&cache_key, 0, 0, 3).await.unwrap();
+        assert!(block.is_none(), "expired blocks are not returned");
+        assert!(!path.exists(), "expired blocks are removed");
+    }
+
     #[test]
     fn data_block_extract_checks() {
         let data_1 = ChecksummedBytes::new("Foo".into());
@@ -1085,7 +3035,7 @@ mod tests {
         let block = DiskBlock::new(cache_key.clone(), 0, 0, data).expect("should have no checksum err");
 
         let mut buf = Vec::new();
-        block.write(&mut buf).unwrap();
+        block.write(&mut buf, None).unwrap();
 
         // Determine the offset and expected value for the length field under test.
         // These values depends on the serialization format for `DiskBlock` and `DiskBlockHeader`.
@@ -1106,7 +3056,8 @@ mod tests {
         replace_u64_at(&mut buf, offset, u64::MAX);
 
         let pool = PagedPool::new_with_candidate_sizes([MAX_LENGTH as usize]);
-        let err = DiskBlock::read(&mut Cursor::new(buf), MAX_LENGTH, &pool).expect_err("deserialization should fail");
+        let err =
+            DiskBlock::read(&mut Cursor::new(buf), MAX_LENGTH, false, &pool).expect_err("deserialization should fail");
         match length_to_corrupt {
             "key" | "etag" => assert!(matches!(
                 err,
@@ -1127,6 +3078,15 @@ mod tests {
                 cache_directory: cache_directory.path().to_path_buf(),
                 block_size: block_size as u64,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
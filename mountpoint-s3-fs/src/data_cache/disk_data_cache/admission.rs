@@ -0,0 +1,119 @@
+//! Deciding which blocks a [DiskDataCache](super::DiskDataCache) writes to disk.
+//!
+//! With the [AdmissionPolicy::SecondRead](super::AdmissionPolicy::SecondRead) policy, a block is only written when it
+//! is read from S3 for the second time within a window. Blocks read only once, for example by a large scan, are then
+//! not written at all, and don't evict the blocks that are read repeatedly.
+//!
+//! The blocks read once are remembered by a [GhostFilter], holding hashes of their keys in two generations. Keys are
+//! added to the current generation, which becomes the previous one after a window, so a key is remembered for between
+//! one and two windows.
+
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use super::DiskBlockKey;
+
+/// Maximum number of keys in a generation of a [GhostFilter]. The current generation becomes the previous one when it
+/// is full, even if the window hasn't elapsed, to bound the memory used by a scan of many blocks.
+const MAX_GENERATION_KEYS: usize = 1 << 20;
+
+/// Remembers the blocks read once within a window.
+#[derive(Debug)]
+pub struct GhostFilter {
+    window: Duration,
+    max_generation_keys: usize,
+    hasher: RandomState,
+    current: HashSet<u64>,
+    previous: HashSet<u64>,
+    /// When the current generation started.
+    started: Instant,
+}
+
+impl GhostFilter {
+    pub fn new(window: Duration) -> Self {
+        Self::with_max_generation_keys(window, MAX_GENERATION_KEYS)
+    }
+
+    fn with_max_generation_keys(window: Duration, max_generation_keys: usize) -> Self {
+        Self {
+            window,
+            max_generation_keys,
+            hasher: RandomState::new(),
+            current: HashSet::new(),
+            previous: HashSet::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record a read of the block with the given key at `now`, and return whether it was already read within the
+    /// window, and should be admitted to the cache.
+    pub fn admit(&mut self, key: &DiskBlockKey, now: Instant) -> bool {
+        if now.saturating_duration_since(self.started) >= self.window || self.current.len() >= self.max_generation_keys
+        {
+            self.previous = mem::take(&mut self.current);
+            self.started = now;
+        }
+        let hash = self.hasher.hash_one(key);
+        if self.current.contains(&hash) || self.previous.remove(&hash) {
+            self.current.remove(&hash);
+            return true;
+        }
+        self.current.insert(hash);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::object::ObjectId;
+
+    fn key(index: u64) -> DiskBlockKey {
+        DiskBlockKey::new(&ObjectId::new("a".into(), ETag::for_tests()), index)
+    }
+
+    #[test]
+    fn test_second_read() {
+        let start = Instant::now();
+        let mut filter = GhostFilter::new(Duration::from_secs(10));
+        assert!(!filter.admit(&key(0), start), "blocks read once are not admitted");
+        assert!(!filter.admit(&key(1), start));
+        assert!(filter.admit(&key(0), start + Duration::from_secs(1)));
+
+        // Admitted blocks are forgotten, so that they're only admitted again after two more reads.
+        assert!(!filter.admit(&key(0), start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_window() {
+        let start = Instant::now();
+        let window = Duration::from_secs(10);
+        let mut filter = GhostFilter::new(window);
+        assert!(!filter.admit(&key(0), start));
+        assert!(!filter.admit(&key(1), start));
+        assert!(
+            filter.admit(&key(0), start + window + Duration::from_secs(1)),
+            "blocks are remembered for at least a window"
+        );
+        assert!(
+            !filter.admit(&key(1), start + 3 * window),
+            "blocks are forgotten after two windows"
+        );
+    }
+
+    #[test]
+    fn test_max_generation_keys() {
+        let start = Instant::now();
+        let mut filter = GhostFilter::with_max_generation_keys(Duration::from_secs(10), 2);
+        for index in 0..4 {
+            assert!(!filter.admit(&key(index), start));
+        }
+        assert!(filter.current.len() <= 2 && filter.previous.len() <= 2);
+        assert!(!filter.admit(&key(0), start), "the oldest blocks are forgotten");
+        assert!(filter.admit(&key(3), start));
+    }
+}
@@ -0,0 +1,163 @@
+//! Encrypting the blocks of a [DiskDataCache](super::DiskDataCache).
+//!
+//! Blocks are encrypted with AES-256-GCM, including their header holding the S3 key and ETag, so that neither the
+//! content nor the names of the cached objects are written to the cache directory unencrypted. Each block is sealed with
+//! a random nonce written before it, and authenticated along with its [DiskBlockKey], so that a block moved to the path
+//! of another one is rejected.
+
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use aws_lc_rs::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use thiserror::Error;
+
+use super::DiskBlockKey;
+
+/// Size in bytes of the keys the blocks are encrypted with.
+pub const CACHE_ENCRYPTION_KEY_LEN: usize = 32;
+
+/// Key the blocks of a [DiskDataCache](super::DiskDataCache) are encrypted with.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CacheEncryptionKey([u8; CACHE_ENCRYPTION_KEY_LEN]);
+
+#[derive(Debug, Error)]
+pub enum CacheEncryptionKeyError {
+    #[error("unable to read the key file")]
+    Io(#[from] io::Error),
+    #[error("the key file must hold a key of {CACHE_ENCRYPTION_KEY_LEN} bytes, or its {} hexadecimal digits", 2 * CACHE_ENCRYPTION_KEY_LEN)]
+    InvalidKey,
+}
+
+impl CacheEncryptionKey {
+    /// Generate a random key, only known to this process. The blocks encrypted with it can't be read by other processes,
+    /// or once the process exits.
+    pub fn ephemeral() -> Self {
+        let mut key = [0; CACHE_ENCRYPTION_KEY_LEN];
+        aws_lc_rs::rand::fill(&mut key).expect("the system random number generator should be available");
+        Self(key)
+    }
+
+    /// Read the key in the file at `path`, holding either its raw bytes or their hexadecimal digits.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CacheEncryptionKeyError> {
+        let content = fs::read(path)?;
+        Self::from_bytes(&content)
+    }
+
+    fn from_bytes(content: &[u8]) -> Result<Self, CacheEncryptionKeyError> {
+        let mut key = [0; CACHE_ENCRYPTION_KEY_LEN];
+        if content.len() == CACHE_ENCRYPTION_KEY_LEN {
+            key.copy_from_slice(content);
+        } else {
+            hex::decode_to_slice(content.trim_ascii(), &mut key).map_err(|_| CacheEncryptionKeyError::InvalidKey)?;
+        }
+        Ok(Self(key))
+    }
+}
+
+impl Debug for CacheEncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CacheEncryptionKey(<redacted>)")
+    }
+}
+
+/// The cipher sealing and opening the blocks of a cache.
+pub struct BlockCipher {
+    key: LessSafeKey,
+}
+
+/// Error when a block couldn't be decrypted, because it was corrupted or encrypted with another key.
+#[derive(Debug, Error)]
+#[error("the block could not be decrypted")]
+pub struct DecryptionError;
+
+impl BlockCipher {
+    pub fn new(key: &CacheEncryptionKey) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, &key.0).expect("the key should have the length of AES-256 keys");
+        Self {
+            key: LessSafeKey::new(key),
+        }
+    }
+
+    /// Encrypt the serialized block in `data`, returning the nonce and encrypted block to write to the block file.
+    pub fn seal(&self, block_key: &DiskBlockKey, mut data: Vec<u8>) -> Vec<u8> {
+        let mut nonce = [0; NONCE_LEN];
+        aws_lc_rs::rand::fill(&mut nonce).expect("the system random number generator should be available");
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(Self::aad(block_key)),
+                &mut data,
+            )
+            .expect("blocks should not exceed the maximum size of AES-GCM messages");
+        let mut sealed = Vec::with_capacity(NONCE_LEN + data.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&data);
+        sealed
+    }
+
+    /// Decrypt the content of a block file written by [Self::seal], returning the serialized block.
+    pub fn open<'a>(&self, block_key: &DiskBlockKey, sealed: &'a mut [u8]) -> Result<&'a mut [u8], DecryptionError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(DecryptionError);
+        }
+        let (nonce, data) = sealed.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| DecryptionError)?;
+        self.key
+            .open_in_place(nonce, Aad::from(Self::aad(block_key)), data)
+            .map_err(|_| DecryptionError)
+    }
+
+    fn aad(block_key: &DiskBlockKey) -> [u8; 40] {
+        let mut aad = [0; 40];
+        aad[..32].copy_from_slice(&block_key.hashed_key);
+        aad[32..].copy_from_slice(&block_key.block_index.to_be_bytes());
+        aad
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::object::ObjectId;
+
+    fn block_key(index: u64) -> DiskBlockKey {
+        DiskBlockKey::new(&ObjectId::new("a".into(), ETag::for_tests()), index)
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let cipher = BlockCipher::new(&CacheEncryptionKey::ephemeral());
+        let mut sealed = cipher.seal(&block_key(0), b"Foo".to_vec());
+        assert_eq!(sealed.len(), NONCE_LEN + 3 + AES_256_GCM.tag_len());
+        assert!(!sealed.windows(3).any(|window| window == b"Foo"));
+
+        assert_eq!(cipher.open(&block_key(0), &mut sealed.clone()).unwrap(), b"Foo");
+        cipher
+            .open(&block_key(1), &mut sealed.clone())
+            .expect_err("blocks are bound to their key");
+        let other_cipher = BlockCipher::new(&CacheEncryptionKey::ephemeral());
+        other_cipher
+            .open(&block_key(0), &mut sealed.clone())
+            .expect_err("blocks can only be opened with their key");
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        cipher
+            .open(&block_key(0), &mut sealed)
+            .expect_err("corrupted blocks are rejected");
+    }
+
+    #[test]
+    fn test_key_from_bytes() {
+        let raw = [7u8; CACHE_ENCRYPTION_KEY_LEN];
+        let key = CacheEncryptionKey::from_bytes(&raw).unwrap();
+        assert_eq!(key, CacheEncryptionKey([7; CACHE_ENCRYPTION_KEY_LEN]));
+        let hex_key = format!("{}\n", hex::encode(raw));
+        assert_eq!(CacheEncryptionKey::from_bytes(hex_key.as_bytes()).unwrap(), key);
+        assert!(CacheEncryptionKey::from_bytes(b"too short").is_err());
+        assert_eq!(format!("{key:?}"), "CacheEncryptionKey(<redacted>)");
+    }
+}
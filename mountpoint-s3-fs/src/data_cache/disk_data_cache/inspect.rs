@@ -0,0 +1,237 @@
+//! Inspecting the blocks of a [DiskDataCache], to report which objects are cached and evict some of them outside of a
+//! mount.
+//!
+//! The cache directory is named after hashes of the cached keys, so the object a block belongs to is read from its
+//! header. Blocks that couldn't be read, for example because they were encrypted with another key or written by another
+//! version of Mountpoint, are still listed so that they can be accounted for and evicted.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mountpoint_s3_client::types::ETag;
+use tracing::warn;
+
+use crate::object::ObjectId;
+
+use super::{BINCODE_CONFIG, BlockIndex, CACHE_VERSION, DiskBlockHeader, DiskBlockKey, DiskDataCache};
+
+/// A block found in the directory of a [DiskDataCache].
+#[derive(Debug, Clone)]
+pub struct CachedBlockInfo {
+    /// The object the block belongs to, or `None` if the block couldn't be read.
+    pub object_id: Option<ObjectId>,
+    /// Index of the block in the object.
+    pub block_idx: BlockIndex,
+    /// Size of the block file.
+    pub size: u64,
+    /// When the block file was last modified, which is when the block was written to the cache.
+    pub modified: SystemTime,
+    block_key: DiskBlockKey,
+}
+
+impl DiskDataCache {
+    /// List the blocks in the cache directory.
+    pub fn list_blocks(&self) -> Vec<CachedBlockInfo> {
+        let mut blocks = Vec::new();
+        self.for_each_block_file(|path, block_key| {
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                // The block was evicted meanwhile.
+                Err(error) if error.kind() == ErrorKind::NotFound => return,
+                Err(error) => {
+                    warn!(?path, ?error, "unable to read cache block metadata");
+                    return;
+                }
+            };
+            blocks.push(CachedBlockInfo {
+                object_id: self.read_block_object_id(&path, &block_key),
+                block_idx: block_key.block_index,
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                block_key,
+            });
+        });
+        blocks
+    }
+
+    /// Remove a block listed by [Self::list_blocks] from the cache directory.
+    pub fn evict_block(&self, block: &CachedBlockInfo) {
+        if let Some(mut usage) = self.lock_usage() {
+            usage.remove(&block.block_key);
+        }
+        self.remove_block(&block.block_key);
+    }
+
+    /// The sizes of the blocks written to the cache directory `cache_directory`, each of which is managed by a
+    /// [DiskDataCache] configured with that block size.
+    pub fn block_sizes_in(cache_directory: &Path) -> io::Result<Vec<u64>> {
+        let entries = match fs::read_dir(cache_directory.join(CACHE_VERSION)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+        let mut block_sizes = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(block_size) = name.to_str().and_then(|name| name.strip_prefix("blocks-"))
+                && let Ok(block_size) = block_size.parse()
+            {
+                block_sizes.push(block_size);
+            }
+        }
+        block_sizes.sort_unstable();
+        Ok(block_sizes)
+    }
+
+    /// Call `f` with the path and key of each block file in the cache directory.
+    pub(super) fn for_each_block_file(&self, mut f: impl FnMut(PathBuf, DiskBlockKey)) {
+        let Ok(first_level) = fs::read_dir(self.blocks_directory()) else {
+            // Nothing was written to the cache yet.
+            return;
+        };
+        for first in first_level.flatten() {
+            // Skip the usage files, only blocks are in directories.
+            if !first.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                continue;
+            }
+            for second in fs::read_dir(first.path()).into_iter().flatten().flatten() {
+                let hex_key = format!(
+                    "{}{}",
+                    first.file_name().to_string_lossy(),
+                    second.file_name().to_string_lossy()
+                );
+                for block in fs::read_dir(second.path()).into_iter().flatten().flatten() {
+                    let name = block.file_name();
+                    let name = name.to_string_lossy();
+                    // Skip the temporary files of blocks being written.
+                    if name.starts_with('.') {
+                        continue;
+                    }
+                    let Some(block_key) = DiskBlockKey::from_hex(&hex_key, &name) else {
+                        warn!(path = ?block.path(), "unexpected file in cache directory");
+                        continue;
+                    };
+                    f(block.path(), block_key);
+                }
+            }
+        }
+    }
+
+    /// Read the object the block at `path` belongs to from its header, without reading its data unless the block is
+    /// encrypted.
+    fn read_block_object_id(&self, path: &Path, block_key: &DiskBlockKey) -> Option<ObjectId> {
+        let mut file = fs::File::open(path).ok()?;
+        let compressed = self.read_block_version(&mut file).ok()??;
+        let header: DiskBlockHeader = if self.cipher.is_some() {
+            self.read_disk_block(&mut file, block_key, compressed).ok()?.header
+        } else {
+            bincode::decode_from_std_read(&mut file, BINCODE_CONFIG).ok()?
+        };
+        let object_id = ObjectId::new(header.s3_key, ETag::from(header.etag.as_str()));
+        (DiskBlockKey::new(&object_id, header.block_idx) == *block_key).then_some(object_id)
+    }
+}
+
+impl DiskBlockKey {
+    /// The key of the block in the directories named after `hex_key`, in the file named `block_index`, if valid.
+    fn from_hex(hex_key: &str, block_index: &str) -> Option<Self> {
+        let mut hashed_key = [0; 32];
+        hex::decode_to_slice(hex_key, &mut hashed_key).ok()?;
+        let block_index = block_index.parse().ok()?;
+        Some(Self {
+            hashed_key,
+            block_index,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksums::ChecksummedBytes;
+    use crate::data_cache::{CacheEncryptionKey, CacheLimit, DataCache, DiskDataCacheConfig};
+    use crate::memory::PagedPool;
+
+    fn new_cache(cache_directory: &Path, block_size: u64, encryption_key: Option<CacheEncryptionKey>) -> DiskDataCache {
+        DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.to_path_buf(),
+                block_size,
+                limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            PagedPool::new_with_candidate_sizes([block_size as usize]),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_list_and_evict_blocks() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let cache = new_cache(cache_directory.path(), 1024, None);
+        assert!(cache.list_blocks().is_empty());
+
+        let a = ObjectId::new("a".into(), ETag::for_tests());
+        let b = ObjectId::new("b".into(), ETag::for_tests());
+        for block_idx in 0..2 {
+            let data = ChecksummedBytes::new(vec![0; 1024].into());
+            cache
+                .put_block(a.clone(), block_idx, block_idx * 1024, data, 2048)
+                .await
+                .unwrap();
+        }
+        let data = ChecksummedBytes::new("Foo".into());
+        cache.put_block(b.clone(), 0, 0, data, 3).await.unwrap();
+
+        let mut blocks = cache.list_blocks();
+        blocks.sort_by_key(|block| (block.object_id.as_ref().map(|id| id.key().to_owned()), block.block_idx));
+        let listed: Vec<_> = blocks
+            .iter()
+            .map(|block| (block.object_id.clone().unwrap(), block.block_idx))
+            .collect();
+        assert_eq!(listed, [(a.clone(), 0), (a.clone(), 1), (b.clone(), 0)]);
+        assert!(blocks[0].size > 1024, "block size includes the header");
+        assert!(blocks[2].size < blocks[0].size);
+
+        cache.evict_block(&blocks[1]);
+        assert!(cache.get_block(&a, 1, 1024, 2048).await.unwrap().is_none());
+        assert_eq!(cache.list_blocks().len(), 2);
+
+        // Blocks encrypted with another key are listed, but their object is unknown.
+        let encrypted_cache = new_cache(cache_directory.path(), 1024, Some(CacheEncryptionKey::ephemeral()));
+        let unreadable = encrypted_cache.list_blocks();
+        assert_eq!(unreadable.len(), 2);
+        assert!(unreadable.iter().all(|block| block.object_id.is_none()));
+
+        assert_eq!(DiskDataCache::block_sizes_in(cache_directory.path()).unwrap(), [1024]);
+        let other_directory = tempfile::tempdir().unwrap();
+        assert!(
+            DiskDataCache::block_sizes_in(other_directory.path())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_encrypted_blocks() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let key = CacheEncryptionKey::ephemeral();
+        let cache = new_cache(cache_directory.path(), 1024, Some(key.clone()));
+        let object_id = ObjectId::new("a".into(), ETag::for_tests());
+        let data = ChecksummedBytes::new("Foo".into());
+        cache.put_block(object_id.clone(), 0, 0, data, 3).await.unwrap();
+
+        let blocks = new_cache(cache_directory.path(), 1024, Some(key)).list_blocks();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].object_id, Some(object_id));
+    }
+}
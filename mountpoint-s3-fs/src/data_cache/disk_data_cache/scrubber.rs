@@ -0,0 +1,191 @@
+//! Verifying the blocks of a [DiskDataCache] in the background.
+//!
+//! Blocks are verified when they are read, but corruption of the storage the cache directory lives on may then only be
+//! noticed when the block is needed, and multiple blocks may be corrupted long before. The scrubber reads all the
+//! blocks of the cache directory periodically, and removes the ones that no longer match their checksums.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use mountpoint_s3_client::types::ETag;
+use tracing::{debug, trace, warn};
+
+use crate::checksums::ChecksummedBytes;
+use crate::metrics::defs::{ATTR_CACHE, CACHE_CORRUPTED_BLOCKS, CACHE_DISK, CACHE_SCRUBBED_BLOCKS};
+use crate::object::ObjectId;
+use crate::sync::Arc;
+
+use super::{DiskBlockKey, DiskDataCache};
+
+/// Outcome of a pass of [DiskDataCache::scrub].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScrubStats {
+    /// Number of blocks verified.
+    pub blocks: usize,
+    /// Number of corrupted blocks found and removed.
+    pub corrupted: usize,
+}
+
+impl DiskDataCache {
+    /// Verify all the blocks in the cache directory, and remove the corrupted ones.
+    pub fn scrub(&self) -> ScrubStats {
+        let mut stats = ScrubStats::default();
+        self.for_each_block_file(|path, block_key| match self.verify_block(&path, &block_key) {
+            Ok(true) => stats.blocks += 1,
+            Ok(false) => {
+                stats.blocks += 1;
+                stats.corrupted += 1;
+                metrics::counter!(CACHE_CORRUPTED_BLOCKS, ATTR_CACHE => CACHE_DISK).increment(1);
+                if let Some(mut usage) = self.lock_usage() {
+                    usage.remove(&block_key);
+                }
+                self.remove_block(&block_key);
+            }
+            // The block was evicted meanwhile.
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => warn!(?path, ?error, "unable to verify cache block"),
+        });
+        let directory = self.blocks_directory();
+        metrics::counter!(CACHE_SCRUBBED_BLOCKS, ATTR_CACHE => CACHE_DISK).increment(stats.blocks as u64);
+        debug!(?directory, ?stats, "verified cache blocks");
+        stats
+    }
+
+    /// Read the block at `path` and return whether it is valid: it must be readable, match its checksums, and belong
+    /// to the object and offset given by `block_key`.
+    fn verify_block(&self, path: &Path, block_key: &DiskBlockKey) -> std::io::Result<bool> {
+        trace!(?path, "verifying cache block");
+        let mut file = fs::File::open(path)?;
+        let Some(compressed) = self.read_block_version(&mut file)? else {
+            warn!(?path, "cache block has a stale format, removing it");
+            return Ok(false);
+        };
+        let block = match self.read_disk_block(&mut file, block_key, compressed) {
+            Ok(block) => block,
+            Err(error) => {
+                warn!(?path, ?error, "cache block could not be deserialized, removing it");
+                return Ok(false);
+            }
+        };
+        let header = &block.header;
+        let cache_key = ObjectId::new(header.s3_key.clone(), ETag::from(&header.etag));
+        if DiskBlockKey::new(&cache_key, header.block_idx) != *block_key
+            || header.block_offset != header.block_idx * self.config.block_size
+        {
+            warn!(
+                ?path,
+                key = header.s3_key,
+                "cache block belongs to another object, removing it"
+            );
+            return Ok(false);
+        }
+        let valid = block
+            .data(&cache_key, header.block_idx, header.block_offset)
+            .ok()
+            .is_some_and(|bytes: ChecksummedBytes| bytes.validate().is_ok());
+        if !valid {
+            warn!(?path, key = header.s3_key, "cache block is corrupted, removing it");
+        }
+        Ok(valid)
+    }
+
+    /// Spawn a thread verifying the blocks of `cache` every `interval`, until the cache is dropped.
+    pub fn spawn_scrubber(cache: &Arc<Self>, interval: Duration) {
+        let cache = Arc::downgrade(cache);
+        let result = thread::Builder::new().name("cache-scrubber".to_owned()).spawn(move || {
+            loop {
+                thread::sleep(interval);
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.scrub();
+            }
+        });
+        if let Err(error) = result {
+            warn!(?error, "unable to start the cache scrubber");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    use super::*;
+    use crate::data_cache::{CacheLimit, DataCache, DiskDataCacheConfig};
+    use crate::memory::PagedPool;
+
+    #[tokio::test]
+    async fn test_scrub() {
+        let cache_directory = tempfile::tempdir().unwrap();
+        let cache = DiskDataCache::new(
+            DiskDataCacheConfig {
+                cache_directory: cache_directory.path().to_path_buf(),
+                block_size: 1024,
+                limit: CacheLimit::TotalSize { max_size: 1024 * 1024 },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
+            },
+            PagedPool::new_with_candidate_sizes([1024]),
+        );
+        assert_eq!(cache.scrub(), ScrubStats::default());
+
+        let keys: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+        for key in &keys {
+            let data = ChecksummedBytes::new("Foo".into());
+            cache.put_block(key.clone(), 0, 0, data, 3).await.unwrap();
+        }
+        assert_eq!(
+            cache.scrub(),
+            ScrubStats {
+                blocks: 3,
+                corrupted: 0
+            }
+        );
+
+        // Flip the last byte of the data of a block.
+        let corrupted_key = DiskBlockKey::new(&keys[1], 0);
+        let path = cache.get_path_for_block_key(&corrupted_key);
+        let mut file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        file.seek(SeekFrom::End(-1)).unwrap();
+        let mut byte = [0];
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::End(-1)).unwrap();
+        file.write_all(&[byte[0] ^ 1]).unwrap();
+        drop(file);
+
+        assert_eq!(
+            cache.scrub(),
+            ScrubStats {
+                blocks: 3,
+                corrupted: 1
+            }
+        );
+        assert!(!path.exists(), "corrupted blocks are removed");
+        let usage = cache.usage.as_ref().unwrap().lock().unwrap();
+        assert!(!usage.info.entries.contains_key(&corrupted_key));
+        assert_eq!(usage.info.entries.len(), 2);
+        drop(usage);
+        assert_eq!(
+            cache.scrub(),
+            ScrubStats {
+                blocks: 2,
+                corrupted: 0
+            }
+        );
+    }
+}
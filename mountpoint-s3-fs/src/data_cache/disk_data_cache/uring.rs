@@ -0,0 +1,386 @@
+//! Reading and writing the blocks of a [DiskDataCache] with io_uring.
+//!
+//! The block files are read and written by a dedicated thread submitting the operations to an io_uring instance, so
+//! that the threads getting and putting blocks wait for them asynchronously rather than block on disk I/O. Opening,
+//! renaming, and removing block files is still done synchronously, as these are metadata operations.
+//!
+//! With `O_DIRECT`, the data bypasses the page cache, which would otherwise hold a second copy of the cached blocks. The
+//! buffers and lengths of direct I/O must be aligned, so block files are read and written in multiples of
+//! [DIRECT_IO_ALIGNMENT], and truncated to their length once written.
+
+use std::alloc::{self, Layout};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::Path;
+use std::ptr::NonNull;
+
+use bytes::Bytes;
+use futures::channel::oneshot;
+use io_uring::{IoUring, opcode, types};
+use tempfile::NamedTempFile;
+use tracing::{trace, warn};
+
+use crate::data_cache::{BlockIndex, DataCacheError, DataCacheResult};
+use crate::object::ObjectId;
+use crate::sync::{mpsc, thread};
+
+use super::{CACHE_VERSION, CachedBlock, DiskBlock, DiskBlockKey, DiskDataCache};
+
+/// Maximum number of operations in flight on the io_uring instance. Further operations wait for one to complete.
+const QUEUE_DEPTH: u32 = 64;
+
+/// Alignment of the buffers, offsets, and lengths of `O_DIRECT` I/O, compatible with the logical block size of common
+/// storage devices.
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Submits the reads and writes of block files to an io_uring instance, driven by a dedicated thread.
+#[derive(Debug)]
+pub struct UringIo {
+    requests: mpsc::Sender<Request>,
+    direct: bool,
+}
+
+/// Buffer aligned for `O_DIRECT` I/O.
+struct AlignedBuffer {
+    ptr: NonNull<u8>,
+    capacity: usize,
+    len: usize,
+}
+
+// SAFETY: the buffer owns its allocation, which is only accessed through it.
+unsafe impl Send for AlignedBuffer {}
+// SAFETY: the buffer is only mutated through `&mut self`.
+unsafe impl Sync for AlignedBuffer {}
+
+impl AlignedBuffer {
+    /// Allocate a zeroed buffer of at least `capacity` bytes, rounded up to the alignment of direct I/O.
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_multiple_of(DIRECT_IO_ALIGNMENT);
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc::alloc_zeroed(Self::layout(capacity)) };
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(Self::layout(capacity));
+        };
+        Self { ptr, capacity, len: 0 }
+    }
+
+    fn from_slice(data: &[u8]) -> Self {
+        let mut buffer = Self::new(data.len());
+        // SAFETY: the buffer has at least `data.len()` bytes, and doesn't overlap with `data`.
+        unsafe { buffer.ptr.as_ptr().copy_from_nonoverlapping(data.as_ptr(), data.len()) };
+        buffer.len = data.len();
+        buffer
+    }
+
+    fn layout(capacity: usize) -> Layout {
+        Layout::from_size_align(capacity, DIRECT_IO_ALIGNMENT).expect("buffer size should be valid")
+    }
+}
+
+impl AsRef<[u8]> for AlignedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        // SAFETY: the first `len` bytes of the allocation are initialized.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: the buffer was allocated with this layout.
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), Self::layout(self.capacity)) };
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    Read,
+    Write,
+}
+
+/// An operation on the bytes `start..end` of `buffer`, at the same offset of `file`. The request owns the file and
+/// buffer until the operation completes, so that they outlive it even if the future waiting for it is dropped.
+struct Request {
+    operation: Operation,
+    file: fs::File,
+    buffer: AlignedBuffer,
+    start: usize,
+    end: usize,
+    done: oneshot::Sender<Completion>,
+}
+
+impl std::fmt::Debug for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Request")
+            .field("operation", &self.operation)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The result of a [Request], returning its file and buffer.
+struct Completion {
+    result: io::Result<usize>,
+    file: fs::File,
+    buffer: AlignedBuffer,
+}
+
+impl UringIo {
+    /// Set up an io_uring instance and the thread driving it. Fails if io_uring isn't available, for example on older
+    /// kernels or where it is disabled.
+    pub fn new(direct: bool) -> io::Result<Self> {
+        let ring = IoUring::new(QUEUE_DEPTH)?;
+        let (requests, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("cache-io-uring".to_owned())
+            .spawn(move || run(ring, receiver))?;
+        Ok(Self { requests, direct })
+    }
+
+    /// Open the file at `path` with `options`, for direct I/O if configured. Falls back to buffered I/O on file
+    /// systems that don't support direct I/O.
+    fn open(&self, path: &Path, options: &mut OpenOptions) -> io::Result<fs::File> {
+        if self.direct {
+            match options.clone().custom_flags(libc::O_DIRECT).open(path) {
+                Err(error) if error.raw_os_error() == Some(libc::EINVAL) => {}
+                result => return result,
+            }
+        }
+        options.open(path)
+    }
+
+    /// Read the first `len` bytes of `file`.
+    async fn read(&self, file: fs::File, len: usize) -> io::Result<AlignedBuffer> {
+        let mut buffer = AlignedBuffer::new(len);
+        let mut file = file;
+        let mut read = 0;
+        while read < len {
+            // Read up to the end of the buffer, rounded up to the alignment of direct I/O.
+            let end = buffer.capacity;
+            let completion = self.submit(Operation::Read, file, buffer, read, end).await?;
+            (file, buffer) = (completion.file, completion.buffer);
+            match completion.result? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => read += n,
+            }
+        }
+        buffer.len = len;
+        Ok(buffer)
+    }
+
+    /// Write `buffer` to `file`, which is then truncated to the length of the buffer.
+    async fn write(&self, file: fs::File, buffer: AlignedBuffer) -> io::Result<()> {
+        let len = buffer.len;
+        // The buffer is zeroed past its length, up to the alignment of direct I/O.
+        let end = len.next_multiple_of(DIRECT_IO_ALIGNMENT);
+        let (mut file, mut buffer) = (file, buffer);
+        let mut written = 0;
+        while written < end {
+            let completion = self.submit(Operation::Write, file, buffer, written, end).await?;
+            (file, buffer) = (completion.file, completion.buffer);
+            match completion.result? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                n => written += n,
+            }
+        }
+        file.set_len(len as u64)
+    }
+
+    async fn submit(
+        &self,
+        operation: Operation,
+        file: fs::File,
+        buffer: AlignedBuffer,
+        start: usize,
+        end: usize,
+    ) -> io::Result<Completion> {
+        let (done, receiver) = oneshot::channel();
+        let request = Request {
+            operation,
+            file,
+            buffer,
+            start,
+            end,
+            done,
+        };
+        let stopped = || io::Error::other("the io_uring thread stopped");
+        self.requests.send(request).map_err(|_| stopped())?;
+        receiver.await.map_err(|_| stopped())
+    }
+}
+
+/// Submit the requests to `ring` and complete them, until the [UringIo] is dropped and all the requests completed.
+fn run(mut ring: IoUring, receiver: mpsc::Receiver<Request>) {
+    let mut in_flight: HashMap<u64, Request> = HashMap::new();
+    let mut backlog = VecDeque::new();
+    let mut next_id = 0u64;
+    loop {
+        if in_flight.is_empty() && backlog.is_empty() {
+            match receiver.recv() {
+                Ok(request) => backlog.push_back(request),
+                Err(_) => return,
+            }
+        }
+        backlog.extend(receiver.try_iter());
+        while in_flight.len() < QUEUE_DEPTH as usize
+            && let Some(request) = backlog.pop_front()
+        {
+            let fd = types::Fd(request.file.as_raw_fd());
+            let offset = request.start as u64;
+            let len = (request.end - request.start) as u32;
+            // SAFETY: the buffer has at least `end` bytes.
+            let ptr = unsafe { request.buffer.ptr.as_ptr().add(request.start) };
+            let entry = match request.operation {
+                Operation::Read => opcode::Read::new(fd, ptr, len).offset(offset).build(),
+                Operation::Write => opcode::Write::new(fd, ptr, len).offset(offset).build(),
+            }
+            .user_data(next_id);
+            // SAFETY: the file and buffer are owned by the request, which is kept until the operation completes.
+            if unsafe { ring.submission().push(&entry) }.is_err() {
+                backlog.push_front(request);
+                break;
+            }
+            in_flight.insert(next_id, request);
+            next_id = next_id.wrapping_add(1);
+        }
+        match ring.submit_and_wait(1) {
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => {
+                warn!(?error, "io_uring submission failed, stopping cache I/O");
+                // The kernel may still be using the buffers of the submitted operations, so they are leaked.
+                std::mem::forget(in_flight);
+                return;
+            }
+        }
+        for entry in ring.completion() {
+            let Some(request) = in_flight.remove(&entry.user_data()) else {
+                continue;
+            };
+            let result = match entry.result() {
+                result if result < 0 => Err(io::Error::from_raw_os_error(-result)),
+                result => Ok(result as usize),
+            };
+            let completion = Completion {
+                result,
+                file: request.file,
+                buffer: request.buffer,
+            };
+            // The future waiting for the operation may have been dropped.
+            _ = request.done.send(completion);
+        }
+    }
+}
+
+impl DiskDataCache {
+    /// Read the block at `path` with io_uring, like [DiskDataCache::read_block].
+    pub(super) async fn read_block_uring(
+        &self,
+        uring: &UringIo,
+        path: &Path,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+    ) -> DataCacheResult<Option<CachedBlock>> {
+        trace!(
+            key = ?cache_key.key(),
+            offset = block_offset,
+            ?path,
+            "reading cache block with io_uring",
+        );
+        let file = match uring.open(path, OpenOptions::new().read(true)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let len = file.metadata()?.len() as usize;
+        if len < CACHE_VERSION.len() {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        let content = Bytes::from_owner(uring.read(file, len).await?);
+
+        let Some(compressed) = self.compressed_version(&content[..CACHE_VERSION.len()]) else {
+            warn!(?path, "stale block format found during reading");
+            return Err(DataCacheError::InvalidBlockContent);
+        };
+        let size = len - CACHE_VERSION.len();
+        let block_key = DiskBlockKey::new(cache_key, block_idx);
+        let block = self
+            .parse_disk_block(content.slice(CACHE_VERSION.len()..), &block_key, compressed)
+            .inspect_err(|e| warn!(?path, "block could not be deserialized: {:?}", e))?;
+        self.cached_block(block, size, cache_key, block_idx, block_offset)
+            .map(Some)
+    }
+
+    /// Write `block` to a temporary file next to `path` with io_uring, like [DiskDataCache::write_block].
+    pub(super) async fn write_block_uring(
+        &self,
+        uring: &UringIo,
+        path: &Path,
+        block_key: &DiskBlockKey,
+        block: DiskBlock,
+    ) -> DataCacheResult<(NamedTempFile, usize)> {
+        let cache_path_for_key = path.parent().expect("path should include cache key in directory name");
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .recursive(true)
+            .create(cache_path_for_key)?;
+        let temp_file = self.create_temp_file(cache_path_for_key)?;
+        trace!(
+            key = block.header.s3_key,
+            offset = block.header.block_offset,
+            block_path = ?path,
+            temp_path = ?temp_file.path(),
+            "writing cache block with io_uring",
+        );
+        let mut content = Vec::new();
+        let size = self.serialize_block(&mut content, block_key, block)?;
+        let file = uring.open(temp_file.path(), OpenOptions::new().write(true))?;
+        uring.write(file, AlignedBuffer::from_slice(&content)).await?;
+        Ok((temp_file, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        for direct in [false, true] {
+            let Ok(uring) = UringIo::new(direct) else {
+                // io_uring isn't available on this host.
+                return;
+            };
+            let directory = tempfile::tempdir().unwrap();
+            let path = directory.path().join("file");
+            let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+            let file = uring
+                .open(&path, OpenOptions::new().write(true).create(true).truncate(true))
+                .unwrap();
+            uring.write(file, AlignedBuffer::from_slice(&data)).await.unwrap();
+            assert_eq!(fs::read(&path).unwrap(), data, "files are truncated to their length");
+
+            let file = uring.open(&path, OpenOptions::new().read(true)).unwrap();
+            let buffer = uring.read(file, data.len()).await.unwrap();
+            assert_eq!(buffer.as_ref(), data);
+
+            let file = uring.open(&path, OpenOptions::new().read(true)).unwrap();
+            let error = uring.read(file, data.len() + 1).await.map(|_| ()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn test_aligned_buffer() {
+        let buffer = AlignedBuffer::from_slice(b"Foo");
+        assert_eq!(buffer.as_ref(), b"Foo");
+        assert_eq!(buffer.capacity, DIRECT_IO_ALIGNMENT);
+        assert_eq!(buffer.ptr.as_ptr() as usize % DIRECT_IO_ALIGNMENT, 0);
+        assert_eq!(AlignedBuffer::new(0).capacity, DIRECT_IO_ALIGNMENT);
+    }
+}
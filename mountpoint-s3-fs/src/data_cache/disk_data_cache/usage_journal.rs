@@ -0,0 +1,325 @@
+//! Sharing the usage bookkeeping of a [DiskDataCache](super::DiskDataCache) with other processes.
+//!
+//! Several Mountpoint processes can use the same cache directory, so that blocks written by one of them are read by
+//! the others. Each process tracks the usage of all the blocks in the directory to decide which ones to evict, and
+//! shares the changes it makes through a [UsageJournal] in the cache directory: changes are appended to the journal,
+//! and each process applies the changes appended by the others before acting on its bookkeeping. Accesses to the
+//! journal are serialized with an exclusive lock on the journal file.
+//!
+//! The journal starts with the generation of the saved usage state it applies to. When the journal grows too large,
+//! the process holding the lock saves its bookkeeping as the next generation of the usage state, and starts a new,
+//! empty journal. Other processes notice the new generation and reload the usage state.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use bincode::{Decode, Encode};
+use tracing::{trace, warn};
+
+use super::{DiskBlockKey, EvictionPolicy, USAGE_STATE_FILE, UsageInfo, read_usage_state, write_usage_state};
+
+/// Name of the journal file, in the blocks directory of the cache.
+const USAGE_JOURNAL_FILE: &str = "usage-journal";
+
+/// Size of the journal header, holding the generation of the usage state the journal applies to.
+const HEADER_SIZE: u64 = 8;
+
+/// Minimum number of records in the journal before it is compacted. Past this number, the journal is compacted once
+/// it holds more records than a few times the number of blocks.
+const COMPACTION_MIN_RECORDS: u64 = 10_000;
+
+/// A change to the usage of a block.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub(super) enum UsageRecord {
+    Add {
+        key: DiskBlockKey,
+        size: usize,
+        checksum: u32,
+        pinned: bool,
+        account: Option<String>,
+        added_at: u64,
+    },
+    Access {
+        key: DiskBlockKey,
+    },
+    Remove {
+        key: DiskBlockKey,
+    },
+}
+
+impl UsageRecord {
+    fn apply(self, usage: &mut UsageInfo<DiskBlockKey>) {
+        match self {
+            UsageRecord::Add {
+                key,
+                size,
+                checksum,
+                pinned,
+                account,
+                added_at,
+            } => usage.add(key, size, checksum, pinned, account.as_deref(), added_at),
+            UsageRecord::Access { key } => {
+                usage.refresh(&key);
+            }
+            UsageRecord::Remove { key } => {
+                usage.remove(&key);
+            }
+        }
+    }
+}
+
+/// Journal of the changes to the usage of the blocks, shared by the processes using the same cache directory.
+#[derive(Debug)]
+pub(super) struct UsageJournal {
+    file: File,
+    path: PathBuf,
+    /// Path of the usage state the journal applies to.
+    state_path: PathBuf,
+    /// Generation of the usage state the journal applies to.
+    generation: u64,
+    /// Offset up to which the records of the journal were applied.
+    offset: u64,
+    /// Number of records in the journal.
+    records: u64,
+}
+
+impl UsageJournal {
+    /// Open the journal in the blocks `directory` of the cache, creating it if needed, and restore the usage it
+    /// tracks. Blocks for which `exists` returns `false` are skipped.
+    pub fn open(
+        directory: &Path,
+        policy: EvictionPolicy,
+        exists: impl Fn(&DiskBlockKey) -> bool,
+    ) -> io::Result<(Self, UsageInfo<DiskBlockKey>)> {
+        fs::DirBuilder::new().mode(0o700).recursive(true).create(directory)?;
+        let path = directory.join(USAGE_JOURNAL_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(&path)?;
+        let mut journal = Self {
+            file,
+            path,
+            state_path: directory.join(USAGE_STATE_FILE),
+            generation: 0,
+            offset: HEADER_SIZE,
+            records: 0,
+        };
+        let mut usage = UsageInfo::new(policy);
+        journal.file.lock()?;
+        let result = journal.reload(&mut usage, exists);
+        journal.file.unlock()?;
+        result?;
+        trace!(
+            path = ?journal.path,
+            generation = journal.generation,
+            entries = usage.entries.len(),
+            "opened usage journal"
+        );
+        Ok((journal, usage))
+    }
+
+    /// Lock the journal, and apply to `usage` the records appended by other processes since the last call. The
+    /// journal stays locked until [Self::finish] is called.
+    pub fn sync(&mut self, usage: &mut UsageInfo<DiskBlockKey>) -> io::Result<()> {
+        self.file.lock()?;
+        let result = match self.read_generation() {
+            Ok(Some(generation)) if generation == self.generation => self.apply_new_records(usage),
+            Ok(_) => {
+                trace!(path = ?self.path, "usage journal was compacted by another process, reloading it");
+                self.reload(usage, |_| true)
+            }
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            _ = self.file.unlock();
+        }
+        result
+    }
+
+    /// Append `records` to the locked journal, compact it if it grew too large, and unlock it.
+    pub fn finish(&mut self, records: &[UsageRecord], usage: &UsageInfo<DiskBlockKey>) -> io::Result<()> {
+        let result = self.append(records).and_then(|()| {
+            if self.records > COMPACTION_MIN_RECORDS.max(4 * usage.entries.len() as u64) {
+                self.compact(usage)
+            } else {
+                Ok(())
+            }
+        });
+        let unlocked = self.file.unlock();
+        result.and(unlocked)
+    }
+
+    /// Save `usage` as a new generation of the usage state, after applying the records appended by other processes.
+    pub fn save(&mut self, usage: &mut UsageInfo<DiskBlockKey>) -> io::Result<()> {
+        self.sync(usage)?;
+        let result = self.compact(usage);
+        let unlocked = self.file.unlock();
+        result.and(unlocked)
+    }
+
+    /// Restore `usage` from the saved usage state and the records of the journal, or start a new journal if it
+    /// doesn't apply to the saved usage state. The journal must be locked.
+    fn reload(
+        &mut self,
+        usage: &mut UsageInfo<DiskBlockKey>,
+        exists: impl Fn(&DiskBlockKey) -> bool,
+    ) -> io::Result<()> {
+        let state = read_usage_state(&self.state_path);
+        self.generation = state.as_ref().map_or(0, |state| state.generation);
+        *usage = match state {
+            Some(state) => UsageInfo::from_state(usage.policy, state, exists),
+            None => UsageInfo::new(usage.policy),
+        };
+        if self.read_generation()? == Some(self.generation) {
+            self.offset = HEADER_SIZE;
+            self.records = 0;
+            self.apply_new_records(usage)
+        } else {
+            self.reset()
+        }
+    }
+
+    /// Read the generation of the usage state the journal applies to, or `None` if the journal is empty.
+    fn read_generation(&mut self) -> io::Result<Option<u64>> {
+        let mut header = [0; HEADER_SIZE as usize];
+        self.file.seek(SeekFrom::Start(0))?;
+        match self.file.read_exact(&mut header) {
+            Ok(()) => Ok(Some(u64::from_le_bytes(header))),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn apply_new_records(&mut self, usage: &mut UsageInfo<DiskBlockKey>) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        self.file.read_to_end(&mut buffer)?;
+        let mut position = 0;
+        while position < buffer.len() {
+            match bincode::decode_from_slice::<UsageRecord, _>(&buffer[position..], bincode::config::standard()) {
+                Ok((record, length)) => {
+                    record.apply(usage);
+                    position += length;
+                    self.records += 1;
+                }
+                Err(error) => {
+                    // A process may have exited while appending a record. Records after it can't be decoded.
+                    warn!(path = ?self.path, ?error, "unable to decode the usage journal, skipping its end");
+                    break;
+                }
+            }
+        }
+        self.offset += buffer.len() as u64;
+        Ok(())
+    }
+
+    fn append(&mut self, records: &[UsageRecord]) -> io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut buffer = Vec::new();
+        for record in records {
+            bincode::encode_into_std_write(record, &mut buffer, bincode::config::standard())
+                .map_err(io::Error::other)?;
+        }
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        self.file.write_all(&buffer)?;
+        self.offset += buffer.len() as u64;
+        self.records += records.len() as u64;
+        Ok(())
+    }
+
+    /// Save `usage` as the next generation of the usage state, and start a new journal applying to it. The journal
+    /// must be locked.
+    fn compact(&mut self, usage: &UsageInfo<DiskBlockKey>) -> io::Result<()> {
+        let generation = self.generation + 1;
+        write_usage_state(&self.state_path, &usage.to_state(generation))?;
+        trace!(path = ?self.path, generation, records = self.records, "compacted usage journal");
+        self.generation = generation;
+        self.reset()
+    }
+
+    /// Start a new, empty journal applying to the current generation of the usage state.
+    fn reset(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&self.generation.to_le_bytes())?;
+        self.offset = HEADER_SIZE;
+        self.records = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::object::ObjectId;
+
+    fn block_key(index: u64) -> DiskBlockKey {
+        DiskBlockKey::new(&ObjectId::new("key".into(), ETag::for_tests()), index)
+    }
+
+    fn add(journal: &mut UsageJournal, usage: &mut UsageInfo<DiskBlockKey>, index: u64) {
+        journal.sync(usage).unwrap();
+        let record = UsageRecord::Add {
+            key: block_key(index),
+            size: 10,
+            checksum: 0,
+            pinned: false,
+            account: None,
+            added_at: 0,
+        };
+        record.clone().apply(usage);
+        journal.finish(&[record], usage).unwrap();
+    }
+
+    #[test]
+    fn test_changes_are_shared() {
+        let directory = tempfile::tempdir().unwrap();
+        let (mut first, mut first_usage) = UsageJournal::open(directory.path(), EvictionPolicy::Lru, |_| true).unwrap();
+        let (mut second, mut second_usage) =
+            UsageJournal::open(directory.path(), EvictionPolicy::Lru, |_| true).unwrap();
+
+        add(&mut first, &mut first_usage, 0);
+        add(&mut second, &mut second_usage, 1);
+        assert_eq!(second_usage.size, 20, "the second journal applied the first record");
+
+        first.sync(&mut first_usage).unwrap();
+        first.finish(&[], &first_usage).unwrap();
+        assert_eq!(first_usage.size, 20);
+        assert_eq!(
+            first_usage.eviction_order.values().collect::<Vec<_>>(),
+            second_usage.eviction_order.values().collect::<Vec<_>>(),
+        );
+
+        let (_, third_usage) = UsageJournal::open(directory.path(), EvictionPolicy::Lru, |_| true).unwrap();
+        assert_eq!(third_usage.size, 20, "a new journal applies the existing records");
+    }
+
+    #[test]
+    fn test_compaction() {
+        let directory = tempfile::tempdir().unwrap();
+        let (mut first, mut first_usage) = UsageJournal::open(directory.path(), EvictionPolicy::Lru, |_| true).unwrap();
+        let (mut second, mut second_usage) =
+            UsageJournal::open(directory.path(), EvictionPolicy::Lru, |_| true).unwrap();
+
+        add(&mut first, &mut first_usage, 0);
+        let accesses = vec![UsageRecord::Access { key: block_key(0) }; COMPACTION_MIN_RECORDS as usize];
+        first.sync(&mut first_usage).unwrap();
+        first.finish(&accesses, &first_usage).unwrap();
+        assert_eq!(first.generation, 1, "the journal was compacted");
+        assert_eq!(first.records, 0);
+
+        add(&mut second, &mut second_usage, 1);
+        assert_eq!(second.generation, 1, "the second journal reloaded the usage state");
+        assert_eq!(second_usage.size, 20);
+    }
+}
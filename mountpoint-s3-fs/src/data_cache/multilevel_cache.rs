@@ -9,13 +9,13 @@ use crate::{Runtime, object::ObjectId};
 use super::{BlockIndex, ChecksummedBytes, DataCache, DataCacheResult};
 
 /// A data cache which uses both the local disk and S3 Express One Zone bucket as a storage.
-pub struct MultilevelDataCache<DiskCache, ExpressCache> {
+pub struct MultilevelDataCache<DiskCache: ?Sized, ExpressCache> {
     disk_cache: Arc<DiskCache>,
     express_cache: ExpressCache,
     runtime: Runtime,
 }
 
-impl<DiskCache: DataCache, ExpressCache: DataCache> MultilevelDataCache<DiskCache, ExpressCache> {
+impl<DiskCache: DataCache + ?Sized, ExpressCache: DataCache> MultilevelDataCache<DiskCache, ExpressCache> {
     /// Both the `disk_cache` and `express_cache` must be configured with the same `block_size`.
     pub fn new(disk_cache: Arc<DiskCache>, express_cache: ExpressCache, runtime: Runtime) -> Self {
         // The same blocks are written to both caches. The `block_size`-s must match.
@@ -35,7 +35,7 @@ impl<DiskCache: DataCache, ExpressCache: DataCache> MultilevelDataCache<DiskCach
 #[async_trait]
 impl<DiskCache, ExpressCache> DataCache for MultilevelDataCache<DiskCache, ExpressCache>
 where
-    DiskCache: DataCache + Sync + Send + 'static,
+    DiskCache: DataCache + Sync + Send + ?Sized + 'static,
     ExpressCache: DataCache + Sync,
 {
     /// Gets a block from one of the underlying caches. Populates the disk cache with data fetched from the S3 Express cache.
@@ -135,6 +135,15 @@ mod tests {
                 cache_directory: cache_directory.path().to_path_buf(),
                 block_size: BLOCK_SIZE,
                 limit: CacheLimit::Unbounded,
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             },
             pool,
         );
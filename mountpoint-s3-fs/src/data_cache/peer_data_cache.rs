@@ -0,0 +1,846 @@
+//! A data cache made of the local caches of the other mounts of a cluster.
+//!
+//! Each block is owned by one of the peers, chosen by consistent hashing of the block ID, so that all the peers agree on
+//! it and only the blocks of a peer move to the others when it is added or removed. Blocks missing from the local cache
+//! are requested from their owner before falling back to S3, and blocks fetched from S3 are sent to their owner, where
+//! the other peers find them.
+//!
+//! Peers talk a small subset of HTTP/1.1 over TCP, on connections kept open between requests:
+//!
+//! * `GET /v1/blocks/<key>/<etag>/<block index>?offset=<offset>&object_size=<size>` returns the data of the block, or
+//!   `404 Not Found` if it isn't in the local cache of the peer.
+//! * `PUT` on the same path stores the data of the block in the local cache of the peer, and returns `204 No Content`.
+//!
+//! Keys and ETags are hex-encoded, and the CRC32C checksum of the data is sent in the `x-mountpoint-crc32c` header, so
+//! that blocks are validated end to end. Blocks are sent unencrypted and peers aren't authenticated, so peers must only
+//! be reachable on a trusted network.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use mountpoint_s3_client::checksums::crc32c::Crc32c;
+use mountpoint_s3_client::types::ETag;
+use sha2::{Digest, Sha256};
+use tracing::{debug, trace, warn};
+
+use crate::metrics::defs::{
+    ATTR_CACHE, CACHE_GET_ERRORS, CACHE_GET_IO_SIZE, CACHE_GET_LATENCY, CACHE_PEER, CACHE_PEER_SERVED_BLOCKS,
+    CACHE_PUT_ERRORS, CACHE_PUT_IO_SIZE, CACHE_PUT_LATENCY,
+};
+use crate::object::ObjectId;
+use crate::sync::{Arc, Mutex, async_channel};
+
+use super::{BlockIndex, ChecksummedBytes, DataCache, DataCacheError, DataCacheResult};
+
+/// Default timeout of the connections and requests to the peers.
+pub const DEFAULT_PEER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of points of each peer on the hash ring. More points spread the blocks more evenly across the peers.
+const POINTS_PER_PEER: usize = 128;
+
+/// Number of threads sending requests to the peers.
+const CLIENT_THREADS: usize = 16;
+
+/// Maximum number of requests waiting for a client thread.
+const MAX_PENDING_REQUESTS: usize = 1024;
+
+/// How long a peer that failed to respond is skipped for.
+const PEER_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long connections from the peers are kept open without requests.
+const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Maximum size of the start line and headers of a message.
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+const CHECKSUM_HEADER: &str = "x-mountpoint-crc32c";
+
+/// Configuration for a [PeerDataCache].
+#[derive(Debug, Clone)]
+pub struct PeerCacheConfig {
+    /// Address the blocks of the local cache are served to the peers on, which should be one of the `peers`.
+    pub address: SocketAddr,
+    /// Addresses of all the mounts of the cluster, including this one. Must be the same for all of them, so that they
+    /// agree on the owner of each block.
+    pub peers: Vec<SocketAddr>,
+    /// Size of data blocks, which must be the same for all the peers.
+    pub block_size: u64,
+    /// Timeout of the connections and requests to the peers.
+    pub timeout: Duration,
+}
+
+impl PeerCacheConfig {
+    pub fn new(address: SocketAddr, peers: Vec<SocketAddr>) -> Self {
+        Self {
+            address,
+            peers,
+            block_size: 1024 * 1024, // 1 MiB
+            timeout: DEFAULT_PEER_TIMEOUT,
+        }
+    }
+
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// A data cache storing each block in the local cache of the peer owning it. Also serves the blocks of the local cache
+/// of this mount to the peers, until dropped.
+///
+/// Blocks owned by this mount are neither stored nor returned: this cache is meant to be used behind the local cache,
+/// for example in a [super::MultilevelDataCache].
+pub struct PeerDataCache {
+    config: PeerCacheConfig,
+    ring: PeerRing,
+    client: PeerClient,
+    /// When each of the peers that failed to respond can be requested again.
+    unavailable: Mutex<HashMap<SocketAddr, Instant>>,
+    _server: PeerCacheServer,
+}
+
+impl PeerDataCache {
+    /// Create a new instance, serving the blocks of `local_cache` to the peers on the address of `config`.
+    pub fn new(config: PeerCacheConfig, local_cache: Arc<dyn DataCache + Send + Sync>) -> io::Result<Self> {
+        assert_eq!(local_cache.block_size(), config.block_size, "block sizes must be equal");
+        let server = PeerCacheServer::spawn(config.address, local_cache)?;
+        let client = PeerClient::new(config.timeout, config.block_size as usize)?;
+        debug!(address = ?config.address, peers = ?config.peers, "serving the local cache to the peers");
+        Ok(Self {
+            ring: PeerRing::new(&config.peers),
+            config,
+            client,
+            unavailable: Default::default(),
+            _server: server,
+        })
+    }
+
+    /// The peer owning a block, or `None` if it is owned by this mount or its owner is unavailable.
+    fn remote_owner(&self, cache_key: &ObjectId, block_idx: BlockIndex) -> Option<SocketAddr> {
+        let owner = self.ring.owner(&block_resource(cache_key, block_idx))?;
+        if owner == self.config.address {
+            return None;
+        }
+        let mut unavailable = self.unavailable.lock().unwrap();
+        match unavailable.get(&owner) {
+            Some(retry_at) if *retry_at > Instant::now() => return None,
+            Some(_) => _ = unavailable.remove(&owner),
+            None => {}
+        }
+        Some(owner)
+    }
+
+    /// Send `request` to `peer`, and skip the peer for a while if it doesn't respond.
+    async fn send(&self, peer: SocketAddr, request: PeerRequest) -> io::Result<PeerResponse> {
+        let result = self.client.send(peer, request).await;
+        if let Err(error) = &result {
+            warn!(
+                ?peer,
+                ?error,
+                "cache peer didn't respond, skipping it for {PEER_RETRY_INTERVAL:?}"
+            );
+            self.unavailable
+                .lock()
+                .unwrap()
+                .insert(peer, Instant::now() + PEER_RETRY_INTERVAL);
+        }
+        result
+    }
+
+    async fn read_block(
+        &self,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        object_size: usize,
+    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+        let Some(peer) = self.remote_owner(cache_key, block_idx) else {
+            return Ok(None);
+        };
+        trace!(?cache_key, block_idx, ?peer, "requesting block from cache peer");
+        let request = PeerRequest {
+            method: "GET",
+            path: block_path(cache_key, block_idx, block_offset, object_size),
+            checksum: None,
+            body: Bytes::new(),
+        };
+        let response = self.send(peer, request).await?;
+        match response.status {
+            200 => {
+                let checksum = response.checksum.ok_or(DataCacheError::InvalidBlockChecksum)?;
+                Ok(Some(ChecksummedBytes::new_from_inner_data(response.body, checksum)))
+            }
+            404 => Ok(None),
+            status => Err(DataCacheError::IoFailure(anyhow!(
+                "cache peer {peer} responded with status {status}"
+            ))),
+        }
+    }
+
+    async fn write_block(
+        &self,
+        cache_key: ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        bytes: ChecksummedBytes,
+        object_size: usize,
+    ) -> DataCacheResult<()> {
+        let Some(peer) = self.remote_owner(&cache_key, block_idx) else {
+            return Ok(());
+        };
+        trace!(?cache_key, block_idx, ?peer, "sending block to cache peer");
+        let (body, checksum) = bytes.into_inner().map_err(|_| DataCacheError::InvalidBlockChecksum)?;
+        let request = PeerRequest {
+            method: "PUT",
+            path: block_path(&cache_key, block_idx, block_offset, object_size),
+            checksum: Some(checksum),
+            body,
+        };
+        let response = self.send(peer, request).await?;
+        match response.status {
+            204 => Ok(()),
+            status => Err(DataCacheError::IoFailure(anyhow!(
+                "cache peer {peer} responded with status {status}"
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl DataCache for PeerDataCache {
+    async fn get_block(
+        &self,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        object_size: usize,
+    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+        let start = Instant::now();
+        let result = match self.read_block(cache_key, block_idx, block_offset, object_size).await {
+            Ok(Some(data)) => {
+                metrics::histogram!(CACHE_GET_IO_SIZE, ATTR_CACHE => CACHE_PEER).record(data.len() as f64);
+                Ok(Some(data))
+            }
+            Ok(None) => Ok(None),
+            Err(err) => {
+                metrics::counter!(CACHE_GET_ERRORS, ATTR_CACHE => CACHE_PEER).increment(1);
+                Err(err)
+            }
+        };
+        metrics::histogram!(CACHE_GET_LATENCY, ATTR_CACHE => CACHE_PEER).record(start.elapsed().as_micros() as f64);
+        result
+    }
+
+    async fn put_block(
+        &self,
+        cache_key: ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        bytes: ChecksummedBytes,
+        object_size: usize,
+    ) -> DataCacheResult<()> {
+        let start = Instant::now();
+        let bytes_len = bytes.len();
+        let result = match self
+            .write_block(cache_key, block_idx, block_offset, bytes, object_size)
+            .await
+        {
+            Ok(()) => {
+                metrics::histogram!(CACHE_PUT_IO_SIZE, ATTR_CACHE => CACHE_PEER).record(bytes_len as f64);
+                Ok(())
+            }
+            Err(err) => {
+                metrics::counter!(CACHE_PUT_ERRORS, ATTR_CACHE => CACHE_PEER).increment(1);
+                Err(err)
+            }
+        };
+        metrics::histogram!(CACHE_PUT_LATENCY, ATTR_CACHE => CACHE_PEER).record(start.elapsed().as_micros() as f64);
+        result
+    }
+
+    fn block_size(&self) -> u64 {
+        self.config.block_size
+    }
+}
+
+/// Consistent hash ring of the peers.
+#[derive(Debug)]
+struct PeerRing {
+    /// Points of the peers on the ring, sorted by hash.
+    points: Vec<(u64, SocketAddr)>,
+}
+
+impl PeerRing {
+    fn new(peers: &[SocketAddr]) -> Self {
+        let mut points: Vec<_> = peers
+            .iter()
+            .flat_map(|peer| (0..POINTS_PER_PEER).map(move |i| (hash(&format!("{peer}/{i}")), *peer)))
+            .collect();
+        points.sort_unstable();
+        points.dedup_by_key(|(point, _)| *point);
+        Self { points }
+    }
+
+    /// The peer owning `resource`: the first one after its hash on the ring.
+    fn owner(&self, resource: &str) -> Option<SocketAddr> {
+        let hash = hash(resource);
+        let index = self.points.partition_point(|(point, _)| *point < hash);
+        self.points
+            .get(index)
+            .or_else(|| self.points.first())
+            .map(|(_, peer)| *peer)
+    }
+}
+
+fn hash(value: &str) -> u64 {
+    let digest = Sha256::digest(value.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+/// The path identifying a block, without the query parameters of requests for it.
+fn block_resource(cache_key: &ObjectId, block_idx: BlockIndex) -> String {
+    format!(
+        "/v1/blocks/{}/{}/{block_idx}",
+        hex::encode(cache_key.key()),
+        hex::encode(cache_key.etag().as_str())
+    )
+}
+
+fn block_path(cache_key: &ObjectId, block_idx: BlockIndex, block_offset: u64, object_size: usize) -> String {
+    format!(
+        "{}?offset={block_offset}&object_size={object_size}",
+        block_resource(cache_key, block_idx)
+    )
+}
+
+/// A block requested by a peer, as identified by the path of its request.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockRequest {
+    cache_key: ObjectId,
+    block_idx: BlockIndex,
+    block_offset: u64,
+    object_size: usize,
+}
+
+impl BlockRequest {
+    /// Parse the path of a request built by [block_path].
+    fn from_path(path: &str) -> Option<Self> {
+        let (path, query) = path.split_once('?')?;
+        let mut segments = path.strip_prefix("/v1/blocks/")?.split('/');
+        let key = String::from_utf8(hex::decode(segments.next()?).ok()?).ok()?;
+        let etag = String::from_utf8(hex::decode(segments.next()?).ok()?).ok()?;
+        let block_idx = segments.next()?.parse().ok()?;
+        if segments.next().is_some() {
+            return None;
+        }
+        let (mut block_offset, mut object_size) = (None, None);
+        for parameter in query.split('&') {
+            match parameter.split_once('=')? {
+                ("offset", value) => block_offset = Some(value.parse().ok()?),
+                ("object_size", value) => object_size = Some(value.parse().ok()?),
+                _ => {}
+            }
+        }
+        Some(Self {
+            cache_key: ObjectId::new(key, ETag::from(etag)),
+            block_idx,
+            block_offset: block_offset?,
+            object_size: object_size?,
+        })
+    }
+}
+
+/// A request sent to a peer.
+#[derive(Debug)]
+struct PeerRequest {
+    method: &'static str,
+    path: String,
+    checksum: Option<Crc32c>,
+    body: Bytes,
+}
+
+/// A response from a peer.
+#[derive(Debug)]
+struct PeerResponse {
+    status: u16,
+    checksum: Option<Crc32c>,
+    body: Bytes,
+}
+
+type PendingRequest = (SocketAddr, PeerRequest, oneshot::Sender<io::Result<PeerResponse>>);
+
+/// Sends the requests to the peers from a pool of threads, so that waiting for the network doesn't block the threads
+/// getting and putting blocks.
+#[derive(Debug)]
+struct PeerClient {
+    requests: async_channel::Sender<PendingRequest>,
+}
+
+impl PeerClient {
+    /// Start the client threads, which stop once the client is dropped.
+    fn new(timeout: Duration, max_body_size: usize) -> io::Result<Self> {
+        let (requests, receiver) = async_channel::bounded::<PendingRequest>(MAX_PENDING_REQUESTS);
+        for _ in 0..CLIENT_THREADS {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name("cache-peer-client".to_owned())
+                .spawn(move || {
+                    let mut connections = HashMap::new();
+                    while let Ok((peer, request, done)) = receiver.recv_blocking() {
+                        let result = send_request(&mut connections, peer, &request, timeout, max_body_size);
+                        // The future waiting for the response may have been dropped.
+                        _ = done.send(result);
+                    }
+                })?;
+        }
+        Ok(Self { requests })
+    }
+
+    async fn send(&self, peer: SocketAddr, request: PeerRequest) -> io::Result<PeerResponse> {
+        let (done, receiver) = oneshot::channel();
+        let stopped = || io::Error::other("the cache peer client stopped");
+        self.requests.send((peer, request, done)).await.map_err(|_| stopped())?;
+        receiver.await.map_err(|_| stopped())?
+    }
+}
+
+/// Send `request` to `peer`, on the connection to it in `connections` if any.
+fn send_request(
+    connections: &mut HashMap<SocketAddr, BufReader<TcpStream>>,
+    peer: SocketAddr,
+    request: &PeerRequest,
+    timeout: Duration,
+    max_body_size: usize,
+) -> io::Result<PeerResponse> {
+    // The peer may have closed the connection since the last request, in which case it is retried on a new one.
+    if let Some(mut connection) = connections.remove(&peer)
+        && let Ok(response) = exchange(&mut connection, request, max_body_size)
+    {
+        connections.insert(peer, connection);
+        return Ok(response);
+    }
+    let stream = TcpStream::connect_timeout(&peer, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    stream.set_nodelay(true)?;
+    let mut connection = BufReader::new(stream);
+    let response = exchange(&mut connection, request, max_body_size)?;
+    connections.insert(peer, connection);
+    Ok(response)
+}
+
+fn exchange(
+    connection: &mut BufReader<TcpStream>,
+    request: &PeerRequest,
+    max_body_size: usize,
+) -> io::Result<PeerResponse> {
+    let start_line = format!("{} {} HTTP/1.1", request.method, request.path);
+    write_message(
+        &mut BufWriter::new(connection.get_mut()),
+        &start_line,
+        request.checksum,
+        &request.body,
+    )?;
+    let response = read_message(connection, max_body_size)?.ok_or(ErrorKind::UnexpectedEof)?;
+    let status = response
+        .start_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid_data("invalid status line"))?;
+    Ok(PeerResponse {
+        status,
+        checksum: response.checksum()?,
+        body: response.body,
+    })
+}
+
+/// Serves the blocks of the local cache to the peers, until dropped.
+#[derive(Debug)]
+struct PeerCacheServer {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl PeerCacheServer {
+    fn spawn(address: SocketAddr, cache: Arc<dyn DataCache + Send + Sync>) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let server_stopped = stopped.clone();
+        thread::Builder::new()
+            .name("cache-peer-server".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if server_stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(error) => {
+                            warn!(?error, "unable to accept cache peer connection");
+                            continue;
+                        }
+                    };
+                    let cache = cache.clone();
+                    let result = thread::Builder::new()
+                        .name("cache-peer-connection".to_owned())
+                        .spawn(move || serve_connection(stream, cache.as_ref()));
+                    if let Err(error) = result {
+                        warn!(?error, "unable to serve cache peer connection");
+                    }
+                }
+            })?;
+        Ok(Self { address, stopped })
+    }
+}
+
+impl Drop for PeerCacheServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake up the thread accepting the connections, so that it stops.
+        _ = TcpStream::connect_timeout(&self.address, DEFAULT_PEER_TIMEOUT);
+    }
+}
+
+fn serve_connection(stream: TcpStream, cache: &(dyn DataCache + Send + Sync)) {
+    let peer = stream.peer_addr().ok();
+    trace!(?peer, "cache peer connected");
+    if let Err(error) = serve_requests(stream, cache) {
+        debug!(?peer, ?error, "cache peer connection closed");
+    }
+}
+
+fn serve_requests(mut stream: TcpStream, cache: &(dyn DataCache + Send + Sync)) -> io::Result<()> {
+    stream.set_read_timeout(Some(IDLE_CONNECTION_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    while let Some(request) = read_message(&mut reader, cache.block_size() as usize)? {
+        let (status, checksum, body) = serve_request(&request, cache);
+        let start_line = format!("HTTP/1.1 {status}");
+        write_message(&mut BufWriter::new(&mut stream), &start_line, checksum, &body)?;
+    }
+    Ok(())
+}
+
+fn serve_request(request: &Message, cache: &(dyn DataCache + Send + Sync)) -> (&'static str, Option<Crc32c>, Bytes) {
+    const OK: &str = "200 OK";
+    const NO_CONTENT: &str = "204 No Content";
+    const BAD_REQUEST: &str = "400 Bad Request";
+    const NOT_FOUND: &str = "404 Not Found";
+    const METHOD_NOT_ALLOWED: &str = "405 Method Not Allowed";
+    const INTERNAL_SERVER_ERROR: &str = "500 Internal Server Error";
+
+    let mut start_line = request.start_line.split(' ');
+    let (Some(method), Some(path)) = (start_line.next(), start_line.next()) else {
+        return (BAD_REQUEST, None, Bytes::new());
+    };
+    let Some(block) = BlockRequest::from_path(path) else {
+        return (NOT_FOUND, None, Bytes::new());
+    };
+    let status = match method {
+        "GET" => {
+            match block_on(cache.get_block(&block.cache_key, block.block_idx, block.block_offset, block.object_size)) {
+                Ok(Some(data)) => match data.into_inner() {
+                    Ok((bytes, checksum)) => {
+                        metrics::counter!(CACHE_PEER_SERVED_BLOCKS).increment(1);
+                        return (OK, Some(checksum), bytes);
+                    }
+                    Err(error) => {
+                        warn!(cache_key = ?block.cache_key, block.block_idx, ?error, "cache block is corrupted");
+                        INTERNAL_SERVER_ERROR
+                    }
+                },
+                Ok(None) => NOT_FOUND,
+                Err(error) => {
+                    warn!(cache_key = ?block.cache_key, block.block_idx, ?error, "unable to read block for cache peer");
+                    INTERNAL_SERVER_ERROR
+                }
+            }
+        }
+        "PUT" => {
+            let Ok(Some(checksum)) = request.checksum() else {
+                return (BAD_REQUEST, None, Bytes::new());
+            };
+            let data = ChecksummedBytes::new_from_inner_data(request.body.clone(), checksum);
+            if data.validate().is_err() {
+                return (BAD_REQUEST, None, Bytes::new());
+            }
+            match block_on(cache.put_block(
+                block.cache_key.clone(),
+                block.block_idx,
+                block.block_offset,
+                data,
+                block.object_size,
+            )) {
+                Ok(()) => NO_CONTENT,
+                Err(error) => {
+                    warn!(cache_key = ?block.cache_key, block.block_idx, ?error, "unable to store block from cache peer");
+                    INTERNAL_SERVER_ERROR
+                }
+            }
+        }
+        _ => METHOD_NOT_ALLOWED,
+    };
+    (status, None, Bytes::new())
+}
+
+/// The start line, headers, and body of a request or response.
+#[derive(Debug)]
+struct Message {
+    start_line: String,
+    /// Headers, with lowercase names.
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Message {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn checksum(&self) -> io::Result<Option<Crc32c>> {
+        self.header(CHECKSUM_HEADER)
+            .map(|checksum| {
+                checksum
+                    .parse()
+                    .map(Crc32c::new)
+                    .map_err(|_| invalid_data("invalid checksum header"))
+            })
+            .transpose()
+    }
+}
+
+/// Read a message from `reader`, or `None` if the connection was closed before it started. Messages with bodies larger
+/// than `max_body_size` are rejected.
+fn read_message(reader: &mut impl BufRead, max_body_size: usize) -> io::Result<Option<Message>> {
+    let mut lines = Vec::new();
+    let mut head_size = 0;
+    loop {
+        let mut line = String::new();
+        let limit = (MAX_HEAD_SIZE - head_size + 1) as u64;
+        let len = reader.take(limit).read_line(&mut line)?;
+        head_size += len;
+        if len == 0 {
+            if lines.is_empty() && head_size == 0 {
+                return Ok(None);
+            }
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        if head_size > MAX_HEAD_SIZE {
+            return Err(invalid_data("message head too large"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        match line {
+            // Empty lines before the start line are ignored.
+            "" if lines.is_empty() => continue,
+            "" => break,
+            line => lines.push(line.to_owned()),
+        }
+    }
+
+    let mut lines = lines.into_iter();
+    let start_line = lines.next().expect("message should have a start line");
+    let headers = lines
+        .map(|line| {
+            let (name, value) = line.split_once(':').ok_or_else(|| invalid_data("invalid header"))?;
+            Ok((name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut message = Message {
+        start_line,
+        headers,
+        body: Bytes::new(),
+    };
+    let body_size = match message.header("content-length") {
+        Some(len) => len.parse().map_err(|_| invalid_data("invalid content length"))?,
+        None => 0,
+    };
+    if body_size > max_body_size {
+        return Err(invalid_data("message body too large"));
+    }
+    let mut body = vec![0; body_size];
+    reader.read_exact(&mut body)?;
+    message.body = body.into();
+    Ok(Some(message))
+}
+
+fn write_message(writer: &mut impl Write, start_line: &str, checksum: Option<Crc32c>, body: &[u8]) -> io::Result<()> {
+    let mut head = format!("{start_line}\r\ncontent-length: {}\r\n", body.len());
+    if let Some(checksum) = checksum {
+        _ = write!(head, "{CHECKSUM_HEADER}: {}\r\n", checksum.value());
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(body)?;
+    writer.flush()
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use test_case::test_case;
+
+    use super::*;
+    use crate::data_cache::InMemoryDataCache;
+
+    const BLOCK_SIZE: u64 = 1024;
+
+    /// Addresses on the loopback interface that are free to listen on.
+    fn free_addresses(count: usize) -> Vec<SocketAddr> {
+        let listeners: Vec<_> = (0..count).map(|_| TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+        listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect()
+    }
+
+    fn new_peer(address: SocketAddr, peers: &[SocketAddr]) -> (Arc<InMemoryDataCache>, PeerDataCache) {
+        let local_cache = Arc::new(InMemoryDataCache::new(BLOCK_SIZE));
+        let config = PeerCacheConfig::new(address, peers.to_vec()).block_size(BLOCK_SIZE);
+        let peer_cache = PeerDataCache::new(config, local_cache.clone()).unwrap();
+        (local_cache, peer_cache)
+    }
+
+    /// A block of `peers[0]` that is owned by `owner`.
+    fn block_owned_by(peer_cache: &PeerDataCache, owner: SocketAddr) -> ObjectId {
+        (0..)
+            .map(|i| ObjectId::new(format!("key{i}"), ETag::for_tests()))
+            .find(|cache_key| peer_cache.ring.owner(&block_resource(cache_key, 0)) == Some(owner))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_and_put_blocks() {
+        let peers = free_addresses(2);
+        let (_, cache_a) = new_peer(peers[0], &peers);
+        let (local_b, cache_b) = new_peer(peers[1], &peers);
+        let data = ChecksummedBytes::new("Foo".into());
+
+        // Blocks are sent to their owner, and found there by the other peers.
+        let remote_key = block_owned_by(&cache_a, peers[1]);
+        cache_a
+            .put_block(remote_key.clone(), 0, 0, data.clone(), 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            local_b.get_block(&remote_key, 0, 0, 3).await.unwrap(),
+            Some(data.clone())
+        );
+        assert_eq!(
+            cache_a.get_block(&remote_key, 0, 0, 3).await.unwrap(),
+            Some(data.clone())
+        );
+        assert_eq!(
+            cache_a.get_block(&remote_key, 1, BLOCK_SIZE, 2048).await.unwrap(),
+            None,
+            "blocks missing from their owner are not found"
+        );
+
+        // Blocks owned by this mount are left to the local cache.
+        let local_key = block_owned_by(&cache_a, peers[0]);
+        cache_a
+            .put_block(local_key.clone(), 0, 0, data.clone(), 3)
+            .await
+            .unwrap();
+        assert_eq!(cache_b.get_block(&local_key, 0, 0, 3).await.unwrap(), None);
+        assert_eq!(cache_a.get_block(&local_key, 0, 0, 3).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_peer() {
+        let peers = free_addresses(2);
+        let (_, cache) = new_peer(peers[0], &peers);
+        let cache_key = block_owned_by(&cache, peers[1]);
+        cache
+            .get_block(&cache_key, 0, 0, 3)
+            .await
+            .expect_err("requests to unavailable peers fail");
+        assert_eq!(
+            cache.get_block(&cache_key, 0, 0, 3).await.unwrap(),
+            None,
+            "unavailable peers are skipped"
+        );
+    }
+
+    #[test]
+    fn test_ring() {
+        let peers: Vec<SocketAddr> = (0..4).map(|i| SocketAddr::from(([10, 0, 0, i], 7000))).collect();
+        let ring = PeerRing::new(&peers);
+        let resources: Vec<_> = (0..1000).map(|i| format!("/v1/blocks/{i}")).collect();
+        let owners: Vec<_> = resources.iter().map(|resource| ring.owner(resource).unwrap()).collect();
+        for peer in &peers {
+            let count = owners.iter().filter(|owner| *owner == peer).count();
+            assert!((150..350).contains(&count), "blocks are spread across peers: {count}");
+        }
+
+        // Removing a peer only moves its blocks.
+        let smaller_ring = PeerRing::new(&peers[1..]);
+        for (resource, owner) in resources.iter().zip(&owners) {
+            let new_owner = smaller_ring.owner(resource).unwrap();
+            assert!(*owner == peers[0] || new_owner == *owner);
+        }
+        assert_eq!(PeerRing::new(&[]).owner("/v1/blocks/0"), None);
+    }
+
+    #[test_case("key", "etag"; "simple")]
+    #[test_case("dir/key with spaces?&=", "\"quoted-etag\""; "special characters")]
+    fn test_block_path(key: &str, etag: &str) {
+        let cache_key = ObjectId::new(key.to_owned(), ETag::from(etag));
+        let path = block_path(&cache_key, 3, 3 * BLOCK_SIZE, 10_000);
+        assert_eq!(
+            BlockRequest::from_path(&path),
+            Some(BlockRequest {
+                cache_key,
+                block_idx: 3,
+                block_offset: 3 * BLOCK_SIZE,
+                object_size: 10_000,
+            })
+        );
+        assert_eq!(BlockRequest::from_path("/v1/blocks/00/00/0"), None);
+        assert_eq!(
+            BlockRequest::from_path("/v1/blocks/zz/00/0?offset=0&object_size=0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut message = Vec::new();
+        write_message(&mut message, "PUT /path HTTP/1.1", Some(Crc32c::new(42)), b"Foo").unwrap();
+        let mut reader = Cursor::new(message.clone());
+        let request = read_message(&mut reader, 3).unwrap().unwrap();
+        assert_eq!(request.start_line, "PUT /path HTTP/1.1");
+        assert_eq!(request.checksum().unwrap(), Some(Crc32c::new(42)));
+        assert_eq!(request.body, "Foo");
+        assert!(read_message(&mut reader, 3).unwrap().is_none());
+
+        read_message(&mut Cursor::new(message.clone()), 2).expect_err("bodies larger than the limit are rejected");
+        read_message(&mut Cursor::new(&message[..message.len() - 1]), 3).expect_err("truncated messages are invalid");
+        let long_header = format!("GET / HTTP/1.1\r\nx: {}\r\n\r\n", "a".repeat(MAX_HEAD_SIZE));
+        read_message(&mut Cursor::new(long_header), 0).expect_err("heads larger than the limit are rejected");
+    }
+}
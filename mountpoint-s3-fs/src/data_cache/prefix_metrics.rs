@@ -0,0 +1,187 @@
+//! A data cache reporting its hits and misses for each prefix of the object keys.
+//!
+//! The metrics of the caches themselves only tell how well the cache performs overall. Breaking
+//! them down by the first directories of the keys shows which datasets actually benefit from the
+//! cache, and helps sizing it for the datasets that matter.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::metrics::defs::{
+    ATTR_PREFIX, CACHE_PREFIX_FILL_BYTES, CACHE_PREFIX_HIT_BYTES, CACHE_PREFIX_HIT_RATIO, CACHE_PREFIX_HITS,
+    CACHE_PREFIX_MISSES,
+};
+use crate::object::ObjectId;
+use crate::sync::Mutex;
+
+use super::{BlockIndex, ChecksummedBytes, DataCache, DataCacheResult};
+
+/// Configuration of the prefixes the metrics of a [PrefixMetricsDataCache] are reported for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixMetricsConfig {
+    /// Number of directories below `mounted_prefix` the metrics are broken down by. Objects less
+    /// deep than this are reported under their own directory.
+    pub depth: usize,
+    /// Prefix of all the keys read through the cache, not counted towards `depth`.
+    pub mounted_prefix: String,
+}
+
+/// A [DataCache] recording metrics of the blocks read from and written to an inner cache for each
+/// prefix of their keys.
+pub struct PrefixMetricsDataCache<Cache> {
+    cache: Cache,
+    config: PrefixMetricsConfig,
+    /// Hits and misses for each prefix, to report their ratio.
+    lookups: Mutex<HashMap<String, PrefixLookups>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct PrefixLookups {
+    hits: u64,
+    misses: u64,
+}
+
+impl<Cache: DataCache> PrefixMetricsDataCache<Cache> {
+    /// Create a [PrefixMetricsDataCache] over `cache`.
+    pub fn new(cache: Cache, config: PrefixMetricsConfig) -> Self {
+        Self {
+            cache,
+            config,
+            lookups: Default::default(),
+        }
+    }
+
+    /// The prefix `key` is reported under: the mounted prefix followed by up to `depth` directories.
+    fn prefix_of(&self, key: &str) -> String {
+        let mounted_prefix = &self.config.mounted_prefix;
+        let relative_key = key.strip_prefix(mounted_prefix.as_str()).unwrap_or(key);
+        let mut prefix = mounted_prefix.clone();
+        let mut directories = relative_key.split('/');
+        // The last component is the name of the object itself.
+        directories.next_back();
+        for directory in directories.take(self.config.depth) {
+            prefix.push_str(directory);
+            prefix.push('/');
+        }
+        if prefix.is_empty() {
+            prefix.push('/');
+        }
+        prefix
+    }
+
+    fn record_lookup(&self, prefix: String, hit: bool) {
+        let ratio = {
+            let mut lookups = self.lookups.lock().unwrap();
+            let lookups = lookups.entry(prefix.clone()).or_default();
+            if hit {
+                lookups.hits += 1;
+            } else {
+                lookups.misses += 1;
+            }
+            lookups.hits as f64 / (lookups.hits + lookups.misses) as f64
+        };
+        if hit {
+            metrics::counter!(CACHE_PREFIX_HITS, ATTR_PREFIX => prefix.clone()).increment(1);
+        } else {
+            metrics::counter!(CACHE_PREFIX_MISSES, ATTR_PREFIX => prefix.clone()).increment(1);
+        }
+        metrics::gauge!(CACHE_PREFIX_HIT_RATIO, ATTR_PREFIX => prefix).set(ratio);
+    }
+}
+
+#[async_trait]
+impl<Cache> DataCache for PrefixMetricsDataCache<Cache>
+where
+    Cache: DataCache + Send + Sync,
+{
+    /// Gets a block from the inner cache, counting a hit or a miss for the prefix of its key.
+    /// Errors count as misses, as the block is then fetched from S3.
+    async fn get_block(
+        &self,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        object_size: usize,
+    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+        let result = self
+            .cache
+            .get_block(cache_key, block_idx, block_offset, object_size)
+            .await;
+        let prefix = self.prefix_of(cache_key.key());
+        if let Ok(Some(bytes)) = &result {
+            metrics::counter!(CACHE_PREFIX_HIT_BYTES, ATTR_PREFIX => prefix.clone()).increment(bytes.len() as u64);
+        }
+        self.record_lookup(prefix, matches!(result, Ok(Some(_))));
+        result
+    }
+
+    /// Puts a block to the inner cache, counting its bytes as filled from S3 for the prefix of its key.
+    async fn put_block(
+        &self,
+        cache_key: ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        bytes: ChecksummedBytes,
+        object_size: usize,
+    ) -> DataCacheResult<()> {
+        let prefix = self.prefix_of(cache_key.key());
+        let len = bytes.len() as u64;
+        self.cache
+            .put_block(cache_key, block_idx, block_offset, bytes, object_size)
+            .await?;
+        metrics::counter!(CACHE_PREFIX_FILL_BYTES, ATTR_PREFIX => prefix).increment(len);
+        Ok(())
+    }
+
+    fn block_size(&self) -> u64 {
+        self.cache.block_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::types::ETag;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::data_cache::InMemoryDataCache;
+
+    fn cache(depth: usize, mounted_prefix: &str) -> PrefixMetricsDataCache<InMemoryDataCache> {
+        let config = PrefixMetricsConfig {
+            depth,
+            mounted_prefix: mounted_prefix.to_owned(),
+        };
+        PrefixMetricsDataCache::new(InMemoryDataCache::new(1024), config)
+    }
+
+    #[test_case(1, "", "a.txt", "/")]
+    #[test_case(1, "", "train/a.txt", "train/")]
+    #[test_case(1, "", "train/images/a.txt", "train/")]
+    #[test_case(2, "", "train/images/a.txt", "train/images/")]
+    #[test_case(2, "", "train/a.txt", "train/")]
+    #[test_case(0, "", "train/a.txt", "/")]
+    #[test_case(1, "datasets/", "datasets/train/a.txt", "datasets/train/")]
+    #[test_case(1, "datasets/", "datasets/a.txt", "datasets/")]
+    fn test_prefix_of(depth: usize, mounted_prefix: &str, key: &str, expected: &str) {
+        assert_eq!(cache(depth, mounted_prefix).prefix_of(key), expected);
+    }
+
+    #[tokio::test]
+    async fn test_lookups() {
+        let cache = cache(1, "");
+        let train = ObjectId::new("train/a".into(), ETag::for_tests());
+        let test = ObjectId::new("test/a".into(), ETag::for_tests());
+        let block = ChecksummedBytes::new(b"Foo".as_slice().into());
+
+        assert_eq!(cache.get_block(&train, 0, 0, 3).await.unwrap(), None);
+        cache.put_block(train.clone(), 0, 0, block.clone(), 3).await.unwrap();
+        assert_eq!(cache.get_block(&train, 0, 0, 3).await.unwrap(), Some(block.clone()));
+        assert_eq!(cache.get_block(&train, 0, 0, 3).await.unwrap(), Some(block));
+        assert_eq!(cache.get_block(&test, 0, 0, 3).await.unwrap(), None);
+
+        let lookups = cache.lookups.lock().unwrap();
+        assert_eq!(lookups["train/"], PrefixLookups { hits: 2, misses: 1 });
+        assert_eq!(lookups["test/"], PrefixLookups { hits: 0, misses: 1 });
+    }
+}
@@ -0,0 +1,257 @@
+//! A data cache keeping the hottest blocks of a disk cache in memory.
+//!
+//! Blocks are written to the disk tier only. A block read from the disk tier is promoted to a
+//! bounded memory tier, so that blocks read repeatedly, for example those of small objects opened
+//! again and again, are served from memory. When the memory tier is full, the least recently read
+//! blocks are demoted back to the disk tier, which still holds them unless it evicted them too.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tracing::trace;
+
+use crate::metrics::defs::{ATTR_CACHE, CACHE_GET_IO_SIZE, CACHE_GET_LATENCY, CACHE_MEMORY, CACHE_TOTAL_SIZE};
+use crate::object::ObjectId;
+use crate::sync::Mutex;
+
+use super::{BlockIndex, ChecksummedBytes, DataCache, DataCacheError, DataCacheResult};
+
+/// Configuration for the memory tier of a [TieredDataCache].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryCacheConfig {
+    /// Maximum size of the blocks held in memory, in bytes.
+    pub max_size: usize,
+}
+
+/// A [DataCache] holding the most recently read blocks of a disk cache in memory.
+pub struct TieredDataCache<DiskCache> {
+    disk_cache: DiskCache,
+    memory: Mutex<MemoryTier>,
+    config: MemoryCacheConfig,
+}
+
+type MemoryBlockKey = (ObjectId, BlockIndex);
+
+/// Blocks held in memory, with the order to demote them in.
+#[derive(Debug, Default)]
+struct MemoryTier {
+    blocks: HashMap<MemoryBlockKey, MemoryBlock>,
+    /// Keys by last access, the first one being the next to demote.
+    demotion_order: BTreeMap<u64, MemoryBlockKey>,
+    size: usize,
+    /// Logical clock ordering the accesses to blocks.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct MemoryBlock {
+    bytes: ChecksummedBytes,
+    accessed: u64,
+}
+
+impl MemoryTier {
+    fn get(&mut self, key: &MemoryBlockKey) -> Option<ChecksummedBytes> {
+        self.clock += 1;
+        let block = self.blocks.get_mut(key)?;
+        self.demotion_order.remove(&block.accessed);
+        block.accessed = self.clock;
+        self.demotion_order.insert(block.accessed, key.clone());
+        Some(block.bytes.clone())
+    }
+
+    /// Insert a block, replacing any previous one for the same key, then demote blocks until the
+    /// tier is within `max_size`.
+    fn insert(&mut self, key: MemoryBlockKey, bytes: ChecksummedBytes, max_size: usize) {
+        self.remove(&key);
+        self.clock += 1;
+        self.size += bytes.len();
+        self.demotion_order.insert(self.clock, key.clone());
+        self.blocks.insert(
+            key,
+            MemoryBlock {
+                bytes,
+                accessed: self.clock,
+            },
+        );
+        while self.size > max_size {
+            let Some((_, key)) = self.demotion_order.pop_first() else {
+                break;
+            };
+            let block = self.blocks.remove(&key).expect("ordered keys have blocks");
+            self.size -= block.bytes.len();
+            trace!(cache_key = ?key.0, block_idx = key.1, "demoted block from memory");
+        }
+    }
+
+    fn remove(&mut self, key: &MemoryBlockKey) {
+        if let Some(block) = self.blocks.remove(key) {
+            self.demotion_order.remove(&block.accessed);
+            self.size -= block.bytes.len();
+        }
+    }
+}
+
+impl<DiskCache: DataCache> TieredDataCache<DiskCache> {
+    /// Create a [TieredDataCache] holding up to `config.max_size` bytes of the blocks of `disk_cache` in memory.
+    pub fn new(disk_cache: DiskCache, config: MemoryCacheConfig) -> Self {
+        Self {
+            disk_cache,
+            memory: Default::default(),
+            config,
+        }
+    }
+
+    /// Size of the blocks currently held in memory.
+    pub fn memory_size(&self) -> usize {
+        self.memory.lock().unwrap().size
+    }
+}
+
+#[async_trait]
+impl<DiskCache> DataCache for TieredDataCache<DiskCache>
+where
+    DiskCache: DataCache + Send + Sync,
+{
+    /// Gets a block from memory, or from the disk cache, promoting it to memory.
+    async fn get_block(
+        &self,
+        cache_key: &ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        object_size: usize,
+    ) -> DataCacheResult<Option<ChecksummedBytes>> {
+        if block_offset != block_idx * self.block_size() {
+            return Err(DataCacheError::InvalidBlockOffset);
+        }
+        let start = Instant::now();
+        let key = (cache_key.clone(), block_idx);
+        let in_memory = self.memory.lock().unwrap().get(&key);
+        if let Some(bytes) = in_memory {
+            trace!(?cache_key, block_idx, "block served from memory");
+            metrics::histogram!(CACHE_GET_IO_SIZE, ATTR_CACHE => CACHE_MEMORY).record(bytes.len() as f64);
+            metrics::histogram!(CACHE_GET_LATENCY, ATTR_CACHE => CACHE_MEMORY)
+                .record(start.elapsed().as_micros() as f64);
+            return Ok(Some(bytes));
+        }
+
+        let bytes = self
+            .disk_cache
+            .get_block(cache_key, block_idx, block_offset, object_size)
+            .await?;
+        if let Some(bytes) = &bytes
+            && bytes.len() <= self.config.max_size
+        {
+            trace!(?cache_key, block_idx, "promoted block to memory");
+            let mut memory = self.memory.lock().unwrap();
+            memory.insert(key, bytes.clone(), self.config.max_size);
+            metrics::gauge!(CACHE_TOTAL_SIZE, ATTR_CACHE => CACHE_MEMORY).set(memory.size as f64);
+        }
+        Ok(bytes)
+    }
+
+    /// Puts a block to the disk cache, replacing the block in memory if it was promoted.
+    async fn put_block(
+        &self,
+        cache_key: ObjectId,
+        block_idx: BlockIndex,
+        block_offset: u64,
+        bytes: ChecksummedBytes,
+        object_size: usize,
+    ) -> DataCacheResult<()> {
+        {
+            let mut memory = self.memory.lock().unwrap();
+            let key = (cache_key.clone(), block_idx);
+            if memory.blocks.contains_key(&key) {
+                memory.insert(key, bytes.clone(), self.config.max_size);
+            }
+        }
+        self.disk_cache
+            .put_block(cache_key, block_idx, block_offset, bytes, object_size)
+            .await
+    }
+
+    fn block_size(&self) -> u64 {
+        self.disk_cache.block_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::data_cache::InMemoryDataCache;
+    use crate::sync::Arc;
+
+    const BLOCK_SIZE: u64 = 1024;
+
+    fn block(data: &'static [u8]) -> ChecksummedBytes {
+        ChecksummedBytes::new(data.into())
+    }
+
+    #[tokio::test]
+    async fn test_promotion() {
+        let disk_cache = Arc::new(InMemoryDataCache::new(BLOCK_SIZE));
+        let cache = TieredDataCache::new(disk_cache.clone(), MemoryCacheConfig { max_size: 1024 });
+        let key = ObjectId::new("a".into(), ETag::for_tests());
+
+        cache.put_block(key.clone(), 0, 0, block(b"Foo"), 3).await.unwrap();
+        assert_eq!(cache.memory_size(), 0, "blocks are only written to the disk tier");
+        assert_eq!(disk_cache.block_count(&key), 1);
+
+        let bytes = cache.get_block(&key, 0, 0, 3).await.unwrap();
+        assert_eq!(bytes, Some(block(b"Foo")));
+        assert_eq!(cache.memory_size(), 3, "blocks read from the disk tier are promoted");
+
+        // Blocks in memory are served without reading the disk tier.
+        disk_cache.put_block(key.clone(), 0, 0, block(b"Bar"), 3).await.unwrap();
+        let bytes = cache.get_block(&key, 0, 0, 3).await.unwrap();
+        assert_eq!(bytes, Some(block(b"Foo")));
+
+        // Blocks written through the cache replace the blocks in memory.
+        cache.put_block(key.clone(), 0, 0, block(b"Baz"), 3).await.unwrap();
+        let bytes = cache.get_block(&key, 0, 0, 3).await.unwrap();
+        assert_eq!(bytes, Some(block(b"Baz")));
+
+        let missing = ObjectId::new("b".into(), ETag::for_tests());
+        assert_eq!(cache.get_block(&missing, 0, 0, 3).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_demotion() {
+        let disk_cache = InMemoryDataCache::new(BLOCK_SIZE);
+        let cache = TieredDataCache::new(disk_cache, MemoryCacheConfig { max_size: 6 });
+        let keys: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| ObjectId::new(key.into(), ETag::for_tests()))
+            .collect();
+        for key in &keys {
+            cache.put_block(key.clone(), 0, 0, block(b"Foo"), 3).await.unwrap();
+        }
+
+        let _ = cache.get_block(&keys[0], 0, 0, 3).await.unwrap();
+        let _ = cache.get_block(&keys[1], 0, 0, 3).await.unwrap();
+        let _ = cache.get_block(&keys[0], 0, 0, 3).await.unwrap();
+        let _ = cache.get_block(&keys[2], 0, 0, 3).await.unwrap();
+        assert_eq!(cache.memory_size(), 6);
+        let memory = cache.memory.lock().unwrap();
+        assert!(memory.blocks.contains_key(&(keys[0].clone(), 0)));
+        assert!(
+            !memory.blocks.contains_key(&(keys[1].clone(), 0)),
+            "the least recently read block is demoted"
+        );
+        assert!(memory.blocks.contains_key(&(keys[2].clone(), 0)));
+    }
+
+    #[tokio::test]
+    async fn test_large_blocks_are_not_promoted() {
+        let disk_cache = InMemoryDataCache::new(BLOCK_SIZE);
+        let cache = TieredDataCache::new(disk_cache, MemoryCacheConfig { max_size: 2 });
+        let key = ObjectId::new("a".into(), ETag::for_tests());
+        cache.put_block(key.clone(), 0, 0, block(b"Foo"), 3).await.unwrap();
+        let bytes = cache.get_block(&key, 0, 0, 3).await.unwrap();
+        assert_eq!(bytes, Some(block(b"Foo")));
+        assert_eq!(cache.memory_size(), 0);
+    }
+}
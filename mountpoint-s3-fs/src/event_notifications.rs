@@ -0,0 +1,216 @@
+//! Invalidation of cached metadata from S3 Event Notifications.
+//!
+//! Metadata served from the cache may be stale for as long as the metadata TTL, when the bucket is changed by other
+//! writers. With an [EventNotificationConfig], Mountpoint consumes the [S3 event notifications] of the bucket from a
+//! queue, usually an SQS queue the bucket or an EventBridge rule sends them to, and expires the cached metadata of the
+//! objects they name as they arrive, so that changes become visible without short TTLs. The kernel is notified to
+//! drop its own cached entries and pages as well. Blocks of the data cache need no invalidation, as they are keyed by
+//! the ETags of the objects.
+//!
+//! [S3 event notifications]: https://docs.aws.amazon.com/AmazonS3/latest/userguide/EventNotifications.html
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::RemoteHandle;
+use futures::task::{SpawnError, SpawnExt};
+use tracing::{debug, warn};
+
+use crate::async_util::{Runtime, sleep};
+use crate::metablock::{Invalidations, Metablock};
+use crate::sync::Arc;
+
+mod message;
+pub use message::{MessageError, ObjectEvent, parse_message};
+
+/// Time to wait before receiving messages again, after the queue returned an error.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A message received from an [EventQueue].
+#[derive(Debug, Clone)]
+pub struct QueueMessage {
+    /// Handle to delete the message from the queue once its events are applied.
+    pub receipt_handle: String,
+    /// Body of the message, in one of the formats parsed by [parse_message].
+    pub body: String,
+}
+
+pub type EventQueueError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A queue of S3 event notifications, such as an SQS queue.
+#[async_trait]
+pub trait EventQueue: Send + Sync {
+    /// Wait for the next messages in the queue. May return no messages if none arrived for a while.
+    async fn receive(&self) -> Result<Vec<QueueMessage>, EventQueueError>;
+
+    /// Delete messages from the queue, once their events were applied.
+    async fn delete(&self, messages: &[QueueMessage]) -> Result<(), EventQueueError>;
+}
+
+/// Invalidating cached metadata from S3 event notifications, see [crate::MountpointConfig::event_notifications].
+pub struct EventNotificationConfig {
+    queue: Arc<dyn EventQueue>,
+    bucket: String,
+}
+
+impl EventNotificationConfig {
+    /// Consume the notifications in `queue`, ignoring the events of buckets other than `bucket`, the mounted bucket.
+    pub fn new(queue: impl EventQueue + 'static, bucket: &str) -> Self {
+        Self {
+            queue: Arc::new(queue),
+            bucket: bucket.to_owned(),
+        }
+    }
+}
+
+impl Debug for EventNotificationConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventNotificationConfig")
+            .field("bucket", &self.bucket)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Spawn the task consuming the queue, which expires the cached metadata of the objects named by the events and passes
+/// the entries of the kernel caches to invalidate to `notify`. The task stops when the returned handle is dropped.
+pub(crate) fn spawn_listener(
+    config: EventNotificationConfig,
+    metablock: Arc<dyn Metablock>,
+    runtime: &Runtime,
+    notify: impl Fn(&Invalidations) + Send + Sync + 'static,
+) -> Result<RemoteHandle<()>, SpawnError> {
+    runtime.spawn_with_handle(async move {
+        loop {
+            let messages = match config.queue.receive().await {
+                Ok(messages) => messages,
+                Err(error) => {
+                    warn!(?error, "failed to receive event notifications");
+                    sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            if messages.is_empty() {
+                continue;
+            }
+            for message in &messages {
+                apply_message(&message.body, &config.bucket, metablock.as_ref(), &notify).await;
+            }
+            // Invalid messages are deleted too, as they would never be applied.
+            if let Err(error) = config.queue.delete(&messages).await {
+                warn!(?error, "failed to delete event notifications");
+            }
+        }
+    })
+}
+
+async fn apply_message(body: &str, bucket: &str, metablock: &dyn Metablock, notify: &impl Fn(&Invalidations)) {
+    let events = match parse_message(body) {
+        Ok(events) => events,
+        Err(error) => {
+            warn!(?error, "ignoring invalid event notification");
+            return;
+        }
+    };
+    for event in events.iter().filter(|event| event.bucket == bucket) {
+        debug!(
+            key = event.key,
+            event = event.name,
+            "invalidating cached metadata from event notification"
+        );
+        metrics::counter!("event_notifications.events").increment(1);
+        let invalidations = metablock.invalidate(&event.key).await;
+        if !invalidations.is_empty() {
+            notify(&invalidations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::ThreadPool;
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject};
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::fs::{CacheConfig, FUSE_ROOT_INODE, TimeToLive};
+    use crate::s3::{Bucket, S3Path};
+    use crate::sync::Mutex;
+    use crate::{Superblock, SuperblockConfig};
+
+    /// A queue holding the messages sent to a channel.
+    struct TestQueue {
+        messages: async_channel::Receiver<QueueMessage>,
+        deleted: async_channel::Sender<String>,
+    }
+
+    #[async_trait]
+    impl EventQueue for TestQueue {
+        async fn receive(&self) -> Result<Vec<QueueMessage>, EventQueueError> {
+            Ok(vec![self.messages.recv().await?])
+        }
+
+        async fn delete(&self, messages: &[QueueMessage]) -> Result<(), EventQueueError> {
+            for message in messages {
+                self.deleted.send(message.receipt_handle.clone()).await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listener() {
+        let bucket = "test_bucket";
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        client.add_object("file0.txt", MockObject::constant(0xaa, 30, ETag::for_tests()));
+        let ttl = Duration::from_secs(60 * 60 * 24 * 7);
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(Bucket::new(bucket).unwrap(), Default::default()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(ttl)),
+                ..Default::default()
+            },
+        );
+        let file0 = superblock
+            .lookup(FUSE_ROOT_INODE, "file0.txt".as_ref())
+            .await
+            .unwrap()
+            .ino();
+
+        let (sender, messages) = async_channel::unbounded();
+        let (deleted, deleted_receiver) = async_channel::unbounded();
+        let config = EventNotificationConfig::new(TestQueue { messages, deleted }, bucket);
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let _listener = spawn_listener(config, Arc::new(superblock), &runtime, {
+            let notified = notified.clone();
+            move |invalidations: &Invalidations| notified.lock().unwrap().extend(invalidations.inodes.clone())
+        })
+        .unwrap();
+
+        let notification = |bucket: &str, key: &str| {
+            serde_json::json!({
+                "Records": [{
+                    "eventName": "ObjectRemoved:Delete",
+                    "s3": {"bucket": {"name": bucket}, "object": {"key": key}},
+                }],
+            })
+            .to_string()
+        };
+        let messages = [
+            notification("other_bucket", "file0.txt"),
+            "not a notification".to_owned(),
+            notification(bucket, "file0.txt"),
+        ];
+        for (i, body) in messages.into_iter().enumerate() {
+            let message = QueueMessage {
+                receipt_handle: i.to_string(),
+                body,
+            };
+            sender.send(message).await.unwrap();
+            assert_eq!(deleted_receiver.recv().await.unwrap(), i.to_string());
+        }
+        assert_eq!(*notified.lock().unwrap(), vec![file0]);
+    }
+}
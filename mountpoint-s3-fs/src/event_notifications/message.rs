@@ -0,0 +1,246 @@
+//! Parsing of the messages holding S3 event notifications.
+//!
+//! S3 sends [event notifications] to SQS queues and SNS topics as a JSON document holding a list of records, and to
+//! EventBridge as one [event] per change, which rules may forward to a queue. Subscriptions of SQS queues to SNS topics
+//! wrap the notifications in an SNS envelope, unless raw message delivery is enabled.
+//!
+//! [event notifications]: https://docs.aws.amazon.com/AmazonS3/latest/userguide/notification-content-structure.html
+//! [event]: https://docs.aws.amazon.com/AmazonS3/latest/userguide/ev-events.html
+
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// An object changed in S3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectEvent {
+    /// Name of the bucket holding the object.
+    pub bucket: String,
+    /// Key of the object.
+    pub key: String,
+    /// Name of the event, for example `ObjectCreated:Put` or `Object Deleted`.
+    pub name: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MessageError {
+    #[error("invalid JSON in message")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("message is not an S3 event notification")]
+    UnrecognizedMessage,
+    #[error("invalid encoding of key {0:?}")]
+    InvalidKey(String),
+}
+
+/// Body of a message, with the fields of each of the formats it may have.
+#[derive(Debug, Deserialize)]
+struct Message {
+    /// Records of an S3 event notification.
+    #[serde(rename = "Records")]
+    records: Option<Vec<Record>>,
+    /// Event of a test notification, sent by S3 when notifications are configured.
+    #[serde(rename = "Event")]
+    test_event: Option<String>,
+    /// Type of an SNS message.
+    #[serde(rename = "Type")]
+    sns_type: Option<String>,
+    /// Body of an SNS notification.
+    #[serde(rename = "Message")]
+    sns_message: Option<String>,
+    /// Source of an EventBridge event.
+    source: Option<String>,
+    /// Type of an EventBridge event.
+    #[serde(rename = "detail-type")]
+    detail_type: Option<String>,
+    /// Details of an EventBridge event, which depend on its source.
+    detail: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    event_name: String,
+    s3: Detail,
+}
+
+#[derive(Debug, Deserialize)]
+struct Detail {
+    bucket: Bucket,
+    object: Object,
+}
+
+#[derive(Debug, Deserialize)]
+struct Bucket {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Object {
+    key: String,
+}
+
+/// Parse the events in the body of a message. Test notifications hold no events.
+pub fn parse_message(body: &str) -> Result<Vec<ObjectEvent>, MessageError> {
+    let message: Message = serde_json::from_str(body)?;
+    if let Some(records) = message.records {
+        return records
+            .into_iter()
+            .map(|record| {
+                Ok(ObjectEvent {
+                    bucket: record.s3.bucket.name,
+                    key: decode_key(&record.s3.object.key)?,
+                    name: record.event_name,
+                })
+            })
+            .collect();
+    }
+    match message {
+        Message {
+            sns_type: Some(sns_type),
+            sns_message: Some(sns_message),
+            ..
+        } if sns_type == "Notification" => parse_message(&sns_message),
+        Message {
+            source: Some(source),
+            detail_type: Some(detail_type),
+            detail: Some(detail),
+            ..
+        } if source == "aws.s3" => {
+            let detail: Detail = serde_json::from_value(detail)?;
+            Ok(vec![ObjectEvent {
+                bucket: detail.bucket.name,
+                key: detail.object.key,
+                name: detail_type,
+            }])
+        }
+        Message {
+            test_event: Some(test_event),
+            ..
+        } if test_event == "s3:TestEvent" => Ok(Vec::new()),
+        _ => Err(MessageError::UnrecognizedMessage),
+    }
+}
+
+/// Decode a key of an S3 event notification, which is URL-encoded with spaces as `+`.
+fn decode_key(key: &str) -> Result<String, MessageError> {
+    let key = key.replace('+', " ");
+    percent_decode_str(&key)
+        .decode_utf8()
+        .map(|key| key.into_owned())
+        .map_err(|_| MessageError::InvalidKey(key.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTIFICATION: &str = r#"{
+        "Records": [
+            {
+                "eventVersion": "2.1",
+                "eventSource": "aws:s3",
+                "awsRegion": "us-west-2",
+                "eventTime": "2026-10-01T12:00:00.000Z",
+                "eventName": "ObjectCreated:Put",
+                "s3": {
+                    "s3SchemaVersion": "1.0",
+                    "configurationId": "mountpoint",
+                    "bucket": {"name": "amzn-s3-demo-bucket", "arn": "arn:aws:s3:::amzn-s3-demo-bucket"},
+                    "object": {"key": "dir/my+file%C3%A9%2B1.txt", "size": 1024, "eTag": "d41d8cd98f00b204e9800998ecf8427e", "sequencer": "0055AED6DCD90281E5"}
+                }
+            },
+            {
+                "eventVersion": "2.1",
+                "eventSource": "aws:s3",
+                "eventName": "ObjectRemoved:Delete",
+                "s3": {
+                    "bucket": {"name": "amzn-s3-demo-bucket"},
+                    "object": {"key": "dir/old.txt", "sequencer": "0055AED6DCD90281E6"}
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_notification() {
+        let events = parse_message(NOTIFICATION).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                ObjectEvent {
+                    bucket: "amzn-s3-demo-bucket".to_owned(),
+                    key: "dir/my fileé+1.txt".to_owned(),
+                    name: "ObjectCreated:Put".to_owned(),
+                },
+                ObjectEvent {
+                    bucket: "amzn-s3-demo-bucket".to_owned(),
+                    key: "dir/old.txt".to_owned(),
+                    name: "ObjectRemoved:Delete".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sns_notification() {
+        let body = serde_json::json!({
+            "Type": "Notification",
+            "MessageId": "c5b1f9a0-0000-0000-0000-000000000000",
+            "TopicArn": "arn:aws:sns:us-west-2:111122223333:s3-events",
+            "Subject": "Amazon S3 Notification",
+            "Message": NOTIFICATION,
+        });
+        let events = parse_message(&body.to_string()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].key, "dir/old.txt");
+    }
+
+    #[test]
+    fn test_parse_eventbridge_event() {
+        let body = r#"{
+            "version": "0",
+            "id": "17793124-05d4-b198-2fde-7ededc63b103",
+            "detail-type": "Object Deleted",
+            "source": "aws.s3",
+            "account": "111122223333",
+            "time": "2026-10-01T12:00:00Z",
+            "region": "us-west-2",
+            "resources": ["arn:aws:s3:::amzn-s3-demo-bucket"],
+            "detail": {
+                "version": "0",
+                "bucket": {"name": "amzn-s3-demo-bucket"},
+                "object": {"key": "dir/my file.txt", "sequencer": "617f08299329d189"},
+                "request-id": "N4N7GDK58NMKJ12R",
+                "requester": "111122223333",
+                "reason": "DeleteObject",
+                "deletion-type": "Permanently Deleted"
+            }
+        }"#;
+        assert_eq!(
+            parse_message(body).unwrap(),
+            vec![ObjectEvent {
+                bucket: "amzn-s3-demo-bucket".to_owned(),
+                key: "dir/my file.txt".to_owned(),
+                name: "Object Deleted".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_messages() {
+        let test_event = r#"{"Service":"Amazon S3","Event":"s3:TestEvent","Time":"2026-10-01T12:00:00.000Z","Bucket":"amzn-s3-demo-bucket","RequestId":"5582815E1AEA5ADF","HostId":"8cLeGAmw098X5cv4Zkwcmo8vvZa3eH3eKxsPzbB9wrR+YstdA6Knx4Ip8EXAMPLE"}"#;
+        assert_eq!(parse_message(test_event).unwrap(), Vec::new());
+
+        assert!(matches!(parse_message("not json"), Err(MessageError::InvalidJson(_))));
+        assert!(matches!(
+            parse_message(r#"{"source":"aws.ec2","detail-type":"EC2 Instance State-change Notification","detail":{}}"#),
+            Err(MessageError::UnrecognizedMessage)
+        ));
+        assert!(matches!(
+            parse_message(r#"{"hello":"world"}"#),
+            Err(MessageError::UnrecognizedMessage)
+        ));
+        let invalid_key = NOTIFICATION.replace("dir/old.txt", "dir/%FF.txt");
+        assert!(matches!(parse_message(&invalid_key), Err(MessageError::InvalidKey(_))));
+    }
+}
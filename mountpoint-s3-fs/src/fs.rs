@@ -2,26 +2,41 @@
 
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr as _;
 use std::time::{Duration, UNIX_EPOCH};
 
 use bytes::Bytes;
-use fuser::consts::FOPEN_DIRECT_IO;
-use fuser::{FileAttr, KernelConfig};
+use fuser::consts::{FOPEN_DIRECT_IO, FOPEN_KEEP_CACHE, FUSE_WRITE_CACHE};
+use fuser::{FileAttr, FileType, KernelConfig};
+use futures::task::SpawnExt as _;
 use mountpoint_s3_client::ObjectClient;
-use mountpoint_s3_client::types::ChecksumAlgorithm;
+use mountpoint_s3_client::error::{
+    CopyObjectError, GetObjectTaggingError, HeadObjectError, ObjectClientError, PutObjectTaggingError,
+};
+use mountpoint_s3_client::types::{
+    CopyObjectParams, ETag, HeadObjectParams, HeadObjectResult, ObjectMetadata, ObjectTags, PresignGetObjectParams,
+};
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::{Level, debug, trace};
+use tracing::{Level, debug, trace, warn};
 
 use crate::async_util::Runtime;
 use crate::logging;
 use crate::mem_limiter::MemoryLimiter;
 use crate::memory::PagedPool;
-use crate::metablock::{AddDirEntry, AddDirEntryResult, InodeInformation, Metablock, PendingUploadHook, ReadWriteMode};
+use crate::metablock::{
+    AddDirEntry, AddDirEntryResult, InodeInformation, InodeStat, Invalidations, Lookup, Metablock, PendingUploadHook,
+    ReadWriteMode, S3Location, ValidName,
+};
 pub use crate::metablock::{InodeError, InodeKind, InodeNo};
-use crate::prefetch::{Prefetcher, PrefetcherBuilder};
+use crate::object::ObjectId;
+use crate::prefetch::{HandleId, Prefetcher, PrefetcherBuilder};
+use crate::s3::S3Path;
+use crate::superblock::{Superblock, SuperblockConfig};
 use crate::sync::atomic::{AtomicU64, Ordering};
-use crate::sync::{Arc, AsyncMutex, AsyncRwLock};
+use crate::sync::{Arc, AsyncMutex, AsyncRwLock, Mutex, async_channel};
 use crate::upload::{Uploader, UploaderConfig};
 
 mod config;
@@ -38,24 +53,202 @@ mod flags;
 pub use flags::{OpenFlags, RenameFlags};
 
 mod handles;
+use handles::UploadState;
 pub use handles::{FileHandle, FileHandleState};
 
+mod pinning;
+use pinning::PinnedObjects;
+pub use pinning::{PIN_XATTR, PinningCache};
+
+mod atime;
+pub use atime::ATIME_METADATA_KEY;
+use atime::AccessTimes;
+
+mod mode;
+pub use mode::MODE_METADATA_KEY;
+use mode::format_mode;
+pub(crate) use mode::parse_mode;
+
+mod mtime;
+pub use mtime::MTIME_METADATA_KEY;
+use mtime::format_mtime;
+pub(crate) use mtime::parse_mtime;
+
+mod versions;
+pub use versions::VERSIONS_DIR_SUFFIX;
+pub(crate) use versions::{version_object_key, versions_dir_object};
+
+mod archived;
+pub use archived::{ArchivedObjectsPolicy, RESTORE_STATUS_XATTR};
+pub(crate) use archived::{is_archived, restore_status_value};
+
+mod key_filter;
+pub use key_filter::KeyFilter;
+
+mod shadowing;
+pub(crate) use shadowing::shadowed_file_key;
+pub use shadowing::{SHADOWED_FILE_SUFFIX, ShadowingPolicy};
+
+mod tagging;
+pub use tagging::TAG_XATTR_PREFIX;
+use tagging::{MAX_TAGS, ObjectTagsCache};
+
+mod quota;
+use quota::{QuotaError, WriteQuota};
+
+mod recursive_size;
+pub use recursive_size::RECURSIVE_SIZE_XATTR;
+use recursive_size::{RECURSIVE_SIZE_VALIDITY, RecursiveSizeCache};
+
+mod page_cache;
+use page_cache::PageCaches;
+
+mod direct_io;
+use direct_io::DirectIoKeys;
+
+mod compressed_files;
+pub use compressed_files::Compression;
+pub(crate) use compressed_files::compressed_file_key;
+
+mod content_encoding;
+use content_encoding::Decoding;
+pub(crate) use content_encoding::is_gzip_encoding;
+
+mod prefix_overrides;
+pub use prefix_overrides::{PrefixOverride, PrefixOverrides, PrefixOverridesError};
+
 mod sse;
 pub use sse::{ServerSideEncryption, SseCorruptedError};
 
+mod statfs;
+pub use statfs::StatfsCapacity;
+
+mod storage_class_rule;
+pub use storage_class_rule::{StorageClassRule, StorageClassRuleError, StorageClassRules};
+
 mod time_to_live;
 pub use time_to_live::TimeToLive;
 
+mod ttl_rule;
+pub(crate) use ttl_rule::glob_regex;
+pub use ttl_rule::{MetadataTtlRule, MetadataTtlRuleError};
+
 pub const FUSE_ROOT_INODE: InodeNo = 1u64;
 
+/// Name of the extended attribute holding the prefetching statistics of the file handles open for
+/// reading a file, one line for each of them, see [S3FilesystemConfig::prefetch_stats_xattr].
+pub const PREFETCH_STATS_XATTR: &str = "user.mountpoint.prefetch_stats";
+
+/// Name of the extended attribute which, set to `1` on a file or directory, expires the cached metadata of it and of
+/// everything cached under it, see [S3FilesystemConfig::invalidate_xattr].
+pub const INVALIDATE_XATTR: &str = "user.mountpoint.invalidate";
+
+/// Name of the extended attribute which, set to `1` on a file or directory, makes the later opens of the file or of
+/// the files under the directory use direct I/O, see [S3FilesystemConfig::direct_io_xattr].
+pub const DIRECT_IO_XATTR: &str = "user.mountpoint.direct_io";
+
+/// Prefix of the names of the extended attributes holding the user-defined metadata of objects, the `x-amz-meta-*`
+/// headers, see [S3FilesystemConfig::user_metadata_xattrs].
+pub const USER_METADATA_XATTR_PREFIX: &str = "user.s3.meta.";
+
+/// Name of the extended attribute holding the ETag of the object of a file, without the surrounding quotes, see
+/// [S3FilesystemConfig::object_info_xattrs].
+pub const ETAG_XATTR: &str = "user.s3.etag";
+
+/// Name of the extended attribute holding the version ID of the object of a file, in buckets with versioning
+/// enabled, see [S3FilesystemConfig::object_info_xattrs].
+pub const VERSION_ID_XATTR: &str = "user.s3.version_id";
+
+/// Name of the extended attribute holding the storage class of the object of a file, see
+/// [S3FilesystemConfig::object_info_xattrs].
+pub const STORAGE_CLASS_XATTR: &str = "user.s3.storage_class";
+
+/// Name of the extended attribute holding a pre-signed URL to get the object of a file, which other services can read
+/// the object with without credentials until it expires, see [S3FilesystemConfig::presigned_url_xattr].
+pub const PRESIGNED_URL_XATTR: &str = "user.s3.presigned_url";
+
+/// Prefixes of the names of the extended attributes overlayfs looks up on the files and directories of its layers, with
+/// and without its `userxattr` mount option, see [S3FilesystemConfig::overlayfs_lower].
+pub const OVERLAYFS_XATTR_PREFIXES: [&str; 2] = ["trusted.overlay.", "user.overlay."];
+
+/// Key of the user-defined metadata holding the target of the symbolic link an object represents, the same as goofys
+/// uses, see [S3FilesystemConfig::symlinks].
+pub const SYMLINK_TARGET_METADATA_KEY: &str = "--symlink-target";
+
+const OBJECT_INFO_XATTRS: [&str; 3] = [ETAG_XATTR, VERSION_ID_XATTR, STORAGE_CLASS_XATTR];
+
+/// Number of invalidations of the kernel caches that may wait to be sent, see [S3Filesystem::kernel_invalidations].
+const KERNEL_INVALIDATIONS_CAPACITY: usize = 64;
+
+/// Maximum size of the objects a single CopyObject request can copy, and so whose metadata can be updated.
+const MAX_COPY_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Changes to the attributes of a file kept in the user-defined metadata of its object, see
+/// [S3FilesystemConfig::mtime_metadata], [S3FilesystemConfig::mode_metadata] and
+/// [S3FilesystemConfig::atime_flush_interval].
+#[derive(Debug, Default, Clone, Copy)]
+struct MetadataUpdate {
+    mtime: Option<OffsetDateTime>,
+    mode: Option<u16>,
+    atime: Option<OffsetDateTime>,
+}
+
+impl MetadataUpdate {
+    fn is_empty(&self) -> bool {
+        self.mtime.is_none() && self.mode.is_none() && self.atime.is_none()
+    }
+
+    /// Apply the changes of a later update on top of these ones.
+    fn merge(&mut self, later: MetadataUpdate) {
+        self.mtime = later.mtime.or(self.mtime);
+        self.mode = later.mode.or(self.mode);
+        self.atime = later.atime.or(self.atime);
+    }
+}
+
+/// The error of a server-side copy that isn't supported, see [S3Filesystem::copy_file_range]. The kernel falls back to
+/// reading and writing the data, so it is only logged at debug level.
+fn copy_unsupported(reason: &str) -> Error {
+    Error {
+        errno: libc::EOPNOTSUPP,
+        message: format!("server-side copy is not supported: {reason}"),
+        source: None,
+        level: Level::DEBUG,
+        metadata: Default::default(),
+    }
+}
+
 pub struct S3Filesystem<Client>
 where
     Client: ObjectClient + Clone + Send + Sync + 'static,
 {
     config: S3FilesystemConfig,
+    client: Client,
     metablock: Arc<dyn Metablock>,
     prefetcher: Prefetcher<Client>,
     uploader: Uploader<Client>,
+    runtime: Runtime,
+    /// The objects pinned in the local disk cache with [PIN_XATTR], if supported.
+    pinned_objects: Option<PinnedObjects>,
+    /// The tags of objects read through [TAG_XATTR_PREFIX] extended attributes, if they are exposed.
+    object_tags: Option<ObjectTagsCache>,
+    /// The total size of the objects under directories read through [RECURSIVE_SIZE_XATTR], if it is exposed.
+    recursive_sizes: Option<RecursiveSizeCache>,
+    /// The attributes set on files being written, to put in the metadata of their objects once they are uploaded.
+    pending_metadata: Mutex<HashMap<InodeNo, MetadataUpdate>>,
+    /// The times files were last read, if they are tracked.
+    access_times: Option<AccessTimes>,
+    /// The limits on the size of files and the bytes written to them.
+    quota: WriteQuota,
+    /// The versions of the objects cached by the kernel, if their pages are kept across opens.
+    page_caches: Option<PageCaches>,
+    /// The files and directories [DIRECT_IO_XATTR] was set on, if it is supported.
+    direct_io_keys: Option<DirectIoKeys>,
+    /// Invalidations of the kernel caches to send once the request made them has been replied to.
+    kernel_invalidations: (
+        async_channel::Sender<Invalidations>,
+        async_channel::Receiver<Invalidations>,
+    ),
     next_handle: AtomicU64,
     file_handles: AsyncRwLock<HashMap<u64, Arc<FileHandle<Client>>>>,
 }
@@ -140,7 +333,27 @@ impl<Client> S3Filesystem<Client>
 where
     Client: ObjectClient + Clone + Send + Sync + 'static,
 {
+    /// Create a file system serving the objects under `s3_path`. The options of the superblock it shares with `config`,
+    /// such as [S3FilesystemConfig::cache_config], are taken from `config`.
     pub fn new(
+        client: Client,
+        prefetch_builder: PrefetcherBuilder<Client>,
+        pool: PagedPool,
+        runtime: Runtime,
+        s3_path: S3Path,
+        superblock_config: SuperblockConfig,
+        config: S3FilesystemConfig,
+    ) -> Self {
+        let superblock = Superblock::new(
+            client.clone(),
+            s3_path,
+            superblock_config.with_filesystem_config(&config),
+        );
+        Self::with_metablock(client, prefetch_builder, pool, runtime, superblock, config)
+    }
+
+    /// Create a file system serving the inodes of another [Metablock], such as a manifest.
+    pub fn with_metablock(
         client: Client,
         prefetch_builder: PrefetcherBuilder<Client>,
         pool: PagedPool,
@@ -154,25 +367,66 @@ where
         let prefetcher = prefetch_builder.build(runtime.clone(), mem_limiter.clone(), config.prefetcher_config);
         let uploader = Uploader::new(
             client.clone(),
-            runtime,
+            runtime.clone(),
             pool,
             mem_limiter,
             UploaderConfig::new(client.write_part_size())
                 .storage_class(config.storage_class.to_owned())
+                .prefix_overrides(config.prefix_overrides.clone())
+                .storage_class_rules(config.storage_class_rules.clone())
+                .object_tags(config.object_tags.clone())
+                .content_types(config.content_types.clone())
                 .server_side_encryption(config.server_side_encryption.clone())
-                .default_checksum_algorithm(config.use_upload_checksums.then_some(ChecksumAlgorithm::Crc32c)),
+                .default_checksum_algorithm(config.upload_checksum_algorithm.clone())
+                .write_through_cache(config.write_through_cache.clone())
+                .staging_dir(config.write_staging_dir.clone())
+                .staging_max_size(config.write_staging_max_size)
+                .recoverable_staging(config.defer_staged_uploads)
+                .max_concurrent_uploads(config.max_concurrent_uploads)
+                .max_concurrent_parts_per_upload(config.max_concurrent_parts_per_upload),
         );
 
+        let pinned_objects = config.pinning_cache.clone().map(PinnedObjects::new);
+        let object_tags = config.tag_xattrs.then(ObjectTagsCache::new);
+        let recursive_sizes = config.recursive_size_xattr.then(RecursiveSizeCache::new);
+        let access_times = config.atime.then(|| AccessTimes::new(config.atime_flush_interval));
+        let quota = WriteQuota::new(config.max_file_size, config.max_write_bytes);
+        let page_caches = config.keep_page_cache.then(PageCaches::default);
+        let direct_io_keys = config.direct_io_xattr.then(DirectIoKeys::default);
+        let kernel_invalidations = async_channel::bounded(KERNEL_INVALIDATIONS_CAPACITY);
+        metablock.send_stale_entries(kernel_invalidations.0.clone());
+
         Self {
             config,
+            client,
             metablock: Arc::new(metablock),
             prefetcher,
             uploader,
+            runtime,
+            pinned_objects,
+            object_tags,
+            recursive_sizes,
+            pending_metadata: Mutex::new(HashMap::new()),
+            access_times,
+            quota,
+            page_caches,
+            direct_io_keys,
+            kernel_invalidations,
             next_handle: AtomicU64::new(1),
             file_handles: AsyncRwLock::new(HashMap::new()),
         }
     }
 
+    pub(crate) fn metablock(&self) -> &Arc<dyn Metablock> {
+        &self.metablock
+    }
+
+    /// The invalidations of the kernel caches made by requests, such as setting [INVALIDATE_XATTR]. They can't be sent
+    /// by the handlers of the requests, as the kernel may wait for the request to complete before processing them.
+    pub(crate) fn kernel_invalidations(&self) -> async_channel::Receiver<Invalidations> {
+        self.kernel_invalidations.1.clone()
+    }
+
     fn next_handle(&self) -> u64 {
         self.next_handle.fetch_add(1, Ordering::SeqCst)
     }
@@ -200,6 +454,25 @@ where
             }
         }
 
+        if let Some(max_request_size) = self.config.max_fuse_request_size {
+            // The maximum number of pages of requests follows the larger of the maximum write size and readahead.
+            let max_write = match config.set_max_write(max_request_size) {
+                Ok(_) => max_request_size,
+                Err(nearest) => {
+                    tracing::warn!(
+                        "FUSE requests can't be larger than {} bytes, using it instead of {}",
+                        nearest,
+                        max_request_size
+                    );
+                    config.set_max_write(nearest).expect("the nearest size is valid");
+                    nearest
+                }
+            };
+            // The readahead can only be reduced, and is left to the kernel if it allows less.
+            let _ = config.set_max_readahead(max_write);
+            tracing::info!("set the maximum size of FUSE requests to {} bytes", max_write);
+        }
+
         // Override FUSE congestion threshold if environment variable is present.
         if let Some(congestion_threshold) = self.config.fuse_congestion_threshold() {
             let old = config
@@ -212,6 +485,10 @@ where
             );
         }
 
+        if self.config.writeback_cache && config.add_capabilities(fuser::consts::FUSE_WRITEBACK_CACHE).is_err() {
+            tracing::warn!("the kernel doesn't support the FUSE writeback cache, writes are not cached");
+        }
+
         if self.config.allow_overwrite {
             // Overwrites require FUSE_ATOMIC_O_TRUNC capability on the host, so we will panic if the
             // host doesn't support it.
@@ -222,6 +499,15 @@ where
                 .add_capabilities(fuser::consts::FUSE_ATOMIC_O_TRUNC)
                 .expect("The host must support FUSE_ATOMIC_O_TRUNC capability in order to allow overwrites");
         }
+
+        // Complete the deferred uploads a previous process did not, before new writes can replace their objects.
+        let recovered = self.uploader.recover_staged_uploads().await;
+        if recovered > 0 {
+            tracing::info!(
+                "recovered {} staged uploads left by a previous Mountpoint process",
+                recovered
+            );
+        }
         Ok(())
     }
 
@@ -235,27 +521,42 @@ where
 
         // We don't implement hard links, and don't want to have to list a directory to count its
         // hard links, so we just assume one link for files (itself) and two links for directories
-        // (itself + the "." link).
+        // (itself + the "." link). As lower layer of overlayfs, directories report one link instead,
+        // which is what overlayfs reports for merged directories, and tells tools such as `find`
+        // that the number of subdirectories is unknown.
+        let dir_nlink = if self.config.overlayfs_lower { 1 } else { 2 };
         let (perm, nlink) = match lookup.kind() {
+            InodeKind::File if lookup.stat().symlink_target.is_some() => (0o777, 1),
             InodeKind::File => {
-                if lookup.stat().is_readable {
-                    (self.config.file_mode, 1)
+                if lookup.stat().is_readable || self.config.archived_objects.fails_reads() {
+                    (lookup.stat().mode.unwrap_or(self.config.file_mode), 1)
                 } else {
                     (0o000, 1)
                 }
             }
-            InodeKind::Directory => (self.config.dir_mode, 2),
+            InodeKind::Directory => (self.config.dir_mode, dir_nlink),
+        };
+
+        // The size of a symlink is the length of its target.
+        let (kind, size) = match &lookup.stat().symlink_target {
+            Some(target) => (FileType::Symlink, target.len() as u64),
+            None => (lookup.kind().into(), lookup.stat().size as u64),
+        };
+
+        let atime = match self.access_times.as_ref().and_then(|times| times.get(lookup.ino())) {
+            Some(atime) => atime.max(lookup.stat().atime),
+            None => lookup.stat().atime,
         };
 
         FileAttr {
             ino: lookup.ino(),
-            size: lookup.stat().size as u64,
-            blocks: (lookup.stat().size as u64).div_ceil(STAT_BLOCK_SIZE),
-            atime: lookup.stat().atime.into(),
+            size,
+            blocks: size.div_ceil(STAT_BLOCK_SIZE),
+            atime: atime.into(),
             mtime: lookup.stat().mtime.into(),
             ctime: lookup.stat().ctime.into(),
             crtime: UNIX_EPOCH,
-            kind: lookup.kind().into(),
+            kind,
             perm,
             nlink,
             uid: self.config.uid,
@@ -302,22 +603,91 @@ where
     pub async fn setattr(
         &self,
         ino: InodeNo,
+        mode: Option<u32>,
         atime: Option<OffsetDateTime>,
         mtime: Option<OffsetDateTime>,
         size: Option<u64>,
         _flags: Option<u32>,
     ) -> Result<Attr, Error> {
         tracing::debug!(
-            "fs:setattr with ino {:?} flags {:?} atime {:?} mtime {:?} size {:?}",
+            "fs:setattr with ino {:?} flags {:?} mode {:?} atime {:?} mtime {:?} size {:?}",
             ino,
             _flags,
+            mode,
             atime,
             mtime,
             size
         );
-        let setattr_result = self.metablock.setattr(ino, atime, mtime).await;
+        if let Some(size) = size {
+            self.quota.check_file_size(size)?;
+        }
+        let setattr_result = match (self.metablock.setattr(ino, atime, mtime).await, size) {
+            (Ok(lookup), Some(size)) if lookup.kind() == InodeKind::File && lookup.stat().etag.is_none() => {
+                self.resize_staged_upload(ino, size).await?;
+                self.metablock.getattr(ino, false).await
+            }
+            (result, _) => result,
+        };
+        let update = MetadataUpdate {
+            mtime: mtime.filter(|_| self.config.mtime_metadata),
+            mode: mode
+                .filter(|_| self.config.mode_metadata)
+                .map(|mode| (mode & 0o7777) as u16),
+            atime: None,
+        };
         let lookup = match (setattr_result, size) {
+            (Ok(lookup), _) if lookup.kind() == InodeKind::File && !update.is_empty() => {
+                let lookup = match update.mode {
+                    Some(mode) => self.metablock.set_mode(ino, mode).await?,
+                    None => lookup,
+                };
+                if !self.stage_metadata_update(ino, &update).await {
+                    self.pending_metadata
+                        .lock()
+                        .unwrap()
+                        .entry(ino)
+                        .or_default()
+                        .merge(update);
+                }
+                lookup
+            }
             (Ok(lookup), _) => lookup,
+            (Err(e @ InodeError::SetAttrNotPermittedOnRemoteInode(_)), None) if !update.is_empty() => {
+                let lookup = self.metablock.getattr(ino, false).await?;
+                if lookup.kind() != InodeKind::File {
+                    return Err(e.into());
+                }
+                self.update_object_metadata(&lookup, update).await?
+            }
+            (Err(InodeError::SetAttrNotPermittedOnRemoteInode(_)), None)
+                if mtime.is_some() && mode.is_none() && self.config.writeback_cache =>
+            {
+                // With the writeback cache, the kernel keeps the times of files itself, and sends them when it writes
+                // the file back, possibly once it is uploaded. It keeps reporting its own times, so they are ignored.
+                self.metablock.getattr(ino, false).await?
+            }
+            (Err(e @ InodeError::SetAttrNotPermittedOnRemoteInode(_)), Some(size)) if self.config.allow_overwrite => {
+                let lookup = self.metablock.getattr(ino, false).await?;
+                if lookup.kind() != InodeKind::File {
+                    return Err(e.into());
+                }
+                let lookup = self.truncate_object(&lookup, size).await?;
+                if update.is_empty() {
+                    lookup
+                } else {
+                    self.update_object_metadata(&lookup, update).await?
+                }
+            }
+            (Err(InodeError::SetAttrNotPermittedOnRemoteInode(_)), None)
+                if mtime.is_some() && !self.config.mtime_metadata =>
+            {
+                // Tools like `tar -x`, `rsync -t` and `touch -d` set the modification time of files after closing
+                // them, so point users at the option that persists it.
+                return Err(err!(
+                    libc::EPERM,
+                    "setting the modification time of uploaded files is disabled by default, you need to remount with --mtime-metadata flag to keep it in the metadata of their objects"
+                ));
+            }
             (Err(InodeError::SetAttrNotPermittedOnRemoteInode(_)), Some(0)) if !self.config.allow_overwrite => {
                 // We want to provide better feedback to users to prompt them to opt-in to file overwrites if it looks like what the application needs.
                 // Instead of complex logic to match `setattr` truncation only, we just check for the error and if the size was set in the request.
@@ -338,6 +708,13 @@ where
     pub async fn forget(&self, ino: InodeNo, n: u64) {
         trace!("fs:forget with ino {:?} n {:?}", ino, n);
         self.metablock.forget(ino, n).await;
+        if let Some(object_tags) = &self.object_tags {
+            object_tags.remove(ino);
+        }
+        self.pending_metadata.lock().unwrap().remove(&ino);
+        if let Some(page_caches) = &self.page_caches {
+            page_caches.invalidate(ino);
+        }
     }
 
     pub async fn open(&self, ino: InodeNo, flags: OpenFlags, pid: u32) -> Result<Opened, Error> {
@@ -351,16 +728,47 @@ where
         let fh = self.next_handle(); // TODO: can we delay obtaining the next handle until we know we are creating a new file handle?
         let write_mode = self.config.write_mode();
         let new_handle = self.metablock.open_handle(ino, fh, &write_mode, flags).await?;
-        let state = FileHandleState::new(fh, &new_handle, flags, self).await?;
+        let direct_io = flags.direct_io()
+            || self.direct_io_keys.as_ref().is_some_and(|direct_io_keys| {
+                new_handle
+                    .lookup
+                    .s3_location()
+                    .is_ok_and(|location| direct_io_keys.applies_to(&location.partial_key))
+            });
+        let keep_cache = match &self.page_caches {
+            Some(page_caches)
+                if matches!(new_handle.mode, ReadWriteMode::Read) && new_handle.writer.is_none() && !direct_io =>
+            {
+                page_caches.revalidate(ino, new_handle.lookup.stat())
+            }
+            Some(page_caches) => {
+                page_caches.invalidate(ino);
+                false
+            }
+            None => false,
+        };
+        let state = FileHandleState::new(fh, &new_handle, flags, direct_io, self).await?;
+        // With the writeback cache, the kernel writes back pages in any order, and reads the pages partially written
+        // from the write handle, which only staged uploads allow. The other uploads bypass the page cache.
+        let write_through = self.config.writeback_cache
+            && matches!(&state, FileHandleState::Write { state, .. } if !matches!(state, UploadState::StagedInProgress { .. }));
         let handle = FileHandle {
             ino,
             location: new_handle.lookup.try_into_s3_location()?,
             open_pid: pid,
+            direct_io,
+            written_back: Default::default(),
             state: AsyncMutex::new(state),
         };
         debug!(fh, ino, "new {:?} file handle created", new_handle.mode);
         self.file_handles.write().await.insert(fh, Arc::new(handle));
-        let reply_flags = if flags.direct_io() { FOPEN_DIRECT_IO } else { 0 };
+        let reply_flags = if direct_io || write_through {
+            FOPEN_DIRECT_IO
+        } else if keep_cache {
+            FOPEN_KEEP_CACHE
+        } else {
+            0
+        };
         Ok(Opened { fh, flags: reply_flags })
     }
 
@@ -396,9 +804,45 @@ where
         logging::record_name(handle.file_name());
 
         let mut state = handle.state.lock().await;
-        let (request, flushed) = match &mut *state {
-            FileHandleState::Read { request, flushed } => (request, flushed),
-            FileHandleState::Write { .. } => return Err(err!(libc::EBADF, "file handle is not open for reads")),
+        if let FileHandleState::ReadWhileWriting { writer, flushed } = &mut *state {
+            if *flushed {
+                if !self
+                    .metablock
+                    .try_reactivate_handle(ino, fh, ReadWriteMode::Read)
+                    .await?
+                {
+                    return Err(err!(
+                        libc::EBADF,
+                        "file handle has been invalidated by a newer handle opened"
+                    ));
+                }
+                *flushed = false;
+            }
+            let writer = writer.clone();
+            if let FileHandleState::Write {
+                state: upload_state @ UploadState::StagedInProgress { .. },
+                ..
+            } = &mut *writer.state.lock().await
+            {
+                return upload_state.read(offset, size).await;
+            }
+            // The writer has uploaded the file since this handle was opened, so read its object instead.
+            let lookup = self.metablock.getattr(ino, false).await?;
+            let request = FileHandleState::prefetch(fh, &lookup, handle.direct_io, self).await?;
+            *state = FileHandleState::Read {
+                request: Box::new(request),
+                decoding: FileHandleState::<Client>::decoding(lookup.stat())?,
+                flushed: false,
+            };
+        }
+        let (request, decoding, flushed) = match &mut *state {
+            FileHandleState::Read {
+                request,
+                decoding,
+                flushed,
+            } => (&mut **request, decoding, flushed),
+            FileHandleState::Write { state, .. } => return state.read(offset, size).await,
+            FileHandleState::ReadWhileWriting { .. } => unreachable!("replaced above"),
         };
 
         // If the handle has been flushed, check if it has been overridden by a newer handle opened for the inode.
@@ -416,11 +860,738 @@ where
             }
             *flushed = false;
         }
-        request
-            .read(offset as u64, size as usize)
-            .await?
-            .into_bytes()
-            .map_err(|e| err!(libc::EIO, source:e, "integrity error"))
+        let bytes = match decoding {
+            Some(decoding) => decoding.read(request, offset as u64, size as usize).await?,
+            None => request
+                .read(offset as u64, size as usize)
+                .await?
+                .into_bytes()
+                .map_err(|e| err!(libc::EIO, source:e, "integrity error"))?,
+        };
+        if let Some(access_times) = &self.access_times {
+            access_times.record(ino, OffsetDateTime::now_utc());
+        }
+        Ok(bytes)
+    }
+
+    /// Whether extended attributes are supported, see [S3FilesystemConfig::prefetch_stats_xattr],
+    /// [S3FilesystemConfig::invalidate_xattr], [S3FilesystemConfig::direct_io_xattr],
+    /// [S3FilesystemConfig::user_metadata_xattrs],
+    /// [S3FilesystemConfig::object_info_xattrs], [S3FilesystemConfig::presigned_url_xattr],
+    /// [S3FilesystemConfig::recursive_size_xattr], [S3FilesystemConfig::tag_xattrs] and
+    /// [S3FilesystemConfig::pinning_cache], or as lower layer of overlayfs.
+    pub fn xattrs_supported(&self) -> bool {
+        self.config.overlayfs_lower
+            || self.config.prefetch_stats_xattr
+            || self.config.invalidate_xattr
+            || self.config.direct_io_xattr
+            || self.config.user_metadata_xattrs
+            || self.config.object_info_xattrs
+            || self.config.presigned_url_xattr.is_some()
+            || self.config.recursive_size_xattr
+            || self.config.tag_xattrs
+            || self.config.archived_objects == ArchivedObjectsPolicy::RestoreStatus
+            || self.pinned_objects.is_some()
+    }
+
+    /// Get the value of an extended attribute of an inode, or None if it doesn't have it. Apart from
+    /// the user-defined metadata and tags of objects, the attributes are [PREFETCH_STATS_XATTR], [PIN_XATTR], [DIRECT_IO_XATTR],
+    /// [ETAG_XATTR], [VERSION_ID_XATTR], [STORAGE_CLASS_XATTR], [PRESIGNED_URL_XATTR], [RECURSIVE_SIZE_XATTR] and
+    /// [RESTORE_STATUS_XATTR], which are not listed by `listxattr` so that tools copying extended attributes leave them out.
+    pub async fn getxattr(&self, ino: InodeNo, name: &OsStr) -> Result<Option<Vec<u8>>, Error> {
+        trace!("fs:getxattr with ino {:?} name {:?}", ino, name);
+
+        if self.is_overlayfs_xattr(name) {
+            // No file or directory is an opaque directory, a whiteout, a redirect or a metacopy for overlayfs.
+            return Ok(None);
+        }
+
+        if let Some(key) = self.user_metadata_key(name) {
+            let object_metadata = self.user_metadata(ino).await?;
+            return Ok(object_metadata.get(key).map(|value| value.clone().into_bytes()));
+        }
+        if let Some(key) = self.tag_key(name) {
+            let tags = self.object_tags(ino).await?;
+            return Ok(tags.and_then(|mut tags| tags.remove(key)).map(String::into_bytes));
+        }
+        if let Some(name) = self.object_info_xattr_name(name) {
+            let value = self.object_info(ino, name).await?;
+            return Ok(value.map(String::into_bytes));
+        }
+        if self.is_restore_status_xattr(name) {
+            let value = self.restore_status(ino).await?;
+            return Ok(value.map(String::into_bytes));
+        }
+        if let Some(expires_in) = self.config.presigned_url_xattr.filter(|_| name == PRESIGNED_URL_XATTR) {
+            let url = self.presigned_url(ino, expires_in).await?;
+            return Ok(url.map(String::into_bytes));
+        }
+        if let Some(recursive_sizes) = self.recursive_sizes.as_ref().filter(|_| name == RECURSIVE_SIZE_XATTR) {
+            let size = self.recursive_size(ino, recursive_sizes).await?;
+            return Ok(size.map(|size| size.to_string().into_bytes()));
+        }
+        if name == PIN_XATTR
+            && let Some(pinned_objects) = &self.pinned_objects
+        {
+            let key = self.pinning_key(ino).await?;
+            return Ok(pinned_objects.is_pinned(&key).then(|| b"1".to_vec()));
+        }
+        if let Some(direct_io_keys) = self.direct_io_keys.as_ref().filter(|_| name == DIRECT_IO_XATTR) {
+            let lookup = self.metablock.getattr(ino, false).await?;
+            let location = lookup.s3_location()?;
+            return Ok(direct_io_keys.is_set(&location.partial_key).then(|| b"1".to_vec()));
+        }
+        if !self.config.prefetch_stats_xattr || name != PREFETCH_STATS_XATTR {
+            return Ok(None);
+        }
+        let mut handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .iter()
+                .filter(|(_, handle)| handle.ino == ino)
+                .map(|(fh, handle)| (*fh, handle.clone()))
+                .collect()
+        };
+        handles.sort_by_key(|(fh, _)| *fh);
+
+        let mut value = String::new();
+        for (fh, handle) in handles {
+            let state = handle.state.lock().await;
+            if let FileHandleState::Read { request, .. } = &*state {
+                writeln!(value, "fh={} {}", fh, request.stats()).expect("writing to a string cannot fail");
+            }
+        }
+        Ok(Some(value.into_bytes()))
+    }
+
+    /// Set an extended attribute of an inode. Apart from the user-defined metadata and tags of objects, the only
+    /// attributes that can be set are [PIN_XATTR], to `1` to pin a file in the local disk cache and fetch it in the
+    /// background, or to `0` to unpin it, [INVALIDATE_XATTR], to `1` to expire the cached metadata of a file or
+    /// directory, and [DIRECT_IO_XATTR], to `1` to open a file or the files under a directory with direct I/O, or to `0`
+    /// to open them with the page cache again.
+    pub async fn setxattr(&self, ino: InodeNo, name: &OsStr, value: &[u8]) -> Result<(), Error> {
+        trace!("fs:setxattr with ino {:?} name {:?}", ino, name);
+
+        if let Some(key) = self.user_metadata_key(name) {
+            let Ok(value) = std::str::from_utf8(value) else {
+                return Err(err!(libc::EINVAL, "user metadata {:?} must be valid UTF-8", key));
+            };
+            return self.set_user_metadata(ino, key, value).await;
+        }
+        if let Some(key) = self.tag_key(name) {
+            let Ok(value) = std::str::from_utf8(value) else {
+                return Err(err!(libc::EINVAL, "tag {:?} must be valid UTF-8", key));
+            };
+            self.update_object_tags(ino, |tags| {
+                tags.insert(key.to_owned(), value.to_owned());
+                true
+            })
+            .await?;
+            return Ok(());
+        }
+        if self.object_info_xattr_name(name).is_some()
+            || self.is_restore_status_xattr(name)
+            || (self.config.presigned_url_xattr.is_some() && name == PRESIGNED_URL_XATTR)
+            || (self.config.recursive_size_xattr && name == RECURSIVE_SIZE_XATTR)
+        {
+            return Err(err!(libc::EPERM, "extended attribute {:?} is read-only", name));
+        }
+        if self.config.invalidate_xattr && name == INVALIDATE_XATTR {
+            if value != b"1" {
+                return Err(err!(libc::EINVAL, "{} can only be set to 1", INVALIDATE_XATTR));
+            }
+            let invalidations = self.metablock.invalidate_inode(ino).await?;
+            if self.kernel_invalidations.0.try_send(invalidations).is_err() {
+                debug!(
+                    ino,
+                    "too many pending invalidations, the kernel will revalidate its cache after its TTL"
+                );
+            }
+            return Ok(());
+        }
+        if let Some(direct_io_keys) = self.direct_io_keys.as_ref().filter(|_| name == DIRECT_IO_XATTR) {
+            let direct_io = match value {
+                b"1" => true,
+                b"0" => false,
+                _ => return Err(err!(libc::EINVAL, "{} can only be set to 1 or 0", DIRECT_IO_XATTR)),
+            };
+            let lookup = self.metablock.getattr(ino, false).await?;
+            direct_io_keys.set(&lookup.s3_location()?.partial_key, direct_io);
+            return Ok(());
+        }
+
+        let Some(pinned_objects) = self.pinned_objects.as_ref().filter(|_| name == PIN_XATTR) else {
+            return Err(err!(libc::ENOTSUP, "extended attribute {:?} can not be set", name));
+        };
+        match value {
+            b"1" => {}
+            b"0" => {
+                let key = self.pinning_key(ino).await?;
+                pinned_objects.unpin(&key);
+                return Ok(());
+            }
+            _ => return Err(err!(libc::EINVAL, "{} can only be set to 1 or 0", PIN_XATTR)),
+        }
+
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File {
+            return Err(err!(libc::EINVAL, "inode {} is not a file and can not be pinned", ino));
+        }
+        let stat = lookup.stat();
+        let etag = match &stat.etag {
+            None => return Err(err!(libc::EBUSY, "file {} is being written and can not be pinned", ino)),
+            Some(etag) => ETag::from(etag),
+        };
+        let location = lookup.s3_location()?;
+        let object_id = self.object_id(location, stat, etag);
+        let object_size = stat.object_size() as u64;
+        pinned_objects.pin(&object_id, object_size);
+
+        // Read the whole object, so that the caching prefetcher writes its blocks to the cache.
+        let handle_id = HandleId::new(self.next_handle());
+        let mut request = self.prefetcher.prefetch(
+            location.bucket_name().to_owned(),
+            object_id.clone(),
+            handle_id,
+            object_size,
+        );
+        let read_size = pinned_objects.read_size();
+        let fetch = async move {
+            let mut offset = 0;
+            while offset < object_size {
+                match request.read(offset, read_size).await {
+                    Ok(bytes) if bytes.is_empty() => break,
+                    Ok(bytes) => offset += bytes.len() as u64,
+                    Err(error) => {
+                        warn!(key = object_id.key(), ?error, "unable to fetch pinned object");
+                        return;
+                    }
+                }
+            }
+            debug!(key = object_id.key(), object_size, "fetched pinned object");
+        };
+        self.runtime
+            .spawn(fetch)
+            .map_err(|error| err!(libc::EIO, source: error, "unable to fetch pinned object"))
+    }
+
+    /// Remove an extended attribute of an inode, and return whether it had it. The only attributes
+    /// that can be removed are the tags of objects, [PIN_XATTR], which unpins the file, and [DIRECT_IO_XATTR], which
+    /// opens the file or the files under the directory with the page cache again.
+    pub async fn removexattr(&self, ino: InodeNo, name: &OsStr) -> Result<bool, Error> {
+        trace!("fs:removexattr with ino {:?} name {:?}", ino, name);
+
+        if let Some(key) = self.tag_key(name) {
+            return self.update_object_tags(ino, |tags| tags.remove(key).is_some()).await;
+        }
+        if let Some(direct_io_keys) = self.direct_io_keys.as_ref().filter(|_| name == DIRECT_IO_XATTR) {
+            let lookup = self.metablock.getattr(ino, false).await?;
+            return Ok(direct_io_keys.set(&lookup.s3_location()?.partial_key, false));
+        }
+
+        let Some(pinned_objects) = self.pinned_objects.as_ref().filter(|_| name == PIN_XATTR) else {
+            return Ok(false);
+        };
+        let key = self.pinning_key(ino).await?;
+        Ok(pinned_objects.unpin(&key))
+    }
+
+    /// List the extended attributes of an inode. Only the user-defined metadata and tags of its object are listed, if
+    /// [S3FilesystemConfig::user_metadata_xattrs] and [S3FilesystemConfig::tag_xattrs] are enabled.
+    pub async fn listxattr(&self, ino: InodeNo) -> Result<Vec<OsString>, Error> {
+        trace!("fs:listxattr with ino {:?}", ino);
+
+        let mut names = Vec::new();
+        if self.config.user_metadata_xattrs {
+            let mut keys: Vec<_> = self.user_metadata(ino).await?.into_keys().collect();
+            keys.sort();
+            names.extend(
+                keys.into_iter()
+                    .map(|key| format!("{USER_METADATA_XATTR_PREFIX}{key}").into()),
+            );
+        }
+        if let Some(tags) = self.object_tags(ino).await? {
+            let mut keys: Vec<_> = tags.into_keys().collect();
+            keys.sort();
+            names.extend(keys.into_iter().map(|key| format!("{TAG_XATTR_PREFIX}{key}").into()));
+        }
+        Ok(names)
+    }
+
+    /// The key of the tag named by an extended attribute, if they are exposed.
+    fn tag_key<'a>(&self, name: &'a OsStr) -> Option<&'a str> {
+        self.object_tags.as_ref()?;
+        name.to_str()?
+            .strip_prefix(TAG_XATTR_PREFIX)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// The tags of the object of an inode, if they are exposed. They are fetched with GetObjectTagging the first time
+    /// they are accessed, and cached along with the metadata of the inode. Files being created report the tags they
+    /// are being uploaded with, and directories have none.
+    async fn object_tags(&self, ino: InodeNo) -> Result<Option<ObjectTags>, Error> {
+        let Some(object_tags) = &self.object_tags else {
+            return Ok(None);
+        };
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File {
+            return Ok(None);
+        }
+        let Some(etag) = lookup.stat().etag.as_deref() else {
+            return Ok(self.upload_tags(ino).await);
+        };
+        if let Some(tags) = object_tags.get(ino, etag) {
+            return Ok(Some(tags));
+        }
+        let location = lookup.s3_location()?;
+        let key = self.s3_key(location.full_key().as_ref());
+        let tags = match self.client.get_object_tagging(location.bucket_name(), &key).await {
+            Ok(result) => result.tags,
+            Err(ObjectClientError::ServiceError(GetObjectTaggingError::NoSuchKey)) => {
+                return Err(err!(libc::ENOENT, "object {} no longer exists", location));
+            }
+            Err(e) => return Err(err!(libc::EIO, source:e, "GetObjectTagging failed for {}", location)),
+        };
+        let validity = self.config.cache_config.ttl_for(InodeKind::File, &key);
+        object_tags.insert(ino, etag, tags.clone(), validity);
+        Ok(Some(tags))
+    }
+
+    /// Update the tags of the object of an inode with `update`, which returns whether it changed them, and put them
+    /// with PutObjectTagging if it did. Returns whether the tags were changed.
+    async fn update_object_tags(
+        &self,
+        ino: InodeNo,
+        update: impl FnOnce(&mut ObjectTags) -> bool,
+    ) -> Result<bool, Error> {
+        let Some(object_tags) = &self.object_tags else {
+            return Ok(false);
+        };
+        // Tags are replaced all at once, so concurrent updates could lose each other's changes.
+        let _updating = object_tags.lock_updates().await;
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File {
+            return Err(err!(libc::EPERM, "inode {} is not a file and can not be tagged", ino));
+        }
+        let Some(etag) = lookup.stat().etag.clone() else {
+            return self.update_upload_tags(ino, update).await;
+        };
+        let mut tags = self.object_tags(ino).await?.unwrap_or_default();
+        if !update(&mut tags) {
+            return Ok(false);
+        }
+        if tags.len() > MAX_TAGS {
+            return Err(err!(libc::ENOSPC, "objects can have at most {} tags", MAX_TAGS));
+        }
+        let location = lookup.s3_location()?;
+        let key = self.s3_key(location.full_key().as_ref());
+        match self
+            .client
+            .put_object_tagging(location.bucket_name(), &key, &tags)
+            .await
+        {
+            Ok(_) => {}
+            Err(ObjectClientError::ServiceError(PutObjectTaggingError::NoSuchKey)) => {
+                return Err(err!(libc::ENOENT, "object {} no longer exists", location));
+            }
+            Err(ObjectClientError::ServiceError(PutObjectTaggingError::InvalidTag)) => {
+                return Err(err!(libc::EINVAL, "invalid tags for object {}", location));
+            }
+            Err(e) => return Err(err!(libc::EIO, source:e, "PutObjectTagging failed for {}", location)),
+        }
+        let validity = self.config.cache_config.ttl_for(InodeKind::File, &key);
+        object_tags.insert(ino, &etag, tags, validity);
+        Ok(true)
+    }
+
+    /// The tags a file being created is uploaded with, if its upload can be tagged.
+    async fn upload_tags(&self, ino: InodeNo) -> Option<ObjectTags> {
+        let handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .values()
+                .filter(|handle| handle.ino == ino)
+                .cloned()
+                .collect()
+        };
+        for handle in handles {
+            let state = handle.state.lock().await;
+            if let FileHandleState::Write { state, .. } = &*state
+                && let Some(tags) = state.object_tags()
+            {
+                return Some(tags.clone());
+            }
+        }
+        None
+    }
+
+    /// Update the tags a file being created is uploaded with, like [Self::update_object_tags]. They can only be
+    /// changed before any data is uploaded, that is before the file is written to, unless its writes are staged.
+    async fn update_upload_tags(
+        &self,
+        ino: InodeNo,
+        update: impl FnOnce(&mut ObjectTags) -> bool,
+    ) -> Result<bool, Error> {
+        let handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .values()
+                .filter(|handle| handle.ino == ino)
+                .cloned()
+                .collect()
+        };
+        for handle in handles {
+            let mut state = handle.state.lock().await;
+            let FileHandleState::Write {
+                state: upload_state, ..
+            } = &mut *state
+            else {
+                continue;
+            };
+            let Some(mut tags) = upload_state.object_tags().cloned() else {
+                continue;
+            };
+            if !update(&mut tags) {
+                return Ok(false);
+            }
+            if tags.len() > MAX_TAGS {
+                return Err(err!(libc::ENOSPC, "objects can have at most {} tags", MAX_TAGS));
+            }
+            if !upload_state.set_object_tags(tags) {
+                return Err(err!(
+                    libc::EBUSY,
+                    "tags of file {} can only be set before it is written to",
+                    handle.location
+                ));
+            }
+            return Ok(true);
+        }
+        Err(err!(libc::EBUSY, "file {} is being written and can not be tagged", ino))
+    }
+
+    /// The name of the extended attribute describing the object of a file, if they are exposed.
+    fn object_info_xattr_name(&self, name: &OsStr) -> Option<&'static str> {
+        if !self.config.object_info_xattrs {
+            return None;
+        }
+        OBJECT_INFO_XATTRS.into_iter().find(|xattr| name == *xattr)
+    }
+
+    /// The ETag, version ID or storage class of the object of an inode, from its cached metadata. Files being
+    /// written and directories have none.
+    async fn object_info(&self, ino: InodeNo, name: &str) -> Result<Option<String>, Error> {
+        let mut lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File || lookup.stat().etag.is_none() {
+            return Ok(None);
+        }
+        // Only HeadObject returns the version ID, so files listed from their directory are looked up again.
+        if name == VERSION_ID_XATTR && lookup.stat().version_id.is_none() {
+            lookup = self.metablock.getattr(ino, true).await?;
+        }
+        let stat = lookup.stat();
+        let value = match name {
+            ETAG_XATTR => stat.etag.as_deref().map(|etag| etag.trim_matches('"')),
+            VERSION_ID_XATTR => stat.version_id.as_deref(),
+            STORAGE_CLASS_XATTR => stat.storage_class.as_deref(),
+            _ => unreachable!("not an object info attribute: {name}"),
+        };
+        Ok(value.map(str::to_owned))
+    }
+
+    /// A URL to get the object of an inode, pre-signed to be valid for `expires_in`, at the version the files of
+    /// version directories are pinned to. Signing needs no request to S3, and doesn't check that the object still
+    /// exists. Files being written and directories have none.
+    async fn presigned_url(&self, ino: InodeNo, expires_in: Duration) -> Result<Option<String>, Error> {
+        let lookup = self.metablock.getattr(ino, false).await?;
+        let stat = lookup.stat();
+        if lookup.kind() != InodeKind::File || stat.etag.is_none() {
+            return Ok(None);
+        }
+        let location = lookup.s3_location()?;
+        let key = self.object_key(location, stat);
+        let version_id = stat.version_id.as_deref().filter(|_| stat.pinned_version);
+        let params = PresignGetObjectParams::new().version_id(version_id.map(str::to_owned));
+        match self
+            .client
+            .presign_get_object(location.bucket_name(), &key, expires_in, &params)
+            .await
+        {
+            Ok(result) => Ok(Some(result.url)),
+            Err(e) => Err(err!(libc::EIO, source:e, "pre-signing GetObject failed for {}", location)),
+        }
+    }
+
+    /// The total size of the objects under the prefix of a directory, listed with ListObjectsV2 unless it was listed
+    /// in the last [RECURSIVE_SIZE_VALIDITY]. Every object under the prefix counts, including those of files not shown
+    /// in the directory, but not the files being written. Files have none.
+    async fn recursive_size(&self, ino: InodeNo, recursive_sizes: &RecursiveSizeCache) -> Result<Option<u64>, Error> {
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::Directory {
+            return Ok(None);
+        }
+        let location = lookup.s3_location()?;
+        let prefix = self.s3_key(location.full_key().as_ref());
+        if let Some(size) = recursive_sizes.get(&prefix) {
+            return Ok(Some(size));
+        }
+
+        let bucket = location.bucket_name();
+        let mut size = 0;
+        let mut objects = 0;
+        let mut continuation_token = None;
+        loop {
+            let result = match self
+                .client
+                .list_objects(bucket, continuation_token.as_deref(), "", 1000, &prefix)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => return Err(err!(libc::EIO, source:e, "ListObjectsV2 failed for {}", location)),
+            };
+            objects += result.objects.len();
+            size += result.objects.iter().map(|object| object.size).sum::<u64>();
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        debug!(?prefix, objects, size, "listed the total size of a directory");
+        recursive_sizes.insert(&prefix, size, RECURSIVE_SIZE_VALIDITY);
+        Ok(Some(size))
+    }
+
+    /// Whether an extended attribute is [RESTORE_STATUS_XATTR], if it is exposed.
+    /// Whether the extended attribute is one overlayfs looks up on its layers, when this is its lower layer.
+    fn is_overlayfs_xattr(&self, name: &OsStr) -> bool {
+        self.config.overlayfs_lower
+            && name
+                .to_str()
+                .is_some_and(|name| OVERLAYFS_XATTR_PREFIXES.iter().any(|prefix| name.starts_with(prefix)))
+    }
+
+    fn is_restore_status_xattr(&self, name: &OsStr) -> bool {
+        self.config.archived_objects == ArchivedObjectsPolicy::RestoreStatus && name == RESTORE_STATUS_XATTR
+    }
+
+    /// The restore status of the object of an inode, if it is in an archived storage class, looked up in S3 so that
+    /// it is current. Files being written and directories have none.
+    async fn restore_status(&self, ino: InodeNo) -> Result<Option<String>, Error> {
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File || lookup.stat().etag.is_none() {
+            return Ok(None);
+        }
+        let lookup = self.metablock.getattr(ino, true).await?;
+        let stat = lookup.stat();
+        Ok(restore_status_value(stat.storage_class.as_deref(), stat.restore_status))
+    }
+
+    /// The key of the user-defined metadata named by an extended attribute, if they are exposed.
+    fn user_metadata_key<'a>(&self, name: &'a OsStr) -> Option<&'a str> {
+        if !self.config.user_metadata_xattrs {
+            return None;
+        }
+        name.to_str()?
+            .strip_prefix(USER_METADATA_XATTR_PREFIX)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// The user-defined metadata of the object of an inode, looked up in S3. Files being written report the metadata
+    /// they are being uploaded with, and directories have none.
+    async fn user_metadata(&self, ino: InodeNo) -> Result<ObjectMetadata, Error> {
+        let lookup = self.metablock.getattr(ino, false).await?;
+        if lookup.kind() != InodeKind::File {
+            return Ok(ObjectMetadata::new());
+        }
+        if lookup.stat().etag.is_none() {
+            let handles: Vec<_> = {
+                let file_handles = self.file_handles.read().await;
+                file_handles
+                    .values()
+                    .filter(|handle| handle.ino == ino)
+                    .cloned()
+                    .collect()
+            };
+            for handle in handles {
+                let state = handle.state.lock().await;
+                match &*state {
+                    FileHandleState::Write {
+                        state: UploadState::MPUInProgress { request },
+                        ..
+                    } => return Ok(request.object_metadata().clone()),
+                    FileHandleState::Write {
+                        state: UploadState::StagedInProgress { request },
+                        ..
+                    } => return Ok(request.object_metadata().clone()),
+                    _ => {}
+                }
+            }
+            return Ok(ObjectMetadata::new());
+        }
+        let location = lookup.s3_location()?;
+        match self
+            .client
+            .head_object(
+                location.bucket_name(),
+                &self.s3_key(location.full_key().as_ref()),
+                &HeadObjectParams::new(),
+            )
+            .await
+        {
+            Ok(result) => Ok(result.object_metadata),
+            Err(ObjectClientError::ServiceError(HeadObjectError::NotFound)) => {
+                Err(err!(libc::ENOENT, "object {} no longer exists", location))
+            }
+            Err(e) => Err(err!(libc::EIO, source:e, "HeadObject failed for {}", location)),
+        }
+    }
+
+    /// Truncate or extend a file being written, if its writes are staged. Applications writing to a shared mapping of
+    /// a new file set its size this way before mapping it. The size of other files being written is left unchanged.
+    async fn resize_staged_upload(&self, ino: InodeNo, size: u64) -> Result<(), Error> {
+        let handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .values()
+                .filter(|handle| handle.ino == ino)
+                .cloned()
+                .collect()
+        };
+        for handle in handles {
+            let mut state = handle.state.lock().await;
+            if let FileHandleState::Write {
+                state: upload_state, ..
+            } = &mut *state
+                && upload_state.set_size(size).await?
+            {
+                self.metablock.set_file_size(ino, size as usize).await?;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the user-defined metadata a file being created is uploaded with. The upload is restarted with the new
+    /// metadata, so it can only be set before any data is written, unless its writes are staged.
+    async fn set_user_metadata(&self, ino: InodeNo, key: &str, value: &str) -> Result<(), Error> {
+        let handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .values()
+                .filter(|handle| handle.ino == ino)
+                .cloned()
+                .collect()
+        };
+        for handle in handles {
+            let mut state = handle.state.lock().await;
+            let FileHandleState::Write {
+                state: upload_state, ..
+            } = &mut *state
+            else {
+                continue;
+            };
+            if let UploadState::StagedInProgress { request } = upload_state {
+                // Staged uploads only start once complete, so their metadata can still be set after writes.
+                let mut object_metadata = request.object_metadata().clone();
+                object_metadata.insert(key.to_owned(), value.to_owned());
+                request.set_object_metadata(object_metadata);
+                return Ok(());
+            }
+            let UploadState::MPUInProgress { request } = upload_state else {
+                continue;
+            };
+            if request.size() > 0 {
+                return Err(err!(
+                    libc::EBUSY,
+                    "user metadata of file {} can only be set before it is written to",
+                    handle.location
+                ));
+            }
+            let mut object_metadata = request.object_metadata().clone();
+            object_metadata.insert(key.to_owned(), value.to_owned());
+            let object_tags = request.object_tags().clone();
+            // Abort the current upload before starting the new one for the same key.
+            *upload_state = UploadState::Failed(libc::EIO);
+            let mut request = self
+                .uploader
+                .start_atomic_upload_with_metadata(
+                    handle.location.bucket_name().to_owned(),
+                    self.s3_key(handle.location.full_key().as_ref()),
+                    object_metadata,
+                )
+                .map_err(|e| err!(libc::EIO, source:e, "put failed to restart"))?;
+            request.set_object_tags(object_tags);
+            *upload_state = UploadState::MPUInProgress { request };
+            return Ok(());
+        }
+        Err(err!(
+            libc::EPERM,
+            "user metadata can only be set on files being created, not on inode {}",
+            ino
+        ))
+    }
+
+    /// Set the modification time and permissions of a file being written in the metadata its staged upload is made
+    /// with, rather than copying its object once uploaded. Return whether the file has a staged upload.
+    async fn stage_metadata_update(&self, ino: InodeNo, update: &MetadataUpdate) -> bool {
+        let handles: Vec<_> = {
+            let file_handles = self.file_handles.read().await;
+            file_handles
+                .values()
+                .filter(|handle| handle.ino == ino)
+                .cloned()
+                .collect()
+        };
+        for handle in handles {
+            let mut state = handle.state.lock().await;
+            let FileHandleState::Write {
+                state: UploadState::StagedInProgress { request },
+                ..
+            } = &mut *state
+            else {
+                continue;
+            };
+            let mut object_metadata = request.object_metadata().clone();
+            if let Some(mtime) = update.mtime {
+                object_metadata.insert(MTIME_METADATA_KEY.to_owned(), format_mtime(mtime).0);
+            }
+            if let Some(mode) = update.mode {
+                object_metadata.insert(MODE_METADATA_KEY.to_owned(), format_mode(mode));
+            }
+            request.set_object_metadata(object_metadata);
+            return true;
+        }
+        false
+    }
+
+    /// The key of the object of an inode, which the objects pinned with [PIN_XATTR] are tracked by.
+    async fn pinning_key(&self, ino: InodeNo) -> Result<String, Error> {
+        let lookup = self.metablock.getattr(ino, false).await?;
+        Ok(self.object_key(lookup.s3_location()?, lookup.stat()))
+    }
+
+    /// The key of the object a file is read from. The files of versions directories are versions of the object they
+    /// hold the versions of, see [S3FilesystemConfig::version_directories].
+    fn object_key(&self, location: &S3Location, stat: &InodeStat) -> String {
+        let full_key = location.full_key();
+        match version_object_key(full_key.as_ref()) {
+            Some(object_key) if self.config.version_directories && stat.pinned_version => self.s3_key(object_key),
+            _ => match shadowed_file_key(full_key.as_ref()) {
+                Some(file_key) if stat.shadowed => self.s3_key(file_key),
+                _ => self.s3_key(&compressed_file_key(full_key.as_ref(), stat.decompressed)),
+            },
+        }
+    }
+
+    /// The key of the object at the given path, with the components separated by the delimiter of keys, see
+    /// [S3FilesystemConfig::key_delimiter].
+    fn s3_key(&self, full_key: &str) -> String {
+        self.config.key_mapping().key_for_path(full_key).into_owned()
+    }
+
+    /// Identify the object a file is read from, at the version it is pinned to if any.
+    fn object_id(&self, location: &S3Location, stat: &InodeStat, etag: ETag) -> ObjectId {
+        let version_id = stat.version_id.as_deref().filter(|_| stat.pinned_version);
+        ObjectId::with_version_id(self.object_key(location, stat), etag, version_id.map(Into::into))
     }
 
     pub async fn mknod(
@@ -428,7 +1599,7 @@ where
         parent: InodeNo,
         name: &OsStr,
         mode: libc::mode_t,
-        _umask: u32,
+        umask: u32,
         _rdev: u32,
     ) -> Result<Entry, Error> {
         if mode & libc::S_IFMT != libc::S_IFREG {
@@ -439,8 +1610,20 @@ where
             ));
         }
 
-        let lookup = self.metablock.create(parent, name, InodeKind::File).await?;
+        let mut lookup = self.metablock.create(parent, name, InodeKind::File).await?;
         debug!(ino = lookup.ino(), "new inode created");
+        if self.config.mode_metadata {
+            let mode = (mode & !umask & 0o7777) as u16;
+            lookup = self.metablock.set_mode(lookup.ino(), mode).await?;
+            // Incremental uploads can't set the metadata of new objects, so it is set once they are uploaded instead.
+            if self.config.incremental_upload {
+                let update = MetadataUpdate {
+                    mode: Some(mode),
+                    ..Default::default()
+                };
+                self.pending_metadata.lock().unwrap().insert(lookup.ino(), update);
+            }
+        }
         let ttl = lookup.validity();
         let attr = self.make_attr(&lookup.into());
         Ok(Entry {
@@ -452,6 +1635,9 @@ where
 
     pub async fn mkdir(&self, parent: InodeNo, name: &OsStr, _mode: libc::mode_t, _umask: u32) -> Result<Entry, Error> {
         let lookup = self.metablock.create(parent, name, InodeKind::Directory).await?;
+        if self.config.directory_markers {
+            return self.create_directory_marker(parent, name, &lookup).await;
+        }
         let ttl = lookup.validity();
         let attr = self.make_attr(&lookup.into());
         Ok(Entry {
@@ -461,6 +1647,94 @@ where
         })
     }
 
+    /// Upload the marker object of a newly created directory, see [S3FilesystemConfig::directory_markers]. The directory
+    /// is removed again if the upload fails.
+    async fn create_directory_marker(&self, parent: InodeNo, name: &OsStr, lookup: &Lookup) -> Result<Entry, Error> {
+        let location = lookup.s3_location()?;
+        let key = self.s3_key(location.full_key().as_ref());
+        let result = async {
+            let request = self
+                .uploader
+                .start_atomic_upload_with_metadata(
+                    location.bucket_name().to_owned(),
+                    key.clone(),
+                    ObjectMetadata::new(),
+                )
+                .map_err(|e| err!(libc::EIO, source:e, "put failed to start"))?;
+            request
+                .complete()
+                .await
+                .map_err(|e| err!(libc::EIO, source:e, "put failed"))
+        }
+        .await;
+        if let Err(e) = result {
+            if let Err(rmdir_err) = self.metablock.rmdir(parent, name).await {
+                warn!(key, error=?rmdir_err, "failed to remove directory after its marker could not be created");
+            }
+            return Err(e);
+        }
+        debug!(key, "directory marker created");
+
+        // The directory is now remote, as it has an object.
+        self.metablock.invalidate(&key).await;
+        self.lookup(parent, name).await
+    }
+
+    /// Create a symbolic link, if [S3FilesystemConfig::symlinks] is enabled. It is uploaded right away, as an object
+    /// holding its target both as its content and in its [SYMLINK_TARGET_METADATA_KEY] user-defined metadata.
+    pub async fn symlink(&self, parent: InodeNo, name: &OsStr, link: &Path) -> Result<Entry, Error> {
+        trace!("fs:symlink with parent {:?} name {:?} link {:?}", parent, name, link);
+
+        if !self.config.symlinks {
+            return Err(err!(libc::EPERM, "symlinks are not supported"));
+        }
+        let Some(target) = link.to_str() else {
+            return Err(err!(libc::EINVAL, "symlink target {:?} must be valid UTF-8", link));
+        };
+        match self.metablock.lookup(parent, name).await {
+            Ok(lookup) => return Err(err!(libc::EEXIST, "file already exists at inode {}", lookup.ino())),
+            Err(InodeError::FileDoesNotExist(_, _)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        let parent_lookup = self.metablock.getattr(parent, false).await?;
+        let parent_location = parent_lookup.s3_location()?;
+        let full_key = parent_location
+            .full_key()
+            .new_child(ValidName::parse_os_str(name)?, InodeKind::File)
+            .map_err(|_| err!(libc::ENOTDIR, "inode {} is not a directory", parent))?;
+        let key = self.s3_key(full_key.as_ref());
+
+        let object_metadata = ObjectMetadata::from([(SYMLINK_TARGET_METADATA_KEY.to_owned(), target.to_owned())]);
+        let mut request = self
+            .uploader
+            .start_atomic_upload_with_metadata(parent_location.bucket_name().to_owned(), key.clone(), object_metadata)
+            .map_err(|e| err!(libc::EIO, source:e, "put failed to start"))?;
+        request
+            .write(0, target.as_bytes())
+            .await
+            .map_err(|e| err!(libc::EIO, source:e, "put failed"))?;
+        request
+            .complete()
+            .await
+            .map_err(|e| err!(libc::EIO, source:e, "put failed"))?;
+        debug!(key, target, "symlink created");
+
+        // The lookup before the upload may have cached that the name doesn't exist.
+        self.metablock.invalidate(&key).await;
+        self.lookup(parent, name).await
+    }
+
+    /// Read the target of a symbolic link.
+    pub async fn readlink(&self, ino: InodeNo) -> Result<OsString, Error> {
+        trace!("fs:readlink with ino {:?}", ino);
+
+        let lookup = self.metablock.getattr(ino, false).await?;
+        match &lookup.stat().symlink_target {
+            Some(target) => Ok(target.to_string().into()),
+            None => Err(err!(libc::EINVAL, "inode {} is not a symlink", ino)),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)] // We don't get to choose this interface
     pub async fn write(
         &self,
@@ -468,7 +1742,7 @@ where
         fh: u64,
         offset: i64,
         data: &[u8],
-        _write_flags: u32,
+        write_flags: u32,
         _flags: i32,
         _lock_owner: Option<u64>,
     ) -> Result<u32, Error> {
@@ -486,11 +1760,19 @@ where
             }
         };
         logging::record_name(handle.file_name());
+        // Writes from the page cache write back the dirty pages of a shared writable mapping, which the kernel keeps
+        // doing until the file is unmapped. With the writeback cache, all the writes come from the page cache, and
+        // the kernel writes back the dirty pages of a file before flushing it.
+        if write_flags & FUSE_WRITE_CACHE != 0 && !self.config.writeback_cache {
+            handle.written_back.store(true, Ordering::SeqCst);
+        }
 
         let len = {
             let mut state = handle.state.lock().await;
             let (request, flushed) = match &mut *state {
-                FileHandleState::Read { .. } => return Err(err!(libc::EBADF, "file handle is not open for writes")),
+                FileHandleState::Read { .. } | FileHandleState::ReadWhileWriting { .. } => {
+                    return Err(err!(libc::EBADF, "file handle is not open for writes"));
+                }
                 FileHandleState::Write { state, flushed } => (state, flushed),
             };
 
@@ -510,7 +1792,13 @@ where
                 *flushed = false;
             }
 
-            request.write(self, &handle, offset, data, fh).await?
+            self.quota
+                .check_file_size(u64::try_from(offset).unwrap_or(0).saturating_add(len as u64))?;
+            self.quota.reserve(len as u64)?;
+            let result = request.write(self, &handle, offset, data, fh).await;
+            self.quota
+                .release(len as u64 - result.as_ref().map_or(0, |&written| written as u64));
+            result?
         };
         Ok(len)
     }
@@ -593,7 +1881,7 @@ where
         logging::record_name(file_handle.file_name());
         let mut state = file_handle.state.lock().await;
         match &mut *state {
-            FileHandleState::Read { .. } => {
+            FileHandleState::Read { .. } | FileHandleState::ReadWhileWriting { .. } => {
                 return Ok(());
             }
             FileHandleState::Write { state, .. } => {
@@ -607,6 +1895,114 @@ where
         Ok(())
     }
 
+    /// Copy a range of a file to a file being written, server-side with UploadPartCopy rather than by reading and
+    /// writing its data. Copies must be sequential, from files that are not being written, and into new files not
+    /// written to otherwise, or files appended to by copy, see [S3FilesystemConfig::append_by_copy]. Other copies fail
+    /// with `EOPNOTSUPP`, which makes the kernel fall back to reading and writing the data.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn copy_file_range(
+        &self,
+        ino_in: InodeNo,
+        fh_in: u64,
+        offset_in: i64,
+        ino_out: InodeNo,
+        fh_out: u64,
+        offset_out: i64,
+        len: u64,
+        flags: u32,
+    ) -> Result<u32, Error> {
+        if flags != 0 {
+            return Err(err!(
+                libc::EINVAL,
+                "copy_file_range flags {:#x} are not supported",
+                flags
+            ));
+        }
+        let (Ok(offset_in), Ok(offset_out)) = (u64::try_from(offset_in), u64::try_from(offset_out)) else {
+            return Err(err!(libc::EINVAL, "invalid copy_file_range offset"));
+        };
+        if ino_in == ino_out {
+            return Err(copy_unsupported("the source and destination are the same file"));
+        }
+        let (source_handle, file_handle) = {
+            let file_handles = self.file_handles.read().await;
+            match (file_handles.get(&fh_in), file_handles.get(&fh_out)) {
+                (Some(source_handle), Some(handle)) => (source_handle.clone(), handle.clone()),
+                _ => return Err(err!(libc::EBADF, "invalid file handle")),
+            }
+        };
+        logging::record_name(file_handle.file_name());
+
+        let lookup = self.metablock.getattr(source_handle.ino, false).await?;
+        let stat = lookup.stat();
+        let Some(etag) = stat.etag.as_deref() else {
+            return Err(copy_unsupported("the source file is being written"));
+        };
+        if stat.pinned_version {
+            return Err(copy_unsupported("the source file is a version of its object"));
+        }
+        if stat.encoded_size.is_some() {
+            return Err(copy_unsupported("the source file is decoded from its object"));
+        }
+        // The number of bytes copied is returned as a u32.
+        let end = offset_in.saturating_add(len.min(u32::MAX as u64)).min(stat.size as u64);
+        if offset_in >= end {
+            return Ok(0);
+        }
+        let source = self.object_id(
+            lookup.s3_location()?,
+            stat,
+            ETag::from_str(etag).expect("E-Tag should be set"),
+        );
+
+        let mut state = file_handle.state.lock().await;
+        let FileHandleState::Write { state, .. } = &mut *state else {
+            return Err(err!(libc::EBADF, "file handle is not open for writes"));
+        };
+        let requested = end - offset_in;
+        self.quota.check_file_size(offset_out.saturating_add(requested))?;
+        self.quota.reserve(requested)?;
+        let result = state
+            .copy_from(self, &file_handle, offset_out, &source, offset_in..end, fh_out)
+            .await;
+        self.quota.release(requested - result.as_ref().map_or(0, |&len| len));
+        let len = result?;
+        debug!(source = source.key(), len, "copied range server-side");
+        metrics::counter!("fs.copy_file_range.copied_bytes").increment(len);
+        Ok(len as u32)
+    }
+
+    /// Preallocate space for a file being written. Nothing is allocated, but the end of the range is taken as a hint of
+    /// the final size of the file, which lets its upload choose a part size that keeps it under the maximum number of
+    /// parts of a multipart upload. The size of the file is not changed, even without `FALLOC_FL_KEEP_SIZE`.
+    pub async fn fallocate(&self, _ino: InodeNo, fh: u64, offset: i64, length: i64, mode: i32) -> Result<(), Error> {
+        if mode & !libc::FALLOC_FL_KEEP_SIZE != 0 {
+            return Err(err!(libc::EOPNOTSUPP, "fallocate mode {:#x} is not supported", mode));
+        }
+        let (Ok(offset), Ok(length)) = (u64::try_from(offset), u64::try_from(length)) else {
+            return Err(err!(libc::EINVAL, "invalid fallocate range"));
+        };
+        let file_handle = {
+            let file_handles = self.file_handles.read().await;
+            match file_handles.get(&fh) {
+                Some(handle) => handle.clone(),
+                None => return Err(err!(libc::EBADF, "invalid file handle")),
+            }
+        };
+        logging::record_name(file_handle.file_name());
+        let mut state = file_handle.state.lock().await;
+        match &mut *state {
+            FileHandleState::Read { .. } | FileHandleState::ReadWhileWriting { .. } => {
+                Err(err!(libc::EBADF, "file handle is not open for writes"))
+            }
+            FileHandleState::Write { state, .. } => {
+                self.quota.check_file_size(offset.saturating_add(length))?;
+                state.set_size_hint(offset.saturating_add(length));
+                Ok(())
+            }
+        }
+    }
+
     pub async fn flush(&self, ino: InodeNo, fh: u64, _lock_owner: u64, pid: u32) -> Result<(), Error> {
         // For read-handles:
         // Flush simply records the handle's state as flushed, and is functionally
@@ -642,7 +2038,7 @@ where
         logging::record_name(file_handle.file_name());
         let mut state = file_handle.state.lock().await;
         match &mut *state {
-            FileHandleState::Read { flushed, .. } => {
+            FileHandleState::Read { flushed, .. } | FileHandleState::ReadWhileWriting { flushed, .. } => {
                 self.metablock.flush_reader(ino, fh).await?;
                 *flushed = true;
             }
@@ -651,6 +2047,9 @@ where
                     .complete(self, file_handle.clone(), pid, file_handle.open_pid, fh)
                     .await?;
                 *flushed = true;
+                if matches!(state, UploadState::Completed) {
+                    self.apply_pending_metadata(ino).await?;
+                }
             }
         }
         Ok(())
@@ -678,9 +2077,10 @@ where
         logging::record_name(file_handle.file_name());
 
         match &*file_handle.state.lock().await {
-            FileHandleState::Read { .. } => {
+            FileHandleState::Read { .. } | FileHandleState::ReadWhileWriting { .. } => {
                 metrics::gauge!("fs.current_handles", "type" => "read").decrement(1.0);
                 self.metablock.finish_reading(file_handle.ino, fh).await?;
+                self.flush_access_time(ino).await;
                 return Ok(());
             }
             FileHandleState::Write { .. } => {}
@@ -702,9 +2102,179 @@ where
             .await?;
 
         metrics::gauge!("fs.current_handles", "type" => "write").decrement(1.0);
+        self.apply_pending_metadata(ino).await
+    }
+
+    /// Store the time a file was last read in the metadata of its object, once its last handle is closed, if it changed
+    /// and wasn't stored within [S3FilesystemConfig::atime_flush_interval]. The object is copied onto itself, which
+    /// would fail the reads of other handles, so the time is stored once the last of them is closed. Failures are only
+    /// logged, as the file was read successfully.
+    async fn flush_access_time(&self, ino: InodeNo) {
+        let Some(access_times) = &self.access_times else {
+            return;
+        };
+        if self.file_handles.read().await.values().any(|handle| handle.ino == ino) {
+            return;
+        }
+        let Some(atime) = access_times.take_flush(ino, std::time::Instant::now()) else {
+            return;
+        };
+        let update = MetadataUpdate {
+            atime: Some(atime),
+            ..Default::default()
+        };
+        let result = match self.metablock.getattr(ino, false).await {
+            // Files being written get their metadata when uploaded.
+            Ok(lookup) if lookup.kind() != InodeKind::File || lookup.stat().etag.is_none() => return,
+            Ok(lookup) => self.update_object_metadata(&lookup, update).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(_) => metrics::counter!("fs.atime_flushes").increment(1),
+            Err(error) => {
+                warn!(ino, ?error, "unable to store the access time of file");
+                access_times.flush_failed(ino);
+            }
+        }
+    }
+
+    /// Put the attributes set on a file while it was being written in the metadata of its object, once it has been
+    /// uploaded.
+    async fn apply_pending_metadata(&self, ino: InodeNo) -> Result<(), Error> {
+        let Some(update) = self.pending_metadata.lock().unwrap().remove(&ino) else {
+            return Ok(());
+        };
+        let lookup = self.metablock.getattr(ino, false).await?;
+        self.update_object_metadata(&lookup, update).await?;
         Ok(())
     }
 
+    /// Truncate the object of a remote file to the given size, or extend it with zeros, by replacing it with a copy of
+    /// the range it retains.
+    async fn truncate_object(&self, lookup: &Lookup, size: u64) -> Result<Lookup, Error> {
+        let stat = lookup.stat();
+        if size == stat.size as u64 {
+            return Ok(lookup.clone());
+        }
+        if stat.encoded_size.is_some() {
+            return Err(err!(
+                libc::EPERM,
+                "inode {} is decoded from its object and can not be truncated",
+                lookup.ino()
+            ));
+        }
+        let location = lookup.s3_location()?;
+        let Some(etag) = &stat.etag else {
+            return Err(err!(libc::EBADF, "no E-Tag for inode {}", lookup.ino()));
+        };
+        let result = self
+            .uploader
+            .truncate_object(
+                location.bucket_name().to_owned(),
+                self.s3_key(location.full_key().as_ref()),
+                stat.size as u64,
+                etag.into(),
+                size,
+            )
+            .await?;
+        debug!(%location, old_size = stat.size, size, "truncated object");
+        metrics::counter!("fs.truncates").increment(1);
+        Ok(self
+            .metablock
+            .truncate_remote_file(lookup.ino(), result.etag, size as usize)
+            .await?)
+    }
+
+    /// Set the modification time, permissions or access time of a remote file, by copying its object onto itself with
+    /// them in its [MTIME_METADATA_KEY], [MODE_METADATA_KEY] and [ATIME_METADATA_KEY] metadata. The other user-defined
+    /// metadata, the storage class and the content type of the object are kept.
+    async fn update_object_metadata(&self, lookup: &Lookup, update: MetadataUpdate) -> Result<Lookup, Error> {
+        let location = lookup.s3_location()?;
+        let bucket = location.bucket_name();
+        let key = self.s3_key(location.full_key().as_ref());
+        let head =
+            async |error: &'static str| match self.client.head_object(bucket, &key, &HeadObjectParams::new()).await {
+                Ok(result) => Ok(result),
+                Err(ObjectClientError::ServiceError(HeadObjectError::NotFound)) => {
+                    Err(err!(libc::ENOENT, "object {} no longer exists", location))
+                }
+                Err(e) => Err(err!(libc::EIO, source:e, "{} for {}", error, location)),
+            };
+
+        let HeadObjectResult {
+            size,
+            mut object_metadata,
+            storage_class,
+            content_type,
+            content_encoding,
+            cache_control,
+            content_disposition,
+            sse_type,
+            sse_kms_key_id,
+            ..
+        } = head("HeadObject failed").await?;
+        if size > MAX_COPY_OBJECT_SIZE {
+            return Err(err!(
+                libc::EFBIG,
+                "can't update the metadata of object {} of {} bytes, larger than CopyObject can copy",
+                location,
+                size
+            ));
+        }
+        // The copy changes the last modified time of the object, which would otherwise become the modification time.
+        let mtime = match update.mtime {
+            None if !self.config.mtime_metadata || object_metadata.contains_key(MTIME_METADATA_KEY) => None,
+            mtime => Some(mtime.unwrap_or(lookup.stat().mtime)),
+        };
+        let mtime = mtime.map(|mtime| {
+            let (value, mtime) = format_mtime(mtime);
+            object_metadata.insert(MTIME_METADATA_KEY.to_owned(), value);
+            mtime
+        });
+        if let Some(mode) = update.mode {
+            object_metadata.insert(MODE_METADATA_KEY.to_owned(), format_mode(mode));
+        }
+        if let Some(atime) = update.atime {
+            object_metadata.insert(ATIME_METADATA_KEY.to_owned(), format_mtime(atime).0);
+        }
+        // Without the encryption of the object, the copy would get the default encryption of the bucket.
+        let mut params = CopyObjectParams::new()
+            .object_metadata(object_metadata)
+            .server_side_encryption(sse_type)
+            .ssekms_key_id(sse_kms_key_id);
+        if let Some(storage_class) = storage_class {
+            params = params.storage_class(storage_class);
+        }
+        // Replacing the user-defined metadata would also reset the system-defined metadata of the object.
+        if let Some(content_type) = content_type {
+            params = params.content_type(content_type);
+        }
+        if let Some(content_encoding) = content_encoding {
+            params = params.content_encoding(content_encoding);
+        }
+        if let Some(cache_control) = cache_control {
+            params = params.cache_control(cache_control);
+        }
+        if let Some(content_disposition) = content_disposition {
+            params = params.content_disposition(content_disposition);
+        }
+        match self.client.copy_object(bucket, &key, bucket, &key, &params).await {
+            Ok(_) => {}
+            Err(ObjectClientError::ServiceError(CopyObjectError::NotFound)) => {
+                return Err(err!(libc::ENOENT, "object {} no longer exists", location));
+            }
+            Err(e) => return Err(err!(libc::EIO, source:e, "CopyObject failed for {}", location)),
+        }
+        debug!(%location, ?mtime, mode = ?update.mode, atime = ?update.atime, "updated metadata of object");
+
+        // Copies of objects uploaded in parts have a new ETag.
+        let etag = head("HeadObject failed after CopyObject").await?.etag;
+        Ok(self
+            .metablock
+            .update_remote_file(lookup.ino(), etag, mtime, update.mode)
+            .await?)
+    }
+
     pub async fn rmdir(&self, parent_ino: InodeNo, name: &OsStr) -> Result<(), Error> {
         self.metablock.rmdir(parent_ino, name).await?;
         Ok(())
@@ -726,18 +2296,7 @@ where
     }
 
     pub async fn statfs(&self, _ino: InodeNo) -> Result<StatFs, Error> {
-        const FREE_BLOCKS: u64 = u64::MAX / 1024;
-        const FREE_INODES: u64 = u64::MAX / 1024;
-
-        let reply = StatFs {
-            free_blocks: FREE_BLOCKS,
-            available_blocks: FREE_BLOCKS,
-            free_inodes: FREE_INODES,
-            total_blocks: FREE_BLOCKS,
-            total_inodes: FREE_INODES,
-            ..Default::default()
-        };
-        Ok(reply)
+        self.config.statfs_capacity.statfs().await
     }
 
     pub async fn rename(
@@ -786,9 +2345,9 @@ where
 mod tests {
     use super::*;
 
+    use crate::Snapshot;
     use crate::prefetch::Prefetcher;
-    use crate::s3::{Bucket, S3Path};
-    use crate::{Superblock, SuperblockConfig};
+    use crate::s3::{Bucket, KeyDelimiter};
 
     use fuser::FileType;
     use futures::executor::ThreadPool;
@@ -817,15 +2376,15 @@ mod tests {
             server_side_encryption,
             ..Default::default()
         };
-        let superblock = Superblock::new(
-            client.clone(),
+        let mut fs = S3Filesystem::new(
+            client,
+            prefetcher_builder,
+            pool,
+            runtime,
             S3Path::new(bucket, Default::default()),
-            SuperblockConfig {
-                cache_config: fs_config.cache_config.clone(),
-                s3_personality: fs_config.s3_personality,
-            },
+            Default::default(),
+            fs_config,
         );
-        let mut fs = S3Filesystem::new(client, prefetcher_builder, pool, runtime, superblock, fs_config);
 
         // Lookup inode of the dir1 directory
         let entry = fs.lookup(FUSE_ROOT_INODE, "dir1".as_ref()).await.unwrap();
@@ -1080,14 +2639,281 @@ mod tests {
             incremental_upload,
             ..Default::default()
         };
-        let superblock = Superblock::new(
+        S3Filesystem::new(
+            client,
+            prefetcher_builder,
+            pool,
+            runtime,
+            S3Path::new(bucket, Default::default()),
+            Default::default(),
+            fs_config,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reads_version() {
+        let bucket = Bucket::new("bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024)
+                .enable_backpressure(true)
+                .initial_read_window_size(1024 * 1024)
+                .build(),
+        );
+        let version = |body: &[u8], version_id: &str, seconds| {
+            let mut object = MockObject::from_bytes(body, ETag::from_object_bytes(body));
+            object.set_version_id(Some(version_id.to_owned()));
+            object.set_last_modified(OffsetDateTime::from_unix_timestamp(seconds).unwrap());
+            object
+        };
+        client.add_object("file.txt", version(b"overwritten", "v2", 30));
+        client.add_object_version("file.txt", version(b"original", "v1", 10));
+
+        let s3_path = S3Path::new(bucket, Default::default());
+        let time = OffsetDateTime::from_unix_timestamp(20).unwrap();
+        let snapshot = Snapshot::load(client.as_ref(), &s3_path, time).await.unwrap();
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let prefetcher_builder = Prefetcher::default_builder(client.clone());
+        let fs_config = S3FilesystemConfig::default();
+        let fs = S3Filesystem::new(
+            client,
+            prefetcher_builder,
+            pool,
+            runtime,
+            s3_path,
+            SuperblockConfig {
+                snapshot: Some(snapshot),
+                ..Default::default()
+            },
+            fs_config,
+        );
+
+        let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+        assert_eq!(entry.attr.size, 8);
+        let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+        let data = fs.read(entry.attr.ino, fh, 0, 1024, 0, None).await.unwrap();
+        assert_eq!(&data[..], b"original", "the version of the snapshot is read");
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_version_directories_read_versions() {
+        let bucket = Bucket::new("bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024)
+                .enable_backpressure(true)
+                .initial_read_window_size(1024 * 1024)
+                .build(),
+        );
+        let version = |body: &[u8], version_id: &str| {
+            let mut object = MockObject::from_bytes(body, ETag::from_object_bytes(body));
+            object.set_version_id(Some(version_id.to_owned()));
+            object
+        };
+        client.add_object("dir/file.txt", version(b"overwritten", "v2"));
+        client.add_object_version("dir/file.txt", version(b"original", "v1"));
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let prefetcher_builder = Prefetcher::default_builder(client.clone());
+        let fs_config = S3FilesystemConfig {
+            version_directories: true,
+            ..Default::default()
+        };
+        let fs = S3Filesystem::new(
+            client,
+            prefetcher_builder,
+            pool,
+            runtime,
+            S3Path::new(bucket, Default::default()),
+            Default::default(),
+            fs_config,
+        );
+
+        let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let versions = fs.lookup(dir.attr.ino, "file.txt.versions".as_ref()).await.unwrap();
+        let entry = fs.lookup(versions.attr.ino, "v1".as_ref()).await.unwrap();
+        assert_eq!(entry.attr.size, 8);
+        let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+        let data = fs.read(entry.attr.ino, fh, 0, 1024, 0, None).await.unwrap();
+        assert_eq!(&data[..], b"original", "the version is read from the object");
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+
+        let err = fs
+            .open(entry.attr.ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
+            .await
+            .expect_err("versions can not be overwritten");
+        assert_eq!(err.to_errno(), libc::EPERM);
+    }
+
+    #[tokio::test]
+    async fn test_key_delimiter() {
+        let bucket = Bucket::new("bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024)
+                .enable_backpressure(true)
+                .initial_read_window_size(1024 * 1024)
+                .build(),
+        );
+        client.add_object("dir|file.txt", MockObject::from_bytes(b"hello", ETag::for_tests()));
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let prefetcher_builder = Prefetcher::default_builder(client.clone());
+        let fs_config = S3FilesystemConfig {
+            key_delimiter: KeyDelimiter::new("|").unwrap(),
+            ..Default::default()
+        };
+        let fs = S3Filesystem::new(
+            client.clone(),
+            prefetcher_builder,
+            pool,
+            runtime,
+            S3Path::new(bucket, Default::default()),
+            Default::default(),
+            fs_config,
+        );
+
+        let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entry = fs.lookup(dir.attr.ino, "file.txt".as_ref()).await.unwrap();
+        let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+        let data = fs.read(entry.attr.ino, fh, 0, 1024, 0, None).await.unwrap();
+        assert_eq!(&data[..], b"hello");
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+
+        let entry = fs
+            .mknod(dir.attr.ino, "new.txt".as_ref(), libc::S_IFREG | libc::S_IRWXU, 0, 0)
+            .await
+            .unwrap();
+        let fh = fs.open(entry.attr.ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+        fs.write(entry.attr.ino, fh, 0, b"world", 0, 0, None).await.unwrap();
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+        assert!(
+            client.contains_key("dir|new.txt"),
+            "the object is written with the delimiter"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_shadowed_file() {
+        let bucket = Bucket::new("bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024)
+                .enable_backpressure(true)
+                .initial_read_window_size(1024 * 1024)
+                .build(),
+        );
+        client.add_object("foo", MockObject::from_bytes(b"hello", ETag::for_tests()));
+        client.add_object("foo/bar", MockObject::from_bytes(b"world", ETag::for_tests()));
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let prefetcher_builder = Prefetcher::default_builder(client.clone());
+        let fs_config = S3FilesystemConfig::default();
+        let fs = S3Filesystem::new(
+            client.clone(),
+            prefetcher_builder,
+            pool,
+            runtime,
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                shadowing: ShadowingPolicy::SuffixFile,
+                ..Default::default()
+            },
+            fs_config,
+        );
+
+        let entry = fs.lookup(FUSE_ROOT_INODE, "foo.shadowed".as_ref()).await.unwrap();
+        let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+        let data = fs.read(entry.attr.ino, fh, 0, 1024, 0, None).await.unwrap();
+        assert_eq!(&data[..], b"hello");
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+
+        let err = fs
+            .open(entry.attr.ino, OpenFlags::O_WRONLY, 0)
+            .await
+            .expect_err("shadowed files can't be overwritten");
+        assert_eq!(err.to_errno(), libc::EPERM);
+    }
+
+    #[tokio::test]
+    async fn test_read_decompressed_files() {
+        let bucket = Bucket::new("bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024)
+                .enable_backpressure(true)
+                .initial_read_window_size(1024 * 1024)
+                .build(),
+        );
+        let content: Vec<u8> = (0..300 * 1024).map(|i| b"hello world\n"[i % 12]).collect();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &content).unwrap();
+        client.add_object("data.csv.gz", MockObject::from(encoder.finish().unwrap()));
+        // Concatenated frames are decoded, but the header only holds the size of the first one.
+        let mut frames = zstd::bulk::compress(&content, 3).unwrap();
+        frames.extend(zstd::bulk::compress(b"trailing frame", 3).unwrap());
+        client.add_object(
+            "data.jsonl.zst",
+            MockObject::from(zstd::bulk::compress(&content, 3).unwrap()),
+        );
+        client.add_object("frames.jsonl.zst", MockObject::from(frames));
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let prefetcher_builder = Prefetcher::default_builder(client.clone());
+        let fs_config = S3FilesystemConfig::default();
+        let fs = S3Filesystem::new(
             client.clone(),
+            prefetcher_builder,
+            pool,
+            runtime,
             S3Path::new(bucket, Default::default()),
             SuperblockConfig {
-                cache_config: fs_config.cache_config.clone(),
-                s3_personality: fs_config.s3_personality,
+                decompress_files: true,
+                ..Default::default()
             },
+            fs_config,
         );
-        S3Filesystem::new(client, prefetcher_builder, pool, runtime, superblock, fs_config)
+
+        let read_all = async |name: &str| {
+            let entry = fs.lookup(FUSE_ROOT_INODE, name.as_ref()).await.unwrap();
+            let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+            let mut data = Vec::new();
+            let result = loop {
+                match fs.read(entry.attr.ino, fh, data.len() as i64, 64 * 1024, 0, None).await {
+                    Ok(bytes) if bytes.is_empty() => break Ok(data),
+                    Ok(bytes) => data.extend_from_slice(&bytes),
+                    Err(e) => break Err(e),
+                }
+            };
+            fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+            (entry, result)
+        };
+
+        for name in ["data.csv", "data.jsonl"] {
+            let (entry, data) = read_all(name).await;
+            assert_eq!(entry.attr.size, content.len() as u64);
+            assert_eq!(data.unwrap(), content, "{name} should be decompressed");
+
+            let err = fs
+                .open(entry.attr.ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
+                .await
+                .expect_err("decompressed files can't be overwritten");
+            assert_eq!(err.to_errno(), libc::EPERM);
+        }
+
+        let (_, data) = read_all("frames.jsonl").await;
+        let err = data.expect_err("the frames decode to more than the reported size");
+        assert_eq!(err.to_errno(), libc::EIO);
     }
 }
@@ -0,0 +1,107 @@
+//! Handling of the objects in the archived storage classes, GLACIER and DEEP_ARCHIVE.
+//!
+//! These objects can't be read with GetObject until they are restored. By default they are shown with no permissions,
+//! so that opening them fails with `EACCES`, and [ArchivedObjectsPolicy] can instead hide them, or show them with
+//! their usual permissions and fail to read them with `ENODATA`, optionally along with their restore status in the
+//! [RESTORE_STATUS_XATTR] extended attribute.
+
+use std::time::SystemTime;
+
+use mountpoint_s3_client::types::RestoreStatus;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Name of the extended attribute holding the restore status of the object of a file in an archived storage class,
+/// see [ArchivedObjectsPolicy::RestoreStatus]. It is `archived` until a restore is requested, then `restoring`, and
+/// then `restored until` followed by the time the restored copy expires, in RFC 3339 format.
+pub const RESTORE_STATUS_XATTR: &str = "user.s3.restore_status";
+
+/// How to show the objects in archived storage classes that haven't been restored, see
+/// [crate::SuperblockConfig::archived_objects].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchivedObjectsPolicy {
+    /// Show the objects with no permissions, so that opening them fails with `EACCES`.
+    #[default]
+    Unreadable,
+    /// Hide the objects from listings and lookups, as if they didn't exist.
+    Hidden,
+    /// Show the objects with their usual permissions, and fail to open them for reading with `ENODATA`.
+    FailReads,
+    /// Like [Self::FailReads], and also expose the restore status of the objects as the [RESTORE_STATUS_XATTR]
+    /// extended attribute.
+    RestoreStatus,
+}
+
+impl ArchivedObjectsPolicy {
+    /// Whether the objects are shown with their usual permissions, so that opening them reaches the file system and
+    /// fails with `ENODATA`.
+    pub(crate) fn fails_reads(&self) -> bool {
+        matches!(self, Self::FailReads | Self::RestoreStatus)
+    }
+}
+
+/// Whether an object is in an archived storage class and has not been restored, or its restored copy expired.
+pub(crate) fn is_archived(storage_class: Option<&str>, restore_status: Option<RestoreStatus>) -> bool {
+    match storage_class {
+        Some("GLACIER") | Some("DEEP_ARCHIVE") => {
+            !matches!(restore_status, Some(RestoreStatus::Restored { expiry }) if expiry > SystemTime::now())
+        }
+        _ => false,
+    }
+}
+
+/// The value of the [RESTORE_STATUS_XATTR] extended attribute of an object, if it is in an archived storage class.
+pub(crate) fn restore_status_value(
+    storage_class: Option<&str>,
+    restore_status: Option<RestoreStatus>,
+) -> Option<String> {
+    if !matches!(storage_class, Some("GLACIER") | Some("DEEP_ARCHIVE")) {
+        return None;
+    }
+    let value = match restore_status {
+        Some(RestoreStatus::InProgress) => "restoring".to_owned(),
+        Some(RestoreStatus::Restored { expiry }) if expiry > SystemTime::now() => {
+            let expiry = OffsetDateTime::from(expiry)
+                .format(&Rfc3339)
+                .expect("formatting a system time should succeed");
+            format!("restored until {expiry}")
+        }
+        _ => "archived".to_owned(),
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use test_case::test_case;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test_case(Some("GLACIER"), None, true; "glacier")]
+    #[test_case(Some("DEEP_ARCHIVE"), Some(RestoreStatus::InProgress), true; "restoring")]
+    #[test_case(Some("GLACIER"), Some(RestoreStatus::Restored { expiry: SystemTime::now() + HOUR }), false; "restored")]
+    #[test_case(Some("GLACIER"), Some(RestoreStatus::Restored { expiry: SystemTime::now() - HOUR }), true; "expired")]
+    #[test_case(Some("GLACIER_IR"), None, false; "instant retrieval")]
+    #[test_case(None, None, false; "unknown storage class")]
+    fn test_is_archived(storage_class: Option<&str>, restore_status: Option<RestoreStatus>, expected: bool) {
+        assert_eq!(is_archived(storage_class, restore_status), expected);
+    }
+
+    #[test]
+    fn test_restore_status_value() {
+        assert_eq!(restore_status_value(Some("STANDARD"), None), None);
+        assert_eq!(restore_status_value(Some("GLACIER"), None).as_deref(), Some("archived"));
+        assert_eq!(
+            restore_status_value(Some("DEEP_ARCHIVE"), Some(RestoreStatus::InProgress)).as_deref(),
+            Some("restoring")
+        );
+        let expiry = SystemTime::UNIX_EPOCH + Duration::from_secs(4_000_000_000);
+        assert_eq!(
+            restore_status_value(Some("GLACIER"), Some(RestoreStatus::Restored { expiry })).as_deref(),
+            Some("restored until 2096-10-02T07:06:40Z")
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Access times of files tracked in memory, and optionally kept in the `x-amz-meta-atime` user-defined metadata of
+//! their objects.
+//!
+//! S3 doesn't record when objects are read, so the access time of a file is otherwise the time its object was last
+//! modified. With [crate::S3FilesystemConfig::atime], the file system records the time of the last read of each file,
+//! by inode number, and reports it in place of the access time of its object when it is more recent. With
+//! [crate::S3FilesystemConfig::atime_flush_interval], the access times are also stored in the [ATIME_METADATA_KEY]
+//! metadata of the objects, as a number of seconds since the Unix epoch, which is the convention of s3fs.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+
+use crate::metablock::InodeNo;
+use crate::sync::Mutex;
+
+/// Key of the user-defined metadata holding the time the file an object represents was last read, see
+/// [crate::S3FilesystemConfig::atime_flush_interval].
+pub const ATIME_METADATA_KEY: &str = "atime";
+
+/// The access times of the files read through the file system, see [crate::S3FilesystemConfig::atime].
+#[derive(Debug)]
+pub(super) struct AccessTimes {
+    flush_interval: Option<Duration>,
+    times: Mutex<HashMap<InodeNo, AccessTime>>,
+}
+
+#[derive(Debug)]
+struct AccessTime {
+    atime: OffsetDateTime,
+    /// Whether the access time changed since it was last stored in the metadata of the object.
+    dirty: bool,
+    /// When the access time was last stored in the metadata of the object, if ever.
+    flushed_at: Option<Instant>,
+}
+
+impl AccessTimes {
+    pub fn new(flush_interval: Option<Duration>) -> Self {
+        Self {
+            flush_interval,
+            times: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that the file with the given inode number was read at `atime`.
+    pub fn record(&self, ino: InodeNo, atime: OffsetDateTime) {
+        let mut times = self.times.lock().unwrap();
+        let time = times.entry(ino).or_insert(AccessTime {
+            atime,
+            dirty: true,
+            flushed_at: None,
+        });
+        if atime > time.atime {
+            time.atime = atime;
+            time.dirty = true;
+        }
+    }
+
+    /// The time the file with the given inode number was last read, if it was read through the file system.
+    pub fn get(&self, ino: InodeNo) -> Option<OffsetDateTime> {
+        self.times.lock().unwrap().get(&ino).map(|time| time.atime)
+    }
+
+    /// The access time to store in the metadata of the object of the file with the given inode number, if it changed
+    /// since it was last stored and that is longer than the flush interval ago. It is then considered stored, unless
+    /// [Self::flush_failed] is called.
+    pub fn take_flush(&self, ino: InodeNo, now: Instant) -> Option<OffsetDateTime> {
+        let flush_interval = self.flush_interval?;
+        let mut times = self.times.lock().unwrap();
+        let time = times.get_mut(&ino)?;
+        let due = time
+            .flushed_at
+            .is_none_or(|flushed_at| now.saturating_duration_since(flushed_at) >= flush_interval);
+        if !time.dirty || !due {
+            return None;
+        }
+        time.dirty = false;
+        time.flushed_at = Some(now);
+        Some(time.atime)
+    }
+
+    /// Store the access time of the file with the given inode number again on the next flush.
+    pub fn flush_failed(&self, ino: InodeNo) {
+        if let Some(time) = self.times.lock().unwrap().get_mut(&ino) {
+            time.dirty = true;
+            time.flushed_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let access_times = AccessTimes::new(None);
+        let time = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(access_times.get(2), None);
+        access_times.record(2, time);
+        access_times.record(2, time - Duration::from_secs(1));
+        assert_eq!(access_times.get(2), Some(time), "access times only move forward");
+        assert_eq!(
+            access_times.take_flush(2, Instant::now()),
+            None,
+            "access times are not flushed"
+        );
+    }
+
+    #[test]
+    fn test_take_flush() {
+        let interval = Duration::from_secs(60);
+        let access_times = AccessTimes::new(Some(interval));
+        let time = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let now = Instant::now();
+        assert_eq!(access_times.take_flush(2, now), None);
+
+        access_times.record(2, time);
+        assert_eq!(access_times.take_flush(2, now), Some(time));
+        assert_eq!(
+            access_times.take_flush(2, now + interval),
+            None,
+            "unchanged since flushed"
+        );
+
+        let later = time + Duration::from_secs(10);
+        access_times.record(2, later);
+        assert_eq!(access_times.take_flush(2, now + interval / 2), None, "flushed recently");
+        assert_eq!(access_times.take_flush(2, now + interval), Some(later));
+
+        access_times.record(2, later + Duration::from_secs(10));
+        access_times.flush_failed(2);
+        assert_eq!(
+            access_times.take_flush(2, now + interval),
+            Some(later + Duration::from_secs(10))
+        );
+    }
+}
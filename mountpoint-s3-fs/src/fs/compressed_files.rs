@@ -0,0 +1,111 @@
+//! Serving compressed objects as the files of their decompressed content.
+//!
+//! Many analytics tools can't read compressed input, or pick how to read a file from its extension. With
+//! [crate::SuperblockConfig::decompress_files], the objects whose key ends with the extension of a [Compression], such
+//! as `data.csv.gz`, are listed as read-only files named without it, `data.csv`, which report the size of their
+//! decompressed content and are decompressed as they are read. These files can be read and removed, which deletes
+//! their object, but not written or renamed. The objects can still be looked up with their own name.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Compression of the objects served decompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// A gzip stream, with the `.gz` extension, whose size modulo 4 GiB ends the stream.
+    Gzip,
+    /// Zstandard frames, with the `.zst` extension, whose size is recorded in the header of the first frame.
+    Zstd,
+}
+
+impl Compression {
+    /// The compressions served decompressed, in the order their extensions are looked up.
+    pub const ALL: [Self; 2] = [Self::Gzip, Self::Zstd];
+
+    /// Extension of the keys of the objects compressed this way.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => ".gz",
+            Self::Zstd => ".zst",
+        }
+    }
+
+    /// Name of the compression, in logs and metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// The name of the decompressed content of a file named with the extension of a compression.
+    pub(crate) fn strip_extension(name: &str) -> Option<(&str, Self)> {
+        Self::ALL.into_iter().find_map(|compression| {
+            let file_name = name.strip_suffix(compression.extension())?;
+            (!file_name.is_empty()).then_some((file_name, compression))
+        })
+    }
+
+    /// Size of the smallest object compressed this way: a gzip stream with a 10-byte header, an empty deflate block
+    /// and an 8-byte trailer, or a zstd frame with a 4-byte magic number, a 2-byte header and an empty block.
+    pub(crate) fn min_encoded_size(self) -> usize {
+        match self {
+            Self::Gzip => 20,
+            Self::Zstd => 9,
+        }
+    }
+
+    /// Range of an object of `encoded_size` bytes holding the size of its decompressed content.
+    pub(crate) fn size_range(self, encoded_size: u64) -> Range<u64> {
+        match self {
+            Self::Gzip => encoded_size.saturating_sub(4)..encoded_size,
+            // The frame header is at most 18 bytes long.
+            Self::Zstd => 0..encoded_size.min(18),
+        }
+    }
+
+    /// Size of the decompressed content, read from the [Self::size_range] of its object.
+    pub(crate) fn decoded_size(self, bytes: &[u8]) -> Option<u64> {
+        match self {
+            Self::Gzip => Some(u32::from_le_bytes(bytes.try_into().ok()?) as u64),
+            Self::Zstd => zstd::zstd_safe::get_frame_content_size(bytes).ok().flatten(),
+        }
+    }
+}
+
+/// Key of the object of a file shown with the name of its decompressed content at the given key.
+pub(crate) fn compressed_file_key(key: &str, decompressed: Option<Compression>) -> Cow<'_, str> {
+    match decompressed {
+        Some(compression) => Cow::Owned(format!("{key}{}", compression.extension())),
+        None => Cow::Borrowed(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("data.csv.gz", Some(("data.csv", Compression::Gzip)); "gzip")]
+    #[test_case("data.jsonl.zst", Some(("data.jsonl", Compression::Zstd)); "zstd")]
+    #[test_case(".gz", None; "no file name")]
+    #[test_case("data.csv", None; "uncompressed")]
+    #[test_case("data.tgz", None; "other extension")]
+    fn test_strip_extension(name: &str, expected: Option<(&str, Compression)>) {
+        assert_eq!(Compression::strip_extension(name), expected);
+    }
+
+    #[test]
+    fn test_decoded_size() {
+        let gzip_trailer = 123456u32.to_le_bytes();
+        assert_eq!(Compression::Gzip.decoded_size(&gzip_trailer), Some(123456));
+        assert_eq!(Compression::Gzip.decoded_size(&gzip_trailer[..3]), None);
+
+        let content = vec![b'a'; 100_000];
+        let frame = zstd::bulk::compress(&content, 3).unwrap();
+        let range = Compression::Zstd.size_range(frame.len() as u64);
+        let header = &frame[range.start as usize..range.end as usize];
+        assert_eq!(Compression::Zstd.decoded_size(header), Some(100_000));
+        assert_eq!(Compression::Zstd.decoded_size(b"not a zstd frame"), None);
+    }
+}
@@ -1,13 +1,19 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
+use mountpoint_s3_client::types::{ChecksumAlgorithm, ObjectTags};
 use nix::unistd::{getgid, getuid};
 
 use crate::mem_limiter::MINIMUM_MEM_LIMIT;
-use crate::metablock::WriteMode;
+use crate::metablock::{InodeKind, WriteMode};
 use crate::prefetch::PrefetcherConfig;
-use crate::s3::S3Personality;
+use crate::s3::{KeyDelimiter, KeyMapping, S3Personality};
+use crate::upload::{ContentTypes, WriteThroughCache};
 
-use super::{ServerSideEncryption, TimeToLive};
+use super::{
+    ArchivedObjectsPolicy, MetadataTtlRule, PinningCache, PrefixOverrides, ServerSideEncryption, StatfsCapacity,
+    StorageClassRules, TimeToLive,
+};
 
 #[derive(Debug)]
 pub struct S3FilesystemConfig {
@@ -31,14 +37,119 @@ pub struct S3FilesystemConfig {
     pub allow_rename: bool,
     /// Enable incremental uploads
     pub incremental_upload: bool,
+    /// Allow appending to existing files without incremental uploads, by replacing their object with a multipart
+    /// upload whose first parts are copied from the existing object, followed by the appended data. Along with
+    /// [Self::allow_overwrite], writes can also start before the end of the file to overwrite part of it, in which case
+    /// the content after them is copied as well
+    pub append_by_copy: bool,
+    /// Stage the writes to new and overwritten files in spool files in the given directory rather than streaming them
+    /// to S3, so that they can be made at any offset. Each file is uploaded at once when it is closed or synchronized,
+    /// and can be read back from its write handle, or from new read handles, until then
+    pub write_staging_dir: Option<PathBuf>,
+    /// The maximum total size of the spool files in [Self::write_staging_dir]. Writes that would exceed it fail with
+    /// `ENOSPC`
+    pub write_staging_max_size: Option<u64>,
+    /// Defer the upload of the files whose writes are staged in [Self::write_staging_dir] from when they are closed to
+    /// when their handle is released, so that closing them doesn't wait for the upload. Their spool files are
+    /// recorded once they are closed, so that the uploads not completed when Mountpoint exits are completed when it is
+    /// next mounted with the same staging directory
+    pub defer_staged_uploads: bool,
+    /// Let the kernel cache writes in its page cache and write them back in larger batches. Only the writes to files
+    /// staged in [Self::write_staging_dir] are cached, since the kernel writes back pages in any order, so the handles
+    /// of the other uploads are opened with direct I/O. The kernel keeps the size and times of files itself while
+    /// it caches their writes, and sets their times once it writes them back, which is ignored for files already
+    /// uploaded.
+    pub writeback_cache: bool,
+    /// Allow writes to continue after `fsync` on files being created or overwritten without incremental uploads.
+    /// `fsync` completes their upload as usual, and the following writes append to the object just uploaded by
+    /// copying it, as with [Self::append_by_copy]
+    pub write_after_fsync: bool,
+    /// Only replace the object of an existing file overwritten after being opened with `O_TRUNC` if it wasn't changed
+    /// since, by capturing its ETag when the file is opened and completing the upload with an `If-Match`
+    /// precondition. Closing or synchronizing the file fails with `ESTALE` if another writer changed the object in the
+    /// meantime, leaving that writer's object in place. Doesn't apply to incremental uploads
+    pub conditional_overwrite: bool,
+    /// Largest size files can be written, copied or truncated to, in bytes. Writes that would make a file larger fail
+    /// with `EFBIG`, without aborting its upload. Files can't be larger than the largest object their upload supports
+    /// either, which depends on the write part size of the client.
+    pub max_file_size: Option<u64>,
+    /// Largest number of bytes that can be written to files through the file system, by writes and server-side
+    /// copies, until it is unmounted. Writes beyond it fail with `EDQUOT`, without aborting the upload of the file.
+    pub max_write_bytes: Option<u64>,
+    /// Maximum number of uploads handing data to S3 at once. Uploads take turns before each write, in the order they
+    /// started waiting, so that a large upload doesn't delay the uploads of small files written at the same time.
+    /// Doesn't apply to incremental uploads
+    pub max_concurrent_uploads: Option<usize>,
+    /// Maximum number of parts of a single upload sent to S3 at once. Only uploads split into parts by the client send
+    /// several parts at once
+    pub max_concurrent_parts_per_upload: Option<u32>,
     /// Storage class to be used for new object uploads
     pub storage_class: Option<String>,
+    /// Rules choosing the storage class of new objects by their key, in place of [Self::storage_class]. The storage
+    /// class overridden under a prefix with [Self::prefix_overrides] takes precedence over them.
+    pub storage_class_rules: StorageClassRules,
+    /// Options overridden for the files and directories under prefixes of the mount, see [PrefixOverrides]. The file
+    /// system applies the overrides of the read window, data cache and storage class. The superblock should be
+    /// configured with the same ones, see [crate::SuperblockConfig::prefix_overrides], and the metadata TTLs
+    /// overridden with [PrefixOverrides::ttl_rules].
+    pub prefix_overrides: PrefixOverrides,
     /// S3 personality (for different S3 semantics)
     pub s3_personality: S3Personality,
+    /// Allow creating symbolic links, stored as objects holding their target in their
+    /// [crate::fs::SYMLINK_TARGET_METADATA_KEY] user-defined metadata. The superblock should be configured to report
+    /// them as symlinks with [crate::SuperblockConfig::symlinks].
+    pub symlinks: bool,
+    /// Serve the objects stored with `Content-Encoding: gzip` decoded, as browsers and S3 website endpoints do. The
+    /// superblock should be configured to report the size of their decoded content with
+    /// [crate::SuperblockConfig::decode_content_encoding].
+    pub decode_content_encoding: bool,
+    /// Keep the modification times of files in the [crate::fs::MTIME_METADATA_KEY] user-defined metadata of their
+    /// objects, so that setting them copies the objects onto themselves with the new metadata. The superblock should
+    /// be configured to read them back with [crate::SuperblockConfig::mtime_metadata].
+    pub mtime_metadata: bool,
+    /// Keep the permissions of files in the [crate::fs::MODE_METADATA_KEY] user-defined metadata of their objects, set
+    /// from the mode files are created with and by `chmod`, which copies the objects onto themselves with the new
+    /// metadata. The superblock should be configured to read them back with [crate::SuperblockConfig::mode_metadata].
+    pub mode_metadata: bool,
+    /// Report the time each file was last read through the file system as its access time, when more recent than the
+    /// access time of its object. The times are kept in memory by inode number, so they are kept across lookups only
+    /// if the superblock keeps the numbers of keys stable, see [crate::SuperblockConfig::inode_table].
+    pub atime: bool,
+    /// Store the access times tracked with [Self::atime] in the [crate::fs::ATIME_METADATA_KEY] user-defined metadata
+    /// of the objects, at most once per interval for each file, once its last handle is closed. The object is copied
+    /// onto itself, which changes its ETag and its last modified time, so that [Self::mtime_metadata] should be
+    /// enabled to keep its modification time. The superblock should report the stored times, see
+    /// [crate::SuperblockConfig::atime_metadata].
+    pub atime_flush_interval: Option<Duration>,
+    /// Keep the pages the kernel cached for a file when it is opened again for reading, if its object still has the
+    /// ETag and modification time it had when the file was last opened. These are only revalidated on open when the
+    /// superblock doesn't serve lookups from its metadata cache, so that with a longer metadata TTL, the kernel may
+    /// serve the content of an object replaced within the TTL.
+    pub keep_page_cache: bool,
+    /// Read the files in the synthetic `<name>.versions` directories from the version they name of the object they are
+    /// in the directory of, see [crate::fs::VERSIONS_DIR_SUFFIX]. The superblock should be configured to serve these
+    /// directories with [crate::SuperblockConfig::version_directories].
+    pub version_directories: bool,
+    /// Delimiter of the components of keys, which the paths of files are translated to in their requests. The
+    /// superblock should be configured to build the directory hierarchy with the same
+    /// [crate::SuperblockConfig::key_delimiter].
+    pub key_delimiter: KeyDelimiter,
+    /// Upload an empty marker object, with the key of the directory, when creating a directory, so that it persists
+    /// while it is empty. The superblock should be configured to delete it when removing the directory with
+    /// [crate::SuperblockConfig::directory_markers].
+    pub directory_markers: bool,
+    /// Percent-encode the components of keys that can't be the name of a file, which the paths of files are
+    /// translated back from in their requests. The superblock should be configured to escape them with
+    /// [crate::SuperblockConfig::escape_names].
+    pub escape_names: bool,
+    /// How to show the objects in archived storage classes that haven't been restored. The superblock should be
+    /// configured with the same policy with [crate::SuperblockConfig::archived_objects], which is the one hiding them.
+    pub archived_objects: ArchivedObjectsPolicy,
     /// Server side encryption configuration to be used when creating new S3 object
     pub server_side_encryption: ServerSideEncryption,
-    /// Use additional checksums for uploads
-    pub use_upload_checksums: bool,
+    /// Algorithm of the additional checksums sent with uploads, or `None` to not send any, for S3-compatible stores
+    /// that don't support them. CRC32C checksums of uploads are still verified before they complete.
+    pub upload_checksum_algorithm: Option<ChecksumAlgorithm>,
     /// Memory limit
     pub mem_limit: u64,
     /// Prefetcher configuration
@@ -47,6 +158,63 @@ pub struct S3FilesystemConfig {
     /// This option may also be configured by `UNSTABLE_MOUNTPOINT_MAX_BACKGROUND` environment variable,
     /// but the value specified in the config takes priority.
     pub max_background_fuse_requests: Option<u16>,
+    /// Maximum size in bytes of the reads and writes the kernel sends in a single request, negotiated as the maximum
+    /// write size and the maximum number of pages of requests of the FUSE connection. The kernel caps the number of
+    /// pages to its own limit, 256 pages by default. Default is the largest size the FUSE session can receive, 16 MiB.
+    pub max_fuse_request_size: Option<u32>,
+    /// Expose the prefetching statistics of the file handles open for reading a file in its
+    /// [crate::fs::PREFETCH_STATS_XATTR] extended attribute. When disabled, extended attributes
+    /// are not supported at all, which saves the kernel from looking them up on every write.
+    pub prefetch_stats_xattr: bool,
+    /// Expire the cached metadata of a file, or of a directory and everything cached under it, when the
+    /// [crate::fs::INVALIDATE_XATTR] extended attribute is set on it, so that it is looked up again in S3.
+    pub invalidate_xattr: bool,
+    /// Open a file with direct I/O, bypassing the page cache of the kernel and the data cache as when it is opened with
+    /// `O_DIRECT`, once the [crate::fs::DIRECT_IO_XATTR] extended attribute is set to `1` on it or on a directory above
+    /// it, until it is set to `0` or removed. The choice is kept in memory and only applies to the later opens.
+    pub direct_io_xattr: bool,
+    /// Expose the user-defined metadata of objects as extended attributes named with
+    /// [crate::fs::USER_METADATA_XATTR_PREFIX], and allow setting them on files being created to upload them with
+    /// the object. Getting or listing them issues a HeadObject request.
+    pub user_metadata_xattrs: bool,
+    /// Expose the ETag, version ID and storage class of the objects of files as the read-only
+    /// [crate::fs::ETAG_XATTR], [crate::fs::VERSION_ID_XATTR] and [crate::fs::STORAGE_CLASS_XATTR] extended
+    /// attributes.
+    pub object_info_xattrs: bool,
+    /// Expose URLs to get the objects of files, pre-signed with the credentials of the mount to be valid for the given
+    /// duration, as the read-only [crate::fs::PRESIGNED_URL_XATTR] extended attribute, so that other services can read
+    /// them without credentials. A new URL is signed on each lookup, without any request to S3. The duration must be
+    /// at most [mountpoint_s3_client::types::MAX_PRESIGNED_URL_EXPIRATION].
+    pub presigned_url_xattr: Option<Duration>,
+    /// Expose the total size of the objects under each directory as the read-only
+    /// [crate::fs::RECURSIVE_SIZE_XATTR] extended attribute, aggregated from a recursive listing of its prefix with
+    /// ListObjectsV2 rather than by looking up every file under it. Sizes are reused for a few seconds.
+    pub recursive_size_xattr: bool,
+    /// Expose the tags of the objects of files as extended attributes named with [crate::fs::TAG_XATTR_PREFIX], and
+    /// update the tags of objects when they are set or removed. The tags of a file are fetched with GetObjectTagging
+    /// the first time they are accessed, and cached for as long as its metadata. The tags of files being created can
+    /// also be set, until their upload starts, and their objects are created with them.
+    pub tag_xattrs: bool,
+    /// Tags of the objects created through the mount by new files, copies and overwrites, which lifecycle rules and
+    /// cost allocation can then select them by. Objects rewritten by appends and truncation keep no tags.
+    pub object_tags: ObjectTags,
+    /// Set the `Content-Type` of the objects created through the mount by new files, copies and overwrites, chosen
+    /// from the extension of their key, or recognized from their first bytes, see [crate::upload::ContentTypes]. When
+    /// unset, S3 stores them as `binary/octet-stream`. Objects rewritten by appends and truncation keep theirs.
+    pub content_types: Option<ContentTypes>,
+    /// Cache the objects written through the mount are put to once uploaded, so that reading them back doesn't
+    /// fetch them from S3. Only objects uploaded at once, rather than appended to, are cached.
+    pub write_through_cache: Option<WriteThroughCache>,
+    /// Cache the files pinned with the [crate::fs::PIN_XATTR] extended attribute are kept in. When unset, files can't
+    /// be pinned.
+    pub pinning_cache: Option<PinningCache>,
+    /// The capacity and free space reported by `statfs`, for applications that check the free space before writing
+    pub statfs_capacity: StatfsCapacity,
+    /// Serve as the read-only lower layer of an overlayfs mount: directories report a single link, and the
+    /// [crate::fs::OVERLAYFS_XATTR_PREFIXES] extended attributes are never found, without any request to S3, so that
+    /// no directory is opaque and no file is a whiteout. Inode numbers should also be kept stable with
+    /// [crate::SuperblockConfig::inode_table].
+    pub overlayfs_lower: bool,
 }
 
 impl Default for S3FilesystemConfig {
@@ -64,23 +232,70 @@ impl Default for S3FilesystemConfig {
             allow_delete: false,
             allow_overwrite: false,
             incremental_upload: false,
+            append_by_copy: false,
+            write_staging_dir: None,
+            write_staging_max_size: None,
+            defer_staged_uploads: false,
+            writeback_cache: false,
+            write_after_fsync: false,
+            conditional_overwrite: false,
+            max_file_size: None,
+            max_write_bytes: None,
+            max_concurrent_uploads: None,
+            max_concurrent_parts_per_upload: None,
             allow_rename: true,
             storage_class: None,
+            storage_class_rules: Default::default(),
+            prefix_overrides: Default::default(),
             s3_personality: S3Personality::default(),
             server_side_encryption: Default::default(),
-            use_upload_checksums: true,
+            upload_checksum_algorithm: Some(ChecksumAlgorithm::Crc32c),
             mem_limit: MINIMUM_MEM_LIMIT,
             prefetcher_config: Default::default(),
             max_background_fuse_requests: None,
+            max_fuse_request_size: None,
+            prefetch_stats_xattr: false,
+            invalidate_xattr: false,
+            direct_io_xattr: false,
+            user_metadata_xattrs: false,
+            object_info_xattrs: false,
+            presigned_url_xattr: None,
+            recursive_size_xattr: false,
+            tag_xattrs: false,
+            object_tags: ObjectTags::new(),
+            content_types: None,
+            symlinks: false,
+            decode_content_encoding: false,
+            mtime_metadata: false,
+            mode_metadata: false,
+            atime: false,
+            atime_flush_interval: None,
+            keep_page_cache: false,
+            version_directories: false,
+            key_delimiter: Default::default(),
+            directory_markers: false,
+            escape_names: false,
+            archived_objects: Default::default(),
+            write_through_cache: None,
+            pinning_cache: None,
+            overlayfs_lower: false,
+            statfs_capacity: Default::default(),
         }
     }
 }
 
 impl S3FilesystemConfig {
+    /// Translation between the paths of files and the keys of their objects.
+    pub(crate) fn key_mapping(&self) -> KeyMapping {
+        KeyMapping::new(self.key_delimiter, self.escape_names)
+    }
+
     pub fn write_mode(&self) -> WriteMode {
         WriteMode {
             allow_overwrite: self.allow_overwrite,
             incremental_upload: self.incremental_upload,
+            append_by_copy: self.append_by_copy,
+            read_while_writing: self.write_staging_dir.is_some(),
         }
     }
 
@@ -134,6 +349,9 @@ pub struct CacheConfig {
     pub negative_cache_ttl: Duration,
     /// Maximum number of negative entries to cache.
     pub negative_cache_size: usize,
+    /// Metadata TTLs of the files and directories whose keys match patterns, in place of `serve_lookup_from_cache`,
+    /// `file_ttl` and `dir_ttl`. The first rule matching a key applies.
+    pub ttl_rules: Vec<MetadataTtlRule>,
 }
 
 impl Default for CacheConfig {
@@ -164,6 +382,7 @@ impl Default for CacheConfig {
             use_negative_cache: false,
             negative_cache_ttl: file_ttl,
             negative_cache_size,
+            ttl_rules: Vec::new(),
         }
     }
 }
@@ -192,6 +411,42 @@ impl CacheConfig {
         }
     }
 
+    /// Add rules for the metadata TTLs of the keys matching patterns, see [Self::ttl_rules].
+    pub fn with_ttl_rules(self, ttl_rules: Vec<MetadataTtlRule>) -> Self {
+        Self { ttl_rules, ..self }
+    }
+
+    /// How long the metadata of the inode of the given kind and key, relative to the mounted prefix, is cached.
+    pub fn ttl_for(&self, kind: InodeKind, key: &str) -> Duration {
+        let rule_config = self.ttl_rule(key).map(|rule| Self::new(rule.ttl()));
+        let config = rule_config.as_ref().unwrap_or(self);
+        match kind {
+            InodeKind::File => config.file_ttl,
+            InodeKind::Directory => config.dir_ttl,
+        }
+    }
+
+    /// Whether lookups of the file or directory with the given key, relative to the mounted prefix and without a
+    /// trailing `/`, may be served from cached entries.
+    pub fn serve_lookup_from_cache_for(&self, key: &str) -> bool {
+        if self.ttl_rules.is_empty() {
+            return self.serve_lookup_from_cache;
+        }
+        let directory_key = format!("{key}/");
+        match self
+            .ttl_rules
+            .iter()
+            .find(|rule| rule.matches(key) || rule.matches(&directory_key))
+        {
+            Some(rule) => Self::new(rule.ttl()).serve_lookup_from_cache,
+            None => self.serve_lookup_from_cache,
+        }
+    }
+
+    fn ttl_rule(&self, key: &str) -> Option<&MetadataTtlRule> {
+        self.ttl_rules.iter().find(|rule| rule.matches(key))
+    }
+
     pub fn with_negative_metadata_ttl(self, negative_metadata_ttl: TimeToLive) -> Self {
         match negative_metadata_ttl {
             TimeToLive::Minimal => Self {
@@ -0,0 +1,229 @@
+//! Serving the objects stored with `Content-Encoding: gzip` decoded.
+//!
+//! Web pipelines often upload files compressed, with the `Content-Encoding: gzip` header browsers and S3 website
+//! endpoints decode them with, which leaves them unreadable as stored. With
+//! [crate::S3FilesystemConfig::decode_content_encoding], the files of these objects report the size of their decoded
+//! content, see [crate::SuperblockConfig::decode_content_encoding], and their read handles decompress the objects
+//! fetched by the prefetcher as they are read. The files of the compressed objects shown decompressed, see
+//! [crate::SuperblockConfig::decompress_files], are read the same way. Compressed streams can only be decoded from
+//! their start, so reading a file backwards decodes its object again from the start.
+
+use std::fmt::Debug;
+use std::io::Write;
+
+use bytes::Bytes;
+use flate2::write::MultiGzDecoder;
+use mountpoint_s3_client::ObjectClient;
+
+use super::{Compression, Error};
+use crate::prefetch::PrefetchGetObject;
+
+/// Size of the reads of the compressed object from the prefetcher.
+const ENCODED_READ_SIZE: usize = 128 * 1024;
+
+/// Size of the slices of gzip data decoded at once, which bounds the decoded data held beyond a read, as deflate
+/// compresses at most about 1032:1.
+const GZIP_DECODE_SLICE_SIZE: usize = 16 * 1024;
+
+/// Size of the slices of zstd data decoded at once. Zstd compresses repeated bytes far more than deflate, so the slices
+/// are smaller to hold a similar amount of decoded data.
+const ZSTD_DECODE_SLICE_SIZE: usize = 1024;
+
+/// Whether a `Content-Encoding` is gzip, which is the only encoding decoded.
+pub(crate) fn is_gzip_encoding(content_encoding: &str) -> bool {
+    let content_encoding = content_encoding.trim();
+    content_encoding.eq_ignore_ascii_case("gzip") || content_encoding.eq_ignore_ascii_case("x-gzip")
+}
+
+/// A streaming decoder, which writes the decoded content to its inner buffer.
+enum Decoder {
+    Gzip(MultiGzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl Decoder {
+    fn new(compression: Compression) -> std::io::Result<Self> {
+        Ok(match compression {
+            Compression::Gzip => Self::Gzip(MultiGzDecoder::new(Vec::new())),
+            Compression::Zstd => Self::Zstd(zstd::stream::write::Decoder::new(Vec::new())?),
+        })
+    }
+
+    fn compression(&self) -> Compression {
+        match self {
+            Self::Gzip(_) => Compression::Gzip,
+            Self::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    fn slice_size(&self) -> usize {
+        match self {
+            Self::Gzip(_) => GZIP_DECODE_SLICE_SIZE,
+            Self::Zstd(_) => ZSTD_DECODE_SLICE_SIZE,
+        }
+    }
+
+    /// Decode a slice of compressed data, writing all of its decoded content to the buffer.
+    fn decode(&mut self, slice: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Gzip(decoder) => decoder.write_all(slice).and_then(|()| decoder.flush()),
+            Self::Zstd(decoder) => decoder.write_all(slice).and_then(|()| decoder.flush()),
+        }
+    }
+
+    /// Decode the end of the stream. Truncated zstd frames are only detected by the size they decode to.
+    fn finish(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Gzip(decoder) => decoder.try_finish(),
+            Self::Zstd(decoder) => decoder.flush(),
+        }
+    }
+
+    fn buffer(&self) -> &Vec<u8> {
+        match self {
+            Self::Gzip(decoder) => decoder.get_ref(),
+            Self::Zstd(decoder) => decoder.get_ref(),
+        }
+    }
+
+    fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        match self {
+            Self::Gzip(decoder) => decoder.get_mut(),
+            Self::Zstd(decoder) => decoder.get_mut(),
+        }
+    }
+}
+
+impl Debug for Decoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Decoder")
+            .field("compression", &self.compression())
+            .field("buffered", &self.buffer().len())
+            .finish()
+    }
+}
+
+/// The decoding of the object of a read handle, whose reads are served from its decoded content.
+#[derive(Debug)]
+pub struct Decoding {
+    /// The decoder, which writes the decoded content after [Self::decoded_offset] to its inner buffer
+    decoder: Decoder,
+    encoded_size: u64,
+    decoded_size: u64,
+    /// Offset in the object of the next compressed data to fetch
+    encoded_offset: u64,
+    /// Compressed data fetched but not decoded yet
+    pending: Bytes,
+    /// Offset in the decoded content of the start of the buffer of the decoder
+    decoded_offset: u64,
+    /// Set once the whole object is decoded
+    finished: bool,
+}
+
+impl Decoding {
+    /// Decode an object of `encoded_size` bytes compressed with `compression`, expected to decode to `decoded_size`
+    /// bytes.
+    pub fn new(compression: Compression, encoded_size: u64, decoded_size: u64) -> Result<Self, Error> {
+        let decoder = Decoder::new(compression)
+            .map_err(|e| err!(libc::EIO, source:e, "unable to create {} decoder", compression.as_str()))?;
+        Ok(Self {
+            decoder,
+            encoded_size,
+            decoded_size,
+            encoded_offset: 0,
+            pending: Bytes::new(),
+            decoded_offset: 0,
+            finished: false,
+        })
+    }
+
+    /// Read `length` bytes of the decoded content at `offset`, decoding the object fetched with `request` up to them.
+    /// The read reaching the end of the content decodes the rest of the object, and fails if it doesn't decode to the
+    /// expected size.
+    pub async fn read<Client>(
+        &mut self,
+        request: &mut PrefetchGetObject<Client>,
+        offset: u64,
+        length: usize,
+    ) -> Result<Bytes, Error>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        if offset < self.decoded_offset {
+            let compression = self.decoder.compression();
+            metrics::counter!("fs.decoding_restarts", "compression" => compression.as_str()).increment(1);
+            *self = Self::new(compression, self.encoded_size, self.decoded_size)?;
+        }
+        let compression = self.decoder.compression().as_str();
+        let end = offset.saturating_add(length as u64).min(self.decoded_size);
+        while !self.finished && (end == self.decoded_size || self.decoded_end() < end) {
+            if self.pending.is_empty() {
+                if self.encoded_offset >= self.encoded_size {
+                    self.finish()
+                        .map_err(|e| err!(libc::EIO, source:e, "truncated {} stream", compression))?;
+                    break;
+                }
+                let bytes = request
+                    .read(self.encoded_offset, ENCODED_READ_SIZE)
+                    .await?
+                    .into_bytes()
+                    .map_err(|e| err!(libc::EIO, source:e, "integrity error"))?;
+                if bytes.is_empty() {
+                    self.finish()
+                        .map_err(|e| err!(libc::EIO, source:e, "truncated {} stream", compression))?;
+                    break;
+                }
+                self.encoded_offset += bytes.len() as u64;
+                self.pending = bytes;
+            }
+            let slice = self.pending.split_to(self.pending.len().min(self.decoder.slice_size()));
+            self.decoder
+                .decode(&slice)
+                .map_err(|e| err!(libc::EIO, source:e, "invalid {} stream", compression))?;
+            self.discard_before(offset);
+            if self.decoded_end() > self.decoded_size {
+                return Err(err!(
+                    libc::EIO,
+                    "{} stream decodes to more than the {} bytes of its reported size",
+                    compression,
+                    self.decoded_size
+                ));
+            }
+        }
+        if self.finished && self.decoded_end() != self.decoded_size {
+            return Err(err!(
+                libc::EIO,
+                "{} stream decodes to {} bytes instead of its reported size of {} bytes",
+                compression,
+                self.decoded_end(),
+                self.decoded_size
+            ));
+        }
+
+        self.discard_before(offset);
+        let available = end.saturating_sub(self.decoded_offset) as usize;
+        let bytes = Bytes::copy_from_slice(&self.decoder.buffer()[..available]);
+        self.discard_before(end);
+        Ok(bytes)
+    }
+
+    /// Offset in the decoded content of the end of the data decoded so far.
+    fn decoded_end(&self) -> u64 {
+        self.decoded_offset + self.decoder.buffer().len() as u64
+    }
+
+    /// Drop the decoded data before `offset`, which won't be read again.
+    fn discard_before(&mut self, offset: u64) {
+        let buffer = self.decoder.buffer_mut();
+        let discarded = (offset.saturating_sub(self.decoded_offset) as usize).min(buffer.len());
+        buffer.drain(..discarded);
+        self.decoded_offset += discarded as u64;
+    }
+
+    /// Decode the end of the stream, once the whole object was fetched.
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.decoder.finish()?;
+        self.finished = true;
+        Ok(())
+    }
+}
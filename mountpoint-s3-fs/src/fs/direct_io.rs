@@ -0,0 +1,83 @@
+//! Direct I/O chosen for individual files and directories while the file system is mounted.
+//!
+//! Files opened with `O_DIRECT` bypass the page cache of the kernel, which suits latency-sensitive readers that
+//! expect each read to reach S3, but not the streaming readers of the same mount, and applications can't always be
+//! changed to pass the flag. With [crate::S3FilesystemConfig::direct_io_xattr], setting the
+//! [crate::fs::DIRECT_IO_XATTR] extended attribute to `1` on a file makes its later opens bypass the page cache and
+//! the data cache, like opens with `O_DIRECT`, and on a directory, the opens of every file under it. Setting it to `0`
+//! or removing it makes them use the page cache again. The choices are kept in memory, by key, until the file system
+//! is unmounted.
+
+use std::collections::HashSet;
+
+use crate::sync::Mutex;
+
+/// The keys of the files and directories whose files are opened with direct I/O, relative to the mounted prefix and
+/// ending in `/` for directories, see [crate::S3FilesystemConfig::direct_io_xattr].
+#[derive(Debug, Default)]
+pub(super) struct DirectIoKeys {
+    keys: Mutex<HashSet<String>>,
+}
+
+impl DirectIoKeys {
+    /// Open the file with the given key, or the files under the directory with the given key, with direct I/O, or
+    /// with the page cache again. Return whether the key was set before.
+    pub fn set(&self, key: &str, direct_io: bool) -> bool {
+        let mut keys = self.keys.lock().unwrap();
+        if direct_io {
+            !keys.insert(key.to_owned())
+        } else {
+            keys.remove(key)
+        }
+    }
+
+    /// Whether direct I/O was set on the file or directory with the given key itself.
+    pub fn is_set(&self, key: &str) -> bool {
+        self.keys.lock().unwrap().contains(key)
+    }
+
+    /// Whether the file with the given key is opened with direct I/O, set on it or on a directory above it.
+    pub fn applies_to(&self, key: &str) -> bool {
+        let keys = self.keys.lock().unwrap();
+        if keys.is_empty() {
+            return false;
+        }
+        // The root directory has the empty key.
+        keys.contains(key)
+            || keys.contains("")
+            || key
+                .match_indices('/')
+                .any(|(index, _)| keys.contains(&key[..index + 1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applies_to() {
+        let keys = DirectIoKeys::default();
+        assert!(!keys.applies_to("a.txt"));
+
+        assert!(!keys.set("a.txt", true));
+        assert!(keys.set("a.txt", true), "already set");
+        assert!(keys.applies_to("a.txt"));
+        assert!(!keys.applies_to("a.txt.bak"));
+
+        keys.set("dir/sub/", true);
+        assert!(keys.applies_to("dir/sub/b.txt"));
+        assert!(keys.applies_to("dir/sub/deeper/c.txt"));
+        assert!(!keys.applies_to("dir/b.txt"));
+        assert!(!keys.applies_to("dir/subway.txt"));
+        assert!(keys.is_set("dir/sub/"));
+        assert!(!keys.is_set("dir/sub/b.txt"), "only set on the directory");
+
+        assert!(keys.set("dir/sub/", false));
+        assert!(!keys.set("dir/sub/", false), "not set anymore");
+        assert!(!keys.applies_to("dir/sub/b.txt"));
+
+        keys.set("", true);
+        assert!(keys.applies_to("dir/b.txt"), "set on the root directory");
+    }
+}
@@ -3,6 +3,7 @@
 use mountpoint_s3_client::error::{GetObjectError, ObjectClientError};
 use tracing::Level;
 
+use crate::fs::QuotaError;
 use crate::fs::error_metadata::ErrorMetadata;
 use crate::metablock::InodeError;
 use crate::prefetch::PrefetchReadError;
@@ -144,6 +145,15 @@ impl<E: std::error::Error + Send + Sync + 'static> From<PrefetchReadError<E>> fo
     }
 }
 
+impl From<QuotaError> for Error {
+    fn from(err: QuotaError) -> Self {
+        match err {
+            QuotaError::FileTooLarge { .. } => err!(libc::EFBIG, source:err, "file too large"),
+            QuotaError::WriteQuotaExceeded { .. } => err!(libc::EDQUOT, source:err, "write quota exceeded"),
+        }
+    }
+}
+
 /// Errors that can be converted to a raw OS error (errno)
 pub trait ToErrno {
     fn to_errno(&self) -> libc::c_int;
@@ -179,6 +189,9 @@ impl ToErrno for InodeError {
             InodeError::SetAttrNotPermittedOnRemoteInode(_) => libc::EPERM,
             InodeError::StaleInode { .. } => libc::ESTALE,
             InodeError::CannotRenameDirectory(_) => libc::EPERM,
+            InodeError::DirectoryTooLargeToRename { .. } => libc::EXDEV,
+            InodeError::DirectoryTooLargeToDelete { .. } => libc::ENOTEMPTY,
+            InodeError::DirectoryDeleteIncomplete { .. } => libc::EIO,
             InodeError::RenameDestinationExists { .. } => libc::EEXIST,
             InodeError::RenameNotPermittedWhileWriting(_) => libc::EPERM,
             InodeError::RenameNotSupported() => libc::ENOSYS,
@@ -188,7 +201,10 @@ impl ToErrno for InodeError {
             InodeError::OperationNotSupportedOnSyntheticInode { .. } => libc::EIO,
             InodeError::OutOfOrderReadDir { .. } => libc::EBADF,
             InodeError::NoSuchDirHandle { .. } => libc::EINVAL,
+            InodeError::ObjectChangedWhileWriting(_) => libc::ESTALE,
             InodeError::FlexibleRetrievalObjectNotAccessible(_) => libc::EACCES,
+            InodeError::FlexibleRetrievalObjectNotRestored(_) => libc::ENODATA,
+            InodeError::ReadOnlyPrefix(_) => libc::EROFS,
         }
     }
 }
@@ -203,6 +219,11 @@ impl<E: std::error::Error> ToErrno for UploadError<E> {
             UploadError::HeadObjectFailed(_) => libc::EIO,
             UploadError::OutOfOrderWrite { .. } => libc::EINVAL,
             UploadError::ObjectTooBig { .. } => libc::EFBIG,
+            UploadError::MultipartUploadFailed(_) => libc::EIO,
+            UploadError::GetObjectFailed(_) => libc::EIO,
+            UploadError::StagingFailed(_) => libc::EIO,
+            UploadError::StagingDirFull { .. } => libc::ENOSPC,
+            UploadError::ObjectChanged => libc::ESTALE,
         }
     }
 }
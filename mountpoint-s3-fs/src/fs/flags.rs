@@ -80,6 +80,8 @@ libc_flags! {
 
         #[cfg(target_os = "linux")]
         O_DIRECT,
+        #[cfg(target_os = "linux")]
+        O_NOATIME,
 
         // Incomplete list. To be integrated if/when required.
     }
@@ -95,6 +97,16 @@ impl OpenFlags {
     pub fn direct_io(&self) -> bool {
         self.contains(OpenFlags::O_DIRECT)
     }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn no_atime(&self) -> bool {
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn no_atime(&self) -> bool {
+        self.contains(OpenFlags::O_NOATIME)
+    }
 }
 
 /// Flags used in [rename](super::S3Filesystem::rename).
@@ -1,17 +1,22 @@
+use std::ops::Range;
 use std::str::FromStr as _;
 
+use bytes::Bytes;
 use mountpoint_s3_client::ObjectClient;
-use mountpoint_s3_client::types::ETag;
+use mountpoint_s3_client::types::{ETag, ObjectMetadata, ObjectTags, PutObjectResult};
 use tracing::{debug, error};
 
 use crate::fs::InodeError;
-use crate::metablock::{Lookup, Metablock, NewHandle, PendingUploadHook, ReadWriteMode, S3Location};
+use crate::metablock::{InodeStat, Lookup, Metablock, NewHandle, PendingUploadHook, ReadWriteMode, S3Location};
 use crate::object::ObjectId;
-use crate::prefetch::{HandleId, PrefetchGetObject};
+use crate::prefetch::{HandleId, PrefetchGetObject, PrefetchPriority};
+use crate::sync::atomic::{AtomicBool, Ordering};
 use crate::sync::{Arc, AsyncMutex};
-use crate::upload::{AppendUploadRequest, UploadRequest};
+use crate::upload::{AppendUploadRequest, CopyAppendRequest, StagedUploadRequest, UploadError, UploadRequest};
 
-use super::{Error, InodeNo, OpenFlags, S3Filesystem, ToErrno};
+use super::{
+    Decoding, Error, InodeNo, MODE_METADATA_KEY, OpenFlags, S3Filesystem, ToErrno, copy_unsupported, format_mode,
+};
 
 #[derive(Debug)]
 pub struct FileHandle<Client>
@@ -23,6 +28,11 @@ where
     pub state: AsyncMutex<FileHandleState<Client>>,
     /// Process that created the handle
     pub open_pid: u32,
+    /// Whether the handle was opened with `O_DIRECT`, or direct I/O was set on the file, so its reads bypass the data
+    /// cache
+    pub direct_io: bool,
+    /// Set once the kernel writes back dirty pages of a shared writable mapping of the file through the handle
+    pub written_back: AtomicBool,
 }
 
 impl<Client> FileHandle<Client>
@@ -32,6 +42,12 @@ where
     pub fn file_name(&self) -> &str {
         self.location.name()
     }
+
+    /// Whether the file has a shared writable mapping, whose dirty pages the kernel writes back through the handle
+    /// until it is unmapped, possibly after the file is closed.
+    pub fn is_written_back(&self) -> bool {
+        self.written_back.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug)]
@@ -41,7 +57,17 @@ where
 {
     /// The file handle has been assigned as a read handle
     Read {
-        request: PrefetchGetObject<Client>,
+        request: Box<PrefetchGetObject<Client>>,
+        /// The decoding of the object, when the file is its decoded content, see
+        /// [crate::S3FilesystemConfig::decode_content_encoding] and [crate::SuperblockConfig::decompress_files]
+        decoding: Option<Decoding>,
+        /// Set to true when `flush` called on the handle, and unset on a `read`
+        flushed: bool,
+    },
+    /// The file handle has been assigned as a read handle of a file being written, and reads the data staged by
+    /// its writer until it is uploaded
+    ReadWhileWriting {
+        writer: Arc<FileHandle<Client>>,
         /// Set to true when `flush` called on the handle, and unset on a `read`
         flushed: bool,
     },
@@ -61,27 +87,29 @@ where
         fh: u64,
         handle: &NewHandle,
         flags: OpenFlags,
+        direct_io: bool,
         fs: &S3Filesystem<Client>,
     ) -> Result<FileHandleState<Client>, Error> {
         let ino = handle.lookup.ino();
         let stat = handle.lookup.stat();
         let location = handle.lookup.s3_location()?;
-        let full_key = location.full_key();
+        let full_key = fs.s3_key(location.full_key().as_ref());
         let bucket = location.bucket_name();
 
         match handle.mode {
             ReadWriteMode::Read => {
-                let object_size = stat.size as u64;
-                let etag = match &stat.etag {
-                    None => return Err(err!(libc::EBADF, "no E-Tag for inode {}", ino)),
-                    Some(etag) => ETag::from_str(etag).expect("E-Tag should be set"),
-                };
-                let object_id = ObjectId::new(full_key.into(), etag);
-                let request = fs
-                    .prefetcher
-                    .prefetch(bucket.to_string(), object_id, HandleId::new(fh), object_size);
+                if let Some(writer) = handle.writer {
+                    return Self::new_read_while_writing(fh, ino, writer, fs).await;
+                }
+                let mut request = Self::prefetch(fh, &handle.lookup, direct_io, fs).await?;
+                // Backup and copy tools open files with O_NOATIME so that reading them doesn't
+                // update their access time, which marks reads that can yield to the other ones.
+                if flags.no_atime() {
+                    request.set_priority(PrefetchPriority::Batch);
+                }
                 let handle = FileHandleState::Read {
-                    request,
+                    request: Box::new(request),
+                    decoding: Self::decoding(stat)?,
                     flushed: false,
                 };
                 metrics::gauge!("fs.current_handles", "type" => "read").increment(1.0);
@@ -90,6 +118,16 @@ where
             ReadWriteMode::Write => {
                 let is_truncate = flags.contains(OpenFlags::O_TRUNC);
                 let write_mode = fs.config.write_mode();
+                if stat.encoded_size.is_some()
+                    && !is_truncate
+                    && (write_mode.incremental_upload || write_mode.append_by_copy)
+                {
+                    return Err(err!(
+                        libc::EPERM,
+                        "inode {} is decoded from its object and can only be overwritten, not appended to",
+                        ino
+                    ));
+                }
 
                 let upload_state = if write_mode.incremental_upload {
                     let initial_etag = if is_truncate {
@@ -100,7 +138,7 @@ where
                     let current_offset = if is_truncate { 0 } else { stat.size as u64 };
                     let request = fs.uploader.start_incremental_upload(
                         bucket.to_string(),
-                        full_key.into(),
+                        full_key,
                         current_offset,
                         initial_etag.clone(),
                     );
@@ -109,12 +147,55 @@ where
                         initial_etag,
                         written_bytes: 0,
                     }
+                } else if let Some(etag) = stat.etag.as_ref().filter(|_| write_mode.append_by_copy && !is_truncate) {
+                    let initial_etag: ETag = etag.into();
+                    let request = fs.uploader.start_copy_append(
+                        bucket.to_string(),
+                        full_key,
+                        stat.size as u64,
+                        initial_etag.clone(),
+                        write_mode.allow_overwrite,
+                    );
+                    UploadState::CopyAppendInProgress {
+                        request,
+                        initial_etag: Some(initial_etag),
+                    }
                 } else {
-                    let request = fs
-                        .uploader
-                        .start_atomic_upload(bucket.to_string(), full_key.into())
-                        .map_err(|e| err!(libc::EIO, source:e, "put failed to start"))?;
-                    UploadState::MPUInProgress { request }
+                    let mut object_metadata = ObjectMetadata::new();
+                    if let Some(mode) = stat.mode.filter(|_| fs.config.mode_metadata) {
+                        object_metadata.insert(MODE_METADATA_KEY.to_owned(), format_mode(mode));
+                    }
+                    // The ETag of the object being overwritten, which it must still have once the upload completes.
+                    let if_match: Option<ETag> = stat
+                        .etag
+                        .as_ref()
+                        .filter(|_| fs.config.conditional_overwrite && is_truncate)
+                        .map(|etag| etag.into());
+                    if fs.uploader.stages_writes() {
+                        let mut request = fs
+                            .uploader
+                            .start_staged_upload_with_metadata(bucket.to_string(), full_key, object_metadata)
+                            .map_err(|e| err!(libc::EIO, source:e, "staged upload failed to start"))?;
+                        request.set_if_match(if_match);
+                        UploadState::StagedInProgress { request }
+                    } else if let Some(etag) = if_match {
+                        let request = fs.uploader.start_conditional_overwrite(
+                            bucket.to_string(),
+                            full_key,
+                            object_metadata,
+                            etag,
+                        );
+                        UploadState::CopyAppendInProgress {
+                            request,
+                            initial_etag: None,
+                        }
+                    } else {
+                        let request = fs
+                            .uploader
+                            .start_atomic_upload_with_metadata(bucket.to_string(), full_key, object_metadata)
+                            .map_err(|e| err!(libc::EIO, source:e, "put failed to start"))?;
+                        UploadState::MPUInProgress { request }
+                    }
                 };
                 let handle = FileHandleState::Write {
                     state: upload_state,
@@ -125,18 +206,97 @@ where
             }
         }
     }
+
+    /// Start prefetching the object of a remote file for a read handle. Handles opened with `O_DIRECT`, or of files
+    /// under a prefix overriding the data cache, read straight from S3, bypassing the data cache.
+    pub async fn prefetch(
+        fh: u64,
+        lookup: &Lookup,
+        direct_io: bool,
+        fs: &S3Filesystem<Client>,
+    ) -> Result<PrefetchGetObject<Client>, Error> {
+        let stat = lookup.stat();
+        let location = lookup.s3_location()?;
+        let etag = match &stat.etag {
+            None => return Err(err!(libc::EBADF, "no E-Tag for inode {}", lookup.ino())),
+            Some(etag) => ETag::from_str(etag).expect("E-Tag should be set"),
+        };
+        let object_id = fs.object_id(location, stat, etag);
+        let bucket = location.bucket_name().to_string();
+        let size = stat.object_size() as u64;
+        let overrides = fs.config.prefix_overrides.for_key(location.partial_key.as_ref());
+        let mut request = if direct_io || overrides.and_then(|options| options.data_cache) == Some(false) {
+            fs.prefetcher
+                .prefetch_uncached(bucket, object_id, HandleId::new(fh), size)
+        } else {
+            fs.prefetcher.prefetch(bucket, object_id, HandleId::new(fh), size)
+        };
+        if let Some(max_read_window) = overrides.and_then(|options| options.max_read_window) {
+            request.set_max_read_window_size(max_read_window);
+        }
+        Ok(request)
+    }
+
+    /// The decoding of the object of a file read decoded, see [crate::S3FilesystemConfig::decode_content_encoding]
+    /// and [crate::SuperblockConfig::decompress_files].
+    pub fn decoding(stat: &InodeStat) -> Result<Option<Decoding>, Error> {
+        let (Some(encoded_size), Some(compression)) = (stat.encoded_size, stat.compression()) else {
+            return Ok(None);
+        };
+        Decoding::new(compression, encoded_size as u64, stat.size as u64).map(Some)
+    }
+
+    /// Create a read handle of a file being written by the given handle. Only the data of staged uploads can be
+    /// read back, so the handle is released if the writer streams its upload instead.
+    async fn new_read_while_writing(
+        fh: u64,
+        ino: InodeNo,
+        writer: u64,
+        fs: &S3Filesystem<Client>,
+    ) -> Result<FileHandleState<Client>, Error> {
+        let writer = fs.file_handles.read().await.get(&writer).cloned();
+        if let Some(writer) = writer
+            && let FileHandleState::Write {
+                state: UploadState::StagedInProgress { .. },
+                ..
+            } = &*writer.state.lock().await
+        {
+            metrics::gauge!("fs.current_handles", "type" => "read").increment(1.0);
+            return Ok(FileHandleState::ReadWhileWriting {
+                writer: writer.clone(),
+                flushed: false,
+            });
+        }
+        fs.metablock.finish_reading(ino, fh).await?;
+        Err(err!(
+            libc::EPERM,
+            "inode {} can only be read once its writer is closed, since its upload is not staged",
+            ino
+        ))
+    }
 }
 
 #[derive(Debug)]
-pub enum UploadState<Client: ObjectClient + Send + Sync> {
+pub enum UploadState<Client: ObjectClient + Clone + Send + Sync + 'static> {
     AppendInProgress {
         request: AppendUploadRequest<Client>,
         initial_etag: Option<ETag>,
         written_bytes: usize,
     },
+    /// Appending to an existing object by copying it, or creating a new object from server-side copies, see
+    /// [S3Filesystem::copy_file_range], or from the writes of a conditional overwrite, see
+    /// [super::S3FilesystemConfig::conditional_overwrite]. There is no initial ETag for new objects.
+    CopyAppendInProgress {
+        request: CopyAppendRequest<Client>,
+        initial_etag: Option<ETag>,
+    },
     MPUInProgress {
         request: UploadRequest<Client>,
     },
+    /// Writes are staged in a spool file, and can be made at any offset.
+    StagedInProgress {
+        request: StagedUploadRequest<Client>,
+    },
     Completed,
     // Remember the failure reason to respond to retries
     Failed(libc::c_int),
@@ -154,20 +314,35 @@ where
         data: &[u8],
         fh: u64,
     ) -> Result<u32, Error> {
-        let result: Result<_, Error> = match self {
+        // The number of bytes written, and how much they grew the file by.
+        let result: Result<(usize, usize), Error> = match self {
             UploadState::AppendInProgress {
                 request, written_bytes, ..
             } => match request.write(offset as u64, data).await {
                 Ok(len) => {
                     *written_bytes += len;
-                    Ok(len)
+                    Ok((len, len))
                 }
                 Err(e) => Err(e.into()),
             },
+            UploadState::CopyAppendInProgress { request, .. } => {
+                let size = request.size();
+                match request.write(offset as u64, data).await {
+                    Ok(len) => Ok((len, (request.size() - size) as usize)),
+                    Err(e) => Err(e.into()),
+                }
+            }
             UploadState::MPUInProgress { request, .. } => match request.write(offset, data).await {
-                Ok(len) => Ok(len),
+                Ok(len) => Ok((len, len)),
                 Err(e) => Err(e.into()),
             },
+            UploadState::StagedInProgress { request } => {
+                let size = request.size();
+                match request.write(offset, data).await {
+                    Ok(len) => Ok((len, (request.size() - size) as usize)),
+                    Err(e) => Err(e.into()),
+                }
+            }
             UploadState::Completed => {
                 return Err(err!(libc::EIO, "upload already completed for key {}", handle.location));
             }
@@ -177,14 +352,17 @@ where
         };
 
         match result {
-            Ok(len) => {
-                fs.metablock.inc_file_size(handle.ino, len).await?;
+            Ok((len, growth)) => {
+                fs.metablock.inc_file_size(handle.ino, growth).await?;
                 Ok(len as u32)
             }
             Err(e) => {
                 // Abort the request.
                 match std::mem::replace(self, UploadState::Failed(e.to_errno())) {
-                    UploadState::MPUInProgress { .. } | UploadState::AppendInProgress { .. } => {
+                    UploadState::MPUInProgress { .. }
+                    | UploadState::StagedInProgress { .. }
+                    | UploadState::AppendInProgress { .. }
+                    | UploadState::CopyAppendInProgress { .. } => {
                         Self::finish_on_error(fs.metablock.clone(), handle.ino, &handle.location, fh).await;
                     }
                     UploadState::Failed(_) | UploadState::Completed => unreachable!("checked above"),
@@ -194,6 +372,107 @@ where
         }
     }
 
+    /// Append the given range of another object by copying it server-side, see [S3Filesystem::copy_file_range].
+    /// Only uploads copying their content can do so: appends by copy, and new files not written to yet, whose atomic
+    /// upload is replaced with one built from copies.
+    pub async fn copy_from(
+        &mut self,
+        fs: &S3Filesystem<Client>,
+        handle: &FileHandle<Client>,
+        offset: u64,
+        source: &ObjectId,
+        range: Range<u64>,
+        fh: u64,
+    ) -> Result<u64, Error> {
+        if let UploadState::MPUInProgress { request } = self
+            && request.size() == 0
+            && offset == 0
+        {
+            let request = fs.uploader.start_copy(
+                handle.location.bucket_name().to_owned(),
+                fs.s3_key(handle.location.full_key().as_ref()),
+                request.object_metadata().clone(),
+            );
+            *self = UploadState::CopyAppendInProgress {
+                request,
+                initial_etag: None,
+            };
+        }
+        let UploadState::CopyAppendInProgress { request, .. } = self else {
+            return Err(copy_unsupported(
+                "the file was already written to, or its writes are staged",
+            ));
+        };
+        match request
+            .copy_from(offset, source.key().to_owned(), source.etag().clone(), range)
+            .await
+        {
+            Ok(len) => {
+                fs.metablock.inc_file_size(handle.ino, len as usize).await?;
+                Ok(len)
+            }
+            Err(e) => {
+                // Abort the request, like for failed writes.
+                let e: Error = e.into();
+                *self = UploadState::Failed(e.to_errno());
+                Self::finish_on_error(fs.metablock.clone(), handle.ino, &handle.location, fh).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Give a hint of the final size of the file being written, see [UploadRequest::set_size_hint]. Only atomic
+    /// uploads choose their part size, and only until they are first written to.
+    pub fn set_size_hint(&mut self, size: u64) {
+        if let UploadState::MPUInProgress { request } = self
+            && !request.set_size_hint(size)
+        {
+            debug!(size, "ignored size hint of an upload already started");
+        }
+    }
+
+    /// Truncate or extend the file being written to the given size. Returns whether it was resized, as only staged
+    /// uploads can be: the other ones have already sent what was written.
+    pub async fn set_size(&mut self, size: u64) -> Result<bool, Error> {
+        match self {
+            UploadState::StagedInProgress { request } => {
+                request.set_size(size)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The tags the object is uploaded with, for the uploads of new objects, which can be tagged.
+    pub fn object_tags(&self) -> Option<&ObjectTags> {
+        match self {
+            UploadState::MPUInProgress { request } => Some(request.object_tags()),
+            UploadState::StagedInProgress { request } => Some(request.object_tags()),
+            _ => None,
+        }
+    }
+
+    /// Replace the tags the object is uploaded with. Return false, leaving them unchanged, if the upload can't be
+    /// tagged, or was already started by a write and its writes are not staged.
+    pub fn set_object_tags(&mut self, object_tags: ObjectTags) -> bool {
+        match self {
+            UploadState::MPUInProgress { request } => request.set_object_tags(object_tags),
+            UploadState::StagedInProgress { request } => {
+                request.set_object_tags(object_tags);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Read back data written to the handle. Only staged uploads keep what was written, in their spool file.
+    pub async fn read(&mut self, offset: i64, size: u32) -> Result<Bytes, Error> {
+        match self {
+            UploadState::StagedInProgress { request } => Ok(request.read(offset as u64, size as usize)?.into()),
+            _ => Err(err!(libc::EBADF, "file handle is not open for reads")),
+        }
+    }
+
     /// Commit data to S3 and mark the upload as completed. In case it is an append request, start
     /// a new request with the current offset and new etag.
     pub async fn commit(
@@ -225,7 +504,7 @@ where
                 let initial_etag = etag.or(initial_etag);
                 let request = fs.uploader.start_incremental_upload(
                     handle.location.bucket_name().to_owned(),
-                    handle.location.full_key().to_string(),
+                    fs.s3_key(handle.location.full_key().as_ref()),
                     current_offset,
                     initial_etag.clone(),
                 );
@@ -235,16 +514,88 @@ where
                     written_bytes,
                 };
             }
+            UploadState::CopyAppendInProgress { request, initial_etag } => {
+                let size = request.size();
+                let etag = Self::commit_copy_append(request, &handle.location)
+                    .await
+                    .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?;
+
+                // Restart from the object just uploaded.
+                let Some(initial_etag) = etag.or(initial_etag) else {
+                    unreachable!("new objects are created when committed");
+                };
+                let request = fs.uploader.start_copy_append(
+                    handle.location.bucket_name().to_owned(),
+                    fs.s3_key(handle.location.full_key().as_ref()),
+                    size,
+                    initial_etag.clone(),
+                    fs.config.allow_overwrite,
+                );
+                *self = UploadState::CopyAppendInProgress {
+                    request,
+                    initial_etag: Some(initial_etag),
+                };
+            }
+            UploadState::MPUInProgress { request, .. } if fs.config.write_after_fsync => {
+                let size = request.size();
+                let result = request.complete().await;
+                *self = Self::continue_by_copy(fs, &handle, size, result, fh)
+                    .await
+                    .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?;
+            }
             UploadState::MPUInProgress { request, .. } => {
                 Self::complete_upload(fs.metablock.clone(), handle.ino, &handle.location, request, fh)
                     .await
                     .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?;
             }
+            UploadState::StagedInProgress { request } if fs.config.write_after_fsync => {
+                let size = request.size();
+                let result = request.complete().await;
+                *self = Self::continue_by_copy(fs, &handle, size, result, fh)
+                    .await
+                    .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?;
+            }
+            UploadState::StagedInProgress { request } => {
+                Self::complete_staged_upload(fs.metablock.clone(), handle.ino, &handle.location, request, fh)
+                    .await
+                    .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?;
+            }
             UploadState::Failed(_) | UploadState::Completed => unreachable!("checked above"),
         }
         Ok(())
     }
 
+    /// Once the upload of a file being created or overwritten is committed, start appending to the object just
+    /// uploaded by copying it, so that the handle can keep writing, see [super::S3FilesystemConfig::write_after_fsync].
+    async fn continue_by_copy(
+        fs: &S3Filesystem<Client>,
+        handle: &FileHandle<Client>,
+        size: u64,
+        result: Result<PutObjectResult, UploadError<Client::ClientError>>,
+        fh: u64,
+    ) -> Result<Self, Error> {
+        match result {
+            Ok(put_result) => {
+                debug!(etag=?put_result.etag.as_str(), key=%handle.location, size, "put succeeded, continuing by copy");
+                let request = fs.uploader.start_copy_append(
+                    handle.location.bucket_name().to_owned(),
+                    fs.s3_key(handle.location.full_key().as_ref()),
+                    size,
+                    put_result.etag.clone(),
+                    fs.config.allow_overwrite,
+                );
+                Ok(UploadState::CopyAppendInProgress {
+                    request,
+                    initial_etag: Some(put_result.etag),
+                })
+            }
+            Err(e) => {
+                Self::finish_on_error(fs.metablock.clone(), handle.ino, &handle.location, fh).await;
+                Err(InodeError::upload_error(e, handle.location.clone()).into())
+            }
+        }
+    }
+
     /// Commit any buffered data (if written by the opener-process) to S3, and mark the upload as
     /// completed. In case there is no data written, or if it is written by a different process,
     /// don't complete the upload but mark the handle as flushed.
@@ -264,6 +615,13 @@ where
                     return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
                 }
             }
+            UploadState::CopyAppendInProgress { request, .. } => {
+                if request.written_bytes() == 0 || !are_from_same_process(open_pid, pid) {
+                    // Commit current changes. But don't close the write handle, only mark it as flushed
+                    self.commit(fs, handle.clone(), fh).await?;
+                    return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
+                }
+            }
             UploadState::MPUInProgress { request, .. } => {
                 if request.size() == 0 {
                     debug!(key=%handle.location, "not completing upload because nothing was written yet");
@@ -277,6 +635,27 @@ where
                     return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
                 }
             }
+            UploadState::StagedInProgress { request } => {
+                if fs.config.defer_staged_uploads || handle.is_written_back() {
+                    // Only record the spool file, so that closing the file doesn't wait for its upload, which
+                    // completes once the handle is released. The handle of a mapped file is only released once it
+                    // is unmapped, after the kernel has written back its last dirty pages.
+                    request.record()?;
+                    debug!(key=%handle.location, "deferring staged upload until the handle is released");
+                    return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
+                }
+                if request.size() == 0 {
+                    debug!(key=%handle.location, "not completing staged upload because nothing was written yet");
+                    return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
+                }
+                if !are_from_same_process(open_pid, pid) {
+                    debug!(
+                        key=%handle.location,
+                        pid, open_pid, "not completing staged upload because current PID differs from PID at open",
+                    );
+                    return Self::flush_writer(fs, handle.ino, handle.clone(), fh).await;
+                }
+            }
             UploadState::Completed => return Ok(()),
             UploadState::Failed(e) => {
                 return Err(err!(
@@ -300,11 +679,26 @@ where
             )
             .await
             .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?,
+            UploadState::CopyAppendInProgress { request, initial_etag } => Self::complete_copy_append(
+                fs.metablock.clone(),
+                handle.ino,
+                &handle.location,
+                request,
+                initial_etag,
+                fh,
+            )
+            .await
+            .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?,
             UploadState::MPUInProgress { request, .. } => {
                 Self::complete_upload(fs.metablock.clone(), handle.ino, &handle.location, request, fh)
                     .await
                     .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?
             }
+            UploadState::StagedInProgress { request } => {
+                Self::complete_staged_upload(fs.metablock.clone(), handle.ino, &handle.location, request, fh)
+                    .await
+                    .inspect_err(|e| *self = UploadState::Failed(e.to_errno()))?
+            }
             UploadState::Failed(_) | UploadState::Completed => unreachable!("checked above"),
         };
         Ok(())
@@ -340,9 +734,15 @@ where
             } => Ok(Some(
                 Self::complete_append(metablock, ino, key, request, initial_etag, fh).await?,
             )),
+            UploadState::CopyAppendInProgress { request, initial_etag } => Ok(Some(
+                Self::complete_copy_append(metablock, ino, key, request, initial_etag, fh).await?,
+            )),
             UploadState::MPUInProgress { request, .. } => {
                 Ok(Some(Self::complete_upload(metablock, ino, key, request, fh).await?))
             }
+            UploadState::StagedInProgress { request } => Ok(Some(
+                Self::complete_staged_upload(metablock, ino, key, request, fh).await?,
+            )),
             UploadState::Failed(_) | UploadState::Completed => unreachable!("checked above"),
         }
     }
@@ -367,6 +767,26 @@ where
         }
     }
 
+    async fn complete_staged_upload(
+        metablock: Arc<dyn Metablock>,
+        ino: InodeNo,
+        key: &S3Location,
+        upload: StagedUploadRequest<Client>,
+        fh: u64,
+    ) -> Result<Lookup, InodeError> {
+        let size = upload.size();
+        match upload.complete().await {
+            Ok(put_result) => {
+                debug!(etag=?put_result.etag.as_str(), %key, size, "staged put succeeded");
+                metablock.finish_writing(ino, Some(put_result.etag), fh).await
+            }
+            Err(e) => {
+                Self::finish_on_error(metablock, ino, key, fh).await;
+                Err(InodeError::upload_error(e, key.clone()))
+            }
+        }
+    }
+
     async fn complete_append(
         metablock: Arc<dyn Metablock>,
         ino: InodeNo,
@@ -401,6 +821,44 @@ where
         }
     }
 
+    async fn complete_copy_append(
+        metablock: Arc<dyn Metablock>,
+        ino: InodeNo,
+        key: &S3Location,
+        upload: CopyAppendRequest<Client>,
+        initial_etag: Option<ETag>,
+        fh: u64,
+    ) -> Result<Lookup, InodeError> {
+        match Self::commit_copy_append(upload, key).await {
+            Ok(etag) => {
+                let etag = etag.or(initial_etag);
+                metablock.finish_writing(ino, etag, fh).await
+            }
+            Err(err) => {
+                Self::finish_on_error(metablock, ino, key, fh).await;
+                Err(err)
+            }
+        }
+    }
+
+    async fn commit_copy_append(
+        upload: CopyAppendRequest<Client>,
+        key: &S3Location,
+    ) -> Result<Option<ETag>, InodeError> {
+        let size = upload.size();
+        match upload.complete().await {
+            Ok(Some(result)) => {
+                debug!(etag=?result.etag.as_str(), %key, size, "append by copy succeeded");
+                Ok(Some(result.etag))
+            }
+            Ok(None) => {
+                debug!(%key, "no append required");
+                Ok(None)
+            }
+            Err(e) => Err(InodeError::upload_error(e, key.clone())),
+        }
+    }
+
     async fn finish_on_error(metablock: Arc<dyn Metablock>, ino: InodeNo, s3location: &S3Location, fh: u64) {
         if let Err(err) = metablock.finish_writing(ino, None, fh).await {
             // Log the issue but still return put_result.
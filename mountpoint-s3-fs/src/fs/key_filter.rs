@@ -0,0 +1,123 @@
+//! Filtering of the keys shown in the file system.
+//!
+//! A [KeyFilter] hides the keys matching one of its exclude patterns, and when it has include patterns, the files
+//! not matching any of them. Directories are only hidden by exclude patterns, so that the files they hold can still be
+//! included. Hidden keys are not listed and can't be looked up, as if they didn't exist.
+
+use regex::Regex;
+
+use super::glob_regex;
+
+/// Filter of the keys shown in the file system, see [crate::SuperblockConfig::key_filter].
+///
+/// Patterns with no `/`, other than a trailing one, match the names of files and directories, such as `*.tmp`.
+/// Other patterns match the whole key relative to the mount prefix, such as `logs/**`. The keys and names of
+/// directories end with `/`, so `*_$folder$` only matches files and `tmp/` only matches directories. In patterns, `*`
+/// matches any characters other than `/`, `**` any characters, and `?` a single character other than `/`.
+#[derive(Debug, Clone, Default)]
+pub struct KeyFilter {
+    include: Vec<KeyPattern>,
+    exclude: Vec<KeyPattern>,
+}
+
+#[derive(Debug, Clone)]
+struct KeyPattern {
+    regex: Regex,
+    /// Whether the pattern matches names rather than keys.
+    name_only: bool,
+}
+
+impl KeyPattern {
+    fn new(pattern: &str) -> Self {
+        Self {
+            regex: glob_regex(pattern),
+            name_only: !pattern.trim_end_matches('/').contains('/'),
+        }
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        if self.name_only {
+            let name_start = key.trim_end_matches('/').rfind('/').map_or(0, |i| i + 1);
+            self.regex.is_match(&key[name_start..])
+        } else {
+            self.regex.is_match(key)
+        }
+    }
+}
+
+impl KeyFilter {
+    /// Show the files matching the given pattern. Once a filter has include patterns, it hides the files matching
+    /// none of them.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(KeyPattern::new(pattern));
+        self
+    }
+
+    /// Hide the files and directories matching the given pattern, even if they match an include pattern.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(KeyPattern::new(pattern));
+        self
+    }
+
+    /// Whether the filter shows every key.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the file or directory with the given key, relative to the mount prefix and ending with `/` for
+    /// directories, is shown.
+    pub fn is_visible(&self, key: &str) -> bool {
+        if self.exclude.iter().any(|pattern| pattern.matches(key)) {
+            return false;
+        }
+        key.ends_with('/') || self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("dir/file.txt", true; "plain file")]
+    #[test_case("dir/file.tmp", false; "excluded name")]
+    #[test_case("file.tmp", false; "excluded name at top level")]
+    #[test_case("dir_$folder$", false; "folder marker")]
+    #[test_case("dir_$folder$/", true; "directory named like a marker")]
+    #[test_case("logs/2024/app.log", false; "excluded key")]
+    #[test_case("logs/", false; "directory of excluded keys")]
+    #[test_case("dir/logs/app.log", true; "excluded key pattern in another directory")]
+    #[test_case("scratch/", false; "excluded directory")]
+    #[test_case("dir/scratch/", false; "excluded nested directory")]
+    #[test_case("scratch", true; "file with excluded directory name")]
+    fn test_exclude(key: &str, expected: bool) {
+        let filter = KeyFilter::default()
+            .exclude("*.tmp")
+            .exclude("*_$folder$")
+            .exclude("logs/**")
+            .exclude("scratch/");
+        assert_eq!(filter.is_visible(key), expected);
+    }
+
+    #[test_case("data.parquet", true; "included file")]
+    #[test_case("year=2024/data.parquet", true; "nested included file")]
+    #[test_case("year=2024/_SUCCESS", false; "not included file")]
+    #[test_case("year=2024/", true; "directory")]
+    #[test_case("year=2024/tmp.parquet", false; "excluded included file")]
+    #[test_case("manifests/2024.json", true; "included key")]
+    fn test_include(key: &str, expected: bool) {
+        let filter = KeyFilter::default()
+            .include("*.parquet")
+            .include("manifests/*.json")
+            .exclude("tmp.*");
+        assert_eq!(filter.is_visible(key), expected);
+    }
+
+    #[test]
+    fn test_empty() {
+        let filter = KeyFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.is_visible("dir/file.tmp"));
+        assert!(!filter.include("*.parquet").is_empty());
+    }
+}
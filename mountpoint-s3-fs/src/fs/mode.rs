@@ -0,0 +1,45 @@
+//! Permissions of files kept in the `x-amz-meta-mode` user-defined metadata of their objects.
+//!
+//! This is the convention of s3fs, which writes the whole mode of files in this metadata as a decimal number, including
+//! their type. Mountpoint reads and writes the same, and only uses the permission bits.
+
+/// Key of the user-defined metadata holding the mode of the file an object represents, see
+/// [crate::S3FilesystemConfig::mode_metadata].
+pub const MODE_METADATA_KEY: &str = "mode";
+
+/// Mask of the permission bits of a mode, including the setuid, setgid and sticky bits.
+const PERMISSION_BITS: libc::mode_t = 0o7777;
+
+/// Parse the value of the [MODE_METADATA_KEY] metadata of an object, and return its permission bits.
+pub(crate) fn parse_mode(value: &str) -> Option<u16> {
+    let mode: libc::mode_t = value.parse().ok()?;
+    Some((mode & PERMISSION_BITS) as u16)
+}
+
+/// Format the permission bits of a regular file as the value of the [MODE_METADATA_KEY] metadata of its object.
+pub(crate) fn format_mode(perm: u16) -> String {
+    (libc::S_IFREG | (libc::mode_t::from(perm) & PERMISSION_BITS)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("33188", Some(0o644); "regular file")]
+    #[test_case("16877", Some(0o755); "directory")]
+    #[test_case("420", Some(0o644); "permissions only")]
+    #[test_case("35309", Some(0o4755); "setuid")]
+    #[test_case("", None; "empty")]
+    #[test_case("-1", None; "negative")]
+    #[test_case("rw-r--r--", None; "not a number")]
+    fn test_parse_mode(value: &str, expected: Option<u16>) {
+        assert_eq!(parse_mode(value), expected);
+    }
+
+    #[test]
+    fn test_format_mode() {
+        assert_eq!(format_mode(0o644), "33188");
+        assert_eq!(parse_mode(&format_mode(0o4750)), Some(0o4750));
+    }
+}
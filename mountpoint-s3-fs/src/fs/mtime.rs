@@ -0,0 +1,61 @@
+//! Modification times of files kept in the `x-amz-meta-mtime` user-defined metadata of their objects.
+//!
+//! This is the convention of s3fs and rclone, which write the modification time of files in this metadata as a number
+//! of seconds since the Unix epoch, respectively as an integer and with a fractional part. Mountpoint reads both, and
+//! writes whole seconds so that s3fs can read them back.
+
+use time::OffsetDateTime;
+
+/// Key of the user-defined metadata holding the modification time of the file an object represents, see
+/// [crate::S3FilesystemConfig::mtime_metadata].
+pub const MTIME_METADATA_KEY: &str = "mtime";
+
+/// Parse the value of the [MTIME_METADATA_KEY] metadata of an object.
+pub(crate) fn parse_mtime(value: &str) -> Option<OffsetDateTime> {
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let seconds: i64 = seconds.parse().ok()?;
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // Only nanoseconds are kept, and the digits are padded to them.
+    let nanos = fraction
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0i128, |nanos, digit| nanos * 10 + (digit - b'0') as i128);
+    OffsetDateTime::from_unix_timestamp_nanos(seconds as i128 * 1_000_000_000 + nanos).ok()
+}
+
+/// Format a modification time as the value of the [MTIME_METADATA_KEY] metadata of an object, and return it along with
+/// the modification time it represents, truncated to whole seconds.
+pub(crate) fn format_mtime(mtime: OffsetDateTime) -> (String, OffsetDateTime) {
+    let seconds = mtime.unix_timestamp();
+    let truncated = OffsetDateTime::from_unix_timestamp(seconds).expect("whole seconds of a valid time are valid");
+    (seconds.to_string(), truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("1700000000", Some(1_700_000_000_000_000_000); "whole seconds")]
+    #[test_case("1700000000.5", Some(1_700_000_000_500_000_000); "fractional seconds")]
+    #[test_case("1700000000.123456789123", Some(1_700_000_000_123_456_789); "beyond nanoseconds")]
+    #[test_case("-1", Some(-1_000_000_000); "before the epoch")]
+    #[test_case("", None; "empty")]
+    #[test_case("1700000000.5s", None; "invalid fraction")]
+    #[test_case("2023-11-14T22:13:20Z", None; "not a number")]
+    fn test_parse_mtime(value: &str, expected_nanos: Option<i128>) {
+        let parsed = parse_mtime(value).map(OffsetDateTime::unix_timestamp_nanos);
+        assert_eq!(parsed, expected_nanos);
+    }
+
+    #[test]
+    fn test_format_mtime() {
+        let mtime = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_750_000_000).unwrap();
+        let (value, truncated) = format_mtime(mtime);
+        assert_eq!(value, "1700000000");
+        assert_eq!(parse_mtime(&value), Some(truncated));
+    }
+}
@@ -0,0 +1,91 @@
+//! Keeping the kernel page cache of files across opens while their objects are unchanged.
+//!
+//! The kernel drops the pages it cached for a file each time the file is opened, unless the open is replied to with
+//! `FOPEN_KEEP_CACHE`, so that files read repeatedly are downloaded again on each open. With
+//! [crate::S3FilesystemConfig::keep_page_cache], the file system remembers the ETag and modification time of the object
+//! of each file when it is opened for reading, and keeps the pages cached by the kernel on the next open only if the
+//! object it finds then has the same ones, as the `auto_cache` option of libfuse does.
+
+use std::collections::HashMap;
+
+use time::OffsetDateTime;
+
+use crate::metablock::{InodeNo, InodeStat};
+use crate::sync::Mutex;
+
+/// The versions of the objects whose content the kernel may have cached, see
+/// [crate::S3FilesystemConfig::keep_page_cache].
+#[derive(Debug, Default)]
+pub(super) struct PageCaches {
+    versions: Mutex<HashMap<InodeNo, CachedVersion>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct CachedVersion {
+    etag: Box<str>,
+    mtime: OffsetDateTime,
+}
+
+impl PageCaches {
+    /// Whether the pages cached by the kernel for the file with the given inode number can be kept on its opening for
+    /// reading, with the given attributes. They are cached for the object the file now has from then on.
+    pub fn revalidate(&self, ino: InodeNo, stat: &InodeStat) -> bool {
+        let mut versions = self.versions.lock().unwrap();
+        let Some(etag) = stat.etag.clone() else {
+            // Files being written don't have an object yet.
+            versions.remove(&ino);
+            return false;
+        };
+        let version = CachedVersion {
+            etag,
+            mtime: stat.mtime,
+        };
+        let keep = versions.get(&ino) == Some(&version);
+        if !keep {
+            versions.insert(ino, version);
+        }
+        metrics::counter!("fs.page_cache_revalidations", "result" => if keep { "kept" } else { "dropped" })
+            .increment(1);
+        keep
+    }
+
+    /// Drop the pages cached for the file with the given inode number on its next opening, once it is written or
+    /// forgotten by the kernel.
+    pub fn invalidate(&self, ino: InodeNo) {
+        self.versions.lock().unwrap().remove(&ino);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_revalidate() {
+        let page_caches = PageCaches::default();
+        let mtime = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let stat = |etag: Option<&str>, mtime| {
+            InodeStat::for_file(5, mtime, etag.map(Into::into), None, None, Duration::from_secs(1))
+        };
+
+        assert!(!page_caches.revalidate(2, &stat(Some("etag1"), mtime)), "first open");
+        assert!(page_caches.revalidate(2, &stat(Some("etag1"), mtime)));
+        assert!(!page_caches.revalidate(3, &stat(Some("etag1"), mtime)), "other file");
+
+        assert!(
+            !page_caches.revalidate(2, &stat(Some("etag2"), mtime)),
+            "object replaced"
+        );
+        assert!(page_caches.revalidate(2, &stat(Some("etag2"), mtime)));
+        let later = mtime + Duration::from_secs(1);
+        assert!(!page_caches.revalidate(2, &stat(Some("etag2"), later)), "modified");
+
+        assert!(!page_caches.revalidate(2, &stat(None, later)), "being written");
+        assert!(!page_caches.revalidate(2, &stat(Some("etag2"), later)));
+
+        page_caches.invalidate(2);
+        assert!(!page_caches.revalidate(2, &stat(Some("etag2"), later)));
+    }
+}
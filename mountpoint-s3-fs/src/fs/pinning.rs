@@ -0,0 +1,90 @@
+//! Pinning files in the local disk cache with an extended attribute.
+//!
+//! Setting the [PIN_XATTR] extended attribute of a file to `1` fetches its content to the local disk cache in the
+//! background, and keeps it there until the attribute is set to `0` or removed, whatever the cache limit and eviction
+//! policy. Data loaders can then keep the files they read repeatedly in the cache, and let them go once done with them.
+//! Pins are kept in memory, so they last until the file system is unmounted.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::data_cache::{DataCache, DiskDataCache};
+use crate::object::ObjectId;
+use crate::sync::{Arc, Mutex};
+
+/// Name of the extended attribute pinning a file in the local disk cache when set to `1`, see
+/// [crate::S3FilesystemConfig::pinning_cache].
+pub const PIN_XATTR: &str = "user.mountpoint.pin";
+
+/// The local disk cache the files pinned with [PIN_XATTR] are kept in.
+#[derive(Clone)]
+pub struct PinningCache {
+    cache: Arc<DiskDataCache>,
+}
+
+impl PinningCache {
+    /// Keep the pinned files in `cache`, which the prefetcher of the file system should write the blocks it fetches
+    /// to.
+    pub fn new(cache: Arc<DiskDataCache>) -> Self {
+        Self { cache }
+    }
+}
+
+impl Debug for PinningCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinningCache").finish_non_exhaustive()
+    }
+}
+
+/// The objects pinned in a [PinningCache], by key.
+pub(super) struct PinnedObjects {
+    cache: PinningCache,
+    objects: Mutex<HashMap<String, PinnedObject>>,
+}
+
+struct PinnedObject {
+    object_id: ObjectId,
+    size: u64,
+}
+
+impl PinnedObjects {
+    pub fn new(cache: PinningCache) -> Self {
+        Self {
+            cache,
+            objects: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pin an object of the given size, unpinning any other version of it.
+    pub fn pin(&self, object_id: &ObjectId, size: u64) {
+        let pinned = PinnedObject {
+            object_id: object_id.clone(),
+            size,
+        };
+        let mut objects = self.objects.lock().unwrap();
+        if let Some(previous) = objects.insert(object_id.key().to_owned(), pinned)
+            && previous.object_id != *object_id
+        {
+            self.cache.cache.unpin_object(&previous.object_id, previous.size);
+        }
+        self.cache.cache.pin_object(object_id, size);
+    }
+
+    /// Unpin the object with the given key. Returns `false` if it wasn't pinned.
+    pub fn unpin(&self, key: &str) -> bool {
+        let Some(pinned) = self.objects.lock().unwrap().remove(key) else {
+            return false;
+        };
+        self.cache.cache.unpin_object(&pinned.object_id, pinned.size);
+        true
+    }
+
+    pub fn is_pinned(&self, key: &str) -> bool {
+        self.objects.lock().unwrap().contains_key(key)
+    }
+
+    /// Size of the reads fetching the pinned objects, which each fill a block of the cache.
+    pub fn read_size(&self) -> usize {
+        self.cache.cache.block_size() as usize
+    }
+}
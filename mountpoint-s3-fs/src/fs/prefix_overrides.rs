@@ -0,0 +1,206 @@
+//! Options overridden for the files and directories under prefixes of the mount.
+//!
+//! A single bucket often mixes data that needs different options, such as hot mutable data and cold immutable
+//! archives. [PrefixOverrides] override some of the options of the mount for the files and directories under given
+//! prefixes. They are described by a JSON object mapping prefixes, relative to the mounted prefix and ending in `/`,
+//! to the options overridden under them:
+//!
+//! ```json
+//! {
+//!     "archive/": { "read-only": true, "metadata-ttl": "indefinite", "storage-class": "GLACIER_IR" },
+//!     "scratch/": { "metadata-ttl": "minimal", "max-read-window": 8388608, "data-cache": false }
+//! }
+//! ```
+//!
+//! Only the longest prefix matching a key applies, and the options it doesn't set are the ones of the mount.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use super::{MetadataTtlRule, TimeToLive};
+use crate::s3::{Prefix, PrefixError};
+
+/// The options overridden under a prefix, see [PrefixOverrides].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PrefixOverride {
+    /// Metadata TTL of the files and directories, in place of the one of the mount and of its TTL rules, see
+    /// [super::CacheConfig::ttl_rules].
+    #[serde(default, deserialize_with = "deserialize_ttl")]
+    pub metadata_ttl: Option<TimeToLive>,
+    /// Maximum amount of data prefetched ahead of sequential reads, in bytes, in place of
+    /// [crate::prefetch::PrefetcherConfig::max_read_window_size].
+    pub max_read_window: Option<usize>,
+    /// Whether reads go through the data cache of the mount, if it has one. Reads bypass it if set to false.
+    pub data_cache: Option<bool>,
+    /// Storage class of the objects written, in place of [super::S3FilesystemConfig::storage_class].
+    pub storage_class: Option<String>,
+    /// Reject the creation, modification, renaming and removal of files and directories with `EROFS`.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+fn deserialize_ttl<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<TimeToLive>, D::Error> {
+    let ttl = String::deserialize(deserializer)?;
+    TimeToLive::new_from_str(&ttl)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Error)]
+pub enum PrefixOverridesError {
+    #[error("invalid prefix overrides")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("invalid prefix {0:?}")]
+    InvalidPrefix(String, #[source] PrefixError),
+    #[error("the empty prefix can't be overridden, set the options of the mount instead")]
+    EmptyPrefix,
+}
+
+/// Options overridden for the files and directories under prefixes of the mount, see [PrefixOverride].
+#[derive(Debug, Clone, Default)]
+pub struct PrefixOverrides {
+    /// The mounted prefix, which the prefixes of the overrides are relative to.
+    mount_prefix: String,
+    /// Longest prefix first, so that the first one matching a key applies.
+    overrides: Vec<(Prefix, PrefixOverride)>,
+}
+
+impl PrefixOverrides {
+    /// Overrides of the given options under each prefix, relative to `mount_prefix`.
+    pub fn new(
+        mount_prefix: &Prefix,
+        overrides: impl IntoIterator<Item = (Prefix, PrefixOverride)>,
+    ) -> Result<Self, PrefixOverridesError> {
+        let mut overrides: Vec<_> = overrides.into_iter().collect();
+        if overrides.iter().any(|(prefix, _)| prefix.as_str().is_empty()) {
+            return Err(PrefixOverridesError::EmptyPrefix);
+        }
+        overrides.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.as_str().len()));
+        Ok(Self {
+            mount_prefix: mount_prefix.as_str().to_owned(),
+            overrides,
+        })
+    }
+
+    /// Parse the overrides from a JSON object mapping prefixes, relative to `mount_prefix`, to their options.
+    pub fn from_json(mount_prefix: &Prefix, json: &str) -> Result<Self, PrefixOverridesError> {
+        let overrides: BTreeMap<String, PrefixOverride> = serde_json::from_str(json)?;
+        let overrides = overrides
+            .into_iter()
+            .map(|(prefix, options)| match Prefix::new(&prefix) {
+                Ok(prefix) => Ok((prefix, options)),
+                Err(e) => Err(PrefixOverridesError::InvalidPrefix(prefix, e)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(mount_prefix, overrides)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// The options overridden for the file or directory with the given key, relative to the mounted prefix, if any.
+    pub fn for_key(&self, key: &str) -> Option<&PrefixOverride> {
+        self.overrides
+            .iter()
+            .find(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .map(|(_, options)| options)
+    }
+
+    /// The options overridden for the object with the given key in the bucket, if any.
+    pub fn for_object_key(&self, key: &str) -> Option<&PrefixOverride> {
+        self.for_key(key.strip_prefix(&self.mount_prefix)?)
+    }
+
+    /// Whether the file or directory with the given key, relative to the mounted prefix, is read-only.
+    pub fn is_read_only(&self, key: &str) -> bool {
+        self.for_key(key).is_some_and(|options| options.read_only)
+    }
+
+    /// Whether the file or directory with the given key, relative to the mounted prefix, is read-only, or for the
+    /// keys of directories, ending in `/`, whether any of the files and directories under it is.
+    pub fn is_read_only_under(&self, key: &str) -> bool {
+        self.is_read_only(key)
+            || (key.ends_with('/') || key.is_empty())
+                && self
+                    .overrides
+                    .iter()
+                    .any(|(prefix, options)| options.read_only && prefix.as_str().starts_with(key))
+    }
+
+    /// The metadata TTL rules of the prefixes overriding the metadata TTL, to be applied before the other rules of
+    /// the mount, see [super::CacheConfig::ttl_rules].
+    pub fn ttl_rules(&self) -> Vec<MetadataTtlRule> {
+        self.overrides
+            .iter()
+            .filter_map(|(prefix, options)| {
+                let ttl = options.metadata_ttl?;
+                Some(MetadataTtlRule::for_prefix(prefix.as_str(), ttl))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let json = r#"{
+            "archive/": { "read-only": true, "metadata-ttl": "indefinite", "storage-class": "GLACIER_IR" },
+            "archive/incoming/": { "max-read-window": 1048576, "data-cache": false }
+        }"#;
+        let overrides = PrefixOverrides::from_json(&Prefix::new("mount/").unwrap(), json).unwrap();
+
+        let archive = overrides.for_key("archive/a.txt").unwrap();
+        assert!(archive.read_only);
+        assert!(matches!(archive.metadata_ttl, Some(TimeToLive::Indefinite)));
+        assert_eq!(archive.storage_class.as_deref(), Some("GLACIER_IR"));
+        assert!(overrides.is_read_only("archive/"));
+
+        // Only the longest prefix applies.
+        let incoming = overrides.for_key("archive/incoming/b.txt").unwrap();
+        assert!(!incoming.read_only);
+        assert_eq!(incoming.max_read_window, Some(1024 * 1024));
+        assert_eq!(incoming.data_cache, Some(false));
+        assert!(incoming.storage_class.is_none());
+
+        assert!(overrides.for_key("archive.txt").is_none());
+        assert!(overrides.for_object_key("mount/archive/a.txt").unwrap().read_only);
+        assert!(overrides.for_object_key("archive/a.txt").is_none());
+
+        assert!(overrides.is_read_only_under(""));
+        assert!(!overrides.is_read_only_under("other/"));
+        assert!(!overrides.is_read_only_under("arch"));
+
+        let rules = overrides.ttl_rules();
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].matches("archive/a.txt"));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let mount_prefix = Prefix::empty();
+        let invalid = |json: &str| PrefixOverrides::from_json(&mount_prefix, json).unwrap_err();
+        assert!(matches!(
+            invalid(r#"{ "archive": { "read-only": true } }"#),
+            PrefixOverridesError::InvalidPrefix(..)
+        ));
+        assert!(matches!(
+            invalid(r#"{ "": { "read-only": true } }"#),
+            PrefixOverridesError::EmptyPrefix
+        ));
+        assert!(matches!(
+            invalid(r#"{ "archive/": { "readonly": true } }"#),
+            PrefixOverridesError::InvalidJson(..)
+        ));
+        assert!(matches!(
+            invalid(r#"{ "archive/": { "metadata-ttl": "1w" } }"#),
+            PrefixOverridesError::InvalidJson(..)
+        ));
+    }
+}
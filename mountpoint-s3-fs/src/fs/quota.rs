@@ -0,0 +1,119 @@
+//! Limits on the size of files and on the amount of data written through the mount.
+//!
+//! A runaway job writing through the mount could otherwise upload unbounded data. With
+//! [crate::S3FilesystemConfig::max_file_size], writes that would make a file larger fail with `EFBIG`, and with
+//! [crate::S3FilesystemConfig::max_write_bytes], writes fail with `EDQUOT` once that many bytes have been written to
+//! files since the file system was mounted. Both are checked before the data reaches the upload of the file, which is
+//! left as it was, so that the file can still be closed and uploaded.
+
+use thiserror::Error;
+
+use crate::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Error)]
+pub enum QuotaError {
+    #[error("file would be {size} bytes, larger than the maximum file size of {max_file_size} bytes")]
+    FileTooLarge { size: u64, max_file_size: u64 },
+    #[error(
+        "writing {len} bytes would exceed the write quota of {max_write_bytes} bytes, {written} are already written"
+    )]
+    WriteQuotaExceeded {
+        len: u64,
+        written: u64,
+        max_write_bytes: u64,
+    },
+}
+
+/// The limits of a mount and the bytes written through it so far.
+#[derive(Debug)]
+pub(super) struct WriteQuota {
+    max_file_size: Option<u64>,
+    max_write_bytes: Option<u64>,
+    written: AtomicU64,
+}
+
+impl WriteQuota {
+    pub fn new(max_file_size: Option<u64>, max_write_bytes: Option<u64>) -> Self {
+        Self {
+            max_file_size,
+            max_write_bytes,
+            written: AtomicU64::new(0),
+        }
+    }
+
+    /// Check that a file can be `size` bytes large.
+    pub fn check_file_size(&self, size: u64) -> Result<(), QuotaError> {
+        match self.max_file_size {
+            Some(max_file_size) if size > max_file_size => {
+                metrics::counter!("fs.quota_exceeded", "quota" => "file_size").increment(1);
+                Err(QuotaError::FileTooLarge { size, max_file_size })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Count `len` bytes about to be written against the write quota. The bytes that end up not being written should
+    /// be given back with [Self::release].
+    pub fn reserve(&self, len: u64) -> Result<(), QuotaError> {
+        let Some(max_write_bytes) = self.max_write_bytes else {
+            return Ok(());
+        };
+        self.written
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |written| {
+                written.checked_add(len).filter(|&written| written <= max_write_bytes)
+            })
+            .map(|_| ())
+            .map_err(|written| {
+                metrics::counter!("fs.quota_exceeded", "quota" => "write_bytes").increment(1);
+                QuotaError::WriteQuotaExceeded {
+                    len,
+                    written,
+                    max_write_bytes,
+                }
+            })
+    }
+
+    /// Give back `len` bytes reserved with [Self::reserve] but not written.
+    pub fn release(&self, len: u64) {
+        if self.max_write_bytes.is_some() && len > 0 {
+            self.written.fetch_sub(len, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_file_size() {
+        let quota = WriteQuota::new(Some(10), None);
+        quota.check_file_size(10).unwrap();
+        assert!(matches!(
+            quota.check_file_size(11),
+            Err(QuotaError::FileTooLarge {
+                size: 11,
+                max_file_size: 10
+            })
+        ));
+        WriteQuota::new(None, None).check_file_size(u64::MAX).unwrap();
+    }
+
+    #[test]
+    fn test_reserve() {
+        let quota = WriteQuota::new(None, Some(10));
+        quota.reserve(6).unwrap();
+        assert!(matches!(
+            quota.reserve(5),
+            Err(QuotaError::WriteQuotaExceeded { written: 6, .. })
+        ));
+        quota.reserve(4).unwrap();
+        quota.release(3);
+        quota.reserve(3).unwrap();
+        assert!(quota.reserve(1).is_err());
+        assert!(
+            quota.reserve(u64::MAX).is_err(),
+            "overflowing reservations are rejected"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Reporting the total size of the objects under directories as an extended attribute.
+//!
+//! Tools like `du` walk a directory and stat every file under it, which takes a lookup for each of them. With
+//! [crate::S3FilesystemConfig::recursive_size_xattr], the [RECURSIVE_SIZE_XATTR] extended attribute of a directory
+//! holds the sum of the sizes of the objects under its prefix instead, aggregated from a recursive listing with
+//! ListObjectsV2, which returns 1000 objects per request. The sizes are cached for [RECURSIVE_SIZE_VALIDITY] by
+//! prefix, so that tools reading the attribute of a directory and then of its subdirectories only list them once.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::metablock::Expiry;
+use crate::sync::Mutex;
+
+/// Name of the extended attribute holding the total size in bytes of the objects under a directory, see
+/// [crate::S3FilesystemConfig::recursive_size_xattr].
+pub const RECURSIVE_SIZE_XATTR: &str = "user.s3.recursive_size";
+
+/// How long the total size of the objects under a prefix is reused for.
+pub(super) const RECURSIVE_SIZE_VALIDITY: Duration = Duration::from_secs(10);
+
+/// The total size of the objects under the prefixes of directories, by prefix.
+pub(super) struct RecursiveSizeCache {
+    entries: Mutex<HashMap<String, CachedSize>>,
+}
+
+struct CachedSize {
+    size: u64,
+    expiry: Expiry,
+}
+
+impl RecursiveSizeCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The total size of the objects under a prefix, if it was listed recently enough.
+    pub fn get(&self, prefix: &str) -> Option<u64> {
+        let entries = self.entries.lock().unwrap();
+        let cached = entries.get(prefix)?;
+        (!cached.expiry.is_expired()).then_some(cached.size)
+    }
+
+    pub fn insert(&self, prefix: &str, size: u64, validity: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, cached| !cached.expiry.is_expired());
+        let cached = CachedSize {
+            size,
+            expiry: Expiry::from_now(validity),
+        };
+        entries.insert(prefix.to_owned(), cached);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_sizes() {
+        let cache = RecursiveSizeCache::new();
+        cache.insert("dir/", 100, Duration::from_secs(60));
+        cache.insert("dir/sub/", 10, Duration::ZERO);
+
+        assert_eq!(cache.get("dir/"), Some(100));
+        assert_eq!(cache.get("dir/sub/"), None, "expired sizes should not be returned");
+        assert_eq!(cache.get("other/"), None);
+
+        cache.insert("other/", 0, Duration::from_secs(60));
+        assert_eq!(cache.get("other/"), Some(0));
+        assert_eq!(
+            cache.entries.lock().unwrap().len(),
+            2,
+            "expired sizes should be evicted"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Resolution of the collisions between the keys of files and directories.
+//!
+//! When a bucket has both the keys `foo` and `foo/bar`, `foo` can't be both a file and a directory. By default the
+//! directory shadows the file, which is not accessible. With [ShadowingPolicy::FileWins] the file shadows the
+//! directory instead, and with [ShadowingPolicy::SuffixFile] the file is shown next to the directory, as
+//! `foo.shadowed`. These files can be read and removed, but not written or renamed.
+
+/// Suffix of the name the file of a key shadowed by a directory is shown with, see [ShadowingPolicy::SuffixFile].
+pub const SHADOWED_FILE_SUFFIX: &str = ".shadowed";
+
+/// How to resolve the collision between a file and a directory with the same name, see
+/// [crate::SuperblockConfig::shadowing].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShadowingPolicy {
+    /// Show the directory, and hide the file.
+    #[default]
+    DirectoryWins,
+    /// Show the file, and hide the directory.
+    FileWins,
+    /// Show the directory, and the file with the [SHADOWED_FILE_SUFFIX] appended to its name.
+    SuffixFile,
+}
+
+/// Key of the file shown with the [SHADOWED_FILE_SUFFIX] at the given key.
+pub(crate) fn shadowed_file_key(key: &str) -> Option<&str> {
+    let file_key = key.strip_suffix(SHADOWED_FILE_SUFFIX)?;
+    (!file_key.is_empty() && !file_key.ends_with('/')).then_some(file_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("dir/foo.shadowed", Some("dir/foo"); "nested")]
+    #[test_case("foo.shadowed", Some("foo"); "top level")]
+    #[test_case("dir/.shadowed", None; "no file name")]
+    #[test_case(".shadowed", None; "no file name at top level")]
+    #[test_case("dir/foo", None; "regular file")]
+    fn test_shadowed_file_key(key: &str, expected: Option<&str>) {
+        assert_eq!(shadowed_file_key(key), expected);
+    }
+}
@@ -0,0 +1,107 @@
+//! The capacity and free space reported by `statfs`.
+//!
+//! S3 has no capacity, so by default the file system reports an effectively unlimited one, all of it free. Some
+//! applications check the free space before writing and are confused by such numbers, so a fixed capacity, or the one
+//! of a local file system such as the one holding the cache directory, can be reported instead.
+
+use std::path::PathBuf;
+
+use super::{Error, StatFs};
+
+/// Number of free inodes reported, as S3 has no limit on the number of objects.
+const FREE_INODES: u64 = u64::MAX / 1024;
+
+/// What `statfs` reports as the capacity and free space of the file system, see
+/// [crate::S3FilesystemConfig::statfs_capacity].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StatfsCapacity {
+    /// Report an unlimited capacity, all of it free.
+    #[default]
+    Unlimited,
+    /// Report the given capacity and free space, in bytes.
+    Fixed { total: u64, free: u64 },
+    /// Report the capacity and free space of the local file system holding the given directory, such as the cache
+    /// directory.
+    Directory(PathBuf),
+}
+
+impl StatfsCapacity {
+    pub(super) async fn statfs(&self) -> Result<StatFs, Error> {
+        let default = StatFs {
+            free_inodes: FREE_INODES,
+            total_inodes: FREE_INODES,
+            ..Default::default()
+        };
+        let reply = match self {
+            StatfsCapacity::Unlimited => {
+                const FREE_BLOCKS: u64 = u64::MAX / 1024;
+                StatFs {
+                    free_blocks: FREE_BLOCKS,
+                    available_blocks: FREE_BLOCKS,
+                    total_blocks: FREE_BLOCKS,
+                    ..default
+                }
+            }
+            StatfsCapacity::Fixed { total, free } => {
+                let block_size = u64::from(default.block_size);
+                StatFs {
+                    total_blocks: total / block_size,
+                    free_blocks: free / block_size,
+                    available_blocks: free / block_size,
+                    ..default
+                }
+            }
+            StatfsCapacity::Directory(path) => {
+                let stats = nix::sys::statvfs::statvfs(path).map_err(|e| {
+                    err!(
+                        libc::EIO,
+                        source: e,
+                        "unable to determine the space of the file system holding {:?}",
+                        path
+                    )
+                })?;
+                // The blocks are counted in units of the fragment size.
+                StatFs {
+                    total_blocks: stats.blocks(),
+                    free_blocks: stats.blocks_free(),
+                    available_blocks: stats.blocks_available(),
+                    block_size: stats.block_size() as u32,
+                    fragment_size: stats.fragment_size() as u32,
+                    ..default
+                }
+            }
+        };
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixed() {
+        let capacity = StatfsCapacity::Fixed {
+            total: 1024 * 1024,
+            free: 512 * 1024,
+        };
+        let statfs = capacity.statfs().await.unwrap();
+        let block_size = u64::from(statfs.block_size);
+        assert_eq!(statfs.total_blocks * block_size, 1024 * 1024);
+        assert_eq!(statfs.free_blocks * block_size, 512 * 1024);
+        assert_eq!(statfs.available_blocks * block_size, 512 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let stats = nix::sys::statvfs::statvfs(dir.path()).unwrap();
+        let statfs = StatfsCapacity::Directory(dir.path().to_owned()).statfs().await.unwrap();
+        assert_eq!(statfs.total_blocks, stats.blocks());
+        assert_eq!(u64::from(statfs.fragment_size), stats.fragment_size());
+
+        let missing = StatfsCapacity::Directory(dir.path().join("missing"));
+        let error = missing.statfs().await.expect_err("missing directory should fail");
+        assert_eq!(error.errno, libc::EIO);
+    }
+}
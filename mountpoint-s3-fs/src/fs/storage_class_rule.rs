@@ -0,0 +1,141 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use regex::Regex;
+use thiserror::Error;
+
+use super::glob_regex;
+use crate::s3::Prefix;
+
+/// Storage class of the objects created with keys matching a pattern, see [StorageClassRules].
+///
+/// Patterns are matched against whole keys relative to the mounted prefix, like the patterns of
+/// [super::MetadataTtlRule]: `**` matches any characters, `*` any characters other than `/`, and `?` a single character
+/// other than `/`. For example, `logs/**` matches every object under the `logs` directory, and `*.ckpt` the checkpoint
+/// files at the root of the mount.
+#[derive(Debug, Clone)]
+pub struct StorageClassRule {
+    pattern: String,
+    regex: Regex,
+    storage_class: String,
+}
+
+#[derive(Error, Debug)]
+pub enum StorageClassRuleError {
+    #[error("storage class rule must be of the form PATTERN=STORAGE_CLASS")]
+    MissingStorageClass,
+}
+
+impl StorageClassRule {
+    pub fn new(pattern: &str, storage_class: &str) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            regex: glob_regex(pattern),
+            storage_class: storage_class.to_owned(),
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn storage_class(&self) -> &str {
+        &self.storage_class
+    }
+
+    /// Whether the rule applies to the given key, relative to the mounted prefix.
+    pub fn matches(&self, key: &str) -> bool {
+        self.regex.is_match(key)
+    }
+}
+
+impl Display for StorageClassRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.pattern, self.storage_class)
+    }
+}
+
+impl FromStr for StorageClassRule {
+    type Err = StorageClassRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, storage_class) = s
+            .rsplit_once('=')
+            .filter(|(_, storage_class)| !storage_class.is_empty())
+            .ok_or(StorageClassRuleError::MissingStorageClass)?;
+        Ok(Self::new(pattern, storage_class))
+    }
+}
+
+/// Rules choosing the storage class of new objects by their key, in place of
+/// [super::S3FilesystemConfig::storage_class]. The first rule matching a key applies.
+#[derive(Debug, Clone, Default)]
+pub struct StorageClassRules {
+    /// The mounted prefix, which the patterns of the rules are relative to.
+    mount_prefix: String,
+    rules: Vec<StorageClassRule>,
+}
+
+impl StorageClassRules {
+    /// Rules matching keys relative to `mount_prefix`, in the order they apply.
+    pub fn new(mount_prefix: &Prefix, rules: Vec<StorageClassRule>) -> Self {
+        Self {
+            mount_prefix: mount_prefix.as_str().to_owned(),
+            rules,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The storage class of the first rule matching the object with the given key in the bucket, if any.
+    pub fn for_object_key(&self, key: &str) -> Option<&str> {
+        let key = key.strip_prefix(&self.mount_prefix)?;
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(key))
+            .map(StorageClassRule::storage_class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_object_key() {
+        let rules = StorageClassRules::new(
+            &Prefix::new("data/").unwrap(),
+            vec![
+                "logs/**=STANDARD_IA".parse().unwrap(),
+                "*.ckpt=INTELLIGENT_TIERING".parse().unwrap(),
+                "**=GLACIER_IR".parse().unwrap(),
+            ],
+        );
+        assert_eq!(rules.for_object_key("data/logs/2024/app.log"), Some("STANDARD_IA"));
+        assert_eq!(rules.for_object_key("data/logs/model.ckpt"), Some("STANDARD_IA"));
+        assert_eq!(rules.for_object_key("data/model.ckpt"), Some("INTELLIGENT_TIERING"));
+        assert_eq!(rules.for_object_key("data/models/model.ckpt"), Some("GLACIER_IR"));
+        assert_eq!(rules.for_object_key("other/logs/app.log"), None, "outside of the mount");
+        assert!(StorageClassRules::default().for_object_key("logs/app.log").is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule: StorageClassRule = "logs/**=STANDARD_IA".parse().unwrap();
+        assert_eq!(rule.pattern(), "logs/**");
+        assert_eq!(rule.storage_class(), "STANDARD_IA");
+        assert_eq!(rule.to_string(), "logs/**=STANDARD_IA");
+
+        let rule: StorageClassRule = "a=b/*=GLACIER".parse().unwrap();
+        assert_eq!(rule.pattern(), "a=b/*");
+
+        "logs/**"
+            .parse::<StorageClassRule>()
+            .expect_err("the storage class is required");
+        "logs/**="
+            .parse::<StorageClassRule>()
+            .expect_err("the storage class can't be empty");
+    }
+}
@@ -0,0 +1,92 @@
+//! Reading and writing the tags of objects as extended attributes.
+//!
+//! The tags of the object of a file are exposed as extended attributes named with [TAG_XATTR_PREFIX] followed by the
+//! key of the tag. They are fetched with GetObjectTagging the first time they are accessed, and cached for as long as
+//! the metadata of the file. Setting or removing one of them replaces the tags of the object with PutObjectTagging.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mountpoint_s3_client::types::ObjectTags;
+
+use crate::metablock::{Expiry, InodeNo};
+use crate::sync::{AsyncMutex, Mutex};
+
+/// Prefix of the names of the extended attributes holding the tags of objects, see
+/// [crate::S3FilesystemConfig::tag_xattrs].
+pub const TAG_XATTR_PREFIX: &str = "user.s3.tag.";
+
+/// Maximum number of tags of an object.
+pub(super) const MAX_TAGS: usize = 10;
+
+/// The tags of the objects of files accessed through their extended attributes, by inode.
+pub(super) struct ObjectTagsCache {
+    entries: Mutex<HashMap<InodeNo, CachedTags>>,
+    /// Serializes the updates of tags, which replace all the tags of an object at once.
+    updates: AsyncMutex<()>,
+}
+
+struct CachedTags {
+    /// ETag of the object the tags were fetched for.
+    etag: Box<str>,
+    tags: ObjectTags,
+    expiry: Expiry,
+}
+
+impl ObjectTagsCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            updates: AsyncMutex::new(()),
+        }
+    }
+
+    /// The cached tags of an inode, if they are still valid and were fetched for the object with the given ETag.
+    pub fn get(&self, ino: InodeNo, etag: &str) -> Option<ObjectTags> {
+        let entries = self.entries.lock().unwrap();
+        let cached = entries.get(&ino)?;
+        (*cached.etag == *etag && !cached.expiry.is_expired()).then(|| cached.tags.clone())
+    }
+
+    pub fn insert(&self, ino: InodeNo, etag: &str, tags: ObjectTags, validity: Duration) {
+        let cached = CachedTags {
+            etag: etag.into(),
+            tags,
+            expiry: Expiry::from_now(validity),
+        };
+        self.entries.lock().unwrap().insert(ino, cached);
+    }
+
+    pub fn remove(&self, ino: InodeNo) {
+        self.entries.lock().unwrap().remove(&ino);
+    }
+
+    /// Wait for the other updates of tags to complete. Updates should hold the returned guard until they are done.
+    pub async fn lock_updates(&self) -> async_lock::MutexGuard<'_, ()> {
+        self.updates.lock().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_tags() {
+        let cache = ObjectTagsCache::new();
+        let tags = ObjectTags::from([("project".to_owned(), "mountpoint".to_owned())]);
+        cache.insert(2, "etag0", tags.clone(), Duration::from_secs(60));
+        cache.insert(3, "etag0", tags.clone(), Duration::ZERO);
+
+        assert_eq!(cache.get(2, "etag0"), Some(tags));
+        assert_eq!(
+            cache.get(2, "etag1"),
+            None,
+            "tags of another object should not be returned"
+        );
+        assert_eq!(cache.get(3, "etag0"), None, "expired tags should not be returned");
+
+        cache.remove(2);
+        assert_eq!(cache.get(2, "etag0"), None);
+    }
+}
@@ -12,7 +12,9 @@ pub enum TimeToLive {
 
 #[derive(Error, Debug)]
 pub enum TimeToLiveError {
-    #[error("TTL must be a valid number of seconds, or 'indefinite', or 'minimal'")]
+    #[error(
+        "TTL must be a valid number of seconds, or a number followed by s, m, h or d, or 'indefinite', or 'minimal'"
+    )]
     InvalidInt(#[from] ParseIntError),
     #[error(
         "TTL must not be greater than {}s (~{} years), or be 'indefinite', or 'minimal'",
@@ -38,7 +40,20 @@ impl TimeToLive {
             Self::MINIMAL => Ok(Self::Minimal),
             Self::INDEFINITE => Ok(Self::Indefinite),
             _ => {
-                let seconds = s.parse()?;
+                let (number, unit) = match s.strip_suffix(['s', 'm', 'h', 'd']) {
+                    Some(number) => (number, &s[number.len()..]),
+                    None => (s, "s"),
+                };
+                let unit_seconds = match unit {
+                    "m" => 60,
+                    "h" => 60 * 60,
+                    "d" => 24 * 60 * 60,
+                    _ => 1,
+                };
+                let seconds = number
+                    .parse::<u64>()?
+                    .checked_mul(unit_seconds)
+                    .ok_or(TimeToLiveError::TooLarge)?;
                 if seconds > Self::MAXIMUM_TTL_SECONDS {
                     return Err(TimeToLiveError::TooLarge);
                 }
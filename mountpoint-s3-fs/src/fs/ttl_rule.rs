@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use regex::Regex;
+use thiserror::Error;
+
+use super::TimeToLive;
+use super::time_to_live::TimeToLiveError;
+
+/// Metadata TTL of the files and directories whose keys match a pattern, see [super::CacheConfig::ttl_rules].
+///
+/// Patterns are matched against whole keys relative to the mounted prefix, where the keys of directories end with `/`.
+/// In patterns, `**` matches any characters, `*` any characters other than `/`, and `?` a single character other than
+/// `/`. For example, `static-data/**` matches the `static-data` directory and everything under it, and `*.json` matches
+/// the JSON files at the root of the mount.
+#[derive(Debug, Clone)]
+pub struct MetadataTtlRule {
+    pattern: String,
+    regex: Regex,
+    ttl: TimeToLive,
+}
+
+#[derive(Error, Debug)]
+pub enum MetadataTtlRuleError {
+    #[error("TTL rule must be of the form PATTERN=TTL")]
+    MissingTtl,
+    #[error(transparent)]
+    InvalidTtl(#[from] TimeToLiveError),
+}
+
+impl MetadataTtlRule {
+    pub fn new(pattern: &str, ttl: TimeToLive) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            regex: glob_regex(pattern),
+            ttl,
+        }
+    }
+
+    /// A rule applying to the files and directories under the given prefix, relative to the mounted prefix. Unlike
+    /// the patterns of [Self::new], the prefix is matched literally.
+    pub fn for_prefix(prefix: &str, ttl: TimeToLive) -> Self {
+        let regex = format!("(?s)^{}", regex::escape(prefix));
+        Self {
+            pattern: format!("{prefix}**"),
+            regex: Regex::new(&regex).expect("escaped prefix should be a valid regex"),
+            ttl,
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn ttl(&self) -> TimeToLive {
+        self.ttl
+    }
+
+    /// Whether the rule applies to the given key, relative to the mounted prefix.
+    pub fn matches(&self, key: &str) -> bool {
+        self.regex.is_match(key)
+    }
+}
+
+/// The regex matching the same keys as a pattern, where `**` matches any characters, `*` any characters other than
+/// `/`, and `?` a single character other than `/`.
+pub(crate) fn glob_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("(?s)^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.next_if_eq(&'*').is_some() => regex.push_str(".*"),
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped pattern should be a valid regex")
+}
+
+impl Display for MetadataTtlRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.pattern, self.ttl)
+    }
+}
+
+impl FromStr for MetadataTtlRule {
+    type Err = MetadataTtlRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, ttl) = s.rsplit_once('=').ok_or(MetadataTtlRuleError::MissingTtl)?;
+        Ok(Self::new(pattern, ttl.parse()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("static-data/**", "static-data/", true; "directory of a recursive pattern")]
+    #[test_case("static-data/**", "static-data/a/b.bin", true; "nested file of a recursive pattern")]
+    #[test_case("static-data/**", "static-data-2/a", false; "sibling of a recursive pattern")]
+    #[test_case("*.json", "a.json", true; "file of a wildcard")]
+    #[test_case("*.json", "a/b.json", false; "nested file of a wildcard")]
+    #[test_case("logs/day-?/", "logs/day-1/", true; "single character")]
+    #[test_case("logs/day-?/", "logs/day-10/", false; "more characters")]
+    #[test_case("a+b/(c)", "a+b/(c)", true; "regex characters")]
+    fn test_matches(pattern: &str, key: &str, matches: bool) {
+        let rule = MetadataTtlRule::new(pattern, TimeToLive::Indefinite);
+        assert_eq!(rule.matches(key), matches);
+    }
+
+    #[test]
+    fn test_for_prefix() {
+        let rule = MetadataTtlRule::for_prefix("logs/*/", TimeToLive::Indefinite);
+        assert_eq!(rule.pattern(), "logs/*/**");
+        assert!(rule.matches("logs/*/"));
+        assert!(rule.matches("logs/*/a.txt"));
+        assert!(!rule.matches("logs/day-1/a.txt"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule: MetadataTtlRule = "static-data/**=1h".parse().unwrap();
+        assert_eq!(rule.pattern(), "static-data/**");
+        assert!(matches!(rule.ttl(), TimeToLive::Duration(ttl) if ttl == Duration::from_secs(3600)));
+        assert_eq!(rule.to_string(), "static-data/**=3600s");
+
+        let rule: MetadataTtlRule = "a=b/**=minimal".parse().unwrap();
+        assert_eq!(rule.pattern(), "a=b/**");
+        assert!(matches!(rule.ttl(), TimeToLive::Minimal));
+
+        "static-data/**"
+            .parse::<MetadataTtlRule>()
+            .expect_err("the TTL is required");
+        "static-data/**=1w"
+            .parse::<MetadataTtlRule>()
+            .expect_err("weeks are not a unit");
+    }
+}
@@ -0,0 +1,47 @@
+//! Synthetic directories holding the prior versions of objects in versioned buckets.
+//!
+//! With [crate::SuperblockConfig::version_directories], looking up `foo.txt.versions` in the directory of an object
+//! `foo.txt` with versions returns a directory listing each of its versions as a file named by its version ID, such
+//! as `foo.txt.versions/3HL4kqtJlcpXroDTDmJ.rHSiXdjvpPD_`. These files are read from the version of `foo.txt` they
+//! name, and can not be written, removed or renamed.
+
+/// Suffix of the name of the synthetic directory holding the versions of an object, see
+/// [crate::SuperblockConfig::version_directories].
+pub const VERSIONS_DIR_SUFFIX: &str = ".versions";
+
+/// Key of the object whose versions are held in the synthetic directory with the given key, ending in `/`.
+pub(crate) fn versions_dir_object(dir_key: &str) -> Option<&str> {
+    let object_key = dir_key.strip_suffix('/')?.strip_suffix(VERSIONS_DIR_SUFFIX)?;
+    let name_start = object_key.rfind('/').map_or(0, |i| i + 1);
+    (name_start < object_key.len()).then_some(object_key)
+}
+
+/// Key of the object the file with the given key is a version of, if it is in a synthetic versions directory.
+pub(crate) fn version_object_key(file_key: &str) -> Option<&str> {
+    let dir_end = file_key.rfind('/')? + 1;
+    versions_dir_object(&file_key[..dir_end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("foo.txt.versions/", Some("foo.txt"); "top level")]
+    #[test_case("dir/foo.txt.versions/", Some("dir/foo.txt"); "nested")]
+    #[test_case("dir/.versions/", None; "no object name")]
+    #[test_case(".versions/", None; "no object name at top level")]
+    #[test_case("dir/foo.txt/", None; "regular directory")]
+    #[test_case("dir/foo.txt.versions", None; "not a directory")]
+    fn test_versions_dir_object(dir_key: &str, expected: Option<&str>) {
+        assert_eq!(versions_dir_object(dir_key), expected);
+    }
+
+    #[test_case("dir/foo.txt.versions/v1", Some("dir/foo.txt"); "version")]
+    #[test_case("foo.txt.versions/v1", Some("foo.txt"); "top level version")]
+    #[test_case("dir/foo.txt", None; "regular file")]
+    #[test_case("foo.txt", None; "top level file")]
+    fn test_version_object_key(file_key: &str, expected: Option<&str>) {
+        assert_eq!(version_object_key(file_key), expected);
+    }
+}
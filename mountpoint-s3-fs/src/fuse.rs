@@ -11,17 +11,24 @@ use tracing::{Instrument, field, instrument};
 use crate::fs::{
     DirectoryEntry, DirectoryReplier, InodeNo, S3Filesystem, ToErrno, error_metadata::MOUNTPOINT_EVENT_READY,
 };
+use crate::metablock::Invalidations;
 use crate::metrics::defs::{ATTR_FUSE_REQUEST, FUSE_IO_SIZE, FUSE_REQUEST_ERRORS};
 #[cfg(target_os = "macos")]
 use fuser::ReplyXTimes;
 use fuser::{
-    Filesystem, KernelConfig, ReplyAttr, ReplyBmap, ReplyCreate, ReplyData, ReplyEmpty, ReplyEntry, ReplyIoctl,
-    ReplyLock, ReplyLseek, ReplyOpen, ReplyStatfs, ReplyWrite, ReplyXattr, Request, TimeOrNow,
+    Filesystem, KernelConfig, Notifier, ReplyAttr, ReplyBmap, ReplyCreate, ReplyData, ReplyEmpty, ReplyEntry,
+    ReplyIoctl, ReplyLock, ReplyLseek, ReplyOpen, ReplyStatfs, ReplyWrite, ReplyXattr, Request, TimeOrNow,
 };
 
 pub mod config;
 pub mod session;
 
+/// Error returned for extended attributes that don't exist.
+#[cfg(target_os = "macos")]
+const ENOATTR: libc::c_int = libc::ENOATTR;
+#[cfg(not(target_os = "macos"))]
+const ENOATTR: libc::c_int = libc::ENODATA;
+
 /// A trait that can be implemented to log errors returned by fuse operations.
 pub trait ErrorLogger: std::fmt::Debug {
     /// Log an error returned by a fuse operation.
@@ -78,6 +85,20 @@ macro_rules! fuse_unsupported {
     };
 }
 
+/// Drop the entries of the kernel caches made stale by a change in S3, see [crate::metablock::Invalidations].
+pub(crate) fn invalidate_kernel_caches(notifier: &Notifier, invalidations: &Invalidations) {
+    for (parent, name) in &invalidations.entries {
+        if let Err(error) = notifier.inval_entry(*parent, name) {
+            tracing::debug!(parent, ?name, ?error, "failed to invalidate kernel directory entry");
+        }
+    }
+    for &ino in &invalidations.inodes {
+        if let Err(error) = notifier.inval_inode(ino, 0, 0) {
+            tracing::debug!(ino, ?error, "failed to invalidate kernel inode");
+        }
+    }
+}
+
 /// This is just a thin wrapper around [S3Filesystem] that implements the actual `fuser` protocol,
 /// so that we can test our actual filesystem implementation without having actual FUSE in the loop.
 pub struct S3FuseFilesystem<Client>
@@ -370,7 +391,7 @@ where
         &self,
         req: &Request<'_>,
         ino: u64,
-        _mode: Option<u32>,
+        mode: Option<u32>,
         _uid: Option<u32>,
         _gid: Option<u32>,
         size: Option<u64>,
@@ -392,25 +413,106 @@ where
             TimeOrNow::SpecificTime(st) => OffsetDateTime::from(st),
             TimeOrNow::Now => OffsetDateTime::now_utc(),
         });
-        match block_on(self.fs.setattr(ino, atime, mtime, size, flags).in_current_span()) {
+        match block_on(self.fs.setattr(ino, mode, atime, mtime, size, flags).in_current_span()) {
             Ok(attr) => reply.attr(&attr.ttl, &attr.attr),
             Err(e) => fuse_error!("setattr", reply, e, self, req),
         }
     }
 
-    // Everything below here is stubs for unsupported functions so we log them correctly
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino, name=?name))]
+    fn getxattr(&self, req: &Request<'_>, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        if !self.fs.xattrs_supported() {
+            fuse_unsupported!("getxattr", reply);
+            return;
+        }
+        match block_on(self.fs.getxattr(ino, name).in_current_span()) {
+            // A size of 0 asks for the size of the value, to allocate a buffer for it.
+            Ok(Some(value)) if size == 0 => reply.size(value.len() as u32),
+            Ok(Some(value)) if value.len() > size as usize => reply.error(libc::ERANGE),
+            Ok(Some(value)) => reply.data(&value),
+            Ok(None) => reply.error(ENOATTR),
+            Err(e) => fuse_error!("getxattr", reply, e, self, req),
+        }
+    }
 
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino))]
-    fn readlink(&self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
-        fuse_unsupported!("readlink", reply);
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino, name=?name))]
+    fn setxattr(
+        &self,
+        req: &Request<'_>,
+        ino: u64,
+        name: &OsStr,
+        value: &[u8],
+        _flags: i32,
+        _position: u32,
+        reply: ReplyEmpty,
+    ) {
+        if !self.fs.xattrs_supported() {
+            fuse_unsupported!("setxattr", reply);
+            return;
+        }
+        match block_on(self.fs.setxattr(ino, name, value).in_current_span()) {
+            Ok(()) => reply.ok(),
+            Err(e) => fuse_error!("setxattr", reply, e, self, req),
+        }
     }
 
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), parent=parent, name=?name, link=?link))]
-    fn symlink(&self, _req: &Request<'_>, parent: u64, name: &OsStr, link: &Path, reply: ReplyEntry) {
-        // Userspace expects EPERM for link/symlink if unsupported
-        fuse_unsupported!("symlink", reply, libc::EPERM);
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino, name=?name))]
+    fn removexattr(&self, req: &Request<'_>, ino: u64, name: &OsStr, reply: ReplyEmpty) {
+        if !self.fs.xattrs_supported() {
+            fuse_unsupported!("removexattr", reply);
+            return;
+        }
+        match block_on(self.fs.removexattr(ino, name).in_current_span()) {
+            Ok(true) => reply.ok(),
+            Ok(false) => reply.error(ENOATTR),
+            Err(e) => fuse_error!("removexattr", reply, e, self, req),
+        }
+    }
+
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino))]
+    fn listxattr(&self, req: &Request<'_>, ino: u64, size: u32, reply: ReplyXattr) {
+        if !self.fs.xattrs_supported() {
+            fuse_unsupported!("listxattr", reply);
+            return;
+        }
+        match block_on(self.fs.listxattr(ino).in_current_span()) {
+            Ok(names) => {
+                // The names are returned as a list of null-terminated strings.
+                let mut value = Vec::new();
+                for name in names {
+                    value.extend_from_slice(name.as_encoded_bytes());
+                    value.push(0);
+                }
+                if size == 0 {
+                    reply.size(value.len() as u32);
+                } else if value.len() > size as usize {
+                    reply.error(libc::ERANGE);
+                } else {
+                    reply.data(&value);
+                }
+            }
+            Err(e) => fuse_error!("listxattr", reply, e, self, req),
+        }
+    }
+
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino))]
+    fn readlink(&self, req: &Request<'_>, ino: u64, reply: ReplyData) {
+        match block_on(self.fs.readlink(ino).in_current_span()) {
+            Ok(target) => reply.data(target.as_encoded_bytes()),
+            Err(e) => fuse_error!("readlink", reply, e, self, req),
+        }
     }
 
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), parent=parent, name=?name, link=?link))]
+    fn symlink(&self, req: &Request<'_>, parent: u64, name: &OsStr, link: &Path, reply: ReplyEntry) {
+        match block_on(self.fs.symlink(parent, name, link).in_current_span()) {
+            Ok(entry) => reply.entry(&entry.ttl, &entry.attr, entry.generation),
+            Err(e) => fuse_error!("symlink", reply, e, self, req),
+        }
+    }
+
+    // Everything below here is stubs for unsupported functions so we log them correctly
+
     #[instrument(level="warn", skip_all, fields(req=req.unique(), parent=parent, name=?name, newparent=newparent, newname=?newname))]
     fn rename(
         &self,
@@ -443,35 +545,6 @@ where
         fuse_unsupported!("fsyncdir", reply);
     }
 
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, name=?name))]
-    fn setxattr(
-        &self,
-        _req: &Request<'_>,
-        ino: u64,
-        name: &OsStr,
-        _value: &[u8],
-        _flags: i32,
-        _position: u32,
-        reply: ReplyEmpty,
-    ) {
-        fuse_unsupported!("setxattr", reply);
-    }
-
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, name=?name))]
-    fn getxattr(&self, _req: &Request<'_>, ino: u64, name: &OsStr, _size: u32, reply: ReplyXattr) {
-        fuse_unsupported!("getxattr", reply);
-    }
-
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino))]
-    fn listxattr(&self, _req: &Request<'_>, ino: u64, _size: u32, reply: ReplyXattr) {
-        fuse_unsupported!("listxattr", reply);
-    }
-
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, name=?name))]
-    fn removexattr(&self, _req: &Request<'_>, ino: u64, name: &OsStr, reply: ReplyEmpty) {
-        fuse_unsupported!("removexattr", reply);
-    }
-
     #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, mask=mask))]
     fn access(&self, _req: &Request<'_>, ino: u64, mask: i32, reply: ReplyEmpty) {
         fuse_unsupported!("access", reply);
@@ -544,18 +617,12 @@ where
         fuse_unsupported!("ioctl", reply, libc::ENOSYS, tracing::Level::DEBUG);
     }
 
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, fh=fh, offset=offset, length=length))]
-    fn fallocate(
-        &self,
-        _req: &Request<'_>,
-        ino: u64,
-        fh: u64,
-        offset: i64,
-        length: i64,
-        _mode: i32,
-        reply: ReplyEmpty,
-    ) {
-        fuse_unsupported!("fallocate", reply);
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino=ino, fh=fh, offset=offset, length=length, mode=mode, name=field::Empty))]
+    fn fallocate(&self, req: &Request<'_>, ino: u64, fh: u64, offset: i64, length: i64, mode: i32, reply: ReplyEmpty) {
+        match block_on(self.fs.fallocate(ino, fh, offset, length, mode).in_current_span()) {
+            Ok(()) => reply.ok(),
+            Err(e) => fuse_error!("fallocate", reply, e, self, req),
+        }
     }
 
     #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino=ino, fh=fh, offset=offset, whence=whence))]
@@ -563,10 +630,10 @@ where
         fuse_unsupported!("lseek", reply);
     }
 
-    #[instrument(level="warn", skip_all, fields(req=_req.unique(), ino_in=ino_in, fh_in=fh_in, offset_in=offset_in, ino_out=ino_out, fh_out=fh_out, offset_out=offset_out, len=len))]
+    #[instrument(level="warn", skip_all, fields(req=req.unique(), ino_in=ino_in, fh_in=fh_in, offset_in=offset_in, ino_out=ino_out, fh_out=fh_out, offset_out=offset_out, len=len, name=field::Empty))]
     fn copy_file_range(
         &self,
-        _req: &Request<'_>,
+        req: &Request<'_>,
         ino_in: u64,
         fh_in: u64,
         offset_in: i64,
@@ -574,10 +641,18 @@ where
         fh_out: u64,
         offset_out: i64,
         len: u64,
-        _flags: u32,
+        flags: u32,
         reply: ReplyWrite,
     ) {
-        fuse_unsupported!("copy_file_range", reply);
+        let result = block_on(
+            self.fs
+                .copy_file_range(ino_in, fh_in, offset_in, ino_out, fh_out, offset_out, len, flags)
+                .in_current_span(),
+        );
+        match result {
+            Ok(len) => reply.written(len),
+            Err(e) => fuse_error!("copy_file_range", reply, e, self, req),
+        }
     }
 
     #[cfg(target_os = "macos")]
@@ -30,6 +30,8 @@ pub struct FuseOptions {
     pub allow_other: bool,
     /// UNSTABLE: Use clone_fd optimization?
     pub clone_fd: bool,
+    /// Maximum size in bytes of the read requests of the kernel
+    pub max_read: Option<u32>,
 }
 
 impl FuseSessionConfig {
@@ -52,12 +54,16 @@ impl FuseSessionConfig {
         if fuse_options.allow_other {
             options.push(MountOption::AllowOther);
         }
+        if let Some(max_read) = fuse_options.max_read {
+            options.push(MountOption::CUSTOM(format!("max_read={max_read}")));
+        }
 
         #[cfg(target_os = "linux")]
         if matches!(mount_point, MountPoint::FileDescriptor(_)) {
             let passed_mount_options = &[
                 (fuse_options.read_only, "--read-only"),
                 (fuse_options.auto_unmount, "--auto-unmount"),
+                (fuse_options.max_read.is_some(), "--max-fuse-request-size"),
             ]
             .iter()
             .filter(|o| o.0)
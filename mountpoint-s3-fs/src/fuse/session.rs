@@ -3,7 +3,7 @@ use std::io;
 use anyhow::Context;
 #[cfg(target_os = "linux")]
 use fuser::MountOption;
-use fuser::{Filesystem, Session, SessionUnmounter};
+use fuser::{Filesystem, Notifier, Session, SessionUnmounter};
 use tracing::{debug, error, info, trace, warn};
 
 use super::config::{FuseSessionConfig, MountPoint};
@@ -16,6 +16,8 @@ use crate::sync::thread::{self, JoinHandle};
 /// external shutdown.
 pub struct FuseSession {
     unmounter: SessionUnmounter,
+    /// Sends notifications to the kernel.
+    notifier: Notifier,
     /// Waits for thread termination or external shutdown.
     receiver: mpsc::Receiver<Message>,
     /// Send external shutdown signal.
@@ -73,6 +75,7 @@ impl FuseSession {
         );
 
         let unmounter = session.unmount_callable();
+        let notifier = session.notifier();
 
         let (tx, rx) = mpsc::channel();
 
@@ -120,12 +123,19 @@ impl FuseSession {
 
         Ok(Self {
             unmounter,
+            notifier,
             receiver: rx,
             sender: tx,
             on_close: Default::default(),
         })
     }
 
+    /// Handle to send notifications to the kernel, such as invalidations of its caches. Notifications must not be sent
+    /// from the handlers of FUSE requests, as the kernel may wait for the request to complete before processing them.
+    pub fn notifier(&self) -> Notifier {
+        self.notifier.clone()
+    }
+
     /// Add a new handler which is executed when this session is shutting down.
     pub fn run_on_close(&mut self, handler: OnClose) {
         self.on_close.push(handler);
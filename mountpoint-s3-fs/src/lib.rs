@@ -5,6 +5,7 @@ pub mod autoconfigure;
 pub mod checksums;
 mod config;
 pub mod data_cache;
+pub mod event_notifications;
 pub mod fs;
 pub mod fuse;
 pub mod logging;
@@ -25,7 +26,10 @@ pub mod upload;
 pub use async_util::Runtime;
 pub use config::MountpointConfig;
 pub use fs::{S3Filesystem, S3FilesystemConfig, ServerSideEncryption};
-pub use superblock::{Superblock, SuperblockConfig};
+pub use superblock::{
+    DirectoryRenameConfig, InodeTable, InventoryConfig, ReaddirReadahead, RecursiveDeleteConfig, Snapshot,
+    SnapshotError, Superblock, SuperblockConfig, TrashConfig,
+};
 
 /// Enable tracing and CRT logging when running unit tests.
 #[cfg(test)]
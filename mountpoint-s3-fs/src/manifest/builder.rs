@@ -51,6 +51,8 @@ pub enum InputManifestError {
 }
 
 /// ChannelConfig represents per-channel configuration, when multiple buckets are mounted.
+///
+/// A single channel with an empty `directory_name` is mounted at the root of the file system.
 #[derive(Debug, Deserialize)]
 pub struct ChannelConfig {
     pub directory_name: String,
@@ -272,22 +274,29 @@ impl ManifestBuilder {
         &mut self,
         channel_manifests: Vec<ChannelManifest<EntriesIterator>>,
     ) -> Result<(), InputManifestError> {
+        let single_channel = channel_manifests.len() == 1;
         for (channel_id, channel_manifest) in channel_manifests.into_iter().enumerate() {
-            // insert synthetic channel dir
-            let channel_root_id = self.next_id;
-            let channel_directory_name = ValidName::try_from(channel_manifest.directory_name.as_str())
-                .map_err(|_| InputManifestError::InvalidChannel(channel_manifest.directory_name.clone()))?;
-            self.insert_buffer.push(DbEntry::new(
-                channel_root_id,
-                ROOT_INODE_NO,
-                channel_id,
-                None,
-                channel_directory_name,
-                None,
-                None,
-                &channel_manifest.s3_path,
-            )?);
-            self.next_id += 1;
+            let channel_root_id = if single_channel && channel_manifest.directory_name.is_empty() {
+                // the only channel is mounted at the root
+                ROOT_INODE_NO
+            } else {
+                // insert synthetic channel dir
+                let channel_root_id = self.next_id;
+                let channel_directory_name = ValidName::try_from(channel_manifest.directory_name.as_str())
+                    .map_err(|_| InputManifestError::InvalidChannel(channel_manifest.directory_name.clone()))?;
+                self.insert_buffer.push(DbEntry::new(
+                    channel_root_id,
+                    ROOT_INODE_NO,
+                    channel_id,
+                    None,
+                    channel_directory_name,
+                    None,
+                    None,
+                    &channel_manifest.s3_path,
+                )?);
+                self.next_id += 1;
+                channel_root_id
+            };
 
             // insert keys from the manifest (and corresponding dirs)
             for entry in channel_manifest.entries {
@@ -387,7 +396,10 @@ impl ManifestBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::manifest::Manifest;
+    use crate::sync::Arc;
     use test_case::test_case;
+    use time::OffsetDateTime;
 
     const DUMMY_ETAG: &str = "\"3bebe4037c8f040e0e573e191d34b2c6\"";
     const DUMMY_SIZE: usize = 1024;
@@ -458,6 +470,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_root_channel() {
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("s3_keys.db3");
+        let entries = ["a.txt", "dir1/b.txt"]
+            .into_iter()
+            .map(|key| InputManifestEntry::new_without_checksum(key, DUMMY_ETAG, DUMMY_SIZE));
+        create_db(
+            &db_path,
+            vec![ChannelManifest {
+                directory_name: "".to_string(),
+                s3_path: S3Path::new(Bucket::new("bucket").unwrap(), Prefix::new("prefix/").unwrap()),
+                entries,
+            }],
+            1000,
+        )
+        .expect("db creation must succeed");
+
+        let manifest = Manifest::new(&db_path).unwrap();
+        let channels: Vec<_> = manifest.load_channels().unwrap().into_iter().map(Arc::new).collect();
+        let lookup = |parent_id, name| {
+            manifest
+                .manifest_lookup(parent_id, name)
+                .unwrap()
+                .expect("entry should exist")
+                .into_lookup(&channels, OffsetDateTime::now_utc())
+                .unwrap()
+        };
+        let file = lookup(ROOT_INODE_NO, "a.txt");
+        assert_eq!(file.s3_location().unwrap().full_key().to_string(), "prefix/a.txt");
+        let dir = lookup(ROOT_INODE_NO, "dir1");
+        assert_eq!(dir.kind(), InodeKind::Directory);
+        let nested_file = lookup(dir.ino(), "b.txt");
+        assert_eq!(
+            nested_file.s3_location().unwrap().full_key().to_string(),
+            "prefix/dir1/b.txt"
+        );
+    }
+
+    #[test_case(&["", "channel_1"]; "empty first")]
+    #[test_case(&["channel_0", ""]; "empty second")]
+    fn test_root_channel_with_others(dir_names: &[&str]) {
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("s3_keys.db3");
+        let channels = dir_names
+            .iter()
+            .map(|dir_name| ChannelManifest {
+                directory_name: dir_name.to_string(),
+                s3_path: S3Path::new(Bucket::new("bucket").unwrap(), Default::default()),
+                entries: std::iter::empty(),
+            })
+            .collect();
+        let err = create_db(&db_path, channels, 1000).expect_err("must be an error");
+        assert!(matches!(err, InputManifestError::InvalidChannel(channel) if channel.is_empty()));
+    }
+
     #[test]
     fn test_directory_marker_ignored() {
         let db_dir = tempfile::tempdir().unwrap();
@@ -223,6 +223,14 @@ impl Metablock for ManifestMetablock {
         }))
     }
 
+    async fn set_file_size(&self, ino: InodeNo, _size: usize) -> Result<(), InodeError> {
+        Err(InodeError::InodeNotWritable(InodeErrorInfo {
+            ino,
+            key: "".into(),
+            bucket: None,
+        }))
+    }
+
     async fn finish_writing(&self, ino: InodeNo, _etag: Option<ETag>, _fh: u64) -> Result<Lookup, InodeError> {
         Err(InodeError::InodeNotWritable(InodeErrorInfo {
             ino,
@@ -49,6 +49,40 @@ pub trait Metablock: Send + Sync {
         mtime: Option<OffsetDateTime>,
     ) -> Result<Lookup, InodeError>;
 
+    /// Set the permission bits of a file that is not yet uploaded.
+    async fn set_mode(&self, ino: InodeNo, _mode: u16) -> Result<Lookup, InodeError> {
+        Err(InodeError::InodeNotWritable(InodeErrorInfo {
+            ino,
+            key: "".into(),
+            bucket: None,
+        }))
+    }
+
+    /// Update the ETag, and the modification time or permissions if given, of a remote file, after the file system
+    /// replaced its object in S3 itself, such as by copying it onto itself with new metadata.
+    async fn update_remote_file(
+        &self,
+        ino: InodeNo,
+        _etag: ETag,
+        _mtime: Option<OffsetDateTime>,
+        _mode: Option<u16>,
+    ) -> Result<Lookup, InodeError> {
+        Err(InodeError::InodeNotWritable(InodeErrorInfo {
+            ino,
+            key: "".into(),
+            bucket: None,
+        }))
+    }
+
+    /// Update the ETag and size of a remote file, after the file system truncated or extended its object in S3 itself.
+    async fn truncate_remote_file(&self, ino: InodeNo, _etag: ETag, _size: usize) -> Result<Lookup, InodeError> {
+        Err(InodeError::InodeNotWritable(InodeErrorInfo {
+            ino,
+            key: "".into(),
+            bucket: None,
+        }))
+    }
+
     /// Create a new regular file or directory inode ready to be opened in write-only mode
     async fn create(&self, dir: InodeNo, name: &OsStr, kind: InodeKind) -> Result<Lookup, InodeError>;
 
@@ -70,6 +104,9 @@ pub trait Metablock: Send + Sync {
     /// Returns the new size after the increase.
     async fn inc_file_size(&self, ino: InodeNo, len: usize) -> Result<usize, InodeError>;
 
+    /// Set the size of a file open for writing, when it is truncated or extended while being written.
+    async fn set_file_size(&self, ino: InodeNo, size: usize) -> Result<(), InodeError>;
+
     /// Called when the filesystem has finished writing to the inode referenced by `ino` using the
     /// file handle `fh`.
     ///
@@ -152,6 +189,39 @@ pub trait Metablock: Send + Sync {
 
     /// Unlink the entry described by `parent_ino` and `name`.
     async fn unlink(&self, parent_ino: InodeNo, name: &OsStr) -> Result<(), InodeError>;
+
+    /// Expire the cached metadata of the given key, and of the directories holding it, after it was changed in S3 by
+    /// another writer, and return the entries of the kernel caches to invalidate. Keys outside of the mounted prefix
+    /// are ignored.
+    async fn invalidate(&self, _key: &str) -> Invalidations {
+        Invalidations::default()
+    }
+
+    /// Expire the cached metadata of an inode and, for a directory, of everything cached under it, so that they are
+    /// looked up again in S3, and return the entries of the kernel caches to invalidate.
+    async fn invalidate_inode(&self, _ino: InodeNo) -> Result<Invalidations, InodeError> {
+        Ok(Invalidations::default())
+    }
+
+    /// Send the entries of the kernel caches made stale when revalidating the cached metadata of an inode finds that
+    /// its object was changed or removed in S3 to `sender`, so that the kernel stops serving the old attributes and
+    /// data of files kept open.
+    fn send_stale_entries(&self, _sender: async_channel::Sender<Invalidations>) {}
+}
+
+/// Entries of the kernel caches made stale by [Metablock::invalidate] or [Metablock::invalidate_inode].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Invalidations {
+    /// Names looked up in directories, as the directory and the name.
+    pub entries: Vec<(InodeNo, OsString)>,
+    /// Inodes whose attributes or data may be cached.
+    pub inodes: Vec<InodeNo>,
+}
+
+impl Invalidations {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.inodes.is_empty()
+    }
 }
 
 /// Callback to the file system which adds directory entries to the reply buffer.
@@ -189,11 +259,17 @@ pub struct WriteMode {
     pub allow_overwrite: bool,
     /// Enable incremental uploads
     pub incremental_upload: bool,
+    /// Allow appending to existing files by copying their object, see
+    /// [crate::S3FilesystemConfig::append_by_copy]
+    pub append_by_copy: bool,
+    /// Allow files to be read while they are written, from the data staged by their writer, see
+    /// [crate::S3FilesystemConfig::write_staging_dir]
+    pub read_while_writing: bool,
 }
 
 impl WriteMode {
     pub fn is_inode_writable(&self, is_truncate: bool) -> bool {
-        if self.incremental_upload || (self.allow_overwrite && is_truncate) {
+        if self.incremental_upload || (self.append_by_copy && !is_truncate) || (self.allow_overwrite && is_truncate) {
             true
         } else {
             if is_truncate {
@@ -202,7 +278,7 @@ impl WriteMode {
                 );
             } else {
                 tracing::warn!(
-                    "modifying an existing file is disabled by default, you need to remount with the --allow-overwrite or the --incremental-upload flag to enable it"
+                    "modifying an existing file is disabled by default, you need to remount with the --allow-overwrite, the --incremental-upload or the --allow-append flag to enable it"
                 );
             }
             false
@@ -226,6 +302,8 @@ pub enum ReadWriteMode {
 pub struct NewHandle {
     pub lookup: Lookup,
     pub mode: ReadWriteMode,
+    /// For a read handle of a file being written, the handle of its writer, whose data it reads
+    pub writer: Option<u64>,
 }
 
 impl NewHandle {
@@ -233,6 +311,7 @@ impl NewHandle {
         Self {
             lookup,
             mode: ReadWriteMode::Read,
+            writer: None,
         }
     }
 
@@ -240,6 +319,7 @@ impl NewHandle {
         Self {
             lookup,
             mode: ReadWriteMode::Write,
+            writer: None,
         }
     }
 }
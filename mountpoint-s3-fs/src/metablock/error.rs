@@ -50,6 +50,12 @@ pub enum InodeError {
     UnlinkNotPermittedWhileWriting(InodeErrorInfo),
     #[error("inode {0} is a directory and cannot be renamed")]
     CannotRenameDirectory(InodeErrorInfo),
+    #[error("directory {inode} holds more than {max_objects} objects and cannot be renamed")]
+    DirectoryTooLargeToRename { max_objects: usize, inode: InodeErrorInfo },
+    #[error("directory {inode} holds more than {max_objects} objects and cannot be removed")]
+    DirectoryTooLargeToDelete { max_objects: usize, inode: InodeErrorInfo },
+    #[error("{failed} objects under directory {inode} could not be deleted")]
+    DirectoryDeleteIncomplete { failed: usize, inode: InodeErrorInfo },
     #[error("inode {0} cannot be renamed while being written")]
     RenameNotPermittedWhileWriting(InodeErrorInfo),
     #[error("rename destination {dest_key:?} already exists, cannot rename inode {src_inode}")]
@@ -80,8 +86,14 @@ pub enum InodeError {
     OutOfOrderReadDir { expected: i64, actual: i64, fh: u64 },
     #[error("invalid directory handle {fh}")]
     NoSuchDirHandle { fh: u64 },
+    #[error("object {0:?} was changed since it was opened for writing")]
+    ObjectChangedWhileWriting(String),
     #[error("objects in flexible retrieval storage classes are not accessible")]
     FlexibleRetrievalObjectNotAccessible(InodeErrorInfo),
+    #[error("inode {0} is in a flexible retrieval storage class and must be restored before it can be read")]
+    FlexibleRetrievalObjectNotRestored(InodeErrorInfo),
+    #[error("key {0:?} is under a read-only prefix")]
+    ReadOnlyPrefix(String),
 }
 
 impl InodeError {
@@ -112,6 +124,9 @@ impl InodeError {
     where
         E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
     {
+        if let UploadError::ObjectChanged = err {
+            return InodeError::ObjectChangedWhileWriting(key.full_key().to_string());
+        }
         let metadata = ErrorMetadata {
             client_error_meta: err.meta(),
             error_code: Some(MOUNTPOINT_ERROR_CLIENT.to_string()),
@@ -1,9 +1,11 @@
 //! Core types for the metablock module
 use std::time::Duration;
 
+use mountpoint_s3_client::types::RestoreStatus;
 use time::OffsetDateTime;
 
 use super::Expiry;
+use crate::fs::{Compression, is_archived};
 
 pub type InodeNo = u64;
 
@@ -47,6 +49,29 @@ pub struct InodeStat {
     pub atime: OffsetDateTime,
     /// Etag for the file (object)
     pub etag: Option<Box<str>>,
+    /// Storage class of the object, if known
+    pub storage_class: Option<Box<str>>,
+    /// Restore status of the object, if it is in a flexible retrieval storage class and a restore was requested
+    pub restore_status: Option<RestoreStatus>,
+    /// Version ID of the object, only known when it was looked up with HeadObject in a versioned bucket
+    pub version_id: Option<Box<str>>,
+    /// Whether the object is read at its [Self::version_id], rather than as the current version of its key, which may
+    /// have been overwritten since. Set for the objects of a snapshot and the files of versions directories.
+    pub pinned_version: bool,
+    /// Whether the file is the object of a key shadowed by a directory, shown with the
+    /// [crate::fs::SHADOWED_FILE_SUFFIX] appended to its name, see [crate::fs::ShadowingPolicy::SuffixFile].
+    pub shadowed: bool,
+    /// Target of the symbolic link the object represents, only known when it was looked up with HeadObject
+    pub symlink_target: Option<Box<str>>,
+    /// Permission bits of the file, only known when they were looked up with HeadObject or set through the file system
+    pub mode: Option<u16>,
+    /// Size in bytes of the object, when the file is its content decoded from the `gzip` content encoding or from the
+    /// compression of its key, and [Self::size] the size of the decoded content, see
+    /// [crate::SuperblockConfig::decode_content_encoding] and [crate::SuperblockConfig::decompress_files]
+    pub encoded_size: Option<usize>,
+    /// Compression of the object of a file shown with the name of its decompressed content, whose key has the
+    /// extension of the compression appended to it, see [crate::SuperblockConfig::decompress_files]
+    pub decompressed: Option<Compression>,
     /// Inodes corresponding to S3 objects with GLACIER or DEEP_ARCHIVE storage classes
     /// are only readable after restoration. For objects with other storage classes
     /// this field should be always `true`.
@@ -62,30 +87,30 @@ impl InodeStat {
         self.expiry = Expiry::from_now(validity);
     }
 
+    /// Size in bytes of the object of the file, which differs from the size of the file when its content is decoded.
+    pub fn object_size(&self) -> usize {
+        self.encoded_size.unwrap_or(self.size)
+    }
+
+    /// Compression the object of the file is decoded from, if the file is its decoded content.
+    pub fn compression(&self) -> Option<Compression> {
+        self.encoded_size?;
+        Some(self.decompressed.unwrap_or(Compression::Gzip))
+    }
+
     /// Objects in flexible retrieval storage classes can't be accessed via GetObject unless they are
     /// restored, and so we override their permissions to 000 and reject reads to them. We also warn
     /// the first time we see an object like this, because FUSE enforces the 000 permissions on our
     /// behalf so we might not see an attempted `open` call.
-    fn is_readable(
-        storage_class: Option<&str>,
-        restore_status: Option<mountpoint_s3_client::types::RestoreStatus>,
-    ) -> bool {
+    fn is_readable(storage_class: Option<&str>, restore_status: Option<RestoreStatus>) -> bool {
         use crate::sync::atomic::{AtomicBool, Ordering};
-        use std::time::SystemTime;
 
         static HAS_SENT_WARNING: AtomicBool = AtomicBool::new(false);
-        match storage_class {
-            Some("GLACIER") | Some("DEEP_ARCHIVE") => {
-                let restored = matches!(restore_status, Some(mountpoint_s3_client::types::RestoreStatus::Restored { expiry }) if expiry > SystemTime::now());
-                if !restored && !HAS_SENT_WARNING.swap(true, Ordering::SeqCst) {
-                    tracing::warn!(
-                        "objects in the GLACIER and DEEP_ARCHIVE storage classes are only accessible if restored"
-                    );
-                }
-                restored
-            }
-            _ => true,
+        let archived = is_archived(storage_class, restore_status);
+        if archived && !HAS_SENT_WARNING.swap(true, Ordering::SeqCst) {
+            tracing::warn!("objects in the GLACIER and DEEP_ARCHIVE storage classes are only accessible if restored");
         }
+        !archived
     }
 
     /// Initialize an [InodeStat] for a file, given some metadata.
@@ -94,7 +119,7 @@ impl InodeStat {
         datetime: OffsetDateTime,
         etag: Option<Box<str>>,
         storage_class: Option<&str>,
-        restore_status: Option<mountpoint_s3_client::types::RestoreStatus>,
+        restore_status: Option<RestoreStatus>,
         validity: Duration,
     ) -> InodeStat {
         let is_readable = Self::is_readable(storage_class, restore_status);
@@ -105,6 +130,15 @@ impl InodeStat {
             ctime: datetime,
             mtime: datetime,
             etag,
+            storage_class: storage_class.map(Into::into),
+            restore_status,
+            version_id: None,
+            pinned_version: false,
+            shadowed: false,
+            symlink_target: None,
+            mode: None,
+            encoded_size: None,
+            decompressed: None,
             is_readable,
         }
     }
@@ -118,6 +152,15 @@ impl InodeStat {
             ctime: datetime,
             mtime: datetime,
             etag: None,
+            storage_class: None,
+            restore_status: None,
+            version_id: None,
+            pinned_version: false,
+            shadowed: false,
+            symlink_target: None,
+            mode: None,
+            encoded_size: None,
+            decompressed: None,
             is_readable: true,
         }
     }
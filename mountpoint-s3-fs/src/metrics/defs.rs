@@ -29,6 +29,15 @@ pub const FUSE_CACHE_HIT: &str = "fuse.cache_hit";
 pub const PROCESS_MEMORY_USAGE: &str = "process.memory_usage";
 
 pub const PREFETCH_RESET_STATE: &str = "prefetch.reset_state";
+pub const PREFETCH_REVERSE_MODE: &str = "prefetch.reverse_mode";
+pub const PREFETCH_DIRECT_READ_MODE: &str = "prefetch.direct_read_mode";
+pub const PREFETCH_STRIDED_MODE: &str = "prefetch.strided_mode";
+pub const PREFETCH_BUDGET_PRESSURE: &str = "prefetch.budget_pressure";
+pub const PREFETCH_BUDGET_RESERVED: &str = "prefetch.budget_reserved";
+pub const PREFETCH_COMPLETED_PARTS_REUSED: &str = "prefetch.completed_parts_reused";
+pub const PREFETCH_HEDGED_REQUESTS: &str = "prefetch.hedged_requests";
+pub const PREFETCH_HEDGED_REQUESTS_WON: &str = "prefetch.hedged_requests_won";
+pub const PREFETCH_REQUEST_PART_SIZE: &str = "prefetch.request_part_size";
 
 pub const CACHE_GET_IO_SIZE: &str = "cache.get_io_size";
 pub const CACHE_PUT_IO_SIZE: &str = "cache.put_io_size";
@@ -39,14 +48,28 @@ pub const CACHE_GET_ERRORS: &str = "cache.get_errors";
 pub const CACHE_PUT_ERRORS: &str = "cache.put_errors";
 pub const CACHE_TOTAL_SIZE: &str = "cache.total_size";
 pub const CACHE_OVERSIZED_OBJECTS: &str = "cache.oversized_objects";
+pub const CACHE_SCRUBBED_BLOCKS: &str = "cache.scrubbed_blocks";
+pub const CACHE_CORRUPTED_BLOCKS: &str = "cache.corrupted_blocks";
+pub const CACHE_PEER_SERVED_BLOCKS: &str = "cache.peer_served_blocks";
+pub const CACHE_ACCOUNT_SIZE: &str = "cache.account_size";
+pub const CACHE_ACCOUNT_EVICTIONS: &str = "cache.account_evictions";
+pub const CACHE_PREFIX_HITS: &str = "cache.prefix_hits";
+pub const CACHE_PREFIX_MISSES: &str = "cache.prefix_misses";
+pub const CACHE_PREFIX_HIT_RATIO: &str = "cache.prefix_hit_ratio";
+pub const CACHE_PREFIX_HIT_BYTES: &str = "cache.prefix_hit_bytes";
+pub const CACHE_PREFIX_FILL_BYTES: &str = "cache.prefix_fill_bytes";
 
 // Attribute constants
 pub const ATTR_FUSE_REQUEST: &str = "fuse_request";
 pub const ATTR_CACHE: &str = "cache";
+pub const ATTR_PREFIX: &str = "prefix";
+pub const ATTR_ACCOUNT: &str = "account";
 
 // Cache type constants
 pub const CACHE_DISK: &str = "disk";
 pub const CACHE_EXPRESS: &str = "express";
+pub const CACHE_MEMORY: &str = "memory";
+pub const CACHE_PEER: &str = "peer";
 
 pub fn lookup_config(name: &str) -> MetricConfig {
     match name {
@@ -105,6 +128,51 @@ pub fn lookup_config(name: &str) -> MetricConfig {
             stability: MetricStability::Experimental,
             otlp_attributes: &[],
         },
+        PREFETCH_REVERSE_MODE => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_DIRECT_READ_MODE => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_STRIDED_MODE => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_HEDGED_REQUESTS => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_HEDGED_REQUESTS_WON => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_REQUEST_PART_SIZE => MetricConfig {
+            unit: Unit::Bytes,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_COMPLETED_PARTS_REUSED => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_BUDGET_PRESSURE => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        PREFETCH_BUDGET_RESERVED => MetricConfig {
+            unit: Unit::Bytes,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
         FUSE_CACHE_HIT => MetricConfig {
             unit: Unit::Count,
             stability: MetricStability::Experimental,
@@ -140,6 +208,36 @@ pub fn lookup_config(name: &str) -> MetricConfig {
             stability: MetricStability::Internal,
             otlp_attributes: &[],
         },
+        CACHE_SCRUBBED_BLOCKS | CACHE_CORRUPTED_BLOCKS => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[ATTR_CACHE],
+        },
+        CACHE_ACCOUNT_SIZE => MetricConfig {
+            unit: Unit::Bytes,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[ATTR_ACCOUNT],
+        },
+        CACHE_ACCOUNT_EVICTIONS => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[ATTR_ACCOUNT],
+        },
+        CACHE_PEER_SERVED_BLOCKS => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[],
+        },
+        CACHE_PREFIX_HITS | CACHE_PREFIX_MISSES | CACHE_PREFIX_HIT_RATIO => MetricConfig {
+            unit: Unit::Count,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[ATTR_PREFIX],
+        },
+        CACHE_PREFIX_HIT_BYTES | CACHE_PREFIX_FILL_BYTES => MetricConfig {
+            unit: Unit::Bytes,
+            stability: MetricStability::Experimental,
+            otlp_attributes: &[ATTR_PREFIX],
+        },
         // Treat everything else as count metrics
         _ => MetricConfig {
             unit: Unit::Count,
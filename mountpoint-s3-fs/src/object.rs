@@ -5,7 +5,7 @@ use mountpoint_s3_client::types::ETag;
 use crate::sync::Arc;
 
 /// Identifier for a specific version of an S3 object.
-/// Formed by the object key and etag. Holds its components in an [Arc], so it can be cheaply cloned.
+/// Formed by the object key and etag, and the ID of the version to read if it is not the current one. Holds its components in an [Arc], so it can be cheaply cloned.
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct ObjectId {
     inner: Arc<InnerObjectId>,
@@ -16,6 +16,7 @@ impl Debug for ObjectId {
         f.debug_struct("ObjectId")
             .field("key", &self.inner.key)
             .field("etag", &self.inner.etag)
+            .field("version_id", &self.inner.version_id)
             .finish()
     }
 }
@@ -24,12 +25,19 @@ impl Debug for ObjectId {
 struct InnerObjectId {
     key: String,
     etag: ETag,
+    version_id: Option<String>,
 }
 
 impl ObjectId {
     pub fn new(key: String, etag: ETag) -> Self {
+        Self::with_version_id(key, etag, None)
+    }
+
+    /// Identify a version of an object, which is read with its version ID if given, rather than as the current
+    /// version of its key.
+    pub fn with_version_id(key: String, etag: ETag, version_id: Option<String>) -> Self {
         Self {
-            inner: Arc::new(InnerObjectId { key, etag }),
+            inner: Arc::new(InnerObjectId { key, etag, version_id }),
         }
     }
 
@@ -40,4 +48,8 @@ impl ObjectId {
     pub fn etag(&self) -> &ETag {
         &self.inner.etag
     }
+
+    pub fn version_id(&self) -> Option<&str> {
+        self.inner.version_id.as_deref()
+    }
 }
@@ -7,7 +7,11 @@
 //! don't accumulate a lot of unread object data in memory or wastefully download data we'll never
 //! read. As the reader continues to make sequential reads, we increase the size of the read window
 //! up to some maximum. If the reader ever makes a non-sequential read, we abandon the prefetching
-//! and start again with a new GetObject request with minimum read window size.
+//! and start again with a new GetObject request with minimum read window size. The exception is a
+//! reader going backwards through the object, which we detect and serve by requesting chunks of the
+//! object in reverse order (see [reverse_stream]), and a reader skipping through the object with a
+//! fixed stride, which we serve with ranged requests for the offsets it will read next (see
+//! [strided_stream]).
 //!
 //! In more technical details, the prefetcher creates a RequestTask when receiving the first read
 //! request from the file system or after it has just been reset. The RequestTask consists of two main
@@ -31,35 +35,57 @@
 //! more data from the sources and put them into the part queue. The BackpressureLimiter should be used
 //! as a mean to block ObjectPartStream thread to fetch more data.
 
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::ops::Range;
 
 use metrics::{counter, histogram};
 use mountpoint_s3_client::error::{GetObjectError, ObjectClientError};
 use mountpoint_s3_client::{ObjectClient, error_metadata::ProvideErrorMetadata};
 use thiserror::Error;
-use tracing::trace;
+use tracing::{debug, trace};
 
 use crate::checksums::{ChecksummedBytes, IntegrityError};
 use crate::data_cache::DataCache;
 use crate::fs::error_metadata::{ErrorMetadata, MOUNTPOINT_ERROR_CLIENT};
 use crate::mem_limiter::{BufferArea, MemoryLimiter};
-use crate::metrics::defs::{FUSE_CACHE_HIT, PREFETCH_RESET_STATE};
+use crate::metrics::defs::{
+    FUSE_CACHE_HIT, PREFETCH_COMPLETED_PARTS_REUSED, PREFETCH_DIRECT_READ_MODE, PREFETCH_REQUEST_PART_SIZE,
+    PREFETCH_RESET_STATE, PREFETCH_REVERSE_MODE, PREFETCH_STRIDED_MODE,
+};
 use crate::object::ObjectId;
 use crate::sync::Arc;
+use crate::sync::atomic::{AtomicU64, Ordering};
 
 mod backpressure_controller;
+mod budget;
 mod builder;
 mod caching_stream;
+mod hedging;
+mod parquet;
 mod part;
 mod part_queue;
+mod part_size;
 mod part_stream;
+mod reverse_stream;
 mod seek_window;
+mod strategy;
+mod strided_stream;
 mod task;
 
+pub use budget::{PrefetchBudget, PrefetchPriority};
 pub use builder::PrefetcherBuilder;
-use part::PartOperationError;
+use hedging::RequestHedging;
+use parquet::{PARQUET_MAGIC, ParquetLayout, ParquetState};
+use part::{Part, PartOperationError};
+use part_queue::CompletedParts;
+use part_size::AdaptivePartSize;
+pub use part_size::candidate_part_sizes;
 use part_stream::{PartStream, RequestRange, RequestTaskConfig};
+use reverse_stream::{ReverseStream, ReverseStreamConfig};
 use seek_window::SeekWindow;
+pub use strategy::{PrefetchStrategy, ReadWindowLimits, SequentialPrefetchStrategy};
+use strided_stream::{Stride, StrideDetector, StridedStream, StridedStreamConfig};
 use task::RequestTask;
 
 /// Opaque identifier for a file handle, used to attribute prefetch requests to their origin.
@@ -86,6 +112,18 @@ impl HandleId {
 // to avoid the latency hit of the second request.
 pub const INITIAL_REQUEST_SIZE: usize = 1024 * 1024 + 128 * 1024;
 
+/// Number of consecutive reads, each ending where the previous one started, after which we assume
+/// the object is being read backwards and start prefetching in reverse order. Requiring more than
+/// one such read avoids switching modes on a single out-of-order Linux readahead.
+const BACKWARD_READ_THRESHOLD: usize = 2;
+
+/// How much of the end of a Parquet file to fetch when looking for its footer. This is enough for
+/// the file metadata of most files, avoiding a second request.
+const PARQUET_FOOTER_PREFETCH_SIZE: usize = 1024 * 1024;
+
+/// Parquet footers larger than this are not decoded, and reads are prefetched as for any object.
+const PARQUET_MAX_FOOTER_SIZE: u64 = 64 * 1024 * 1024;
+
 #[derive(Debug, Error)]
 pub enum PrefetchReadError<E> {
     #[error("get object request failed")]
@@ -142,6 +180,52 @@ pub struct PrefetcherConfig {
     /// is made to lower the latency in small-random-reads usage pattern. If set to 0, initial request
     /// is skipped.
     pub initial_request_size: usize,
+    /// Detect file formats with known access patterns and prefetch them accordingly. Currently
+    /// this recognizes Parquet files, by their key suffix or their magic bytes: their footer is
+    /// fetched first, and requests are bounded to the column chunk being read.
+    pub format_aware: bool,
+    /// Number of out-of-order reads on a file handle after which the prefetcher stops prefetching,
+    /// and serves reads with ranged GET requests of [Self::random_access_request_size] bytes
+    /// instead. Out-of-order reads only count towards this threshold if less than that many bytes
+    /// were read sequentially since the previous one. If set to 0, prefetching is never disabled.
+    pub random_access_threshold: usize,
+    /// Size of the ranged GET requests made once random access has been detected. Larger reads
+    /// are served with a request of their own size.
+    pub random_access_request_size: usize,
+    /// Maximum amount of memory for the read windows of all file handles, in bytes. When it is
+    /// exhausted, read windows are scaled down so that each file handle gets a fair share of it,
+    /// see [PrefetchBudget]. If [None], read windows are only limited by the memory limiter.
+    pub memory_budget: Option<u64>,
+    /// Number of reads to prefetch ahead once reads of the same size at regularly spaced offsets
+    /// have been detected, with a ranged GET request for each of them. If set to 0, strided reads
+    /// are treated as any other out-of-order read.
+    pub strided_prefetch_depth: usize,
+    /// Maximum number of sequential streams tracked for each file handle. When a read doesn't
+    /// continue the current stream, the stream is kept alongside a new one, so that a reader
+    /// interleaving sequential reads at different offsets (for example, the head and the tail of
+    /// a file) doesn't restart prefetching on every read. If set to 1, only the current stream is
+    /// tracked.
+    pub max_streams_per_handle: usize,
+    /// Maximum amount of data downloaded by discarded requests that is kept for each file handle,
+    /// so that a later seek landing within it is served without fetching it again. Requests are
+    /// discarded with the data they had downloaded but not returned yet when prefetching restarts
+    /// at another offset. If set to 0, that data is dropped.
+    pub max_preserved_parts_size: usize,
+    /// Percentile of the recent first-byte latencies of GetObject requests, between 0 and 100,
+    /// after which a request still waiting for its first byte is duplicated with another request
+    /// for the same range. Reads continue with whichever request returns data first. If [None],
+    /// requests are never hedged.
+    pub hedge_percentile: Option<f64>,
+    /// Sequential reads smaller than this many bytes are coalesced: a chunk of this size is taken
+    /// from the prefetched data at once, and the following reads are served from it, so that the
+    /// read window is only updated once for the whole chunk. This reduces the overhead of readers
+    /// making many small reads. If set to 0, reads are never coalesced.
+    pub read_coalesce_size: usize,
+    /// Maximum part size of the GetObject requests made by the prefetcher. The part size of each
+    /// request is picked between the client's read part size and this maximum, based on the
+    /// first-byte latency and throughput measured for previous requests, see [AdaptivePartSize].
+    /// If [None], requests always use the client's read part size.
+    pub max_read_part_size: Option<usize>,
 }
 
 impl Default for PrefetcherConfig {
@@ -157,6 +241,16 @@ impl Default for PrefetcherConfig {
             max_forward_seek_wait_distance: 16 * 1024 * 1024,
             max_backward_seek_distance: 1 * 1024 * 1024,
             initial_request_size: INITIAL_REQUEST_SIZE,
+            format_aware: false,
+            random_access_threshold: 16,
+            random_access_request_size: 512 * 1024,
+            memory_budget: None,
+            strided_prefetch_depth: 4,
+            max_streams_per_handle: 2,
+            max_preserved_parts_size: 8 * 1024 * 1024,
+            hedge_percentile: None,
+            read_coalesce_size: 128 * 1024,
+            max_read_part_size: None,
         }
     }
 }
@@ -197,11 +291,53 @@ fn determine_max_read_size() -> usize {
     }
 }
 
+/// State of a [PrefetchGetObject] serving reads without prefetching, after random access has been
+/// detected.
+#[derive(Debug, Default)]
+struct DirectReads {
+    /// Data fetched for the last read that wasn't served from memory
+    buffer: Option<Part>,
+    /// End offset of the last read
+    last_read_end: Option<u64>,
+}
+
+impl DirectReads {
+    fn covers(&self, offset: u64, length: usize) -> bool {
+        self.buffer
+            .as_ref()
+            .is_some_and(|buffer| offset >= buffer.offset() && offset + length as u64 <= buffer.end_offset())
+    }
+}
+
+/// A sequential stream of a [PrefetchGetObject], kept while the reader is reading another part of
+/// the object, see [PrefetcherConfig::max_streams_per_handle].
+#[derive(Debug)]
+struct ParkedStream<Client: ObjectClient> {
+    task: RequestTask<Client>,
+    sequential_read_start_offset: u64,
+    next_sequential_read_offset: u64,
+    next_request_offset: u64,
+}
+
+impl<Client: ObjectClient> ParkedStream<Client> {
+    /// Whether a read at `offset` continues this stream, possibly after a short forward seek.
+    fn continues_at(&self, offset: u64) -> bool {
+        offset == self.next_sequential_read_offset
+            || (offset > self.next_sequential_read_offset && offset < self.task.read_window_end_offset())
+    }
+}
+
 /// A [Prefetcher] creates and manages prefetching GetObject requests to objects.
 #[derive(Debug)]
 pub struct Prefetcher<Client> {
     part_stream: PartStream<Client>,
+    /// Part stream reading straight from the client, if [Self::part_stream] goes through a cache.
+    uncached_part_stream: Option<PartStream<Client>>,
     config: PrefetcherConfig,
+    strategy: Arc<dyn PrefetchStrategy>,
+    budget: Arc<PrefetchBudget>,
+    hedging: Arc<RequestHedging>,
+    adaptive_part_size: Arc<AdaptivePartSize>,
     mem_limiter: Arc<MemoryLimiter>,
 }
 
@@ -223,14 +359,34 @@ where
     }
 
     /// Create a new [Prefetcher] from the given [ObjectPartStream] instance.
+    ///
+    /// The prefetcher uses a [SequentialPrefetchStrategy] scaling the read window by
+    /// [PrefetcherConfig::sequential_prefetch_multiplier], unless overridden with [Self::with_strategy].
     pub fn new(part_stream: PartStream<Client>, config: PrefetcherConfig, mem_limiter: Arc<MemoryLimiter>) -> Self {
         Self {
             part_stream,
+            uncached_part_stream: None,
             config,
+            strategy: Arc::new(SequentialPrefetchStrategy::new(config.sequential_prefetch_multiplier)),
+            budget: Arc::new(PrefetchBudget::new(config.memory_budget)),
+            hedging: Arc::new(RequestHedging::new(config.hedge_percentile)),
+            adaptive_part_size: Arc::new(AdaptivePartSize::new(config.max_read_part_size)),
             mem_limiter,
         }
     }
 
+    /// Replace the [PrefetchStrategy] used by all the requests started by this [Prefetcher].
+    pub fn with_strategy(mut self, strategy: Arc<dyn PrefetchStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Use the given [PartStream] for the requests that must bypass the cache, see [Self::prefetch_uncached].
+    pub fn with_uncached_part_stream(mut self, part_stream: PartStream<Client>) -> Self {
+        self.uncached_part_stream = Some(part_stream);
+        self
+    }
+
     /// Start a new prefetch request to the specified object.
     pub fn prefetch(
         &self,
@@ -239,12 +395,46 @@ where
         handle_id: HandleId,
         size: u64,
     ) -> PrefetchGetObject<Client>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        self.new_request(self.part_stream.clone(), bucket, object_id, handle_id, size)
+    }
+
+    /// Start a new prefetch request to the specified object, which neither reads from nor writes to the data
+    /// cache, if any.
+    pub fn prefetch_uncached(
+        &self,
+        bucket: String,
+        object_id: ObjectId,
+        handle_id: HandleId,
+        size: u64,
+    ) -> PrefetchGetObject<Client>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        let part_stream = self.uncached_part_stream.as_ref().unwrap_or(&self.part_stream);
+        self.new_request(part_stream.clone(), bucket, object_id, handle_id, size)
+    }
+
+    fn new_request(
+        &self,
+        part_stream: PartStream<Client>,
+        bucket: String,
+        object_id: ObjectId,
+        handle_id: HandleId,
+        size: u64,
+    ) -> PrefetchGetObject<Client>
     where
         Client: ObjectClient + Clone + Send + Sync + 'static,
     {
         PrefetchGetObject::new(
-            self.part_stream.clone(),
+            part_stream,
             self.config,
+            self.strategy.clone(),
+            self.budget.clone(),
+            self.hedging.clone(),
+            self.adaptive_part_size.clone(),
             bucket,
             object_id,
             handle_id,
@@ -254,6 +444,32 @@ where
     }
 }
 
+/// Statistics of the prefetching for a single [PrefetchGetObject], to help understand how a
+/// workload is served.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefetchStats {
+    /// Preferred size of the read window of the current sequential stream, or 0 if there is none.
+    pub read_window_size: usize,
+    /// Bytes received by the requests, from S3 or from the cache.
+    pub bytes_prefetched: u64,
+    /// Bytes returned to reads.
+    pub bytes_read: u64,
+    /// Number of times prefetching restarted at another offset, with a new request.
+    pub restarts: u64,
+    /// Number of reads that didn't start where the previous one ended.
+    pub out_of_order_reads: u64,
+}
+
+impl std::fmt::Display for PrefetchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "read_window_size={} bytes_prefetched={} bytes_read={} restarts={} out_of_order_reads={}",
+            self.read_window_size, self.bytes_prefetched, self.bytes_read, self.restarts, self.out_of_order_reads
+        )
+    }
+}
+
 /// Result of a prefetch request. Allows callers to read object data.
 #[derive(Debug)]
 pub struct PrefetchGetObject<Client>
@@ -262,7 +478,33 @@ where
 {
     part_stream: PartStream<Client>,
     config: PrefetcherConfig,
+    strategy: Arc<dyn PrefetchStrategy>,
+    budget: Arc<PrefetchBudget>,
+    hedging: Arc<RequestHedging>,
+    adaptive_part_size: Arc<AdaptivePartSize>,
+    priority: PrefetchPriority,
     backpressure_task: Option<RequestTask<Client>>,
+    /// Other sequential streams on this object, least recently read first.
+    parked_streams: VecDeque<ParkedStream<Client>>,
+    /// Parts downloaded by discarded requests, see [PrefetcherConfig::max_preserved_parts_size].
+    completed_parts: CompletedParts,
+    /// Stream serving reads while the object is being read backwards, see [ReverseStream].
+    reverse_stream: Option<Box<ReverseStream<Client>>>,
+    /// Offset of the previous read, used to detect backward sequential reads
+    last_read_offset: Option<u64>,
+    /// Number of consecutive backward sequential reads
+    backward_read_count: usize,
+    stride_detector: StrideDetector,
+    /// Stream serving reads while the object is being read with a fixed stride, see [StridedStream].
+    strided_stream: Option<Box<StridedStream<Client>>>,
+    /// State for objects detected as Parquet files, see [PrefetcherConfig::format_aware].
+    parquet: Option<Box<ParquetState>>,
+    /// Whether the first read still has to be checked for magic bytes indicating the file format.
+    detect_format: bool,
+    /// Number of out-of-order reads, see [PrefetcherConfig::random_access_threshold].
+    out_of_order_reads: usize,
+    /// Set once random access has been detected, to serve reads without prefetching.
+    direct_reads: Option<Box<DirectReads>>,
     // Invariant: the offset of the last byte in this window is always
     // self.next_sequential_read_offset - 1.
     backward_seek_window: SeekWindow,
@@ -278,6 +520,13 @@ where
     mem_limiter: Arc<MemoryLimiter>,
     /// File handle ID that owns this prefetch request, for per-handle memory accounting.
     handle_id: HandleId,
+    /// Counters reported by [Self::stats]. The read window size and the bytes prefetched are
+    /// filled in when they are reported.
+    stats: PrefetchStats,
+    /// Bytes received by all the requests of this prefetch request
+    bytes_prefetched: Arc<AtomicU64>,
+    /// End offset of the previous read, used to count out-of-order reads
+    last_read_end: Option<u64>,
 }
 
 impl<Client> PrefetchGetObject<Client>
@@ -285,9 +534,14 @@ where
     Client: ObjectClient + Clone + Send + Sync + 'static,
 {
     /// Create and spawn a new prefetching request for an object
+    #[allow(clippy::too_many_arguments)]
     fn new(
         part_stream: PartStream<Client>,
         config: PrefetcherConfig,
+        strategy: Arc<dyn PrefetchStrategy>,
+        budget: Arc<PrefetchBudget>,
+        hedging: Arc<RequestHedging>,
+        adaptive_part_size: Arc<AdaptivePartSize>,
         bucket: String,
         object_id: ObjectId,
         handle_id: HandleId,
@@ -300,10 +554,27 @@ where
         let seek_window_reservation =
             Self::seek_window_reservation(part_stream.client().read_part_size(), max_backward_seek_distance);
         mem_limiter.reserve(BufferArea::Prefetch, seek_window_reservation);
+        let parquet = (config.format_aware && parquet::is_parquet_key(object_id.key())).then(Default::default);
         PrefetchGetObject {
+            detect_format: config.format_aware && parquet.is_none(),
+            out_of_order_reads: 0,
+            direct_reads: None,
+            parquet,
             part_stream,
             config,
+            strategy,
+            budget,
+            hedging,
+            adaptive_part_size,
+            priority: PrefetchPriority::default(),
             backpressure_task: None,
+            parked_streams: VecDeque::new(),
+            completed_parts: CompletedParts::new(config.max_preserved_parts_size, mem_limiter.clone()),
+            reverse_stream: None,
+            last_read_offset: None,
+            backward_read_count: 0,
+            stride_detector: Default::default(),
+            strided_stream: None,
             backward_seek_window: SeekWindow::new(max_backward_seek_distance),
             preferred_part_size: 128 * 1024,
             sequential_read_start_offset: 0,
@@ -314,6 +585,9 @@ where
             size,
             mem_limiter,
             handle_id,
+            stats: Default::default(),
+            bytes_prefetched: Default::default(),
+            last_read_end: None,
         }
     }
 
@@ -332,8 +606,18 @@ where
             "read"
         );
 
+        if self.last_read_end.is_some_and(|end| end != offset) {
+            self.stats.out_of_order_reads += 1;
+        }
         match self.try_read(offset, length).await {
             Ok((data, cache_hit)) => {
+                self.stats.bytes_read += data.len() as u64;
+                self.last_read_end = Some(offset + data.len() as u64);
+                if self.detect_format {
+                    self.detect_format = false;
+                    self.detect_parquet(offset, &data);
+                }
+
                 // Record cache hit metric for FUSE layer. We only record a cache hit when ALL parts
                 // for this read request were served from cache storage (disk/express), not from S3.
                 // Partial cache hits (some parts from cache, some from S3) are counted as cache misses
@@ -355,6 +639,32 @@ where
         }
     }
 
+    /// Current prefetching statistics, see [PrefetchStats].
+    pub fn stats(&self) -> PrefetchStats {
+        PrefetchStats {
+            read_window_size: self
+                .backpressure_task
+                .as_ref()
+                .map_or(0, |task| task.read_window_size()),
+            bytes_prefetched: self.bytes_prefetched.load(Ordering::SeqCst),
+            ..self.stats
+        }
+    }
+
+    /// Set the priority of this request when sharing memory with the other requests of the
+    /// [Prefetcher], see [PrefetchPriority]. This applies to the GetObject requests started after
+    /// the call, so should be set before the first read.
+    pub fn set_priority(&mut self, priority: PrefetchPriority) {
+        self.priority = priority;
+    }
+
+    /// Limit the read window of this request to `max_read_window_size`, in place of
+    /// [PrefetcherConfig::max_read_window_size]. This applies to the GetObject requests started
+    /// after the call, so should be set before the first read.
+    pub fn set_max_read_window_size(&mut self, max_read_window_size: usize) {
+        self.config.max_read_window_size = max_read_window_size;
+    }
+
     async fn try_read(
         &mut self,
         offset: u64,
@@ -375,12 +685,33 @@ where
             return Ok((ChecksummedBytes::default(), false));
         }
         let mut to_read = (length as u64).min(remaining);
+        let stride = self.stride_detector.record(offset, length);
+
+        if let Some(data) = self.try_read_parquet_footer(offset, to_read as usize).await? {
+            return Ok((data, false));
+        }
+
+        if let Some(data) = self.try_read_strided(offset, to_read as usize).await? {
+            return Ok((data, false));
+        }
+
+        if let Some(data) = self.try_read_direct(offset, to_read as usize).await? {
+            return Ok((data, false));
+        }
+
+        if let Some(data) = self.try_read_backward(offset, to_read as usize).await? {
+            return Ok((data, false));
+        }
 
         // Try to seek if this read is not sequential, and if seeking fails, cancel and reset the
         // prefetcher.
         if self.next_sequential_read_offset != offset {
             if self.try_seek(offset).await? {
                 trace!("seek succeeded");
+            } else if self.try_switch_stream(offset).await? {
+                trace!("switched to another stream");
+            } else if self.try_resume_from_completed_parts(offset).await? {
+                trace!("resumed from parts downloaded by a discarded request");
             } else {
                 trace!(
                     expected = self.next_sequential_read_offset,
@@ -388,22 +719,45 @@ where
                     "out-of-order read, resetting prefetch"
                 );
                 counter!(PREFETCH_RESET_STATE).increment(1);
+                self.stats.restarts += 1;
 
                 // This is an approximation, tolerating some seeking caused by concurrent readahead.
                 self.record_contiguous_read_metric();
 
-                self.reset_prefetch_to_offset(offset);
+                if let Some(stride) = stride
+                    && self.config.strided_prefetch_depth > 0
+                {
+                    trace!(?stride, "strided reads detected, prefetching predicted offsets");
+                    counter!(PREFETCH_STRIDED_MODE).increment(1);
+                    self.reset_prefetch_to_offset(offset);
+                    let data = self.start_strided_reads(offset, stride, to_read as usize).await?;
+                    return Ok((data, false));
+                }
+
+                let random_access = self.record_out_of_order_read();
+                if random_access {
+                    self.reset_prefetch_to_offset(offset);
+                    trace!(
+                        out_of_order_reads = self.out_of_order_reads,
+                        "random access detected, disabling prefetching"
+                    );
+                    counter!(PREFETCH_DIRECT_READ_MODE).increment(1);
+                    self.direct_reads = Some(Default::default());
+                    return Ok((self.read_direct(offset, to_read as usize).await?, false));
+                }
+                self.start_new_stream(offset);
             }
         }
         assert_eq!(self.next_sequential_read_offset, offset);
 
-        if self.backpressure_task.is_none() {
-            self.backpressure_task = Some(self.spawn_read_backpressure_request()?);
-        }
-
         let mut all_parts_from_cache = true;
         let mut response = ChecksummedBytes::default();
         while to_read > 0 {
+            // The strategy may choose requests that end before the object does, in which case we
+            // continue with a new request once the current one has been fully read.
+            if self.backpressure_task.as_ref().is_none_or(|task| task.remaining() == 0) {
+                self.backpressure_task = Some(self.spawn_read_backpressure_request()?);
+            }
             let Some(current_task) = self.backpressure_task.as_mut() else {
                 trace!(offset, length, "read beyond object size");
                 break;
@@ -431,6 +785,333 @@ where
         Ok((response, all_parts_from_cache))
     }
 
+    /// Serve reads of the footer of a Parquet file from memory, fetching it first if needed. Returns
+    /// None if the object is not a Parquet file, or if the read is not entirely within the footer.
+    async fn try_read_parquet_footer(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<Option<ChecksummedBytes>, PrefetchReadError<Client::ClientError>> {
+        let Some(parquet) = self.parquet.as_ref() else {
+            return Ok(None);
+        };
+        if parquet.footer.is_none() {
+            self.fetch_parquet_footer().await?;
+        }
+
+        let footer = self
+            .parquet
+            .as_ref()
+            .and_then(|parquet| parquet.footer.as_ref())
+            .expect("footer was just fetched");
+        if offset < footer.offset() {
+            return Ok(None);
+        }
+        let part = footer.slice(offset..offset + length as u64);
+        Ok(Some(part.into_bytes(&self.object_id, offset)?))
+    }
+
+    /// Fetch the end of a Parquet file and decode the layout of its column chunks from the footer.
+    async fn fetch_parquet_footer(&mut self) -> Result<(), PrefetchReadError<Client::ClientError>> {
+        let tail_start = self.size.saturating_sub(PARQUET_FOOTER_PREFETCH_SIZE as u64);
+        let mut footer = self.fetch_range(tail_start..self.size).await?;
+        let tail = footer.clone().into_bytes(&self.object_id, tail_start)?.into_bytes()?;
+
+        let mut layout = None;
+        match parquet::footer_len(&tail, self.size) {
+            Ok(footer_len) if footer_len > PARQUET_MAX_FOOTER_SIZE => {
+                debug!(
+                    footer_len,
+                    "Parquet footer is too large, prefetching it as a regular object"
+                );
+            }
+            Ok(footer_len) => {
+                let footer_start = self.size - footer_len;
+                let footer_bytes = if footer_start < tail_start {
+                    let mut head = self.fetch_range(footer_start..tail_start).await?;
+                    head.extend(&footer)?;
+                    footer = head;
+                    footer.clone().into_bytes(&self.object_id, footer_start)?.into_bytes()?
+                } else {
+                    tail.slice((footer_start - tail_start) as usize..)
+                };
+                match ParquetLayout::from_footer(&footer_bytes) {
+                    Ok(parquet_layout) => {
+                        trace!(
+                            column_chunks = parquet_layout.num_column_chunks(),
+                            "decoded Parquet footer"
+                        );
+                        layout = Some(parquet_layout);
+                    }
+                    Err(err) => debug!(?err, "failed to decode Parquet footer"),
+                }
+            }
+            Err(err) => debug!(?err, "failed to find Parquet footer"),
+        }
+
+        self.mem_limiter.reserve(BufferArea::Prefetch, footer.len() as u64);
+        let parquet = self.parquet.as_mut().expect("object is a Parquet file");
+        parquet.footer = Some(footer);
+        parquet.layout = layout;
+        Ok(())
+    }
+
+    /// Check the first read of an object for the Parquet magic bytes, at the start or the end of
+    /// the object.
+    fn detect_parquet(&mut self, offset: u64, data: &ChecksummedBytes) {
+        let magic_len = PARQUET_MAGIC.len();
+        if data.len() < magic_len {
+            return;
+        }
+        let magic = if offset == 0 {
+            data.slice(..magic_len)
+        } else if offset + data.len() as u64 == self.size {
+            data.slice(data.len() - magic_len..)
+        } else {
+            return;
+        };
+        if magic.into_bytes().is_ok_and(|magic| magic[..] == PARQUET_MAGIC[..]) {
+            trace!("detected Parquet file from its magic bytes");
+            self.parquet = Some(Default::default());
+        }
+    }
+
+    /// Fetch a range of the object with a single request, and wait for all of its data.
+    async fn fetch_range(&self, range: Range<u64>) -> Result<Part, PrefetchReadError<Client::ClientError>> {
+        let size = (range.end - range.start) as usize;
+        let config = RequestTaskConfig {
+            bucket: self.bucket.clone(),
+            object_id: self.object_id.clone(),
+            handle_id: self.handle_id,
+            range: RequestRange::new(self.size as usize, range.start, size),
+            read_part_size: self.part_stream.client().read_part_size(),
+            preferred_part_size: self.preferred_part_size,
+            initial_request_size: size,
+            max_read_window_size: size,
+            strategy: self.strategy.clone(),
+            budget: self.budget.clone(),
+            hedging: self.hedging.clone(),
+            adaptive_part_size: self.adaptive_part_size.clone(),
+            priority: self.priority,
+            bytes_prefetched: self.bytes_prefetched.clone(),
+        };
+        let mut task = self.part_stream.spawn_get_object_request(config);
+        task.read_to_end()
+            .await?
+            .ok_or(PrefetchReadError::GetRequestTerminatedUnexpectedly)
+    }
+
+    /// Count an out-of-order read which reset the prefetcher. Returns true if there have been
+    /// enough of them to stop prefetching, see [PrefetcherConfig::random_access_threshold].
+    fn record_out_of_order_read(&mut self) -> bool {
+        if self.config.random_access_threshold == 0 {
+            return false;
+        }
+        let contiguous_read_len = self.next_sequential_read_offset - self.sequential_read_start_offset;
+        if contiguous_read_len >= self.config.random_access_request_size as u64 {
+            self.out_of_order_reads = 0;
+        }
+        self.out_of_order_reads += 1;
+        self.out_of_order_reads >= self.config.random_access_threshold
+    }
+
+    /// Serve the read with a ranged GET request if random access has been detected. Returns None
+    /// if prefetching is enabled, or if the reader has become sequential again.
+    async fn try_read_direct(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<Option<ChecksummedBytes>, PrefetchReadError<Client::ClientError>> {
+        let Some(direct_reads) = self.direct_reads.as_ref() else {
+            return Ok(None);
+        };
+        // A read continuing the previous one past the data we fetched means the reader went through
+        // all of it sequentially, so prefetching is worth trying again.
+        if !direct_reads.covers(offset, length) && direct_reads.last_read_end == Some(offset) {
+            trace!(offset, "sequential read after random access, enabling prefetching");
+            self.leave_direct_reads();
+            self.out_of_order_reads = 0;
+            self.reset_prefetch_to_offset(offset);
+            return Ok(None);
+        }
+        self.read_direct(offset, length).await.map(Some)
+    }
+
+    /// Serve a read from the data fetched by the previous direct read, or with a new ranged GET.
+    async fn read_direct(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<ChecksummedBytes, PrefetchReadError<Client::ClientError>> {
+        let end = offset + length as u64;
+        let direct_reads = self.direct_reads.as_ref().expect("direct reads are enabled");
+        if !direct_reads.covers(offset, length) {
+            let request_size = length.max(self.config.random_access_request_size) as u64;
+            let request_end = offset.saturating_add(request_size).min(self.size);
+            let part = self.fetch_range(offset..request_end).await?;
+            self.mem_limiter.reserve(BufferArea::Prefetch, part.len() as u64);
+            self.leave_direct_reads();
+            self.direct_reads = Some(Box::new(DirectReads {
+                buffer: Some(part),
+                last_read_end: None,
+            }));
+        }
+
+        let direct_reads = self.direct_reads.as_mut().expect("direct reads are enabled");
+        let buffer = direct_reads.buffer.as_ref().expect("buffer covers the read range");
+        let data = buffer.slice(offset..end).into_bytes(&self.object_id, offset)?;
+        direct_reads.last_read_end = Some(end);
+        // Keep the prefetching state consistent, should the reader become sequential again.
+        self.sequential_read_start_offset = offset;
+        self.next_sequential_read_offset = end;
+        self.next_request_offset = end;
+        Ok(data)
+    }
+
+    /// Drop the state of direct reads, releasing the memory of their buffer.
+    fn leave_direct_reads(&mut self) {
+        if let Some(buffer) = self.direct_reads.take().and_then(|direct_reads| direct_reads.buffer) {
+            self.mem_limiter.release(BufferArea::Prefetch, buffer.len() as u64);
+        }
+    }
+
+    /// Serve the read from a [StridedStream] if the object is being read with a fixed stride.
+    /// Returns None if the read doesn't follow the stride, in which case we stop prefetching with
+    /// it.
+    async fn try_read_strided(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<Option<ChecksummedBytes>, PrefetchReadError<Client::ClientError>> {
+        let Some(stream) = self.strided_stream.as_ref() else {
+            return Ok(None);
+        };
+        if !stream.can_serve(offset, length) {
+            trace!(offset, length, "read outside of strided stream, leaving strided mode");
+            self.strided_stream = None;
+            return Ok(None);
+        }
+        self.read_strided(offset, length).await.map(Some)
+    }
+
+    /// Start prefetching reads following `stride` from `offset`, and serve the read of `length`
+    /// bytes at `offset`.
+    async fn start_strided_reads(
+        &mut self,
+        offset: u64,
+        stride: Stride,
+        length: usize,
+    ) -> Result<ChecksummedBytes, PrefetchReadError<Client::ClientError>> {
+        let config = StridedStreamConfig {
+            bucket: self.bucket.clone(),
+            object_id: self.object_id.clone(),
+            handle_id: self.handle_id,
+            object_size: self.size,
+            first_offset: offset,
+            stride: stride.stride,
+            read_size: stride.length,
+            depth: self.config.strided_prefetch_depth,
+            preferred_part_size: self.preferred_part_size,
+            strategy: self.strategy.clone(),
+            budget: self.budget.clone(),
+            hedging: self.hedging.clone(),
+            adaptive_part_size: self.adaptive_part_size.clone(),
+            priority: self.priority,
+            bytes_prefetched: self.bytes_prefetched.clone(),
+        };
+        self.strided_stream = Some(Box::new(StridedStream::new(
+            self.part_stream.clone(),
+            config,
+            self.mem_limiter.clone(),
+        )));
+        self.read_strided(offset, length).await
+    }
+
+    async fn read_strided(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<ChecksummedBytes, PrefetchReadError<Client::ClientError>> {
+        let stream = self.strided_stream.as_mut().expect("strided reads are enabled");
+        let data = stream.read(offset, length).await?;
+        // Keep the prefetching state consistent, should the reader become sequential again.
+        let read_end = offset + data.len() as u64;
+        self.sequential_read_start_offset = offset;
+        self.next_sequential_read_offset = read_end;
+        self.next_request_offset = read_end;
+        Ok(data)
+    }
+
+    /// Serve the read from a [ReverseStream] if the object is being read backwards. Returns None if
+    /// the read should go through the forward prefetching path instead.
+    async fn try_read_backward(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<Option<ChecksummedBytes>, PrefetchReadError<Client::ClientError>> {
+        let read_end = offset + length as u64;
+        let previous_offset = self.last_read_offset.replace(offset);
+        if previous_offset.is_some_and(|previous| offset < previous && read_end >= previous) {
+            self.backward_read_count += 1;
+        } else {
+            self.backward_read_count = 0;
+        }
+
+        if let Some(stream) = self.reverse_stream.as_ref()
+            && !stream.can_serve(offset, length)
+        {
+            trace!(offset, length, "read outside of reverse stream, leaving reverse mode");
+            self.reverse_stream = None;
+        }
+
+        if self.reverse_stream.is_none() {
+            if self.backward_read_count < BACKWARD_READ_THRESHOLD {
+                return Ok(None);
+            }
+            trace!(
+                offset,
+                length, "backward sequential reads detected, prefetching in reverse"
+            );
+            counter!(PREFETCH_REVERSE_MODE).increment(1);
+            self.record_contiguous_read_metric();
+            self.reset_prefetch_to_offset(offset);
+            let config = ReverseStreamConfig {
+                bucket: self.bucket.clone(),
+                object_id: self.object_id.clone(),
+                handle_id: self.handle_id,
+                object_size: self.size,
+                end_offset: read_end,
+                initial_chunk_size: self
+                    .config
+                    .initial_request_size
+                    .min(self.config.max_read_window_size)
+                    .max(length),
+                max_chunk_size: self.config.max_read_window_size,
+                preferred_part_size: self.preferred_part_size,
+                strategy: self.strategy.clone(),
+                budget: self.budget.clone(),
+                hedging: self.hedging.clone(),
+                adaptive_part_size: self.adaptive_part_size.clone(),
+                priority: self.priority,
+                bytes_prefetched: self.bytes_prefetched.clone(),
+            };
+            self.reverse_stream = Some(Box::new(ReverseStream::new(
+                self.part_stream.clone(),
+                config,
+                self.mem_limiter.clone(),
+            )));
+        }
+
+        let stream = self.reverse_stream.as_mut().expect("reverse stream was just created");
+        let data = stream.read(offset, length).await?;
+        // Backward reads are served outside of the forward stream, so it restarts from here if the
+        // reader changes direction.
+        self.sequential_read_start_offset = offset;
+        self.next_sequential_read_offset = read_end;
+        self.next_request_offset = read_end;
+        Ok(Some(data))
+    }
+
     /// Spawn a backpressure GetObject request which has a range from current offset to the end of the file.
     /// We will be using flow-control window to control how much data we want to download into the prefetcher.
     fn spawn_read_backpressure_request(
@@ -438,8 +1119,38 @@ where
     ) -> Result<RequestTask<Client>, PrefetchReadError<Client::ClientError>> {
         let start = self.next_sequential_read_offset;
         let object_size = self.size as usize;
-        let read_part_size = self.part_stream.client().read_part_size();
-        let range = RequestRange::new(object_size, start, object_size);
+        let read_part_size = self
+            .adaptive_part_size
+            .part_size(self.part_stream.client().read_part_size());
+        if self.adaptive_part_size.is_enabled() {
+            histogram!(PREFETCH_REQUEST_PART_SIZE).record(read_part_size as f64);
+        }
+        let strategy_range = self.strategy.request_range(start, self.size);
+        debug_assert_eq!(
+            strategy_range.start, start,
+            "strategy must start requests at the read offset"
+        );
+        let mut range_end = strategy_range.end;
+        let mut initial_request_size = self.config.initial_request_size;
+        if let Some(column_chunk) = self
+            .parquet
+            .as_ref()
+            .and_then(|parquet| parquet.layout.as_ref())
+            .and_then(|layout| layout.column_chunk(start))
+        {
+            // Readers of Parquet files read column chunks independently, so don't prefetch past the
+            // end of this one. They usually read whole column chunks, so fetch all of it at once when
+            // we have the memory to.
+            range_end = range_end.min(column_chunk.end);
+            let chunk_len = range_end.saturating_sub(start) as usize;
+            if chunk_len <= self.config.max_read_window_size && self.mem_limiter.available_mem() >= chunk_len as u64 {
+                initial_request_size = chunk_len;
+            }
+            trace!(?column_chunk, "bounding request to Parquet column chunk");
+        }
+        // Requests must make progress, so they cover at least one byte of the object.
+        let range_size = range_end.saturating_sub(start).max(1) as usize;
+        let range = RequestRange::new(object_size, start, range_size);
 
         // The prefetcher now relies on backpressure mechanism so it must be enabled
         match self.part_stream.client().initial_read_window_size() {
@@ -459,16 +1170,127 @@ where
             range,
             read_part_size,
             preferred_part_size: self.preferred_part_size,
-            initial_request_size: self.config.initial_request_size,
+            initial_request_size,
             max_read_window_size: self.config.max_read_window_size,
-            read_window_size_multiplier: self.config.sequential_prefetch_multiplier,
+            strategy: self.strategy.clone(),
+            budget: self.budget.clone(),
+            hedging: self.hedging.clone(),
+            adaptive_part_size: self.adaptive_part_size.clone(),
+            priority: self.priority,
+            bytes_prefetched: self.bytes_prefetched.clone(),
+        };
+        let mut task = self.part_stream.spawn_get_object_request(config);
+        task.set_read_coalesce_size(self.config.read_coalesce_size);
+        Ok(task)
+    }
+
+    /// Start a new sequential stream at `offset`, keeping the current one so that the reader can
+    /// come back to it, see [PrefetcherConfig::max_streams_per_handle].
+    fn start_new_stream(&mut self, offset: u64) {
+        let mut parked_streams = std::mem::take(&mut self.parked_streams);
+        if let Some(stream) = self.take_current_stream() {
+            parked_streams.push_back(stream);
+        }
+        while parked_streams.len() >= self.config.max_streams_per_handle.max(1) {
+            let stream = parked_streams
+                .pop_front()
+                .expect("there are more parked streams than the limit");
+            self.preserve_received_parts(stream.task);
+        }
+        self.reset_prefetch_to_offset(offset);
+        self.parked_streams = parked_streams;
+    }
+
+    /// Make the stream continuing at `offset` the current one, if there is any. Returns true if
+    /// the switch succeeded, in which case self.next_sequential_read_offset will be `offset`.
+    async fn try_switch_stream(&mut self, offset: u64) -> Result<bool, PrefetchReadError<Client::ClientError>> {
+        let Some(index) = self
+            .parked_streams
+            .iter()
+            .position(|stream| stream.continues_at(offset))
+        else {
+            return Ok(false);
         };
-        Ok(self.part_stream.spawn_get_object_request(config))
+        let stream = self
+            .parked_streams
+            .remove(index)
+            .expect("index is within the parked streams");
+        if let Some(current) = self.take_current_stream() {
+            self.parked_streams.push_back(current);
+        }
+        trace!(from = self.next_sequential_read_offset, to = offset, "switching stream");
+        self.record_contiguous_read_metric();
+        self.backpressure_task = Some(stream.task);
+        self.sequential_read_start_offset = stream.sequential_read_start_offset;
+        self.next_sequential_read_offset = stream.next_sequential_read_offset;
+        self.next_request_offset = stream.next_request_offset;
+        if self.next_sequential_read_offset == offset {
+            return Ok(true);
+        }
+        self.try_seek(offset).await
+    }
+
+    /// Serve a seek landing within parts downloaded by a discarded request, by pushing them in front
+    /// of a new request starting where they end. Returns true if the seek succeeded, in which case
+    /// self.next_sequential_read_offset will be `offset`.
+    async fn try_resume_from_completed_parts(
+        &mut self,
+        offset: u64,
+    ) -> Result<bool, PrefetchReadError<Client::ClientError>> {
+        // A new request is needed to continue after the parts, so it can't start at the end of the object.
+        let Some(end) = self
+            .completed_parts
+            .contiguous_end(offset)
+            .filter(|&end| end < self.size)
+        else {
+            return Ok(false);
+        };
+        trace!(offset, end, "resuming from parts downloaded by a discarded request");
+        counter!(PREFETCH_COMPLETED_PARTS_REUSED).increment(1);
+        self.record_contiguous_read_metric();
+        let parts = self.completed_parts.take_from(offset);
+        self.start_new_stream(end);
+        let mut task = self.spawn_read_backpressure_request()?;
+        task.push_front(parts).await?;
+        self.backpressure_task = Some(task);
+        self.sequential_read_start_offset = offset;
+        self.next_sequential_read_offset = offset;
+        Ok(true)
+    }
+
+    /// Keep the parts a request downloaded but that haven't been read yet, and cancel it.
+    fn preserve_received_parts(&mut self, task: RequestTask<Client>) {
+        if self.config.max_preserved_parts_size == 0 {
+            return;
+        }
+        for part in task.into_received_parts() {
+            self.completed_parts.insert(part);
+        }
+    }
+
+    /// Take the current sequential stream out of this prefetch request, if it has more to read.
+    fn take_current_stream(&mut self) -> Option<ParkedStream<Client>> {
+        let task = self.backpressure_task.take()?;
+        // The backwards seek window only covers the data before the current read offset.
+        self.backward_seek_window.clear();
+        (self.next_sequential_read_offset < self.size).then_some(ParkedStream {
+            task,
+            sequential_read_start_offset: self.sequential_read_start_offset,
+            next_sequential_read_offset: self.next_sequential_read_offset,
+            next_request_offset: self.next_request_offset,
+        })
     }
 
     /// Reset this prefetch request to a new offset, clearing any existing tasks queued.
     fn reset_prefetch_to_offset(&mut self, offset: u64) {
-        self.backpressure_task = None;
+        if let Some(task) = self.backpressure_task.take() {
+            self.preserve_received_parts(task);
+        }
+        for stream in std::mem::take(&mut self.parked_streams) {
+            self.preserve_received_parts(stream.task);
+        }
+        self.reverse_stream = None;
+        self.strided_stream = None;
         self.backward_seek_window.clear();
         self.sequential_read_start_offset = offset;
         self.next_sequential_read_offset = offset;
@@ -576,6 +1398,10 @@ where
             self.backward_seek_window.max_size(),
         );
         self.mem_limiter.release(BufferArea::Prefetch, seek_window_reservation);
+        if let Some(footer) = self.parquet.as_ref().and_then(|parquet| parquet.footer.as_ref()) {
+            self.mem_limiter.release(BufferArea::Prefetch, footer.len() as u64);
+        }
+        self.leave_direct_reads();
         self.record_contiguous_read_metric();
     }
 }
@@ -596,12 +1422,15 @@ mod tests {
     use mountpoint_s3_client::failure_client::{
         CountdownFailureConfig, GetObjectFailureMode, countdown_failure_client,
     };
-    use mountpoint_s3_client::mock_client::{MockClient, MockClientConfig, MockClientError, MockObject, ramp_bytes};
+    use mountpoint_s3_client::mock_client::{
+        MockClient, MockClientConfig, MockClientError, MockObject, Operation, ramp_bytes,
+    };
     use mountpoint_s3_client::types::ETag;
     use proptest::proptest;
     use proptest::strategy::{Just, Strategy};
     use proptest_derive::Arbitrary;
     use std::collections::HashMap;
+    use std::time::Duration;
     use test_case::test_case;
 
     const KB: usize = 1024;
@@ -671,6 +1500,7 @@ mod tests {
             max_forward_seek_wait_distance: test_config.max_forward_seek_wait_distance,
             max_backward_seek_distance: test_config.max_backward_seek_distance,
             initial_request_size: test_config.initial_request_size,
+            ..Default::default()
         };
 
         let prefetcher = build_prefetcher(client.clone(), prefetcher_type, prefetcher_config);
@@ -970,6 +1800,7 @@ mod tests {
             max_forward_seek_wait_distance: test_config.max_forward_seek_wait_distance,
             max_backward_seek_distance: test_config.max_backward_seek_distance,
             initial_request_size: test_config.initial_request_size,
+            ..Default::default()
         };
 
         let prefetcher = build_prefetcher(client, prefetcher_type, prefetcher_config);
@@ -1362,6 +2193,648 @@ mod tests {
         assert_eq!(next_offset, object_size);
     }
 
+    /// A strategy that splits the object into fixed size chunks and requests them one at a time.
+    #[derive(Debug)]
+    struct FixedChunkStrategy {
+        chunk_size: u64,
+    }
+
+    impl PrefetchStrategy for FixedChunkStrategy {
+        fn request_range(&self, offset: u64, object_size: u64) -> std::ops::Range<u64> {
+            let chunk_end = (offset / self.chunk_size + 1) * self.chunk_size;
+            offset..chunk_end.min(object_size)
+        }
+
+        fn scale_up(&self, _current: usize, limits: ReadWindowLimits) -> usize {
+            limits.max
+        }
+
+        fn scale_down(&self, _current: usize, limits: ReadWindowLimits) -> usize {
+            limits.min
+        }
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_custom_strategy_request_range(prefetcher_type: PrefetcherType) {
+        const CHUNK_SIZE: u64 = 4 * MB as u64;
+        let object_size = 4 * CHUNK_SIZE + 123;
+
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(1 * MB)
+                .enable_backpressure(true)
+                .initial_read_window_size(1 * MB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher = build_prefetcher(client.clone(), prefetcher_type, Default::default())
+            .with_strategy(Arc::new(FixedChunkStrategy { chunk_size: CHUNK_SIZE }));
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+
+        let get_object_counter = client.new_counter(Operation::GetObject);
+        let mut next_offset = 0;
+        while next_offset < object_size {
+            let buf = block_on(request.read(next_offset, 1 * MB)).unwrap();
+            let buf = buf.into_bytes().unwrap();
+            let expected = ramp_bytes((0xaa + next_offset) as usize, buf.len());
+            assert_eq!(&buf[..], &expected[..]);
+            next_offset += buf.len() as u64;
+        }
+        assert_eq!(next_offset, object_size);
+        // Each full chunk is fetched with an initial request followed by a request for the rest of
+        // the chunk, while the last 123 bytes fit in a single initial request.
+        assert_eq!(get_object_counter.count(), 9);
+    }
+
+    #[test_case(PrefetcherType::Default, "data.parquet"; "default by key suffix")]
+    #[test_case(PrefetcherType::InMemoryCache(64 * KB), "data.parquet"; "cache by key suffix")]
+    #[test_case(PrefetcherType::Default, "data"; "default by magic bytes")]
+    #[test_case(PrefetcherType::InMemoryCache(64 * KB), "data"; "cache by magic bytes")]
+    fn test_parquet_column_chunk_reads(prefetcher_type: PrefetcherType, key: &str) {
+        let column_sizes = [3 * MB, 200 * KB, 5 * MB, 1 * MB + 7];
+        let (data, column_chunks) = parquet::test_utils::parquet_file(&column_sizes, |offset| offset as u8);
+        let object_size = data.len() as u64;
+        let uses_cache = matches!(prefetcher_type, PrefetcherType::InMemoryCache(_));
+
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::from(data.clone());
+        let etag = object.etag();
+        client.add_object(key, object);
+
+        let prefetcher_config = PrefetcherConfig {
+            format_aware: true,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new(key.to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+
+        let read_and_check = |request: &mut PrefetchGetObject<_>, offset: u64, length: usize| {
+            let buf = block_on(request.read(offset, length)).unwrap().into_bytes().unwrap();
+            let end = (offset + length as u64).min(object_size) as usize;
+            assert!(
+                buf[..] == data[offset as usize..end],
+                "wrong data for read at offset {offset}"
+            );
+        };
+
+        // Like analytics engines, read the length of the metadata, then the metadata
+        read_and_check(&mut request, object_size - 8, 8);
+        let metadata_len = u32::from_le_bytes(data[data.len() - 8..data.len() - 4].try_into().unwrap()) as u64;
+        read_and_check(&mut request, object_size - 8 - metadata_len, metadata_len as usize);
+        assert!(request.parquet.as_ref().is_some_and(|parquet| parquet.layout.is_some()));
+
+        // Then read some column chunks, out of order
+        let get_object_counter = client.new_counter(Operation::GetObject);
+        for index in [2, 0, 1] {
+            let chunk = &column_chunks[index];
+            let mut offset = chunk.start;
+            while offset < chunk.end {
+                let length = (128 * KB).min((chunk.end - offset) as usize);
+                read_and_check(&mut request, offset, length);
+                offset += length as u64;
+            }
+        }
+        // Each column chunk is fetched with a single request, ending at the end of the chunk
+        // (the caching prefetcher also splits requests by cache block).
+        if !uses_cache {
+            assert_eq!(get_object_counter.count(), 3);
+        }
+        // Reads in the footer are still served from memory
+        read_and_check(&mut request, column_chunks[3].start + 100, 1000);
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(64 * KB))]
+    fn test_random_access_detection(prefetcher_type: PrefetcherType) {
+        const THRESHOLD: usize = 4;
+        const REQUEST_SIZE: usize = 64 * KB;
+        let object_size = 64 * MB as u64;
+        let uses_cache = matches!(prefetcher_type, PrefetcherType::InMemoryCache(_));
+
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            random_access_threshold: THRESHOLD,
+            random_access_request_size: REQUEST_SIZE,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+
+        let read_and_check = |request: &mut PrefetchGetObject<_>, offset: u64, length: usize| {
+            let buf = block_on(request.read(offset, length)).unwrap().into_bytes().unwrap();
+            let expected = ramp_bytes((0xaa + offset) as usize, length);
+            assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+        };
+
+        // Small reads far apart from each other, until random access is detected. They are not
+        // evenly spaced, so that they aren't detected as strided reads.
+        let get_object_counter = client.new_counter(Operation::GetObject);
+        for i in 0..THRESHOLD as u64 {
+            assert!(request.direct_reads.is_none());
+            read_and_check(&mut request, (i * i * 5 + 3) * MB as u64, 4 * KB);
+        }
+        assert!(request.direct_reads.is_some());
+        let requests_before_detection = get_object_counter.count();
+
+        // Each further random read makes a single small request, and nearby reads reuse its data
+        for i in 0..8u64 {
+            let offset = (50 - i * 4) * MB as u64 + 1000;
+            read_and_check(&mut request, offset, 4 * KB);
+            read_and_check(&mut request, offset + 8 * KB as u64, 4 * KB);
+        }
+        if !uses_cache {
+            assert_eq!(get_object_counter.count() - requests_before_detection, 8);
+        }
+
+        // A read larger than the request size is served with a request of its own size
+        read_and_check(&mut request, 2 * MB as u64, 3 * REQUEST_SIZE);
+        assert!(request.direct_reads.is_some());
+
+        // Reading sequentially through the data of the last request enables prefetching again
+        let mut offset = 20 * MB as u64;
+        for _ in 0..(REQUEST_SIZE / (16 * KB)) {
+            read_and_check(&mut request, offset, 16 * KB);
+            offset += 16 * KB as u64;
+        }
+        assert!(request.direct_reads.is_some());
+        read_and_check(&mut request, offset, 16 * KB);
+        assert!(request.direct_reads.is_none());
+        assert!(request.backpressure_task.is_some());
+    }
+
+    #[test]
+    fn test_random_access_detection_disabled() {
+        let object_size = 16 * MB as u64;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            random_access_threshold: 0,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client, PrefetcherType::Default, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+        for i in 0..32u64 {
+            let offset = (i * 5 % 16) * MB as u64;
+            let _ = block_on(request.read(offset, 4 * KB)).unwrap();
+            assert!(request.direct_reads.is_none());
+        }
+    }
+
+    #[test_case(4; "enabled")]
+    #[test_case(0; "disabled")]
+    fn test_strided_reads(strided_prefetch_depth: usize) {
+        let object_size = 64 * MB as u64;
+        let stride = 8 * MB as u64;
+        let read_size = 64 * KB;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 1 * MB,
+            initial_request_size: 256 * KB,
+            strided_prefetch_depth,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), PrefetcherType::Default, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+        let get_object_counter = client.new_counter(Operation::GetObject);
+
+        let mut requests_before_detection = 0;
+        for (i, offset) in (0..object_size).step_by(stride as usize).enumerate() {
+            // Each read is split in two halves, as FUSE might do.
+            for half in [0, read_size as u64 / 2] {
+                let buf = block_on(request.read(offset + half, read_size / 2)).unwrap();
+                let buf = buf.into_bytes().unwrap();
+                let expected = ramp_bytes((0xaa + offset + half) as usize, read_size / 2);
+                assert!(
+                    buf[..] == expected[..],
+                    "wrong data for read at offset {}",
+                    offset + half
+                );
+            }
+            if i == 2 {
+                requests_before_detection = get_object_counter.count();
+            }
+            // The two halves are, taken together, a strided read, so reads are detected as strided
+            // from the fourth one.
+            assert_eq!(request.strided_stream.is_some(), i >= 3 && strided_prefetch_depth > 0);
+        }
+        if strided_prefetch_depth > 0 {
+            // The forward request for the third read may only reach the client after it was counted.
+            let requests = get_object_counter.count() - requests_before_detection;
+            assert!(
+                (5..=6).contains(&requests),
+                "there should be a single request for each strided read, got {requests}"
+            );
+        }
+
+        // Leaving the stride falls back to prefetching.
+        let _ = block_on(request.read(1, read_size)).unwrap();
+        assert!(request.strided_stream.is_none());
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_hedged_requests_return_correct_data(prefetcher_type: PrefetcherType) {
+        let object_size = 2 * MB;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        // Hedge any request slower than the fastest one seen recently.
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 1 * MB,
+            initial_request_size: 256 * KB,
+            hedge_percentile: Some(0.0),
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client, prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        for i in 0..30 {
+            let mut request = prefetcher.prefetch(
+                "test-bucket".to_owned(),
+                object_id.clone(),
+                HandleId::new(i),
+                object_size as u64,
+            );
+            let mut offset = 0;
+            while offset < object_size {
+                let buf = block_on(request.read(offset as u64, 128 * KB)).unwrap();
+                let buf = buf.into_bytes().unwrap();
+                let expected = ramp_bytes(0xaa + offset, buf.len());
+                assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+                offset += buf.len();
+            }
+        }
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_adaptive_part_size(prefetcher_type: PrefetcherType) {
+        let object_size = 4 * MB;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(64 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(64 * KB)
+                // The caching prefetcher doesn't align read windows to the part size.
+                .fail_on_non_aligned_read_window(matches!(prefetcher_type, PrefetcherType::Default))
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 2 * MB,
+            initial_request_size: 0,
+            max_read_part_size: Some(512 * KB),
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client, prefetcher_type, prefetcher_config);
+        // Pretend previous requests saw a high latency, so that larger parts are needed.
+        for _ in 0..10 {
+            prefetcher.adaptive_part_size.record_request(
+                Duration::from_millis(100),
+                100 * MB as u64,
+                Duration::from_secs(1),
+            );
+        }
+        assert_eq!(prefetcher.adaptive_part_size.part_size(64 * KB), 512 * KB);
+
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch(
+            "test-bucket".to_owned(),
+            object_id,
+            HandleId::new(0),
+            object_size as u64,
+        );
+        let mut offset = 0;
+        while offset < object_size {
+            let buf = block_on(request.read(offset as u64, 128 * KB)).unwrap();
+            let buf = buf.into_bytes().unwrap();
+            let expected = ramp_bytes(0xaa + offset, buf.len());
+            assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+            offset += buf.len();
+        }
+    }
+
+    #[test_case(PrefetcherType::Default, 64 * KB; "coalesced")]
+    #[test_case(PrefetcherType::Default, 0; "not coalesced")]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB), 64 * KB; "coalesced with cache")]
+    fn test_small_reads(prefetcher_type: PrefetcherType, read_coalesce_size: usize) {
+        let object_size = 1 * MB;
+        let read_size = 4 * KB + 1;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 512 * KB,
+            initial_request_size: 128 * KB,
+            read_coalesce_size,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch(
+            "test-bucket".to_owned(),
+            object_id,
+            HandleId::new(1),
+            object_size as u64,
+        );
+        let get_object_counter = client.new_counter(Operation::GetObject);
+
+        let mut offset = 0;
+        let mut reads = 0;
+        while offset < object_size {
+            let buf = block_on(request.read(offset as u64, read_size)).unwrap();
+            let buf = buf.into_bytes().unwrap();
+            let expected = ramp_bytes(0xaa + offset, buf.len());
+            assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+            reads += 1;
+            // Seek backwards and forwards now and then, within the chunk being read and outside of it.
+            offset = match reads % 7 {
+                3 => offset.saturating_sub(read_size / 2),
+                5 => offset + buf.len() + 2 * read_size,
+                6 => offset.saturating_sub(16 * KB),
+                _ => offset + buf.len(),
+            };
+        }
+        assert_eq!(
+            get_object_counter.count(),
+            2,
+            "seeks within the read window should not start new requests after the initial one"
+        );
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_prefetch_stats(prefetcher_type: PrefetcherType) {
+        let object_size = 8 * MB;
+        let read_size = 64 * KB;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 1 * MB,
+            initial_request_size: 256 * KB,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client, prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch(
+            "test-bucket".to_owned(),
+            object_id,
+            HandleId::new(1),
+            object_size as u64,
+        );
+        assert_eq!(request.stats(), PrefetchStats::default());
+
+        for offset in (0..4 * read_size).step_by(read_size).chain([6 * MB]) {
+            let _ = block_on(request.read(offset as u64, read_size)).unwrap();
+        }
+        let stats = request.stats();
+        assert_eq!(stats.bytes_read, 5 * read_size as u64);
+        assert_eq!(stats.out_of_order_reads, 1);
+        assert_eq!(
+            stats.restarts, 1,
+            "the seek past the read window should restart prefetching"
+        );
+        assert!(stats.read_window_size > 0);
+        assert!(stats.bytes_prefetched >= stats.bytes_read, "unexpected stats: {stats}");
+    }
+
+    #[test_case(2; "two streams")]
+    #[test_case(1; "single stream")]
+    fn test_interleaved_sequential_streams(max_streams_per_handle: usize) {
+        let object_size = 16 * MB as u64;
+        let tail_offset = 8 * MB as u64;
+        let read_size = 64 * KB;
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 1 * MB,
+            initial_request_size: 256 * KB,
+            max_streams_per_handle,
+            // Only track streams, without keeping the data of the discarded ones.
+            max_preserved_parts_size: 0,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), PrefetcherType::Default, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+        let get_object_counter = client.new_counter(Operation::GetObject);
+
+        // Read the head and the tail of the object in turns.
+        for i in 0..32 {
+            for offset in [i * read_size as u64, tail_offset + i * read_size as u64] {
+                let buf = block_on(request.read(offset, read_size)).unwrap();
+                let buf = buf.into_bytes().unwrap();
+                let expected = ramp_bytes((0xaa + offset) as usize, read_size);
+                assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+            }
+        }
+        if max_streams_per_handle > 1 {
+            // The initial request and the one for the rest of the object, for each stream.
+            assert_eq!(get_object_counter.count(), 4, "both streams should be kept");
+        } else {
+            assert_eq!(get_object_counter.count(), 64, "every read should restart prefetching");
+        }
+    }
+
+    /// Run the reads and return the number of GetObject requests and of reads served in reverse mode.
+    fn run_backward_read_test(
+        prefetcher_type: PrefetcherType,
+        object_size: u64,
+        reads: &[(u64, usize)],
+    ) -> (u64, usize) {
+        let client = Arc::new(
+            MockClient::config()
+                .bucket("test-bucket")
+                .part_size(256 * KB)
+                .enable_backpressure(true)
+                .initial_read_window_size(256 * KB)
+                .build(),
+        );
+        let object = MockObject::ramp(0xaa, object_size as usize, ETag::for_tests());
+        let etag = object.etag();
+        client.add_object("test-object", object);
+
+        let prefetcher_config = PrefetcherConfig {
+            max_read_window_size: 4 * MB,
+            initial_request_size: 256 * KB,
+            ..Default::default()
+        };
+        let prefetcher = build_prefetcher(client.clone(), prefetcher_type, prefetcher_config);
+        let object_id = ObjectId::new("test-object".to_owned(), etag);
+        let mut request = prefetcher.prefetch("test-bucket".to_owned(), object_id, HandleId::new(1), object_size);
+
+        let get_object_counter = client.new_counter(Operation::GetObject);
+        let mut reverse_reads = 0;
+        for &(offset, length) in reads {
+            let buf = block_on(request.read(offset, length)).unwrap();
+            let buf = buf.into_bytes().unwrap();
+            let expected_len = length.min(object_size.saturating_sub(offset) as usize);
+            let expected = ramp_bytes((0xaa + offset) as usize, expected_len);
+            assert_eq!(buf.len(), expected_len, "wrong length for read at offset {offset}");
+            assert!(buf[..] == expected[..], "wrong data for read at offset {offset}");
+            if request.reverse_stream.is_some() {
+                reverse_reads += 1;
+            }
+        }
+        (get_object_counter.count(), reverse_reads)
+    }
+
+    /// Reads of `read_size` bytes covering `range` from its end to its start.
+    fn backward_reads(range: std::ops::Range<u64>, read_size: usize) -> Vec<(u64, usize)> {
+        let mut reads = Vec::new();
+        let mut end = range.end;
+        while end > range.start {
+            let start = end.saturating_sub(read_size as u64).max(range.start);
+            reads.push((start, (end - start) as usize));
+            end = start;
+        }
+        reads
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_backward_sequential_read(prefetcher_type: PrefetcherType) {
+        let object_size = 16 * MB as u64 + 111;
+        let reads = backward_reads(0..object_size, 128 * KB);
+
+        let (requests, reverse_reads) = run_backward_read_test(prefetcher_type, object_size, &reads);
+        assert_eq!(reverse_reads, reads.len() - BACKWARD_READ_THRESHOLD);
+        // One request per read until the pattern is detected, then chunks growing up to 4MiB
+        assert!(
+            requests < 16,
+            "expected few requests for {} backward reads, got {requests}",
+            reads.len()
+        );
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_mixed_forward_backward_read(prefetcher_type: PrefetcherType) {
+        let object_size = 8 * MB as u64;
+        let mut reads = Vec::new();
+        // Forward through the first half, then backwards through it again
+        reads.extend((0..32).map(|i| (i * 128 * KB as u64, 128 * KB)));
+        reads.extend(backward_reads(0..4 * MB as u64, 100 * KB));
+        // Forward from somewhere in the middle, which leaves reverse mode
+        reads.extend((0..8).map(|i| (2 * MB as u64 + i * 64 * KB as u64, 64 * KB)));
+        // Backwards from the end of the object, then forward again from where that stopped
+        reads.extend(backward_reads(6 * MB as u64..object_size, 200 * KB));
+        reads.extend((0..16).map(|i| (6 * MB as u64 + i * 128 * KB as u64, 128 * KB)));
+
+        let (_, reverse_reads) = run_backward_read_test(prefetcher_type, object_size, &reads);
+        assert!(reverse_reads > 0);
+    }
+
+    #[test_case(PrefetcherType::Default)]
+    #[test_case(PrefetcherType::InMemoryCache(1 * MB))]
+    fn test_readahead_does_not_trigger_reverse_mode(prefetcher_type: PrefetcherType) {
+        let object_size = 4 * MB as u64;
+        // A sequential reader whose reads are sometimes reordered by readahead
+        let mut reads: Vec<_> = (0..8).map(|i| (i * 128 * KB as u64, 128 * KB)).collect();
+        reads.extend([
+            (1280 * KB as u64, 128 * KB),
+            (1024 * KB as u64, 128 * KB),
+            (1152 * KB as u64, 128 * KB),
+            (1408 * KB as u64, 128 * KB),
+            (1664 * KB as u64, 128 * KB),
+            (1536 * KB as u64, 128 * KB),
+            (1792 * KB as u64, 128 * KB),
+        ]);
+
+        let (_, reverse_reads) = run_backward_read_test(prefetcher_type, object_size, &reads);
+        assert_eq!(reverse_reads, 0);
+    }
+
     #[cfg(feature = "shuttle")]
     mod shuttle_tests {
         use super::*;
@@ -1409,6 +2882,7 @@ mod tests {
                 max_forward_seek_wait_distance,
                 max_backward_seek_distance,
                 initial_request_size,
+                ..Default::default()
             };
 
             let prefetcher =
@@ -1472,6 +2946,7 @@ mod tests {
                 max_forward_seek_wait_distance,
                 max_backward_seek_distance,
                 initial_request_size,
+                ..Default::default()
             };
 
             let prefetcher =
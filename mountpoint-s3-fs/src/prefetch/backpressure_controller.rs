@@ -8,6 +8,8 @@ use tracing::trace;
 use crate::mem_limiter::{BufferArea, MemoryLimiter};
 
 use super::PrefetchReadError;
+use super::budget::{BudgetReservation, PrefetchBudget, PrefetchPriority};
+use super::strategy::{PrefetchStrategy, ReadWindowLimits, clamped_scale_down, clamped_scale_up};
 
 #[derive(Debug)]
 pub enum BackpressureFeedbackEvent {
@@ -56,8 +58,12 @@ pub struct BackpressureConfig {
     pub min_read_window_size: usize,
     /// Maximum read window size that the backpressure controller is allowed to scale up to
     pub max_read_window_size: usize,
-    /// Factor to increase the read window size by when the part queue is stalled
-    pub read_window_size_multiplier: usize,
+    /// Strategy deciding how to scale the read window when the part queue is stalled or memory is low
+    pub strategy: Arc<dyn PrefetchStrategy>,
+    /// Memory budget shared with the read windows of other requests
+    pub budget: Arc<PrefetchBudget>,
+    /// Priority of the reader when sharing the budget
+    pub priority: PrefetchPriority,
     /// Request range to apply backpressure
     pub request_range: Range<u64>,
     /// Enable alignment of read window end to part boundary
@@ -75,8 +81,8 @@ pub struct BackpressureController {
     preferred_read_window_size: usize,
     min_read_window_size: usize,
     max_read_window_size: usize,
-    /// Multiplier by which [Self::preferred_read_window_size] is scaled.
-    read_window_size_multiplier: usize,
+    /// Strategy by which [Self::preferred_read_window_size] is scaled.
+    strategy: Arc<dyn PrefetchStrategy>,
     /// Upper bound of the current read window, relative to the start of the S3 object.
     ///
     /// The request can return data up to this offset *exclusively*.
@@ -92,6 +98,9 @@ pub struct BackpressureController {
     ///
     /// For example, when memory is low we should scale down [Self::preferred_read_window_size].
     mem_limiter: Arc<MemoryLimiter>,
+    /// Share of the prefetch memory budget held by this read window. Like the memory limiter, it is
+    /// used to scale down [Self::preferred_read_window_size] when other read windows need memory.
+    budget: BudgetReservation,
     /// Enable alignment of read window end to part boundary
     read_window_alignment_config: ReadWindowAlignmentConfig,
 }
@@ -120,10 +129,10 @@ pub fn new_backpressure_controller(
     config: BackpressureConfig,
     mem_limiter: Arc<MemoryLimiter>,
 ) -> (BackpressureController, BackpressureLimiter) {
-    // Minimum window size multiplier as the scaling up and down won't work if the multiplier is 1.
-    const MIN_WINDOW_SIZE_MULTIPLIER: usize = 2;
     let read_window_end_offset = config.request_range.start + config.initial_read_window_size as u64;
     mem_limiter.reserve(BufferArea::Prefetch, config.initial_read_window_size as u64);
+    let mut budget = config.budget.register(config.priority);
+    budget.reserve(config.initial_read_window_size as u64);
 
     let (read_window_updater, read_window_increment_queue) = unbounded();
     let read_window_increment_queue = ReadWindowIncrementQueue::new(read_window_increment_queue);
@@ -133,11 +142,12 @@ pub fn new_backpressure_controller(
         preferred_read_window_size: config.initial_read_window_size,
         min_read_window_size: config.min_read_window_size,
         max_read_window_size: config.max_read_window_size,
-        read_window_size_multiplier: config.read_window_size_multiplier.max(MIN_WINDOW_SIZE_MULTIPLIER),
+        strategy: config.strategy,
         read_window_end_offset,
         next_read_offset: config.request_range.start,
         request_end_offset: config.request_range.end,
         mem_limiter,
+        budget,
         read_window_alignment_config: config.read_window_alignment_config,
     };
 
@@ -157,6 +167,10 @@ impl BackpressureController {
         self.read_window_end_offset
     }
 
+    pub fn preferred_read_window_size(&self) -> usize {
+        self.preferred_read_window_size
+    }
+
     /// Send a feedback to the backpressure controller when reading data out of the stream. The backpressure controller
     /// will ensure that the read window size is enough to read this offset and that it is always close to `preferred_read_window_size`.
     pub async fn send_feedback<E>(&mut self, event: BackpressureFeedbackEvent) -> Result<(), PrefetchReadError<E>> {
@@ -165,6 +179,7 @@ impl BackpressureController {
             BackpressureFeedbackEvent::DataRead { offset, length } => {
                 self.next_read_offset = offset + length as u64;
                 self.mem_limiter.release(BufferArea::Prefetch, length as u64);
+                self.budget.release(length as u64);
                 let remaining_window = self.read_window_end_offset.saturating_sub(self.next_read_offset) as usize;
 
                 // Increment the read window only if the remaining window reaches some threshold i.e. half of it left.
@@ -195,19 +210,22 @@ impl BackpressureController {
                     if self.preferred_read_window_size <= self.min_read_window_size {
                         trace!(new_read_window_end_offset, "sending a read window increment");
                         self.mem_limiter.reserve(BufferArea::Prefetch, to_increase as u64);
+                        self.budget.reserve(to_increase as u64);
                         self.increment_read_window(to_increase).await;
                         break;
                     }
 
                     // Try to reserve the memory for the length we want to increase before sending the request,
-                    // scale down the read window if it fails.
-                    if self.mem_limiter.try_reserve(BufferArea::Prefetch, to_increase as u64) {
-                        trace!(new_read_window_end_offset, "sending a read window increment");
-                        self.increment_read_window(to_increase).await;
-                        break;
-                    } else {
-                        self.scale_down();
+                    // both from the prefetch budget and the memory limiter, and scale down the read window if it fails.
+                    if self.budget.try_reserve(to_increase as u64) {
+                        if self.mem_limiter.try_reserve(BufferArea::Prefetch, to_increase as u64) {
+                            trace!(new_read_window_end_offset, "sending a read window increment");
+                            self.increment_read_window(to_increase).await;
+                            break;
+                        }
+                        self.budget.release(to_increase as u64);
                     }
+                    self.scale_down();
                 }
             }
             BackpressureFeedbackEvent::PartQueueStall => self.scale_up(),
@@ -233,20 +251,33 @@ impl BackpressureController {
         self.read_window_end_offset = next_window_end_offset;
     }
 
-    /// Scale up preferred read window size with a multiplier configured at initialization.
+    fn read_window_limits(&self) -> ReadWindowLimits {
+        ReadWindowLimits {
+            min: self.min_read_window_size,
+            max: self.max_read_window_size,
+        }
+    }
+
+    /// Scale up preferred read window size with the strategy configured at initialization.
     ///
-    /// Fails silently if there is insufficient free memory to perform it according to [Self::mem_limiter].
+    /// Fails silently if there is insufficient free memory to perform it according to [Self::mem_limiter]
+    /// or [Self::budget].
     fn scale_up(&mut self) {
         if self.preferred_read_window_size < self.max_read_window_size {
-            let new_read_window_size = (self.preferred_read_window_size * self.read_window_size_multiplier)
-                .max(self.min_read_window_size)
-                .min(self.max_read_window_size);
+            let new_read_window_size = clamped_scale_up(
+                &*self.strategy,
+                self.preferred_read_window_size,
+                self.read_window_limits(),
+            );
+            if new_read_window_size <= self.preferred_read_window_size {
+                return;
+            }
             // Only scale up when there is enough memory. We don't have to reserve the memory here
             // because only `preferred_read_window_size` is increased but the actual read window will
             // be updated later on `DataRead` event (where we do reserve memory).
             let to_increase = (new_read_window_size - self.preferred_read_window_size) as u64;
             let available_mem = self.mem_limiter.available_mem();
-            if available_mem >= to_increase {
+            if available_mem >= to_increase && self.budget.can_grow(to_increase) {
                 let formatter = make_format(humansize::BINARY);
                 trace!(
                     prev_size = formatter(self.preferred_read_window_size),
@@ -260,13 +291,21 @@ impl BackpressureController {
         }
     }
 
-    /// Scale down [Self::preferred_read_window_size] by a multiplier configured at initialization.
+    /// Scale down [Self::preferred_read_window_size] with the strategy configured at initialization.
     fn scale_down(&mut self) {
         if self.preferred_read_window_size > self.min_read_window_size {
-            assert!(self.read_window_size_multiplier > 1);
-            let new_read_window_size = (self.preferred_read_window_size / self.read_window_size_multiplier)
-                .max(self.min_read_window_size)
-                .min(self.max_read_window_size);
+            let new_read_window_size = clamped_scale_down(
+                &*self.strategy,
+                self.preferred_read_window_size,
+                self.read_window_limits(),
+            );
+            // Make progress even if the strategy declines to scale down, otherwise we would keep
+            // failing to reserve memory for the same read window.
+            let new_read_window_size = if new_read_window_size >= self.preferred_read_window_size {
+                self.min_read_window_size
+            } else {
+                new_read_window_size
+            };
             let formatter = make_format(humansize::BINARY);
             trace!(
                 current_size = formatter(self.preferred_read_window_size),
@@ -380,6 +419,7 @@ mod tests {
     use crate::mem_limiter::MemoryLimiter;
     use crate::memory::PagedPool;
     use crate::prefetch::INITIAL_REQUEST_SIZE;
+    use crate::prefetch::strategy::SequentialPrefetchStrategy;
 
     #[test_case(INITIAL_REQUEST_SIZE, 2)] // real config
     #[test_case(3 * 1024 * 1024, 4)]
@@ -391,7 +431,9 @@ mod tests {
             initial_read_window_size,
             min_read_window_size: 8 * 1024 * 1024,
             max_read_window_size: 2 * 1024 * 1024 * 1024,
-            read_window_size_multiplier,
+            strategy: Arc::new(SequentialPrefetchStrategy::new(read_window_size_multiplier)),
+            budget: Arc::new(PrefetchBudget::new(None)),
+            priority: PrefetchPriority::Interactive,
             request_range,
             read_window_alignment_config: ReadWindowAlignmentConfig::Disable,
         };
@@ -419,7 +461,9 @@ mod tests {
             initial_read_window_size,
             min_read_window_size: 8 * 1024 * 1024,
             max_read_window_size: 2 * 1024 * 1024 * 1024,
-            read_window_size_multiplier,
+            strategy: Arc::new(SequentialPrefetchStrategy::new(read_window_size_multiplier)),
+            budget: Arc::new(PrefetchBudget::new(None)),
+            priority: PrefetchPriority::Interactive,
             request_range,
             read_window_alignment_config: ReadWindowAlignmentConfig::Disable,
         };
@@ -449,7 +493,9 @@ mod tests {
             initial_read_window_size: 1 * MIB,
             min_read_window_size: 8 * MIB,
             max_read_window_size: 2 * GIB,
-            read_window_size_multiplier: 2,
+            strategy: Arc::new(SequentialPrefetchStrategy::new(2)),
+            budget: Arc::new(PrefetchBudget::new(None)),
+            priority: PrefetchPriority::Interactive,
             request_range: 0..(5 * GIB as u64),
             read_window_alignment_config: ReadWindowAlignmentConfig::Disable,
         };
@@ -484,6 +530,64 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_read_window_scaled_down_to_fair_share_of_budget() {
+        const MIB: usize = 1024 * 1024;
+
+        let pool = PagedPool::new_with_candidate_sizes([8 * MIB]);
+        let mem_limiter = Arc::new(MemoryLimiter::new(pool, 1024 * MIB as u64));
+        let budget = Arc::new(PrefetchBudget::new(Some(16 * MIB as u64)));
+        let config = || BackpressureConfig {
+            initial_read_window_size: 8 * MIB,
+            min_read_window_size: MIB,
+            max_read_window_size: 64 * MIB,
+            strategy: Arc::new(SequentialPrefetchStrategy::new(2)),
+            budget: budget.clone(),
+            priority: PrefetchPriority::Interactive,
+            request_range: 0..(1024 * MIB as u64),
+            read_window_alignment_config: ReadWindowAlignmentConfig::Disable,
+        };
+
+        let (mut first, _first_limiter) = new_backpressure_controller(config(), mem_limiter.clone());
+        first.scale_up();
+        first.scale_up();
+        assert_eq!(
+            first.preferred_read_window_size,
+            16 * MIB,
+            "a single read window should only grow to the whole budget"
+        );
+
+        let (_second, _second_limiter) = new_backpressure_controller(config(), mem_limiter.clone());
+        block_on(async {
+            first
+                .send_feedback::<MockClientError>(BackpressureFeedbackEvent::DataRead {
+                    offset: 0,
+                    length: 4 * MIB,
+                })
+                .await
+                .unwrap();
+            assert_eq!(
+                first.preferred_read_window_size,
+                8 * MIB,
+                "read window should be scaled down to its share of the budget"
+            );
+
+            first
+                .send_feedback::<MockClientError>(BackpressureFeedbackEvent::DataRead {
+                    offset: 4 * MIB as u64,
+                    length: 2 * MIB,
+                })
+                .await
+                .unwrap();
+            assert_eq!(
+                first.read_window_end_offset(),
+                14 * MIB as u64,
+                "read window should still grow within its share of the budget"
+            );
+        });
+        assert_eq!(budget.reserved(), 16 * MIB as u64);
+    }
+
     #[test_case(500, 1000, 100, 500; "offset before second request start")]
     #[test_case(1000, 1000, 512, 1000; "offset at second request start")]
     #[test_case(1500, 1000, 512, 1512; "offset after second request start, needs alignment")]
@@ -0,0 +1,247 @@
+//! A memory budget for prefetched data, shared by all the file handles of a [super::Prefetcher].
+//!
+//! The [MemoryLimiter](crate::mem_limiter::MemoryLimiter) bounds the memory used by the whole process, but lets the
+//! first readers grow their read windows into most of it, leaving little for readers opening files later. A
+//! [PrefetchBudget] bounds the memory used by read windows, and splits it fairly between the prefetching requests
+//! currently running: each of them can always grow its read window up to its share, `limit / readers`, but only grows
+//! past it while the budget isn't exhausted. When new readers start, readers holding more than their share fail to
+//! grow their read window, and scale it down instead. Read windows at their minimum size are never blocked by the
+//! budget, so reads don't fail even if the budget is too small for the number of readers.
+//!
+//! Readers have a [PrefetchPriority]. Interactive readers are entitled to a larger share of the budget than batch
+//! readers, and while any interactive reader is active, batch readers never grow their read window past their share,
+//! even if the budget isn't exhausted (or isn't limited at all), leaving memory and client bandwidth to interactive
+//! readers.
+
+use metrics::{counter, gauge};
+use tracing::trace;
+
+use crate::metrics::defs::{PREFETCH_BUDGET_PRESSURE, PREFETCH_BUDGET_RESERVED};
+use crate::sync::Arc;
+use crate::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Priority class of a reader, see [super::PrefetchGetObject::set_priority].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrefetchPriority {
+    /// Latency-sensitive reads, for example from an application waiting on the data.
+    #[default]
+    Interactive,
+    /// Throughput-oriented reads, for example from a copy or a backup, which yield to interactive ones.
+    Batch,
+}
+
+impl PrefetchPriority {
+    /// Weight of the readers of this priority, when splitting the budget between them.
+    fn weight(&self) -> usize {
+        match self {
+            PrefetchPriority::Interactive => 4,
+            PrefetchPriority::Batch => 1,
+        }
+    }
+}
+
+/// Memory budget shared by the read windows of all prefetching requests.
+#[derive(Debug)]
+pub struct PrefetchBudget {
+    /// Maximum memory for read windows in bytes, or [None] if only the memory limiter applies.
+    limit: Option<u64>,
+    /// Memory reserved by all the read windows.
+    reserved: AtomicU64,
+    /// Sum of the weights of the read windows sharing the budget.
+    weights: AtomicUsize,
+    /// Number of read windows with [PrefetchPriority::Interactive].
+    interactive_readers: AtomicUsize,
+}
+
+impl PrefetchBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            reserved: AtomicU64::new(0),
+            weights: AtomicUsize::new(0),
+            interactive_readers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register a new reader, which holds its share of the budget until the returned
+    /// [BudgetReservation] is dropped.
+    pub(super) fn register(self: &Arc<Self>, priority: PrefetchPriority) -> BudgetReservation {
+        self.weights.fetch_add(priority.weight(), Ordering::SeqCst);
+        if priority == PrefetchPriority::Interactive {
+            self.interactive_readers.fetch_add(1, Ordering::SeqCst);
+        }
+        BudgetReservation {
+            budget: self.clone(),
+            priority,
+            reserved: 0,
+        }
+    }
+
+    /// Memory reserved by all the readers.
+    pub fn reserved(&self) -> u64 {
+        self.reserved.load(Ordering::SeqCst)
+    }
+
+    /// The share of the budget a reader of the given priority is entitled to, if the budget is limited.
+    fn fair_share(&self, priority: PrefetchPriority) -> Option<u64> {
+        let weights = self.weights.load(Ordering::SeqCst).max(1) as u64;
+        self.limit
+            .map(|limit| limit.saturating_mul(priority.weight() as u64) / weights)
+    }
+
+    fn has_interactive_readers(&self) -> bool {
+        self.interactive_readers.load(Ordering::SeqCst) > 0
+    }
+
+    /// Whether `size` more bytes can be reserved without exceeding the limit.
+    fn has_room(&self, size: u64) -> bool {
+        self.limit
+            .is_none_or(|limit| self.reserved().saturating_add(size) <= limit)
+    }
+
+    fn add(&self, size: u64) {
+        self.reserved.fetch_add(size, Ordering::SeqCst);
+        gauge!(PREFETCH_BUDGET_RESERVED).increment(size as f64);
+    }
+
+    fn sub(&self, size: u64) {
+        self.reserved.fetch_sub(size, Ordering::SeqCst);
+        gauge!(PREFETCH_BUDGET_RESERVED).decrement(size as f64);
+    }
+}
+
+/// The part of a [PrefetchBudget] reserved by a single reader.
+#[derive(Debug)]
+pub(super) struct BudgetReservation {
+    budget: Arc<PrefetchBudget>,
+    priority: PrefetchPriority,
+    reserved: u64,
+}
+
+impl BudgetReservation {
+    /// Reserve memory from the budget. Always succeeds, even if it means going beyond the limit.
+    pub fn reserve(&mut self, size: u64) {
+        self.reserved += size;
+        self.budget.add(size);
+    }
+
+    /// Reserve memory from the budget, if it is within this reader's share or there is room left
+    /// in the budget that isn't left to interactive readers. Returns `false` otherwise.
+    pub fn try_reserve(&mut self, size: u64) -> bool {
+        if !self.can_grow(size) {
+            trace!(size, reserved = self.reserved, "not enough prefetch budget to reserve");
+            counter!(PREFETCH_BUDGET_PRESSURE).increment(1);
+            return false;
+        }
+        self.reserve(size);
+        true
+    }
+
+    /// Whether [Self::try_reserve] would currently succeed for `size` bytes.
+    pub fn can_grow(&self, size: u64) -> bool {
+        let yields = self.priority == PrefetchPriority::Batch && self.budget.has_interactive_readers();
+        match self.budget.fair_share(self.priority) {
+            None => !yields,
+            Some(share) => self.reserved.saturating_add(size) <= share || (!yields && self.budget.has_room(size)),
+        }
+    }
+
+    /// Release memory reserved by this reader.
+    pub fn release(&mut self, size: u64) {
+        let size = size.min(self.reserved);
+        self.reserved -= size;
+        self.budget.sub(size);
+    }
+}
+
+impl Drop for BudgetReservation {
+    fn drop(&mut self) {
+        self.budget.sub(self.reserved);
+        self.budget.weights.fetch_sub(self.priority.weight(), Ordering::SeqCst);
+        if self.priority == PrefetchPriority::Interactive {
+            self.budget.interactive_readers.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_budget() {
+        let budget = Arc::new(PrefetchBudget::new(None));
+        let mut reservation = budget.register(PrefetchPriority::Interactive);
+        assert!(reservation.try_reserve(u64::MAX / 2));
+        assert_eq!(budget.reserved(), u64::MAX / 2);
+        drop(reservation);
+        assert_eq!(budget.reserved(), 0);
+    }
+
+    #[test]
+    fn test_budget_is_shared_fairly() {
+        let budget = Arc::new(PrefetchBudget::new(Some(100)));
+        let mut first = budget.register(PrefetchPriority::Interactive);
+        assert!(first.try_reserve(90), "a single reader can use the whole budget");
+
+        let mut second = budget.register(PrefetchPriority::Interactive);
+        assert!(
+            !first.try_reserve(20),
+            "the first reader is over its share and the budget is exhausted"
+        );
+        assert!(second.try_reserve(50), "the second reader is entitled to its share");
+        assert_eq!(budget.reserved(), 140);
+        assert!(!second.try_reserve(1), "the second reader is at its share");
+
+        first.release(60);
+        assert!(second.try_reserve(10), "the budget has room again");
+
+        drop(first);
+        assert_eq!(budget.reserved(), 60);
+        assert!(second.try_reserve(40), "the remaining reader gets the whole budget");
+        assert!(!second.try_reserve(1));
+    }
+
+    #[test]
+    fn test_batch_readers_yield_to_interactive_ones() {
+        let budget = Arc::new(PrefetchBudget::new(Some(100)));
+        let mut interactive = budget.register(PrefetchPriority::Interactive);
+        let mut batch = budget.register(PrefetchPriority::Batch);
+        assert!(batch.try_reserve(20), "the batch reader is entitled to its share");
+        assert!(
+            !batch.try_reserve(1),
+            "the batch reader doesn't grow past its share while an interactive reader is active"
+        );
+        assert!(
+            interactive.try_reserve(80),
+            "the interactive reader gets a larger share"
+        );
+
+        drop(interactive);
+        assert!(
+            batch.try_reserve(50),
+            "the batch reader gets the whole budget once alone"
+        );
+    }
+
+    #[test]
+    fn test_batch_readers_yield_without_budget_limit() {
+        let budget = Arc::new(PrefetchBudget::new(None));
+        let interactive = budget.register(PrefetchPriority::Interactive);
+        let mut batch = budget.register(PrefetchPriority::Batch);
+        assert!(!batch.try_reserve(1));
+        drop(interactive);
+        assert!(batch.try_reserve(1));
+    }
+
+    #[test]
+    fn test_unconditional_reserve() {
+        let budget = Arc::new(PrefetchBudget::new(Some(100)));
+        let mut reservation = budget.register(PrefetchPriority::Interactive);
+        reservation.reserve(150);
+        assert!(!reservation.can_grow(1));
+        reservation.release(1000);
+        assert_eq!(budget.reserved(), 0, "releases are capped to the reserved memory");
+        assert!(reservation.can_grow(100));
+    }
+}
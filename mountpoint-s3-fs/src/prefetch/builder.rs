@@ -6,11 +6,12 @@ use crate::{Runtime, mem_limiter::MemoryLimiter};
 
 use super::caching_stream::CachingPartStream;
 use super::part_stream::{ClientPartStream, PartStream};
-use super::{Prefetcher, PrefetcherConfig};
+use super::{PrefetchStrategy, Prefetcher, PrefetcherConfig};
 
 /// A builder for [Prefetcher] instances.
 pub struct PrefetcherBuilder<Client> {
     inner: Box<dyn PrefetcherBuild<Client>>,
+    strategy: Option<Arc<dyn PrefetchStrategy>>,
 }
 
 impl<Client> PrefetcherBuilder<Client>
@@ -21,6 +22,7 @@ where
     pub fn default_builder(client: Client) -> Self {
         Self {
             inner: Box::new(DefaultPrefetcherBuilder { client }),
+            strategy: None,
         }
     }
 
@@ -31,9 +33,19 @@ where
     {
         Self {
             inner: Box::new(CachingPrefetcherBuilder { cache, client }),
+            strategy: None,
         }
     }
 
+    /// Use a custom [PrefetchStrategy] instead of the default sequential one.
+    pub fn with_strategy<Strategy>(mut self, strategy: Strategy) -> Self
+    where
+        Strategy: PrefetchStrategy + 'static,
+    {
+        self.strategy = Some(Arc::new(strategy));
+        self
+    }
+
     /// Build a [Prefetcher] instance.
     pub fn build(
         self,
@@ -41,7 +53,11 @@ where
         mem_limiter: Arc<MemoryLimiter>,
         prefetcher_config: PrefetcherConfig,
     ) -> Prefetcher<Client> {
-        self.inner.build(runtime, mem_limiter, prefetcher_config)
+        let prefetcher = self.inner.build(runtime, mem_limiter, prefetcher_config);
+        match self.strategy {
+            Some(strategy) => prefetcher.with_strategy(strategy),
+            None => prefetcher,
+        }
     }
 }
 
@@ -98,7 +114,9 @@ where
         mem_limiter: Arc<MemoryLimiter>,
         prefetcher_config: PrefetcherConfig,
     ) -> Prefetcher<Client> {
+        let uncached_part_stream = ClientPartStream::new(runtime.clone(), self.client.clone(), mem_limiter.clone());
         let part_stream = CachingPartStream::new(runtime, self.client, mem_limiter.clone(), self.cache);
         Prefetcher::new(PartStream::new(part_stream), prefetcher_config, mem_limiter)
+            .with_uncached_part_stream(PartStream::new(uncached_part_stream))
     }
 }
@@ -56,13 +56,16 @@ where
             initial_read_window_size: config.initial_read_window_size(),
             min_read_window_size: config.read_part_size,
             max_read_window_size: config.max_read_window_size,
-            read_window_size_multiplier: config.read_window_size_multiplier,
+            strategy: config.strategy.clone(),
+            budget: config.budget.clone(),
+            priority: config.priority,
             request_range: range.into(),
             read_window_alignment_config: ReadWindowAlignmentConfig::Disable, // we don't know where S3 request starts, so can not align the read window
         };
         let (backpressure_controller, backpressure_limiter) =
             new_backpressure_controller(backpressure_config, self.mem_limiter.clone());
-        let (part_queue, part_queue_producer) = unbounded_part_queue(self.mem_limiter.clone());
+        let (part_queue, part_queue_producer) =
+            unbounded_part_queue(self.mem_limiter.clone(), config.bytes_prefetched.clone());
         trace!(?range, "spawning request");
 
         let request_task = {
@@ -221,6 +224,9 @@ where
             initial_request_end_offset,
             block_aligned_byte_range,
             self.config.handle_id,
+            self.config.read_part_size,
+            &self.config.hedging,
+            &self.config.adaptive_part_size,
         );
 
         let mut part_composer = CachingPartComposer {
@@ -421,9 +427,13 @@ mod tests {
         memory::PagedPool,
         object::ObjectId,
         prefetch::HandleId,
+        prefetch::strategy::{PrefetchStrategy, SequentialPrefetchStrategy},
     };
 
     use super::*;
+    use crate::prefetch::budget::PrefetchBudget;
+    use crate::prefetch::hedging::RequestHedging;
+    use crate::prefetch::part_size::AdaptivePartSize;
 
     const KB: usize = 1024;
     const MB: usize = 1024 * 1024;
@@ -451,7 +461,7 @@ mod tests {
         // backpressure config
         let initial_request_size = 1 * MB;
         let max_read_window_size = 64 * MB;
-        let read_window_size_multiplier = 2;
+        let strategy: Arc<dyn PrefetchStrategy> = Arc::new(SequentialPrefetchStrategy::new(2));
 
         let cache = InMemoryDataCache::new(block_size as u64);
         let bucket = "test-bucket";
@@ -486,7 +496,12 @@ mod tests {
                 preferred_part_size: 256 * KB,
                 initial_request_size,
                 max_read_window_size,
-                read_window_size_multiplier,
+                strategy: strategy.clone(),
+                budget: Arc::new(PrefetchBudget::new(None)),
+                hedging: Arc::new(RequestHedging::new(None)),
+                adaptive_part_size: Arc::new(AdaptivePartSize::new(None)),
+                priority: Default::default(),
+                bytes_prefetched: Default::default(),
             };
             let request_task = stream.spawn_get_object_request(config);
             compare_read(&id, &object, request_task);
@@ -513,7 +528,12 @@ mod tests {
                 preferred_part_size: 256 * KB,
                 initial_request_size,
                 max_read_window_size,
-                read_window_size_multiplier,
+                strategy: strategy.clone(),
+                budget: Arc::new(PrefetchBudget::new(None)),
+                hedging: Arc::new(RequestHedging::new(None)),
+                adaptive_part_size: Arc::new(AdaptivePartSize::new(None)),
+                priority: Default::default(),
+                bytes_prefetched: Default::default(),
             };
             let request_task = stream.spawn_get_object_request(config);
             compare_read(&id, &object, request_task);
@@ -536,7 +556,7 @@ mod tests {
         // backpressure config
         let initial_request_size = 1 * MB;
         let max_read_window_size = 64 * MB;
-        let read_window_size_multiplier = 2;
+        let strategy: Arc<dyn PrefetchStrategy> = Arc::new(SequentialPrefetchStrategy::new(2));
 
         let cache = InMemoryDataCache::new(block_size as u64);
         let bucket = "test-bucket";
@@ -567,7 +587,12 @@ mod tests {
                     preferred_part_size: 256 * KB,
                     initial_request_size,
                     max_read_window_size,
-                    read_window_size_multiplier,
+                    strategy: strategy.clone(),
+                    budget: Arc::new(PrefetchBudget::new(None)),
+                    hedging: Arc::new(RequestHedging::new(None)),
+                    adaptive_part_size: Arc::new(AdaptivePartSize::new(None)),
+                    priority: Default::default(),
+                    bytes_prefetched: Default::default(),
                 };
                 let request_task = stream.spawn_get_object_request(config);
                 compare_read(&id, &object, request_task);
@@ -0,0 +1,171 @@
+//! Hedged GetObject requests.
+//!
+//! Occasional slow S3 requests dominate the tail latency of reads. When hedging is enabled with
+//! [super::PrefetcherConfig::hedge_percentile], the prefetcher tracks how long its requests take to
+//! return their first byte. A request still waiting for its first byte after that percentile of the
+//! recent latencies is duplicated with another request for the same range, and the prefetcher
+//! continues with whichever of the two returns data first, cancelling the other one.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::{Either, select};
+use futures::{Stream, StreamExt, pin_mut};
+use metrics::counter;
+use tracing::trace;
+
+use crate::async_util::sleep;
+use crate::metrics::defs::{PREFETCH_HEDGED_REQUESTS, PREFETCH_HEDGED_REQUESTS_WON};
+use crate::sync::Mutex;
+
+/// Number of recent first-byte latencies the threshold is computed from.
+const LATENCY_SAMPLES: usize = 100;
+
+/// Minimum number of first-byte latencies recorded before requests are hedged.
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// First-byte latencies of the requests of a [super::Prefetcher], used to decide when to hedge.
+#[derive(Debug)]
+pub struct RequestHedging {
+    /// Percentile of the first-byte latency after which requests are hedged, between 0 and 100,
+    /// or [None] if hedging is disabled.
+    percentile: Option<f64>,
+    /// Most recent first-byte latencies, oldest first
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl RequestHedging {
+    pub fn new(percentile: Option<f64>) -> Self {
+        Self {
+            percentile: percentile.map(|percentile| percentile.clamp(0.0, 100.0)),
+            samples: Mutex::new(VecDeque::with_capacity(LATENCY_SAMPLES)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.percentile.is_some()
+    }
+
+    /// Record the time a request took to return its first byte.
+    pub fn record_first_byte_latency(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// How long to wait for the first byte of a request before hedging it, or [None] if hedging
+    /// is disabled or not enough latencies were recorded yet.
+    pub fn threshold(&self) -> Option<Duration> {
+        let percentile = self.percentile?;
+        let mut samples: Vec<_> = self.samples.lock().unwrap().iter().copied().collect();
+        if samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        samples.sort_unstable();
+        let index = ((samples.len() - 1) as f64 * percentile / 100.0).round() as usize;
+        Some(samples[index])
+    }
+}
+
+/// Wait for the first item of `primary`. If it doesn't arrive within `threshold`, start another
+/// stream with `hedge`, and continue with whichever of the two returns its first item first.
+///
+/// Returns the stream to continue with, its first item, and whether it is the hedged one.
+pub(super) async fn first_item_hedged<S, E, Fut>(
+    mut primary: S,
+    threshold: Duration,
+    hedge: impl FnOnce() -> Fut,
+) -> Result<(S, Option<S::Item>, bool), E>
+where
+    S: Stream + Unpin,
+    Fut: Future<Output = Result<S, E>>,
+{
+    let first_item = {
+        let timer = sleep(threshold);
+        pin_mut!(timer);
+        match select(primary.next(), timer).await {
+            Either::Left((item, _)) => Some(item),
+            Either::Right(_) => None,
+        }
+    };
+    if let Some(item) = first_item {
+        return Ok((primary, item, false));
+    }
+
+    trace!(?threshold, "first byte is late, hedging request");
+    counter!(PREFETCH_HEDGED_REQUESTS).increment(1);
+    let mut hedged = hedge().await?;
+    let (item, hedge_won) = match select(primary.next(), hedged.next()).await {
+        Either::Left((item, _)) => (item, false),
+        Either::Right((item, _)) => (item, true),
+    };
+    if hedge_won {
+        trace!("hedged request returned first");
+        counter!(PREFETCH_HEDGED_REQUESTS_WON).increment(1);
+        Ok((hedged, item, true))
+    } else {
+        Ok((primary, item, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::stream::{self, BoxStream};
+
+    #[test]
+    fn test_threshold_requires_samples() {
+        let hedging = RequestHedging::new(Some(90.0));
+        for i in 1..MIN_LATENCY_SAMPLES as u64 {
+            hedging.record_first_byte_latency(Duration::from_millis(i));
+        }
+        assert_eq!(hedging.threshold(), None);
+        hedging.record_first_byte_latency(Duration::from_millis(MIN_LATENCY_SAMPLES as u64));
+        assert_eq!(hedging.threshold(), Some(Duration::from_millis(18)));
+
+        let disabled = RequestHedging::new(None);
+        for _ in 0..LATENCY_SAMPLES {
+            disabled.record_first_byte_latency(Duration::from_millis(1));
+        }
+        assert_eq!(disabled.threshold(), None);
+    }
+
+    #[test]
+    fn test_threshold_uses_recent_samples() {
+        let hedging = RequestHedging::new(Some(50.0));
+        for _ in 0..LATENCY_SAMPLES {
+            hedging.record_first_byte_latency(Duration::from_secs(10));
+        }
+        for _ in 0..LATENCY_SAMPLES {
+            hedging.record_first_byte_latency(Duration::from_millis(10));
+        }
+        assert_eq!(hedging.threshold(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_fast_request_is_not_hedged() {
+        let primary: BoxStream<'static, u32> = stream::iter([1, 2]).boxed();
+        let (stream, first, hedged) = block_on(first_item_hedged(primary, Duration::from_secs(10), || async {
+            Err::<BoxStream<'static, u32>, _>("should not hedge")
+        }))
+        .unwrap();
+        assert_eq!((first, hedged), (Some(1), false));
+        assert_eq!(block_on(stream.collect::<Vec<_>>()), [2]);
+    }
+
+    #[test]
+    fn test_slow_request_is_hedged() {
+        let primary: BoxStream<'static, u32> = stream::pending().boxed();
+        let (stream, first, hedged) = block_on(first_item_hedged(primary, Duration::from_millis(1), || async {
+            Ok::<_, ()>(stream::iter([10, 11]).boxed())
+        }))
+        .unwrap();
+        assert_eq!((first, hedged), (Some(10), true));
+        assert_eq!(block_on(stream.collect::<Vec<_>>()), [11]);
+    }
+}
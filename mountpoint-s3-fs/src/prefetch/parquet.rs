@@ -0,0 +1,611 @@
+//! Support for prefetching Parquet files.
+//!
+//! Readers of Parquet files (analytics engines like DuckDB or Trino) start by reading the footer at
+//! the end of the file, and then read the column chunks they need, often out of order. Prefetching
+//! the rest of the object from each of those reads mostly downloads data that is never read. When
+//! we know the object is a Parquet file, we instead fetch the footer first and use the file metadata
+//! it contains to bound each request to the column chunk being read.
+//!
+//! See <https://parquet.apache.org/docs/file-format/> for a description of the file format.
+
+use std::ops::Range;
+
+use thiserror::Error;
+
+use super::part::Part;
+
+/// Magic bytes at the start and at the end of every Parquet file.
+pub const PARQUET_MAGIC: &[u8; 4] = b"PAR1";
+
+/// Size of the end of the footer: the length of the file metadata (4 bytes) followed by the magic.
+const FOOTER_TAIL_LEN: usize = 8;
+
+/// Limit to the nesting of structs and lists in the file metadata, so that a corrupted footer
+/// can't make us recurse without bounds.
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParquetFooterError {
+    #[error("object does not end with the Parquet magic bytes")]
+    MissingMagic,
+
+    #[error("file metadata length {0} is larger than the object")]
+    InvalidMetadataLength(u64),
+
+    #[error("file metadata could not be decoded")]
+    InvalidMetadata,
+}
+
+/// Whether the key of an object indicates a Parquet file.
+pub fn is_parquet_key(key: &str) -> bool {
+    let suffix = b".parquet";
+    // Compare bytes, as the suffix of a key of the same length may start inside a multi-byte character.
+    key.len() > suffix.len() && key.as_bytes()[key.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+}
+
+/// Length of the whole footer (file metadata, its length and the magic bytes) of a Parquet file,
+/// given the last bytes of the object.
+pub fn footer_len(tail: &[u8], object_size: u64) -> Result<u64, ParquetFooterError> {
+    if tail.len() < FOOTER_TAIL_LEN || !tail.ends_with(PARQUET_MAGIC) {
+        return Err(ParquetFooterError::MissingMagic);
+    }
+    let len_bytes = &tail[tail.len() - FOOTER_TAIL_LEN..tail.len() - PARQUET_MAGIC.len()];
+    let metadata_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as u64;
+    // The file also starts with the magic bytes
+    if metadata_len + (FOOTER_TAIL_LEN + PARQUET_MAGIC.len()) as u64 > object_size {
+        return Err(ParquetFooterError::InvalidMetadataLength(metadata_len));
+    }
+    Ok(metadata_len + FOOTER_TAIL_LEN as u64)
+}
+
+/// Prefetching state for an object detected as a Parquet file.
+#[derive(Debug, Default)]
+pub struct ParquetState {
+    /// End of the object including the footer, fetched before serving any other read.
+    pub footer: Option<Part>,
+    /// Layout decoded from the footer, or None if it couldn't be decoded.
+    pub layout: Option<ParquetLayout>,
+}
+
+/// Location of the column chunks of a Parquet file, as described by its file metadata.
+#[derive(Debug, Default)]
+pub struct ParquetLayout {
+    /// Byte ranges of the column chunks, sorted by start offset
+    column_chunks: Vec<Range<u64>>,
+}
+
+impl ParquetLayout {
+    /// Decode the layout from a complete footer, as sized by [footer_len].
+    pub fn from_footer(footer: &[u8]) -> Result<Self, ParquetFooterError> {
+        let metadata = footer
+            .len()
+            .checked_sub(FOOTER_TAIL_LEN)
+            .map(|len| &footer[..len])
+            .ok_or(ParquetFooterError::InvalidMetadata)?;
+        let mut decoder = CompactDecoder::new(metadata);
+        let mut column_chunks = Vec::new();
+        decoder.read_file_metadata(&mut column_chunks)?;
+        column_chunks.retain(|range| !range.is_empty());
+        column_chunks.sort_by_key(|range| (range.start, range.end));
+        Ok(Self { column_chunks })
+    }
+
+    /// The column chunk containing `offset`, if any.
+    pub fn column_chunk(&self, offset: u64) -> Option<Range<u64>> {
+        let index = self.column_chunks.partition_point(|range| range.start <= offset);
+        // Chunks shouldn't overlap, but pick the one ending last if they do.
+        self.column_chunks[..index]
+            .iter()
+            .rev()
+            .take_while(|range| range.end > offset)
+            .max_by_key(|range| range.end)
+            .cloned()
+    }
+
+    pub fn num_column_chunks(&self) -> usize {
+        self.column_chunks.len()
+    }
+}
+
+/// Field types of the Thrift compact protocol.
+mod field_type {
+    pub const STOP: u8 = 0;
+    pub const BOOLEAN_TRUE: u8 = 1;
+    pub const BOOLEAN_FALSE: u8 = 2;
+    pub const BYTE: u8 = 3;
+    pub const I16: u8 = 4;
+    pub const I32: u8 = 5;
+    pub const I64: u8 = 6;
+    pub const DOUBLE: u8 = 7;
+    pub const BINARY: u8 = 8;
+    pub const LIST: u8 = 9;
+    pub const SET: u8 = 10;
+    pub const MAP: u8 = 11;
+    pub const STRUCT: u8 = 12;
+}
+
+/// A minimal decoder for the Thrift compact protocol the Parquet file metadata is encoded with. It
+/// only understands the few fields we need to locate column chunks and skips everything else.
+struct CompactDecoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> CompactDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// `FileMetaData` struct, where field 4 is the list of `RowGroup`s.
+    fn read_file_metadata(&mut self, column_chunks: &mut Vec<Range<u64>>) -> Result<(), ParquetFooterError> {
+        self.read_struct(0, |decoder, field_id, field_type| match (field_id, field_type) {
+            (4, field_type::LIST) => {
+                decoder.read_list(0, field_type::STRUCT, |decoder| decoder.read_row_group(column_chunks))
+            }
+            _ => decoder.skip(field_type, 1),
+        })
+    }
+
+    /// `RowGroup` struct, where field 1 is the list of `ColumnChunk`s.
+    fn read_row_group(&mut self, column_chunks: &mut Vec<Range<u64>>) -> Result<(), ParquetFooterError> {
+        self.read_struct(1, |decoder, field_id, field_type| match (field_id, field_type) {
+            (1, field_type::LIST) => decoder.read_list(1, field_type::STRUCT, |decoder| {
+                if let Some(range) = decoder.read_column_chunk()? {
+                    column_chunks.push(range);
+                }
+                Ok(())
+            }),
+            _ => decoder.skip(field_type, 2),
+        })
+    }
+
+    /// `ColumnChunk` struct, where field 3 is the `ColumnMetaData`. Returns None for column chunks
+    /// stored in another file.
+    fn read_column_chunk(&mut self) -> Result<Option<Range<u64>>, ParquetFooterError> {
+        let mut range = None;
+        let mut external = false;
+        self.read_struct(2, |decoder, field_id, field_type| match (field_id, field_type) {
+            (1, field_type::BINARY) => {
+                external = true;
+                decoder.skip(field_type, 3)
+            }
+            (3, field_type::STRUCT) => {
+                range = decoder.read_column_metadata()?;
+                Ok(())
+            }
+            _ => decoder.skip(field_type, 3),
+        })?;
+        Ok(range.filter(|_| !external))
+    }
+
+    /// `ColumnMetaData` struct, which locates the pages of a column chunk with
+    /// `total_compressed_size` (7), `data_page_offset` (9) and `dictionary_page_offset` (11).
+    fn read_column_metadata(&mut self) -> Result<Option<Range<u64>>, ParquetFooterError> {
+        let mut total_compressed_size = None;
+        let mut data_page_offset = None;
+        let mut dictionary_page_offset = None;
+        self.read_struct(3, |decoder, field_id, field_type| {
+            match (field_id, field_type) {
+                (7, field_type::I64) => total_compressed_size = Some(decoder.read_i64()?),
+                (9, field_type::I64) => data_page_offset = Some(decoder.read_i64()?),
+                (11, field_type::I64) => dictionary_page_offset = Some(decoder.read_i64()?),
+                _ => decoder.skip(field_type, 4)?,
+            }
+            Ok(())
+        })?;
+
+        let (Some(size), Some(data_page_offset)) = (total_compressed_size, data_page_offset) else {
+            return Ok(None);
+        };
+        // Some writers set the dictionary page offset to 0 when there is no dictionary page
+        let start = match dictionary_page_offset {
+            Some(offset) if offset > 0 && offset < data_page_offset => offset,
+            _ => data_page_offset,
+        };
+        let (Ok(start), Ok(size)) = (u64::try_from(start), u64::try_from(size)) else {
+            return Err(ParquetFooterError::InvalidMetadata);
+        };
+        Ok(Some(start..start.saturating_add(size)))
+    }
+
+    /// Read a struct, calling `read_field` with the id and type of each field. `read_field` must
+    /// consume the value of the field.
+    fn read_struct(
+        &mut self,
+        depth: usize,
+        mut read_field: impl FnMut(&mut Self, i16, u8) -> Result<(), ParquetFooterError>,
+    ) -> Result<(), ParquetFooterError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ParquetFooterError::InvalidMetadata);
+        }
+        let mut last_field_id = 0i16;
+        loop {
+            let header = self.read_byte()?;
+            let field_type = header & 0x0f;
+            if field_type == field_type::STOP {
+                return Ok(());
+            }
+            let delta = (header >> 4) as i16;
+            let field_id = if delta == 0 {
+                self.read_varint().map(zigzag_decode)? as i16
+            } else {
+                last_field_id.wrapping_add(delta)
+            };
+            last_field_id = field_id;
+            read_field(self, field_id, field_type)?;
+        }
+    }
+
+    /// Read a list of elements of type `expected_type`, calling `read_element` for each of them.
+    fn read_list(
+        &mut self,
+        depth: usize,
+        expected_type: u8,
+        mut read_element: impl FnMut(&mut Self) -> Result<(), ParquetFooterError>,
+    ) -> Result<(), ParquetFooterError> {
+        let (size, element_type) = self.read_list_header()?;
+        if element_type != expected_type {
+            for _ in 0..size {
+                self.skip_element(element_type, depth + 1)?;
+            }
+            return Ok(());
+        }
+        for _ in 0..size {
+            read_element(self)?;
+        }
+        Ok(())
+    }
+
+    fn read_list_header(&mut self) -> Result<(u64, u8), ParquetFooterError> {
+        let header = self.read_byte()?;
+        let size = match header >> 4 {
+            15 => self.read_varint()?,
+            size => size as u64,
+        };
+        // Elements take at least one byte, so this also protects against absurd sizes
+        if size > (self.data.len() - self.position) as u64 {
+            return Err(ParquetFooterError::InvalidMetadata);
+        }
+        Ok((size, header & 0x0f))
+    }
+
+    /// Skip over a value of the given type.
+    fn skip(&mut self, field_type: u8, depth: usize) -> Result<(), ParquetFooterError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ParquetFooterError::InvalidMetadata);
+        }
+        match field_type {
+            field_type::BOOLEAN_TRUE | field_type::BOOLEAN_FALSE => Ok(()),
+            field_type::BYTE => self.read_byte().map(|_| ()),
+            field_type::I16 | field_type::I32 | field_type::I64 => self.read_varint().map(|_| ()),
+            field_type::DOUBLE => self.advance(8),
+            field_type::BINARY => {
+                let len = self.read_varint()?;
+                self.advance(len.try_into().map_err(|_| ParquetFooterError::InvalidMetadata)?)
+            }
+            field_type::LIST | field_type::SET => {
+                let (size, element_type) = self.read_list_header()?;
+                for _ in 0..size {
+                    self.skip_element(element_type, depth + 1)?;
+                }
+                Ok(())
+            }
+            field_type::MAP => {
+                let size = self.read_varint()?;
+                if size == 0 {
+                    return Ok(());
+                }
+                // Entries take at least two bytes, so this also protects against absurd sizes
+                if size > (self.data.len() - self.position) as u64 {
+                    return Err(ParquetFooterError::InvalidMetadata);
+                }
+                let types = self.read_byte()?;
+                for _ in 0..size {
+                    self.skip_element(types >> 4, depth + 1)?;
+                    self.skip_element(types & 0x0f, depth + 1)?;
+                }
+                Ok(())
+            }
+            field_type::STRUCT => {
+                self.read_struct(depth + 1, |decoder, _, field_type| decoder.skip(field_type, depth + 2))
+            }
+            _ => Err(ParquetFooterError::InvalidMetadata),
+        }
+    }
+
+    /// Skip over an element of a list, a set or a map of the given type.
+    fn skip_element(&mut self, element_type: u8, depth: usize) -> Result<(), ParquetFooterError> {
+        // Booleans in collections take a byte each, unlike boolean fields
+        if matches!(element_type, field_type::BOOLEAN_TRUE | field_type::BOOLEAN_FALSE) {
+            self.read_byte().map(|_| ())
+        } else {
+            self.skip(element_type, depth)
+        }
+    }
+
+    fn read_i64(&mut self) -> Result<i64, ParquetFooterError> {
+        self.read_varint().map(zigzag_decode)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, ParquetFooterError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ParquetFooterError::InvalidMetadata)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, ParquetFooterError> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or(ParquetFooterError::InvalidMetadata)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn advance(&mut self, len: usize) -> Result<(), ParquetFooterError> {
+        if len > self.data.len() - self.position {
+            return Err(ParquetFooterError::InvalidMetadata);
+        }
+        self.position += len;
+        Ok(())
+    }
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+pub(super) mod test_utils {
+    use std::ops::Range;
+
+    use super::{PARQUET_MAGIC, field_type};
+
+    /// Encoder for the subset of the Thrift compact protocol used by [parquet_file].
+    #[derive(Default)]
+    struct CompactEncoder {
+        data: Vec<u8>,
+        last_field_ids: Vec<i16>,
+    }
+
+    impl CompactEncoder {
+        fn begin_struct(&mut self) {
+            self.last_field_ids.push(0);
+        }
+
+        fn end_struct(&mut self) {
+            self.data.push(field_type::STOP);
+            self.last_field_ids.pop();
+        }
+
+        fn field_header(&mut self, field_id: i16, field_type: u8) {
+            let last = self.last_field_ids.last_mut().unwrap();
+            let delta = field_id - *last;
+            *last = field_id;
+            if (1..=15).contains(&delta) {
+                self.data.push(((delta as u8) << 4) | field_type);
+            } else {
+                self.data.push(field_type);
+                self.varint(zigzag_encode(field_id as i64));
+            }
+        }
+
+        fn i64_field(&mut self, field_id: i16, value: i64) {
+            self.field_header(field_id, field_type::I64);
+            self.varint(zigzag_encode(value));
+        }
+
+        fn binary_field(&mut self, field_id: i16, value: &[u8]) {
+            self.field_header(field_id, field_type::BINARY);
+            self.varint(value.len() as u64);
+            self.data.extend_from_slice(value);
+        }
+
+        fn list_header(&mut self, size: usize, element_type: u8) {
+            if size < 15 {
+                self.data.push(((size as u8) << 4) | element_type);
+            } else {
+                self.data.push(0xf0 | element_type);
+                self.varint(size as u64);
+            }
+        }
+
+        fn varint(&mut self, mut value: u64) {
+            while value >= 0x80 {
+                self.data.push((value as u8) | 0x80);
+                value >>= 7;
+            }
+            self.data.push(value as u8);
+        }
+    }
+
+    fn zigzag_encode(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    /// Encode file metadata with the given row groups, each being a list of column chunk ranges.
+    /// Also adds a few fields we don't decode, to check they are skipped correctly.
+    pub fn file_metadata(row_groups: &[Vec<Range<u64>>]) -> Vec<u8> {
+        file_metadata_with_field(row_groups, None)
+    }
+
+    /// Encode file metadata like [file_metadata], with an extra field of an unknown id holding the given type and
+    /// encoded value before the row groups, followed by another field.
+    pub fn file_metadata_with_field(row_groups: &[Vec<Range<u64>>], extra_field: Option<(u8, &[u8])>) -> Vec<u8> {
+        let mut encoder = CompactEncoder::default();
+        encoder.begin_struct();
+        // version
+        encoder.field_header(1, field_type::I32);
+        encoder.varint(zigzag_encode(2));
+        // schema, as a list of structs with a name
+        encoder.field_header(2, field_type::LIST);
+        encoder.list_header(1, field_type::STRUCT);
+        encoder.begin_struct();
+        encoder.binary_field(4, b"schema");
+        encoder.end_struct();
+        // num_rows
+        encoder.i64_field(3, 1000);
+        if let Some((field_type, value)) = extra_field {
+            encoder.field_header(100, field_type);
+            encoder.data.extend_from_slice(value);
+            encoder.binary_field(101, b"after");
+        }
+        // row_groups
+        encoder.field_header(4, field_type::LIST);
+        encoder.list_header(row_groups.len(), field_type::STRUCT);
+        for columns in row_groups {
+            encoder.begin_struct();
+            encoder.field_header(1, field_type::LIST);
+            encoder.list_header(columns.len(), field_type::STRUCT);
+            for range in columns {
+                encoder.begin_struct();
+                // file_offset
+                encoder.i64_field(2, range.end as i64);
+                // meta_data
+                encoder.field_header(3, field_type::STRUCT);
+                encoder.begin_struct();
+                encoder.field_header(3, field_type::LIST);
+                encoder.list_header(1, field_type::BINARY);
+                encoder.varint(6);
+                encoder.data.extend_from_slice(b"column");
+                encoder.i64_field(5, 100);
+                encoder.i64_field(6, (range.end - range.start) as i64 * 2);
+                encoder.i64_field(7, (range.end - range.start) as i64);
+                encoder.i64_field(9, range.start as i64);
+                encoder.end_struct();
+                encoder.end_struct();
+            }
+            // total_byte_size and num_rows, with a long field id delta
+            encoder.i64_field(2, 0);
+            encoder.i64_field(3, 100);
+            encoder.field_header(20, field_type::BOOLEAN_TRUE);
+            encoder.end_struct();
+        }
+        // created_by
+        encoder.binary_field(6, b"mountpoint-s3 tests");
+        encoder.end_struct();
+        encoder.data
+    }
+
+    /// Build a Parquet file with the given column chunk sizes, in a single row group, where the
+    /// content of column chunks is filled with `fill`. Returns the file and the column chunk ranges.
+    pub fn parquet_file(column_sizes: &[usize], fill: impl Fn(u64) -> u8) -> (Vec<u8>, Vec<Range<u64>>) {
+        let mut data = PARQUET_MAGIC.to_vec();
+        let mut ranges = Vec::new();
+        for &size in column_sizes {
+            let start = data.len() as u64;
+            data.extend((start..start + size as u64).map(&fill));
+            ranges.push(start..data.len() as u64);
+        }
+        let metadata = file_metadata(&[ranges.clone()]);
+        data.extend_from_slice(&metadata);
+        data.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        data.extend_from_slice(PARQUET_MAGIC);
+        (data, ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::{file_metadata, file_metadata_with_field, parquet_file};
+    use super::*;
+
+    use test_case::test_case;
+
+    #[test_case("data.parquet", true)]
+    #[test_case("dir/part-0001.PARQUET", true)]
+    #[test_case(".parquet", false)]
+    #[test_case("data.parquet.crc", false)]
+    #[test_case("data.csv", false)]
+    #[test_case("données/€€€", false)]
+    #[test_case("données/€€€.parquet", true)]
+    fn test_is_parquet_key(key: &str, expected: bool) {
+        assert_eq!(is_parquet_key(key), expected);
+    }
+
+    #[test]
+    fn test_parse_footer() {
+        let (file, ranges) = parquet_file(&[100, 2000, 30], |_| 0);
+        let footer_len = footer_len(&file[file.len() - 16..], file.len() as u64).unwrap() as usize;
+        let layout = ParquetLayout::from_footer(&file[file.len() - footer_len..]).unwrap();
+        assert_eq!(layout.num_column_chunks(), 3);
+        assert_eq!(layout.column_chunk(0), None);
+        assert_eq!(layout.column_chunk(4), Some(ranges[0].clone()));
+        assert_eq!(layout.column_chunk(103), Some(ranges[0].clone()));
+        assert_eq!(layout.column_chunk(104), Some(ranges[1].clone()));
+        assert_eq!(layout.column_chunk(2133), Some(ranges[2].clone()));
+        assert_eq!(layout.column_chunk(2134), None);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_parse_multiple_row_groups() {
+        let row_groups = [vec![10..20, 20..35], vec![35..40], Vec::new(), vec![40..100]];
+        let mut footer = file_metadata(&row_groups);
+        footer.extend_from_slice(&[0; 4]);
+        footer.extend_from_slice(PARQUET_MAGIC);
+        let layout = ParquetLayout::from_footer(&footer).unwrap();
+        assert_eq!(layout.num_column_chunks(), 4);
+        assert_eq!(layout.column_chunk(37), Some(35..40));
+        assert_eq!(layout.column_chunk(99), Some(40..100));
+    }
+
+    #[test]
+    fn test_footer_len_errors() {
+        assert_eq!(footer_len(b"PAR1", 4), Err(ParquetFooterError::MissingMagic));
+        assert_eq!(
+            footer_len(b"\x10\0\0\0PAR2", 100),
+            Err(ParquetFooterError::MissingMagic)
+        );
+        assert_eq!(
+            footer_len(b"\x60\0\0\0PAR1", 100),
+            Err(ParquetFooterError::InvalidMetadataLength(0x60))
+        );
+        assert_eq!(footer_len(b"\x10\0\0\0PAR1", 100), Ok(0x18));
+    }
+
+    #[test]
+    fn test_truncated_metadata() {
+        let metadata = file_metadata(&[vec![10..20, 20..35]]);
+        for len in 0..metadata.len() {
+            let mut footer = metadata[..len].to_vec();
+            footer.extend_from_slice(&[0; 4]);
+            footer.extend_from_slice(PARQUET_MAGIC);
+            assert_eq!(
+                ParquetLayout::from_footer(&footer).unwrap_err(),
+                ParquetFooterError::InvalidMetadata
+            );
+        }
+    }
+
+    #[test]
+    fn test_skip_map() {
+        let row_groups = [vec![10..20, 20..35]];
+        let parse = |value: &[u8]| {
+            let mut footer = file_metadata_with_field(&row_groups, Some((field_type::MAP, value)));
+            footer.extend_from_slice(&[0; 4]);
+            footer.extend_from_slice(PARQUET_MAGIC);
+            ParquetLayout::from_footer(&footer).map(|layout| layout.column_chunk(25))
+        };
+
+        // A map of two binary keys to boolean values, which take a byte each.
+        let types = (field_type::BINARY << 4) | field_type::BOOLEAN_TRUE;
+        assert_eq!(parse(&[2, types, 1, b'a', 1, 1, b'b', 0]), Ok(Some(20..35)));
+        // A map of boolean keys to 32-bit integers.
+        let types = (field_type::BOOLEAN_TRUE << 4) | field_type::I32;
+        assert_eq!(parse(&[1, types, 1, 4]), Ok(Some(20..35)));
+        // An empty map has no types.
+        assert_eq!(parse(&[0]), Ok(Some(20..35)));
+
+        // A map declaring more entries than there are bytes left is rejected without skipping them one by one.
+        let types = (field_type::BOOLEAN_TRUE << 4) | field_type::BOOLEAN_TRUE;
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, types, 1, 1];
+        assert_eq!(parse(&huge), Err(ParquetFooterError::InvalidMetadata));
+    }
+}
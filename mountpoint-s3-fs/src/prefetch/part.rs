@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
 use crate::checksums::{ChecksummedBytes, IntegrityError};
@@ -56,10 +58,34 @@ impl Part {
         }
     }
 
+    /// Returns the part of the object in `range`, which must be within this part.
+    ///
+    /// Like [ChecksummedBytes::slice], this doesn't copy or validate the data.
+    pub fn slice(&self, range: Range<u64>) -> Part {
+        assert!(
+            range.start >= self.offset && range.start <= range.end && range.end <= self.end_offset(),
+            "range {range:?} out of bounds of part at offset {} with length {}",
+            self.offset,
+            self.len(),
+        );
+        let start = (range.start - self.offset) as usize;
+        let end = (range.end - self.offset) as usize;
+        Part {
+            id: self.id.clone(),
+            offset: range.start,
+            checksummed_bytes: self.checksummed_bytes.slice(start..end),
+            source: self.source,
+        }
+    }
+
     pub(super) fn offset(&self) -> u64 {
         self.offset
     }
 
+    pub(super) fn end_offset(&self) -> u64 {
+        self.offset + self.len() as u64
+    }
+
     pub(super) fn len(&self) -> usize {
         self.checksummed_bytes.len()
     }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
 use std::time::Instant;
 
 use mountpoint_s3_client::ObjectClient;
@@ -6,7 +8,7 @@ use tracing::trace;
 use crate::mem_limiter::{BufferArea, MemoryLimiter};
 use crate::sync::Arc;
 use crate::sync::async_channel::{Receiver, RecvError, Sender, unbounded};
-use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use super::PrefetchReadError;
 use super::part::Part;
@@ -32,11 +34,15 @@ pub struct PartQueueProducer<E: std::error::Error> {
     sender: Sender<Result<Part, PrefetchReadError<E>>>,
     /// The total number of bytes sent to `self.sender`
     bytes_sent: Arc<AtomicUsize>,
+    /// The total number of bytes sent by all the queues of a file handle
+    bytes_prefetched: Arc<AtomicU64>,
 }
 
-/// Creates an unbounded [PartQueue] and its related [PartQueueProducer].
+/// Creates an unbounded [PartQueue] and its related [PartQueueProducer]. The bytes sent to the
+/// queue are also added to `bytes_prefetched`.
 pub fn unbounded_part_queue<Client: ObjectClient>(
     mem_limiter: Arc<MemoryLimiter>,
+    bytes_prefetched: Arc<AtomicU64>,
 ) -> (PartQueue<Client>, PartQueueProducer<Client::ClientError>) {
     let (sender, receiver) = unbounded();
     let bytes_counter = Arc::new(AtomicUsize::new(0));
@@ -50,6 +56,7 @@ pub fn unbounded_part_queue<Client: ObjectClient>(
     let part_queue_producer = PartQueueProducer {
         sender,
         bytes_sent: bytes_counter,
+        bytes_prefetched,
     };
     (part_queue, part_queue_producer)
 }
@@ -110,9 +117,147 @@ impl<Client: ObjectClient> PartQueue<Client> {
         Ok(())
     }
 
+    /// Put back a [Part] read from the queue but not consumed, in front of the queue. Unlike
+    /// [Self::push_front], its memory is still accounted for by the backpressure controller.
+    pub fn unread(&mut self, part: Part) {
+        assert!(!self.failed, "cannot use a PartQueue after failure");
+
+        metrics::gauge!("prefetch.bytes_in_queue").increment(part.len() as f64);
+        self.front_queue.push(part);
+    }
+
     pub fn bytes_received(&self) -> usize {
         self.bytes_received.load(Ordering::SeqCst)
     }
+
+    /// Remove all the parts already received by the queue, in offset order, without waiting for
+    /// more. Stops at the first error, as the parts after it can't be trusted.
+    pub fn take_received(&mut self) -> Vec<Part> {
+        let mut parts = Vec::new();
+        if self.failed {
+            return parts;
+        }
+        while let Some(part) = self.front_queue.pop() {
+            parts.push(part);
+        }
+        while let Ok(Ok(part)) = self.receiver.try_recv() {
+            parts.push(part);
+        }
+        let taken_size: usize = parts.iter().map(|part| part.len()).sum();
+        metrics::gauge!("prefetch.bytes_in_queue").decrement(taken_size as f64);
+        parts
+    }
+}
+
+/// A window of parts downloaded by requests that have since been discarded, indexed by offset, so
+/// that a later seek landing within them can be served without fetching them again. Older parts
+/// are dropped to remain within a maximum size. Memory for the parts is reserved in the memory
+/// limiter while they are in the window.
+#[derive(Debug)]
+pub struct CompletedParts {
+    /// Parts by offset, along with the order they were inserted in
+    parts: BTreeMap<u64, (u64, Part)>,
+    next_insertion: u64,
+    max_size: usize,
+    current_size: usize,
+    mem_limiter: Arc<MemoryLimiter>,
+}
+
+impl CompletedParts {
+    pub fn new(max_size: usize, mem_limiter: Arc<MemoryLimiter>) -> Self {
+        Self {
+            parts: BTreeMap::new(),
+            next_insertion: 0,
+            max_size,
+            current_size: 0,
+            mem_limiter,
+        }
+    }
+
+    /// Add a part to the window, replacing any part it overlaps, and drop the oldest parts
+    /// necessary to fit it within the maximum size.
+    pub fn insert(&mut self, part: Part) {
+        if part.is_empty() || part.len() > self.max_size {
+            return;
+        }
+        self.remove_overlapping(part.offset()..part.end_offset());
+        while self.max_size - self.current_size < part.len() {
+            let (&oldest, _) = self
+                .parts
+                .iter()
+                .min_by_key(|(_, (insertion, _))| *insertion)
+                .expect("window is non-empty if current size is non-zero");
+            self.remove(oldest);
+        }
+
+        self.mem_limiter.reserve(BufferArea::Prefetch, part.len() as u64);
+        self.current_size += part.len();
+        self.parts.insert(part.offset(), (self.next_insertion, part));
+        self.next_insertion += 1;
+    }
+
+    /// End offset of the contiguous parts in the window starting at a part containing `offset`,
+    /// or [None] if no part contains it.
+    pub fn contiguous_end(&self, offset: u64) -> Option<u64> {
+        let (_, (_, part)) = self.parts.range(..=offset).next_back()?;
+        let mut end = part.end_offset();
+        if end <= offset {
+            return None;
+        }
+        while let Some((_, part)) = self.parts.get(&end) {
+            end = part.end_offset();
+        }
+        Some(end)
+    }
+
+    /// Remove the contiguous parts starting at `offset` from the window, in offset order, the first
+    /// one being trimmed to start at `offset`. Returns an empty list if no part contains `offset`.
+    pub fn take_from(&mut self, offset: u64) -> Vec<Part> {
+        let mut parts = Vec::new();
+        let Some(end) = self.contiguous_end(offset) else {
+            return parts;
+        };
+        let (&first, _) = self
+            .parts
+            .range(..=offset)
+            .next_back()
+            .expect("a part contains the offset");
+        let mut next = first;
+        while next < end {
+            let part = self.remove(next).expect("parts are contiguous up to the end offset");
+            next = part.end_offset();
+            parts.push(part);
+        }
+        let head = &mut parts[0];
+        parts[0] = head.split_off((offset - head.offset()) as usize);
+        parts
+    }
+
+    /// Remove all the parts overlapping `range` from the window.
+    pub fn remove_overlapping(&mut self, range: Range<u64>) {
+        let overlapping: Vec<u64> = self
+            .parts
+            .range(..range.end)
+            .filter(|(_, (_, part))| part.end_offset() > range.start)
+            .map(|(&offset, _)| offset)
+            .collect();
+        for offset in overlapping {
+            self.remove(offset);
+        }
+    }
+
+    fn remove(&mut self, offset: u64) -> Option<Part> {
+        let (_, part) = self.parts.remove(&offset)?;
+        self.current_size -= part.len();
+        self.mem_limiter.release(BufferArea::Prefetch, part.len() as u64);
+        Some(part)
+    }
+}
+
+impl Drop for CompletedParts {
+    fn drop(&mut self) {
+        self.mem_limiter.release(BufferArea::Prefetch, self.current_size as u64);
+    }
 }
 
 impl<E: std::error::Error + Send + Sync> PartQueueProducer<E> {
@@ -126,6 +271,7 @@ impl<E: std::error::Error + Send + Sync> PartQueueProducer<E> {
             trace!("closed channel");
         } else {
             self.bytes_sent.fetch_add(part_len, Ordering::SeqCst);
+            self.bytes_prefetched.fetch_add(part_len as u64, Ordering::SeqCst);
             metrics::gauge!("prefetch.bytes_in_queue").increment(part_len as f64);
         }
     }
@@ -177,7 +323,8 @@ mod tests {
         let pool = PagedPool::new_with_candidate_sizes([1024]);
         let mem_limiter = MemoryLimiter::new(pool, MINIMUM_MEM_LIMIT);
         let part_id = ObjectId::new("key".to_owned(), ETag::for_tests());
-        let (mut part_queue, part_queue_producer) = unbounded_part_queue::<MockClient>(mem_limiter.into());
+        let (mut part_queue, part_queue_producer) =
+            unbounded_part_queue::<MockClient>(mem_limiter.into(), Default::default());
         let mut current_offset = 0;
         let mut current_length = 0;
         for op in ops {
@@ -223,6 +370,41 @@ mod tests {
         }
     }
 
+    fn make_part(offset: u64, len: usize) -> Part {
+        let part_id = ObjectId::new("key".to_owned(), ETag::for_tests());
+        let body: Box<[u8]> = (0u8..=255).cycle().skip(offset as u8 as usize).take(len).collect();
+        let bytes: Bytes = body.into();
+        Part::new(part_id, offset, ChecksummedBytes::new(bytes), PartSource::S3)
+    }
+
+    #[test]
+    fn completed_parts_window() {
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let mem_limiter = Arc::new(MemoryLimiter::new(pool, MINIMUM_MEM_LIMIT));
+        let initial_available_mem = mem_limiter.available_mem();
+        let mut window = CompletedParts::new(300, mem_limiter.clone());
+        for offset in [0, 100, 200, 500] {
+            window.insert(make_part(offset, 100));
+        }
+        assert_eq!(mem_limiter.available_mem(), initial_available_mem - 300);
+        // The oldest part was dropped to make room for the last one.
+        assert_eq!(window.contiguous_end(50), None);
+        assert_eq!(window.contiguous_end(150), Some(300));
+        assert_eq!(window.contiguous_end(300), None);
+
+        let parts = window.take_from(150);
+        let ranges: Vec<_> = parts.iter().map(|part| part.offset()..part.end_offset()).collect();
+        assert_eq!(ranges, [150..200, 200..300]);
+        assert_eq!(window.contiguous_end(100), None, "the first part was taken as a whole");
+        assert_eq!(mem_limiter.available_mem(), initial_available_mem - 100);
+
+        // Parts replace the ones they overlap.
+        window.insert(make_part(450, 100));
+        assert_eq!(window.contiguous_end(520), Some(550));
+        drop(window);
+        assert_eq!(mem_limiter.available_mem(), initial_available_mem);
+    }
+
     #[test]
     fn part_queue_simple() {
         block_on(run_test(vec![Op::Push(1), Op::Push(1), Op::Read(1), Op::Read(1)]));
@@ -0,0 +1,163 @@
+//! Adaptive part size for GetObject requests.
+//!
+//! The S3 client splits each GetObject request into ranged requests of its read part size. Each
+//! of them waits for its first byte, so small parts limit the throughput of sequential reads when
+//! the latency is high, while large parts hold more memory and delay the first byte of short
+//! reads. When [super::PrefetcherConfig::max_read_part_size] is set, the prefetcher measures the
+//! first-byte latency and the throughput of its requests, and picks the part size of each new
+//! request between the client's read part size and that maximum, so that receiving a part takes a
+//! few times as long as waiting for its first byte.
+
+use std::time::Duration;
+
+use tracing::trace;
+
+use crate::sync::Mutex;
+
+/// Ratio between the time to receive a part and its first-byte latency that the part size targets.
+const TRANSFER_TO_LATENCY_RATIO: f64 = 4.0;
+
+/// Weight of the latest request in the latency and throughput estimates.
+const SMOOTHING_FACTOR: f64 = 0.2;
+
+/// Minimum number of requests measured before the part size is adjusted.
+const MIN_SAMPLES: usize = 5;
+
+/// Estimates of the latency and throughput of the requests of a [super::Prefetcher], used to pick
+/// the part size of new requests.
+#[derive(Debug)]
+pub struct AdaptivePartSize {
+    /// Maximum part size, or [None] if the client's read part size is always used.
+    max_part_size: Option<usize>,
+    estimates: Mutex<Estimates>,
+}
+
+#[derive(Debug, Default)]
+struct Estimates {
+    samples: usize,
+    /// Exponentially weighted moving average of the first-byte latency, in seconds
+    first_byte_latency: f64,
+    /// Exponentially weighted moving average of the throughput, in bytes per second
+    throughput: f64,
+}
+
+impl AdaptivePartSize {
+    pub fn new(max_part_size: Option<usize>) -> Self {
+        Self {
+            max_part_size,
+            estimates: Mutex::new(Estimates::default()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_part_size.is_some()
+    }
+
+    /// Record the time a request took to return its first byte, and the time it took to return
+    /// the `bytes` following it.
+    pub fn record_request(&self, first_byte_latency: Duration, bytes: u64, transfer_time: Duration) {
+        if !self.is_enabled() || bytes == 0 || transfer_time.is_zero() {
+            return;
+        }
+        let throughput = bytes as f64 / transfer_time.as_secs_f64();
+        let first_byte_latency = first_byte_latency.as_secs_f64();
+        let mut estimates = self.estimates.lock().unwrap();
+        if estimates.samples == 0 {
+            estimates.first_byte_latency = first_byte_latency;
+            estimates.throughput = throughput;
+        } else {
+            estimates.first_byte_latency += SMOOTHING_FACTOR * (first_byte_latency - estimates.first_byte_latency);
+            estimates.throughput += SMOOTHING_FACTOR * (throughput - estimates.throughput);
+        }
+        estimates.samples += 1;
+    }
+
+    /// The part size for a new request, given the client's read part size.
+    pub fn part_size(&self, min_part_size: usize) -> usize {
+        let Some(max_part_size) = self.max_part_size else {
+            return min_part_size;
+        };
+        let target = {
+            let estimates = self.estimates.lock().unwrap();
+            if estimates.samples < MIN_SAMPLES {
+                return min_part_size;
+            }
+            estimates.throughput * estimates.first_byte_latency * TRANSFER_TO_LATENCY_RATIO
+        };
+        let sizes = candidate_part_sizes(min_part_size, max_part_size);
+        let part_size = sizes
+            .iter()
+            .copied()
+            .find(|&size| size as f64 >= target)
+            .unwrap_or_else(|| *sizes.last().expect("there is at least one candidate"));
+        trace!(target, part_size, "picked part size");
+        part_size
+    }
+}
+
+/// The part sizes [AdaptivePartSize] picks from: the client's read part size doubled as long as
+/// it doesn't exceed `max_part_size`.
+pub fn candidate_part_sizes(min_part_size: usize, max_part_size: usize) -> Vec<usize> {
+    let mut sizes = vec![min_part_size];
+    let mut size = min_part_size;
+    while let Some(next) = size.checked_mul(2).filter(|&next| next <= max_part_size) {
+        sizes.push(next);
+        size = next;
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: usize = 1024 * 1024;
+
+    #[test]
+    fn test_candidate_part_sizes() {
+        assert_eq!(candidate_part_sizes(8 * MB, 8 * MB), [8 * MB]);
+        assert_eq!(candidate_part_sizes(8 * MB, 4 * MB), [8 * MB]);
+        assert_eq!(candidate_part_sizes(8 * MB, 63 * MB), [8 * MB, 16 * MB, 32 * MB]);
+        assert_eq!(
+            candidate_part_sizes(8 * MB, 64 * MB),
+            [8 * MB, 16 * MB, 32 * MB, 64 * MB]
+        );
+    }
+
+    #[test]
+    fn test_part_size_requires_samples() {
+        let part_size = AdaptivePartSize::new(Some(64 * MB));
+        for _ in 1..MIN_SAMPLES {
+            // 100ms latency at 1GB/s: 400MB parts would be needed
+            part_size.record_request(Duration::from_millis(100), 1000 * MB as u64, Duration::from_secs(1));
+        }
+        assert_eq!(part_size.part_size(8 * MB), 8 * MB);
+        part_size.record_request(Duration::from_millis(100), 1000 * MB as u64, Duration::from_secs(1));
+        assert_eq!(part_size.part_size(8 * MB), 64 * MB, "capped to the maximum");
+    }
+
+    #[test]
+    fn test_part_size_follows_estimates() {
+        let part_size = AdaptivePartSize::new(Some(64 * MB));
+        for _ in 0..MIN_SAMPLES {
+            // 20ms latency at 100MB/s: 8MB parts are enough
+            part_size.record_request(Duration::from_millis(20), 100 * MB as u64, Duration::from_secs(1));
+        }
+        assert_eq!(part_size.part_size(8 * MB), 8 * MB);
+
+        for _ in 0..50 {
+            // 50ms latency at 100MB/s: 20MB parts are needed
+            part_size.record_request(Duration::from_millis(50), 100 * MB as u64, Duration::from_secs(1));
+        }
+        assert_eq!(part_size.part_size(8 * MB), 32 * MB);
+    }
+
+    #[test]
+    fn test_disabled_part_size() {
+        let part_size = AdaptivePartSize::new(None);
+        for _ in 0..MIN_SAMPLES {
+            part_size.record_request(Duration::from_millis(100), 1000 * MB as u64, Duration::from_secs(1));
+        }
+        assert_eq!(part_size.part_size(8 * MB), 8 * MB);
+    }
+}
@@ -4,7 +4,9 @@ use futures::{Stream, StreamExt, pin_mut};
 use mountpoint_s3_client::ObjectClient;
 use mountpoint_s3_client::types::{ClientBackpressureHandle, GetBodyPart, GetObjectParams, GetObjectResponse};
 use std::marker::{Send, Sync};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{fmt::Debug, ops::Range};
 use tracing::{Instrument, debug_span, error, trace};
 
@@ -13,12 +15,17 @@ use crate::checksums::ChecksummedBytes;
 use crate::mem_limiter::MemoryLimiter;
 use crate::object::ObjectId;
 use crate::prefetch::backpressure_controller::ReadWindowAlignmentConfig;
+use crate::sync::atomic::AtomicU64;
 
 use super::HandleId;
 use super::PrefetchReadError;
 use super::backpressure_controller::{BackpressureConfig, BackpressureLimiter, new_backpressure_controller};
+use super::budget::{PrefetchBudget, PrefetchPriority};
+use super::hedging::{RequestHedging, first_item_hedged};
 use super::part::{Part, PartSource};
 use super::part_queue::{PartQueueProducer, unbounded_part_queue};
+use super::part_size::AdaptivePartSize;
+use super::strategy::PrefetchStrategy;
 use super::task::RequestTask;
 
 /// A generic interface to retrieve data from objects in a S3-like store.
@@ -43,7 +50,13 @@ pub struct RequestTaskConfig {
     pub preferred_part_size: usize,
     pub initial_request_size: usize,
     pub max_read_window_size: usize,
-    pub read_window_size_multiplier: usize,
+    pub strategy: Arc<dyn PrefetchStrategy>,
+    pub budget: Arc<PrefetchBudget>,
+    pub hedging: Arc<RequestHedging>,
+    pub adaptive_part_size: Arc<AdaptivePartSize>,
+    pub priority: PrefetchPriority,
+    /// Counter of the bytes received by the requests of the file handle.
+    pub bytes_prefetched: Arc<AtomicU64>,
 }
 
 impl RequestTaskConfig {
@@ -241,7 +254,9 @@ impl<Client: ObjectClient + Clone + Send + Sync + 'static> ObjectPartStream<Clie
             // the read part size as minimum read window.
             min_read_window_size: config.read_part_size,
             max_read_window_size: config.max_read_window_size,
-            read_window_size_multiplier: config.read_window_size_multiplier,
+            strategy: config.strategy.clone(),
+            budget: config.budget.clone(),
+            priority: config.priority,
             request_range: range.into(),
             read_window_alignment_config: ReadWindowAlignmentConfig::AlignToPartSize {
                 from_offset: range.start() + config.initial_request_size as u64,
@@ -250,7 +265,8 @@ impl<Client: ObjectClient + Clone + Send + Sync + 'static> ObjectPartStream<Clie
         };
         let (backpressure_controller, mut backpressure_limiter) =
             new_backpressure_controller(backpressure_config, self.mem_limiter.clone());
-        let (part_queue, part_queue_producer) = unbounded_part_queue(self.mem_limiter.clone());
+        let (part_queue, part_queue_producer) =
+            unbounded_part_queue(self.mem_limiter.clone(), config.bytes_prefetched.clone());
         trace!(?range, "spawning request");
 
         let span = debug_span!("prefetch", ?range);
@@ -268,6 +284,9 @@ impl<Client: ObjectClient + Clone + Send + Sync + 'static> ObjectPartStream<Clie
                         initial_request_end_offset,
                         config.range,
                         config.handle_id,
+                        config.read_part_size,
+                        &config.hedging,
+                        &config.adaptive_part_size,
                     );
 
                     let part_composer = ClientPartComposer {
@@ -341,6 +360,7 @@ where
 /// This is a workaround for a specific issue where initial read window size could be very small (~1MB), but the CRT only returns data
 /// in chunks of part size (default to 8MB) even if initial read window is smaller than that, which make time to first byte much higher
 /// than expected.
+#[allow(clippy::too_many_arguments)]
 pub fn read_from_client_stream<'a, Client: ObjectClient + Clone + 'a>(
     backpressure_limiter: &'a mut BackpressureLimiter,
     client: &'a Client,
@@ -349,6 +369,9 @@ pub fn read_from_client_stream<'a, Client: ObjectClient + Clone + 'a>(
     initial_request_end_offset: u64,
     range: RequestRange,
     handle_id: HandleId,
+    part_size: usize,
+    hedging: &'a RequestHedging,
+    adaptive_part_size: &'a AdaptivePartSize,
 ) -> impl Stream<Item = RequestReaderOutput<Client::ClientError>> + 'a {
     try_stream! {
         // Let's start by issuing the first request with a range trimmed to initial read window offset
@@ -362,6 +385,9 @@ pub fn read_from_client_stream<'a, Client: ObjectClient + Clone + 'a>(
                 object_id.clone(),
                 first_req_range.into(),
                 handle_id,
+                part_size,
+                hedging,
+                adaptive_part_size,
             );
             pin_mut!(first_request_stream);
             while let Some(next) = first_request_stream.next().await {
@@ -415,6 +441,9 @@ pub fn read_from_client_stream<'a, Client: ObjectClient + Clone + 'a>(
                 object_id.clone(),
                 range.into(),
                 handle_id,
+                part_size,
+                hedging,
+                adaptive_part_size,
             );
             pin_mut!(request_stream);
             while let Some(next) = request_stream.next().await {
@@ -424,9 +453,60 @@ pub fn read_from_client_stream<'a, Client: ObjectClient + Clone + 'a>(
     }
 }
 
+/// A GetObject response along with its backpressure handle.
+type StartedRequest<Client> = (
+    Pin<Box<<Client as ObjectClient>::GetObjectResponse>>,
+    <<Client as ObjectClient>::GetObjectResponse as GetObjectResponse>::BackpressureHandle,
+);
+
+/// Starts a meta GetObject request with the specified range, with a read window covering the one of
+/// `backpressure_limiter`.
+async fn start_request<Client: ObjectClient>(
+    backpressure_limiter: &BackpressureLimiter,
+    client: &Client,
+    bucket: &str,
+    id: &ObjectId,
+    request_range: Range<u64>,
+    part_size: usize,
+) -> Result<StartedRequest<Client>, PrefetchReadError<Client::ClientError>> {
+    let mut request = client
+        .get_object(
+            bucket,
+            id.key(),
+            &GetObjectParams::new()
+                .range(Some(request_range))
+                .if_match(Some(id.etag().clone()))
+                .version_id(id.version_id().map(str::to_owned))
+                .part_size(Some(part_size)),
+        )
+        .await
+        .inspect_err(|e| error!(key = id.key(), error = ?e, "GetObject request failed"))
+        .map_err(|err| PrefetchReadError::get_request_failed(err, bucket, id.key()))?;
+
+    let mut client_backpressure_handle = request
+        .backpressure_handle()
+        .expect("S3 client backpressure should always be enabled in Mountpoint")
+        .clone();
+    // If `initial_read_window` configured on the client is smaller than what
+    // `backpressure_limiter` expects, immediately increase the window to guarantee progress.
+    //
+    // Without this line we could get stuck in `request.next()` when `initial_read_window`
+    // on the client is lower than the one on the `backpressure_limiter`.
+    //
+    // Example: This could happen if `initial_request_size` is 3x larger than `part_size` and
+    // `initial_read_window` on the client equals to `part_size`.
+    //
+    // In that case, `wait_for_read_window_increment` will return `None`, assuming that the
+    // window end (set in the `backpressure_limiter`) is also known to the client. However,
+    // the client will only know about a lower value configured by `initial_read_window`.
+    client_backpressure_handle.ensure_read_window(backpressure_limiter.read_window_end_offset());
+    Ok((Box::pin(request), client_backpressure_handle))
+}
+
 /// Creates a meta GetObject request with the specified range and sends received body parts via the returned [Stream].
 ///
 /// A [PrefetchReadError] is returned when something goes wrong in the underlying meta GetObject request.
+#[allow(clippy::too_many_arguments)]
 fn read_from_request<'a, Client: ObjectClient + 'a>(
     backpressure_limiter: &'a mut BackpressureLimiter,
     client: &'a Client,
@@ -434,41 +514,72 @@ fn read_from_request<'a, Client: ObjectClient + 'a>(
     id: ObjectId,
     request_range: Range<u64>,
     handle_id: HandleId,
+    part_size: usize,
+    hedging: &'a RequestHedging,
+    adaptive_part_size: &'a AdaptivePartSize,
 ) -> impl Stream<Item = RequestReaderOutput<Client::ClientError>> + 'a {
     // TODO: Pass handle_id to GetObjectParams once the client crate exposes the field
     let _ = handle_id;
     try_stream! {
-        let mut request = client
-            .get_object(&bucket, id.key(), &GetObjectParams::new().range(Some(request_range.clone())).if_match(Some(id.etag().clone())))
-            .await
-            .inspect_err(|e| error!(key=id.key(), error=?e, "GetObject request failed"))
-            .map_err(|err| PrefetchReadError::get_request_failed(err, &bucket, id.key()))?;
-
-        let mut client_backpressure_handle = request.backpressure_handle()
-            .expect("S3 client backpressure should always be enabled in Mountpoint")
-            .clone();
-        // If `initial_read_window` configured on the client is smaller than what
-        // `backpressure_limiter` expects, immediately increase the window to guarantee progress.
-        //
-        // Without this line we could get stuck in `request.next()` when `initial_read_window`
-        // on the client is lower than the one on the `backpressure_limiter`.
-        //
-        // Example: This could happen if `initial_request_size` is 3x larger than `part_size` and
-        // `initial_read_window` on the client equals to `part_size`.
-        //
-        // In that case, `wait_for_read_window_increment` will return `None`, assuming that the
-        // window end (set in the `backpressure_limiter`) is also known to the client. However,
-        // the client will only know about a lower value configured by `initial_read_window`.
-        client_backpressure_handle.ensure_read_window(backpressure_limiter.read_window_end_offset());
-
-        pin_mut!(request);
-        while let Some(next) = request.next().await {
+        let started = Instant::now();
+        let (mut request, mut client_backpressure_handle) =
+            start_request(backpressure_limiter, client, &bucket, &id, request_range.clone(), part_size).await?;
+
+        // When hedging, wait for the first part here to measure its latency, and to duplicate the
+        // request if it is late.
+        let mut first_part = None;
+        if hedging.is_enabled() {
+            let next = match hedging.threshold() {
+                None => request.next().await,
+                Some(threshold) => {
+                    let mut hedged_handle = None;
+                    let hedged_handle_slot = &mut hedged_handle;
+                    let (limiter, bucket, id, range) = (&*backpressure_limiter, &bucket, &id, request_range.clone());
+                    let (winner, next, hedged) = first_item_hedged(request, threshold, move || async move {
+                        let (request, handle) = start_request(limiter, client, bucket, id, range, part_size).await?;
+                        *hedged_handle_slot = Some(handle);
+                        Ok::<_, PrefetchReadError<Client::ClientError>>(request)
+                    })
+                    .await?;
+                    request = winner;
+                    if hedged {
+                        client_backpressure_handle = hedged_handle.expect("the hedged request was started");
+                    }
+                    next
+                }
+            };
+            if matches!(next, Some(Ok(_))) {
+                hedging.record_first_byte_latency(started.elapsed());
+            }
+            first_part = Some(next);
+        }
+
+        // Time to the first part, and time spent waiting for the parts after it with their size,
+        // to measure the latency and throughput of the request.
+        let mut first_byte_latency = None;
+        let mut transfer_time = Duration::ZERO;
+        let mut transferred_bytes = 0;
+        loop {
+            let waiting = Instant::now();
+            let next = match first_part.take() {
+                Some(next) => next,
+                None => request.next().await,
+            };
+            let Some(next) = next else {
+                break;
+            };
             let part = next
                 .inspect_err(|e| error!(key=id.key(), error=?e, "GetObject body part failed"))
                 .map_err(|err| PrefetchReadError::get_request_failed(err, &bucket, id.key()))?;
 
             let length = part.data.len() as u64;
             trace!(offset=part.offset, length, "received GetObject part");
+            if first_byte_latency.is_none() {
+                first_byte_latency = Some(started.elapsed());
+            } else {
+                transfer_time += waiting.elapsed();
+                transferred_bytes += length;
+            }
             metrics::counter!("s3.client.total_bytes", "type" => "read").increment(length);
 
             let next_offset = part.offset + length;
@@ -495,6 +606,9 @@ fn read_from_request<'a, Client: ObjectClient + 'a>(
                 client_backpressure_handle.ensure_read_window(next_read_window_end_offset);
             }
         }
+        if let Some(first_byte_latency) = first_byte_latency {
+            adaptive_part_size.record_request(first_byte_latency, transferred_bytes, transfer_time);
+        }
         trace!("request finished");
     }
 }
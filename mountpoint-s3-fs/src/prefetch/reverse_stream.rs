@@ -0,0 +1,194 @@
+//! Prefetching for objects that are read backwards.
+//!
+//! Some applications (for example video players scrubbing in reverse, or seismic processing tools)
+//! read an object from the end towards the start. Each of those reads is out of order for the
+//! forward prefetcher, which would start a new GetObject request for every read. Instead, a
+//! [ReverseStream] buffers a contiguous range of the object ending where the reader started going
+//! backwards, and always keeps one ranged request in flight for the chunk right before it.
+
+use mountpoint_s3_client::ObjectClient;
+use tracing::trace;
+
+use crate::checksums::ChecksummedBytes;
+use crate::mem_limiter::{BufferArea, MemoryLimiter};
+use crate::object::ObjectId;
+use crate::sync::Arc;
+use crate::sync::atomic::AtomicU64;
+
+use super::budget::{PrefetchBudget, PrefetchPriority};
+use super::hedging::RequestHedging;
+use super::part::Part;
+use super::part_size::AdaptivePartSize;
+use super::part_stream::{PartStream, RequestRange, RequestTaskConfig};
+use super::strategy::{ReadWindowLimits, clamped_scale_up};
+use super::task::RequestTask;
+use super::{HandleId, PrefetchReadError, PrefetchStrategy};
+
+/// Configuration for a [ReverseStream].
+#[derive(Debug)]
+pub struct ReverseStreamConfig {
+    pub bucket: String,
+    pub object_id: ObjectId,
+    pub handle_id: HandleId,
+    pub object_size: u64,
+    /// End offset (exclusive) of the range the reader is moving backwards from.
+    pub end_offset: u64,
+    /// Size of the first chunk to request before `end_offset`, which must cover the first read.
+    pub initial_chunk_size: usize,
+    /// Maximum size of a single chunk, which the chunk size grows to while the reader keeps
+    /// reading backwards.
+    pub max_chunk_size: usize,
+    pub preferred_part_size: usize,
+    pub strategy: Arc<dyn PrefetchStrategy>,
+    pub budget: Arc<PrefetchBudget>,
+    pub hedging: Arc<RequestHedging>,
+    pub adaptive_part_size: Arc<AdaptivePartSize>,
+    pub priority: PrefetchPriority,
+    pub bytes_prefetched: Arc<AtomicU64>,
+}
+
+/// A stream of chunks of an object, requested in reverse order.
+#[derive(Debug)]
+pub struct ReverseStream<Client: ObjectClient + Clone + Send + Sync + 'static> {
+    part_stream: PartStream<Client>,
+    config: ReverseStreamConfig,
+    /// Data already fetched, covering `buffer_start..buffer_end`. Memory for it is reserved in the
+    /// memory limiter.
+    buffer: Option<Part>,
+    buffer_start: u64,
+    buffer_end: u64,
+    /// Inflight request for the chunk right before `buffer_start`, if any.
+    next_chunk: Option<RequestTask<Client>>,
+    next_chunk_start: u64,
+    chunk_size: usize,
+    mem_limiter: Arc<MemoryLimiter>,
+}
+
+impl<Client> ReverseStream<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    pub fn new(part_stream: PartStream<Client>, config: ReverseStreamConfig, mem_limiter: Arc<MemoryLimiter>) -> Self {
+        let end_offset = config.end_offset.min(config.object_size);
+        let mut stream = Self {
+            part_stream,
+            buffer: None,
+            buffer_start: end_offset,
+            buffer_end: end_offset,
+            next_chunk: None,
+            next_chunk_start: end_offset,
+            chunk_size: config.initial_chunk_size.max(1),
+            config,
+            mem_limiter,
+        };
+        stream.spawn_next_chunk();
+        stream
+    }
+
+    /// Whether a read of `length` bytes at `offset` can be served by this stream, either from the
+    /// buffer or from the chunk currently being requested.
+    pub fn can_serve(&self, offset: u64, length: usize) -> bool {
+        let end = offset.saturating_add(length as u64).min(self.config.object_size);
+        offset >= self.next_chunk_start && end <= self.buffer_end && offset < end
+    }
+
+    /// Read `length` bytes at `offset`. The caller must have checked the range with [Self::can_serve].
+    pub async fn read(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<ChecksummedBytes, PrefetchReadError<Client::ClientError>> {
+        assert!(self.can_serve(offset, length), "read outside of the reverse stream");
+        let end = offset.saturating_add(length as u64).min(self.config.object_size);
+
+        if offset < self.buffer_start {
+            self.advance(end).await?;
+        }
+
+        let buffer = self.buffer.as_ref().expect("buffer covers the read range");
+        Ok(buffer.slice(offset..end).into_bytes(&self.config.object_id, offset)?)
+    }
+
+    /// Wait for the inflight chunk, prepend it to the buffer and request the next one. Data in the
+    /// buffer past `keep_end` won't be read again by a backwards reader, so it is dropped.
+    async fn advance(&mut self, keep_end: u64) -> Result<(), PrefetchReadError<Client::ClientError>> {
+        let mut task = self.next_chunk.take().expect("a chunk is inflight before the buffer");
+        let mut chunk = task.read_to_end().await?.expect("chunks are never empty");
+        self.mem_limiter.reserve(BufferArea::Prefetch, chunk.len() as u64);
+
+        if let Some(mut buffer) = self.buffer.take() {
+            let keep_end = keep_end.clamp(self.buffer_start, self.buffer_end);
+            if keep_end < self.buffer_end {
+                self.mem_limiter
+                    .release(BufferArea::Prefetch, self.buffer_end - keep_end);
+                if keep_end > self.buffer_start {
+                    let _ = buffer.split_off((keep_end - self.buffer_start) as usize);
+                    chunk.extend(&buffer)?;
+                }
+                self.buffer_end = keep_end;
+            } else {
+                chunk.extend(&buffer)?;
+            }
+        }
+        self.buffer_start = self.next_chunk_start;
+        self.buffer = Some(chunk);
+
+        self.scale_up();
+        self.spawn_next_chunk();
+        Ok(())
+    }
+
+    /// Grow the chunk size for the next request, as long as we have the memory to buffer it.
+    fn scale_up(&mut self) {
+        let limits = ReadWindowLimits {
+            min: self.config.preferred_part_size.min(self.config.max_chunk_size),
+            max: self.config.max_chunk_size,
+        };
+        let new_size = clamped_scale_up(self.config.strategy.as_ref(), self.chunk_size, limits);
+        if new_size > self.chunk_size && self.mem_limiter.available_mem() >= new_size as u64 {
+            trace!(from = self.chunk_size, to = new_size, "scaling up reverse chunk size");
+            self.chunk_size = new_size;
+        }
+    }
+
+    fn spawn_next_chunk(&mut self) {
+        if self.buffer_start == 0 {
+            return;
+        }
+        let start = self.buffer_start.saturating_sub(self.chunk_size as u64);
+        let size = (self.buffer_start - start) as usize;
+        trace!(start, size, "requesting reverse chunk");
+        let config = RequestTaskConfig {
+            bucket: self.config.bucket.clone(),
+            object_id: self.config.object_id.clone(),
+            handle_id: self.config.handle_id,
+            range: RequestRange::new(self.config.object_size as usize, start, size),
+            read_part_size: self.part_stream.client().read_part_size(),
+            preferred_part_size: self.config.preferred_part_size,
+            // Fetch the whole chunk in a single request, as it is read to completion before any of
+            // its data is returned.
+            initial_request_size: size,
+            max_read_window_size: size,
+            strategy: self.config.strategy.clone(),
+            budget: self.config.budget.clone(),
+            hedging: self.config.hedging.clone(),
+            adaptive_part_size: self.config.adaptive_part_size.clone(),
+            priority: self.config.priority,
+            bytes_prefetched: self.config.bytes_prefetched.clone(),
+        };
+        self.next_chunk = Some(self.part_stream.spawn_get_object_request(config));
+        self.next_chunk_start = start;
+    }
+}
+
+impl<Client> Drop for ReverseStream<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if self.buffer.is_some() {
+            self.mem_limiter
+                .release(BufferArea::Prefetch, self.buffer_end - self.buffer_start);
+        }
+    }
+}
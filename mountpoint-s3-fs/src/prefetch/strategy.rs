@@ -0,0 +1,139 @@
+//! Strategies deciding how the prefetcher sizes its read window and which ranges it requests.
+//!
+//! The prefetcher delegates these decisions to a [PrefetchStrategy], so that embedders with a known
+//! access pattern can plug in their own logic with [super::PrefetcherBuilder::with_strategy]. The
+//! default [SequentialPrefetchStrategy] assumes sequential reads: it requests the rest of the object
+//! and grows the read window geometrically while the reader keeps up.
+
+use std::fmt::Debug;
+use std::ops::Range;
+
+/// Bounds that any read window size chosen by a [PrefetchStrategy] is clamped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadWindowLimits {
+    /// Minimum read window size, usually the part size of the client.
+    pub min: usize,
+    /// Maximum read window size, from [super::PrefetcherConfig::max_read_window_size].
+    pub max: usize,
+}
+
+impl ReadWindowLimits {
+    fn clamp(&self, size: usize) -> usize {
+        size.max(self.min).min(self.max)
+    }
+}
+
+/// Decides how a prefetcher sizes its read window and orders its requests to S3.
+///
+/// A strategy is shared by all the file handles of a [super::Prefetcher], so it must not keep
+/// per-handle state.
+pub trait PrefetchStrategy: Debug + Send + Sync {
+    /// Range of the object that a new request should cover when prefetching (re)starts at `offset`.
+    ///
+    /// The range must start at `offset`. It is clamped to the end of the object. When a request
+    /// completes before the reader reaches the end of the object, the prefetcher asks for the next
+    /// range from the offset the request ended at.
+    fn request_range(&self, offset: u64, object_size: u64) -> Range<u64> {
+        offset..object_size
+    }
+
+    /// New preferred read window size after the reader has consumed all the data fetched so far.
+    fn scale_up(&self, current: usize, limits: ReadWindowLimits) -> usize;
+
+    /// New preferred read window size when there is not enough memory to grow the read window.
+    fn scale_down(&self, current: usize, limits: ReadWindowLimits) -> usize;
+}
+
+/// The default [PrefetchStrategy], tuned for sequential reads. Each request covers the rest of the
+/// object, and the read window is scaled up and down by a constant multiplier.
+#[derive(Debug, Clone, Copy)]
+pub struct SequentialPrefetchStrategy {
+    multiplier: usize,
+}
+
+impl SequentialPrefetchStrategy {
+    /// Minimum multiplier, as scaling the read window up and down won't work with a multiplier of 1.
+    const MIN_MULTIPLIER: usize = 2;
+
+    /// Create a strategy scaling the read window by `multiplier`, which is raised to at least 2.
+    pub fn new(multiplier: usize) -> Self {
+        Self {
+            multiplier: multiplier.max(Self::MIN_MULTIPLIER),
+        }
+    }
+
+    pub fn multiplier(&self) -> usize {
+        self.multiplier
+    }
+}
+
+impl PrefetchStrategy for SequentialPrefetchStrategy {
+    fn scale_up(&self, current: usize, limits: ReadWindowLimits) -> usize {
+        limits.clamp(current.saturating_mul(self.multiplier))
+    }
+
+    fn scale_down(&self, current: usize, limits: ReadWindowLimits) -> usize {
+        limits.clamp(current / self.multiplier)
+    }
+}
+
+/// Apply a strategy's choice of read window size, enforcing the limits regardless of the strategy.
+pub(super) fn clamped_scale_up(strategy: &dyn PrefetchStrategy, current: usize, limits: ReadWindowLimits) -> usize {
+    limits.clamp(strategy.scale_up(current, limits))
+}
+
+/// Apply a strategy's choice of read window size, enforcing the limits regardless of the strategy.
+pub(super) fn clamped_scale_down(strategy: &dyn PrefetchStrategy, current: usize, limits: ReadWindowLimits) -> usize {
+    limits.clamp(strategy.scale_down(current, limits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    const LIMITS: ReadWindowLimits = ReadWindowLimits {
+        min: 8 * 1024 * 1024,
+        max: 64 * 1024 * 1024,
+    };
+
+    #[test_case(0, 2)]
+    #[test_case(1, 2)]
+    #[test_case(2, 2)]
+    #[test_case(8, 8)]
+    fn test_sequential_multiplier(multiplier: usize, expected: usize) {
+        assert_eq!(SequentialPrefetchStrategy::new(multiplier).multiplier(), expected);
+    }
+
+    #[test]
+    fn test_sequential_scaling_respects_limits() {
+        let strategy = SequentialPrefetchStrategy::new(4);
+        assert_eq!(strategy.scale_up(1024 * 1024, LIMITS), LIMITS.min);
+        assert_eq!(strategy.scale_up(8 * 1024 * 1024, LIMITS), 32 * 1024 * 1024);
+        assert_eq!(strategy.scale_up(32 * 1024 * 1024, LIMITS), LIMITS.max);
+        assert_eq!(strategy.scale_up(usize::MAX, LIMITS), LIMITS.max);
+        assert_eq!(strategy.scale_down(64 * 1024 * 1024, LIMITS), 16 * 1024 * 1024);
+        assert_eq!(strategy.scale_down(16 * 1024 * 1024, LIMITS), LIMITS.min);
+    }
+
+    #[test]
+    fn test_custom_strategy_is_clamped() {
+        #[derive(Debug)]
+        struct Unbounded;
+
+        impl PrefetchStrategy for Unbounded {
+            fn scale_up(&self, _current: usize, _limits: ReadWindowLimits) -> usize {
+                usize::MAX
+            }
+
+            fn scale_down(&self, _current: usize, _limits: ReadWindowLimits) -> usize {
+                0
+            }
+        }
+
+        assert_eq!(clamped_scale_up(&Unbounded, LIMITS.min, LIMITS), LIMITS.max);
+        assert_eq!(clamped_scale_down(&Unbounded, LIMITS.max, LIMITS), LIMITS.min);
+        assert_eq!(Unbounded.request_range(10, 100), 10..100);
+    }
+}
@@ -0,0 +1,308 @@
+//! Prefetching for objects read with a fixed stride.
+//!
+//! Some applications read blocks of the same size at regularly spaced offsets, for example a
+//! column of a row-major matrix, or every Nth record of a genomics file. Each of those reads is out
+//! of order for the forward prefetcher, which would start a new GetObject request for every read,
+//! and eventually stop prefetching altogether. Instead, a [StrideDetector] recognizes the pattern,
+//! and a [StridedStream] keeps ranged requests in flight for the next few offsets it predicts.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use mountpoint_s3_client::ObjectClient;
+use tracing::trace;
+
+use crate::checksums::ChecksummedBytes;
+use crate::mem_limiter::{BufferArea, MemoryLimiter};
+use crate::object::ObjectId;
+use crate::sync::Arc;
+use crate::sync::atomic::AtomicU64;
+
+use super::budget::{PrefetchBudget, PrefetchPriority};
+use super::hedging::RequestHedging;
+use super::part::Part;
+use super::part_size::AdaptivePartSize;
+use super::part_stream::{PartStream, RequestRange, RequestTaskConfig};
+use super::task::RequestTask;
+use super::{HandleId, PrefetchReadError, PrefetchStrategy};
+
+/// Number of reads of the same size, each separated from the next one by the same stride, after
+/// which we assume the object is read with that stride.
+const STRIDED_READ_THRESHOLD: usize = 3;
+
+/// A fixed-stride access pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stride {
+    /// Distance between the offsets of two consecutive reads.
+    pub stride: u64,
+    /// Size of each read.
+    pub length: usize,
+}
+
+/// Detects reads of the same size, separated by a constant gap.
+///
+/// Reads continuing the previous one sequentially are merged with it, as FUSE splits large reads
+/// into several requests.
+#[derive(Debug, Default)]
+pub struct StrideDetector {
+    /// Range of the latest read, including the reads continuing it
+    current: Option<Range<u64>>,
+    /// Ranges of the reads before it, oldest first
+    history: VecDeque<Range<u64>>,
+}
+
+impl StrideDetector {
+    /// Record a read, and return the stride if the reads before it followed one, which this read
+    /// starts the next block of.
+    pub fn record(&mut self, offset: u64, length: usize) -> Option<Stride> {
+        let end = offset.saturating_add(length as u64);
+        match self.current.take() {
+            Some(current) if current.end == offset => {
+                self.current = Some(current.start..end);
+                return None;
+            }
+            Some(current) => {
+                if self.history.len() == STRIDED_READ_THRESHOLD {
+                    self.history.pop_front();
+                }
+                self.history.push_back(current);
+            }
+            None => {}
+        }
+        self.current = Some(offset..end);
+        self.stride(offset)
+    }
+
+    fn stride(&self, offset: u64) -> Option<Stride> {
+        if self.history.len() < STRIDED_READ_THRESHOLD {
+            return None;
+        }
+        let last = self.history.back()?;
+        let stride = Stride {
+            stride: offset.checked_sub(last.start)?,
+            length: (last.end - last.start) as usize,
+        };
+        // Reads covering the gap between them are sequential rather than strided.
+        if stride.stride <= stride.length as u64 {
+            return None;
+        }
+        let next_starts = self.history.iter().skip(1).map(|read| read.start).chain([offset]);
+        let follows_stride = self.history.iter().zip(next_starts).all(|(read, next_start)| {
+            read.end - read.start == stride.length as u64 && next_start.checked_sub(read.start) == Some(stride.stride)
+        });
+        follows_stride.then_some(stride)
+    }
+}
+
+/// Configuration for a [StridedStream].
+#[derive(Debug)]
+pub struct StridedStreamConfig {
+    pub bucket: String,
+    pub object_id: ObjectId,
+    pub handle_id: HandleId,
+    pub object_size: u64,
+    /// Offset of the first read to prefetch.
+    pub first_offset: u64,
+    /// Distance between the offsets of two consecutive reads.
+    pub stride: u64,
+    /// Size of each read.
+    pub read_size: usize,
+    /// Number of reads to keep requests in flight for.
+    pub depth: usize,
+    pub preferred_part_size: usize,
+    pub strategy: Arc<dyn PrefetchStrategy>,
+    pub budget: Arc<PrefetchBudget>,
+    pub hedging: Arc<RequestHedging>,
+    pub adaptive_part_size: Arc<AdaptivePartSize>,
+    pub priority: PrefetchPriority,
+    pub bytes_prefetched: Arc<AtomicU64>,
+}
+
+/// A stream of ranged requests for reads predicted from a stride.
+#[derive(Debug)]
+pub struct StridedStream<Client: ObjectClient + Clone + Send + Sync + 'static> {
+    part_stream: PartStream<Client>,
+    config: StridedStreamConfig,
+    /// Data fetched for the last read, which may be read in several pieces. Memory for it is
+    /// reserved in the memory limiter.
+    buffer: Option<Part>,
+    /// Inflight requests for the predicted reads, in offset order.
+    inflight: VecDeque<RequestTask<Client>>,
+    next_offset: u64,
+    mem_limiter: Arc<MemoryLimiter>,
+}
+
+impl<Client> StridedStream<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    pub fn new(part_stream: PartStream<Client>, config: StridedStreamConfig, mem_limiter: Arc<MemoryLimiter>) -> Self {
+        let mut stream = Self {
+            part_stream,
+            buffer: None,
+            inflight: VecDeque::new(),
+            next_offset: config.first_offset,
+            config,
+            mem_limiter,
+        };
+        stream.spawn_requests();
+        stream
+    }
+
+    /// Whether a read of `length` bytes at `offset` can be served by this stream, either from the
+    /// buffer or from one of the inflight requests.
+    pub fn can_serve(&self, offset: u64, length: usize) -> bool {
+        let end = offset.saturating_add(length as u64).min(self.config.object_size);
+        let covers = |range: Range<u64>| offset >= range.start && end <= range.end && offset < end;
+        self.buffer
+            .as_ref()
+            .is_some_and(|buffer| covers(buffer.offset()..buffer.end_offset()))
+            || self
+                .inflight
+                .iter()
+                .any(|task| covers(task.start_offset()..task.end_offset()))
+    }
+
+    /// Read `length` bytes at `offset`. The caller must have checked the range with [Self::can_serve].
+    pub async fn read(
+        &mut self,
+        offset: u64,
+        length: usize,
+    ) -> Result<ChecksummedBytes, PrefetchReadError<Client::ClientError>> {
+        assert!(self.can_serve(offset, length), "read outside of the strided stream");
+        let end = offset.saturating_add(length as u64).min(self.config.object_size);
+
+        let in_buffer = self
+            .buffer
+            .as_ref()
+            .is_some_and(|buffer| offset >= buffer.offset() && end <= buffer.end_offset());
+        if !in_buffer {
+            // Requests for reads the reader skipped won't be needed anymore.
+            while let Some(task) = self.inflight.pop_front() {
+                if offset >= task.start_offset() && end <= task.end_offset() {
+                    self.fill_buffer(task).await?;
+                    break;
+                }
+            }
+            self.spawn_requests();
+        }
+
+        let buffer = self.buffer.as_ref().expect("buffer covers the read range");
+        Ok(buffer.slice(offset..end).into_bytes(&self.config.object_id, offset)?)
+    }
+
+    /// Wait for all the data of a request, and replace the buffer with it.
+    async fn fill_buffer(
+        &mut self,
+        mut task: RequestTask<Client>,
+    ) -> Result<(), PrefetchReadError<Client::ClientError>> {
+        let part = task
+            .read_to_end()
+            .await?
+            .ok_or(PrefetchReadError::GetRequestTerminatedUnexpectedly)?;
+        self.mem_limiter.reserve(BufferArea::Prefetch, part.len() as u64);
+        if let Some(buffer) = self.buffer.replace(part) {
+            self.mem_limiter.release(BufferArea::Prefetch, buffer.len() as u64);
+        }
+        Ok(())
+    }
+
+    fn spawn_requests(&mut self) {
+        while self.inflight.len() < self.config.depth && self.next_offset < self.config.object_size {
+            let start = self.next_offset;
+            let size = (self.config.read_size as u64).min(self.config.object_size - start) as usize;
+            trace!(start, size, "requesting strided read");
+            let config = RequestTaskConfig {
+                bucket: self.config.bucket.clone(),
+                object_id: self.config.object_id.clone(),
+                handle_id: self.config.handle_id,
+                range: RequestRange::new(self.config.object_size as usize, start, size),
+                read_part_size: self.part_stream.client().read_part_size(),
+                preferred_part_size: self.config.preferred_part_size,
+                // Fetch each read in a single request, as it is read to completion before any of
+                // its data is returned.
+                initial_request_size: size,
+                max_read_window_size: size,
+                strategy: self.config.strategy.clone(),
+                budget: self.config.budget.clone(),
+                hedging: self.config.hedging.clone(),
+                adaptive_part_size: self.config.adaptive_part_size.clone(),
+                priority: self.config.priority,
+                bytes_prefetched: self.config.bytes_prefetched.clone(),
+            };
+            self.inflight
+                .push_back(self.part_stream.spawn_get_object_request(config));
+            self.next_offset = start.saturating_add(self.config.stride);
+        }
+    }
+}
+
+impl<Client> Drop for StridedStream<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.as_ref() {
+            self.mem_limiter.release(BufferArea::Prefetch, buffer.len() as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stride_detection() {
+        let mut detector = StrideDetector::default();
+        let detected: Vec<_> = [0, 100, 200, 300, 400]
+            .into_iter()
+            .map(|offset| detector.record(offset, 10))
+            .collect();
+        let stride = Stride {
+            stride: 100,
+            length: 10,
+        };
+        assert_eq!(detected, [None, None, None, Some(stride), Some(stride)]);
+
+        // A different stride starts over.
+        assert_eq!(detector.record(450, 10), None);
+        assert_eq!(detector.record(500, 10), None);
+    }
+
+    #[test]
+    fn test_stride_detection_merges_contiguous_reads() {
+        let mut detector = StrideDetector::default();
+        for offset in [0, 100, 200] {
+            assert_eq!(detector.record(offset, 10), None);
+            assert_eq!(detector.record(offset + 10, 10), None);
+        }
+        let stride = Stride {
+            stride: 100,
+            length: 20,
+        };
+        assert_eq!(detector.record(300, 10), Some(stride));
+    }
+
+    #[test]
+    fn test_sequential_reads_are_not_strided() {
+        let mut detector = StrideDetector::default();
+        for i in 0..10 {
+            assert_eq!(detector.record(i * 10, 10), None);
+        }
+        // Reads overlapping each other don't have a stride either.
+        for i in 0..10 {
+            assert_eq!(detector.record(1000 + i * 5, 10), None);
+        }
+    }
+
+    #[test]
+    fn test_stride_detection_requires_same_read_size() {
+        let mut detector = StrideDetector::default();
+        assert_eq!(detector.record(0, 10), None);
+        assert_eq!(detector.record(100, 10), None);
+        assert_eq!(detector.record(200, 20), None);
+        assert_eq!(detector.record(300, 20), None);
+        assert_eq!(detector.record(400, 20), None);
+    }
+}
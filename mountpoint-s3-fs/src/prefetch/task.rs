@@ -18,6 +18,14 @@ pub struct RequestTask<Client: ObjectClient> {
     range: RequestRange,
     part_queue: PartQueue<Client>,
     backpressure_controller: BackpressureController,
+    /// Reads smaller than this are served from a chunk of this size taken from the part queue at
+    /// once, see [Self::set_read_coalesce_size].
+    read_coalesce_size: usize,
+    /// Remaining data of the chunk taken by a coalesced read, which hasn't been returned yet.
+    coalesced: Option<Part>,
+    /// Start offset of that chunk, which the backpressure controller is told about once the whole
+    /// chunk has been returned.
+    coalesced_start: u64,
 }
 
 impl<Client: ObjectClient> RequestTask<Client> {
@@ -33,11 +41,21 @@ impl<Client: ObjectClient> RequestTask<Client> {
             range,
             part_queue,
             backpressure_controller,
+            read_coalesce_size: 0,
+            coalesced: None,
+            coalesced_start: 0,
         }
     }
 
+    /// Coalesce reads smaller than `size` bytes, so that a single lookup in the part queue and a
+    /// single read window update cover many small reads. If set to 0, reads are never coalesced.
+    pub fn set_read_coalesce_size(&mut self, size: usize) {
+        self.read_coalesce_size = size;
+    }
+
     // Push a given list of parts in front of the part queue
     pub async fn push_front(&mut self, parts: Vec<Part>) -> Result<(), PrefetchReadError<Client::ClientError>> {
+        self.return_coalesced().await?;
         // Iterate backwards to push each part to the front of the part queue
         for part in parts.into_iter().rev() {
             self.remaining += part.len();
@@ -47,27 +65,85 @@ impl<Client: ObjectClient> RequestTask<Client> {
     }
 
     pub async fn read(&mut self, length: usize) -> Result<Part, PrefetchReadError<Client::ClientError>> {
+        if self.coalesced.is_none() && length < self.read_coalesce_size {
+            let chunk = self
+                .part_queue
+                .read(self.read_coalesce_size.min(self.remaining))
+                .await?;
+            self.coalesced_start = chunk.offset();
+            self.coalesced = Some(chunk);
+        }
+        if let Some(chunk) = self.coalesced.as_mut() {
+            let part = if length < chunk.len() {
+                let tail = chunk.split_off(length);
+                std::mem::replace(chunk, tail)
+            } else {
+                self.coalesced.take().expect("chunk was just matched")
+            };
+            debug_assert!(part.len() <= self.remaining);
+            self.remaining -= part.len();
+            if self.coalesced.is_none() {
+                self.report_read(self.coalesced_start, part.end_offset()).await?;
+            }
+            return Ok(part);
+        }
+
         let part = self.part_queue.read(length).await?;
         debug_assert!(part.len() <= self.remaining);
         self.remaining -= part.len();
+        self.report_read(part.offset(), part.end_offset()).await?;
+        Ok(part)
+    }
 
-        // We read some data out of the part queue so the read window should be moved
+    /// Tell the backpressure controller the data in `start..end` has been read out of the part
+    /// queue, so that the read window can be moved.
+    async fn report_read(&mut self, start: u64, end: u64) -> Result<(), PrefetchReadError<Client::ClientError>> {
         self.backpressure_controller
             .send_feedback(DataRead {
-                offset: part.offset(),
-                length: part.len(),
+                offset: start,
+                length: (end - start) as usize,
             })
             .await?;
 
-        let next_offset = part.offset() + part.len() as u64;
+        let next_offset = end;
         let remaining_in_queue = self.available_offset().saturating_sub(next_offset) as usize;
         // If the part queue is empty it means we are reading faster than the task could prefetch,
         // so we should use larger window for the task.
         if remaining_in_queue == 0 {
             self.backpressure_controller.send_feedback(PartQueueStall).await?;
         }
+        Ok(())
+    }
 
-        Ok(part)
+    /// Put the remaining data of a coalesced read back into the part queue.
+    async fn return_coalesced(&mut self) -> Result<(), PrefetchReadError<Client::ClientError>> {
+        if let Some(chunk) = self.coalesced.take() {
+            if chunk.offset() > self.coalesced_start {
+                self.report_read(self.coalesced_start, chunk.offset()).await?;
+            }
+            self.part_queue.unread(chunk);
+        }
+        Ok(())
+    }
+
+    /// Read all the remaining data of the request into a single part, or None if nothing remains.
+    pub async fn read_to_end(&mut self) -> Result<Option<Part>, PrefetchReadError<Client::ClientError>> {
+        let mut result: Option<Part> = None;
+        while self.remaining > 0 {
+            let part = self.read(self.remaining).await?;
+            match result.as_mut() {
+                Some(result) => result.extend(&part)?,
+                None => result = Some(part),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Cancel the request, and return the parts it already downloaded that haven't been read yet.
+    pub fn into_received_parts(mut self) -> Vec<Part> {
+        let mut parts: Vec<_> = self.coalesced.take().into_iter().collect();
+        parts.extend(self.part_queue.take_received());
+        parts
     }
 
     pub fn start_offset(&self) -> u64 {
@@ -94,4 +170,8 @@ impl<Client: ObjectClient> RequestTask<Client> {
     pub fn read_window_end_offset(&self) -> u64 {
         self.backpressure_controller.read_window_end_offset()
     }
+
+    pub fn read_window_size(&self) -> usize {
+        self.backpressure_controller.preferred_read_window_size()
+    }
 }
@@ -4,9 +4,13 @@
 use mountpoint_s3_client::config::{EndpointConfig, SigningAlgorithm};
 
 pub mod config;
+pub mod delimiter;
+pub mod key_mapping;
 pub mod path;
 pub mod prefix;
 
+pub use delimiter::{KeyDelimiter, KeyDelimiterError};
+pub use key_mapping::KeyMapping;
 pub use path::{Bucket, S3Path, S3PathError};
 pub use prefix::{Prefix, PrefixError};
 
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum KeyDelimiterError {
+    #[error("the delimiter must be a single character")]
+    NotASingleCharacter,
+}
+
+/// The character separating the components of the keys that the directory hierarchy is built from, `/` by default.
+///
+/// Paths in the file system always separate their components with `/`, so they are translated to keys by replacing
+/// it with the delimiter, and the other way around. When the delimiter is not `/`, keys including a `/` have no path
+/// and are not accessible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyDelimiter {
+    delimiter: char,
+    /// UTF-8 encoding of the delimiter, which [Self::as_str] returns.
+    encoded: [u8; 4],
+}
+
+impl KeyDelimiter {
+    pub fn new(delimiter: &str) -> Result<Self, KeyDelimiterError> {
+        let mut chars = delimiter.chars();
+        match (chars.next(), chars.next()) {
+            (Some(delimiter), None) => Ok(Self::from_char(delimiter)),
+            _ => Err(KeyDelimiterError::NotASingleCharacter),
+        }
+    }
+
+    fn from_char(delimiter: char) -> Self {
+        let mut encoded = [0; 4];
+        delimiter.encode_utf8(&mut encoded);
+        Self { delimiter, encoded }
+    }
+
+    /// The delimiter, as passed to ListObjectsV2.
+    pub fn as_str(&self) -> &str {
+        let len = self.delimiter.len_utf8();
+        std::str::from_utf8(&self.encoded[..len]).expect("delimiter should be valid UTF-8")
+    }
+
+    /// Whether this is the default `/` delimiter, for which paths and keys are the same.
+    pub fn is_default(&self) -> bool {
+        self.delimiter == '/'
+    }
+
+    /// The key of the given path.
+    pub fn key_for_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if self.is_default() {
+            Cow::Borrowed(path)
+        } else {
+            Cow::Owned(path.replace('/', self.as_str()))
+        }
+    }
+
+    /// The path of the given key, if it has one.
+    pub fn path_for_key<'a>(&self, key: &'a str) -> Option<Cow<'a, str>> {
+        if self.is_default() {
+            Some(Cow::Borrowed(key))
+        } else if key.contains('/') {
+            None
+        } else {
+            Some(Cow::Owned(key.replace(self.delimiter, "/")))
+        }
+    }
+}
+
+impl Default for KeyDelimiter {
+    fn default() -> Self {
+        Self::from_char('/')
+    }
+}
+
+impl Display for KeyDelimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.delimiter)
+    }
+}
+
+impl FromStr for KeyDelimiter {
+    type Err = KeyDelimiterError;
+
+    fn from_str(s: &str) -> Result<Self, KeyDelimiterError> {
+        KeyDelimiter::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(""; "empty string")]
+    #[test_case("||"; "two characters")]
+    fn test_invalid_delimiter(delimiter: &str) {
+        assert_eq!(
+            KeyDelimiter::new(delimiter),
+            Err(KeyDelimiterError::NotASingleCharacter)
+        );
+    }
+
+    #[test_case("/", "dir/file.txt", Some("dir/file.txt"); "default")]
+    #[test_case("|", "dir|sub|file.txt", Some("dir/sub/file.txt"); "pipe")]
+    #[test_case("|", "dir|sub|", Some("dir/sub/"); "directory")]
+    #[test_case("|", "dir/file.txt", None; "path separator in key")]
+    #[test_case("→", "dir→file.txt", Some("dir/file.txt"); "multibyte")]
+    fn test_translate(delimiter: &str, key: &str, path: Option<&str>) {
+        let delimiter = KeyDelimiter::new(delimiter).unwrap();
+        assert_eq!(delimiter.as_str().len(), delimiter.to_string().len());
+        assert_eq!(delimiter.path_for_key(key).as_deref(), path);
+        if let Some(path) = path {
+            assert_eq!(delimiter.key_for_path(path), key);
+        }
+    }
+}
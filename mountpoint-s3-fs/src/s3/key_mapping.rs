@@ -0,0 +1,216 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+
+use super::KeyDelimiter;
+
+/// Name of the components of keys that are empty, as in `a//b`.
+const EMPTY_NAME: &str = "%";
+
+/// Translation between the paths of the file system and the keys of objects.
+///
+/// The components of paths are separated with `/`, and those of keys with the [KeyDelimiter]. When escaping names,
+/// the components of keys that can't be the name of a file are also percent-encoded, so that every object is
+/// reachable: `%`, NUL, `/` and the delimiter are replaced by the `%XX` encoding of their UTF-8 bytes, the `.` and `..`
+/// names by `%2E` and `%2E%2E`, and empty names by `%`. Names given to the file system are decoded back, and must be
+/// in this escaped form.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyMapping {
+    delimiter: KeyDelimiter,
+    escape_names: bool,
+}
+
+impl KeyMapping {
+    pub fn new(delimiter: KeyDelimiter, escape_names: bool) -> Self {
+        Self {
+            delimiter,
+            escape_names,
+        }
+    }
+
+    /// The delimiter of the components of keys.
+    pub fn delimiter(&self) -> KeyDelimiter {
+        self.delimiter
+    }
+
+    /// The key of the given path.
+    pub fn key_for_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if !self.escape_names {
+            return self.delimiter.key_for_path(path);
+        }
+        if path.is_empty() {
+            return Cow::Borrowed(path);
+        }
+        let (names, is_directory) = match path.strip_suffix('/') {
+            Some(names) => (names, true),
+            None => (path, false),
+        };
+        let mut key = String::with_capacity(path.len());
+        for (i, name) in names.split('/').enumerate() {
+            if i > 0 {
+                key.push_str(self.delimiter.as_str());
+            }
+            key.push_str(&unescape_name(name));
+        }
+        if is_directory {
+            key.push_str(self.delimiter.as_str());
+        }
+        Cow::Owned(key)
+    }
+
+    /// The path of the given key, if it has one. Every key has a path when escaping names.
+    pub fn path_for_key<'a>(&self, key: &'a str) -> Option<Cow<'a, str>> {
+        if !self.escape_names {
+            return self.delimiter.path_for_key(key);
+        }
+        if key.is_empty() {
+            return Some(Cow::Borrowed(key));
+        }
+        let delimiter = self.delimiter.as_str();
+        let (names, is_directory) = match key.strip_suffix(delimiter) {
+            Some(names) => (names, true),
+            None => (key, false),
+        };
+        let mut path = String::with_capacity(key.len());
+        for (i, name) in names.split(delimiter).enumerate() {
+            if i > 0 {
+                path.push('/');
+            }
+            path.push_str(&self.escape_name(name));
+        }
+        if is_directory {
+            path.push('/');
+        }
+        Some(Cow::Owned(path))
+    }
+
+    /// Whether the given name is the escaped form of a name, as listed by the file system. Other names, such as
+    /// `100%` rather than `100%25`, would be aliases of the listed ones.
+    pub fn is_escaped_name(&self, name: &str) -> bool {
+        !self.escape_names || self.escape_name(&unescape_name(name)) == name
+    }
+
+    /// Whether the given path is in the escaped form of the path of its key.
+    pub fn is_escaped_path(&self, path: &str) -> bool {
+        self.path_for_key(&self.key_for_path(path)).as_deref() == Some(path)
+    }
+
+    fn escape_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match name {
+            "" => return Cow::Borrowed(EMPTY_NAME),
+            "." => return Cow::Borrowed("%2E"),
+            ".." => return Cow::Borrowed("%2E%2E"),
+            _ => {}
+        }
+        let delimiter = self.delimiter.as_str();
+        let needs_escape = |c: char| matches!(c, '%' | '\0' | '/') || delimiter.starts_with(c);
+        if !name.contains(needs_escape) {
+            return Cow::Borrowed(name);
+        }
+        let mut escaped = String::with_capacity(name.len() + 2);
+        for c in name.chars() {
+            if needs_escape(c) {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(escaped, "%{byte:02X}").expect("writing to a string should succeed");
+                }
+            } else {
+                escaped.push(c);
+            }
+        }
+        Cow::Owned(escaped)
+    }
+}
+
+/// Decode the `%XX` sequences of an escaped name. Other `%` are kept as they are, and names which would not decode to
+/// valid UTF-8 are not decoded.
+fn unescape_name(name: &str) -> Cow<'_, str> {
+    if name == EMPTY_NAME {
+        return Cow::Borrowed("");
+    }
+    if !name.contains('%') {
+        return Cow::Borrowed(name);
+    }
+    let bytes = name.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped_byte = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped_byte {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 3;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    match String::from_utf8(unescaped) {
+        Ok(unescaped) => Cow::Owned(unescaped),
+        Err(_) => Cow::Borrowed(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("dir/file.txt", "dir/file.txt"; "plain")]
+    #[test_case("dir/", "dir/"; "directory")]
+    #[test_case("a//b", "a/%/b"; "empty component")]
+    #[test_case("/", "%/"; "empty directory at the root")]
+    #[test_case("dir/./..", "dir/%2E/%2E%2E"; "dot components")]
+    #[test_case("100%", "100%25"; "percent")]
+    #[test_case("nul\0byte", "nul%00byte"; "nul")]
+    #[test_case("...", "..."; "three dots")]
+    fn test_escape(key: &str, path: &str) {
+        let mapping = KeyMapping::new(KeyDelimiter::default(), true);
+        assert_eq!(mapping.path_for_key(key).as_deref(), Some(path));
+        assert_eq!(mapping.key_for_path(path), key);
+        for name in path.trim_end_matches('/').split('/') {
+            assert!(mapping.is_escaped_name(name), "{name:?} should be escaped");
+        }
+    }
+
+    #[test_case("dir|a/b", "dir/a%2Fb"; "path separator")]
+    #[test_case("dir|", "dir/"; "directory")]
+    #[test_case("a||b", "a/%/b"; "empty component")]
+    fn test_escape_delimiter(key: &str, path: &str) {
+        let mapping = KeyMapping::new(KeyDelimiter::new("|").unwrap(), true);
+        assert_eq!(mapping.path_for_key(key).as_deref(), Some(path));
+        assert_eq!(mapping.key_for_path(path), key);
+    }
+
+    #[test_case("100%"; "lone percent")]
+    #[test_case("%2e"; "lowercase")]
+    #[test_case("%41"; "unneeded escape")]
+    #[test_case("%FF"; "invalid UTF-8")]
+    #[test_case("a|b"; "delimiter")]
+    fn test_not_escaped(name: &str) {
+        let mapping = KeyMapping::new(KeyDelimiter::new("|").unwrap(), true);
+        assert!(!mapping.is_escaped_name(name));
+        assert!(KeyMapping::new(KeyDelimiter::new("|").unwrap(), false).is_escaped_name(name));
+    }
+
+    #[test_case("data/", true; "plain")]
+    #[test_case("100%25/", true; "escaped")]
+    #[test_case("%/", true; "empty component")]
+    #[test_case("100%/", false; "not escaped")]
+    fn test_escaped_path(path: &str, escaped: bool) {
+        let mapping = KeyMapping::new(KeyDelimiter::default(), true);
+        assert_eq!(mapping.is_escaped_path(path), escaped);
+    }
+
+    #[test]
+    fn test_no_escape() {
+        let mapping = KeyMapping::new(KeyDelimiter::new("|").unwrap(), false);
+        assert_eq!(mapping.path_for_key("a|b%"), Some("a/b%".into()));
+        assert_eq!(mapping.path_for_key("a/b"), None);
+        assert_eq!(mapping.key_for_path("a/b%"), "a|b%");
+    }
+}
@@ -21,45 +21,70 @@
 //! Some cached state is dependent on the inode kind; that state is hidden behind a [InodeStatKind]
 //! enum.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::default::Default;
 use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::sync::OnceLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 
 use async_trait::async_trait;
-use futures::{FutureExt, select_biased};
+use futures::{FutureExt, TryStreamExt, select_biased};
 use mountpoint_s3_client::ObjectClient;
-use mountpoint_s3_client::error::{HeadObjectError, ObjectClientError, RenameObjectError};
+use mountpoint_s3_client::error::{CopyObjectError, HeadObjectError, ObjectClientError, RenameObjectError};
 use mountpoint_s3_client::types::{
-    ETag, HeadObjectParams, HeadObjectResult, RenameObjectParams, RenamePreconditionTypes,
+    CopyObjectParams, ETag, GetObjectParams, HeadObjectParams, HeadObjectResult, ObjectVersionInfo, RenameObjectParams,
+    RenamePreconditionTypes,
 };
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
-use crate::fs::{CacheConfig, FUSE_ROOT_INODE, OpenFlags};
+use crate::fs::{
+    ATIME_METADATA_KEY, ArchivedObjectsPolicy, CacheConfig, Compression, FUSE_ROOT_INODE, KeyFilter, MODE_METADATA_KEY,
+    MTIME_METADATA_KEY, OpenFlags, PrefixOverrides, S3FilesystemConfig, SHADOWED_FILE_SUFFIX,
+    SYMLINK_TARGET_METADATA_KEY, ShadowingPolicy, compressed_file_key, is_gzip_encoding, parse_mode, parse_mtime,
+    shadowed_file_key, versions_dir_object,
+};
 use crate::logging;
 use crate::metablock::{
-    AddDirEntry, AddDirEntryResult, InodeError, InodeInformation, InodeKind, InodeNo, InodeStat, Lookup, Metablock,
-    NewHandle, PendingUploadHook, ReadWriteMode, S3Location, ValidKey, ValidName, WriteMode,
+    AddDirEntry, AddDirEntryResult, InodeError, InodeInformation, InodeKind, InodeNo, InodeStat, Invalidations, Lookup,
+    Metablock, NewHandle, PendingUploadHook, ReadWriteMode, S3Location, ValidKey, ValidName, WriteMode,
 };
-use crate::s3::{S3Path, S3Personality};
+use crate::s3::{KeyDelimiter, KeyMapping, S3Path, S3Personality};
 use crate::sync::{Arc, RwLock};
 
+mod delete_batcher;
+use delete_batcher::DeleteBatcher;
+
 mod handles_map;
 use handles_map::{InodeHandleMap, SetWriterError};
 
 mod inode;
 pub use inode::{Inode, InodeKindData, InodeLockedForWriting, InodeState, WriteStatus};
 
+mod inode_table;
+pub use inode_table::InodeTable;
+
+mod inventory;
+pub use inventory::InventoryConfig;
+use inventory::{Inventory, InventoryIndex, InventoryLookup, InventoryObject};
+
 mod negative_cache;
 use negative_cache::NegativeCache;
 mod readdir;
-pub use readdir::ReaddirHandle;
 use readdir::{DirHandle, DirectoryEntryReaddir};
+mod recent_writes;
+pub use readdir::{ReaddirHandle, ReaddirReadahead};
+use recent_writes::RecentWrites;
+
+mod snapshot;
+pub use snapshot::{Snapshot, SnapshotError};
+
+mod trash;
+use trash::Trash;
+pub use trash::TrashConfig;
 
 /// Superblock is the root object of the file system
 #[derive(Debug)]
@@ -118,13 +143,156 @@ struct SuperblockInner<OC: ObjectClient + Send + Sync> {
     client: OC,
     dir_handles: RwLock<HashMap<u64, Arc<DirHandle>>>,
     next_dir_handle_id: AtomicU64,
+    inventory: Option<Inventory>,
+    /// Where to send the entries of the kernel caches made stale when revalidating inodes, see
+    /// [Metablock::send_stale_entries].
+    stale_entries: OnceLock<async_channel::Sender<Invalidations>>,
+    /// Objects written by this mount, shown until ListObjectsV2 and HeadObject return them.
+    recent_writes: RecentWrites,
+    /// Batches the deletes of unlinked files, see [SuperblockConfig::batch_deletes].
+    delete_batcher: DeleteBatcher,
+    /// Where the objects of unlinked files are copied before they are deleted, see [SuperblockConfig::trash].
+    trash: Option<Trash>,
+}
+
+/// Renaming directories by renaming each object under their prefix, see [SuperblockConfig::directory_rename].
+#[derive(Debug, Clone, Copy)]
+pub struct DirectoryRenameConfig {
+    /// Directories holding more objects than this are not renamed, and fail with `EXDEV` as renames across file
+    /// systems do, so that tools such as `mv` fall back to copying them.
+    pub max_objects: usize,
+    /// How many objects are renamed, copied or deleted at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for DirectoryRenameConfig {
+    fn default() -> Self {
+        Self {
+            max_objects: 1000,
+            concurrency: 16,
+        }
+    }
+}
+
+/// Removing non-empty directories by deleting every object under their prefix, see
+/// [SuperblockConfig::recursive_delete].
+#[derive(Debug, Clone, Copy)]
+pub struct RecursiveDeleteConfig {
+    /// Directories holding more objects than this are not removed, and fail with `ENOTEMPTY`.
+    pub max_objects: usize,
+}
+
+impl Default for RecursiveDeleteConfig {
+    fn default() -> Self {
+        Self { max_objects: 10000 }
+    }
 }
 
-/// Configuration for superblock operations
+/// The most keys a single DeleteObjects request can delete.
+const MAX_DELETE_OBJECTS_KEYS: usize = 1000;
+
+/// Configuration for superblock operations. The options shared with [S3FilesystemConfig] are set from it by
+/// [crate::S3Filesystem::new].
 #[derive(Debug, Clone, Default)]
 pub struct SuperblockConfig {
+    /// Kernel cache config, see [S3FilesystemConfig::cache_config]
     pub cache_config: CacheConfig,
+    /// S3 personality, see [S3FilesystemConfig::s3_personality]
     pub s3_personality: S3Personality,
+    /// Table keeping inode numbers across mounts
+    pub inode_table: Option<InodeTable>,
+    /// Fetch the pages of directory listings ahead of readdir
+    pub readdir_readahead: Option<ReaddirReadahead>,
+    /// List directories from the latest S3 Inventory report of the bucket
+    pub inventory: Option<InventoryConfig>,
+    /// List directories and read files as they were at a point in time
+    pub snapshot: Option<Snapshot>,
+    /// Report symlinks, see [S3FilesystemConfig::symlinks]
+    pub symlinks: bool,
+    /// Report the decoded size of gzip-encoded objects, see [S3FilesystemConfig::decode_content_encoding]
+    pub decode_content_encoding: bool,
+    /// List compressed objects as read-only files of their decompressed content, see [Compression]
+    pub decompress_files: bool,
+    /// Report modification times from metadata, see [S3FilesystemConfig::mtime_metadata]
+    pub mtime_metadata: bool,
+    /// Report permissions from metadata, see [S3FilesystemConfig::mode_metadata]
+    pub mode_metadata: bool,
+    /// Report access times from metadata, see [S3FilesystemConfig::atime_flush_interval]
+    pub atime_metadata: bool,
+    /// Serve version directories, see [S3FilesystemConfig::version_directories]
+    pub version_directories: bool,
+    /// Delimiter of the components of keys, see [S3FilesystemConfig::key_delimiter]
+    pub key_delimiter: KeyDelimiter,
+    /// Remove the marker objects of directories, see [S3FilesystemConfig::directory_markers]
+    pub directory_markers: bool,
+    /// Escape the names of keys, see [S3FilesystemConfig::escape_names]
+    pub escape_names: bool,
+    /// Policy for files and directories with the same name
+    pub shadowing: ShadowingPolicy,
+    /// Policy for archived objects, see [S3FilesystemConfig::archived_objects]
+    pub archived_objects: ArchivedObjectsPolicy,
+    /// Fall back to case-insensitive lookups
+    pub case_insensitive: bool,
+    /// Which keys are shown
+    pub key_filter: KeyFilter,
+    /// How long objects written by this mount stay visible for, disabled if zero
+    pub recent_writes_window: Duration,
+    /// Rename files by copying and deleting their object where RenameObject is not supported
+    pub rename_by_copy: bool,
+    /// Rename directories by renaming every object under them
+    pub directory_rename: Option<DirectoryRenameConfig>,
+    /// Remove directories that are not empty by deleting every object under them
+    pub recursive_delete: Option<RecursiveDeleteConfig>,
+    /// Batch the deletes of unlinked files into DeleteObjects requests
+    pub batch_deletes: bool,
+    /// Copy unlinked objects to a trash prefix before deleting them
+    pub trash: Option<TrashConfig>,
+    /// Options overridden under prefixes, see [S3FilesystemConfig::prefix_overrides]
+    pub prefix_overrides: PrefixOverrides,
+}
+
+impl SuperblockConfig {
+    /// Take the options shared with the configuration of the file system from it.
+    pub(crate) fn with_filesystem_config(self, config: &S3FilesystemConfig) -> Self {
+        Self {
+            cache_config: config.cache_config.clone(),
+            s3_personality: config.s3_personality,
+            symlinks: config.symlinks,
+            decode_content_encoding: config.decode_content_encoding,
+            mtime_metadata: config.mtime_metadata,
+            mode_metadata: config.mode_metadata,
+            atime_metadata: config.atime_flush_interval.is_some(),
+            version_directories: config.version_directories,
+            key_delimiter: config.key_delimiter,
+            directory_markers: config.directory_markers,
+            escape_names: config.escape_names,
+            archived_objects: config.archived_objects,
+            prefix_overrides: config.prefix_overrides.clone(),
+            ..self
+        }
+    }
+
+    /// Translation between the paths of inodes and the keys of their objects.
+    pub(crate) fn key_mapping(&self) -> KeyMapping {
+        KeyMapping::new(self.key_delimiter, self.escape_names)
+    }
+}
+
+/// The parameters of the copy of an object renamed by copying it, keeping its storage class.
+fn copy_object_params(storage_class: Option<&str>) -> CopyObjectParams {
+    let mut params = CopyObjectParams::new();
+    if let Some(storage_class) = storage_class.filter(|storage_class| *storage_class != "STANDARD") {
+        params = params.storage_class(storage_class.to_owned());
+    }
+    params
+}
+
+/// The key of the child of the given kind and name, relative to the mount prefix and ending with `/` for directories.
+fn child_key(parent: &Inode, name: &str, kind: InodeKind) -> String {
+    match kind {
+        InodeKind::File => format!("{}{}", parent.key(), name),
+        InodeKind::Directory => format!("{}{}/", parent.key(), name),
+    }
 }
 
 /// A manager for automatically setting and removing the `PendingRename` write status on an inode.
@@ -220,7 +388,7 @@ impl<'a> RenameLockGuard<'a> {
     }
 }
 
-impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
+impl<OC: ObjectClient + Send + Sync + Clone + 'static> Superblock<OC> {
     /// Create a new Superblock that targets the given bucket/prefix
     pub fn new(client: OC, s3_path: S3Path, config: SuperblockConfig) -> Self {
         let mount_time = OffsetDateTime::now_utc();
@@ -234,18 +402,35 @@ impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
             config.cache_config.negative_cache_ttl,
         );
 
+        let inventory = config
+            .inventory
+            .as_ref()
+            .map(|inventory| Inventory::start(client.clone(), s3_path.clone(), inventory));
+
+        let recent_writes = RecentWrites::new(config.recent_writes_window);
+
+        let trash = config
+            .trash
+            .as_ref()
+            .map(|trash| Trash::start(client.clone(), &s3_path.bucket, trash));
+
         let inner = SuperblockInner {
             s3_path: Arc::new(s3_path),
             inodes: RwLock::new(inodes),
             open_handles: Default::default(),
             negative_cache,
-            next_ino: AtomicU64::new(2),
+            next_ino: AtomicU64::new(config.inode_table.as_ref().map_or(2, InodeTable::next_ino)),
             mount_time,
             config,
             cached_rename_support: RenameCache::new(),
             client,
             next_dir_handle_id: AtomicU64::new(1),
             dir_handles: Default::default(),
+            inventory,
+            stale_entries: OnceLock::new(),
+            recent_writes,
+            delete_batcher: Default::default(),
+            trash,
         };
         Self { inner: Arc::new(inner) }
     }
@@ -285,10 +470,7 @@ impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
             if has_local_state || !force_revalidate_if_remote {
                 // If the inode is local (open/unopened) and/or has a pending upload, extend its stat's validity before returning
                 if has_local_state {
-                    let validity = match inode.kind() {
-                        InodeKind::File => self.inner.config.cache_config.file_ttl,
-                        InodeKind::Directory => self.inner.config.cache_config.dir_ttl,
-                    };
+                    let validity = self.inner.config.cache_config.ttl_for(inode.kind(), inode.key());
                     sync.stat.update_validity(validity);
                 }
                 if sync.stat.is_valid() {
@@ -320,7 +502,10 @@ impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
         }
     }
 
-    async fn new_readdir_handle_with_pagesize(&self, dir_ino: InodeNo, page_size: usize) -> Result<u64, InodeError> {
+    async fn new_readdir_handle_with_pagesize(&self, dir_ino: InodeNo, page_size: usize) -> Result<u64, InodeError>
+    where
+        OC: Clone + 'static,
+    {
         trace!(dir=?dir_ino, "readdir");
 
         let dir = self.inner.get(dir_ino)?;
@@ -332,7 +517,8 @@ impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
 
         let dir_key = self.inner.full_key_for_inode(&dir);
         assert_eq!(dir_key.kind(), InodeKind::Directory);
-        let handle = ReaddirHandle::new(&self.inner, dir_ino, parent_ino, dir_key.into(), page_size)?;
+        let index = self.inner.listing_index(dir_key.as_ref()).await?;
+        let handle = ReaddirHandle::new(&self.inner, dir_ino, parent_ino, dir_key.into(), page_size, index)?;
         let handle_id = self.inner.next_dir_handle_id.fetch_add(1, Ordering::SeqCst);
         let dirhandle = DirHandle::new(handle.parent(), handle);
         self.inner
@@ -411,13 +597,13 @@ impl<OC: ObjectClient + Send + Sync> Superblock<OC> {
 }
 
 #[async_trait]
-impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
+impl<OC: ObjectClient + Send + Sync + Clone + 'static> Metablock for Superblock<OC> {
     /// Lookups inode and increments its lookup count.
     async fn lookup(&self, parent_ino: InodeNo, name: &OsStr) -> Result<Lookup, InodeError> {
         trace!(parent=?parent_ino, ?name, "lookup");
         let lookup = self
             .inner
-            .lookup_by_name(parent_ino, name, self.inner.config.cache_config.serve_lookup_from_cache)
+            .lookup_by_name(parent_ino, name, self.inner.serve_lookup_from_cache(parent_ino, name))
             .await?;
         self.inner.remember(&lookup.inode);
         Ok(lookup.into())
@@ -441,24 +627,45 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         dst_name: &OsStr,
         allow_overwrite: bool,
     ) -> Result<(), InodeError> {
-        // If we have cached a failed rename, we will directly fail
-        if !self.inner.cached_rename_support.should_try_rename() {
+        // If we have cached a failed rename, we will directly fail, unless renames can copy the object instead.
+        let try_rename_object = self.inner.cached_rename_support.should_try_rename();
+        if !try_rename_object && !self.inner.config.rename_by_copy {
             trace!("Cached rename failure, returning NotSupported");
             return Err(InodeError::RenameNotSupported());
         }
         let src_parent = self.inner.get(src_parent_ino)?;
         let dst_parent = self.inner.get(dst_parent_ino)?;
+        for parent in [&src_parent, &dst_parent] {
+            if self.inner.is_versions_dir(parent) {
+                return Err(InodeError::InodeNotWritable(parent.err()));
+            }
+        }
         let src_inode = self
             .inner
             .lookup_by_name(
                 src_parent_ino,
                 src_name,
-                self.inner.config.cache_config.serve_lookup_from_cache,
+                self.inner.serve_lookup_from_cache(src_parent_ino, src_name),
             )
             .await?
             .inode;
+        self.inner.check_writable(src_inode.key())?;
+        let dst_key = child_key(&dst_parent, &dst_name.to_string_lossy(), src_inode.kind());
+        self.inner.check_writable(&dst_key)?;
         if src_inode.kind() == InodeKind::Directory {
-            return Err(InodeError::CannotRenameDirectory(src_inode.err()));
+            let Some(config) = self.inner.config.directory_rename else {
+                return Err(InodeError::CannotRenameDirectory(src_inode.err()));
+            };
+            return self
+                .inner
+                .rename_directory(&src_parent, &src_inode, &dst_parent, dst_name, allow_overwrite, config)
+                .await;
+        }
+        if src_inode
+            .get_inode_state()
+            .map(|state| state.stat.shadowed || state.stat.decompressed.is_some())?
+        {
+            return Err(InodeError::InodeNotWritable(src_inode.err()));
         }
         // Check write status from source and set to PendingRename
         let mut src_status_guard = PendingRenameGuard::try_transition(&src_inode)?;
@@ -468,7 +675,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             .lookup_by_name(
                 dst_parent_ino,
                 dst_name,
-                self.inner.config.cache_config.serve_lookup_from_cache,
+                self.inner.serve_lookup_from_cache(dst_parent_ino, dst_name),
             )
             .await
             .ok()
@@ -489,7 +696,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             .transpose()?;
 
         let src_key = self.inner.full_key_for_inode(&src_inode);
-        let dest_name = ValidName::parse_os_str(dst_name)?;
+        let dest_name = self.inner.parse_name(dst_name)?;
         let dest_full_valid_name = dst_parent
             .valid_key()
             .new_child(dest_name, InodeKind::File)
@@ -503,15 +710,41 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             RenameObjectParams::new().if_none_match(Some("*".to_string()))
         };
 
-        let rename_object_result = self
-            .inner
-            .client
-            .rename_object(&self.inner.s3_path.bucket, src_key.as_ref(), &dest_key, &rename_params)
-            .await;
+        let key_mapping = self.inner.config.key_mapping();
+        let src_s3_key = key_mapping.key_for_path(src_key.as_ref());
+        let dest_s3_key = key_mapping.key_for_path(&dest_key);
+        let rename_object_result = if try_rename_object {
+            self.inner
+                .client
+                .rename_object(&self.inner.s3_path.bucket, &src_s3_key, &dest_s3_key, &rename_params)
+                .await
+        } else {
+            Err(ObjectClientError::ServiceError(RenameObjectError::NotImplementedError))
+        };
 
+        let mut copied = false;
         match rename_object_result {
             Ok(_res) => {
                 debug!(?src_key, ?dest_key, "RenameObject succeeded");
+                self.inner.cached_rename_support.cache_success();
+            }
+            Err(ObjectClientError::ServiceError(RenameObjectError::NotImplementedError))
+                if self.inner.config.rename_by_copy =>
+            {
+                if try_rename_object {
+                    self.inner.cached_rename_support.cache_failure();
+                }
+                let storage_class = src_inode.get_inode_state()?.stat.storage_class.clone();
+                self.inner
+                    .copy_and_delete(
+                        &src_inode,
+                        &src_s3_key,
+                        &dest_s3_key,
+                        storage_class.as_deref(),
+                        allow_overwrite,
+                    )
+                    .await?;
+                copied = true;
             }
             Err(error) => {
                 debug!(?src_key, ?dest_key, ?error, "RenameObject failed");
@@ -545,7 +778,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             }
         };
 
-        self.inner.cached_rename_support.cache_success();
+        self.inner.recent_writes.remove(src_inode.key());
         // Invalidate destination from negative cache, as it is now in S3
         self.inner.negative_cache.remove(
             dst_parent.ino(),
@@ -582,10 +815,19 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
                 }
                 InodeKindData::Directory { children, .. } => {
                     let dst_name_as_str: Box<str> = dest_name.as_ref().into();
+                    // Copies may have a different ETag than their source, so they are looked up again.
+                    let validity = if copied {
+                        Duration::ZERO
+                    } else {
+                        self.inner
+                            .config
+                            .cache_config
+                            .ttl_for(InodeKind::File, &dest_full_valid_name)
+                    };
                     let new_inode = src_inode.try_clone_with_new_key(
                         dest_full_valid_name,
                         &self.inner.s3_path.prefix,
-                        self.inner.config.cache_config.file_ttl,
+                        validity,
                         dst_parent_ino,
                     )?;
 
@@ -608,6 +850,9 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
                         );
                     }
 
+                    if let Some(inode_table) = &self.inner.config.inode_table {
+                        inode_table.rename(src_inode.key(), new_inode.key(), new_inode.ino());
+                    }
                     children.insert(dst_name_as_str, new_inode.clone());
                     let mut inodes_write = self.inner.inodes.write().unwrap();
                     inodes_write.replace_or_insert(new_inode.ino(), &new_inode);
@@ -623,27 +868,40 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
     }
 
     /// Remove local-only empty directory, i.e., the ones created by mkdir.
-    /// It does not affect empty directories represented remotely with directory markers.
+    /// It does not affect empty directories represented remotely with directory markers, unless
+    /// [SuperblockConfig::directory_markers] or [SuperblockConfig::recursive_delete] is set.
     async fn rmdir(&self, parent_ino: InodeNo, name: &OsStr) -> Result<(), InodeError> {
         let LookedUpInode { inode, .. } = self
             .inner
-            .lookup_by_name(parent_ino, name, self.inner.config.cache_config.serve_lookup_from_cache)
+            .lookup_by_name(parent_ino, name, self.inner.serve_lookup_from_cache(parent_ino, name))
             .await?;
 
         if inode.kind() == InodeKind::File {
             return Err(InodeError::NotADirectory(inode.err()));
         }
+        self.inner.check_writable(inode.key())?;
+
+        let directory_markers = self.inner.config.directory_markers;
+        let recursive_delete = self.inner.config.recursive_delete;
+        if inode.get_inode_state()?.write_status == WriteStatus::Remote {
+            if let Some(config) = recursive_delete {
+                self.inner.delete_directory(&inode, config).await?;
+            } else if directory_markers {
+                self.inner.delete_directory_marker(&inode).await?;
+            }
+        }
 
         let parent = self.inner.get(parent_ino)?;
         let mut parent_state = parent.get_mut_inode_state()?;
         let mut inode_state = inode.get_mut_inode_state()?;
+        let is_remote = inode_state.write_status == WriteStatus::Remote;
 
         match &inode_state.write_status {
             WriteStatus::LocalOpenForWriting => unreachable!("A directory cannot be in LocalOpenForWriting state"),
-            WriteStatus::Remote => {
+            WriteStatus::Remote if !directory_markers && recursive_delete.is_none() => {
                 return Err(InodeError::CannotRemoveRemoteDirectory(inode.err()));
             }
-            WriteStatus::LocalUnopened => match &mut inode_state.kind_data {
+            WriteStatus::LocalUnopened | WriteStatus::Remote => match &mut inode_state.kind_data {
                 InodeKindData::File {} => unreachable!("Already checked that inode is a directory"),
                 InodeKindData::Directory {
                     writing_children,
@@ -671,7 +929,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             } => {
                 let removed = writing_children.remove(&inode.ino());
                 debug_assert!(
-                    removed,
+                    removed || is_remote,
                     "should be able to remove the directory from its parents writing children as it was local"
                 );
                 children.remove(inode.name());
@@ -686,18 +944,27 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
     /// If the entry exists, delete it from S3 and the superblock.
     async fn unlink(&self, parent_ino: InodeNo, name: &OsStr) -> Result<(), InodeError> {
         let parent = self.inner.get(parent_ino)?;
+        if self.inner.is_versions_dir(&parent) {
+            return Err(InodeError::InodeNotWritable(parent.err()));
+        }
         let LookedUpInode { inode, .. } = self
             .inner
-            .lookup_by_name(parent_ino, name, self.inner.config.cache_config.serve_lookup_from_cache)
+            .lookup_by_name(parent_ino, name, self.inner.serve_lookup_from_cache(parent_ino, name))
             .await?;
 
         if inode.kind() == InodeKind::Directory {
             return Err(InodeError::IsDirectory(inode.err()));
         }
+        self.inner.check_writable(inode.key())?;
 
-        let write_status = {
+        let (write_status, shadowed, decompressed, storage_class) = {
             let inode_state = inode.get_inode_state()?;
-            inode_state.write_status
+            (
+                inode_state.write_status,
+                inode_state.stat.shadowed,
+                inode_state.stat.decompressed,
+                inode_state.stat.storage_class.clone(),
+            )
         };
 
         match write_status {
@@ -712,19 +979,43 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             }
             WriteStatus::Remote => {
                 let bucket = &self.inner.s3_path.bucket;
-                let s3_key = self.inner.full_key_for_inode(&inode);
+                let full_key = self.inner.full_key_for_inode(&inode);
+                let full_key = match shadowed_file_key(full_key.as_ref()) {
+                    Some(file_key) if shadowed => file_key.into(),
+                    _ => compressed_file_key(full_key.as_ref(), decompressed),
+                };
+                let s3_key = self.inner.config.key_mapping().key_for_path(&full_key);
                 debug!(parent=?parent_ino, ?name, "unlink on remote file will delete key {}", s3_key);
-                let delete_obj_result = self.inner.client.delete_object(bucket, &s3_key).await;
+                if let Some(trash_key) =
+                    (self.inner.trash.as_ref()).and_then(|trash| trash.key(&s3_key, OffsetDateTime::now_utc()))
+                {
+                    self.inner
+                        .copy_to_trash(&inode, &s3_key, &trash_key, storage_class.as_deref())
+                        .await?;
+                }
+                let delete_obj_result = if self.inner.config.batch_deletes {
+                    self.inner
+                        .delete_batcher
+                        .delete(&self.inner.client, bucket, &s3_key)
+                        .await
+                } else {
+                    self.inner
+                        .client
+                        .delete_object(bucket, &s3_key)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| InodeError::client_error(e, "DeleteObject failed", bucket, &s3_key))
+                };
 
                 match delete_obj_result {
-                    Ok(_res) => (),
+                    Ok(()) => self.inner.recent_writes.remove(inode.key()),
                     Err(e) => {
                         error!(
                             inode=%inode.err(),
                             error=?e,
                             "DeleteObject failed for unlink",
                         );
-                        Err(InodeError::client_error(e, "DeleteObject failed", bucket, &s3_key))?;
+                        Err(e)?;
                     }
                 };
             }
@@ -762,6 +1053,79 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         Ok(())
     }
 
+    async fn set_mode(&self, ino: InodeNo, mode: u16) -> Result<Lookup, InodeError> {
+        let inode = self.inner.get(ino)?;
+        logging::record_name(inode.name());
+        let mut sync = inode.get_mut_inode_state()?;
+        if sync.write_status == WriteStatus::Remote {
+            return Err(InodeError::SetAttrNotPermittedOnRemoteInode(inode.err()));
+        }
+        sync.stat.mode = Some(mode);
+
+        let stat = sync.stat.clone();
+        drop(sync);
+        Ok(Lookup::new(
+            inode.ino(),
+            stat,
+            inode.kind(),
+            Some(S3Location::new(self.inner.s3_path.clone(), inode.valid_key().clone())),
+        ))
+    }
+
+    async fn update_remote_file(
+        &self,
+        ino: InodeNo,
+        etag: ETag,
+        mtime: Option<OffsetDateTime>,
+        mode: Option<u16>,
+    ) -> Result<Lookup, InodeError> {
+        let inode = self.inner.get(ino)?;
+        let mut sync = inode.get_mut_inode_state()?;
+        if sync.write_status != WriteStatus::Remote {
+            return Err(InodeError::InodeInvalidWriteStatus(inode.err()));
+        }
+        sync.stat.etag = Some(etag.into_inner().into_boxed_str());
+        if let Some(mtime) = mtime {
+            sync.stat.mtime = mtime;
+        }
+        if let Some(mode) = mode {
+            sync.stat.mode = Some(mode);
+        }
+        let validity = self.inner.config.cache_config.ttl_for(InodeKind::File, inode.key());
+        sync.stat.update_validity(validity);
+
+        let stat = sync.stat.clone();
+        drop(sync);
+        Ok(Lookup::new(
+            inode.ino(),
+            stat,
+            inode.kind(),
+            Some(S3Location::new(self.inner.s3_path.clone(), inode.valid_key().clone())),
+        ))
+    }
+
+    async fn truncate_remote_file(&self, ino: InodeNo, etag: ETag, size: usize) -> Result<Lookup, InodeError> {
+        let inode = self.inner.get(ino)?;
+        let mut sync = inode.get_mut_inode_state()?;
+        if sync.write_status != WriteStatus::Remote {
+            return Err(InodeError::InodeInvalidWriteStatus(inode.err()));
+        }
+        sync.stat.etag = Some(etag.into_inner().into_boxed_str());
+        sync.stat.size = size;
+        sync.stat.mtime = OffsetDateTime::now_utc();
+        let validity = self.inner.config.cache_config.ttl_for(InodeKind::File, inode.key());
+        sync.stat.update_validity(validity);
+
+        let stat = sync.stat.clone();
+        drop(sync);
+        Ok(Lookup::new(
+            inode.ino(),
+            stat,
+            inode.kind(),
+            Some(S3Location::new(self.inner.s3_path.clone(), inode.valid_key().clone())),
+        ))
+    }
+
     async fn setattr(
         &self,
         ino: InodeNo,
@@ -776,10 +1140,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             return Err(InodeError::SetAttrNotPermittedOnRemoteInode(inode.err()));
         }
 
-        let validity = match inode.kind() {
-            InodeKind::File => self.inner.config.cache_config.file_ttl,
-            InodeKind::Directory => self.inner.config.cache_config.dir_ttl,
-        };
+        let validity = self.inner.config.cache_config.ttl_for(inode.kind(), inode.key());
 
         // Resetting the InodeStat expiry because the new InodeStat should have new validity
         sync.stat.update_validity(validity);
@@ -815,7 +1176,9 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         write_mode: &WriteMode,
         flags: OpenFlags,
     ) -> Result<NewHandle, InodeError> {
-        let force_revalidate_if_remote = !self.inner.config.cache_config.serve_lookup_from_cache || flags.direct_io();
+        let key = self.inner.get(ino)?.key().to_owned();
+        let force_revalidate_if_remote =
+            !self.inner.config.cache_config.serve_lookup_from_cache_for(&key) || flags.direct_io();
         let looked_up_inode = self.getattr_with_inode(ino, force_revalidate_if_remote).await?;
         match looked_up_inode.inode.kind() {
             InodeKind::Directory => return Err(InodeError::IsDirectory(looked_up_inode.inode.err())),
@@ -825,7 +1188,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         let mode = if flags.contains(OpenFlags::O_RDWR) {
             if looked_up_inode.write_status == WriteStatus::LocalUnopened
                 || (write_mode.allow_overwrite && flags.contains(OpenFlags::O_TRUNC))
-                || (write_mode.incremental_upload && flags.contains(OpenFlags::O_APPEND))
+                || ((write_mode.incremental_upload || write_mode.append_by_copy) && flags.contains(OpenFlags::O_APPEND))
             {
                 // If the file is new, or if it was opened in truncate or in append mode,
                 // we know it should be a write handle.
@@ -842,18 +1205,43 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             ReadWriteMode::Read
         };
 
+        // Files read at a pinned version or shown for a shadowed key or decompressed can not be overwritten.
+        if matches!(mode, ReadWriteMode::Write)
+            && (looked_up_inode.stat.pinned_version
+                || looked_up_inode.stat.shadowed
+                || looked_up_inode.stat.decompressed.is_some())
+        {
+            return Err(InodeError::InodeNotWritable(looked_up_inode.inode.err()));
+        }
+        if matches!(mode, ReadWriteMode::Write) {
+            self.inner.check_writable(looked_up_inode.inode.key())?;
+        }
+
         if matches!(mode, ReadWriteMode::Read) && !looked_up_inode.stat.is_readable {
-            return Err(InodeError::FlexibleRetrievalObjectNotAccessible(
-                looked_up_inode.inode.err(),
-            ));
+            let inode = looked_up_inode.inode.err();
+            if self.inner.config.archived_objects.fails_reads() {
+                return Err(InodeError::FlexibleRetrievalObjectNotRestored(inode));
+            }
+            return Err(InodeError::FlexibleRetrievalObjectNotAccessible(inode));
         }
 
         let inode = looked_up_inode.inode;
+        let mut writer = None;
         let (pending_upload_hook, inode_lookup) = {
             let mut locked_inode = inode.get_mut_inode_state()?;
 
             let pending_upload_hook = match mode {
-                ReadWriteMode::Read => self.start_reading(&mut locked_inode, inode.clone(), fh)?,
+                ReadWriteMode::Read => {
+                    // Readers of a file being written read the data staged by its writer, rather than waiting for it
+                    // to be uploaded.
+                    if write_mode.read_while_writing {
+                        writer = self.inner.open_handles.try_add_reader_of_writer(&locked_inode, fh);
+                    }
+                    match writer {
+                        Some(_) => None,
+                        None => self.start_reading(&mut locked_inode, inode.clone(), fh)?,
+                    }
+                }
                 ReadWriteMode::Write => {
                     let is_truncate = flags.contains(OpenFlags::O_TRUNC);
                     self.start_writing(&mut locked_inode, inode.clone(), write_mode, is_truncate, fh)?
@@ -887,7 +1275,7 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
             inode_lookup
         };
 
-        Ok(NewHandle { lookup, mode })
+        Ok(NewHandle { lookup, mode, writer })
     }
 
     async fn inc_file_size(&self, ino: InodeNo, len: usize) -> Result<usize, InodeError> {
@@ -901,6 +1289,17 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         Ok(state.stat.size)
     }
 
+    async fn set_file_size(&self, ino: InodeNo, size: usize) -> Result<(), InodeError> {
+        let inode = self.inner.get(ino)?;
+        let mut state = inode.get_mut_inode_state()?;
+        if !matches!(state.write_status, WriteStatus::LocalOpenForWriting) {
+            debug!(?inode, "Error trying to set file size on write");
+            return Err(InodeError::InodeInvalidWriteStatus(inode.err()));
+        }
+        state.stat.size = size;
+        Ok(())
+    }
+
     /// Concludes a writing operation for a file handle and marks the inode closed for writing anymore.
     ///
     /// Transitions the inode and all its ancestor directories from local writing state to remote
@@ -955,9 +1354,12 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
                         locked_inode.write_status = WriteStatus::Remote;
                     }
                     locked_inode.stat.etag = Some(etag.into_inner().into_boxed_str());
-                    locked_inode
-                        .stat
-                        .update_validity(self.inner.config.cache_config.file_ttl);
+                    // The storage class and version of the new object are not known until it is looked up again.
+                    locked_inode.stat.storage_class = None;
+                    locked_inode.stat.version_id = None;
+                    let validity = self.inner.config.cache_config.ttl_for(InodeKind::File, inode.key());
+                    locked_inode.stat.update_validity(validity);
+                    self.inner.recent_writes.insert(inode.key(), locked_inode.stat.clone());
                 } else {
                     // Upload failed
                     locked_inode.write_status = WriteStatus::Remote;
@@ -1087,13 +1489,9 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
         if offset == 0 && dir_handle.offset() != 0 {
             trace!("new handle");
             let dir = self.inner.get(parent)?;
-            let new_handle = ReaddirHandle::new(
-                &self.inner,
-                dir.ino(),
-                dir.parent(),
-                self.inner.full_key_for_inode(&dir).to_string(),
-                1000,
-            )?;
+            let dir_key = self.inner.full_key_for_inode(&dir);
+            let index = self.inner.listing_index(dir_key.as_ref()).await?;
+            let new_handle = ReaddirHandle::new(&self.inner, dir.ino(), dir.parent(), dir_key.into(), 1000, index)?;
             *dir_handle.handle.lock().await = new_handle;
             dir_handle.rewind_offset();
             // drop any cached entries, as new response may be unordered and cache would be stale
@@ -1240,9 +1638,14 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
     async fn create(&self, dir: InodeNo, name: &OsStr, kind: InodeKind) -> Result<Lookup, InodeError> {
         trace!(parent=?dir, ?name, "create");
 
+        let parent = self.inner.get(dir)?;
+        if self.inner.is_versions_dir(&parent) {
+            return Err(InodeError::InodeNotWritable(parent.err()));
+        }
+
         let existing = self
             .inner
-            .lookup_by_name(dir, name, self.inner.config.cache_config.serve_lookup_from_cache)
+            .lookup_by_name(dir, name, self.inner.serve_lookup_from_cache(dir, name))
             .await;
         match existing {
             Ok(lookup) => return Err(InodeError::FileAlreadyExists(lookup.inode.err())),
@@ -1252,6 +1655,10 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
 
         // Should be impossible to fail since [lookup] does this check, but let's be sure
         let name: ValidName = name.try_into()?;
+        let key = child_key(&parent, name.as_ref(), kind);
+        if self.inner.config.prefix_overrides.is_read_only(&key) {
+            return Err(InodeError::ReadOnlyPrefix(key));
+        }
 
         // Put inode creation in a block so we don't hold the lock on the parent state longer than needed.
         let (lookup, inode) = {
@@ -1268,19 +1675,11 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
                 return Err(InodeError::FileAlreadyExists(inode.err()));
             }
 
+            let validity = self.inner.child_ttl(&parent_inode, name.as_ref(), kind);
             let stat = match kind {
                 // Objects don't have an ETag until they are uploaded to S3
-                InodeKind::File => InodeStat::for_file(
-                    0,
-                    OffsetDateTime::now_utc(),
-                    None,
-                    None,
-                    None,
-                    self.inner.config.cache_config.file_ttl,
-                ),
-                InodeKind::Directory => {
-                    InodeStat::for_directory(self.inner.mount_time, self.inner.config.cache_config.dir_ttl)
-                }
+                InodeKind::File => InodeStat::for_file(0, OffsetDateTime::now_utc(), None, None, None, validity),
+                InodeKind::Directory => InodeStat::for_directory(self.inner.mount_time, validity),
             };
 
             let write_status = WriteStatus::LocalUnopened;
@@ -1334,6 +1733,10 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
 
                 drop(inodes);
 
+                if let Some(inode_table) = &self.inner.config.inode_table {
+                    inode_table.release(removed_inode.key(), ino);
+                }
+
                 if let Ok(state) = removed_inode.get_inode_state() {
                     metrics::counter!("metadata_cache.inode_forgotten_before_expiry")
                         .increment(state.stat.is_valid().into());
@@ -1366,6 +1769,20 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
     /// Tries to activate either a reader or writer handle based on the mode. If the handle is still
     /// open (not overridden by another open), it gets marked as "Active" in the open handles map.
     /// Returns true if the handle was successfully activated, false otherwise.
+    async fn invalidate(&self, key: &str) -> Invalidations {
+        self.inner.invalidate(key)
+    }
+
+    async fn invalidate_inode(&self, ino: InodeNo) -> Result<Invalidations, InodeError> {
+        self.inner.invalidate_inode(ino)
+    }
+
+    fn send_stale_entries(&self, sender: async_channel::Sender<Invalidations>) {
+        if self.inner.stale_entries.set(sender).is_err() {
+            warn!("stale kernel cache entries are already sent elsewhere");
+        }
+    }
+
     async fn try_reactivate_handle(&self, ino: InodeNo, fh: u64, mode: ReadWriteMode) -> Result<bool, InodeError> {
         let inode = self.inner.get(ino)?;
         let mut locked_inode = inode.get_mut_inode_state()?;
@@ -1388,6 +1805,225 @@ impl<OC: ObjectClient + Send + Sync + Clone> Metablock for Superblock<OC> {
 }
 
 impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
+    /// The index to serve directory listings and lookups from rather than S3, either the snapshot of the mount or the
+    /// latest inventory report, if one is loaded.
+    fn index(&self) -> Option<Arc<InventoryIndex>> {
+        match &self.config.snapshot {
+            Some(snapshot) => Some(snapshot.index.clone()),
+            None => self.inventory.as_ref().and_then(Inventory::index),
+        }
+    }
+
+    /// Index to list the directory with the given key from: an index of the versions of the object if it is a
+    /// versions directory, see [SuperblockConfig::version_directories], or else [Self::index].
+    async fn listing_index(&self, dir_key: &str) -> Result<Option<Arc<InventoryIndex>>, InodeError> {
+        let Some(object_key) = self.versions_dir_object(dir_key) else {
+            return Ok(self.index());
+        };
+        let objects = self
+            .list_versions(object_key)
+            .await?
+            .into_iter()
+            .map(|version| {
+                let key = format!("{dir_key}{}", version.version_id);
+                let object = InventoryObject {
+                    size: version.size,
+                    last_modified: version.last_modified,
+                    etag: version.etag,
+                    storage_class: version.storage_class,
+                    version_id: Some(version.version_id),
+                };
+                (key, object)
+            })
+            .collect();
+        Ok(Some(Arc::new(InventoryIndex::new(dir_key.to_owned(), objects))))
+    }
+
+    /// Delete the marker object of a remote directory, the empty object with its key, if the directory is empty, see
+    /// [SuperblockConfig::directory_markers].
+    async fn delete_directory_marker(&self, inode: &Inode) -> Result<(), InodeError> {
+        if let InodeKindData::Directory { writing_children, .. } = &inode.get_inode_state()?.kind_data
+            && !writing_children.is_empty()
+        {
+            return Err(InodeError::DirectoryNotEmpty(inode.err()));
+        }
+
+        let full_key = self.full_key_for_inode(inode);
+        let key_mapping = self.config.key_mapping();
+        let delimiter = key_mapping.delimiter();
+        let prefix = key_mapping.key_for_path(full_key.as_ref());
+        let bucket = &self.s3_path.bucket;
+        let result = self
+            .client
+            .list_objects(bucket, None, delimiter.as_str(), 2, &prefix)
+            .await
+            .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &prefix))?;
+        let has_marker = result.objects.iter().any(|object| object.key == *prefix);
+        if !result.common_prefixes.is_empty() || result.objects.len() > usize::from(has_marker) {
+            return Err(InodeError::DirectoryNotEmpty(inode.err()));
+        }
+        if has_marker {
+            debug!(key=?prefix, "rmdir will delete the directory marker");
+            self.client
+                .delete_object(bucket, &prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "DeleteObject failed", bucket, &prefix))?;
+        }
+        Ok(())
+    }
+
+    /// Delete every object under the prefix of a remote directory with DeleteObjects, see
+    /// [SuperblockConfig::recursive_delete].
+    async fn delete_directory(&self, inode: &Inode, config: RecursiveDeleteConfig) -> Result<(), InodeError> {
+        if self.is_versions_dir(inode) {
+            return Err(InodeError::InodeNotWritable(inode.err()));
+        }
+        // Files being written have no object to delete yet, and would be uploaded into the removed directory.
+        let mut descendants = vec![inode.clone()];
+        while let Some(descendant) = descendants.pop() {
+            let state = descendant.get_inode_state()?;
+            if let InodeKindData::Directory {
+                children,
+                writing_children,
+                ..
+            } = &state.kind_data
+            {
+                if !writing_children.is_empty() {
+                    return Err(InodeError::DirectoryNotEmpty(inode.err()));
+                }
+                descendants.extend(
+                    children
+                        .values()
+                        .filter(|child| child.kind() == InodeKind::Directory)
+                        .cloned(),
+                );
+            }
+        }
+
+        let prefix = self
+            .config
+            .key_mapping()
+            .key_for_path(self.full_key_for_inode(inode).as_ref())
+            .into_owned();
+        let bucket = &self.s3_path.bucket;
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let result = self
+                .client
+                .list_objects(bucket, continuation_token.as_deref(), "", 1000, &prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &prefix))?;
+            keys.extend(result.objects.into_iter().map(|object| object.key));
+            if keys.len() > config.max_objects {
+                return Err(InodeError::DirectoryTooLargeToDelete {
+                    max_objects: config.max_objects,
+                    inode: inode.err(),
+                });
+            }
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        debug!(?prefix, objects = keys.len(), "deleting directory");
+        let mut failed = 0;
+        for batch in keys.chunks(MAX_DELETE_OBJECTS_KEYS) {
+            let result = self
+                .client
+                .delete_objects(bucket, batch)
+                .await
+                .map_err(|e| InodeError::client_error(e, "DeleteObjects failed", bucket, &prefix))?;
+            // The keys are logged as they are deleted, so that the removal of a directory can be audited.
+            for key in batch {
+                match result.errors.iter().find(|error| error.key == *key) {
+                    Some(error) => {
+                        warn!(?key, code = ?error.code, message = ?error.message, "rmdir could not delete object")
+                    }
+                    None => info!(?key, "rmdir deleted object"),
+                }
+            }
+            failed += result.errors.len();
+            metrics::counter!("fs.recursive_deletes.objects").increment((batch.len() - result.errors.len()) as u64);
+        }
+        self.recent_writes.remove_prefix(inode.key());
+        if failed > 0 {
+            warn!(
+                ?prefix,
+                failed,
+                total = keys.len(),
+                "directory removal failed, the objects not deleted are still under its prefix"
+            );
+            return Err(InodeError::DirectoryDeleteIncomplete {
+                failed,
+                inode: inode.err(),
+            });
+        }
+        metrics::counter!("fs.recursive_deletes").increment(1);
+        info!(?prefix, objects = keys.len(), "rmdir deleted directory");
+        Ok(())
+    }
+
+    /// Key of the object whose versions the directory with the given key holds, if it is a versions directory, see
+    /// [SuperblockConfig::version_directories].
+    fn versions_dir_object<'a>(&self, dir_key: &'a str) -> Option<&'a str> {
+        versions_dir_object(dir_key).filter(|_| self.config.version_directories)
+    }
+
+    /// Whether the given inode is a versions directory, whose entries can not be created or removed.
+    /// Fail with [InodeError::ReadOnlyPrefix] if the file or directory with the given key, relative to the mounted
+    /// prefix, is under a read-only prefix, or for directories if a read-only prefix is under it, see
+    /// [SuperblockConfig::prefix_overrides].
+    fn check_writable(&self, key: &str) -> Result<(), InodeError> {
+        if self.config.prefix_overrides.is_read_only_under(key) {
+            return Err(InodeError::ReadOnlyPrefix(key.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn is_versions_dir(&self, inode: &Inode) -> bool {
+        inode.kind() == InodeKind::Directory
+            && self
+                .versions_dir_object(self.full_key_for_inode(inode).as_ref())
+                .is_some()
+    }
+
+    /// List the versions of the object with the given key, leaving out its delete markers.
+    async fn list_versions(&self, object_key: &str) -> Result<Vec<ObjectVersionInfo>, InodeError> {
+        let object_key = self.config.key_mapping().key_for_path(object_key);
+        let object_key = object_key.as_ref();
+        let mut versions = Vec::new();
+        let (mut key_marker, mut version_id_marker) = (None, None);
+        loop {
+            let result = self
+                .client
+                .list_object_versions(
+                    &self.s3_path.bucket,
+                    key_marker.as_deref(),
+                    version_id_marker.as_deref(),
+                    "",
+                    1000,
+                    object_key,
+                )
+                .await
+                .map_err(|e| {
+                    InodeError::client_error(e, "ListObjectVersions failed", &self.s3_path.bucket, object_key)
+                })?;
+            // The listing is ordered by key, and the key of the object comes first as the others start with it, so
+            // there is no need to go on once another key is listed.
+            let other_key_listed = result.versions.iter().any(|version| version.key != object_key)
+                || result.delete_markers.iter().any(|marker| marker.key != object_key);
+            versions.extend(result.versions.into_iter().filter(|version| version.key == object_key));
+            if other_key_listed || result.next_key_marker.is_none() {
+                break;
+            }
+            key_marker = result.next_key_marker;
+            version_id_marker = result.next_version_id_marker;
+        }
+        Ok(versions)
+    }
+
     /// Retrieve the inode for the given number if it exists.
     ///
     /// The expiry of its stat field is not checked.
@@ -1404,10 +2040,46 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
         Ok(inode)
     }
 
+    /// Parse the name of a file given to the file system, which must be escaped when
+    /// [SuperblockConfig::escape_names] is enabled.
+    fn parse_name<'a>(&self, name: &'a OsStr) -> Result<ValidName<'a>, InodeError> {
+        let name = ValidName::parse_os_str(name)?;
+        if !self.config.key_mapping().is_escaped_name(&name) {
+            return Err(InodeError::InvalidFileName(name.as_ref().into()));
+        }
+        Ok(name)
+    }
+
     fn full_key_for_inode(&self, inode: &Inode) -> ValidKey {
         inode.valid_key().full_key(&self.s3_path.prefix)
     }
 
+    /// Whether the lookup of `name` in the given directory may be served from cached entries, see
+    /// [CacheConfig::ttl_rules].
+    fn serve_lookup_from_cache(&self, parent_ino: InodeNo, name: &OsStr) -> bool {
+        let cache_config = &self.config.cache_config;
+        if cache_config.ttl_rules.is_empty() {
+            return cache_config.serve_lookup_from_cache;
+        }
+        match self.get(parent_ino) {
+            Ok(parent) => {
+                let key = format!("{}{}", parent.key(), name.to_string_lossy());
+                cache_config.serve_lookup_from_cache_for(&key)
+            }
+            Err(_) => cache_config.serve_lookup_from_cache,
+        }
+    }
+
+    /// How long the metadata of the child `name` of the given directory is cached, see [CacheConfig::ttl_rules].
+    fn child_ttl(&self, parent: &Inode, name: &str, kind: InodeKind) -> Duration {
+        self.config.cache_config.ttl_for(kind, &child_key(parent, name, kind))
+    }
+
+    /// Whether the child of the given kind and name is shown by the [SuperblockConfig::key_filter].
+    fn is_visible(&self, parent: &Inode, name: &str, kind: InodeKind) -> bool {
+        self.config.key_filter.is_empty() || self.config.key_filter.is_visible(&child_key(parent, name, kind))
+    }
+
     /// Increase the lookup count of the given inode and
     /// ensure it is registered with this superblock.
     ///
@@ -1428,10 +2100,13 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
         name: &OsStr,
         allow_cache: bool,
     ) -> Result<LookedUpInode, InodeError> {
-        let name: ValidName = name.try_into()?;
+        let name = self.parse_name(name)?;
 
         let lookup = if allow_cache {
             self.cache_lookup(parent_ino, &name)
+        } else if self.config.cache_config.use_negative_cache {
+            // Negative entries have their own TTL, so they may be served even when positive ones are revalidated.
+            self.negative_cache_lookup(parent_ino, &name)
         } else {
             None
         };
@@ -1440,6 +2115,12 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
             Some(lookup) => lookup?,
             None => {
                 let remote = self.remote_lookup(parent_ino, name).await?;
+                if remote.is_none()
+                    && self.config.case_insensitive
+                    && let Some(matching_name) = self.case_insensitive_match(parent_ino, name).await?
+                {
+                    return Box::pin(self.lookup_by_name(parent_ino, matching_name.as_ref(), allow_cache)).await;
+                }
                 self.update_from_remote(parent_ino, name, remote)?
             }
         };
@@ -1499,28 +2180,202 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
         lookup
     }
 
-    /// Lookup an inode in the parent directory with the given name
-    /// on the remote client.
-    async fn remote_lookup(
-        &self,
-        parent_ino: InodeNo,
-        name: ValidName<'_>,
-    ) -> Result<Option<RemoteLookup>, InodeError> {
-        let parent = self.get(parent_ino)?;
-        let full_path: String = self
-            .full_key_for_inode(&parent)
-            .new_child(name, InodeKind::Directory)
-            .map_err(|_| InodeError::NotADirectory(parent.err()))?
-            .into();
-
-        let object_key = &full_path[..(full_path.len() - 1)];
-        let directory_prefix = &full_path[..];
+    /// Lookup the given `name` in the negative cache only.
+    /// If an entry is found, returns [Some(Err(InodeError::FileDoesNotExist))], otherwise returns [None].
+    fn negative_cache_lookup(&self, parent_ino: InodeNo, name: &str) -> Option<Result<LookedUpInode, InodeError>> {
+        let parent = self.get(parent_ino).ok()?;
+        if !self.negative_cache.contains(parent_ino, name) {
+            return None;
+        }
+        trace!("negative lookup returned from cache");
+        metrics::counter!("metadata_cache.cache_hit").increment(1);
+        Some(Err(InodeError::FileDoesNotExist(name.to_owned(), parent.err())))
+    }
 
-        // We need to try two requests here, one to find an object with the given name, and one to
-        // discover a possible shadowing (implicit) directory with the same name. There's a few
-        // different cases we need to consider here:
-        //   (1) Consider this namespace with two keys:
-        //           a
+    /// Expire the cached metadata of `key` and of the directories holding it, see [Metablock::invalidate].
+    ///
+    /// Each component of the key is removed from the negative cache, and the inodes cached for them are expired, as
+    /// the directories may have been created or emptied along with the object.
+    fn invalidate(&self, key: &str) -> Invalidations {
+        let mut invalidations = Invalidations::default();
+        let Some(path) = self.config.key_mapping().path_for_key(key) else {
+            return invalidations;
+        };
+        let Some(key) = path.strip_prefix(self.s3_path.prefix.as_str()) else {
+            return invalidations;
+        };
+        let Ok(mut parent) = self.get(FUSE_ROOT_INODE) else {
+            return invalidations;
+        };
+        let key = key.trim_end_matches('/');
+        if key.is_empty() {
+            return invalidations;
+        }
+        for name in key.split('/') {
+            if ValidName::parse_str(name).is_err() {
+                break;
+            }
+            self.negative_cache.remove(parent.ino(), name);
+            invalidations.entries.push((parent.ino(), name.into()));
+            let child = parent.get_inode_state().ok().and_then(|state| match &state.kind_data {
+                InodeKindData::Directory { children, .. } => children.get(name).cloned(),
+                InodeKindData::File { .. } => None,
+            });
+            let Some(child) = child else {
+                break;
+            };
+            child
+                .get_mut_inode_state_no_check()
+                .stat
+                .update_validity(Duration::ZERO);
+            invalidations.inodes.push(child.ino());
+            parent = child;
+        }
+        trace!(key, ?invalidations, "invalidated cached metadata");
+        invalidations
+    }
+
+    /// Expire the cached metadata of an inode and of the inodes cached under it, see [Metablock::invalidate_inode].
+    ///
+    /// Inodes being written are left alone, as their metadata is not in S3 yet. The negative cache entries of the
+    /// directories are removed.
+    fn invalidate_inode(&self, ino: InodeNo) -> Result<Invalidations, InodeError> {
+        let inode = self.get(ino)?;
+        let mut invalidations = Invalidations::default();
+        if ino != FUSE_ROOT_INODE {
+            invalidations.entries.push((inode.parent(), inode.name().into()));
+        }
+        let mut directories = HashSet::new();
+        let mut pending = vec![inode];
+        while let Some(inode) = pending.pop() {
+            let mut state = inode.get_mut_inode_state_no_check();
+            if state.write_status == WriteStatus::Remote {
+                state.stat.update_validity(Duration::ZERO);
+            }
+            invalidations.inodes.push(inode.ino());
+            if let InodeKindData::Directory { children, .. } = &state.kind_data {
+                directories.insert(inode.ino());
+                for (name, child) in children {
+                    invalidations.entries.push((inode.ino(), name.as_ref().into()));
+                    pending.push(child.clone());
+                }
+            }
+        }
+        for (parent, name) in self.negative_cache.remove_children(&directories) {
+            invalidations.entries.push((parent, name.into()));
+        }
+        debug!(ino, inodes = invalidations.inodes.len(), "invalidated cached metadata");
+        Ok(invalidations)
+    }
+
+    /// Lookup an inode in the parent directory with the given name
+    /// on the remote client.
+    async fn remote_lookup(
+        &self,
+        parent_ino: InodeNo,
+        name: ValidName<'_>,
+    ) -> Result<Option<RemoteLookup>, InodeError> {
+        let parent = self.get(parent_ino)?;
+        let parent_key = self.full_key_for_inode(&parent);
+        let full_path: String = parent_key
+            .new_child(name, InodeKind::Directory)
+            .map_err(|_| InodeError::NotADirectory(parent.err()))?
+            .into();
+
+        let object_key = &full_path[..(full_path.len() - 1)];
+        let directory_prefix = &full_path[..];
+
+        // The entries of versions directories are the versions of their object, named by their ID.
+        if let Some(versioned_key) = self.versions_dir_object(parent_key.as_ref()) {
+            let version = self
+                .list_versions(versioned_key)
+                .await?
+                .into_iter()
+                .find(|version| version.version_id == *name);
+            let Some(version) = version else {
+                trace!(parent = ?parent_ino, ?name, "version not found");
+                return Ok(None);
+            };
+            trace!(parent = ?parent_ino, ?name, etag = version.etag, "versions lookup found a version");
+            let mut stat = InodeStat::for_file(
+                version.size as usize,
+                version.last_modified,
+                Some(version.etag.into_boxed_str()),
+                version.storage_class.as_deref(),
+                version.restore_status,
+                self.config.cache_config.file_ttl,
+            );
+            stat.version_id = Some(version.version_id.into_boxed_str());
+            stat.pinned_version = true;
+            return Ok(Some(RemoteLookup {
+                kind: InodeKind::File,
+                stat,
+            }));
+        }
+
+        // Keys hidden by the filter are not looked up at all.
+        let file_visible = self.is_visible(&parent, &name, InodeKind::File);
+        let dir_visible = self.is_visible(&parent, &name, InodeKind::Directory);
+        if !file_visible && !dir_visible {
+            trace!(parent = ?parent_ino, ?name, "name hidden by the key filter");
+            return Ok(None);
+        }
+
+        if let Some(versioned_key) = self.versions_dir_object(directory_prefix)
+            && !self.list_versions(versioned_key).await?.is_empty()
+        {
+            trace!(parent = ?parent_ino, ?name, "versions lookup found a versions directory");
+            let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
+            return Ok(Some(RemoteLookup {
+                kind: InodeKind::Directory,
+                stat,
+            }));
+        }
+
+        // Keys that are not in the inventory report, such as the objects created since, are looked up in S3, unless
+        // the index is a snapshot.
+        if let Some(inventory) = self.index() {
+            match inventory.lookup(object_key) {
+                Some(InventoryLookup::Directory) if !dir_visible => return Ok(None),
+                Some(InventoryLookup::File(_)) if !file_visible => return Ok(None),
+                Some(InventoryLookup::Directory) => {
+                    trace!(parent = ?parent_ino, ?name, "inventory lookup found a directory");
+                    let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
+                    return Ok(Some(RemoteLookup {
+                        kind: InodeKind::Directory,
+                        stat,
+                    }));
+                }
+                Some(InventoryLookup::File(object)) => {
+                    trace!(parent = ?parent_ino, ?name, etag = object.etag, "inventory lookup found a regular file");
+                    let mut stat = InodeStat::for_file(
+                        object.size as usize,
+                        object.last_modified,
+                        Some(object.etag.clone().into_boxed_str()),
+                        object.storage_class.as_deref(),
+                        None,
+                        self.config.cache_config.file_ttl,
+                    );
+                    stat.version_id = object.version_id.as_deref().map(Into::into);
+                    stat.pinned_version = stat.version_id.is_some();
+                    return Ok(Some(RemoteLookup {
+                        kind: InodeKind::File,
+                        stat,
+                    }));
+                }
+                None if self.config.snapshot.is_some() => {
+                    trace!(parent = ?parent_ino, ?name, "key not found in snapshot");
+                    return Ok(None);
+                }
+                None => {}
+            }
+        }
+
+        // We need to try two requests here, one to find an object with the given name, and one to
+        // discover a possible shadowing (implicit) directory with the same name. There's a few
+        // different cases we need to consider here:
+        //   (1) Consider this namespace with two keys:
+        //           a
         //           a/b
         //       Here we need to make a choice about whether to make `a` visible as a file or as a
         //       directory. We choose to make it a directory. If we lookup("a") and only do a
@@ -1540,49 +2395,68 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
         //       "/" to the prefix in the request, the first common prefix we'll get back will be
         //       "dir-1/", because that precedes "dir/" in lexicographic order. Doing the
         //       ListObjects with "/" appended makes sure we always observe the correct prefix.
+        let key_mapping = self.config.key_mapping();
+        let delimiter = key_mapping.delimiter();
+        let object_key = key_mapping.key_for_path(object_key);
+        let directory_prefix = key_mapping.key_for_path(directory_prefix);
+        // An empty name, escaped as `%`, is only ever a directory, as its file would be the marker of its parent.
+        let is_directory_key = object_key.is_empty() || object_key.ends_with(delimiter.as_str());
         let head_object_params = HeadObjectParams::new();
-        let mut file_lookup = self
-            .client
-            .head_object(&self.s3_path.bucket, object_key, &head_object_params)
-            .fuse();
-        let mut dir_lookup = self
-            .client
-            .list_objects(&self.s3_path.bucket, None, "/", 1, directory_prefix)
-            .fuse();
+        let mut file_lookup = Box::pin(async {
+            if !file_visible {
+                return Err(ObjectClientError::ServiceError(HeadObjectError::NotFound));
+            }
+            self.client
+                .head_object(&self.s3_path.bucket, &object_key, &head_object_params)
+                .await
+        })
+        .fuse();
+        let mut dir_lookup = Box::pin(async {
+            if !dir_visible {
+                return Ok(None);
+            }
+            self.client
+                .list_objects(&self.s3_path.bucket, None, delimiter.as_str(), 1, &directory_prefix)
+                .await
+                .map(Some)
+        })
+        .fuse();
 
         let mut file_state = None;
+        let mut directory_found = false;
 
         for _ in 0..2 {
             select_biased! {
                 result = file_lookup => {
                     match result {
-                        Ok(HeadObjectResult { size, last_modified, restore_status, etag, storage_class, .. }) => {
-                            let stat = InodeStat::for_file(size as usize, last_modified, Some(etag.into_inner().into_boxed_str()), storage_class.as_deref(), restore_status, self.config.cache_config.file_ttl);
-                            file_state = Some(stat);
-                        }
+                        _ if is_directory_key => {}
+                        Ok(head) => file_state = Some(self.file_stat(head)),
                         // If the object is not found, might be a directory, so keep going
                         Err(ObjectClientError::ServiceError(HeadObjectError::NotFound)) => {},
-                        Err(e) => return Err(InodeError::client_error(e, "HeadObject failed", &self.s3_path.bucket, object_key)),
+                        Err(e) => return Err(InodeError::client_error(e, "HeadObject failed", &self.s3_path.bucket, &object_key)),
                     }
                 }
 
                 result = dir_lookup => {
-                    let result = result.map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", &self.s3_path.bucket, object_key))?;
+                    let result = result.map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", &self.s3_path.bucket, &object_key))?;
+                    let Some(result) = result else {
+                        continue;
+                    };
 
                     let found_directory = if result
                         .common_prefixes
                         .first()
-                        .map(|prefix| prefix.starts_with(directory_prefix))
+                        .map(|prefix| prefix.starts_with(&*directory_prefix))
                         .unwrap_or(false)
                     {
                         true
                     } else if result
                         .objects
                         .first()
-                        .map(|object| object.key.starts_with(directory_prefix))
+                        .map(|object| object.key.starts_with(&*directory_prefix))
                         .unwrap_or(false)
                     {
-                        if result.objects[0].key == directory_prefix {
+                        if result.objects[0].key == *directory_prefix {
                             trace!(
                                 parent = ?parent_ino,
                                 ?name,
@@ -1603,153 +2477,869 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
                         false
                     };
 
-                    // We don't have to wait for the HeadObject to complete because in our
-                    // semantics, directories always shadow files.
+                    // By default directories shadow files, so we don't have to wait for the HeadObject to
+                    // complete.
                     if found_directory {
                         trace!(parent = ?parent_ino, ?name, "lookup ListObjects found a directory");
-                        let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
-                        return Ok(Some(RemoteLookup { kind: InodeKind::Directory, stat }));
+                        if self.config.shadowing == ShadowingPolicy::DirectoryWins {
+                            let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
+                            return Ok(Some(RemoteLookup { kind: InodeKind::Directory, stat }));
+                        }
+                        directory_found = true;
                     }
                 }
             }
         }
 
+        // Objects that haven't been restored may be hidden, and then don't shadow directories either.
+        if self.config.archived_objects == ArchivedObjectsPolicy::Hidden
+            && file_state.as_ref().is_some_and(|stat| !stat.is_readable)
+        {
+            trace!(parent = ?parent_ino, ?name, "hiding an archived object");
+            file_state = None;
+        }
+
+        if directory_found && file_state.is_some() {
+            trace!(parent = ?parent_ino, ?name, policy = ?self.config.shadowing, "file and directory have the same name");
+            metrics::counter!("fs.shadowed_keys").increment(1);
+        }
+        if directory_found && (file_state.is_none() || self.config.shadowing != ShadowingPolicy::FileWins) {
+            let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
+            return Ok(Some(RemoteLookup {
+                kind: InodeKind::Directory,
+                stat,
+            }));
+        }
+
         // If we reach here, the ListObjects didn't find a shadowing directory, so we know we either
         // have a valid file, or both requests failed to find the object so the file must not exist remotely
         if let Some(mut stat) = file_state {
             trace!(parent = ?parent_ino, ?name, etag =? stat.etag, "found a regular file in S3");
+            self.decode_size(&self.s3_path.bucket, &object_key, &mut stat).await?;
             // Update the validity of the stat in case the racing ListObjects took a long time
             stat.update_validity(self.config.cache_config.file_ttl);
             Ok(Some(RemoteLookup {
                 kind: InodeKind::File,
                 stat,
             }))
+        } else if let Some(remote) = self.lookup_shadowed_file(&parent, &parent_key, name).await? {
+            Ok(Some(remote))
+        } else if let Some(remote) = self.lookup_recent_write(&parent, &name) {
+            Ok(Some(remote))
+        } else if let Some(remote) = self.lookup_compressed_file(&parent, object_key.as_ref(), name).await? {
+            Ok(Some(remote))
         } else {
             trace!(parent = ?parent_ino, ?name, "not found");
             Ok(None)
         }
     }
 
-    /// Update the inode with the given name in a parent directory with the remote data.
-    /// It may update or delete an existing inode, or insert a new one.
-    fn update_from_remote(
+    /// Rename an object by copying it to the destination key and deleting the source, for the buckets that don't
+    /// support RenameObject, see [SuperblockConfig::rename_by_copy]. The CRT copies objects over 5 GiB with a
+    /// multipart upload of UploadPartCopy requests. Without `allow_overwrite`, the copy is only made if the destination
+    /// doesn't exist, with an `If-None-Match: *` precondition, so that a destination created concurrently by another
+    /// client isn't replaced.
+    async fn copy_and_delete(
         &self,
-        parent_ino: InodeNo,
-        name: ValidName,
-        remote: Option<RemoteLookup>,
-    ) -> Result<LookedUpInode, InodeError> {
-        let parent = self.get(parent_ino)?;
+        src_inode: &Inode,
+        src_key: &str,
+        dest_key: &str,
+        storage_class: Option<&str>,
+        allow_overwrite: bool,
+    ) -> Result<(), InodeError> {
+        let bucket = &self.s3_path.bucket;
+        let mut params = copy_object_params(storage_class);
+        if !allow_overwrite {
+            params = params.if_none_match(Some("*".to_owned()));
+        }
+        debug!(?src_key, ?dest_key, "renaming by copying the object");
+        match self
+            .client
+            .copy_object(bucket, src_key, bucket, dest_key, &params)
+            .await
+        {
+            Ok(_) => {}
+            Err(ObjectClientError::ServiceError(CopyObjectError::PreconditionFailed)) => {
+                return Err(InodeError::RenameDestinationExists {
+                    dest_key: dest_key.to_owned(),
+                    src_inode: src_inode.err(),
+                });
+            }
+            Err(ObjectClientError::ServiceError(CopyObjectError::NotFound)) => {
+                return Err(InodeError::InodeDoesNotExist(src_inode.ino()));
+            }
+            Err(ObjectClientError::ServiceError(CopyObjectError::ObjectNotInActiveTierError)) => {
+                return Err(InodeError::FlexibleRetrievalObjectNotAccessible(src_inode.err()));
+            }
+            Err(e) => return Err(InodeError::client_error(e, "CopyObject failed", bucket, src_key)),
+        }
+        metrics::counter!("fs.renames_by_copy").increment(1);
+
+        // The copy is in place, so failing to delete the source leaves the object at both keys.
+        if let Err(e) = self.client.delete_object(bucket, src_key).await {
+            warn!(
+                ?src_key,
+                ?dest_key,
+                "object was copied for rename but its source could not be deleted"
+            );
+            return Err(InodeError::client_error(e, "DeleteObject failed", bucket, src_key));
+        }
+        Ok(())
+    }
 
-        // Should be impossible since all callers check this already, but let's be safe
-        if parent.kind() != InodeKind::Directory {
-            return Err(InodeError::NotADirectory(parent.err()));
+    /// Copy the object of a file being unlinked to its key in the trash, see [SuperblockConfig::trash].
+    async fn copy_to_trash(
+        &self,
+        inode: &Inode,
+        key: &str,
+        trash_key: &str,
+        storage_class: Option<&str>,
+    ) -> Result<(), InodeError> {
+        let bucket = &self.s3_path.bucket;
+        debug!(?key, ?trash_key, "copying the object to the trash before deleting it");
+        match self
+            .client
+            .copy_object(bucket, key, bucket, trash_key, &copy_object_params(storage_class))
+            .await
+        {
+            Ok(_) => {}
+            Err(ObjectClientError::ServiceError(CopyObjectError::NotFound)) => {
+                return Err(InodeError::InodeDoesNotExist(inode.ino()));
+            }
+            Err(ObjectClientError::ServiceError(CopyObjectError::ObjectNotInActiveTierError)) => {
+                return Err(InodeError::FlexibleRetrievalObjectNotAccessible(inode.err()));
+            }
+            Err(e) => return Err(InodeError::client_error(e, "CopyObject failed", bucket, key)),
         }
+        metrics::counter!("fs.trash.objects").increment(1);
+        Ok(())
+    }
 
-        if self.config.cache_config.use_negative_cache {
-            match &remote {
-                // Remove negative cache entry.
-                Some(_) => self.negative_cache.remove(parent_ino, &name),
-                // Insert or update TTL of negative cache entry.
-                None => self.negative_cache.insert(parent_ino, &name),
+    /// Rename a directory by renaming every object under its prefix, see [SuperblockConfig::directory_rename]. The
+    /// objects copied rather than renamed are only deleted once all of them are copied, so that a failure leaves every
+    /// object at its old key, and the copied ones at both keys.
+    async fn rename_directory(
+        &self,
+        src_parent: &Inode,
+        src_inode: &Inode,
+        dst_parent: &Inode,
+        dst_name: &OsStr,
+        allow_overwrite: bool,
+        config: DirectoryRenameConfig,
+    ) -> Result<(), InodeError> {
+        // Files being written, and local directories, have no object to rename yet.
+        let mut descendants = vec![src_inode.clone()];
+        while let Some(inode) = descendants.pop() {
+            let state = inode.get_inode_state()?;
+            if let InodeKindData::Directory {
+                children,
+                writing_children,
+                ..
+            } = &state.kind_data
+            {
+                if !writing_children.is_empty() {
+                    return Err(InodeError::RenameNotPermittedWhileWriting(src_inode.err()));
+                }
+                descendants.extend(
+                    children
+                        .values()
+                        .filter(|child| child.kind() == InodeKind::Directory)
+                        .cloned(),
+                );
             }
         }
+        let is_local = src_inode.get_inode_state()?.write_status != WriteStatus::Remote;
 
-        // Fast path: try with only a read lock on the directory first.
-        if let Some(looked_up) = Self::try_update_fast_path(&parent, &name, &remote, self.s3_path.clone())? {
-            return Ok(looked_up);
+        let dest_name = self.parse_name(dst_name)?;
+        let dest_valid_key = dst_parent
+            .valid_key()
+            .new_child(dest_name, InodeKind::Directory)
+            .map_err(|_| InodeError::NotADirectory(dst_parent.err()))?;
+        let key_mapping = self.config.key_mapping();
+        let src_prefix = key_mapping
+            .key_for_path(self.full_key_for_inode(src_inode).as_ref())
+            .into_owned();
+        let dest_prefix = key_mapping
+            .key_for_path(dest_valid_key.full_key(&self.s3_path.prefix).as_ref())
+            .into_owned();
+        let bucket = &self.s3_path.bucket;
+
+        let dest_inode = self
+            .lookup_by_name(
+                dst_parent.ino(),
+                dst_name,
+                self.serve_lookup_from_cache(dst_parent.ino(), dst_name),
+            )
+            .await
+            .ok()
+            .map(|looked_up| looked_up.inode);
+        if let Some(dest_inode) = &dest_inode {
+            if dest_inode.kind() == InodeKind::File {
+                return Err(InodeError::NotADirectory(dest_inode.err()));
+            }
+            if !allow_overwrite {
+                return Err(InodeError::RenameDestinationExists {
+                    dest_key: dest_prefix,
+                    src_inode: src_inode.err(),
+                });
+            }
+            if let InodeKindData::Directory { writing_children, .. } = &dest_inode.get_inode_state()?.kind_data
+                && !writing_children.is_empty()
+            {
+                return Err(InodeError::DirectoryNotEmpty(dest_inode.err()));
+            }
+            let result = self
+                .client
+                .list_objects(bucket, None, "", 2, &dest_prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &dest_prefix))?;
+            if result.objects.iter().any(|object| object.key != dest_prefix) {
+                return Err(InodeError::DirectoryNotEmpty(dest_inode.err()));
+            }
         }
 
-        self.update_slow_path(parent, name, remote)
-    }
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let result = self
+                .client
+                .list_objects(bucket, continuation_token.as_deref(), "", 1000, &src_prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &src_prefix))?;
+            objects.extend(result.objects);
+            if objects.len() > config.max_objects {
+                return Err(InodeError::DirectoryTooLargeToRename {
+                    max_objects: config.max_objects,
+                    inode: src_inode.err(),
+                });
+            }
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
 
-    /// Try to update the inode for the given name in the parent directory with only a read lock on
-    /// the parent.
-    fn try_update_fast_path(
-        parent: &Inode,
-        name: &str,
-        remote: &Option<RemoteLookup>,
-        s3_path: Arc<S3Path>,
-    ) -> Result<Option<LookedUpInode>, InodeError> {
-        let parent_state = parent.get_inode_state()?;
-        let inode = match &parent_state.kind_data {
-            InodeKindData::File { .. } => unreachable!("we know parent is a directory"),
-            InodeKindData::Directory { children, .. } => children.get(name),
-        };
-        match (remote, inode) {
-            (None, None) => Err(InodeError::FileDoesNotExist(name.to_owned(), parent.err())),
-            (Some(remote), Some(existing_inode)) => {
-                let mut existing_state = existing_inode.get_mut_inode_state()?;
-                let existing_has_local_state =
-                    existing_state.write_status != WriteStatus::Remote || existing_state.pending_upload_hook.is_some();
-                if remote.kind == existing_inode.kind()
-                    && !existing_has_local_state
-                    && existing_state.stat.etag == remote.stat.etag
+        debug!(?src_prefix, ?dest_prefix, objects = objects.len(), "renaming directory");
+        let renamed = AtomicUsize::new(0);
+        let copied = std::sync::Mutex::new(Vec::new());
+        let result = futures::stream::iter(objects.iter().map(Ok))
+            .try_for_each_concurrent(config.concurrency, |object| async {
+                let dest_key = format!("{dest_prefix}{}", &object.key[src_prefix.len()..]);
+                if self
+                    .rename_directory_object(&object.key, &dest_key, object.storage_class.as_deref())
+                    .await?
                 {
-                    trace!(parent=?existing_inode.parent(), name=?existing_inode.name(), ino=?existing_inode.ino(), "updating inode in place");
-                    existing_state.stat = remote.stat.clone();
-                    Ok(Some(LookedUpInode {
-                        inode: existing_inode.clone(),
-                        stat: remote.stat.clone(),
-                        path: s3_path.clone(),
-                        write_status: existing_state.write_status,
-                    }))
-                } else {
-                    Ok(None)
+                    copied.lock().unwrap().push(object.key.as_str());
                 }
+                renamed.fetch_add(1, Ordering::SeqCst);
+                metrics::counter!("fs.directory_renames.objects").increment(1);
+                Ok(())
+            })
+            .await;
+        if let Err(e) = result {
+            warn!(
+                ?src_prefix,
+                ?dest_prefix,
+                renamed = renamed.load(Ordering::SeqCst),
+                total = objects.len(),
+                "directory rename failed, the objects renamed or copied so far are at their new key"
+            );
+            return Err(e);
+        }
+
+        let copied = copied.into_inner().unwrap();
+        let deleted = AtomicUsize::new(0);
+        let result = futures::stream::iter(copied.iter().map(Ok))
+            .try_for_each_concurrent(config.concurrency, |key| async {
+                self.client
+                    .delete_object(bucket, key)
+                    .await
+                    .map_err(|e| InodeError::client_error(e, "DeleteObject failed", bucket, key))?;
+                deleted.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        if let Err(e) = result {
+            warn!(
+                ?src_prefix,
+                ?dest_prefix,
+                deleted = deleted.load(Ordering::SeqCst),
+                total = copied.len(),
+                "objects were copied for directory rename but some of their sources could not be deleted"
+            );
+            return Err(e);
+        }
+        metrics::counter!("fs.directory_renames").increment(1);
+        debug!(
+            ?src_prefix,
+            ?dest_prefix,
+            objects = objects.len(),
+            "directory rename completed"
+        );
+
+        self.recent_writes.remove_prefix(src_inode.key());
+        self.negative_cache.remove(dst_parent.ino(), dest_name.as_ref());
+        // The entries of the directory are looked up again under the new key.
+        let new_inode =
+            src_inode.try_clone_with_new_key(dest_valid_key, &self.s3_path.prefix, Duration::ZERO, dst_parent.ino())?;
+        let mut rename_guard = RenameLockGuard::new(src_parent, dst_parent, self)?;
+        if let InodeKindData::Directory {
+            children,
+            writing_children,
+            ..
+        } = &mut rename_guard.source_parent_mut().kind_data
+        {
+            children.remove(src_inode.name());
+            writing_children.remove(&src_inode.ino());
+        }
+        if let InodeKindData::Directory {
+            children,
+            writing_children,
+            ..
+        } = &mut rename_guard.destination_parent_mut().kind_data
+        {
+            if let Some(old_inode) = children.remove(dest_name.as_ref())
+                && let InodeKindData::Directory { deleted, .. } =
+                    &mut old_inode.get_mut_inode_state_no_check().kind_data
+            {
+                *deleted = true;
             }
-            _ => Ok(None),
+            if is_local {
+                writing_children.insert(new_inode.ino());
+            }
+            if let Some(inode_table) = &self.config.inode_table {
+                inode_table.rename(src_inode.key(), new_inode.key(), new_inode.ino());
+            }
+            children.insert(dest_name.as_ref().into(), new_inode.clone());
         }
+        self.inodes
+            .write()
+            .unwrap()
+            .replace_or_insert(new_inode.ino(), &new_inode);
+        Ok(())
     }
 
-    /// Update or create the inode for the given name in the parent directory with a write lock on
-    /// the parent. This method still needs to handle the cases handled by [try_update_fast_path]
-    /// because an intervening writer might have modified the inode we're updating.
-    fn update_slow_path(
+    /// Rename an object under a directory being renamed, with RenameObject where it's supported and otherwise by
+    /// copying it. Returns whether the object was copied, in which case its source is still to be deleted.
+    async fn rename_directory_object(
         &self,
-        parent: Inode,
-        name: ValidName,
-        remote: Option<RemoteLookup>,
-    ) -> Result<LookedUpInode, InodeError> {
-        let mut parent_state = parent.get_mut_inode_state()?;
-        let inode = match &parent_state.kind_data {
-            InodeKindData::File { .. } => unreachable!("we know parent is a directory"),
-            InodeKindData::Directory { children, .. } => children.get(name.as_ref()).cloned(),
-        };
-        match (remote, inode) {
-            (None, None) => Err(InodeError::FileDoesNotExist(name.to_string(), parent.err())),
-            (None, Some(existing_inode)) => {
-                let InodeKindData::Directory {
-                    children,
-                    writing_children,
-                    ..
-                } = &mut parent_state.kind_data
-                else {
-                    unreachable!("we know parent is a directory");
-                };
-                if writing_children.contains(&existing_inode.ino()) {
-                    let mut sync = existing_inode.get_mut_inode_state()?;
-
-                    let validity = match existing_inode.kind() {
-                        InodeKind::File => self.config.cache_config.file_ttl,
-                        InodeKind::Directory => self.config.cache_config.dir_ttl,
-                    };
-                    sync.stat.update_validity(validity);
-                    let stat = sync.stat.clone();
-                    let write_status = sync.write_status;
-                    drop(sync);
-
-                    Ok(LookedUpInode {
-                        inode: existing_inode,
-                        stat,
-                        path: self.s3_path.clone(),
-                        write_status,
-                    })
-                } else {
-                    // This existing inode is local-only (because `remote` is None), but is not
-                    // being written. It must have previously existed but been removed on the remote
-                    // side.
-                    children.remove(name.as_ref());
-                    Err(InodeError::FileDoesNotExist(name.to_string(), parent.err()))
+        src_key: &str,
+        dest_key: &str,
+        storage_class: Option<&str>,
+    ) -> Result<bool, InodeError> {
+        let bucket = &self.s3_path.bucket;
+        if self.cached_rename_support.should_try_rename() {
+            match self
+                .client
+                .rename_object(bucket, src_key, dest_key, &RenameObjectParams::new())
+                .await
+            {
+                Ok(_) => {
+                    self.cached_rename_support.cache_success();
+                    return Ok(false);
+                }
+                Err(ObjectClientError::ServiceError(RenameObjectError::NotImplementedError)) => {
+                    self.cached_rename_support.cache_failure();
+                    if !self.config.rename_by_copy {
+                        return Err(InodeError::RenameNotSupported());
+                    }
+                }
+                Err(ObjectClientError::ServiceError(RenameObjectError::KeyTooLong)) => {
+                    return Err(InodeError::NameTooLong(dest_key.to_owned()));
+                }
+                Err(e) => return Err(InodeError::client_error(e, "RenameObject failed", bucket, src_key)),
+            }
+        }
+        self.client
+            .copy_object(bucket, src_key, bucket, dest_key, &copy_object_params(storage_class))
+            .await
+            .map_err(|e| InodeError::client_error(e, "CopyObject failed", bucket, src_key))?;
+        Ok(true)
+    }
+
+    /// Look up a file or directory that S3 doesn't return yet from the objects recently written by this mount, see
+    /// [SuperblockConfig::recent_writes_window].
+    fn lookup_recent_write(&self, parent: &Inode, name: &str) -> Option<RemoteLookup> {
+        if let Some(mut stat) = self.recent_writes.file(&child_key(parent, name, InodeKind::File)) {
+            trace!(parent = ?parent.ino(), ?name, "found a recently written file");
+            metrics::counter!("metadata_cache.recent_writes.hits").increment(1);
+            stat.update_validity(self.config.cache_config.file_ttl);
+            return Some(RemoteLookup {
+                kind: InodeKind::File,
+                stat,
+            });
+        }
+        if self
+            .recent_writes
+            .has_directory(&child_key(parent, name, InodeKind::Directory))
+        {
+            trace!(parent = ?parent.ino(), ?name, "found a directory with recently written files");
+            metrics::counter!("metadata_cache.recent_writes.hits").increment(1);
+            let stat = InodeStat::for_directory(self.mount_time, self.config.cache_config.dir_ttl);
+            return Some(RemoteLookup {
+                kind: InodeKind::Directory,
+                stat,
+            });
+        }
+        None
+    }
+
+    /// Look up the file of a key shadowed by a directory, shown with the [SHADOWED_FILE_SUFFIX] appended to its name,
+    /// see [ShadowingPolicy::SuffixFile].
+    async fn lookup_shadowed_file(
+        &self,
+        parent: &Inode,
+        parent_key: &ValidKey,
+        name: ValidName<'_>,
+    ) -> Result<Option<RemoteLookup>, InodeError> {
+        if self.config.shadowing != ShadowingPolicy::SuffixFile {
+            return Ok(None);
+        }
+        let Some(file_name) = name.strip_suffix(SHADOWED_FILE_SUFFIX) else {
+            return Ok(None);
+        };
+        let Ok(file_name) = ValidName::parse_str(file_name) else {
+            return Ok(None);
+        };
+        if !self.is_visible(parent, &file_name, InodeKind::File) {
+            return Ok(None);
+        }
+        let full_path: String = parent_key
+            .new_child(file_name, InodeKind::Directory)
+            .map_err(|_| InodeError::NotADirectory(parent.err()))?
+            .into();
+        let key_mapping = self.config.key_mapping();
+        let object_key = key_mapping.key_for_path(&full_path[..(full_path.len() - 1)]);
+        let directory_prefix = key_mapping.key_for_path(&full_path);
+        let bucket = &self.s3_path.bucket;
+        let delimiter = key_mapping.delimiter();
+        let head_object_params = HeadObjectParams::new();
+        let (head, list) = futures::join!(
+            self.client.head_object(bucket, &object_key, &head_object_params),
+            self.client
+                .list_objects(bucket, None, delimiter.as_str(), 1, &directory_prefix),
+        );
+        let head = match head {
+            Ok(head) => head,
+            Err(ObjectClientError::ServiceError(HeadObjectError::NotFound)) => return Ok(None),
+            Err(e) => return Err(InodeError::client_error(e, "HeadObject failed", bucket, &object_key)),
+        };
+        let list = list.map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &directory_prefix))?;
+        let found_directory = list
+            .common_prefixes
+            .first()
+            .is_some_and(|prefix| prefix.starts_with(&*directory_prefix))
+            || list
+                .objects
+                .first()
+                .is_some_and(|object| object.key.starts_with(&*directory_prefix));
+        if !found_directory {
+            return Ok(None);
+        }
+        trace!(?name, ?object_key, "lookup found the file of a shadowed key");
+        let mut stat = self.file_stat(head);
+        self.decode_size(bucket, &object_key, &mut stat).await?;
+        stat.shadowed = true;
+        Ok(Some(RemoteLookup {
+            kind: InodeKind::File,
+            stat,
+        }))
+    }
+
+    /// Look up the file of an object whose key is `object_key` with the extension of a [Compression] appended to it,
+    /// shown with the name of its decompressed content, see [SuperblockConfig::decompress_files]. The objects with each
+    /// extension are looked up concurrently, and when several exist, the first one in [Compression::ALL] is used.
+    /// Objects whose decompressed size can't be read, or which can't be read because they are archived, are not
+    /// shown.
+    async fn lookup_compressed_file(
+        &self,
+        parent: &Inode,
+        object_key: &str,
+        name: ValidName<'_>,
+    ) -> Result<Option<RemoteLookup>, InodeError> {
+        if !self.config.decompress_files {
+            return Ok(None);
+        }
+        let bucket = &self.s3_path.bucket;
+        let candidates: Vec<_> = Compression::ALL
+            .into_iter()
+            .filter(|compression| {
+                self.is_visible(parent, &format!("{name}{}", compression.extension()), InodeKind::File)
+            })
+            .map(|compression| (compression, format!("{object_key}{}", compression.extension())))
+            .collect();
+        let head_object_params = HeadObjectParams::new();
+        let heads = futures::future::join_all(
+            candidates
+                .iter()
+                .map(|(_, compressed_key)| self.client.head_object(bucket, compressed_key, &head_object_params)),
+        )
+        .await;
+        for ((compression, compressed_key), head) in candidates.iter().zip(heads) {
+            let head = match head {
+                Ok(head) => head,
+                Err(ObjectClientError::ServiceError(HeadObjectError::NotFound)) => continue,
+                Err(e) => return Err(InodeError::client_error(e, "HeadObject failed", bucket, compressed_key)),
+            };
+            let mut stat = self.file_stat(head);
+            if !stat.is_readable {
+                continue;
+            }
+            stat.encoded_size = Some(stat.size);
+            stat.decompressed = Some(*compression);
+            self.decode_size(bucket, compressed_key, &mut stat).await?;
+            if stat.encoded_size.is_none() {
+                continue;
+            }
+            trace!(?name, ?compressed_key, "lookup found the file of a compressed object");
+            return Ok(Some(RemoteLookup {
+                kind: InodeKind::File,
+                stat,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// The name of the entry of a directory matching `name` case-insensitively, when no entry has exactly this name,
+    /// see [SuperblockConfig::case_insensitive]. The local entries of the directory and the keys listed under its
+    /// prefix are compared to `name`, and when several of them match, the first one in byte order is used.
+    async fn case_insensitive_match(
+        &self,
+        parent_ino: InodeNo,
+        name: ValidName<'_>,
+    ) -> Result<Option<String>, InodeError> {
+        let parent = self.get(parent_ino)?;
+        let parent_key = self.full_key_for_inode(&parent);
+        // Inventory reports, snapshots and versions directories aren't listed from S3.
+        if self.index().is_some() || self.versions_dir_object(parent_key.as_ref()).is_some() {
+            return Ok(None);
+        }
+        let folded_name = name.to_lowercase();
+        let mut matching_names = BTreeSet::new();
+        {
+            let parent_state = parent.get_inode_state()?;
+            let InodeKindData::Directory { children, .. } = &parent_state.kind_data else {
+                return Err(InodeError::NotADirectory(parent.err()));
+            };
+            // Local entries, such as files being written, are not returned by the remote lookup.
+            if children.contains_key(&*name) {
+                return Ok(None);
+            }
+            for (child_name, child) in children {
+                if child_name.to_lowercase() == folded_name
+                    && child.get_inode_state()?.write_status != WriteStatus::Remote
+                {
+                    matching_names.insert(child_name.to_string());
+                }
+            }
+        }
+
+        let key_mapping = self.config.key_mapping();
+        let delimiter = key_mapping.delimiter();
+        let prefix = key_mapping.key_for_path(parent_key.as_ref());
+        let bucket = &self.s3_path.bucket;
+        let mut continuation_token = None;
+        loop {
+            let result = self
+                .client
+                .list_objects(bucket, continuation_token.as_deref(), delimiter.as_str(), 1000, &prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &prefix))?;
+            let keys = result
+                .common_prefixes
+                .iter()
+                .map(String::as_str)
+                .chain(result.objects.iter().map(|object| object.key.as_str()));
+            for key in keys {
+                let Some(path) = key_mapping.path_for_key(key) else {
+                    continue;
+                };
+                let Some(child_name) = path.strip_prefix(parent_key.as_ref()) else {
+                    continue;
+                };
+                let child_name = child_name.strip_suffix('/').unwrap_or(child_name);
+                if !child_name.is_empty() && child_name != &*name && child_name.to_lowercase() == folded_name {
+                    matching_names.insert(child_name.to_owned());
+                }
+            }
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        let mut matching_names = matching_names.into_iter();
+        let matching_name = matching_names.next();
+        if let Some(matching_name) = &matching_name {
+            let other_names: Vec<_> = matching_names.collect();
+            if other_names.is_empty() {
+                trace!(parent = ?parent_ino, ?name, ?matching_name, "found a case-insensitive match");
+            } else {
+                warn!(
+                    parent = ?parent_ino,
+                    ?name,
+                    ?matching_name,
+                    ?other_names,
+                    "several entries match the name case-insensitively, using the first one in byte order"
+                );
+                metrics::counter!("fs.case_insensitive_collisions").increment(1);
+            }
+        }
+        Ok(matching_name)
+    }
+
+    /// Stat of the file of an object, from its HeadObject result.
+    fn file_stat(&self, head: HeadObjectResult) -> InodeStat {
+        let HeadObjectResult {
+            size,
+            last_modified,
+            restore_status,
+            etag,
+            storage_class,
+            version_id,
+            mut object_metadata,
+            content_encoding,
+            ..
+        } = head;
+        // HeadObject omits the storage class of objects in S3 Standard.
+        let storage_class = storage_class.as_deref().unwrap_or("STANDARD");
+        let mut stat = InodeStat::for_file(
+            size as usize,
+            last_modified,
+            Some(etag.into_inner().into_boxed_str()),
+            Some(storage_class),
+            restore_status,
+            self.config.cache_config.file_ttl,
+        );
+        stat.version_id = version_id.map(String::into_boxed_str);
+        if self.config.symlinks {
+            stat.symlink_target = object_metadata
+                .remove(SYMLINK_TARGET_METADATA_KEY)
+                .map(String::into_boxed_str);
+        }
+        if self.config.decode_content_encoding && content_encoding.as_deref().is_some_and(is_gzip_encoding) {
+            // The size of the decoded content is looked up separately, see [Self::decode_size].
+            stat.encoded_size = Some(stat.size);
+        }
+        if self.config.mtime_metadata
+            && let Some(mtime) = object_metadata
+                .get(MTIME_METADATA_KEY)
+                .and_then(|value| parse_mtime(value))
+        {
+            stat.mtime = mtime;
+        }
+        if self.config.atime_metadata
+            && let Some(atime) = object_metadata
+                .get(ATIME_METADATA_KEY)
+                .and_then(|value| parse_mtime(value))
+        {
+            stat.atime = atime;
+        }
+        if self.config.mode_metadata {
+            stat.mode = object_metadata
+                .get(MODE_METADATA_KEY)
+                .and_then(|value| parse_mode(value));
+        }
+        stat
+    }
+
+    /// Report the size of the decoded content of a file whose object is compressed, read from the ISIZE field ending
+    /// gzip streams or the header of zstd frames, see [SuperblockConfig::decode_content_encoding] and
+    /// [SuperblockConfig::decompress_files]. Objects too small to be compressed, or whose size can't be read, are
+    /// served as stored, with [InodeStat::encoded_size] unset.
+    async fn decode_size(&self, bucket: &str, key: &str, stat: &mut InodeStat) -> Result<(), InodeError> {
+        let (Some(encoded_size), Some(compression)) = (stat.encoded_size, stat.compression()) else {
+            return Ok(());
+        };
+        if encoded_size < compression.min_encoded_size() {
+            warn!(
+                key,
+                encoded_size,
+                compression = compression.as_str(),
+                "object is too small to be compressed, serving it as stored"
+            );
+            stat.encoded_size = None;
+            return Ok(());
+        }
+        let params = GetObjectParams::new()
+            .range(Some(compression.size_range(encoded_size as u64)))
+            .if_match(stat.etag.as_ref().map(ETag::from));
+        let to_inode_error = |e| InodeError::client_error(e, "GetObject failed", bucket, key);
+        let response = self
+            .client
+            .get_object(bucket, key, &params)
+            .await
+            .map_err(to_inode_error)?;
+        let parts: Vec<_> = response.try_collect().await.map_err(to_inode_error)?;
+        let data: Vec<u8> = parts.iter().flat_map(|part| part.data.iter().copied()).collect();
+        let Some(decoded_size) = compression.decoded_size(&data) else {
+            warn!(
+                key,
+                compression = compression.as_str(),
+                "unable to read the size of the compressed content, serving the object as stored"
+            );
+            stat.encoded_size = None;
+            return Ok(());
+        };
+        stat.size = decoded_size as usize;
+        Ok(())
+    }
+
+    /// Tell the kernel to drop the entry of an inode replaced in its parent, as its object was changed or removed in S3,
+    /// and the attributes and data it cached for the inode, see [Metablock::send_stale_entries].
+    fn send_stale_entry(&self, parent: &Inode, name: &str, stale_inode: &Inode) {
+        let Some(sender) = self.stale_entries.get() else {
+            return;
+        };
+        debug!(
+            parent = parent.ino(),
+            name,
+            ino = stale_inode.ino(),
+            "invalidating stale kernel cache entries"
+        );
+        metrics::counter!("metadata_cache.stale_kernel_entries").increment(1);
+        let invalidations = Invalidations {
+            entries: vec![(parent.ino(), name.into())],
+            inodes: vec![stale_inode.ino()],
+        };
+        if sender.try_send(invalidations).is_err() {
+            debug!(
+                ino = stale_inode.ino(),
+                "too many pending invalidations, the kernel will revalidate its cache after its TTL"
+            );
+        }
+    }
+
+    /// Update the inode with the given name in a parent directory with the remote data.
+    /// It may update or delete an existing inode, or insert a new one.
+    fn update_from_remote(
+        &self,
+        parent_ino: InodeNo,
+        name: ValidName,
+        remote: Option<RemoteLookup>,
+    ) -> Result<LookedUpInode, InodeError> {
+        let parent = self.get(parent_ino)?;
+
+        // Should be impossible since all callers check this already, but let's be safe
+        if parent.kind() != InodeKind::Directory {
+            return Err(InodeError::NotADirectory(parent.err()));
+        }
+
+        // Remote lookups use the default TTLs, apply the rules of the key instead.
+        let remote = remote.map(|mut remote| {
+            if !self.config.cache_config.ttl_rules.is_empty() {
+                let validity = self.child_ttl(&parent, name.as_ref(), remote.kind);
+                remote.stat.update_validity(validity);
+            }
+            remote
+        });
+
+        if self.config.cache_config.use_negative_cache {
+            match &remote {
+                // Remove negative cache entry.
+                Some(_) => self.negative_cache.remove(parent_ino, &name),
+                // Insert or update TTL of negative cache entry.
+                None => self.negative_cache.insert(parent_ino, &name),
+            }
+        }
+
+        // Fast path: try with only a read lock on the directory first.
+        if let Some(looked_up) = Self::try_update_fast_path(&parent, &name, &remote, self.s3_path.clone())? {
+            return Ok(looked_up);
+        }
+
+        self.update_slow_path(parent, name, remote)
+    }
+
+    /// Try to update the inode for the given name in the parent directory with only a read lock on
+    /// the parent.
+    fn try_update_fast_path(
+        parent: &Inode,
+        name: &str,
+        remote: &Option<RemoteLookup>,
+        s3_path: Arc<S3Path>,
+    ) -> Result<Option<LookedUpInode>, InodeError> {
+        let parent_state = parent.get_inode_state()?;
+        let inode = match &parent_state.kind_data {
+            InodeKindData::File { .. } => unreachable!("we know parent is a directory"),
+            InodeKindData::Directory { children, .. } => children.get(name),
+        };
+        match (remote, inode) {
+            (None, None) => Err(InodeError::FileDoesNotExist(name.to_owned(), parent.err())),
+            (Some(remote), Some(existing_inode)) => {
+                let mut existing_state = existing_inode.get_mut_inode_state()?;
+                let existing_has_local_state =
+                    existing_state.write_status != WriteStatus::Remote || existing_state.pending_upload_hook.is_some();
+                if remote.kind == existing_inode.kind()
+                    && !existing_has_local_state
+                    && existing_state.stat.etag == remote.stat.etag
+                {
+                    trace!(parent=?existing_inode.parent(), name=?existing_inode.name(), ino=?existing_inode.ino(), "updating inode in place");
+                    existing_state.stat = remote.stat.clone();
+                    Ok(Some(LookedUpInode {
+                        inode: existing_inode.clone(),
+                        stat: remote.stat.clone(),
+                        path: s3_path.clone(),
+                        write_status: existing_state.write_status,
+                    }))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Update or create the inode for the given name in the parent directory with a write lock on
+    /// the parent. This method still needs to handle the cases handled by [try_update_fast_path]
+    /// because an intervening writer might have modified the inode we're updating.
+    fn update_slow_path(
+        &self,
+        parent: Inode,
+        name: ValidName,
+        remote: Option<RemoteLookup>,
+    ) -> Result<LookedUpInode, InodeError> {
+        let mut parent_state = parent.get_mut_inode_state()?;
+        let inode = match &parent_state.kind_data {
+            InodeKindData::File { .. } => unreachable!("we know parent is a directory"),
+            InodeKindData::Directory { children, .. } => children.get(name.as_ref()).cloned(),
+        };
+        match (remote, inode) {
+            (None, None) => Err(InodeError::FileDoesNotExist(name.to_string(), parent.err())),
+            (None, Some(existing_inode)) => {
+                let InodeKindData::Directory {
+                    children,
+                    writing_children,
+                    ..
+                } = &mut parent_state.kind_data
+                else {
+                    unreachable!("we know parent is a directory");
+                };
+                if writing_children.contains(&existing_inode.ino()) {
+                    let mut sync = existing_inode.get_mut_inode_state()?;
+
+                    let validity = self
+                        .config
+                        .cache_config
+                        .ttl_for(existing_inode.kind(), existing_inode.key());
+                    sync.stat.update_validity(validity);
+                    let stat = sync.stat.clone();
+                    let write_status = sync.write_status;
+                    drop(sync);
+
+                    Ok(LookedUpInode {
+                        inode: existing_inode,
+                        stat,
+                        path: self.s3_path.clone(),
+                        write_status,
+                    })
+                } else {
+                    // This existing inode is local-only (because `remote` is None), but is not
+                    // being written. It must have previously existed but been removed on the remote
+                    // side.
+                    children.remove(name.as_ref());
+                    self.send_stale_entry(&parent, &name, &existing_inode);
+                    Err(InodeError::FileDoesNotExist(name.to_string(), parent.err()))
                 }
             }
             (Some(remote), None) => {
@@ -1825,6 +3415,9 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
                     ino=?existing_inode.ino(),
                     "inode needs to be recreated",
                 );
+                if !existing_has_local_state {
+                    self.send_stale_entry(&parent, &name, &existing_inode);
+                }
                 let write_status = WriteStatus::Remote;
                 let state = InodeState::new(&remote.stat, remote.kind, write_status);
                 let new_inode =
@@ -1856,7 +3449,11 @@ impl<OC: ObjectClient + Send + Sync> SuperblockInner<OC> {
             .valid_key()
             .new_child(name, kind)
             .map_err(|_| InodeError::NotADirectory(parent.err()))?;
-        let next_ino = self.next_ino.fetch_add(1, Ordering::SeqCst);
+        let new_ino = || self.next_ino.fetch_add(1, Ordering::SeqCst);
+        let next_ino = match &self.config.inode_table {
+            Some(inode_table) => inode_table.assign(key.as_ref(), new_ino),
+            None => new_ino(),
+        };
         let inode = Inode::new(next_ino, parent.ino(), key, &self.s3_path.prefix, state);
         trace!(parent=?inode.parent(), name=?inode.name(), kind=?inode.kind(), new_ino=?inode.ino(), key=?inode.key(), "created new inode");
 
@@ -2027,6 +3624,7 @@ pub enum InodeMapError {
 
 #[cfg(test)]
 mod tests {
+    use futures::executor::ThreadPool;
     use mountpoint_s3_client::{
         mock_client::{MockClient, MockObject, Operation},
         types::ETag,
@@ -2036,6 +3634,7 @@ mod tests {
     use test_case::test_case;
     use time::{Duration, OffsetDateTime};
 
+    use crate::Runtime;
     use crate::fs::{FUSE_ROOT_INODE, TimeToLive, ToErrno};
     use crate::metablock::AddDirEntryResult;
     use crate::s3::{Bucket, Prefix};
@@ -2223,6 +3822,31 @@ mod tests {
             SuperblockConfig {
                 cache_config: CacheConfig::new(TimeToLive::Duration(ttl)),
                 s3_personality: S3Personality::Standard,
+                inode_table: None,
+                readdir_readahead: None,
+                inventory: None,
+                snapshot: None,
+                version_directories: false,
+                key_delimiter: Default::default(),
+                directory_markers: false,
+                escape_names: false,
+                shadowing: Default::default(),
+                archived_objects: Default::default(),
+                case_insensitive: false,
+                key_filter: Default::default(),
+                recent_writes_window: Default::default(),
+                rename_by_copy: Default::default(),
+                directory_rename: Default::default(),
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                symlinks: false,
+                decode_content_encoding: false,
+                decompress_files: false,
+                mtime_metadata: false,
+                mode_metadata: false,
             },
         );
 
@@ -2248,37 +3872,137 @@ mod tests {
         }
     }
 
-    #[test_case(true; "cached")]
-    #[test_case(false; "not cached")]
     #[tokio::test]
-    async fn test_negative_lookup_with_caching(cached: bool) {
+    async fn test_lookup_with_ttl_rules() {
         let bucket = Bucket::new("test_bucket").unwrap();
-        let prefix = "prefix/";
         let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let keys = ["static/file0.txt", "static/sdir0/file0.txt", "live/file0.txt"];
+        for key in keys {
+            client.add_object(key, MockObject::constant(0xaa, 30, ETag::for_tests()));
+        }
 
-        let prefix = Prefix::new(prefix).expect("valid prefix");
-        let ttl = if cached {
-            std::time::Duration::from_secs(60 * 60 * 24 * 7) // 7 days should be enough
-        } else {
-            std::time::Duration::ZERO
-        };
+        let ttl_rules = vec!["static/**=1h".parse().unwrap()];
         let superblock = Superblock::new(
             client.clone(),
-            S3Path::new(bucket, prefix.clone()),
+            S3Path::new(bucket, Default::default()),
             SuperblockConfig {
-                cache_config: CacheConfig::new(TimeToLive::Duration(ttl)),
+                cache_config: CacheConfig::new(TimeToLive::Minimal).with_ttl_rules(ttl_rules),
                 s3_personality: S3Personality::Standard,
+                inode_table: None,
+                readdir_readahead: None,
+                inventory: None,
+                snapshot: None,
+                version_directories: false,
+                key_delimiter: Default::default(),
+                directory_markers: false,
+                escape_names: false,
+                shadowing: Default::default(),
+                archived_objects: Default::default(),
+                case_insensitive: false,
+                key_filter: Default::default(),
+                recent_writes_window: Default::default(),
+                rename_by_copy: Default::default(),
+                directory_rename: Default::default(),
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                symlinks: false,
+                decode_content_encoding: false,
+                decompress_files: false,
+                mtime_metadata: false,
+                mode_metadata: false,
             },
         );
 
-        let entries = ["file0.txt", "sdir0"];
-        for entry in entries {
-            _ = superblock
-                .lookup(FUSE_ROOT_INODE, entry.as_ref())
-                .await
-                .expect_err("should not exist");
+        let mut inodes = Vec::new();
+        for dir in ["static", "live"] {
+            let dir = superblock.lookup(FUSE_ROOT_INODE, dir.as_ref()).await.unwrap();
+            let file = superblock.lookup(dir.ino(), "file0.txt".as_ref()).await.unwrap();
+            inodes.push((dir.ino(), file.ino()));
         }
-
+        let static_sdir = superblock.lookup(inodes[0].0, "sdir0".as_ref()).await.unwrap();
+        superblock
+            .lookup(static_sdir.ino(), "file0.txt".as_ref())
+            .await
+            .unwrap();
+
+        for key in keys {
+            client.remove_object(key);
+        }
+
+        let (static_dir, live_dir) = (inodes[0].0, inodes[1].0);
+        superblock
+            .lookup(static_dir, "file0.txt".as_ref())
+            .await
+            .expect("files matching a rule should be served from cache");
+        superblock
+            .lookup(static_sdir.ino(), "file0.txt".as_ref())
+            .await
+            .expect("nested files matching a rule should be served from cache");
+        superblock
+            .lookup(live_dir, "file0.txt".as_ref())
+            .await
+            .expect_err("other files should be revalidated");
+    }
+
+    #[test_case(true; "cached")]
+    #[test_case(false; "not cached")]
+    #[tokio::test]
+    async fn test_negative_lookup_with_caching(cached: bool) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let prefix = "prefix/";
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+
+        let prefix = Prefix::new(prefix).expect("valid prefix");
+        let ttl = if cached {
+            std::time::Duration::from_secs(60 * 60 * 24 * 7) // 7 days should be enough
+        } else {
+            std::time::Duration::ZERO
+        };
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, prefix.clone()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(ttl)),
+                s3_personality: S3Personality::Standard,
+                inode_table: None,
+                readdir_readahead: None,
+                inventory: None,
+                snapshot: None,
+                version_directories: false,
+                key_delimiter: Default::default(),
+                directory_markers: false,
+                escape_names: false,
+                shadowing: Default::default(),
+                archived_objects: Default::default(),
+                case_insensitive: false,
+                key_filter: Default::default(),
+                recent_writes_window: Default::default(),
+                rename_by_copy: Default::default(),
+                directory_rename: Default::default(),
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                symlinks: false,
+                decode_content_encoding: false,
+                decompress_files: false,
+                mtime_metadata: false,
+                mode_metadata: false,
+            },
+        );
+
+        let entries = ["file0.txt", "sdir0"];
+        for entry in entries {
+            _ = superblock
+                .lookup(FUSE_ROOT_INODE, entry.as_ref())
+                .await
+                .expect_err("should not exist");
+        }
+
         let keys = &[
             format!("{prefix}file0.txt"),
             format!("{prefix}sdir0/file0.txt"),
@@ -2305,236 +4029,1401 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_getattr_with_inode_local_invalid_stat_force_revalidate() {
-        let (superblock, client) = setup_test_superblock();
-        let head_object_counter = client.new_counter(Operation::HeadObject);
-        let inode = create_test_inode(&superblock, WriteStatus::LocalUnopened, true, None, true).unwrap();
+    async fn test_inode_table() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        for key in ["file0.txt", "dir/file1.txt"] {
+            client.add_object(key, MockObject::constant(0xaa, 30, ETag::for_tests()));
+        }
+        let directory = tempfile::tempdir().unwrap();
+        let s3_path = S3Path::new(bucket, Default::default());
+
+        let mount = || {
+            let inode_table = InodeTable::open(&directory.path().join("inodes"), &s3_path).unwrap();
+            Superblock::new(
+                client.clone(),
+                s3_path.clone(),
+                SuperblockConfig {
+                    inode_table: Some(inode_table),
+                    ..Default::default()
+                },
+            )
+        };
+        let lookup = async |superblock: &Superblock<_>, path: &str| {
+            let mut ino = FUSE_ROOT_INODE;
+            for name in path.split('/') {
+                ino = superblock.lookup(ino, name.as_ref()).await.unwrap().ino();
+            }
+            ino
+        };
 
-        let result = superblock.getattr_with_inode(inode.ino(), true).await;
-        assert!(result.is_ok());
-        let looked_up = result.unwrap();
-        assert_eq!(looked_up.write_status, WriteStatus::LocalUnopened);
-        assert_eq!(looked_up.inode.ino(), inode.ino());
-        assert_eq!(looked_up.stat.etag, None);
-        assert_eq!(head_object_counter.count(), 0);
+        let superblock = mount();
+        let file0 = lookup(&superblock, "file0.txt").await;
+        let file1 = lookup(&superblock, "dir/file1.txt").await;
+        drop(superblock);
+
+        let superblock = mount();
+        assert_eq!(lookup(&superblock, "dir/file1.txt").await, file1);
+        assert_eq!(lookup(&superblock, "file0.txt").await, file0);
+        let file2 = superblock
+            .create(FUSE_ROOT_INODE, "file2.txt".as_ref(), InodeKind::File)
+            .await
+            .unwrap()
+            .ino();
+        assert!(file2 > file0.max(file1), "new keys get new numbers");
+
+        superblock.forget(file0, 1).await;
+        assert_eq!(
+            lookup(&superblock, "file0.txt").await,
+            file0,
+            "forgotten inodes get their number again"
+        );
     }
 
     #[tokio::test]
-    async fn test_getattr_with_inode_remote_valid_stat_no_force_revalidate() {
-        let (superblock, client) = setup_test_superblock();
-        let test_etag = ETag::for_tests();
+    async fn test_negative_lookup_with_own_ttl() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("file0.txt", MockObject::constant(0xaa, 30, ETag::for_tests()));
         let head_object_counter = client.new_counter(Operation::HeadObject);
-        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(test_etag.clone()), false).unwrap();
 
-        let result = superblock.getattr_with_inode(inode.ino(), false).await;
-        assert!(result.is_ok());
-        let looked_up = result.unwrap();
-        assert_eq!(looked_up.write_status, WriteStatus::Remote);
-        assert_eq!(looked_up.inode.ino(), inode.ino());
-        assert_eq!(looked_up.stat.etag, Some(test_etag.into_inner().into_boxed_str()));
-        assert_eq!(head_object_counter.count(), 0);
+        let ttl = std::time::Duration::from_secs(60 * 60 * 24 * 7);
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Minimal)
+                    .with_negative_metadata_ttl(TimeToLive::Duration(ttl)),
+                s3_personality: S3Personality::Standard,
+                inode_table: None,
+                readdir_readahead: None,
+                inventory: None,
+                snapshot: None,
+                version_directories: false,
+                key_delimiter: Default::default(),
+                directory_markers: false,
+                escape_names: false,
+                shadowing: Default::default(),
+                archived_objects: Default::default(),
+                case_insensitive: false,
+                key_filter: Default::default(),
+                recent_writes_window: Default::default(),
+                rename_by_copy: Default::default(),
+                directory_rename: Default::default(),
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                symlinks: false,
+                decode_content_encoding: false,
+                decompress_files: false,
+                mtime_metadata: false,
+                mode_metadata: false,
+            },
+        );
+
+        for _ in 0..3 {
+            superblock
+                .lookup(FUSE_ROOT_INODE, "_SUCCESS".as_ref())
+                .await
+                .expect_err("should not exist");
+        }
+        assert_eq!(
+            head_object_counter.count(),
+            1,
+            "negative lookups should be served from cache"
+        );
+
+        superblock.lookup(FUSE_ROOT_INODE, "file0.txt".as_ref()).await.unwrap();
+        client.remove_object("file0.txt");
+        superblock
+            .lookup(FUSE_ROOT_INODE, "file0.txt".as_ref())
+            .await
+            .expect_err("positive lookups should still be revalidated");
     }
 
     #[tokio::test]
-    async fn test_getattr_with_inode_remote_force_revalidate() {
-        let (superblock, client) = setup_test_superblock();
-        let test_etag = ETag::for_tests();
-        let head_object_counter = client.new_counter(Operation::HeadObject);
-        client.add_object("prefix/test inode", MockObject::constant(0xaa, 1024, test_etag.clone()));
-        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(test_etag.clone()), false).unwrap();
+    async fn test_invalidate() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object(
+            "prefix/dir/file0.txt",
+            MockObject::constant(0xaa, 30, ETag::for_tests()),
+        );
 
-        let result = superblock.getattr_with_inode(inode.ino(), true).await;
-        assert!(result.is_ok());
-        let looked_up = result.unwrap();
-        assert_eq!(looked_up.write_status, WriteStatus::Remote);
-        assert_eq!(looked_up.inode.ino(), inode.ino());
-        assert_eq!(looked_up.stat.etag, Some(test_etag.into_inner().into_boxed_str()));
-        assert!(head_object_counter.count() > 0);
+        let ttl = std::time::Duration::from_secs(60 * 60 * 24 * 7);
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Prefix::new("prefix/").unwrap()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(ttl)),
+                ..Default::default()
+            },
+        );
+
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+        let file0 = superblock.lookup(dir, "file0.txt".as_ref()).await.unwrap().ino();
+        superblock
+            .lookup(dir, "file1.txt".as_ref())
+            .await
+            .expect_err("should not exist");
+
+        client.remove_object("prefix/dir/file0.txt");
+        client.add_object(
+            "prefix/dir/file1.txt",
+            MockObject::constant(0xbb, 10, ETag::for_tests()),
+        );
+        superblock
+            .lookup(dir, "file0.txt".as_ref())
+            .await
+            .expect("removed object should still be served from cache");
+
+        assert!(superblock.invalidate("other/dir/file0.txt").await.is_empty());
+        assert_eq!(
+            superblock.invalidate("prefix/dir/file0.txt").await,
+            Invalidations {
+                entries: vec![(FUSE_ROOT_INODE, "dir".into()), (dir, "file0.txt".into())],
+                inodes: vec![dir, file0],
+            }
+        );
+        assert_eq!(
+            superblock.invalidate("prefix/dir/file1.txt").await,
+            Invalidations {
+                entries: vec![(FUSE_ROOT_INODE, "dir".into()), (dir, "file1.txt".into())],
+                inodes: vec![dir],
+            }
+        );
+
+        superblock
+            .lookup(dir, "file0.txt".as_ref())
+            .await
+            .expect_err("removed object should have been looked up in S3");
+        let file1 = superblock.lookup(dir, "file1.txt".as_ref()).await.unwrap();
+        assert_eq!(file1.stat().size, 10);
     }
 
     #[tokio::test]
-    async fn test_getattr_with_stale_inode_remote_no_force_revalidate() {
-        let (superblock, client) = setup_test_superblock();
-        let head_object_counter = client.new_counter(Operation::HeadObject);
-        // Create the object with a different e-tag than which the inode is created with
-        client.add_object("prefix/test inode", MockObject::constant(0xaa, 1024, ETag::for_tests()));
-        let local_etag = ETag::from_str("Stale local etag").unwrap();
-        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(local_etag), true).unwrap();
+    async fn test_send_stale_entries() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("dir/file0.txt", MockObject::constant(0xaa, 30, ETag::for_tests()));
 
-        let result = superblock.getattr_with_inode(inode.ino(), false).await;
-        if let Err(InodeError::StaleInode {
-            remote_key,
-            old_inode,
-            new_inode,
-        }) = result
-        {
-            assert_eq!(remote_key, "prefix/test inode");
-            assert_ne!(old_inode.ino, new_inode.ino);
-            assert!(head_object_counter.count() > 0);
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(std::time::Duration::ZERO)),
+                ..Default::default()
+            },
+        );
+        let (sender, receiver) = async_channel::unbounded();
+        superblock.send_stale_entries(sender);
+
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+        let file0 = superblock.lookup(dir, "file0.txt".as_ref()).await.unwrap().ino();
+        assert_eq!(superblock.lookup(dir, "file0.txt".as_ref()).await.unwrap().ino(), file0);
+        assert!(receiver.try_recv().is_err(), "unchanged objects are not stale");
+
+        client.add_object(
+            "dir/file0.txt",
+            MockObject::constant(0xbb, 10, ETag::from_str("changed").unwrap()),
+        );
+        let changed = superblock.lookup(dir, "file0.txt".as_ref()).await.unwrap().ino();
+        assert_ne!(changed, file0);
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Invalidations {
+                entries: vec![(dir, "file0.txt".into())],
+                inodes: vec![file0],
+            }
+        );
+
+        client.remove_object("dir/file0.txt");
+        superblock
+            .lookup(dir, "file0.txt".as_ref())
+            .await
+            .expect_err("removed object should not be found");
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Invalidations {
+                entries: vec![(dir, "file0.txt".into())],
+                inodes: vec![changed],
+            }
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rename_directory_disabled() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("dir/a.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            Default::default(),
+        );
+        superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+
+        let result = superblock
+            .rename(FUSE_ROOT_INODE, "dir".as_ref(), FUSE_ROOT_INODE, "new".as_ref(), false)
+            .await;
+        assert!(matches!(result, Err(InodeError::CannotRenameDirectory(_))));
+        assert!(client.contains_key("dir/a.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_rename_by_copy() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(32)
+                .enable_rename(false)
+                .build(),
+        );
+        client.add_object("dir/a.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object("dir/b.txt", MockObject::constant(0xbb, 20, ETag::for_tests()));
+        let mut infrequent = MockObject::constant(0xcc, 30, ETag::for_tests());
+        infrequent.set_storage_class(Some("STANDARD_IA".to_owned()));
+        client.add_object("dir/infrequent.txt", infrequent);
+
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                rename_by_copy: true,
+                ..Default::default()
+            },
+        );
+        let rename_counter = client.new_counter(Operation::RenameObject);
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+
+        superblock
+            .rename(dir, "a.txt".as_ref(), dir, "c.txt".as_ref(), false)
+            .await
+            .expect("rename should copy the object");
+        assert!(!client.contains_key("dir/a.txt"));
+        assert!(client.contains_key("dir/c.txt"));
+        let lookup = superblock.lookup(dir, "c.txt".as_ref()).await.unwrap();
+        assert_eq!(lookup.stat().size, 10);
+
+        let result = superblock
+            .rename(dir, "c.txt".as_ref(), dir, "b.txt".as_ref(), false)
+            .await;
+        assert!(matches!(result, Err(InodeError::RenameDestinationExists { .. })));
+        assert!(client.contains_key("dir/c.txt"));
+
+        superblock
+            .rename(dir, "c.txt".as_ref(), dir, "b.txt".as_ref(), true)
+            .await
+            .expect("rename should overwrite the destination");
+        assert!(!client.contains_key("dir/c.txt"));
+        let lookup = superblock.lookup(dir, "b.txt".as_ref()).await.unwrap();
+        assert_eq!(lookup.stat().size, 10);
+
+        // The copy keeps the storage class of its source.
+        superblock
+            .rename(dir, "infrequent.txt".as_ref(), dir, "moved.txt".as_ref(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.get_object_storage_class("dir/moved.txt").unwrap().as_deref(),
+            Some("STANDARD_IA")
+        );
+
+        // RenameObject is only tried once, and not supported.
+        assert_eq!(rename_counter.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rename_by_copy_no_replace() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(32)
+                .enable_rename(false)
+                .build(),
+        );
+        client.add_object("dir/a.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                rename_by_copy: true,
+                cache_config: CacheConfig {
+                    serve_lookup_from_cache: true,
+                    use_negative_cache: true,
+                    negative_cache_ttl: std::time::Duration::from_secs(60),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+
+        // Another client commits the destination after it was looked up, so the rename only finds out when copying.
+        let result = superblock.lookup(dir, "committed.txt".as_ref()).await;
+        assert!(matches!(result, Err(InodeError::FileDoesNotExist(..))));
+        client.add_object("dir/committed.txt", MockObject::constant(0xbb, 20, ETag::for_tests()));
+        let result = superblock
+            .rename(dir, "a.txt".as_ref(), dir, "committed.txt".as_ref(), false)
+            .await;
+        assert!(matches!(result, Err(InodeError::RenameDestinationExists { .. })));
+        assert!(client.contains_key("dir/a.txt"));
+        let head = client
+            .head_object("test_bucket", "dir/committed.txt", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, 20);
+    }
+
+    #[test_case(true; "with RenameObject")]
+    #[test_case(false; "by copy")]
+    #[tokio::test]
+    async fn test_rename_directory(rename_supported: bool) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(32)
+                .enable_rename(rename_supported)
+                .build(),
+        );
+        for key in [
+            "_temporary/0/a.txt",
+            "_temporary/0/sub/b.txt",
+            "_temporary/1/c.txt",
+            "output/_SUCCESS",
+        ] {
+            client.add_object(key, MockObject::constant(0xaa, key.len(), ETag::for_tests()));
+        }
+
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                rename_by_copy: true,
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                directory_rename: Some(DirectoryRenameConfig {
+                    max_objects: 2,
+                    concurrency: 2,
+                }),
+                ..Default::default()
+            },
+        );
+        let delete_counter = client.new_counter(Operation::DeleteObject);
+        let temporary = superblock
+            .lookup(FUSE_ROOT_INODE, "_temporary".as_ref())
+            .await
+            .unwrap()
+            .ino();
+        let output = superblock
+            .lookup(FUSE_ROOT_INODE, "output".as_ref())
+            .await
+            .unwrap()
+            .ino();
+
+        superblock
+            .rename(temporary, "0".as_ref(), output, "part-0".as_ref(), false)
+            .await
+            .expect("rename should move every object of the directory");
+        for key in ["_temporary/0/a.txt", "_temporary/0/sub/b.txt"] {
+            assert!(!client.contains_key(key), "{key} should be removed");
+        }
+        for key in ["output/part-0/a.txt", "output/part-0/sub/b.txt", "_temporary/1/c.txt"] {
+            assert!(client.contains_key(key), "{key} should exist");
+        }
+        // The renamed directory keeps its inode number, and its entries are under its new key.
+        let part = superblock.lookup(output, "part-0".as_ref()).await.unwrap();
+        assert_eq!(
+            part.ino(),
+            superblock.lookup(output, "part-0".as_ref()).await.unwrap().ino()
+        );
+        let sub = superblock.lookup(part.ino(), "sub".as_ref()).await.unwrap();
+        let file = superblock.lookup(sub.ino(), "b.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, "_temporary/0/sub/b.txt".len());
+        let result = superblock.lookup(temporary, "0".as_ref()).await;
+        assert!(matches!(result, Err(InodeError::FileDoesNotExist(_, _))));
+        // The objects are only deleted when they were copied.
+        assert_eq!(delete_counter.count(), if rename_supported { 0 } else { 2 });
+
+        // Existing destinations are not replaced without overwrites, and files never replaced by directories.
+        let result = superblock
+            .rename(temporary, "1".as_ref(), FUSE_ROOT_INODE, "output".as_ref(), false)
+            .await;
+        assert!(matches!(result, Err(InodeError::RenameDestinationExists { .. })));
+        let result = superblock
+            .rename(temporary, "1".as_ref(), output, "_SUCCESS".as_ref(), true)
+            .await;
+        assert!(matches!(result, Err(InodeError::NotADirectory(_))));
+        let result = superblock
+            .rename(temporary, "1".as_ref(), FUSE_ROOT_INODE, "output".as_ref(), true)
+            .await;
+        assert!(matches!(result, Err(InodeError::DirectoryNotEmpty(_))));
+
+        // Directories holding more objects than the limit are not renamed.
+        let result = superblock
+            .rename(
+                FUSE_ROOT_INODE,
+                "output".as_ref(),
+                FUSE_ROOT_INODE,
+                "final".as_ref(),
+                false,
+            )
+            .await;
+        assert!(matches!(result, Err(InodeError::DirectoryTooLargeToRename { .. })));
+        assert!(client.contains_key("output/_SUCCESS"));
+    }
+
+    #[tokio::test]
+    async fn test_recursive_delete() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("dir/a.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object("dir/sub/b.txt", MockObject::constant(0xbb, 20, ETag::for_tests()));
+        client.add_object("dir/sub/c/d.txt", MockObject::constant(0xcc, 30, ETag::for_tests()));
+        client.add_object("dir.txt", MockObject::constant(0xdd, 40, ETag::for_tests()));
+        for i in 0..1200 {
+            client.add_object(&format!("large/{i}"), MockObject::constant(0xee, 1, ETag::for_tests()));
+        }
+        for i in 0..1600 {
+            client.add_object(
+                &format!("too_large/{i}"),
+                MockObject::constant(0xff, 1, ETag::for_tests()),
+            );
+        }
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                recursive_delete: Some(RecursiveDeleteConfig { max_objects: 1500 }),
+                ..Default::default()
+            },
+        );
+        let delete_counter = client.new_counter(Operation::DeleteObjects);
+
+        superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        superblock
+            .rmdir(FUSE_ROOT_INODE, "dir".as_ref())
+            .await
+            .expect("rmdir should delete the objects under the directory");
+        assert_eq!(delete_counter.count(), 1);
+        for key in ["dir/a.txt", "dir/sub/b.txt", "dir/sub/c/d.txt"] {
+            assert!(!client.contains_key(key));
+        }
+        assert!(client.contains_key("dir.txt"));
+        superblock
+            .lookup(FUSE_ROOT_INODE, "dir".as_ref())
+            .await
+            .expect_err("removed directory should not be found");
+
+        // The objects are deleted in batches of at most 1000 keys.
+        superblock.lookup(FUSE_ROOT_INODE, "large".as_ref()).await.unwrap();
+        superblock.rmdir(FUSE_ROOT_INODE, "large".as_ref()).await.unwrap();
+        assert_eq!(delete_counter.count(), 3);
+        assert_eq!(client.object_count(), 1601);
+
+        // Directories holding more objects than the limit are not removed.
+        superblock.lookup(FUSE_ROOT_INODE, "too_large".as_ref()).await.unwrap();
+        let result = superblock.rmdir(FUSE_ROOT_INODE, "too_large".as_ref()).await;
+        assert!(matches!(result, Err(InodeError::DirectoryTooLargeToDelete { .. })));
+        assert_eq!(client.object_count(), 1601);
+        assert_eq!(delete_counter.count(), 3);
+    }
+
+    #[test_case(true; "with recent writes")]
+    #[test_case(false; "without recent writes")]
+    #[tokio::test]
+    async fn test_recent_writes(recent_writes: bool) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("other.txt", MockObject::constant(0xaa, 30, ETag::for_tests()));
+
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(std::time::Duration::ZERO)),
+                recent_writes_window: if recent_writes {
+                    std::time::Duration::from_secs(60)
+                } else {
+                    std::time::Duration::ZERO
+                },
+                ..Default::default()
+            },
+        );
+
+        // The object of the file is never added to the mock client, as if S3 didn't return it yet.
+        let dir = superblock
+            .create(FUSE_ROOT_INODE, "dir".as_ref(), InodeKind::Directory)
+            .await
+            .unwrap();
+        let file = superblock
+            .create(dir.ino(), "new.txt".as_ref(), InodeKind::File)
+            .await
+            .unwrap();
+        superblock
+            .open_handle(file.ino(), 0, &Default::default(), OpenFlags::O_WRONLY)
+            .await
+            .unwrap();
+        superblock
+            .finish_writing(file.ino(), Some(ETag::for_tests()), 0)
+            .await
+            .unwrap();
+
+        let root_entries = collect_dir_entries(&superblock, FUSE_ROOT_INODE, false, 1).await;
+        let lookup = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await;
+        if !recent_writes {
+            assert_eq!(root_entries, ["other.txt"]);
+            assert!(matches!(lookup, Err(InodeError::FileDoesNotExist(_, _))));
+            return;
+        }
+        assert_eq!(root_entries, ["dir", "other.txt"]);
+        assert_eq!(lookup.unwrap().ino(), dir.ino());
+        assert_eq!(collect_dir_entries(&superblock, dir.ino(), false, 1).await, ["new.txt"]);
+        let lookup = superblock.lookup(dir.ino(), "new.txt".as_ref()).await.unwrap();
+        assert_eq!(lookup.ino(), file.ino());
+
+        // Once S3 returns the object, it is listed once.
+        client.add_object("dir/new.txt", MockObject::constant(0xaa, 0, ETag::for_tests()));
+        assert_eq!(collect_dir_entries(&superblock, dir.ino(), false, 1).await, ["new.txt"]);
+
+        superblock.unlink(dir.ino(), "new.txt".as_ref()).await.unwrap();
+        assert!(matches!(
+            superblock.lookup(dir.ino(), "new.txt".as_ref()).await,
+            Err(InodeError::FileDoesNotExist(_, _))
+        ));
+        let root_entries = collect_dir_entries(&superblock, FUSE_ROOT_INODE, false, 1).await;
+        assert_eq!(root_entries, ["other.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_getattr_with_inode_local_invalid_stat_force_revalidate() {
+        let (superblock, client) = setup_test_superblock();
+        let head_object_counter = client.new_counter(Operation::HeadObject);
+        let inode = create_test_inode(&superblock, WriteStatus::LocalUnopened, true, None, true).unwrap();
+
+        let result = superblock.getattr_with_inode(inode.ino(), true).await;
+        assert!(result.is_ok());
+        let looked_up = result.unwrap();
+        assert_eq!(looked_up.write_status, WriteStatus::LocalUnopened);
+        assert_eq!(looked_up.inode.ino(), inode.ino());
+        assert_eq!(looked_up.stat.etag, None);
+        assert_eq!(head_object_counter.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_getattr_with_inode_remote_valid_stat_no_force_revalidate() {
+        let (superblock, client) = setup_test_superblock();
+        let test_etag = ETag::for_tests();
+        let head_object_counter = client.new_counter(Operation::HeadObject);
+        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(test_etag.clone()), false).unwrap();
+
+        let result = superblock.getattr_with_inode(inode.ino(), false).await;
+        assert!(result.is_ok());
+        let looked_up = result.unwrap();
+        assert_eq!(looked_up.write_status, WriteStatus::Remote);
+        assert_eq!(looked_up.inode.ino(), inode.ino());
+        assert_eq!(looked_up.stat.etag, Some(test_etag.into_inner().into_boxed_str()));
+        assert_eq!(head_object_counter.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_getattr_with_inode_remote_force_revalidate() {
+        let (superblock, client) = setup_test_superblock();
+        let test_etag = ETag::for_tests();
+        let head_object_counter = client.new_counter(Operation::HeadObject);
+        client.add_object("prefix/test inode", MockObject::constant(0xaa, 1024, test_etag.clone()));
+        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(test_etag.clone()), false).unwrap();
+
+        let result = superblock.getattr_with_inode(inode.ino(), true).await;
+        assert!(result.is_ok());
+        let looked_up = result.unwrap();
+        assert_eq!(looked_up.write_status, WriteStatus::Remote);
+        assert_eq!(looked_up.inode.ino(), inode.ino());
+        assert_eq!(looked_up.stat.etag, Some(test_etag.into_inner().into_boxed_str()));
+        assert!(head_object_counter.count() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_getattr_with_stale_inode_remote_no_force_revalidate() {
+        let (superblock, client) = setup_test_superblock();
+        let head_object_counter = client.new_counter(Operation::HeadObject);
+        // Create the object with a different e-tag than which the inode is created with
+        client.add_object("prefix/test inode", MockObject::constant(0xaa, 1024, ETag::for_tests()));
+        let local_etag = ETag::from_str("Stale local etag").unwrap();
+        let inode = create_test_inode(&superblock, WriteStatus::Remote, false, Some(local_etag), true).unwrap();
+
+        let result = superblock.getattr_with_inode(inode.ino(), false).await;
+        if let Err(InodeError::StaleInode {
+            remote_key,
+            old_inode,
+            new_inode,
+        }) = result
+        {
+            assert_eq!(remote_key, "prefix/test inode");
+            assert_ne!(old_inode.ino, new_inode.ino);
+            assert!(head_object_counter.count() > 0);
+        } else {
+            panic!("Expected StaleInode error");
+        }
+    }
+
+    fn setup_test_superblock() -> (Superblock<Arc<MockClient>>, Arc<MockClient>) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let prefix = "prefix/";
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let prefix = Prefix::new(prefix).expect("valid prefix");
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, prefix.clone()),
+            SuperblockConfig {
+                cache_config: CacheConfig::new(TimeToLive::Duration(std::time::Duration::from_secs(24 * 60 * 60))),
+                s3_personality: S3Personality::Standard,
+                inode_table: None,
+                readdir_readahead: None,
+                inventory: None,
+                snapshot: None,
+                version_directories: false,
+                key_delimiter: Default::default(),
+                directory_markers: false,
+                escape_names: false,
+                shadowing: Default::default(),
+                archived_objects: Default::default(),
+                case_insensitive: false,
+                key_filter: Default::default(),
+                recent_writes_window: Default::default(),
+                rename_by_copy: Default::default(),
+                directory_rename: Default::default(),
+                recursive_delete: Default::default(),
+                batch_deletes: false,
+                trash: None,
+                atime_metadata: false,
+                prefix_overrides: Default::default(),
+                symlinks: false,
+                decode_content_encoding: false,
+                decompress_files: false,
+                mtime_metadata: false,
+                mode_metadata: false,
+            },
+        );
+        (superblock, client)
+    }
+
+    fn create_test_inode(
+        superblock: &Superblock<Arc<MockClient>>,
+        write_status: WriteStatus,
+        is_new_file: bool,
+        etag: Option<ETag>,
+        invalidate_stat: bool,
+    ) -> Result<Inode, InodeError> {
+        let parent_inode = superblock.inner.get(FUSE_ROOT_INODE).unwrap();
+        let etag = etag.map(|etag| etag.into_inner().into_boxed_str());
+
+        let mut stat = InodeStat::for_file(
+            1024,
+            OffsetDateTime::now_utc(),
+            etag,
+            None,
+            None,
+            std::time::Duration::from_secs(24 * 60 * 60),
+        );
+        if invalidate_stat {
+            // For testing the expired stat cases
+            stat.update_validity(std::time::Duration::from_secs(0));
+        }
+        let state = InodeState::new(&stat, InodeKind::File, write_status);
+
+        let mut parent_state = parent_inode.get_mut_inode_state()?;
+
+        let inode = superblock.inner.create_inode_locked(
+            &parent_inode,
+            &mut parent_state,
+            ValidName::parse_str("test inode")?,
+            InodeKind::File,
+            state,
+            is_new_file,
+        )?;
+        // Manually add the inode to the Superblock's inode map, done by lookup/create in reality
+        superblock.inner.remember(&inode);
+        Ok(inode)
+    }
+
+    #[test_case(""; "unprefixed")]
+    #[test_case("test_prefix/"; "prefixed")]
+    #[tokio::test]
+    async fn test_readdir(prefix: &str) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(
+            MockClient::config()
+                .bucket(bucket.to_string())
+                .part_size(1024 * 1024)
+                .build(),
+        );
+
+        let keys = &[
+            format!("{prefix}dir0/file0.txt"),
+            format!("{prefix}dir0/sdir0/file0.txt"),
+            format!("{prefix}dir0/sdir0/file1.txt"),
+            format!("{prefix}dir0/sdir0/file2.txt"),
+            format!("{prefix}dir0/sdir1/file0.txt"),
+            format!("{prefix}dir0/sdir1/file1.txt"),
+            format!("{prefix}dir1/sdir2/file0.txt"),
+            format!("{prefix}dir1/sdir2/file1.txt"),
+            format!("{prefix}dir1/sdir2/file2.txt"),
+            format!("{prefix}dir1/sdir3/file0.txt"),
+            format!("{prefix}dir1/sdir3/file1.txt"),
+        ];
+
+        let last_modified = OffsetDateTime::UNIX_EPOCH + Duration::days(30);
+        for key in keys {
+            let mut obj = MockObject::constant(0xaa, 30, ETag::for_tests());
+            obj.set_last_modified(last_modified);
+            client.add_object(key, obj);
+        }
+
+        let prefix = Prefix::new(prefix).expect("valid prefix");
+        let ts = OffsetDateTime::now_utc();
+        let superblock = Superblock::new(client.clone(), S3Path::new(bucket, prefix.clone()), Default::default());
+
+        // Try it all twice to test inode reuse
+        for _ in 0..2 {
+            // Step 1: Read root directory
+            let root_entries = collect_dir_entries_with_info(&superblock, FUSE_ROOT_INODE, true, 100).await;
+
+            assert_eq!(root_entries.len(), 2);
+            assert_eq!(
+                root_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
+                &["dir0", "dir1"]
+            );
+
+            let (dir0_entry, _) = &root_entries[0];
+            let (dir1_entry, _) = &root_entries[1];
+
+            // Check stats for root directory entries
+            assert_eq!(dir0_entry.kind(), InodeKind::Directory);
+            assert_eq!(dir0_entry.stat().mtime.date(), ts.date());
+            assert_eq!(dir0_entry.stat().size, 0);
+
+            assert_eq!(dir1_entry.kind(), InodeKind::Directory);
+            assert_eq!(dir1_entry.stat().mtime.date(), ts.date());
+            assert_eq!(dir1_entry.stat().size, 0);
+
+            // Step 2: Read dir0 directory
+            let dir0_ino = dir0_entry.ino();
+            let dir0_entries = collect_dir_entries_with_info(&superblock, dir0_ino, true, 100).await;
+
+            assert_eq!(dir0_entries.len(), 3);
+            assert_eq!(
+                dir0_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
+                &["file0.txt", "sdir0", "sdir1"]
+            );
+
+            let (file0_entry, _) = &dir0_entries[0];
+            let (sdir0_entry, _) = &dir0_entries[1];
+            let (sdir1_entry, _) = &dir0_entries[2];
+
+            // Check stats for dir0 entries
+            assert_eq!(file0_entry.kind(), InodeKind::File);
+            assert_eq!(file0_entry.stat().mtime.date(), last_modified.date());
+            assert_eq!(file0_entry.stat().size, 30);
+
+            assert_eq!(sdir0_entry.kind(), InodeKind::Directory);
+            assert_eq!(sdir0_entry.stat().mtime.date(), ts.date());
+            assert_eq!(sdir0_entry.stat().size, 0);
+
+            assert_eq!(sdir1_entry.kind(), InodeKind::Directory);
+            assert_eq!(sdir1_entry.stat().mtime.date(), ts.date());
+            assert_eq!(sdir1_entry.stat().size, 0);
+
+            // Step 3: Read sdir0 directory
+            let sdir0_ino = sdir0_entry.ino();
+            let sdir0_entries = collect_dir_entries_with_info(&superblock, sdir0_ino, true, 100).await;
+
+            assert_eq!(sdir0_entries.len(), 3);
+            assert_eq!(
+                sdir0_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
+                &["file0.txt", "file1.txt", "file2.txt"]
+            );
+
+            // Check stats for all files in sdir0
+            for (entry, _) in &sdir0_entries {
+                assert_eq!(entry.kind(), InodeKind::File);
+                assert_eq!(entry.stat().mtime.date(), last_modified.date());
+                assert_eq!(entry.stat().size, 30);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readdir_readahead() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let mut expected = Vec::new();
+        for i in 0..25 {
+            let name = format!("file{i:02}.txt");
+            client.add_object(
+                &format!("dir/{name}"),
+                MockObject::constant(0xaa, 30, ETag::for_tests()),
+            );
+            expected.push(OsString::from(name));
+        }
+        let list_counter = client.new_counter(Operation::ListObjectsV2);
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                readdir_readahead: Some(ReaddirReadahead::new(runtime, 2)),
+                ..Default::default()
+            },
+        );
+        let dir_ino = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+        let lookup_lists = list_counter.count();
+
+        // Two pages are queued, and the task waits to queue the third one.
+        superblock.new_readdir_handle_with_pagesize(dir_ino, 5).await.unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while list_counter.count() < lookup_lists + 3 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(list_counter.count(), lookup_lists + 3, "readahead is bounded");
+
+        let entries = collect_dir_entries(&superblock, dir_ino, false, 5).await;
+        assert_eq!(entries, expected);
+    }
+
+    #[tokio::test]
+    async fn test_inventory() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let mut rows = Vec::new();
+        for i in 0..10 {
+            let key = format!("dir/file{i}.txt");
+            client.add_object(&key, MockObject::constant(0xaa, 30, ETag::from_str("etag").unwrap()));
+            rows.push(format!(
+                r#""test_bucket","{key}","30","2024-01-01T00:00:00.000Z","etag","STANDARD""#
+            ));
+        }
+        let rows: Vec<_> = rows.iter().map(String::as_str).collect();
+        inventory::tests::add_report(
+            &client,
+            "inventory/",
+            "2024-01-01T01-00Z",
+            "Bucket, Key, Size, LastModifiedDate, ETag, StorageClass",
+            &rows,
+        );
+        // An object created since the report.
+        client.add_object("dir/new.txt", MockObject::constant(0xaa, 30, ETag::for_tests()));
+
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let location = S3Path::new(bucket.clone(), Prefix::new("inventory/").unwrap());
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                inventory: Some(InventoryConfig::new(
+                    location,
+                    std::time::Duration::from_secs(3600),
+                    runtime,
+                )),
+                ..Default::default()
+            },
+        );
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while superblock.inner.inventory.as_ref().unwrap().index().is_none() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let list_counter = client.new_counter(Operation::ListObjectsV2);
+        let head_counter = client.new_counter(Operation::HeadObject);
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 3).await;
+        let expected: Vec<_> = (0..10).map(|i| OsString::from(format!("file{i}.txt"))).collect();
+        assert_eq!(entries, expected, "the listing is served from the report");
+        let file = superblock.lookup(dir.ino(), "file3.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 30);
+        assert_eq!(list_counter.count(), 0);
+        assert_eq!(head_counter.count(), 0);
+
+        superblock
+            .lookup(dir.ino(), "new.txt".as_ref())
+            .await
+            .expect("keys missing from the report are looked up in S3");
+        assert_eq!(head_counter.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let time = |seconds| OffsetDateTime::from_unix_timestamp(seconds).unwrap();
+        let version = |size, version_id: &str, seconds| {
+            let mut object = MockObject::constant(0xaa, size, ETag::for_tests());
+            object.set_version_id(Some(version_id.to_owned()));
+            object.set_last_modified(time(seconds));
+            object
+        };
+        client.add_object("dir/file0.txt", version(20, "v2", 30));
+        client.add_object_version("dir/file0.txt", version(10, "v1", 10));
+        client.add_object("dir/file1.txt", version(30, "v3", 30));
+
+        let s3_path = S3Path::new(bucket, Default::default());
+        let snapshot = Snapshot::load(&client, &s3_path, time(20)).await.unwrap();
+        let superblock = Superblock::new(
+            client.clone(),
+            s3_path,
+            SuperblockConfig {
+                snapshot: Some(snapshot),
+                ..Default::default()
+            },
+        );
+
+        let list_counter = client.new_counter(Operation::ListObjectsV2);
+        let head_counter = client.new_counter(Operation::HeadObject);
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 3).await;
+        assert_eq!(
+            entries,
+            [OsString::from("file0.txt")],
+            "the listing is served from the snapshot"
+        );
+        let file = superblock.lookup(dir.ino(), "file0.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 10);
+        assert_eq!(file.stat().version_id.as_deref(), Some("v1"));
+        assert!(file.stat().pinned_version);
+
+        let err = superblock
+            .lookup(dir.ino(), "file1.txt".as_ref())
+            .await
+            .expect_err("keys missing from the snapshot are not looked up in S3");
+        assert_eq!(err.to_errno(), libc::ENOENT);
+        assert_eq!(list_counter.count(), 0);
+        assert_eq!(head_counter.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_key_delimiter() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("data|dir|file0.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object(
+            "data|dir|sub|file1.txt",
+            MockObject::constant(0xaa, 20, ETag::for_tests()),
+        );
+        client.add_object(
+            "data|dir|not/a/path.txt",
+            MockObject::constant(0xaa, 30, ETag::for_tests()),
+        );
+        client.add_object("data/dir/file2.txt", MockObject::constant(0xaa, 40, ETag::for_tests()));
+
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Prefix::new("data/").unwrap()),
+            SuperblockConfig {
+                key_delimiter: KeyDelimiter::new("|").unwrap(),
+                ..Default::default()
+            },
+        );
+
+        let entries = collect_dir_entries(&superblock, FUSE_ROOT_INODE, false, 2).await;
+        assert_eq!(entries, [OsString::from("dir")]);
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 2).await;
+        assert_eq!(
+            entries,
+            [OsString::from("file0.txt"), OsString::from("sub")],
+            "keys including a '/' are not listed"
+        );
+
+        let sub = superblock.lookup(dir.ino(), "sub".as_ref()).await.unwrap();
+        assert_eq!(sub.kind(), InodeKind::Directory);
+        let file = superblock.lookup(sub.ino(), "file1.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 20);
+
+        superblock.unlink(dir.ino(), "file0.txt".as_ref()).await.unwrap();
+        assert!(!client.contains_key("data|dir|file0.txt"));
+
+        assert!(
+            !superblock.invalidate("data|dir|sub|file1.txt").await.is_empty(),
+            "invalidated keys use the delimiter"
+        );
+    }
+
+    #[test_case(ShadowingPolicy::DirectoryWins, &["foo"], InodeKind::Directory; "directory wins")]
+    #[test_case(ShadowingPolicy::FileWins, &["foo"], InodeKind::File; "file wins")]
+    #[test_case(ShadowingPolicy::SuffixFile, &["foo", "foo.shadowed"], InodeKind::Directory; "suffix file")]
+    #[tokio::test]
+    async fn test_shadowing(shadowing: ShadowingPolicy, expected_entries: &[&str], expected_kind: InodeKind) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("dir/foo", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object("dir/foo/bar", MockObject::constant(0xaa, 20, ETag::for_tests()));
+
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                shadowing,
+                ..Default::default()
+            },
+        );
+
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 5).await;
+        assert_eq!(entries, expected_entries);
+
+        // Look up the names again, without the entries cached by the listing.
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(Bucket::new("test_bucket").unwrap(), Default::default()),
+            SuperblockConfig {
+                shadowing,
+                ..Default::default()
+            },
+        );
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let foo = superblock.lookup(dir.ino(), "foo".as_ref()).await.unwrap();
+        assert_eq!(foo.kind(), expected_kind);
+        let shadowed = superblock.lookup(dir.ino(), "foo.shadowed".as_ref()).await;
+        if shadowing == ShadowingPolicy::SuffixFile {
+            let shadowed = shadowed.unwrap();
+            assert_eq!(shadowed.kind(), InodeKind::File);
+            assert_eq!(shadowed.stat().size, 10);
+            assert!(shadowed.stat().shadowed);
+            superblock.unlink(dir.ino(), "foo.shadowed".as_ref()).await.unwrap();
+            assert!(!client.contains_key("dir/foo"), "the shadowed key should be deleted");
+        } else {
+            assert!(matches!(shadowed, Err(InodeError::FileDoesNotExist(_, _))));
+        }
+    }
+
+    #[test_case(true; "decompressed")]
+    #[test_case(false; "as stored")]
+    #[tokio::test]
+    async fn test_decompress_files(decompress_files: bool) {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let content = b"hello world\n".repeat(100);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &content).unwrap();
+        let gzip = encoder.finish().unwrap();
+        let zstd = zstd::bulk::compress(&content, 3).unwrap();
+        client.add_object("dir/a.csv.gz", MockObject::from(&gzip));
+        client.add_object("dir/b.jsonl.zst", MockObject::from(&zstd));
+        client.add_object("dir/c.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object("dir/c.txt.bak", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        // The decompressed content would have the name of `c.txt`, which is listed first, so it keeps its own name.
+        client.add_object("dir/c.txt.gz", MockObject::from(&gzip));
+
+        let new_superblock = || {
+            Superblock::new(
+                client.clone(),
+                S3Path::new(Bucket::new("test_bucket").unwrap(), Default::default()),
+                SuperblockConfig {
+                    decompress_files,
+                    ..Default::default()
+                },
+            )
+        };
+        let superblock = new_superblock();
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 5).await;
+        if decompress_files {
+            assert_eq!(entries, ["a.csv", "b.jsonl", "c.txt", "c.txt.bak", "c.txt.gz"]);
         } else {
-            panic!("Expected StaleInode error");
+            assert_eq!(entries, ["a.csv.gz", "b.jsonl.zst", "c.txt", "c.txt.bak", "c.txt.gz"]);
+        }
+
+        // Look up the names again, without the entries cached by the listing.
+        let superblock = new_superblock();
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let gzip_file = superblock.lookup(dir.ino(), "a.csv".as_ref()).await;
+        let zstd_file = superblock.lookup(dir.ino(), "b.jsonl".as_ref()).await;
+        let file = superblock.lookup(dir.ino(), "c.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 10, "the object with the name of the content wins");
+        assert_eq!(file.stat().decompressed, None);
+        let compressed = superblock.lookup(dir.ino(), "a.csv.gz".as_ref()).await.unwrap();
+        assert_eq!(
+            compressed.stat().size,
+            gzip.len(),
+            "compressed objects are found with their own name"
+        );
+        if decompress_files {
+            let gzip_file = gzip_file.unwrap();
+            assert_eq!(gzip_file.stat().size, content.len());
+            assert_eq!(gzip_file.stat().encoded_size, Some(gzip.len()));
+            assert_eq!(gzip_file.stat().decompressed, Some(Compression::Gzip));
+            let zstd_file = zstd_file.unwrap();
+            assert_eq!(zstd_file.stat().size, content.len());
+            assert_eq!(zstd_file.stat().encoded_size, Some(zstd.len()));
+            assert_eq!(zstd_file.stat().decompressed, Some(Compression::Zstd));
+
+            let err = superblock
+                .rename(dir.ino(), "a.csv".as_ref(), dir.ino(), "d.csv".as_ref(), true)
+                .await
+                .expect_err("decompressed files can't be renamed");
+            assert!(matches!(err, InodeError::InodeNotWritable(_)));
+            superblock.unlink(dir.ino(), "b.jsonl".as_ref()).await.unwrap();
+            assert!(
+                !client.contains_key("dir/b.jsonl.zst"),
+                "the compressed object should be deleted"
+            );
+        } else {
+            assert!(matches!(gzip_file, Err(InodeError::FileDoesNotExist(_, _))));
+            assert!(matches!(zstd_file, Err(InodeError::FileDoesNotExist(_, _))));
         }
     }
 
-    fn setup_test_superblock() -> (Superblock<Arc<MockClient>>, Arc<MockClient>) {
+    #[test_case(true; "case insensitive")]
+    #[test_case(false; "case sensitive")]
+    #[tokio::test]
+    async fn test_case_insensitive(case_insensitive: bool) {
         let bucket = Bucket::new("test_bucket").unwrap();
-        let prefix = "prefix/";
         let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
-        let prefix = Prefix::new(prefix).expect("valid prefix");
+        for key in [
+            "readme.txt",
+            "Docs/a.txt",
+            "collide.TXT",
+            "Collide.txt",
+            "Exact",
+            "EXACT",
+        ] {
+            client.add_object(
+                &format!("data/{key}"),
+                MockObject::constant(0xaa, 10, ETag::for_tests()),
+            );
+        }
+
         let superblock = Superblock::new(
             client.clone(),
-            S3Path::new(bucket, prefix.clone()),
+            S3Path::new(bucket, Prefix::new("data/").unwrap()),
             SuperblockConfig {
-                cache_config: CacheConfig::new(TimeToLive::Duration(std::time::Duration::from_secs(24 * 60 * 60))),
-                s3_personality: S3Personality::Standard,
+                case_insensitive,
+                ..Default::default()
             },
         );
-        (superblock, client)
-    }
-
-    fn create_test_inode(
-        superblock: &Superblock<Arc<MockClient>>,
-        write_status: WriteStatus,
-        is_new_file: bool,
-        etag: Option<ETag>,
-        invalidate_stat: bool,
-    ) -> Result<Inode, InodeError> {
-        let parent_inode = superblock.inner.get(FUSE_ROOT_INODE).unwrap();
-        let etag = etag.map(|etag| etag.into_inner().into_boxed_str());
+        let lookup_key = async |name: &str| {
+            let lookup = superblock.lookup(FUSE_ROOT_INODE, name.as_ref()).await?;
+            Ok::<_, InodeError>(lookup.s3_location()?.full_key().to_string())
+        };
 
-        let mut stat = InodeStat::for_file(
-            1024,
-            OffsetDateTime::now_utc(),
-            etag,
-            None,
-            None,
-            std::time::Duration::from_secs(24 * 60 * 60),
-        );
-        if invalidate_stat {
-            // For testing the expired stat cases
-            stat.update_validity(std::time::Duration::from_secs(0));
+        assert_eq!(lookup_key("Exact").await.unwrap(), "data/Exact");
+        assert!(matches!(
+            lookup_key("missing").await,
+            Err(InodeError::FileDoesNotExist(_, _))
+        ));
+        let readme = lookup_key("README.TXT").await;
+        if case_insensitive {
+            assert_eq!(readme.unwrap(), "data/readme.txt");
+            assert_eq!(lookup_key("docs").await.unwrap(), "data/Docs/");
+            assert_eq!(lookup_key("COLLIDE.txt").await.unwrap(), "data/Collide.txt");
+            let upper = superblock.lookup(FUSE_ROOT_INODE, "README.TXT".as_ref()).await.unwrap();
+            let lower = superblock.lookup(FUSE_ROOT_INODE, "readme.txt".as_ref()).await.unwrap();
+            assert_eq!(upper.ino(), lower.ino());
+        } else {
+            assert!(matches!(readme, Err(InodeError::FileDoesNotExist(_, _))));
         }
-        let state = InodeState::new(&stat, InodeKind::File, write_status);
-
-        let mut parent_state = parent_inode.get_mut_inode_state()?;
-
-        let inode = superblock.inner.create_inode_locked(
-            &parent_inode,
-            &mut parent_state,
-            ValidName::parse_str("test inode")?,
-            InodeKind::File,
-            state,
-            is_new_file,
-        )?;
-        // Manually add the inode to the Superblock's inode map, done by lookup/create in reality
-        superblock.inner.remember(&inode);
-        Ok(inode)
     }
 
-    #[test_case(""; "unprefixed")]
-    #[test_case("test_prefix/"; "prefixed")]
     #[tokio::test]
-    async fn test_readdir(prefix: &str) {
+    async fn test_key_filter() {
         let bucket = Bucket::new("test_bucket").unwrap();
-        let client = Arc::new(
-            MockClient::config()
-                .bucket(bucket.to_string())
-                .part_size(1024 * 1024)
-                .build(),
-        );
-
-        let keys = &[
-            format!("{prefix}dir0/file0.txt"),
-            format!("{prefix}dir0/sdir0/file0.txt"),
-            format!("{prefix}dir0/sdir0/file1.txt"),
-            format!("{prefix}dir0/sdir0/file2.txt"),
-            format!("{prefix}dir0/sdir1/file0.txt"),
-            format!("{prefix}dir0/sdir1/file1.txt"),
-            format!("{prefix}dir1/sdir2/file0.txt"),
-            format!("{prefix}dir1/sdir2/file1.txt"),
-            format!("{prefix}dir1/sdir2/file2.txt"),
-            format!("{prefix}dir1/sdir3/file0.txt"),
-            format!("{prefix}dir1/sdir3/file1.txt"),
-        ];
-
-        let last_modified = OffsetDateTime::UNIX_EPOCH + Duration::days(30);
-        for key in keys {
-            let mut obj = MockObject::constant(0xaa, 30, ETag::for_tests());
-            obj.set_last_modified(last_modified);
-            client.add_object(key, obj);
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        for key in [
+            "a.parquet",
+            "b.tmp",
+            "both",
+            "both/c.parquet",
+            "dir/d.parquet",
+            "dir/e.json",
+            "dir_$folder$",
+        ] {
+            client.add_object(
+                &format!("data/{key}"),
+                MockObject::constant(0xaa, 10, ETag::for_tests()),
+            );
         }
 
-        let prefix = Prefix::new(prefix).expect("valid prefix");
-        let ts = OffsetDateTime::now_utc();
-        let superblock = Superblock::new(client.clone(), S3Path::new(bucket, prefix.clone()), Default::default());
-
-        // Try it all twice to test inode reuse
-        for _ in 0..2 {
-            // Step 1: Read root directory
-            let root_entries = collect_dir_entries_with_info(&superblock, FUSE_ROOT_INODE, true, 100).await;
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Prefix::new("data/").unwrap()),
+            SuperblockConfig {
+                key_filter: KeyFilter::default()
+                    .include("*.parquet")
+                    .include("both")
+                    .exclude("both/")
+                    .exclude("*_$folder$"),
+                ..Default::default()
+            },
+        );
 
-            assert_eq!(root_entries.len(), 2);
-            assert_eq!(
-                root_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
-                &["dir0", "dir1"]
-            );
+        let entries = collect_dir_entries(&superblock, FUSE_ROOT_INODE, false, 2).await;
+        assert_eq!(entries, ["a.parquet", "both", "dir"]);
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        assert_eq!(dir.kind(), InodeKind::Directory);
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 2).await;
+        assert_eq!(entries, ["d.parquet"]);
 
-            let (dir0_entry, _) = &root_entries[0];
-            let (dir1_entry, _) = &root_entries[1];
+        // The excluded directory doesn't shadow the file with the same name.
+        let both = superblock.lookup(FUSE_ROOT_INODE, "both".as_ref()).await.unwrap();
+        assert_eq!(both.kind(), InodeKind::File);
 
-            // Check stats for root directory entries
-            assert_eq!(dir0_entry.kind(), InodeKind::Directory);
-            assert_eq!(dir0_entry.stat().mtime.date(), ts.date());
-            assert_eq!(dir0_entry.stat().size, 0);
+        let head_object_counter = client.new_counter(Operation::HeadObject);
+        let list_objects_counter = client.new_counter(Operation::ListObjectsV2);
+        for (parent, name) in [
+            (FUSE_ROOT_INODE, "b.tmp"),
+            (FUSE_ROOT_INODE, "dir_$folder$"),
+            (dir.ino(), "e.json"),
+        ] {
+            assert!(matches!(
+                superblock.lookup(parent, name.as_ref()).await,
+                Err(InodeError::FileDoesNotExist(_, _))
+            ));
+        }
+        // Hidden names can still be directories, so only their HeadObject is skipped.
+        assert_eq!(head_object_counter.count(), 0);
+        assert_eq!(list_objects_counter.count(), 3);
+    }
 
-            assert_eq!(dir1_entry.kind(), InodeKind::Directory);
-            assert_eq!(dir1_entry.stat().mtime.date(), ts.date());
-            assert_eq!(dir1_entry.stat().size, 0);
+    #[tokio::test]
+    async fn test_escape_names() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("data/dir/", MockObject::constant(0, 0, ETag::for_tests()));
+        client.add_object("data/dir//file0.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object(
+            "data/dir/./file1.txt",
+            MockObject::constant(0xaa, 20, ETag::for_tests()),
+        );
+        client.add_object("data/dir/100%", MockObject::constant(0xaa, 30, ETag::for_tests()));
 
-            // Step 2: Read dir0 directory
-            let dir0_ino = dir0_entry.ino();
-            let dir0_entries = collect_dir_entries_with_info(&superblock, dir0_ino, true, 100).await;
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Prefix::new("data/").unwrap()),
+            SuperblockConfig {
+                escape_names: true,
+                ..Default::default()
+            },
+        );
 
-            assert_eq!(dir0_entries.len(), 3);
-            assert_eq!(
-                dir0_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
-                &["file0.txt", "sdir0", "sdir1"]
-            );
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let mut entries = collect_dir_entries(&superblock, dir.ino(), false, 2).await;
+        entries.sort();
+        assert_eq!(
+            entries,
+            [OsString::from("%"), OsString::from("%2E"), OsString::from("100%25")],
+            "every key should be listed"
+        );
 
-            let (file0_entry, _) = &dir0_entries[0];
-            let (sdir0_entry, _) = &dir0_entries[1];
-            let (sdir1_entry, _) = &dir0_entries[2];
+        let empty = superblock.lookup(dir.ino(), "%".as_ref()).await.unwrap();
+        assert_eq!(
+            empty.kind(),
+            InodeKind::Directory,
+            "the marker of the parent is not a file"
+        );
+        let file = superblock.lookup(empty.ino(), "file0.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 10);
+        let dot = superblock.lookup(dir.ino(), "%2E".as_ref()).await.unwrap();
+        let file = superblock.lookup(dot.ino(), "file1.txt".as_ref()).await.unwrap();
+        assert_eq!(file.stat().size, 20);
+
+        let err = superblock
+            .lookup(dir.ino(), "100%".as_ref())
+            .await
+            .expect_err("names must be escaped");
+        assert!(matches!(err, InodeError::InvalidFileName(_)));
+        superblock.unlink(dir.ino(), "100%25".as_ref()).await.unwrap();
+        assert!(!client.contains_key("data/dir/100%"));
 
-            // Check stats for dir0 entries
-            assert_eq!(file0_entry.kind(), InodeKind::File);
-            assert_eq!(file0_entry.stat().mtime.date(), last_modified.date());
-            assert_eq!(file0_entry.stat().size, 30);
+        assert!(
+            !superblock.invalidate("data/dir//file0.txt").await.is_empty(),
+            "invalidated keys are escaped"
+        );
+    }
 
-            assert_eq!(sdir0_entry.kind(), InodeKind::Directory);
-            assert_eq!(sdir0_entry.stat().mtime.date(), ts.date());
-            assert_eq!(sdir0_entry.stat().size, 0);
+    #[tokio::test]
+    async fn test_version_directories() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        let time = |seconds| OffsetDateTime::from_unix_timestamp(seconds).unwrap();
+        let version = |size, version_id: &str, seconds| {
+            let mut object = MockObject::constant(0xaa, size, ETag::for_tests());
+            object.set_version_id(Some(version_id.to_owned()));
+            object.set_last_modified(time(seconds));
+            object
+        };
+        client.add_object("dir/file0.txt", version(20, "v2", 20));
+        client.add_object_version("dir/file0.txt", version(10, "v1", 10));
+        client.add_delete_marker("dir/file0.txt", "v0", time(5));
+        client.add_object("dir/file0.txt.bak", version(30, "v3", 30));
 
-            assert_eq!(sdir1_entry.kind(), InodeKind::Directory);
-            assert_eq!(sdir1_entry.stat().mtime.date(), ts.date());
-            assert_eq!(sdir1_entry.stat().size, 0);
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Default::default()),
+            SuperblockConfig {
+                version_directories: true,
+                ..Default::default()
+            },
+        );
 
-            // Step 3: Read sdir0 directory
-            let sdir0_ino = sdir0_entry.ino();
-            let sdir0_entries = collect_dir_entries_with_info(&superblock, sdir0_ino, true, 100).await;
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+        let entries = collect_dir_entries(&superblock, dir.ino(), false, 3).await;
+        assert_eq!(
+            entries,
+            [OsString::from("file0.txt"), OsString::from("file0.txt.bak")],
+            "versions directories are not listed"
+        );
 
-            assert_eq!(sdir0_entries.len(), 3);
-            assert_eq!(
-                sdir0_entries.iter().map(|(_, name)| name).collect::<Vec<_>>(),
-                &["file0.txt", "file1.txt", "file2.txt"]
-            );
+        let versions = superblock
+            .lookup(dir.ino(), "file0.txt.versions".as_ref())
+            .await
+            .unwrap();
+        assert_eq!(versions.kind(), InodeKind::Directory);
+        let entries = collect_dir_entries(&superblock, versions.ino(), false, 3).await;
+        assert_eq!(entries, [OsString::from("v1"), OsString::from("v2")]);
+
+        let file = superblock.lookup(versions.ino(), "v1".as_ref()).await.unwrap();
+        assert_eq!(file.kind(), InodeKind::File);
+        assert_eq!(file.stat().size, 10);
+        assert_eq!(file.stat().version_id.as_deref(), Some("v1"));
+        assert!(file.stat().pinned_version);
+
+        let err = superblock
+            .lookup(versions.ino(), "v0".as_ref())
+            .await
+            .expect_err("delete markers are not versions");
+        assert_eq!(err.to_errno(), libc::ENOENT);
+        let err = superblock
+            .lookup(dir.ino(), "file1.txt.versions".as_ref())
+            .await
+            .expect_err("objects without versions have no versions directory");
+        assert_eq!(err.to_errno(), libc::ENOENT);
 
-            // Check stats for all files in sdir0
-            for (entry, _) in &sdir0_entries {
-                assert_eq!(entry.kind(), InodeKind::File);
-                assert_eq!(entry.stat().mtime.date(), last_modified.date());
-                assert_eq!(entry.stat().size, 30);
-            }
-        }
+        let err = superblock
+            .create(versions.ino(), "v4".as_ref(), InodeKind::File)
+            .await
+            .expect_err("versions can not be created");
+        assert_eq!(err.to_errno(), libc::EPERM);
+        let err = superblock
+            .unlink(versions.ino(), "v1".as_ref())
+            .await
+            .expect_err("versions can not be removed");
+        assert_eq!(err.to_errno(), libc::EPERM);
+        let err = superblock
+            .rename(versions.ino(), "v1".as_ref(), dir.ino(), "file2.txt".as_ref(), true)
+            .await
+            .expect_err("versions can not be renamed");
+        assert_eq!(err.to_errno(), libc::EPERM);
     }
 
     /// Helper function to collect directory entries with their inode information
@@ -3009,6 +5898,135 @@ mod tests {
         assert_eq!(libc::ENOENT, err, "lookup should return no existing entry error");
     }
 
+    #[tokio::test]
+    async fn test_unlink_to_trash() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object("mount/dir/a.txt", MockObject::constant(0xaa, 10, ETag::for_tests()));
+        client.add_object("mount/.trash/b.txt", MockObject::constant(0xbb, 10, ETag::for_tests()));
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, Prefix::new("mount/").unwrap()),
+            SuperblockConfig {
+                trash: Some(TrashConfig::new(Prefix::new("mount/.trash/").unwrap(), None, runtime)),
+                cache_config: CacheConfig {
+                    serve_lookup_from_cache: true,
+                    file_ttl: std::time::Duration::from_secs(60),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        let dir = superblock.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().ino();
+        superblock.unlink(dir, "a.txt".as_ref()).await.unwrap();
+        assert!(!client.contains_key("mount/dir/a.txt"));
+        let trashed = client
+            .list_objects("test_bucket", None, "", 10, "mount/.trash/20")
+            .await
+            .unwrap();
+        assert_eq!(trashed.objects.len(), 1);
+        assert!(trashed.objects[0].key.ends_with("Z/mount/dir/a.txt"));
+
+        // Files already in the trash are just deleted.
+        let trash = superblock
+            .lookup(FUSE_ROOT_INODE, ".trash".as_ref())
+            .await
+            .unwrap()
+            .ino();
+        superblock.unlink(trash, "b.txt".as_ref()).await.unwrap();
+        assert_eq!(client.object_count(), 1);
+
+        // Unlinks fail if the object can't be copied, here because another client deleted it after it was looked up.
+        client.add_object("mount/c.txt", MockObject::constant(0xcc, 10, ETag::for_tests()));
+        superblock.lookup(FUSE_ROOT_INODE, "c.txt".as_ref()).await.unwrap();
+        client.remove_object("mount/c.txt");
+        let result = superblock.unlink(FUSE_ROOT_INODE, "c.txt".as_ref()).await;
+        assert!(matches!(result, Err(InodeError::InodeDoesNotExist(..))));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_prefix() {
+        let bucket = Bucket::new("test_bucket").unwrap();
+        let client = Arc::new(MockClient::config().bucket(bucket.to_string()).part_size(32).build());
+        client.add_object(
+            "mount/data/archive/a.txt",
+            MockObject::constant(0xaa, 10, ETag::for_tests()),
+        );
+        client.add_object("mount/data/b.txt", MockObject::constant(0xbb, 10, ETag::for_tests()));
+        let mount_prefix = Prefix::new("mount/").unwrap();
+        let read_only = crate::fs::PrefixOverride {
+            read_only: true,
+            ..Default::default()
+        };
+        let prefix_overrides =
+            PrefixOverrides::new(&mount_prefix, [(Prefix::new("data/archive/").unwrap(), read_only)]).unwrap();
+        let superblock = Superblock::new(
+            client.clone(),
+            S3Path::new(bucket, mount_prefix),
+            SuperblockConfig {
+                prefix_overrides,
+                ..Default::default()
+            },
+        );
+
+        let data = superblock.lookup(FUSE_ROOT_INODE, "data".as_ref()).await.unwrap().ino();
+        let archive = superblock.lookup(data, "archive".as_ref()).await.unwrap().ino();
+        let a = superblock.lookup(archive, "a.txt".as_ref()).await.unwrap().ino();
+        fn is_read_only<T>(result: Result<T, InodeError>) -> bool {
+            matches!(result, Err(InodeError::ReadOnlyPrefix(_)))
+        }
+
+        assert!(is_read_only(
+            superblock.create(archive, "new.txt".as_ref(), InodeKind::File).await
+        ));
+        assert!(is_read_only(superblock.unlink(archive, "a.txt".as_ref()).await));
+        assert!(is_read_only(
+            superblock
+                .open_handle(a, 0, &Default::default(), OpenFlags::O_WRONLY)
+                .await
+        ));
+        assert!(is_read_only(
+            superblock
+                .rename(data, "b.txt".as_ref(), archive, "b.txt".as_ref(), true)
+                .await
+        ));
+        assert!(is_read_only(
+            superblock
+                .rename(archive, "a.txt".as_ref(), data, "a.txt".as_ref(), true)
+                .await
+        ));
+        // The parents of read-only prefixes can't be removed or renamed either.
+        assert!(is_read_only(superblock.rmdir(FUSE_ROOT_INODE, "data".as_ref()).await));
+        assert!(is_read_only(
+            superblock
+                .rename(
+                    FUSE_ROOT_INODE,
+                    "data".as_ref(),
+                    FUSE_ROOT_INODE,
+                    "other".as_ref(),
+                    true
+                )
+                .await
+        ));
+        assert_eq!(
+            InodeError::ReadOnlyPrefix("data/archive/a.txt".to_owned()).to_errno(),
+            libc::EROFS
+        );
+
+        // Outside of the prefix, files can still be created and read, including next to it.
+        superblock
+            .create(data, "new.txt".as_ref(), InodeKind::File)
+            .await
+            .unwrap();
+        superblock.unlink(data, "b.txt".as_ref()).await.unwrap();
+        superblock
+            .open_handle(a, 0, &Default::default(), OpenFlags::empty())
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_finish_writing_convert_parent_local_dirs_to_remote() {
         let bucket = Bucket::new("test_bucket").unwrap();
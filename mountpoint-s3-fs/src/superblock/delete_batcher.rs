@@ -0,0 +1,176 @@
+use futures::channel::oneshot;
+use mountpoint_s3_client::ObjectClient;
+use tracing::debug;
+
+use crate::metablock::InodeError;
+use crate::sync::{AsyncMutex, Mutex};
+
+use super::MAX_DELETE_OBJECTS_KEYS;
+
+/// Deletes the objects of unlinked files, see [super::SuperblockConfig::batch_deletes]. Only one request is in flight
+/// at a time, and the keys of the deletes started in the meantime are sent together in a single DeleteObjects request
+/// once it completes. A delete started alone is sent with DeleteObject, so deleting files one at a time is not
+/// delayed and costs the same requests as without batching.
+#[derive(Debug, Default)]
+pub struct DeleteBatcher {
+    /// The deletes waiting for the request in flight to complete.
+    queue: Mutex<Vec<PendingDelete>>,
+    /// Held while a request is in flight.
+    in_flight: AsyncMutex<()>,
+}
+
+#[derive(Debug)]
+struct PendingDelete {
+    key: String,
+    result: oneshot::Sender<Result<(), InodeError>>,
+}
+
+impl DeleteBatcher {
+    /// Delete the object with the given key, along with the objects other callers are waiting to delete.
+    pub async fn delete<Client: ObjectClient>(
+        &self,
+        client: &Client,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(), InodeError> {
+        let mut receiver = self.enqueue(key);
+        loop {
+            let _in_flight = self.in_flight.lock().await;
+            match receiver.try_recv() {
+                Ok(Some(result)) => return result,
+                Ok(None) => {}
+                // The delete that took the key from the queue was dropped before sending it.
+                Err(oneshot::Canceled) => receiver = self.enqueue(key),
+            }
+            let batch = {
+                let mut queue = self.queue.lock().unwrap();
+                let len = queue.len().min(MAX_DELETE_OBJECTS_KEYS);
+                queue.drain(..len).collect::<Vec<_>>()
+            };
+            Self::send(client, bucket, batch).await;
+        }
+    }
+
+    fn enqueue(&self, key: &str) -> oneshot::Receiver<Result<(), InodeError>> {
+        let (sender, receiver) = oneshot::channel();
+        self.queue.lock().unwrap().push(PendingDelete {
+            key: key.to_owned(),
+            result: sender,
+        });
+        receiver
+    }
+
+    /// Delete the objects of a batch, and return the result of each delete to the caller waiting for it.
+    async fn send<Client: ObjectClient>(client: &Client, bucket: &str, mut batch: Vec<PendingDelete>) {
+        if batch.len() == 1 {
+            let pending = batch.pop().unwrap();
+            let result = client
+                .delete_object(bucket, &pending.key)
+                .await
+                .map(|_| ())
+                .map_err(|e| InodeError::client_error(e, "DeleteObject failed", bucket, &pending.key));
+            let _ = pending.result.send(result);
+            return;
+        }
+
+        debug!(keys = batch.len(), "deleting objects in a batch");
+        let keys: Vec<String> = batch.iter().map(|pending| pending.key.clone()).collect();
+        match client.delete_objects(bucket, &keys).await {
+            Ok(result) => {
+                metrics::counter!("fs.batched_deletes").increment(1);
+                metrics::counter!("fs.batched_deletes.objects").increment((batch.len() - result.errors.len()) as u64);
+                for pending in batch {
+                    let result = match result.errors.iter().find(|failure| failure.key == pending.key) {
+                        Some(failure) => Err(InodeError::client_error(
+                            failure.clone(),
+                            "DeleteObjects failed",
+                            bucket,
+                            &pending.key,
+                        )),
+                        None => Ok(()),
+                    };
+                    let _ = pending.result.send(result);
+                }
+            }
+            Err(e) => {
+                let error = InodeError::client_error(e, "DeleteObjects failed", bucket, "");
+                for pending in batch {
+                    let mut error = error.clone();
+                    if let InodeError::ClientError { metadata, .. } = &mut error {
+                        metadata.s3_object_key = Some(pending.key.clone());
+                    }
+                    let _ = pending.result.send(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject, Operation};
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+
+    /// Run the deletes of the given keys while a request is in flight, so that they are batched.
+    async fn delete_concurrently(
+        batcher: &DeleteBatcher,
+        client: &MockClient,
+        bucket: &str,
+        keys: &[String],
+    ) -> Vec<Result<(), InodeError>> {
+        let in_flight = batcher.in_flight.lock().await;
+        let deletes = futures::future::join_all(keys.iter().map(|key| batcher.delete(client, bucket, key)));
+        let (results, ()) = futures::join!(deletes, async {
+            tokio::task::yield_now().await;
+            drop(in_flight);
+        });
+        results
+    }
+
+    #[tokio::test]
+    async fn test_batched_deletes() {
+        let bucket = "test_bucket";
+        let client = MockClient::config().bucket(bucket).part_size(32).build();
+        let keys: Vec<String> = (0..1500).map(|i| format!("dir/{i}")).collect();
+        for key in &keys {
+            client.add_object(key, MockObject::constant(0xaa, 1, ETag::for_tests()));
+        }
+        client.add_object("alone", MockObject::constant(0xaa, 1, ETag::for_tests()));
+        let delete_object_counter = client.new_counter(Operation::DeleteObject);
+        let delete_objects_counter = client.new_counter(Operation::DeleteObjects);
+        let batcher = DeleteBatcher::default();
+
+        let results = delete_concurrently(&batcher, &client, bucket, &keys[..100]).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(delete_objects_counter.count(), 1);
+        assert_eq!(client.object_count(), 1401);
+
+        // Batches hold at most 1000 keys.
+        let results = delete_concurrently(&batcher, &client, bucket, &keys[100..]).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(delete_objects_counter.count(), 3);
+        assert_eq!(client.object_count(), 1);
+
+        // A delete started alone is not batched.
+        batcher.delete(&client, bucket, "alone").await.unwrap();
+        assert_eq!(delete_object_counter.count(), 1);
+        assert_eq!(client.object_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batched_deletes_failure() {
+        let client = MockClient::config().bucket("test_bucket").part_size(32).build();
+        let batcher = DeleteBatcher::default();
+        let keys = ["a.txt".to_owned(), "b.txt".to_owned()];
+
+        let results = delete_concurrently(&batcher, &client, "other_bucket", &keys).await;
+        for (result, key) in results.iter().zip(&keys) {
+            let Err(InodeError::ClientError { metadata, .. }) = result else {
+                panic!("delete should fail: {result:?}");
+            };
+            assert_eq!(metadata.s3_object_key.as_ref(), Some(key));
+        }
+    }
+}
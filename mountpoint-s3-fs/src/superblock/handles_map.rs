@@ -51,6 +51,20 @@ impl InodeHandleMap {
         true
     }
 
+    /// Add a new active reader of a file being written, which reads the data of its writer rather than the object
+    /// in S3.
+    ///
+    /// Return the writer, or `None` without adding the reader if there is no active writer.
+    pub fn try_add_reader_of_writer(&self, locked_inode: &InodeLockedForWriting<'_>, fh: u64) -> Option<u64> {
+        let mut handles = self.handles.lock().unwrap();
+        let entry = handles.get_mut(&locked_inode.ino)?;
+        let &(writer, HandleState::Active) = entry.writer.as_ref()? else {
+            return None;
+        };
+        entry.readers.insert(fh, HandleState::Active);
+        Some(writer)
+    }
+
     /// Set an existing reader to inactive.
     ///
     /// This is a no-op if the reader is already marked inactive or does not exist in the map.
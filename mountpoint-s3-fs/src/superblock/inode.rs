@@ -4,7 +4,6 @@ use std::time::Duration;
 
 use mountpoint_s3_client::checksums::crc32c::{self, Crc32c};
 use time::OffsetDateTime;
-use tracing::debug;
 
 use crate::metablock::{
     InodeError, InodeErrorInfo, InodeKind, InodeNo, InodeStat, NEVER_EXPIRE_TTL, PendingUploadHook, ROOT_INODE_NO,
@@ -173,23 +172,28 @@ impl Inode {
         new_validity: Duration,
         new_parent: InodeNo,
     ) -> Result<Inode, InodeError> {
-        if self.kind() != InodeKind::File {
-            debug!("Cannot re-create an inode of kind != InodeKind::File");
-            return Err(InodeError::IsDirectory(self.err()));
-        }
         let old_inode_state = self.get_inode_state()?;
-        let new_inode_state = InodeState {
-            stat: InodeStat::for_file(
-                old_inode_state.stat.size,
-                old_inode_state.stat.atime,
-                old_inode_state.stat.etag.clone(),
-                None,
-                None,
-                new_validity,
-            ),
-            write_status: WriteStatus::Remote,
-            kind_data: InodeKindData::default_for(InodeKind::File),
-            pending_upload_hook: None,
+        let new_inode_state = match self.kind() {
+            InodeKind::File => InodeState {
+                stat: InodeStat::for_file(
+                    old_inode_state.stat.size,
+                    old_inode_state.stat.atime,
+                    old_inode_state.stat.etag.clone(),
+                    None,
+                    None,
+                    new_validity,
+                ),
+                write_status: WriteStatus::Remote,
+                kind_data: InodeKindData::default_for(InodeKind::File),
+                pending_upload_hook: None,
+            },
+            // Directories keep their write status, and no children: they are looked up again under the new key.
+            InodeKind::Directory => InodeState {
+                stat: InodeStat::for_directory(old_inode_state.stat.mtime, new_validity),
+                write_status: old_inode_state.write_status,
+                kind_data: InodeKindData::default_for(InodeKind::Directory),
+                pending_upload_hook: None,
+            },
         };
 
         Ok(Self::new(self.ino(), new_parent, new_key, prefix, new_inode_state))
@@ -0,0 +1,481 @@
+//! Keeping inode numbers stable across mounts.
+//!
+//! Inode numbers are otherwise allocated in the order inodes are looked up, so the same file gets a different number
+//! each time the bucket is mounted. This breaks NFS re-exports of the mount, and tools tracking files by device and
+//! inode number. An [InodeTable] saves the number of each key in a file, so that the next mounts of the same bucket
+//! and prefix give the key the same number.
+//!
+//! The table is a header identifying the bucket and prefix, followed by records appended whenever a key is given a
+//! number. Each number is given to at most one inode at a time: when an object is replaced while the mount is running,
+//! the new inode gets a new number, which the following mounts use. Records superseded this way are dropped when the
+//! table is compacted at mount time. Keys are never removed from the table, except by renames. Once the kernel forgets
+//! the inode of a key, the key gets the same number again when it is looked up.
+//!
+//! A table can also be kept [in memory](InodeTable::in_memory), which keeps numbers stable for the lifetime of a single
+//! mount only, as overlayfs expects from its lower layers.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use bincode::{Decode, Encode};
+use tracing::{debug, warn};
+
+use crate::metablock::InodeNo;
+use crate::s3::S3Path;
+use crate::sync::{Arc, Mutex};
+
+/// Minimum number of records in the table before it is compacted. Past this number, the table is compacted once it
+/// holds more than twice as many records as keys.
+const COMPACTION_MIN_RECORDS: u64 = 10_000;
+
+/// Inode number of the first inode after the root.
+const FIRST_INO: InodeNo = 2;
+
+/// Identifies the bucket and prefix the numbers of a table apply to.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+struct InodeTableHeader {
+    version: u32,
+    bucket: String,
+    prefix: String,
+}
+
+impl InodeTableHeader {
+    const VERSION: u32 = 1;
+
+    fn new(s3_path: &S3Path) -> Self {
+        Self {
+            version: Self::VERSION,
+            bucket: s3_path.bucket.to_string(),
+            prefix: s3_path.prefix.to_string(),
+        }
+    }
+}
+
+/// A change to the number of a key, relative to the mounted prefix.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+enum InodeRecord {
+    Assign { key: String, ino: InodeNo },
+    Remove { key: String },
+}
+
+/// Inode numbers of the keys of a bucket and prefix, saved across mounts, see [crate::SuperblockConfig::inode_table].
+#[derive(Clone)]
+pub struct InodeTable {
+    inner: Arc<Mutex<InodeTableInner>>,
+}
+
+struct InodeTableInner {
+    /// The file the table is saved in, unless it is kept in memory.
+    file: Option<File>,
+    path: Option<PathBuf>,
+    entries: HashMap<String, InodeTableEntry>,
+    /// Number of records in the table.
+    records: u64,
+    /// Largest inode number in the table.
+    max_ino: InodeNo,
+}
+
+struct InodeTableEntry {
+    ino: InodeNo,
+    /// Whether the number was given to an inode in this mount.
+    assigned: bool,
+}
+
+impl InodeTable {
+    /// Open the table at `path` for the given bucket and prefix, creating it if needed. The table is locked until the
+    /// file system is unmounted, and opening a table in use by another mount fails with [ErrorKind::WouldBlock]. A
+    /// table saved for another bucket or prefix is discarded.
+    pub fn open(path: &Path, s3_path: &S3Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(path)?;
+        lock(&file)?;
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        let mut inner = InodeTableInner {
+            file: Some(file),
+            path: Some(path.to_owned()),
+            entries: HashMap::new(),
+            records: 0,
+            max_ino: FIRST_INO - 1,
+        };
+
+        let header = InodeTableHeader::new(s3_path);
+        match bincode::decode_from_slice::<InodeTableHeader, _>(&buffer, bincode::config::standard()) {
+            Ok((saved, length)) if saved == header => {
+                let valid_length = inner.apply_records(&buffer[length..]) + length;
+                for entry in inner.entries.values_mut() {
+                    entry.assigned = false;
+                }
+                if valid_length < buffer.len() {
+                    // Drop the records which can't be decoded, so that new records can be appended.
+                    let file = inner.file.as_ref().expect("opened tables are saved in a file");
+                    file.set_len(valid_length as u64)?;
+                }
+                if inner.records > COMPACTION_MIN_RECORDS.max(2 * inner.entries.len() as u64) {
+                    inner.compact(&header)?;
+                }
+            }
+            Ok(_) => {
+                warn!(
+                    ?path,
+                    "inode table was saved for another bucket or prefix, discarding it"
+                );
+                inner.compact(&header)?;
+            }
+            Err(_) if buffer.is_empty() => inner.compact(&header)?,
+            Err(error) => {
+                warn!(?path, ?error, "unable to decode the inode table, discarding it");
+                inner.compact(&header)?;
+            }
+        }
+        debug!(?path, keys = inner.entries.len(), "opened inode table");
+        Ok(Self {
+            inner: Arc::new(Mutex::new(inner)),
+        })
+    }
+
+    /// A table which isn't saved, so that the numbers of keys are only stable until the file system is unmounted.
+    pub fn in_memory() -> Self {
+        let inner = InodeTableInner {
+            file: None,
+            path: None,
+            entries: HashMap::new(),
+            records: 0,
+            max_ino: FIRST_INO - 1,
+        };
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Inode number to give to new keys, greater than all the numbers in the table.
+    pub(super) fn next_ino(&self) -> InodeNo {
+        self.inner.lock().unwrap().max_ino + 1
+    }
+
+    /// Number of a new inode for `key`: the saved number of the key if it wasn't given to another inode in this mount
+    /// yet, or `new_ino`, which is then saved as the number of the key.
+    pub(super) fn assign(&self, key: &str, new_ino: impl FnOnce() -> InodeNo) -> InodeNo {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entry) = inner.entries.get_mut(key)
+            && !entry.assigned
+        {
+            entry.assigned = true;
+            return entry.ino;
+        }
+        let ino = new_ino();
+        inner.apply(InodeRecord::Assign {
+            key: key.to_owned(),
+            ino,
+        });
+        inner.append(&[InodeRecord::Assign {
+            key: key.to_owned(),
+            ino,
+        }]);
+        ino
+    }
+
+    /// Let the next inode of `key` get its number again, once its inode numbered `ino` was forgotten.
+    pub(super) fn release(&self, key: &str, ino: InodeNo) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entry) = inner.entries.get_mut(key)
+            && entry.ino == ino
+        {
+            entry.assigned = false;
+        }
+    }
+
+    /// Move the number `ino` of the key `from` to the key `to`.
+    pub(super) fn rename(&self, from: &str, to: &str, ino: InodeNo) {
+        let records = [
+            InodeRecord::Remove { key: from.to_owned() },
+            InodeRecord::Assign {
+                key: to.to_owned(),
+                ino,
+            },
+        ];
+        let mut inner = self.inner.lock().unwrap();
+        for record in &records {
+            inner.apply(record.clone());
+        }
+        inner.append(&records);
+    }
+}
+
+impl InodeTableInner {
+    /// Apply the records encoded in `buffer`, and return the length of the records which could be decoded.
+    fn apply_records(&mut self, buffer: &[u8]) -> usize {
+        let mut position = 0;
+        while position < buffer.len() {
+            match bincode::decode_from_slice::<InodeRecord, _>(&buffer[position..], bincode::config::standard()) {
+                Ok((record, length)) => {
+                    self.apply(record);
+                    position += length;
+                }
+                Err(error) => {
+                    // Mountpoint may have exited while appending a record.
+                    warn!(path = ?self.path, ?error, "unable to decode the inode table, skipping its end");
+                    break;
+                }
+            }
+        }
+        position
+    }
+
+    fn apply(&mut self, record: InodeRecord) {
+        match record {
+            InodeRecord::Assign { key, ino } => {
+                self.max_ino = self.max_ino.max(ino);
+                self.entries.insert(key, InodeTableEntry { ino, assigned: true });
+            }
+            InodeRecord::Remove { key } => {
+                self.entries.remove(&key);
+            }
+        }
+        self.records += 1;
+    }
+
+    fn append(&mut self, records: &[InodeRecord]) {
+        let result = records
+            .iter()
+            .try_fold(Vec::new(), |mut buffer, record| {
+                bincode::encode_into_std_write(record, &mut buffer, bincode::config::standard())
+                    .map_err(io::Error::other)?;
+                Ok::<_, io::Error>(buffer)
+            })
+            .and_then(|buffer| {
+                let Some(file) = &mut self.file else {
+                    return Ok(());
+                };
+                file.seek(SeekFrom::End(0))?;
+                file.write_all(&buffer)
+            });
+        if let Err(error) = result {
+            // The numbers are still unique in this mount, but may change in the next ones.
+            warn!(path = ?self.path, ?error, "unable to save inode numbers");
+        }
+    }
+
+    /// Replace the table with the given header and one record for each key. Entries are marked as not assigned in
+    /// this mount, since compaction happens before any inode is created.
+    fn compact(&mut self, header: &InodeTableHeader) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut buffer = bincode::encode_to_vec(header, bincode::config::standard()).map_err(io::Error::other)?;
+        for (key, entry) in &mut self.entries {
+            let record = InodeRecord::Assign {
+                key: key.clone(),
+                ino: entry.ino,
+            };
+            bincode::encode_into_std_write(&record, &mut buffer, bincode::config::standard())
+                .map_err(io::Error::other)?;
+            entry.assigned = false;
+        }
+
+        // Write the new table next to the current one and lock it before replacing it, so that another mount can't
+        // open the new table in between.
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let mut temp_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temp_path)?;
+        lock(&temp_file)?;
+        temp_file.write_all(&buffer)?;
+        temp_file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        debug!(
+            ?path,
+            records = self.records,
+            keys = self.entries.len(),
+            "compacted inode table"
+        );
+        self.file = Some(temp_file);
+        self.records = self.entries.len() as u64;
+        Ok(())
+    }
+}
+
+fn lock(file: &File) -> io::Result<()> {
+    file.try_lock().map_err(|error| match error {
+        TryLockError::WouldBlock => io::Error::new(ErrorKind::WouldBlock, "inode table is used by another mount"),
+        TryLockError::Error(error) => error,
+    })
+}
+
+impl Debug for InodeTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("InodeTable")
+            .field("path", &inner.path)
+            .field("keys", &inner.entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::s3::{Bucket, Prefix};
+
+    use super::*;
+
+    fn s3_path(prefix: &str) -> S3Path {
+        S3Path::new(Bucket::new("bucket").unwrap(), Prefix::new(prefix).unwrap())
+    }
+
+    fn assign(table: &InodeTable, key: &str) -> InodeNo {
+        let next_ino = table.next_ino();
+        table.assign(key, || next_ino)
+    }
+
+    #[test]
+    fn test_numbers_are_stable() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        let a = assign(&table, "a");
+        let b = assign(&table, "dir/b");
+        assert_eq!((a, b), (2, 3));
+        let replaced_a = assign(&table, "a");
+        assert_eq!(replaced_a, 4, "numbers are given to a single inode in a mount");
+        drop(table);
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert_eq!(table.next_ino(), 5);
+        assert_eq!(assign(&table, "dir/b"), b);
+        assert_eq!(assign(&table, "a"), replaced_a);
+        assert_eq!(assign(&table, "c"), 5);
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let table = InodeTable::in_memory();
+        let a = assign(&table, "a");
+        assert_eq!(a, 2);
+        let replaced_a = assign(&table, "a");
+        assert_eq!(replaced_a, 3);
+
+        // Releasing the number of an inode that was replaced keeps the number of the current one.
+        table.release("a", a);
+        assert_eq!(assign(&table, "a"), 4);
+        table.release("a", 4);
+        assert_eq!(assign(&table, "a"), 4, "forgotten inodes get their number again");
+        assert!(format!("{table:?}").contains("keys: 1"));
+    }
+
+    #[test]
+    fn test_rename() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        let a = assign(&table, "a");
+        table.rename("a", "b", a);
+        assert_eq!(assign(&table, "a"), a + 1, "the number moved with the renamed key");
+        drop(table);
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert_eq!(assign(&table, "b"), a);
+        assert_eq!(assign(&table, "a"), a + 1);
+    }
+
+    #[test]
+    fn test_other_prefix() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assign(&table, "a");
+        assign(&table, "b");
+        drop(table);
+
+        let table = InodeTable::open(&path, &s3_path("prefix/")).unwrap();
+        assert_eq!(
+            table.next_ino(),
+            FIRST_INO,
+            "the numbers of another prefix are discarded"
+        );
+    }
+
+    #[test]
+    fn test_locked() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        let error = InodeTable::open(&path, &s3_path("")).expect_err("the table is used by another mount");
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        drop(table);
+        InodeTable::open(&path, &s3_path("")).expect("the table was unlocked");
+    }
+
+    #[test]
+    fn test_truncated_record() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        let a = assign(&table, "a");
+        assign(&table, "b");
+        drop(table);
+        let length = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(length - 1)
+            .unwrap();
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert_eq!(assign(&table, "c"), a + 1, "the truncated record is dropped");
+        drop(table);
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert_eq!(assign(&table, "a"), a);
+        assert_eq!(
+            assign(&table, "c"),
+            a + 1,
+            "records are appended after the truncated one"
+        );
+    }
+
+    #[test]
+    fn test_compaction() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        let mut ino = 0;
+        for _ in 0..=COMPACTION_MIN_RECORDS {
+            ino = assign(&table, "a");
+        }
+        drop(table);
+        let length = fs::metadata(&path).unwrap().len();
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert!(
+            fs::metadata(&path).unwrap().len() < length / 100,
+            "the table was compacted"
+        );
+        assert_eq!(table.inner.lock().unwrap().records, 1);
+        assert_eq!(assign(&table, "a"), ino);
+        drop(table);
+
+        let table = InodeTable::open(&path, &s3_path("")).unwrap();
+        assert_eq!(assign(&table, "a"), ino);
+    }
+}
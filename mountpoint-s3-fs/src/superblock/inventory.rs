@@ -0,0 +1,718 @@
+//! Directory listings from S3 Inventory reports.
+//!
+//! Listing a large bucket with ListObjectsV2 takes one request for every 1,000 keys, each time a directory is read.
+//! For buckets that rarely change, an [S3 Inventory] report lists the same keys in a few files, delivered daily or
+//! weekly. With an [InventoryConfig], the superblock loads the latest report of the mounted bucket into memory, and
+//! serves directory listings and lookups of the keys it holds without requests to S3. Lookups of keys that are not in
+//! the report, for example objects created since it was delivered, still go to S3, so they can be opened by name.
+//!
+//! The report is loaded by a background task, which checks for newer reports periodically. Until the first report is
+//! loaded, directories are listed with ListObjectsV2 as usual.
+//!
+//! [S3 Inventory]: https://docs.aws.amazon.com/AmazonS3/latest/userguide/storage-inventory.html
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::ops::Bound;
+use std::time::Duration;
+
+use futures::task::SpawnExt;
+use futures::{StreamExt, pin_mut};
+use mountpoint_s3_client::ObjectClient;
+use mountpoint_s3_client::error::{GetObjectError, ObjectClientError};
+use mountpoint_s3_client::types::{ClientBackpressureHandle, GetBodyPart, GetObjectParams, GetObjectResponse};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use thiserror::Error;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, info, warn};
+
+use crate::async_util::{Runtime, sleep};
+use crate::s3::S3Path;
+use crate::sync::{Arc, RwLock, Weak};
+
+mod gzip;
+use gzip::GzipError;
+
+/// Name of the file describing an inventory report, in the directory of the report.
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Size of the read window of the requests fetching the files of a report.
+const READ_WINDOW_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Serving directory listings from S3 Inventory reports, see [crate::SuperblockConfig::inventory].
+#[derive(Clone)]
+pub struct InventoryConfig {
+    location: S3Path,
+    refresh_interval: Duration,
+    runtime: Runtime,
+}
+
+impl InventoryConfig {
+    /// Load the reports delivered to `location`, the destination of an inventory configuration of the mounted bucket
+    /// followed by the name of the source bucket and the ID of the configuration (for example
+    /// `s3://amzn-s3-demo-inventory-bucket/prefix/amzn-s3-demo-bucket/config-id/`). The task loading the reports runs
+    /// on `runtime`, and checks for a newer report every `refresh_interval`.
+    pub fn new(location: S3Path, refresh_interval: Duration, runtime: Runtime) -> Self {
+        Self {
+            location,
+            refresh_interval,
+            runtime,
+        }
+    }
+}
+
+impl Debug for InventoryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InventoryConfig")
+            .field("location", &self.location)
+            .field("refresh_interval", &self.refresh_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum InventoryError {
+    #[error("{operation} failed for {key:?}")]
+    ClientError {
+        operation: &'static str,
+        key: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("no inventory report found in {0}")]
+    NoReport(String),
+    #[error("invalid inventory manifest {0:?}")]
+    InvalidManifest(String, #[source] serde_json::Error),
+    #[error("inventory report {0:?} is of bucket {1:?}")]
+    WrongBucket(String, String),
+    #[error("inventory report {0:?} is in the {1} format, but only CSV reports are supported")]
+    UnsupportedFormat(String, String),
+    #[error("inventory report {0:?} does not include the {1} field")]
+    MissingField(String, &'static str),
+    #[error("failed to decompress inventory file {0:?}")]
+    Decompression(String, #[source] GzipError),
+    #[error("invalid row in inventory file {0:?}: {1}")]
+    InvalidRow(String, String),
+}
+
+/// The latest inventory report loaded for a mount, refreshed by a background task.
+#[derive(Debug)]
+pub(super) struct Inventory {
+    index: Arc<RwLock<Option<Arc<InventoryIndex>>>>,
+}
+
+impl Inventory {
+    /// Start loading the reports of the objects under `s3_path`. The background task stops once the [Inventory] is
+    /// dropped.
+    pub fn start<OC>(client: OC, s3_path: S3Path, config: &InventoryConfig) -> Self
+    where
+        OC: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        let inventory = Self {
+            index: Arc::new(RwLock::new(None)),
+        };
+        let task = refresh(
+            client,
+            config.location.clone(),
+            s3_path,
+            config.refresh_interval,
+            Arc::downgrade(&inventory.index),
+        );
+        if let Err(error) = config.runtime.spawn(task) {
+            warn!(?error, "unable to start loading inventory reports");
+        }
+        inventory
+    }
+
+    /// The latest report, if one was loaded.
+    pub fn index(&self) -> Option<Arc<InventoryIndex>> {
+        self.index.read().unwrap().clone()
+    }
+}
+
+async fn refresh<OC: ObjectClient>(
+    client: OC,
+    location: S3Path,
+    s3_path: S3Path,
+    refresh_interval: Duration,
+    index: Weak<RwLock<Option<Arc<InventoryIndex>>>>,
+) {
+    loop {
+        let Some(current) = index.upgrade() else {
+            return;
+        };
+        let current_report = current.read().unwrap().as_ref().map(|index| index.report.clone());
+        drop(current);
+
+        match InventoryIndex::load(&client, &location, &s3_path, current_report.as_deref()).await {
+            Ok(Some(new_index)) => {
+                info!(
+                    report = ?new_index.report,
+                    objects = new_index.objects.len(),
+                    "loaded inventory report"
+                );
+                let Some(current) = index.upgrade() else {
+                    return;
+                };
+                *current.write().unwrap() = Some(Arc::new(new_index));
+            }
+            Ok(None) => debug!(report = ?current_report, "inventory report is up to date"),
+            Err(error) => warn!(?error, "failed to load inventory report"),
+        }
+        sleep(refresh_interval).await;
+    }
+}
+
+/// An object listed by an inventory report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct InventoryObject {
+    pub size: u64,
+    pub last_modified: OffsetDateTime,
+    pub etag: String,
+    pub storage_class: Option<String>,
+    /// Version of the object to read, rather than the current version of its key. Only known for the objects of
+    /// snapshots and versions directories, as reports list the current versions.
+    pub version_id: Option<String>,
+}
+
+/// A page of the listing of a directory, in the same form as a ListObjectsV2 response with the `/` delimiter.
+#[derive(Debug, Default)]
+pub(super) struct InventoryPage {
+    pub common_prefixes: Vec<String>,
+    pub objects: Vec<(String, InventoryObject)>,
+    pub next_continuation_token: Option<String>,
+}
+
+/// What an inventory report knows of a key.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum InventoryLookup<'a> {
+    File(&'a InventoryObject),
+    Directory,
+}
+
+/// The objects of an inventory report under the mounted prefix.
+#[derive(Debug)]
+pub(super) struct InventoryIndex {
+    /// Key of the manifest of the report, which identifies it.
+    report: String,
+    objects: BTreeMap<String, InventoryObject>,
+}
+
+impl InventoryIndex {
+    /// An index of the given objects, by key, identified by `report`.
+    pub fn new(report: String, objects: BTreeMap<String, InventoryObject>) -> Self {
+        Self { report, objects }
+    }
+
+    /// Number of objects in the index.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// List the directory with the given prefix, ending in `/`, from the given continuation token.
+    pub fn list(&self, prefix: &str, continuation_token: Option<&str>, max_keys: usize) -> InventoryPage {
+        let mut page = InventoryPage::default();
+        let mut cursor = match continuation_token {
+            Some(token) => Self::after(token),
+            None => Bound::Included(prefix.to_owned()),
+        };
+        let mut count = 0;
+        let mut last = None;
+        while let Some((key, object)) = self
+            .objects
+            .range::<str, _>((cursor.as_ref().map(String::as_str), Bound::Unbounded))
+            .next()
+        {
+            let Some(name) = key.strip_prefix(prefix) else {
+                break;
+            };
+            if count == max_keys {
+                page.next_continuation_token = last;
+                break;
+            }
+            match name.find('/') {
+                Some(end) => {
+                    let common_prefix = &key[..prefix.len() + end + 1];
+                    cursor = Self::after(common_prefix);
+                    page.common_prefixes.push(common_prefix.to_owned());
+                    last = Some(common_prefix.to_owned());
+                    count += 1;
+                }
+                None => {
+                    cursor = Bound::Excluded(key.clone());
+                    // The marker of the directory itself is not one of its entries.
+                    if !name.is_empty() {
+                        page.objects.push((key.clone(), object.clone()));
+                        last = Some(key.clone());
+                        count += 1;
+                    }
+                }
+            }
+        }
+        page
+    }
+
+    /// Whether the key, without a trailing `/`, is a file or a directory in the report. As in the rest of the file
+    /// system, directories shadow files of the same name.
+    pub fn lookup(&self, object_key: &str) -> Option<InventoryLookup<'_>> {
+        let directory_prefix = format!("{object_key}/");
+        let has_children = self
+            .objects
+            .range::<str, _>((Bound::Included(directory_prefix.as_str()), Bound::Unbounded))
+            .next()
+            .is_some_and(|(key, _)| key.starts_with(&directory_prefix));
+        if has_children {
+            return Some(InventoryLookup::Directory);
+        }
+        self.objects.get(object_key).map(InventoryLookup::File)
+    }
+
+    /// The bound following a key returned in a listing. The keys under a common prefix all precede the prefix with
+    /// its trailing `/` replaced by `0`, the next character.
+    fn after(key: &str) -> Bound<String> {
+        match key.strip_suffix('/') {
+            Some(directory) => Bound::Included(format!("{directory}0")),
+            None => Bound::Excluded(key.to_owned()),
+        }
+    }
+
+    /// Load the latest report delivered to `location` of the objects under `s3_path`, unless it is `current_report`.
+    async fn load(
+        client: &impl ObjectClient,
+        location: &S3Path,
+        s3_path: &S3Path,
+        current_report: Option<&str>,
+    ) -> Result<Option<Self>, InventoryError> {
+        let mut reports = list_reports(client, location).await?;
+        // Reports are named after the time they were created, so sort in that order. The latest report might still
+        // be in progress, in which case it has no manifest yet.
+        reports.sort_unstable_by(|a, b| b.cmp(a));
+        for report in reports {
+            let manifest_key = format!("{report}{MANIFEST_FILE_NAME}");
+            if current_report == Some(manifest_key.as_str()) {
+                return Ok(None);
+            }
+            let Some(manifest) = get_object(client, &location.bucket, &manifest_key).await? else {
+                continue;
+            };
+            let manifest: InventoryManifest = serde_json::from_slice(&manifest)
+                .map_err(|e| InventoryError::InvalidManifest(manifest_key.clone(), e))?;
+            let objects = manifest.load_objects(client, &manifest_key, s3_path).await?;
+            return Ok(Some(Self::new(manifest_key, objects)));
+        }
+        Err(InventoryError::NoReport(format!(
+            "s3://{}/{}",
+            location.bucket, location.prefix
+        )))
+    }
+}
+
+/// List the directories of the reports delivered to `location`.
+async fn list_reports(client: &impl ObjectClient, location: &S3Path) -> Result<Vec<String>, InventoryError> {
+    let prefix = location.prefix.as_str();
+    let mut reports = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let result = client
+            .list_objects(&location.bucket, continuation_token.as_deref(), "/", 1000, prefix)
+            .await
+            .map_err(|e| InventoryError::ClientError {
+                operation: "ListObjectsV2",
+                key: prefix.to_owned(),
+                source: e.into(),
+            })?;
+        reports.extend(
+            result
+                .common_prefixes
+                .into_iter()
+                .filter(|report| is_report_name(&report[prefix.len()..report.len() - 1])),
+        );
+        continuation_token = result.next_continuation_token;
+        if continuation_token.is_none() {
+            return Ok(reports);
+        }
+    }
+}
+
+/// Whether the name is a time of the form `YYYY-MM-DDTHH-MMZ`, which the directories of reports are named after. The
+/// other directories at the location hold the data files of the reports and Hive tables.
+fn is_report_name(name: &str) -> bool {
+    const PATTERN: &[u8] = b"0000-00-00T00-00Z";
+    name.len() == PATTERN.len()
+        && name
+            .bytes()
+            .zip(PATTERN)
+            .all(|(c, &p)| if p == b'0' { c.is_ascii_digit() } else { c == p })
+}
+
+/// Fetch the content of an object, or `None` if it does not exist.
+async fn get_object(client: &impl ObjectClient, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, InventoryError> {
+    let client_error = |e: Box<dyn std::error::Error + Send + Sync>| InventoryError::ClientError {
+        operation: "GetObject",
+        key: key.to_owned(),
+        source: e,
+    };
+    let mut response = match client.get_object(bucket, key, &GetObjectParams::new()).await {
+        Ok(response) => response,
+        Err(ObjectClientError::ServiceError(GetObjectError::NoSuchKey(_))) => return Ok(None),
+        Err(e) => return Err(client_error(e.into())),
+    };
+    let mut backpressure_handle = response.backpressure_handle().cloned();
+    if let Some(handle) = backpressure_handle.as_mut() {
+        handle.ensure_read_window(READ_WINDOW_SIZE);
+    }
+
+    let mut body = Vec::new();
+    pin_mut!(response);
+    while let Some(part) = response.next().await {
+        let GetBodyPart { offset, data } = part.map_err(|e| client_error(e.into()))?;
+        body.extend_from_slice(&data);
+        if let Some(handle) = backpressure_handle.as_mut() {
+            handle.ensure_read_window(offset + data.len() as u64 + READ_WINDOW_SIZE);
+        }
+    }
+    Ok(Some(body))
+}
+
+/// The `manifest.json` file describing a report.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InventoryManifest {
+    source_bucket: String,
+    /// ARN of the bucket the files of the report are in.
+    destination_bucket: String,
+    file_format: String,
+    file_schema: String,
+    files: Vec<InventoryFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InventoryFile {
+    key: String,
+}
+
+/// Position of the fields of a report in the rows of its files.
+struct InventorySchema {
+    key: usize,
+    size: usize,
+    last_modified: usize,
+    etag: usize,
+    storage_class: Option<usize>,
+    is_latest: Option<usize>,
+    is_delete_marker: Option<usize>,
+}
+
+impl InventoryManifest {
+    async fn load_objects(
+        &self,
+        client: &impl ObjectClient,
+        manifest_key: &str,
+        s3_path: &S3Path,
+    ) -> Result<BTreeMap<String, InventoryObject>, InventoryError> {
+        if self.source_bucket != s3_path.bucket.as_str() {
+            return Err(InventoryError::WrongBucket(
+                manifest_key.to_owned(),
+                self.source_bucket.clone(),
+            ));
+        }
+        if self.file_format != "CSV" {
+            return Err(InventoryError::UnsupportedFormat(
+                manifest_key.to_owned(),
+                self.file_format.clone(),
+            ));
+        }
+        let schema = self.schema(manifest_key)?;
+        // Reports are delivered to the bucket with this ARN, whatever its partition.
+        let bucket = self
+            .destination_bucket
+            .rsplit_once(":::")
+            .map_or(self.destination_bucket.as_str(), |(_, bucket)| bucket);
+
+        let mut objects = BTreeMap::new();
+        for file in &self.files {
+            let Some(content) = get_object(client, bucket, &file.key).await? else {
+                return Err(InventoryError::ClientError {
+                    operation: "GetObject",
+                    key: file.key.clone(),
+                    source: "inventory file does not exist".into(),
+                });
+            };
+            let content = gzip::decompress(&content).map_err(|e| InventoryError::Decompression(file.key.clone(), e))?;
+            schema.parse_file(&file.key, &content, s3_path, &mut objects)?;
+        }
+        Ok(objects)
+    }
+
+    fn schema(&self, manifest_key: &str) -> Result<InventorySchema, InventoryError> {
+        let fields: Vec<&str> = self.file_schema.split(',').map(str::trim).collect();
+        let optional = |name: &str| fields.iter().position(|field| *field == name);
+        let required = |name: &'static str| {
+            optional(name).ok_or_else(|| InventoryError::MissingField(manifest_key.to_owned(), name))
+        };
+        Ok(InventorySchema {
+            key: required("Key")?,
+            size: required("Size")?,
+            last_modified: required("LastModifiedDate")?,
+            etag: required("ETag")?,
+            storage_class: optional("StorageClass"),
+            is_latest: optional("IsLatest"),
+            is_delete_marker: optional("IsDeleteMarker"),
+        })
+    }
+}
+
+impl InventorySchema {
+    /// Add the current objects of a decompressed file of the report under `s3_path` to `objects`.
+    fn parse_file(
+        &self,
+        file_key: &str,
+        content: &[u8],
+        s3_path: &S3Path,
+        objects: &mut BTreeMap<String, InventoryObject>,
+    ) -> Result<(), InventoryError> {
+        let invalid_row = |message: String| InventoryError::InvalidRow(file_key.to_owned(), message);
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content);
+        for record in reader.records() {
+            let record = record.map_err(|e| invalid_row(e.to_string()))?;
+            let field = |index: usize| record.get(index).unwrap_or_default();
+
+            // Reports of versioned buckets list every version, of which only the latest is visible.
+            let is_current = self.is_latest.is_none_or(|index| field(index) == "true")
+                && self.is_delete_marker.is_none_or(|index| field(index) != "true");
+            if !is_current {
+                continue;
+            }
+
+            // Keys are URL-encoded in CSV reports.
+            let Ok(key) = percent_decode_str(field(self.key)).decode_utf8() else {
+                debug!(key = field(self.key), "skipping inventory key that is not valid UTF-8");
+                continue;
+            };
+            if !key.starts_with(s3_path.prefix.as_str()) {
+                continue;
+            }
+
+            let size = field(self.size)
+                .parse()
+                .map_err(|_| invalid_row(format!("invalid size {:?} of key {key:?}", field(self.size))))?;
+            let last_modified = OffsetDateTime::parse(field(self.last_modified), &Rfc3339).map_err(|_| {
+                invalid_row(format!(
+                    "invalid last modified date {:?} of key {key:?}",
+                    field(self.last_modified)
+                ))
+            })?;
+            // Unlike in S3 responses, ETags are not quoted in reports.
+            let etag = match field(self.etag) {
+                etag if etag.starts_with('"') => etag.to_owned(),
+                etag => format!("\"{etag}\""),
+            };
+            let storage_class = self
+                .storage_class
+                .map(field)
+                .filter(|storage_class| !storage_class.is_empty())
+                .map(str::to_owned);
+            let object = InventoryObject {
+                size,
+                last_modified,
+                etag,
+                storage_class,
+                version_id: None,
+            };
+            objects.insert(key.into_owned(), object);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use futures::executor::block_on;
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject};
+    use test_case::test_case;
+
+    use super::*;
+    use crate::s3::{Bucket, Prefix};
+
+    const SCHEMA: &str = "Bucket, Key, Size, LastModifiedDate, ETag, StorageClass";
+
+    /// Add a report created at `date` to the inventory `location` of the mock bucket, with a single file holding the
+    /// `rows` of the given schema.
+    pub fn add_report(client: &MockClient, location: &str, date: &str, schema: &str, rows: &[&str]) {
+        let file_key = format!("{location}data/{date}.csv.gz");
+        let content = rows.iter().map(|row| format!("{row}\n")).collect::<String>();
+        client.add_object(&file_key, gzip::tests::compress_stored(content.as_bytes()).into());
+        let manifest = serde_json::json!({
+            "sourceBucket": "test_bucket",
+            "destinationBucket": "arn:aws:s3:::test_bucket",
+            "version": "2016-11-30",
+            "fileFormat": "CSV",
+            "fileSchema": schema,
+            "files": [{"key": file_key, "size": 0, "MD5checksum": ""}],
+        });
+        client.add_object(
+            &format!("{location}{date}/{MANIFEST_FILE_NAME}"),
+            manifest.to_string().into(),
+        );
+    }
+
+    fn object(size: u64) -> InventoryObject {
+        InventoryObject {
+            size,
+            last_modified: OffsetDateTime::UNIX_EPOCH,
+            etag: "\"etag\"".to_owned(),
+            storage_class: None,
+            version_id: None,
+        }
+    }
+
+    fn index(keys: &[&str]) -> InventoryIndex {
+        InventoryIndex {
+            report: "report".to_owned(),
+            objects: keys.iter().map(|key| (key.to_string(), object(1))).collect(),
+        }
+    }
+
+    fn s3_path(prefix: &str) -> S3Path {
+        S3Path::new(Bucket::new("test_bucket").unwrap(), Prefix::new(prefix).unwrap())
+    }
+
+    #[test]
+    fn test_list() {
+        let index = index(&[
+            "a", "b/", "b/c", "b/d/e", "b/d0", "b0", "c/d", "c/e/f", "c/e/g", "c/h", "d",
+        ]);
+        let list = |prefix: &str, max_keys: usize| {
+            let mut entries = Vec::new();
+            let mut continuation_token = None;
+            loop {
+                let page = index.list(prefix, continuation_token.as_deref(), max_keys);
+                assert!(page.common_prefixes.len() + page.objects.len() <= max_keys);
+                entries.extend(page.common_prefixes);
+                entries.extend(page.objects.into_iter().map(|(key, _)| key));
+                continuation_token = page.next_continuation_token;
+                if continuation_token.is_none() {
+                    entries.sort();
+                    return entries;
+                }
+            }
+        };
+        for max_keys in [1, 2, 1000] {
+            assert_eq!(list("", max_keys), ["a", "b/", "b0", "c/", "d"]);
+            assert_eq!(list("b/", max_keys), ["b/c", "b/d/", "b/d0"]);
+            assert_eq!(list("c/", max_keys), ["c/d", "c/e/", "c/h"]);
+            assert_eq!(list("c/e/", max_keys), ["c/e/f", "c/e/g"]);
+            assert!(list("e/", max_keys).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let index = index(&["a", "a/b", "c", "d/", "e/f"]);
+        assert_eq!(index.lookup("a"), Some(InventoryLookup::Directory));
+        assert_eq!(index.lookup("c"), Some(InventoryLookup::File(&object(1))));
+        assert_eq!(index.lookup("d"), Some(InventoryLookup::Directory));
+        assert_eq!(index.lookup("e"), Some(InventoryLookup::Directory));
+        assert_eq!(index.lookup("e/f"), Some(InventoryLookup::File(&object(1))));
+        assert_eq!(index.lookup("b"), None);
+        assert_eq!(index.lookup("e/g"), None);
+    }
+
+    #[test_case("2024-01-02T01-00Z", true; "report")]
+    #[test_case("data", false; "data files")]
+    #[test_case("hive", false; "hive tables")]
+    #[test_case("2024-01-02T01-00", false; "truncated")]
+    #[test_case("2024-01-02X01-00Z", false; "invalid separator")]
+    fn test_is_report_name(name: &str, expected: bool) {
+        assert_eq!(is_report_name(name), expected);
+    }
+
+    #[test]
+    fn test_load() {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+        let location = S3Path::new(
+            Bucket::new("test_bucket").unwrap(),
+            Prefix::new("inventory/test_bucket/config/").unwrap(),
+        );
+        add_report(
+            &client,
+            "inventory/test_bucket/config/",
+            "2024-01-01T01-00Z",
+            SCHEMA,
+            &[r#""test_bucket","data/old","1","2024-01-01T00:00:00.000Z","etag","STANDARD""#],
+        );
+        add_report(
+            &client,
+            "inventory/test_bucket/config/",
+            "2024-01-02T01-00Z",
+            "Bucket, Key, VersionId, IsLatest, IsDeleteMarker, Size, LastModifiedDate, ETag",
+            &[
+                r#""test_bucket","data/a%20b","v1","true","false","10","2024-01-02T00:00:00.000Z","etag1""#,
+                r#""test_bucket","data/c","v2","false","false","20","2024-01-01T00:00:00.000Z","etag2""#,
+                r#""test_bucket","data/c","v3","true","true","","2024-01-02T00:00:00.000Z","""#,
+                r#""test_bucket","other/d","v4","true","false","30","2024-01-02T00:00:00.000Z","etag4""#,
+            ],
+        );
+        // The latest report is still in progress.
+        client.add_object(
+            "inventory/test_bucket/config/2024-01-03T01-00Z/other",
+            MockObject::from(b"x"),
+        );
+
+        let index = block_on(InventoryIndex::load(&client, &location, &s3_path("data/"), None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            index.report,
+            "inventory/test_bucket/config/2024-01-02T01-00Z/manifest.json"
+        );
+        assert_eq!(index.objects.keys().collect::<Vec<_>>(), ["data/a b"]);
+        assert_eq!(
+            index.objects["data/a b"],
+            InventoryObject {
+                size: 10,
+                last_modified: OffsetDateTime::parse("2024-01-02T00:00:00Z", &Rfc3339).unwrap(),
+                etag: "\"etag1\"".to_owned(),
+                storage_class: None,
+                version_id: None,
+            }
+        );
+
+        let reloaded = block_on(InventoryIndex::load(
+            &client,
+            &location,
+            &s3_path("data/"),
+            Some(&index.report),
+        ))
+        .unwrap();
+        assert!(reloaded.is_none(), "the report is already loaded");
+
+        let empty_location = S3Path::new(Bucket::new("test_bucket").unwrap(), Prefix::new("empty/").unwrap());
+        let error = block_on(InventoryIndex::load(&client, &empty_location, &s3_path(""), None)).unwrap_err();
+        assert!(matches!(error, InventoryError::NoReport(_)));
+    }
+
+    #[test_case("Bucket, Key, Size, LastModifiedDate", "CSV", "does not include the ETag field"; "missing field")]
+    #[test_case(SCHEMA, "Parquet", "only CSV reports are supported"; "unsupported format")]
+    fn test_load_invalid_report(schema: &str, file_format: &str, expected_error: &str) {
+        let client = MockClient::config().bucket("test_bucket").part_size(1024).build();
+        add_report(&client, "inventory/", "2024-01-01T01-00Z", schema, &[]);
+        let manifest_key = format!("inventory/2024-01-01T01-00Z/{MANIFEST_FILE_NAME}");
+        let manifest = String::from_utf8(
+            block_on(get_object(&client, "test_bucket", &manifest_key))
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap()
+        .replace("\"CSV\"", &format!("\"{file_format}\""));
+        client.add_object(&manifest_key, manifest.into());
+
+        let location = S3Path::new(Bucket::new("test_bucket").unwrap(), Prefix::new("inventory/").unwrap());
+        let error = block_on(InventoryIndex::load(&client, &location, &s3_path(""), None)).unwrap_err();
+        assert!(error.to_string().contains(expected_error), "unexpected error: {error}");
+    }
+}
@@ -0,0 +1,438 @@
+//! Decompression of gzip files ([RFC 1952]), which S3 Inventory writes its CSV reports as.
+//!
+//! The compressed data is a DEFLATE stream ([RFC 1951]), decoded one bit at a time against canonical Huffman codes in
+//! the style of zlib's `puff.c`. This is slower than a table-driven decoder, but inventory reports are only read when
+//! they are published, on the background task refreshing the listing.
+//!
+//! [RFC 1952]: https://www.rfc-editor.org/rfc/rfc1952
+//! [RFC 1951]: https://www.rfc-editor.org/rfc/rfc1951
+
+use mountpoint_s3_client::checksums::crc32;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GzipError {
+    #[error("not a gzip file")]
+    InvalidHeader,
+    #[error("unexpected end of gzip file")]
+    Truncated,
+    #[error("invalid compressed data: {0}")]
+    InvalidData(&'static str),
+    #[error("checksum of decompressed data does not match")]
+    ChecksumMismatch,
+}
+
+/// Maximum length of a Huffman code.
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits of the length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of the distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+/// Order the code lengths of the code length alphabet are written in by dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// Decompress a gzip file, which may be made of several members.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, GzipError> {
+    let mut output = Vec::with_capacity(input.len().saturating_mul(4));
+    let mut position = 0;
+    loop {
+        position = decompress_member(input, position, &mut output)?;
+        if position == input.len() {
+            return Ok(output);
+        }
+    }
+}
+
+/// Decompress the member starting at `position` to the end of `output`, and return the position of the next member.
+fn decompress_member(input: &[u8], position: usize, output: &mut Vec<u8>) -> Result<usize, GzipError> {
+    let header = input.get(position..position + 10).ok_or(GzipError::InvalidHeader)?;
+    if header[0..3] != [0x1f, 0x8b, 0x08] {
+        return Err(GzipError::InvalidHeader);
+    }
+    let flags = header[3];
+    let mut position = position + 10;
+    if flags & FLAG_EXTRA != 0 {
+        let length = input.get(position..position + 2).ok_or(GzipError::Truncated)?;
+        position += 2 + u16::from_le_bytes([length[0], length[1]]) as usize;
+    }
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            let end = input
+                .get(position..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or(GzipError::Truncated)?;
+            position += end + 1;
+        }
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        position += 2;
+    }
+    let data = input.get(position..).ok_or(GzipError::Truncated)?;
+
+    let start = output.len();
+    let mut reader = BitReader::new(data);
+    inflate(&mut reader, output)?;
+    let position = position + reader.position;
+
+    let trailer = input.get(position..position + 8).ok_or(GzipError::Truncated)?;
+    let expected_crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+    let expected_size = u32::from_le_bytes(trailer[4..8].try_into().unwrap());
+    let decompressed = &output[start..];
+    if crc32::checksum(decompressed).value() != expected_crc || decompressed.len() as u32 != expected_size {
+        return Err(GzipError::ChecksumMismatch);
+    }
+    Ok(position + 8)
+}
+
+/// Reads the bits of a DEFLATE stream, least significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position of the next byte to load.
+    position: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32, GzipError> {
+        while self.count < n {
+            let byte = *self.data.get(self.position).ok_or(GzipError::Truncated)?;
+            self.buffer |= (byte as u64) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = (self.buffer & ((1 << n) - 1)) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drop the bits left in the current byte. As bytes are only loaded when needed, whole bytes are never buffered,
+    /// so the next byte to read is then at [Self::position].
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], GzipError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + n)
+            .ok_or(GzipError::Truncated)?;
+        self.position += n;
+        Ok(bytes)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the symbols ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build the code from the code length of each symbol, where 0 means the symbol is not used.
+    fn new(lengths: &[u8]) -> Result<Self, GzipError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        // Incomplete codes are allowed, but not codes with more symbols than there are codes of their lengths.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(GzipError::InvalidData("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, GzipError> {
+        // Codes are packed starting from the most significant bit, so are read one bit at a time.
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(GzipError::InvalidData("invalid Huffman code"))
+    }
+}
+
+fn inflate(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), GzipError> {
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(reader, output)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                compressed_block(reader, output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                compressed_block(reader, output, &literals, &distances)?;
+            }
+            _ => return Err(GzipError::InvalidData("invalid block type")),
+        }
+        if last {
+            reader.align();
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), GzipError> {
+    reader.align();
+    let header = reader.bytes(4)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if length != !complement {
+        return Err(GzipError::InvalidData("invalid stored block length"));
+    }
+    output.extend_from_slice(reader.bytes(length as usize)?);
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), GzipError> {
+    let mut lengths = [0u8; 288];
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), GzipError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(GzipError::InvalidData("too many length or distance symbols"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_lengths.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or(GzipError::InvalidData("repeated length without a previous length"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        let end = index + repeat;
+        if end > lengths.len() {
+            return Err(GzipError::InvalidData("too many code lengths"));
+        }
+        lengths[index..end].fill(length);
+        index = end;
+    }
+    if lengths[256] == 0 {
+        return Err(GzipError::InvalidData("missing end-of-block code"));
+    }
+
+    let (literals, distances) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literals)?, Huffman::new(distances)?))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), GzipError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                if symbol >= LENGTH_BASE.len() {
+                    return Err(GzipError::InvalidData("invalid length symbol"));
+                }
+                let length = LENGTH_BASE[symbol] as usize + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+                let symbol = distances.decode(reader)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(GzipError::InvalidData("invalid distance symbol"));
+                }
+                let distance = DISTANCE_BASE[symbol] as usize + reader.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(GzipError::InvalidData("distance beyond the start of the data"));
+                }
+                // The copied range may overlap the bytes it appends, so is copied one byte at a time.
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    /// Compressed with fixed Huffman codes.
+    const FIXED: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40,
+        0x27, 0x01, 0xe3, 0x51, 0x3d, 0x8d, 0x17, 0x00, 0x00, 0x00,
+    ];
+
+    /// Compressed with dynamic Huffman codes, see [inventory_csv].
+    const DYNAMIC: [u8; 226] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x8d, 0xd3, 0x4d, 0x0a, 0xc2, 0x30, 0x10, 0x05,
+        0xe0, 0x7d, 0x8f, 0x91, 0xb5, 0x6d, 0x67, 0x26, 0xff, 0x9e, 0xc3, 0x95, 0xbb, 0xd4, 0x56, 0x29, 0x8a, 0x0b,
+        0xa9, 0xf7, 0x37, 0xd8, 0x41, 0x12, 0x6c, 0x42, 0x21, 0x81, 0x90, 0xc0, 0x07, 0x8f, 0x79, 0x11, 0xc3, 0xfb,
+        0x72, 0x9f, 0x16, 0x71, 0x10, 0x63, 0x58, 0x42, 0x3f, 0xce, 0xaf, 0x16, 0xfa, 0xeb, 0xfc, 0x98, 0x5a, 0xe8,
+        0x86, 0xf9, 0x19, 0xef, 0x21, 0x6e, 0x02, 0x52, 0x2d, 0x60, 0x5c, 0x27, 0x80, 0xe3, 0x77, 0x75, 0x00, 0x70,
+        0x8e, 0x4f, 0xd3, 0x12, 0x6e, 0xf1, 0x08, 0xa2, 0x11, 0xff, 0x14, 0xae, 0x14, 0x32, 0x85, 0x91, 0x49, 0x35,
+        0x2a, 0x69, 0xb8, 0xa9, 0xd1, 0xaa, 0x11, 0x6b, 0x04, 0xca, 0xa5, 0x9a, 0x2c, 0x69, 0xb4, 0xa9, 0x71, 0x4c,
+        0xc9, 0x9a, 0x04, 0x4b, 0xa9, 0xa6, 0x4a, 0x9a, 0xac, 0x25, 0x55, 0xac, 0x29, 0xf0, 0x26, 0xd5, 0x74, 0x49,
+        0x53, 0xb5, 0xa4, 0x9a, 0x35, 0x8d, 0x94, 0x4d, 0xc1, 0x94, 0x34, 0x5d, 0x4b, 0x6a, 0x58, 0x33, 0xa8, 0xb2,
+        0x29, 0xd8, 0x92, 0x66, 0x6a, 0x49, 0x2d, 0x6b, 0x16, 0x4d, 0x36, 0x05, 0x57, 0xd2, 0x6c, 0x2d, 0xa9, 0x63,
+        0xcd, 0xa1, 0xcf, 0xa6, 0xe0, 0x4b, 0x9a, 0xab, 0x25, 0xf5, 0xac, 0x79, 0x42, 0xb3, 0xab, 0xbd, 0xbe, 0xda,
+        0x5e, 0x48, 0xea, 0x0b, 0xbb, 0xfa, 0x1b, 0x6a, 0x59, 0xf1, 0xf7, 0x1d, 0x90, 0x8c, 0xda, 0xd5, 0xe0, 0x41,
+        0x34, 0x1f, 0xb0, 0xc1, 0x7d, 0xc1, 0xa9, 0x03, 0x00, 0x00,
+    ];
+
+    /// Stored without compression, with a file name in the header.
+    const STORED: [u8; 45] = [
+        0x1f, 0x8b, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x72, 0x65, 0x70, 0x6f, 0x72, 0x74, 0x2e, 0x63,
+        0x73, 0x76, 0x00, 0x01, 0x0b, 0x00, 0xf4, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20, 0x64, 0x61, 0x74,
+        0x61, 0x11, 0x55, 0xd7, 0x99, 0x0b, 0x00, 0x00, 0x00,
+    ];
+
+    fn inventory_csv() -> Vec<u8> {
+        (0..12)
+            .map(|i| {
+                format!(
+                    "\"bucket\",\"data/dir-{}/file-{i}.bin\",\"{}\",\"2024-01-0{}T00:00:00.000Z\",\"etag{i:04x}\"\n",
+                    i % 3,
+                    i * 1024,
+                    i % 9 + 1
+                )
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    /// Compress `data` as a gzip file of stored blocks.
+    pub fn compress_stored(data: &[u8]) -> Vec<u8> {
+        let mut output = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+        let mut chunks = data.chunks(u16::MAX as usize).peekable();
+        if chunks.peek().is_none() {
+            output.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+        }
+        while let Some(chunk) = chunks.next() {
+            output.push(chunks.peek().is_none() as u8);
+            output.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            output.extend_from_slice(&(!(chunk.len() as u16)).to_le_bytes());
+            output.extend_from_slice(chunk);
+        }
+        output.extend_from_slice(&crc32::checksum(data).value().to_le_bytes());
+        output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        output
+    }
+
+    #[test_case(&FIXED, b"hello hello hello hello"; "fixed codes")]
+    #[test_case(&STORED, b"stored data"; "stored")]
+    fn test_decompress(input: &[u8], expected: &[u8]) {
+        assert_eq!(decompress(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_decompress_dynamic_codes() {
+        assert_eq!(decompress(&DYNAMIC).unwrap(), inventory_csv());
+    }
+
+    #[test]
+    fn test_decompress_members() {
+        let input = [&FIXED[..], &STORED[..], &compress_stored(b"")[..]].concat();
+        assert_eq!(decompress(&input).unwrap(), b"hello hello hello hellostored data");
+    }
+
+    #[test]
+    fn test_compress_stored() {
+        let data = (0..200_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        assert_eq!(decompress(&compress_stored(&data)).unwrap(), data);
+        assert_eq!(decompress(&compress_stored(b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_decompress_invalid() {
+        assert_eq!(decompress(b"not gzip data"), Err(GzipError::InvalidHeader));
+        assert_eq!(decompress(&DYNAMIC[..100]), Err(GzipError::Truncated));
+
+        let mut corrupted = DYNAMIC;
+        corrupted[DYNAMIC.len() - 8] ^= 1;
+        assert_eq!(decompress(&corrupted), Err(GzipError::ChecksumMismatch));
+
+        let mut corrupted = STORED;
+        corrupted[22] ^= 1;
+        assert!(matches!(decompress(&corrupted), Err(GzipError::InvalidData(_))));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::time::{Duration, Instant};
 
 use linked_hash_map::LinkedHashMap;
@@ -18,7 +19,7 @@ pub struct NegativeCache {
     ttl: Duration,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct Key {
     parent_ino: InodeNo,
     child_name: String,
@@ -74,6 +75,21 @@ impl NegativeCache {
         .record(start.elapsed().as_micros() as f64);
     }
 
+    /// Remove the entries of the children of the given directories, and return them as the directory and the name.
+    pub fn remove_children(&self, parent_inos: &HashSet<InodeNo>) -> Vec<(InodeNo, String)> {
+        let mut map = self.map.write().unwrap();
+        let keys: Vec<Key> = map
+            .keys()
+            .filter(|key| parent_inos.contains(&key.parent_ino))
+            .cloned()
+            .collect();
+        for key in &keys {
+            map.remove(key);
+        }
+        metrics::gauge!("metadata_cache.negative_cache.entries").set(map.len() as f64);
+        keys.into_iter().map(|key| (key.parent_ino, key.child_name)).collect()
+    }
+
     /// Insert an entry into the cache. If the entry already existed,
     /// update its TTL.
     /// Upon insertion, remove entries that exceed the cache limit or
@@ -114,6 +130,7 @@ impl NegativeCache {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::thread::sleep;
     use std::time::{Duration, Instant};
 
@@ -163,6 +180,31 @@ mod tests {
         assert!(cache.contains(2, "child1"));
     }
 
+    #[test]
+    fn test_remove_children() {
+        let cache = NegativeCache::new(100, Duration::from_secs(60));
+
+        cache.insert(1, "child1");
+        cache.insert(2, "child1");
+        cache.insert(3, "child1");
+        cache.insert(1, "child2");
+
+        let mut removed = cache.remove_children(&HashSet::from([1, 3]));
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                (1, "child1".to_owned()),
+                (1, "child2".to_owned()),
+                (3, "child1".to_owned())
+            ]
+        );
+        assert!(!cache.contains(1, "child1"));
+        assert!(!cache.contains(1, "child2"));
+        assert!(cache.contains(2, "child1"));
+        assert!(!cache.contains(3, "child1"));
+    }
+
     #[test]
     fn test_max_size() {
         let cache = NegativeCache::new(2, Duration::from_secs(60));
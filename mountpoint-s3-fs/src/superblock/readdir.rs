@@ -35,24 +35,62 @@
 //!   depending on if the S3 implementation returns ordered or unordered list results.
 //! * [RemoteIter] is an iterator over [ReaddirEntry]s returned by paginated calls to ListObjectsV2.
 //!   Rather than directly streaming the entries out of the list call, it collects them in memory
-//!   and re-sorts them to handle point 3.
+//!   and re-sorts them to handle point 3. With a [ReaddirReadahead], the pages are fetched by a
+//!   background task ahead of the stream, so that listing large directories doesn't stall on every
+//!   page boundary.
 //! * A collection or iterator of [ReaddirEntry]s is built up and used by [ReaddirIter],
 //!   representing the local children of the directory.
 //!   These children are listed only once, at the start of the readdir operation, and so are a
 //!   snapshot in time of the directory.
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::ffi::OsString;
+use std::fmt::Debug;
+use std::time::Duration;
 
 use super::{InodeKindData, LookedUpInode, RemoteLookup, SuperblockInner};
+use crate::async_util::Runtime;
+use crate::fs::{ArchivedObjectsPolicy, Compression, KeyFilter, SHADOWED_FILE_SUFFIX, ShadowingPolicy, is_archived};
 use crate::metablock::{InodeError, InodeKind, InodeNo, InodeStat};
+use crate::s3::KeyMapping;
 use crate::superblock::ValidName;
+use crate::superblock::inventory::InventoryIndex;
 use crate::sync::atomic::{AtomicI64, Ordering};
-use crate::sync::{AsyncMutex, Mutex};
+use crate::sync::{Arc, AsyncMutex, Mutex, async_channel};
+use futures::task::SpawnExt;
 use mountpoint_s3_client::ObjectClient;
-use mountpoint_s3_client::types::RestoreStatus;
+use mountpoint_s3_client::types::{ListObjectsResult, RestoreStatus};
 use time::OffsetDateTime;
-use tracing::{error, trace, warn};
+use tracing::{debug, error, trace, warn};
+
+/// Maximum size of the objects of symlinks, which hold their target, see [super::SuperblockConfig::symlinks].
+const MAX_SYMLINK_OBJECT_SIZE: usize = libc::PATH_MAX as usize;
+
+/// Fetching the pages of directory listings ahead of the readdir stream, see
+/// [crate::SuperblockConfig::readdir_readahead].
+#[derive(Clone)]
+pub struct ReaddirReadahead {
+    runtime: Runtime,
+    max_pages: usize,
+}
+
+impl ReaddirReadahead {
+    /// Fetch pages on `runtime`, keeping up to `max_pages` pages ahead of the stream of each directory handle.
+    pub fn new(runtime: Runtime, max_pages: usize) -> Self {
+        assert!(max_pages > 0, "at least one page should be fetched ahead");
+        Self { runtime, max_pages }
+    }
+}
+
+impl Debug for ReaddirReadahead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReaddirReadahead")
+            .field("max_pages", &self.max_pages)
+            .finish_non_exhaustive()
+    }
+}
+
+type ListPageResult = Result<ListObjectsResult, InodeError>;
 
 /// Handle for an inflight directory listing
 #[derive(Debug)]
@@ -64,12 +102,13 @@ pub struct ReaddirHandle {
 }
 
 impl ReaddirHandle {
-    pub(super) fn new<OC: ObjectClient + Send + Sync>(
+    pub(super) fn new<OC: ObjectClient + Clone + Send + Sync + 'static>(
         inner: &SuperblockInner<OC>,
         dir_ino: InodeNo,
         parent_ino: InodeNo,
         full_path: String,
         page_size: usize,
+        index: Option<Arc<InventoryIndex>>,
     ) -> Result<Self, InodeError> {
         let local_entries = {
             let inode = inner.get(dir_ino)?;
@@ -105,10 +144,60 @@ impl ReaddirHandle {
             }
         };
 
+        let mut remote = RemoteIter::new(
+            &inner.s3_path.bucket,
+            &full_path,
+            page_size,
+            inner.config.s3_personality.is_list_ordered(),
+            inner.config.key_mapping(),
+        );
+        remote.hide_archived = inner.config.archived_objects == ArchivedObjectsPolicy::Hidden;
+        // The entries of versions directories are named by version IDs, which are not filtered.
+        if !inner.config.key_filter.is_empty() && inner.versions_dir_object(&full_path).is_none() {
+            remote.key_filter = Some(inner.config.key_filter.clone());
+            remote.mount_prefix_len = inner.s3_path.prefix.as_str().len();
+        }
+        let dir = inner.get(dir_ino)?;
+        let mut recent_writes: Vec<_> = inner
+            .recent_writes
+            .children(dir.key())
+            .into_iter()
+            .filter_map(|(name, stat)| match stat {
+                None => inner
+                    .is_visible(&dir, &name, InodeKind::Directory)
+                    .then_some(ReaddirEntry::RemotePrefix { name }),
+                Some(stat) => inner
+                    .is_visible(&dir, &name, InodeKind::File)
+                    .then_some(ReaddirEntry::RemoteObject {
+                        full_key: format!("{full_path}{name}"),
+                        name,
+                        size: stat.size as u64,
+                        last_modified: stat.mtime,
+                        storage_class: None,
+                        restore_status: None,
+                        etag: stat.etag?.into(),
+                        version_id: None,
+                        shadowed: false,
+                        decompressed: None,
+                    }),
+            })
+            .collect();
+        recent_writes.sort();
+        remote.recent_writes = recent_writes.into();
+        if let Some(inventory) = index {
+            remote.inventory = Some(inventory);
+        } else if let Some(readahead) = &inner.config.readdir_readahead {
+            remote.start_readahead(inner.client.clone(), readahead);
+        }
         let iter = if inner.config.s3_personality.is_list_ordered() {
-            ReaddirIter::ordered(&inner.s3_path.bucket, &full_path, page_size, local_entries.into())
+            ReaddirIter::ordered(
+                remote,
+                local_entries.into(),
+                inner.config.shadowing,
+                inner.config.decompress_files,
+            )
         } else {
-            ReaddirIter::unordered(&inner.s3_path.bucket, &full_path, page_size, local_entries.into())
+            ReaddirIter::unordered(remote, local_entries.into(), inner.config.decompress_files)
         };
 
         Ok(Self {
@@ -188,16 +277,42 @@ impl ReaddirHandle {
                 etag,
                 storage_class,
                 restore_status,
+                version_id,
+                shadowed,
+                decompressed,
                 ..
             } => {
-                let stat = InodeStat::for_file(
+                // Only HeadObject returns the user-defined metadata and the content encoding of objects, which
+                // listings don't. When the attributes of a file may depend on them, such as its symlink target, its
+                // modification time, permissions or access time, or the size of its decoded or decompressed content,
+                // its entry expires at once, so that it is looked up again with HeadObject before being used.
+                let validity = if inner.config.mtime_metadata
+                    || inner.config.mode_metadata
+                    || inner.config.atime_metadata
+                    || inner.config.decode_content_encoding
+                    || decompressed.is_some()
+                    || (inner.config.symlinks && *size as usize <= MAX_SYMLINK_OBJECT_SIZE)
+                {
+                    Duration::ZERO
+                } else {
+                    inner.config.cache_config.file_ttl
+                };
+                let mut stat = InodeStat::for_file(
                     *size as usize,
                     *last_modified,
                     Some(etag.as_str().into()),
                     storage_class.as_deref(),
                     *restore_status,
-                    inner.config.cache_config.file_ttl,
+                    validity,
                 );
+                stat.version_id = version_id.as_deref().map(Into::into);
+                stat.pinned_version = version_id.is_some();
+                stat.shadowed = *shadowed;
+                // The size of the decompressed content is only known once the file is looked up again.
+                stat.decompressed = *decompressed;
+                if decompressed.is_some() {
+                    stat.encoded_size = Some(stat.size);
+                }
                 RemoteLookup {
                     stat,
                     kind: InodeKind::File,
@@ -234,6 +349,14 @@ enum ReaddirEntry {
         restore_status: Option<RestoreStatus>,
         /// Entity tag of this object.
         etag: String,
+        /// Version of this object to read, for the objects of snapshots.
+        version_id: Option<String>,
+        /// Whether the object is shown with the [SHADOWED_FILE_SUFFIX] appended to its name, as its key is shadowed
+        /// by a directory.
+        shadowed: bool,
+        /// Compression of the object, if it is shown with the name of its decompressed content, without the extension
+        /// of the compression, see [crate::SuperblockConfig::decompress_files].
+        decompressed: Option<Compression>,
     },
     LocalInode {
         lookup: LookedUpInode,
@@ -241,7 +364,7 @@ enum ReaddirEntry {
 }
 
 // This looks a little silly but makes the [Ord] implementation for [ReaddirEntry] a bunch clearer
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
 enum ReaddirEntryKind {
     RemotePrefix,
     RemoteObject,
@@ -265,6 +388,21 @@ impl ReaddirEntry {
         }
     }
 
+    /// Show the object of this entry with the name of its decompressed content if its name has the extension of a
+    /// [Compression], see [crate::SuperblockConfig::decompress_files]. Returns whether it was renamed.
+    fn decompress(&mut self) -> bool {
+        let Self::RemoteObject { name, decompressed, .. } = self else {
+            return false;
+        };
+        let Some((file_name, compression)) = Compression::strip_extension(name) else {
+            return false;
+        };
+        let len = file_name.len();
+        name.truncate(len);
+        *decompressed = Some(compression);
+        true
+    }
+
     /// How to describe this entry in an error message
     fn description(&self) -> String {
         match self {
@@ -321,12 +459,22 @@ enum ReaddirIter {
 }
 
 impl ReaddirIter {
-    fn ordered(bucket: &str, full_path: &str, page_size: usize, local_entries: VecDeque<ReaddirEntry>) -> Self {
-        Self::Ordered(ordered::ReaddirIter::new(bucket, full_path, page_size, local_entries))
+    fn ordered(
+        remote: RemoteIter,
+        local_entries: VecDeque<ReaddirEntry>,
+        shadowing: ShadowingPolicy,
+        decompress_files: bool,
+    ) -> Self {
+        Self::Ordered(ordered::ReaddirIter::new(
+            remote,
+            local_entries,
+            shadowing,
+            decompress_files,
+        ))
     }
 
-    fn unordered(bucket: &str, full_path: &str, page_size: usize, local_entries: VecDeque<ReaddirEntry>) -> Self {
-        Self::Unordered(unordered::ReaddirIter::new(bucket, full_path, page_size, local_entries))
+    fn unordered(remote: RemoteIter, local_entries: VecDeque<ReaddirEntry>, decompress_files: bool) -> Self {
+        Self::Unordered(unordered::ReaddirIter::new(remote, local_entries, decompress_files))
     }
 
     async fn next(&mut self, client: &impl ObjectClient) -> Result<Option<ReaddirEntry>, InodeError> {
@@ -362,10 +510,26 @@ struct RemoteIter {
     state: RemoteIterState,
     /// Does the S3 implementation return ordered results?
     ordered: bool,
+    /// Pages fetched ahead by a background task, in order, if readahead is enabled.
+    readahead: Option<async_channel::Receiver<ListPageResult>>,
+    /// Inventory report or snapshot to list the directory from instead of S3, if one is loaded.
+    inventory: Option<Arc<InventoryIndex>>,
+    /// Translation of [Self::full_path] to the prefix of the keys listed from S3, and of their keys back to paths.
+    key_mapping: KeyMapping,
+    /// Whether to leave out the objects in archived storage classes that haven't been restored, see
+    /// [ArchivedObjectsPolicy::Hidden].
+    hide_archived: bool,
+    /// Filter of the keys to list, relative to the mount prefix, which the first `mount_prefix_len` bytes of
+    /// [Self::full_path] are, see [crate::SuperblockConfig::key_filter].
+    key_filter: Option<KeyFilter>,
+    mount_prefix_len: usize,
+    /// Entries of the objects recently written by this mount, in order, which are added to the listing if S3 doesn't
+    /// return them, see [crate::SuperblockConfig::recent_writes_window].
+    recent_writes: VecDeque<ReaddirEntry>,
 }
 
 impl RemoteIter {
-    fn new(bucket: &str, full_path: &str, page_size: usize, ordered: bool) -> Self {
+    fn new(bucket: &str, full_path: &str, page_size: usize, ordered: bool, key_mapping: KeyMapping) -> Self {
         Self {
             entries: VecDeque::new(),
             bucket: bucket.to_owned(),
@@ -373,11 +537,116 @@ impl RemoteIter {
             page_size,
             state: RemoteIterState::InProgress(None),
             ordered,
+            readahead: None,
+            inventory: None,
+            key_mapping,
+            hide_archived: false,
+            key_filter: None,
+            mount_prefix_len: 0,
+            recent_writes: VecDeque::new(),
+        }
+    }
+
+    /// Fetch the pages of the listing with a background task, ahead of the calls to [Self::next].
+    fn start_readahead<OC: ObjectClient + Send + Sync + 'static>(&mut self, client: OC, readahead: &ReaddirReadahead) {
+        let (sender, receiver) = async_channel::bounded(readahead.max_pages);
+        let bucket = self.bucket.clone();
+        let full_path = self.full_path.clone();
+        let prefix = self.key_mapping.key_for_path(&self.full_path).into_owned();
+        let delimiter = self.key_mapping.delimiter();
+        let page_size = self.page_size;
+        let task = async move {
+            let mut continuation_token = None;
+            loop {
+                let result = client
+                    .list_objects(
+                        &bucket,
+                        continuation_token.as_deref(),
+                        delimiter.as_str(),
+                        page_size,
+                        &prefix,
+                    )
+                    .await
+                    .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", &bucket, &prefix));
+                continuation_token = result
+                    .as_ref()
+                    .ok()
+                    .and_then(|page| page.next_continuation_token.clone());
+                // Stop once the listing is complete, failed, or the directory handle was released.
+                if sender.send(result).await.is_err() || continuation_token.is_none() {
+                    break;
+                }
+                trace!(prefix=?full_path, ?continuation_token, "fetched readdir page ahead");
+            }
+        };
+        match readahead.runtime.spawn(task) {
+            Ok(()) => self.readahead = Some(receiver),
+            Err(error) => warn!(?error, "unable to start readdir readahead"),
+        }
+    }
+
+    /// Add the entries of the recently written objects that are not listed to a page of the listing, see
+    /// [crate::SuperblockConfig::recent_writes_window]. Listings in order get the ones sorting before the end of each
+    /// page, and the others get them all with the last page.
+    fn add_recent_writes(&mut self, page: &mut Vec<ReaddirEntry>) {
+        if self.recent_writes.is_empty() {
+            return;
+        }
+        let finished = self.state == RemoteIterState::Finished;
+        if self.ordered {
+            let mut unlisted = Vec::new();
+            while let Some(recent) = self.recent_writes.front() {
+                if !finished && page.last().is_none_or(|last| recent > last) {
+                    break;
+                }
+                let recent = self.recent_writes.pop_front().expect("the entry was just peeked");
+                if page.binary_search(&recent).is_err() {
+                    unlisted.push(recent);
+                }
+            }
+            if !unlisted.is_empty() {
+                page.extend(unlisted);
+                page.sort();
+            }
+        } else {
+            let listed: HashSet<_> = page.iter().map(|entry| (entry.name(), entry.kind())).collect();
+            self.recent_writes
+                .retain(|recent| !listed.contains(&(recent.name(), recent.kind())));
+            if finished {
+                page.extend(self.recent_writes.drain(..));
+            }
         }
     }
 
+    /// Fetch the next page of the listing, from the readahead task if there is one.
+    async fn fetch_page(&mut self, client: &impl ObjectClient, continuation_token: Option<String>) -> ListPageResult {
+        if let Some(readahead) = &self.readahead {
+            match readahead.recv().await {
+                Ok(result) => return result,
+                Err(_) => {
+                    // The task can only stop early if it could not be polled to completion, for example when the
+                    // runtime shuts down. Fetching the remaining pages directly keeps the stream consistent.
+                    warn!(prefix=?self.full_path, "readdir readahead stopped unexpectedly");
+                    self.readahead = None;
+                }
+            }
+        }
+        let prefix = self.key_mapping.key_for_path(&self.full_path);
+        client
+            .list_objects(
+                &self.bucket,
+                continuation_token.as_deref(),
+                self.key_mapping.delimiter().as_str(),
+                self.page_size,
+                &prefix,
+            )
+            .await
+            .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", &self.bucket, &prefix))
+    }
+
     async fn next(&mut self, client: &impl ObjectClient) -> Result<Option<ReaddirEntry>, InodeError> {
-        if self.entries.is_empty() {
+        // Loop because all the entries of a page may be left out.
+        while self.entries.is_empty() {
             let continuation_token = match &mut self.state {
                 RemoteIterState::Finished => {
                     trace!(self=?self as *const _, prefix=?self.full_path, "remote iter finished");
@@ -388,41 +657,82 @@ impl RemoteIter {
 
             trace!(self=?self as *const _, prefix=?self.full_path, ?continuation_token, "continuing remote iter");
 
-            let result = client
-                .list_objects(
-                    &self.bucket,
-                    continuation_token.as_deref(),
-                    "/",
-                    self.page_size,
-                    self.full_path.as_str(),
-                )
-                .await
-                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", &self.bucket, &self.full_path))?;
-
-            self.state = match result.next_continuation_token {
-                Some(token) => RemoteIterState::InProgress(Some(token)),
-                None => RemoteIterState::Finished,
+            let (mut common_prefixes, mut objects, next_continuation_token) = match &self.inventory {
+                Some(inventory) => {
+                    let page = inventory.list(&self.full_path, continuation_token.as_deref(), self.page_size);
+                    let objects: Vec<_> = page
+                        .objects
+                        .into_iter()
+                        .map(|(key, object)| ReaddirEntry::RemoteObject {
+                            name: key[self.full_path.len()..].to_owned(),
+                            full_key: key,
+                            size: object.size,
+                            last_modified: object.last_modified,
+                            storage_class: object.storage_class,
+                            restore_status: None,
+                            etag: object.etag,
+                            version_id: object.version_id,
+                            shadowed: false,
+                            decompressed: None,
+                        })
+                        .collect();
+                    (page.common_prefixes, objects, page.next_continuation_token)
+                }
+                None => {
+                    let result = self.fetch_page(client, continuation_token).await?;
+                    // Keys without a path can not be listed.
+                    let key_mapping = self.key_mapping;
+                    let hide_archived = self.hide_archived;
+                    let objects: Vec<_> = result
+                        .objects
+                        .into_iter()
+                        .filter(|object_info| {
+                            !hide_archived
+                                || !is_archived(object_info.storage_class.as_deref(), object_info.restore_status)
+                        })
+                        .filter_map(|object_info| {
+                            let full_key = key_mapping.path_for_key(&object_info.key)?.into_owned();
+                            Some(ReaddirEntry::RemoteObject {
+                                name: full_key[self.full_path.len()..].to_owned(),
+                                full_key,
+                                size: object_info.size,
+                                last_modified: object_info.last_modified,
+                                storage_class: object_info.storage_class,
+                                restore_status: object_info.restore_status,
+                                etag: object_info.etag,
+                                version_id: None,
+                                shadowed: false,
+                                decompressed: None,
+                            })
+                        })
+                        .collect();
+                    let common_prefixes = result
+                        .common_prefixes
+                        .iter()
+                        .filter_map(|prefix| Some(key_mapping.path_for_key(prefix)?.into_owned()))
+                        .collect();
+                    (common_prefixes, objects, result.next_continuation_token)
+                }
             };
 
-            let prefixes = result
-                .common_prefixes
-                .into_iter()
-                .map(|prefix| ReaddirEntry::RemotePrefix {
-                    name: prefix[self.full_path.len()..prefix.len() - 1].to_owned(),
+            // Hidden keys are left out before they can shadow other entries.
+            if let Some(key_filter) = &self.key_filter {
+                let start = self.mount_prefix_len;
+                common_prefixes.retain(|prefix: &String| key_filter.is_visible(&prefix[start..]));
+                objects.retain(|entry| match entry {
+                    ReaddirEntry::RemoteObject { full_key, .. } => key_filter.is_visible(&full_key[start..]),
+                    _ => true,
                 });
+            }
 
-            let objects = result
-                .objects
-                .into_iter()
-                .map(|object_info| ReaddirEntry::RemoteObject {
-                    name: object_info.key[self.full_path.len()..].to_owned(),
-                    full_key: object_info.key,
-                    size: object_info.size,
-                    last_modified: object_info.last_modified,
-                    storage_class: object_info.storage_class,
-                    restore_status: object_info.restore_status,
-                    etag: object_info.etag,
-                });
+            self.state = match next_continuation_token {
+                Some(token) => RemoteIterState::InProgress(Some(token)),
+                None => RemoteIterState::Finished,
+            };
+
+            let prefixes = common_prefixes.into_iter().map(|prefix| ReaddirEntry::RemotePrefix {
+                name: prefix[self.full_path.len()..prefix.len() - 1].to_owned(),
+            });
 
             if self.ordered {
                 // ListObjectsV2 results are sorted, so ideally we'd just merge-sort the two streams.
@@ -431,10 +741,13 @@ impl RemoteIter {
                 // but this should be good enough.
                 let mut new_entries = prefixes.chain(objects).collect::<Vec<_>>();
                 new_entries.sort();
+                self.add_recent_writes(&mut new_entries);
 
                 self.entries.extend(new_entries);
             } else {
-                self.entries.extend(prefixes.chain(objects));
+                let mut new_entries = prefixes.chain(objects).collect::<Vec<_>>();
+                self.add_recent_writes(&mut new_entries);
+                self.entries.extend(new_entries);
             }
         }
 
@@ -458,21 +771,32 @@ mod ordered {
         next_remote: Option<ReaddirEntry>,
         next_local: Option<ReaddirEntry>,
         last_entry: Option<ReaddirEntry>,
+        /// How to resolve the collision of a remote directory and file with the same name.
+        shadowing: ShadowingPolicy,
+        /// Whether to show compressed objects with the name of their decompressed content, see
+        /// [crate::SuperblockConfig::decompress_files].
+        decompress_files: bool,
+        /// Names of the entries returned so far that the next entries' names start with, each starting with the
+        /// previous one, which are the only ones the name of the decompressed content of the next entries can be.
+        name_prefixes: Vec<String>,
     }
 
     impl ReaddirIter {
         pub(super) fn new(
-            bucket: &str,
-            full_path: &str,
-            page_size: usize,
+            remote: RemoteIter,
             local_entries: VecDeque<ReaddirEntry>,
+            shadowing: ShadowingPolicy,
+            decompress_files: bool,
         ) -> Self {
             Self {
-                remote: RemoteIter::new(bucket, full_path, page_size, true),
+                remote,
                 local: LocalIter::new(local_entries),
                 next_remote: None,
                 next_local: None,
                 last_entry: None,
+                shadowing,
+                decompress_files,
+                name_prefixes: Vec::new(),
             }
         }
 
@@ -503,11 +827,26 @@ mod ordered {
                     (Some(_), None) => self.next_remote.take(),
                     (None, _) => self.next_local.take(),
                 };
+                let next = match next {
+                    Some(directory @ ReaddirEntry::RemotePrefix { .. }) => {
+                        Some(self.resolve_shadowing(directory, client).await?)
+                    }
+                    next => next,
+                };
+                let mut next = next;
+                if self.decompress_files
+                    && let Some(entry) = &mut next
+                {
+                    self.decompress(entry);
+                }
 
                 // Deduplicate the entry we want to return
                 match (next, &self.last_entry) {
                     (Some(entry), Some(last_entry)) => {
                         if last_entry.name() == entry.name() {
+                            if matches!(entry, ReaddirEntry::RemoteObject { .. }) {
+                                metrics::counter!("fs.shadowed_keys").increment(1);
+                            }
                             warn!(
                                 "{} is omitted because another {} exist with the same name",
                                 entry.description(),
@@ -526,6 +865,67 @@ mod ordered {
                 }
             }
         }
+
+        /// Resolve the collision of a remote directory with the remote file listed right after it, if it has the same
+        /// name, unless the directory shadows the file, which is then omitted as a duplicate.
+        async fn resolve_shadowing(
+            &mut self,
+            directory: ReaddirEntry,
+            client: &impl ObjectClient,
+        ) -> Result<ReaddirEntry, InodeError> {
+            if self.shadowing == ShadowingPolicy::DirectoryWins {
+                return Ok(directory);
+            }
+            if self.next_remote.is_none() {
+                self.next_remote = self.remote.next(client).await?;
+            }
+            let Some(ReaddirEntry::RemoteObject { name, shadowed, .. }) = &mut self.next_remote else {
+                return Ok(directory);
+            };
+            if name != directory.name() {
+                return Ok(directory);
+            }
+            metrics::counter!("fs.shadowed_keys").increment(1);
+            match self.shadowing {
+                ShadowingPolicy::DirectoryWins => Ok(directory),
+                ShadowingPolicy::FileWins => {
+                    debug!(
+                        "{} is omitted because a file exists with the same name",
+                        directory.description()
+                    );
+                    Ok(self.next_remote.take().expect("the next remote entry should be a file"))
+                }
+                ShadowingPolicy::SuffixFile => {
+                    name.push_str(SHADOWED_FILE_SUFFIX);
+                    *shadowed = true;
+                    Ok(directory)
+                }
+            }
+        }
+
+        /// Show the compressed objects with the name of their decompressed content, see
+        /// [crate::SuperblockConfig::decompress_files], unless an entry returned before has this name, in which case
+        /// the object keeps its own name. Entries are returned in name order, so an entry named like the decompressed
+        /// content of an object is returned before it, and every entry in between starts with its name.
+        fn decompress(&mut self, entry: &mut ReaddirEntry) {
+            let listed_name = entry.name().to_owned();
+            while let Some(prefix) = self.name_prefixes.last()
+                && !listed_name.starts_with(prefix.as_str())
+            {
+                self.name_prefixes.pop();
+            }
+            let collides = Compression::strip_extension(&listed_name)
+                .is_some_and(|(file_name, _)| self.name_prefixes.iter().any(|prefix| prefix == file_name));
+            if !collides {
+                entry.decompress();
+            } else if matches!(entry, ReaddirEntry::RemoteObject { .. }) {
+                debug!(
+                    "{} keeps its name because another entry exists with the name of its decompressed content",
+                    entry.description(),
+                );
+            }
+            self.name_prefixes.push(listed_name);
+        }
     }
 
     /// An iterator over local [ReaddirEntry]s listed from a directory at the start of a [ReaddirHandle]
@@ -565,15 +965,13 @@ mod unordered {
         local: HashMap<String, ReaddirEntry>,
         /// Queue of local entries to be returned, prepared based on the contents of [Self::local].
         local_iter: VecDeque<ReaddirEntry>,
+        /// Whether to show compressed objects with the name of their decompressed content, which may then be listed
+        /// twice, see [crate::SuperblockConfig::decompress_files].
+        decompress_files: bool,
     }
 
     impl ReaddirIter {
-        pub(super) fn new(
-            bucket: &str,
-            full_path: &str,
-            page_size: usize,
-            local_entries: VecDeque<ReaddirEntry>,
-        ) -> Self {
+        pub(super) fn new(remote: RemoteIter, local_entries: VecDeque<ReaddirEntry>, decompress_files: bool) -> Self {
             let local_map = local_entries
                 .into_iter()
                 .map(|entry| {
@@ -585,16 +983,20 @@ mod unordered {
                 .collect::<HashMap<_, _>>();
 
             Self {
-                remote: RemoteIter::new(bucket, full_path, page_size, false),
+                remote,
                 local: local_map,
                 local_iter: VecDeque::new(),
+                decompress_files,
             }
         }
 
         /// Return the next [ReaddirEntry] for the directory stream. If the stream is finished, returns
         /// `Ok(None)`.
         pub(super) async fn next(&mut self, client: &impl ObjectClient) -> Result<Option<ReaddirEntry>, InodeError> {
-            if let Some(remote) = self.remote.next(client).await? {
+            if let Some(mut remote) = self.remote.next(client).await? {
+                if self.decompress_files {
+                    remote.decompress();
+                }
                 self.local.remove(remote.name());
                 return Ok(Some(remote));
             }
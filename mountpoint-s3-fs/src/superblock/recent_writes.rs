@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::metablock::{Expiry, InodeStat};
+
+use crate::sync::RwLock;
+
+/// The keys of the objects recently written by this mount, which are shown in listings and lookups until they expire
+/// even if S3 doesn't return them yet, see [super::SuperblockConfig::recent_writes_window].
+#[derive(Debug)]
+pub struct RecentWrites {
+    /// The stat of the objects by their key, relative to the mount prefix.
+    map: RwLock<BTreeMap<String, RecentWrite>>,
+    /// How long keys are kept for after their object is written. Nothing is kept if it is zero.
+    window: Duration,
+}
+
+#[derive(Debug)]
+struct RecentWrite {
+    stat: InodeStat,
+    expiry: Expiry,
+}
+
+impl RecentWrites {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            map: RwLock::new(Default::default()),
+            window,
+        }
+    }
+
+    /// Record that the object with the given key was written with the given stat.
+    pub fn insert(&self, key: &str, stat: InodeStat) {
+        if self.window.is_zero() {
+            return;
+        }
+        let mut map = self.map.write().unwrap();
+        map.retain(|_, write| !write.expiry.is_expired());
+        let expiry = Expiry::from_now(self.window);
+        map.insert(key.to_owned(), RecentWrite { stat, expiry });
+        metrics::gauge!("metadata_cache.recent_writes.entries").set(map.len() as f64);
+    }
+
+    /// Forget the object with the given key, once it is removed. If the key was not present, this is a no-op.
+    pub fn remove(&self, key: &str) {
+        if self.window.is_zero() {
+            return;
+        }
+        let mut map = self.map.write().unwrap();
+        if map.remove(key).is_some() {
+            metrics::gauge!("metadata_cache.recent_writes.entries").set(map.len() as f64);
+        }
+    }
+
+    /// Forget the objects under the directory with the given key, ending with `/`, once it is renamed.
+    pub fn remove_prefix(&self, dir_key: &str) {
+        if self.window.is_zero() {
+            return;
+        }
+        let mut map = self.map.write().unwrap();
+        map.retain(|key, _| !key.starts_with(dir_key));
+        metrics::gauge!("metadata_cache.recent_writes.entries").set(map.len() as f64);
+    }
+
+    /// The stat of the object recently written with the given key, if it has not expired.
+    pub fn file(&self, key: &str) -> Option<InodeStat> {
+        let map = self.map.read().unwrap();
+        let write = map.get(key).filter(|write| !write.expiry.is_expired())?;
+        Some(write.stat.clone())
+    }
+
+    /// Whether an object was recently written under the directory with the given key, ending with `/`.
+    pub fn has_directory(&self, dir_key: &str) -> bool {
+        let map = self.map.read().unwrap();
+        map.range(dir_key.to_owned()..)
+            .take_while(|(key, _)| key.starts_with(dir_key))
+            .any(|(_, write)| !write.expiry.is_expired())
+    }
+
+    /// The entries of the directory with the given key holding recently written objects: the name and stat of the
+    /// objects written in the directory, and the name of the subdirectories they were written under.
+    pub fn children(&self, dir_key: &str) -> Vec<(String, Option<InodeStat>)> {
+        let map = self.map.read().unwrap();
+        let mut children: Vec<(String, Option<InodeStat>)> = Vec::new();
+        let writes = map
+            .range(dir_key.to_owned()..)
+            .take_while(|(key, _)| key.starts_with(dir_key))
+            .filter(|(_, write)| !write.expiry.is_expired());
+        for (key, write) in writes {
+            let child = match key[dir_key.len()..].split_once('/') {
+                Some((name, _)) => (name.to_owned(), None),
+                None => (key[dir_key.len()..].to_owned(), Some(write.stat.clone())),
+            };
+            if children
+                .last()
+                .is_none_or(|last| last.0 != child.0 || last.1.is_some() != child.1.is_some())
+            {
+                children.push(child);
+            }
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use time::OffsetDateTime;
+
+    use super::RecentWrites;
+    use crate::metablock::InodeStat;
+
+    fn stat(size: usize) -> InodeStat {
+        InodeStat::for_file(size, OffsetDateTime::UNIX_EPOCH, None, None, None, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let writes = RecentWrites::new(Duration::from_secs(60));
+        writes.insert("dir/sub/a.txt", stat(1));
+
+        assert_eq!(writes.file("dir/sub/a.txt").map(|stat| stat.size), Some(1));
+        assert!(writes.file("dir/sub/b.txt").is_none());
+        assert!(writes.has_directory("dir/"));
+        assert!(writes.has_directory("dir/sub/"));
+        assert!(!writes.has_directory("di/"));
+
+        writes.remove("dir/sub/a.txt");
+        assert!(writes.file("dir/sub/a.txt").is_none());
+        assert!(!writes.has_directory("dir/"));
+    }
+
+    #[test]
+    fn test_children() {
+        let writes = RecentWrites::new(Duration::from_secs(60));
+        for key in ["dir/a.txt", "dir/b/c.txt", "dir/b/d.txt", "dir/b.txt", "other.txt"] {
+            writes.insert(key, stat(key.len()));
+        }
+
+        let children: Vec<_> = writes
+            .children("dir/")
+            .into_iter()
+            .map(|(name, stat)| (name, stat.map(|stat| stat.size)))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("a.txt".to_owned(), Some(9)),
+                ("b.txt".to_owned(), Some(9)),
+                ("b".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let writes = RecentWrites::new(Duration::from_millis(10));
+        writes.insert("a.txt", stat(1));
+        assert!(writes.file("a.txt").is_some());
+        sleep(Duration::from_millis(20));
+        assert!(writes.file("a.txt").is_none());
+        assert!(writes.children("").is_empty());
+
+        let writes = RecentWrites::new(Duration::ZERO);
+        writes.insert("a.txt", stat(1));
+        assert!(writes.file("a.txt").is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Point-in-time snapshots of versioned buckets.
+//!
+//! A [Snapshot] resolves each key under the mounted prefix to its newest version last modified at or before a given
+//! time, from the listing of all the versions of the objects with ListObjectVersions. Keys whose newest version at that
+//! time is a delete marker, or which did not exist yet, are left out. The superblock serves directory listings and
+//! lookups from the snapshot only, and files are read with the ID of their version, so that the mount shows the same
+//! content whatever was written to the bucket since.
+//!
+//! The snapshot is loaded once, before mounting. Changes made through the mount are not reflected in it, so it should
+//! be mounted read-only.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt::Debug;
+
+use mountpoint_s3_client::ObjectClient;
+use thiserror::Error;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::info;
+
+use super::inventory::{InventoryIndex, InventoryObject};
+use crate::s3::S3Path;
+use crate::sync::Arc;
+
+/// The objects of a bucket as they were at a point in time, see [crate::SuperblockConfig::snapshot].
+#[derive(Clone)]
+pub struct Snapshot {
+    time: OffsetDateTime,
+    pub(super) index: Arc<InventoryIndex>,
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("ListObjectVersions failed for prefix {prefix:?}")]
+    ClientError {
+        prefix: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl Snapshot {
+    /// List the versions of the objects under `s3_path`, and keep the newest version of each key last modified at or
+    /// before `time`.
+    pub async fn load(
+        client: &impl ObjectClient,
+        s3_path: &S3Path,
+        time: OffsetDateTime,
+    ) -> Result<Self, SnapshotError> {
+        let prefix = s3_path.prefix.as_str();
+        // Newest version or delete marker of each key at the time of the snapshot, with the time it was last modified.
+        let mut latest: BTreeMap<String, (OffsetDateTime, Option<InventoryObject>)> = BTreeMap::new();
+        let mut insert = |key: String, last_modified: OffsetDateTime, object: Option<InventoryObject>| {
+            if last_modified > time {
+                return;
+            }
+            match latest.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert((last_modified, object));
+                }
+                Entry::Occupied(mut entry) => {
+                    if last_modified > entry.get().0 {
+                        entry.insert((last_modified, object));
+                    }
+                }
+            }
+        };
+
+        let (mut key_marker, mut version_id_marker) = (None, None);
+        loop {
+            let result = client
+                .list_object_versions(
+                    &s3_path.bucket,
+                    key_marker.as_deref(),
+                    version_id_marker.as_deref(),
+                    "",
+                    1000,
+                    prefix,
+                )
+                .await
+                .map_err(|e| SnapshotError::ClientError {
+                    prefix: prefix.to_owned(),
+                    source: e.into(),
+                })?;
+            for version in result.versions {
+                let object = InventoryObject {
+                    size: version.size,
+                    last_modified: version.last_modified,
+                    etag: version.etag,
+                    storage_class: version.storage_class,
+                    version_id: Some(version.version_id),
+                };
+                insert(version.key, version.last_modified, Some(object));
+            }
+            for delete_marker in result.delete_markers {
+                insert(delete_marker.key, delete_marker.last_modified, None);
+            }
+            if result.next_key_marker.is_none() {
+                break;
+            }
+            key_marker = result.next_key_marker;
+            version_id_marker = result.next_version_id_marker;
+        }
+
+        let objects: BTreeMap<_, _> = latest
+            .into_iter()
+            .filter_map(|(key, (_, object))| Some((key, object?)))
+            .collect();
+        let time_str = time.format(&Rfc3339).unwrap_or_else(|_| time.to_string());
+        info!(time = time_str, objects = objects.len(), "loaded snapshot");
+        Ok(Self {
+            time,
+            index: Arc::new(InventoryIndex::new(time_str, objects)),
+        })
+    }
+
+    /// Time the snapshot shows the objects at.
+    pub fn time(&self) -> OffsetDateTime {
+        self.time
+    }
+}
+
+impl Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("time", &self.time)
+            .field("objects", &self.index.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject};
+    use mountpoint_s3_client::types::ETag;
+
+    use super::*;
+    use crate::s3::{Bucket, Prefix};
+    use crate::superblock::inventory::InventoryLookup;
+
+    fn time(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(seconds).unwrap()
+    }
+
+    fn version(value: u8, version_id: &str, seconds: i64) -> MockObject {
+        let mut object = MockObject::constant(value, 5, ETag::from_object_bytes(&[value]));
+        object.set_version_id(Some(version_id.to_owned()));
+        object.set_last_modified(time(seconds));
+        object
+    }
+
+    #[test]
+    fn test_load() {
+        let client = MockClient::config().bucket("test_bucket").build();
+        // Overwritten after the snapshot.
+        client.add_object("data/a.txt", version(2, "a2", 30));
+        client.add_object_version("data/a.txt", version(1, "a1", 10));
+        // Deleted after the snapshot.
+        client.add_object_version("data/b.txt", version(3, "b1", 10));
+        client.add_delete_marker("data/b.txt", "b2", time(30));
+        // Deleted before the snapshot.
+        client.add_object_version("data/c.txt", version(4, "c1", 10));
+        client.add_delete_marker("data/c.txt", "c2", time(15));
+        // Created after the snapshot.
+        client.add_object("data/d.txt", version(5, "d1", 30));
+        // Outside of the mounted prefix.
+        client.add_object("other.txt", version(6, "o1", 10));
+
+        let s3_path = S3Path::new(Bucket::new("test_bucket").unwrap(), Prefix::new("data/").unwrap());
+        let snapshot = block_on(Snapshot::load(&client, &s3_path, time(20))).unwrap();
+        assert_eq!(snapshot.time(), time(20));
+
+        let page = snapshot.index.list("data/", None, 1000);
+        let versions: Vec<_> = page
+            .objects
+            .iter()
+            .map(|(key, object)| (key.as_str(), object.version_id.as_deref()))
+            .collect();
+        assert_eq!(versions, [("data/a.txt", Some("a1")), ("data/b.txt", Some("b1"))]);
+        assert!(matches!(
+            snapshot.index.lookup("data/a.txt"),
+            Some(InventoryLookup::File(object)) if object.etag == ETag::from_object_bytes(&[1]).as_str()
+        ));
+        assert_eq!(snapshot.index.lookup("data/c.txt"), None);
+        assert_eq!(snapshot.index.lookup("other.txt"), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! Deferred deletes through a trash prefix.
+//!
+//! Without versioning, the object of a removed file can't be recovered. With a [TrashConfig], the superblock copies the
+//! object of each unlinked file under a trash prefix before deleting it, at the key `<prefix><time>/<key>`, where
+//! `<time>` is the time of the unlink in RFC 3339 format, to the second. Files removed by accident can be recovered by
+//! copying their object back.
+//!
+//! If a retention period is configured, a background task periodically purges the objects that have been in the trash
+//! for longer, by listing the times under the trash prefix and deleting everything under the expired ones.
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use futures::task::SpawnExt;
+use mountpoint_s3_client::ObjectClient;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, info, warn};
+
+use crate::async_util::{Runtime, sleep};
+use crate::metablock::InodeError;
+use crate::s3::Prefix;
+use crate::sync::{Arc, Weak};
+
+/// Longest interval between two purges of the trash.
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Copying the objects of unlinked files to a trash prefix before deleting them, see
+/// [crate::SuperblockConfig::trash].
+#[derive(Clone)]
+pub struct TrashConfig {
+    prefix: Prefix,
+    retention: Option<Duration>,
+    runtime: Runtime,
+}
+
+impl TrashConfig {
+    /// Copy the objects of unlinked files under `prefix`, a prefix of the bucket rather than of the mounted prefix. If
+    /// `retention` is given, the objects that have been in the trash for longer are purged by a task running on
+    /// `runtime`, which checks for them every hour, or every `retention` if shorter. Otherwise, they are kept until
+    /// deleted otherwise, for example by a lifecycle rule.
+    pub fn new(prefix: Prefix, retention: Option<Duration>, runtime: Runtime) -> Self {
+        Self {
+            prefix,
+            retention,
+            runtime,
+        }
+    }
+}
+
+impl Debug for TrashConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrashConfig")
+            .field("prefix", &self.prefix)
+            .field("retention", &self.retention)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The trash prefix of a mount, purged by a background task if it has a retention period.
+#[derive(Debug)]
+pub(super) struct Trash {
+    prefix: String,
+    /// Dropped along with the [Trash] to stop the purge task.
+    _alive: Arc<()>,
+}
+
+impl Trash {
+    /// Start purging the trash of `bucket` if the configuration has a retention period. The background task stops once
+    /// the [Trash] is dropped.
+    pub fn start<OC>(client: OC, bucket: &str, config: &TrashConfig) -> Self
+    where
+        OC: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        let trash = Self {
+            prefix: config.prefix.as_str().to_owned(),
+            _alive: Arc::new(()),
+        };
+        if let Some(retention) = config.retention {
+            let task = purge_periodically(
+                client,
+                bucket.to_owned(),
+                trash.prefix.clone(),
+                retention,
+                Arc::downgrade(&trash._alive),
+            );
+            if let Err(error) = config.runtime.spawn(task) {
+                warn!(?error, "unable to start purging the trash");
+            }
+        }
+        trash
+    }
+
+    /// The key in the trash of the object with the given key, deleted at the given time, or [None] if the object is
+    /// already in the trash and should just be deleted.
+    pub fn key(&self, key: &str, time: OffsetDateTime) -> Option<String> {
+        if key.starts_with(&self.prefix) {
+            return None;
+        }
+        let time = time
+            .to_offset(time::UtcOffset::UTC)
+            .replace_nanosecond(0)
+            .expect("zero is a valid nanosecond")
+            .format(&Rfc3339)
+            .expect("times after year 0 can be formatted");
+        Some(format!("{}{}/{}", self.prefix, time, key))
+    }
+}
+
+async fn purge_periodically<OC: ObjectClient>(
+    client: OC,
+    bucket: String,
+    prefix: String,
+    retention: Duration,
+    alive: Weak<()>,
+) {
+    let interval = retention.min(PURGE_INTERVAL);
+    loop {
+        if alive.upgrade().is_none() {
+            return;
+        }
+        match purge(&client, &bucket, &prefix, retention, OffsetDateTime::now_utc()).await {
+            Ok(0) => debug!(?prefix, "no object to purge from the trash"),
+            Ok(purged) => info!(?prefix, purged, "purged objects from the trash"),
+            Err(error) => warn!(?error, ?prefix, "failed to purge the trash"),
+        }
+        sleep(interval).await;
+    }
+}
+
+/// Delete the objects under `prefix` that were trashed more than `retention` before `now`, and return how many were
+/// deleted. The prefixes under `prefix` that are not a time are left alone.
+pub(super) async fn purge<OC: ObjectClient>(
+    client: &OC,
+    bucket: &str,
+    prefix: &str,
+    retention: Duration,
+    now: OffsetDateTime,
+) -> Result<usize, InodeError> {
+    let mut expired = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let result = client
+            .list_objects(bucket, continuation_token.as_deref(), "/", 1000, prefix)
+            .await
+            .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, prefix))?;
+        for time_prefix in result.common_prefixes {
+            let time = time_prefix
+                .strip_prefix(prefix)
+                .and_then(|time| time.strip_suffix('/'))
+                .and_then(|time| OffsetDateTime::parse(time, &Rfc3339).ok());
+            if time.is_some_and(|time| time + retention < now) {
+                expired.push(time_prefix);
+            }
+        }
+        continuation_token = result.next_continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+
+    let mut purged = 0;
+    for time_prefix in expired {
+        let mut continuation_token = None;
+        loop {
+            let result = client
+                .list_objects(bucket, continuation_token.as_deref(), "", 1000, &time_prefix)
+                .await
+                .map_err(|e| InodeError::client_error(e, "ListObjectsV2 failed", bucket, &time_prefix))?;
+            if !result.objects.is_empty() {
+                let keys: Vec<String> = result.objects.into_iter().map(|object| object.key).collect();
+                let result = client
+                    .delete_objects(bucket, &keys)
+                    .await
+                    .map_err(|e| InodeError::client_error(e, "DeleteObjects failed", bucket, &time_prefix))?;
+                for error in &result.errors {
+                    warn!(key = ?error.key, code = ?error.code, message = ?error.message, "could not purge object from the trash");
+                }
+                purged += keys.len() - result.errors.len();
+            }
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+    }
+    metrics::counter!("fs.trash.purged_objects").increment(purged as u64);
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject};
+    use mountpoint_s3_client::types::ETag;
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        let trash = Trash {
+            prefix: "trash/".to_owned(),
+            _alive: Arc::new(()),
+        };
+        let time = datetime!(2026-10-14 17:48:19.250 +02:00);
+        assert_eq!(
+            trash.key("dir/file.txt", time).as_deref(),
+            Some("trash/2026-10-14T15:48:19Z/dir/file.txt")
+        );
+        assert_eq!(trash.key("trash/2026-10-14T15:48:19Z/dir/file.txt", time), None);
+    }
+
+    #[tokio::test]
+    async fn test_purge() {
+        let bucket = "test_bucket";
+        let client = MockClient::config().bucket(bucket).part_size(32).build();
+        let keys = [
+            "trash/2026-10-01T00:00:00Z/a.txt",
+            "trash/2026-10-01T00:00:00Z/dir/b.txt",
+            "trash/2026-10-13T00:00:00Z/c.txt",
+            "trash/not-a-time/d.txt",
+            "trash/e.txt",
+            "f.txt",
+        ];
+        for key in keys {
+            client.add_object(key, MockObject::constant(0xaa, 1, ETag::for_tests()));
+        }
+
+        let now = datetime!(2026-10-14 00:00:00 UTC);
+        let retention = Duration::from_secs(7 * 24 * 3600);
+        let purged = purge(&client, bucket, "trash/", retention, now).await.unwrap();
+        assert_eq!(purged, 2);
+        for key in &keys[..2] {
+            assert!(!client.contains_key(key), "{key} should be purged");
+        }
+        for key in &keys[2..] {
+            assert!(client.contains_key(key), "{key} should be kept");
+        }
+
+        let purged = purge(&client, bucket, "trash/", Duration::ZERO, now).await.unwrap();
+        assert_eq!(purged, 1);
+        assert!(!client.contains_key(keys[2]));
+    }
+}
@@ -1,13 +1,16 @@
 use std::fmt::Debug;
+use std::path::PathBuf;
 
 use mountpoint_s3_client::ObjectClient;
-use mountpoint_s3_client::error::{HeadObjectError, ObjectClientError, PutObjectError};
+use mountpoint_s3_client::error::{
+    GetObjectError, HeadObjectError, MultipartUploadError, ObjectClientError, PutObjectError,
+};
 use mountpoint_s3_client::error_metadata::{ClientErrorMetadata, ProvideErrorMetadata};
-use mountpoint_s3_client::types::{ChecksumAlgorithm, ETag};
+use mountpoint_s3_client::types::{ChecksumAlgorithm, ETag, ObjectMetadata, ObjectTags, PutObjectResult};
 use thiserror::Error;
 
 use crate::async_util::Runtime;
-use crate::fs::{ServerSideEncryption, SseCorruptedError};
+use crate::fs::{PrefixOverride, PrefixOverrides, ServerSideEncryption, SseCorruptedError, StorageClassRules};
 use crate::mem_limiter::MemoryLimiter;
 use crate::memory::PagedPool;
 use crate::sync::Arc;
@@ -16,13 +19,33 @@ mod atomic;
 pub use atomic::UploadRequest;
 use atomic::UploadRequestParams;
 
+mod content_type;
+pub use content_type::{ContentTypeMapping, ContentTypeMappingError, ContentTypes};
+
+mod copy_append;
+use copy_append::CopyAppendParams;
+pub use copy_append::CopyAppendRequest;
+
 mod hasher;
 pub use hasher::ChecksumHasherError;
 
+mod scheduler;
+use scheduler::{UploadScheduler, acquire_turn};
+
 mod incremental;
 use incremental::AppendUploadQueueParams;
 pub use incremental::AppendUploadRequest;
 
+mod journal;
+
+mod staged;
+pub use staged::StagedUploadRequest;
+use staged::StagingDir;
+
+mod write_through;
+use write_through::WriteThroughBuffer;
+pub use write_through::WriteThroughCache;
+
 /// An [Uploader] creates and manages streaming PutObject requests.
 #[derive(Debug)]
 pub struct Uploader<Client: ObjectClient> {
@@ -34,6 +57,14 @@ pub struct Uploader<Client: ObjectClient> {
     pool: PagedPool,
     mem_limiter: Arc<MemoryLimiter>,
     storage_class: Option<String>,
+    /// Storage classes overriding [Self::storage_class] for the keys matching some patterns.
+    storage_class_rules: StorageClassRules,
+    /// Storage classes overriding [Self::storage_class] and [Self::storage_class_rules] under some prefixes.
+    prefix_overrides: PrefixOverrides,
+    /// Tags of new objects.
+    object_tags: ObjectTags,
+    /// How the content types of new objects are chosen, if they are set.
+    content_types: Option<ContentTypes>,
     server_side_encryption: ServerSideEncryption,
     buffer_size: usize,
     /// Default checksum algorithm, if any, to be used for new S3 objects.
@@ -41,6 +72,13 @@ pub struct Uploader<Client: ObjectClient> {
     /// Only [ChecksumAlgorithm::Crc32c] is supported for multi-part uploads.
     /// For existing objects, Mountpoint will instead append using the existing checksum algorithm on the object.
     default_checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Cache the objects written with atomic uploads are put to, if any.
+    write_through_cache: Option<WriteThroughCache>,
+    /// Directory the writes of staged uploads are spooled to, if they are enabled.
+    staging_dir: Option<Arc<StagingDir>>,
+    /// Turns of the uploads to hand data to the client, if their number is limited.
+    scheduler: Option<UploadScheduler>,
+    max_concurrent_parts_per_upload: Option<u32>,
 }
 
 #[derive(Debug, Error)]
@@ -67,6 +105,21 @@ pub enum UploadError<E> {
 
     #[error("object exceeded maximum upload size of {maximum_size} bytes")]
     ObjectTooBig { maximum_size: usize },
+
+    #[error("multipart upload request failed")]
+    MultipartUploadFailed(#[from] ObjectClientError<MultipartUploadError, E>),
+
+    #[error("get object request failed")]
+    GetObjectFailed(#[from] ObjectClientError<GetObjectError, E>),
+
+    #[error("staging file I/O failed")]
+    StagingFailed(#[source] std::io::Error),
+
+    #[error("staging directory exceeded maximum size of {maximum_size} bytes")]
+    StagingDirFull { maximum_size: u64 },
+
+    #[error("object was changed since it was opened, not replacing it")]
+    ObjectChanged,
 }
 
 impl<E> ProvideErrorMetadata for UploadError<E>
@@ -79,9 +132,14 @@ where
             | UploadError::ChecksumComputationFailed(_)
             | UploadError::SseCorruptedError(_)
             | UploadError::UploadAlreadyTerminated
+            | UploadError::StagingFailed(_)
+            | UploadError::StagingDirFull { .. }
+            | UploadError::ObjectChanged
             | UploadError::OutOfOrderWrite { .. } => Default::default(),
             UploadError::PutRequestFailed(object_client_error) => object_client_error.meta(),
             UploadError::HeadObjectFailed(object_client_error) => object_client_error.meta(),
+            UploadError::MultipartUploadFailed(object_client_error) => object_client_error.meta(),
+            UploadError::GetObjectFailed(object_client_error) => object_client_error.meta(),
         }
     }
 }
@@ -89,18 +147,38 @@ where
 #[derive(Debug)]
 pub struct UploaderConfig {
     storage_class: Option<String>,
+    storage_class_rules: StorageClassRules,
+    prefix_overrides: PrefixOverrides,
+    object_tags: ObjectTags,
+    content_types: Option<ContentTypes>,
     server_side_encryption: ServerSideEncryption,
     buffer_size: usize,
     default_checksum_algorithm: Option<ChecksumAlgorithm>,
+    write_through_cache: Option<WriteThroughCache>,
+    staging_dir: Option<PathBuf>,
+    staging_max_size: Option<u64>,
+    recoverable_staging: bool,
+    max_concurrent_uploads: Option<usize>,
+    max_concurrent_parts_per_upload: Option<u32>,
 }
 
 impl UploaderConfig {
     pub fn new(buffer_size: usize) -> Self {
         Self {
             storage_class: None,
+            storage_class_rules: Default::default(),
+            prefix_overrides: Default::default(),
+            object_tags: ObjectTags::new(),
+            content_types: None,
             server_side_encryption: Default::default(),
             buffer_size,
             default_checksum_algorithm: None,
+            write_through_cache: None,
+            staging_dir: None,
+            staging_max_size: None,
+            recoverable_staging: false,
+            max_concurrent_uploads: None,
+            max_concurrent_parts_per_upload: None,
         }
     }
 
@@ -109,6 +187,34 @@ impl UploaderConfig {
         self
     }
 
+    /// Use the storage class of the first rule matching the key of new objects, if any, in place of
+    /// [Self::storage_class].
+    pub fn storage_class_rules(mut self, storage_class_rules: StorageClassRules) -> Self {
+        self.storage_class_rules = storage_class_rules;
+        self
+    }
+
+    /// Use the storage classes overridden under some prefixes for the objects written under them, see
+    /// [crate::fs::PrefixOverride::storage_class].
+    pub fn prefix_overrides(mut self, prefix_overrides: PrefixOverrides) -> Self {
+        self.prefix_overrides = prefix_overrides;
+        self
+    }
+
+    /// Tag the objects created by new files, copies and overwrites. The tags of an upload can then be changed until
+    /// it starts, see [UploadRequest::set_object_tags].
+    pub fn object_tags(mut self, object_tags: ObjectTags) -> Self {
+        self.object_tags = object_tags;
+        self
+    }
+
+    /// Set the content type of the objects created by new files, copies and overwrites, chosen from their key or
+    /// recognized from their content. Objects are otherwise stored as `binary/octet-stream` by S3.
+    pub fn content_types(mut self, content_types: Option<ContentTypes>) -> Self {
+        self.content_types = content_types;
+        self
+    }
+
     pub fn server_side_encryption(mut self, server_side_encryption: ServerSideEncryption) -> Self {
         self.server_side_encryption = server_side_encryption;
         self
@@ -118,6 +224,45 @@ impl UploaderConfig {
         self.default_checksum_algorithm = default_checksum_algorithm;
         self
     }
+
+    /// Put the objects written with atomic uploads to the given cache once uploaded.
+    pub fn write_through_cache(mut self, write_through_cache: Option<WriteThroughCache>) -> Self {
+        self.write_through_cache = write_through_cache;
+        self
+    }
+
+    /// Spool the writes of staged uploads to the given directory, see [Uploader::start_staged_upload_with_metadata].
+    pub fn staging_dir(mut self, staging_dir: Option<PathBuf>) -> Self {
+        self.staging_dir = staging_dir;
+        self
+    }
+
+    /// Limit the total size of the spool files of staged uploads. Writes that would exceed it fail.
+    pub fn staging_max_size(mut self, staging_max_size: Option<u64>) -> Self {
+        self.staging_max_size = staging_max_size;
+        self
+    }
+
+    /// Name the spool files of staged uploads, and record them once their file is closed, so that the uploads not
+    /// completed when Mountpoint exits can be recovered with [Uploader::recover_staged_uploads].
+    pub fn recoverable_staging(mut self, recoverable_staging: bool) -> Self {
+        self.recoverable_staging = recoverable_staging;
+        self
+    }
+
+    /// Limit the number of uploads handing data to the client at once. The uploads wait for their turn before each
+    /// write to their request, or each part they upload themselves, in the order they started waiting, so that large
+    /// uploads don't delay the uploads of small files written at the same time. Incremental uploads are not limited.
+    pub fn max_concurrent_uploads(mut self, max_concurrent_uploads: Option<usize>) -> Self {
+        self.max_concurrent_uploads = max_concurrent_uploads;
+        self
+    }
+
+    /// Limit the number of parts of an atomic upload the client sends at once. Other uploads send one part at a time.
+    pub fn max_concurrent_parts_per_upload(mut self, max_concurrent_parts_per_upload: Option<u32>) -> Self {
+        self.max_concurrent_parts_per_upload = max_concurrent_parts_per_upload;
+        self
+    }
 }
 
 impl<Client> Uploader<Client>
@@ -138,9 +283,23 @@ where
             pool,
             mem_limiter,
             storage_class: config.storage_class,
+            storage_class_rules: config.storage_class_rules,
+            prefix_overrides: config.prefix_overrides,
+            object_tags: config.object_tags,
+            content_types: config.content_types,
             server_side_encryption: config.server_side_encryption,
             buffer_size: config.buffer_size,
             default_checksum_algorithm: config.default_checksum_algorithm,
+            write_through_cache: config.write_through_cache,
+            staging_dir: config.staging_dir.map(|path| {
+                Arc::new(StagingDir::new(
+                    path,
+                    config.staging_max_size,
+                    config.recoverable_staging,
+                ))
+            }),
+            scheduler: config.max_concurrent_uploads.map(UploadScheduler::new),
+            max_concurrent_parts_per_upload: config.max_concurrent_parts_per_upload,
         }
     }
 
@@ -150,14 +309,101 @@ where
         bucket: String,
         key: String,
     ) -> Result<UploadRequest<Client>, UploadError<Client::ClientError>> {
-        let params = UploadRequestParams {
+        self.start_atomic_upload_with_metadata(bucket, key, ObjectMetadata::new())
+    }
+
+    /// Start a new atomic upload of an object with the given user-defined metadata.
+    pub fn start_atomic_upload_with_metadata(
+        &self,
+        bucket: String,
+        key: String,
+        object_metadata: ObjectMetadata,
+    ) -> Result<UploadRequest<Client>, UploadError<Client::ClientError>> {
+        let params = self.upload_request_params(bucket, key, object_metadata);
+        UploadRequest::new(&self.runtime, self.client.clone(), params)
+    }
+
+    /// Whether uploads can be staged, see [Uploader::start_staged_upload_with_metadata].
+    pub fn stages_writes(&self) -> bool {
+        self.staging_dir.is_some()
+    }
+
+    /// Start a new upload of an object with the given user-defined metadata, whose writes are staged in a spool file
+    /// so that they can be made at any offset, and uploaded at once when it completes.
+    ///
+    /// Panics if no staging directory is configured, see [Uploader::stages_writes].
+    pub fn start_staged_upload_with_metadata(
+        &self,
+        bucket: String,
+        key: String,
+        object_metadata: ObjectMetadata,
+    ) -> Result<StagedUploadRequest<Client>, UploadError<Client::ClientError>> {
+        let staging_dir = self
+            .staging_dir
+            .as_ref()
+            .expect("staged uploads require a staging directory");
+        let params = self.upload_request_params(bucket, key, object_metadata);
+        StagedUploadRequest::new(&self.runtime, self.client.clone(), staging_dir.clone(), params)
+    }
+
+    /// Complete the staged uploads left in a recoverable staging directory by a previous Mountpoint process, see
+    /// [UploaderConfig::recoverable_staging]. Return the number of uploads recovered.
+    pub async fn recover_staged_uploads(&self) -> usize {
+        let Some(staging_dir) = self.staging_dir.as_ref().filter(|dir| dir.is_recoverable()) else {
+            return 0;
+        };
+        staged::recover(
+            &self.runtime,
+            &self.client,
+            staging_dir,
+            |bucket, key, object_metadata| self.upload_request_params(bucket, key, object_metadata),
+        )
+        .await
+    }
+
+    fn upload_request_params(
+        &self,
+        bucket: String,
+        key: String,
+        object_metadata: ObjectMetadata,
+    ) -> UploadRequestParams {
+        UploadRequestParams {
+            storage_class: self.storage_class_for(&key),
+            content_type: self.content_type_for(&key),
+            sniff_content_type: self.content_types.as_ref().is_some_and(ContentTypes::sniffs),
             bucket,
             key,
             server_side_encryption: self.server_side_encryption.clone(),
             default_checksum_algorithm: self.default_checksum_algorithm.clone(),
-            storage_class: self.storage_class.clone(),
-        };
-        UploadRequest::new(&self.runtime, self.client.clone(), params)
+            object_metadata,
+            object_tags: self.object_tags.clone(),
+            write_through: self
+                .write_through_cache
+                .clone()
+                .map(|cache| WriteThroughBuffer::new(cache, self.mem_limiter.clone())),
+            scheduler: self.scheduler.clone(),
+            max_concurrent_parts: self.max_concurrent_parts_per_upload,
+        }
+    }
+
+    /// The storage class of new objects with the given key.
+    fn storage_class_for(&self, key: &str) -> Option<String> {
+        match self.prefix_overrides.for_object_key(key) {
+            Some(PrefixOverride {
+                storage_class: Some(storage_class),
+                ..
+            }) => Some(storage_class.clone()),
+            _ => self
+                .storage_class_rules
+                .for_object_key(key)
+                .map(str::to_owned)
+                .or_else(|| self.storage_class.clone()),
+        }
+    }
+
+    /// The content type of new objects with the given key, from its extension.
+    fn content_type_for(&self, key: &str) -> Option<String> {
+        self.content_types.as_ref()?.for_key(key).map(str::to_owned)
     }
 
     /// Start a new incremental upload.
@@ -190,6 +436,117 @@ where
         )
     }
 
+    /// Start appending to the existing object with the given size and ETag, by copying its content into a new
+    /// multipart upload. If `allow_overwrite` is set, the writes can also start before the end of the object to
+    /// overwrite part of it.
+    pub fn start_copy_append(
+        &self,
+        bucket: String,
+        key: String,
+        initial_size: u64,
+        initial_etag: ETag,
+        allow_overwrite: bool,
+    ) -> CopyAppendRequest<Client> {
+        let params = CopyAppendParams {
+            bucket,
+            key,
+            initial_size,
+            initial_etag: Some(initial_etag),
+            storage_class: None,
+            object_metadata: ObjectMetadata::new(),
+            object_tags: ObjectTags::new(),
+            content_type: None,
+            server_side_encryption: self.server_side_encryption.clone(),
+            default_checksum_algorithm: self.default_checksum_algorithm.clone(),
+            allow_overwrite,
+            replace_if_match: None,
+            scheduler: self.scheduler.clone(),
+        };
+        CopyAppendRequest::new(&self.runtime, self.client.clone(), params)
+    }
+
+    /// Start creating a new object with the given user-defined metadata from ranges of other objects copied
+    /// server-side, with [CopyAppendRequest::copy_from], and written data.
+    pub fn start_copy(
+        &self,
+        bucket: String,
+        key: String,
+        object_metadata: ObjectMetadata,
+    ) -> CopyAppendRequest<Client> {
+        let params = CopyAppendParams {
+            storage_class: self.storage_class_for(&key),
+            content_type: self.content_type_for(&key),
+            bucket,
+            key,
+            initial_size: 0,
+            initial_etag: None,
+            object_metadata,
+            object_tags: self.object_tags.clone(),
+            server_side_encryption: self.server_side_encryption.clone(),
+            default_checksum_algorithm: self.default_checksum_algorithm.clone(),
+            allow_overwrite: false,
+            replace_if_match: None,
+            scheduler: self.scheduler.clone(),
+        };
+        CopyAppendRequest::new(&self.runtime, self.client.clone(), params)
+    }
+
+    /// Start replacing the object with the given ETag by a new one with the given user-defined metadata, created from
+    /// sequential writes, failing with [UploadError::ObjectChanged] on completion if the object was changed in the
+    /// meantime. Unlike [Uploader::start_atomic_upload_with_metadata], its parts are uploaded one at a time.
+    pub fn start_conditional_overwrite(
+        &self,
+        bucket: String,
+        key: String,
+        object_metadata: ObjectMetadata,
+        etag: ETag,
+    ) -> CopyAppendRequest<Client> {
+        let params = CopyAppendParams {
+            storage_class: self.storage_class_for(&key),
+            content_type: self.content_type_for(&key),
+            bucket,
+            key,
+            initial_size: 0,
+            initial_etag: None,
+            object_metadata,
+            object_tags: self.object_tags.clone(),
+            server_side_encryption: self.server_side_encryption.clone(),
+            default_checksum_algorithm: self.default_checksum_algorithm.clone(),
+            allow_overwrite: false,
+            replace_if_match: Some(etag),
+            scheduler: self.scheduler.clone(),
+        };
+        CopyAppendRequest::new(&self.runtime, self.client.clone(), params)
+    }
+
+    /// Truncate the existing object with the given size and ETag to `new_size`, or extend it with zeros, keeping its
+    /// storage class, user-defined metadata and content type. Fails if the object no longer has the given ETag.
+    pub async fn truncate_object(
+        &self,
+        bucket: String,
+        key: String,
+        size: u64,
+        etag: ETag,
+        new_size: u64,
+    ) -> Result<PutObjectResult, UploadError<Client::ClientError>> {
+        let params = CopyAppendParams {
+            bucket,
+            key,
+            initial_size: size,
+            initial_etag: Some(etag),
+            storage_class: None,
+            object_metadata: ObjectMetadata::new(),
+            object_tags: ObjectTags::new(),
+            content_type: None,
+            server_side_encryption: self.server_side_encryption.clone(),
+            default_checksum_algorithm: self.default_checksum_algorithm.clone(),
+            allow_overwrite: false,
+            replace_if_match: None,
+            scheduler: self.scheduler.clone(),
+        };
+        copy_append::truncate_object(&self.runtime, self.client.clone(), params, new_size).await
+    }
+
     #[cfg(test)]
     pub fn corrupt_sse(&mut self, sse_type: Option<String>, sse_kms_key_id: Option<String>) {
         self.server_side_encryption.corrupt_data(sse_type, sse_kms_key_id)
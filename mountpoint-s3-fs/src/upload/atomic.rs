@@ -3,30 +3,65 @@ use std::fmt::Debug;
 use mountpoint_s3_client::checksums::{Crc32c, crc32c, crc32c_from_base64};
 use mountpoint_s3_client::error::{ObjectClientError, PutObjectError};
 use mountpoint_s3_client::types::{
-    ChecksumAlgorithm, PutObjectParams, PutObjectResult, PutObjectTrailingChecksums, UploadReview,
+    Checksum, ChecksumAlgorithm, ObjectMetadata, ObjectTags, PutObjectParams, PutObjectResult,
+    PutObjectTrailingChecksums, UploadChecksum, UploadReview,
 };
 use mountpoint_s3_client::{ObjectClient, PutObjectRequest};
-use tracing::error;
+use tracing::{debug, error};
 
 use crate::ServerSideEncryption;
 use crate::async_util::{RemoteResult, Runtime};
 use crate::checksums::combine_checksums;
 
-use super::UploadError;
+use super::content_type::sniff_content_type;
+use super::hasher::ChecksumHasher;
+use super::{ChecksumHasherError, UploadError, UploadScheduler, WriteThroughBuffer, acquire_turn};
 
 const MAX_S3_MULTIPART_UPLOAD_PARTS: usize = 10000;
 
+/// Maximum size of the parts of a multipart upload.
+const MAX_S3_PART_SIZE: usize = 5 * 1024 * 1024 * 1024;
+
+/// Alignment of the part sizes chosen from size hints, see [UploadRequest::set_size_hint].
+const PART_SIZE_ALIGNMENT: usize = 1024 * 1024;
+
+type PutRequestResult<Client> = RemoteResult<
+    <Client as ObjectClient>::PutObjectRequest,
+    ObjectClientError<PutObjectError, <Client as ObjectClient>::ClientError>,
+>;
+
 /// Manages the upload of an object to S3.
 ///
-/// Wraps a PutObject request and enforces sequential writes.
+/// Wraps a PutObject request and enforces sequential writes. The request is only started by the first write (or the
+/// completion of an empty upload), so that its part size can still be chosen until then, see
+/// [UploadRequest::set_size_hint].
 pub struct UploadRequest<Client: ObjectClient> {
-    request: RemoteResult<Client::PutObjectRequest, ObjectClientError<PutObjectError, Client::ClientError>>,
+    request: Option<PutRequestResult<Client>>,
+    /// What is needed to start the request, until it is started.
+    pending: Option<PendingRequest<Client>>,
     bucket: String,
     key: String,
     next_request_offset: u64,
     hasher: crc32c::Hasher,
+    /// Checksums of the parts, computed instead of [Self::hasher] when they are sent with an algorithm other than
+    /// CRC32C, whose checksums can't be combined.
+    part_hasher: Option<PartHasher>,
     maximum_upload_size: usize,
     sse: ServerSideEncryption,
+    object_metadata: ObjectMetadata,
+    object_tags: ObjectTags,
+    /// Whether to recognize the content type of the object from its first write, see
+    /// [UploadRequestParams::sniff_content_type].
+    sniff_content_type: bool,
+    /// Data written so far, to put to the write-through cache once the upload completes.
+    write_through: Option<WriteThroughBuffer>,
+    scheduler: Option<UploadScheduler>,
+}
+
+struct PendingRequest<Client> {
+    runtime: Runtime,
+    client: Client,
+    params: PutObjectParams,
 }
 
 /// Parameters to initialize an [UploadRequest].
@@ -36,6 +71,17 @@ pub struct UploadRequestParams {
     pub server_side_encryption: ServerSideEncryption,
     pub default_checksum_algorithm: Option<ChecksumAlgorithm>,
     pub storage_class: Option<String>,
+    pub object_metadata: ObjectMetadata,
+    pub object_tags: ObjectTags,
+    /// Content type of the object, if known from its key, see [super::UploaderConfig::content_types].
+    pub content_type: Option<String>,
+    /// Recognize the content type of an object without one from the magic bytes at the start of its content.
+    pub sniff_content_type: bool,
+    pub write_through: Option<WriteThroughBuffer>,
+    /// Wait for the turn of the upload before each write, see [super::UploaderConfig::max_concurrent_uploads].
+    pub scheduler: Option<UploadScheduler>,
+    /// Maximum number of parts the client sends at once, see [super::UploaderConfig::max_concurrent_parts_per_upload].
+    pub max_concurrent_parts: Option<u32>,
 }
 
 impl<Client> UploadRequest<Client>
@@ -49,12 +95,15 @@ where
     ) -> Result<Self, UploadError<Client::ClientError>> {
         let mut put_object_params = PutObjectParams::new();
 
+        let mut part_hasher = None;
         match &params.default_checksum_algorithm {
-            Some(ChecksumAlgorithm::Crc32c) => {
-                put_object_params = put_object_params.trailing_checksums(PutObjectTrailingChecksums::Enabled);
-            }
-            Some(unsupported) => {
-                unimplemented!("checksum algorithm not supported: {:?}", unsupported);
+            Some(checksum_algorithm) => {
+                put_object_params = put_object_params
+                    .trailing_checksums(PutObjectTrailingChecksums::Enabled)
+                    .trailing_checksum_algorithm(checksum_algorithm.clone());
+                if *checksum_algorithm != ChecksumAlgorithm::Crc32c {
+                    part_hasher = Some(PartHasher::new(checksum_algorithm.clone(), client.write_part_size())?);
+                }
             }
             None => {
                 put_object_params = put_object_params.trailing_checksums(PutObjectTrailingChecksums::ReviewOnly);
@@ -64,6 +113,13 @@ where
         if let Some(storage_class) = &params.storage_class {
             put_object_params = put_object_params.storage_class(storage_class.clone());
         }
+        if let Some(content_type) = &params.content_type {
+            put_object_params = put_object_params.content_type(content_type.clone());
+        }
+        put_object_params = put_object_params
+            .object_metadata(params.object_metadata.clone())
+            .object_tags(params.object_tags.clone())
+            .max_concurrent_parts(params.max_concurrent_parts);
         // If we have detected corruption of SSE settings, we return an error, which will currently be reported as
         // `libc::EIO` on `open()`. MP won't be able to open files for write from this point, but this is a relatively
         // low-risk error as data can not be uploaded with wrong SSE settings yet. Thus there is no strong reason for
@@ -72,21 +128,28 @@ where
         put_object_params = put_object_params.server_side_encryption(sse_type);
         put_object_params = put_object_params.ssekms_key_id(key_id);
 
-        let put_bucket = params.bucket.to_owned();
-        let put_key = params.key.to_owned();
         let maximum_upload_size = client.write_part_size().saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS);
-        let request = runtime
-            .spawn_with_result(async move { client.put_object(&put_bucket, &put_key, &put_object_params).await })
-            .unwrap();
+        let pending = PendingRequest {
+            runtime: runtime.clone(),
+            client,
+            params: put_object_params,
+        };
 
         Ok(UploadRequest {
-            request,
+            request: None,
+            pending: Some(pending),
             bucket: params.bucket,
             key: params.key,
             next_request_offset: 0,
             hasher: crc32c::Hasher::new(),
+            part_hasher,
             maximum_upload_size,
             sse: params.server_side_encryption,
+            object_metadata: params.object_metadata,
+            object_tags: params.object_tags,
+            sniff_content_type: params.sniff_content_type && params.content_type.is_none(),
+            write_through: params.write_through,
+            scheduler: params.scheduler,
         })
     }
 
@@ -94,6 +157,67 @@ where
         self.next_request_offset
     }
 
+    /// Choose the part size of the upload from a hint of the final size of the object, such as the size its file was
+    /// preallocated to with `fallocate`, so that the object fits in the maximum number of parts of a multipart upload.
+    /// The part size is never made smaller than the write part size of the client. Return false, ignoring the hint,
+    /// if the upload was already started by a write.
+    pub fn set_size_hint(&mut self, size: u64) -> bool {
+        let Some(pending) = &mut self.pending else {
+            return false;
+        };
+        let minimum_part_size = pending.params.part_size.unwrap_or(pending.client.write_part_size());
+        let part_size = usize::try_from(size.div_ceil(MAX_S3_MULTIPART_UPLOAD_PARTS as u64)).unwrap_or(usize::MAX);
+        if part_size > minimum_part_size {
+            let part_size = part_size.min(MAX_S3_PART_SIZE).next_multiple_of(PART_SIZE_ALIGNMENT);
+            debug!(key = self.key, size, part_size, "chose part size from size hint");
+            pending.params.part_size = Some(part_size);
+            if let Some(part_hasher) = &mut self.part_hasher {
+                part_hasher.part_size = part_size;
+            }
+            self.maximum_upload_size = part_size.saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS);
+        }
+        true
+    }
+
+    /// The PutObject request, started by the first call.
+    fn request(&mut self) -> &mut PutRequestResult<Client> {
+        if let Some(PendingRequest {
+            runtime,
+            client,
+            params,
+        }) = self.pending.take()
+        {
+            let bucket = self.bucket.clone();
+            let key = self.key.clone();
+            let request = runtime
+                .spawn_with_result(async move { client.put_object(&bucket, &key, &params).await })
+                .unwrap();
+            self.request = Some(request);
+        }
+        self.request.as_mut().expect("the request is started")
+    }
+
+    /// The user-defined metadata the object is uploaded with.
+    pub fn object_metadata(&self) -> &ObjectMetadata {
+        &self.object_metadata
+    }
+
+    /// The tags the object is uploaded with.
+    pub fn object_tags(&self) -> &ObjectTags {
+        &self.object_tags
+    }
+
+    /// Replace the tags the object is uploaded with. Return false, leaving them unchanged, if the upload was already
+    /// started by a write.
+    pub fn set_object_tags(&mut self, object_tags: ObjectTags) -> bool {
+        let Some(pending) = &mut self.pending else {
+            return false;
+        };
+        pending.params.object_tags = object_tags.clone();
+        self.object_tags = object_tags;
+        true
+    }
+
     pub async fn write(&mut self, offset: i64, data: &[u8]) -> Result<usize, UploadError<Client::ClientError>> {
         let next_offset = self.next_request_offset;
         if offset != next_offset as i64 {
@@ -108,8 +232,20 @@ where
             });
         }
 
-        self.hasher.update(data);
-        self.request
+        if self.sniff_content_type
+            && let Some(pending) = &mut self.pending
+            && let Some(content_type) = sniff_content_type(data)
+        {
+            debug!(key = self.key, content_type, "recognized content type");
+            pending.params.content_type = Some(content_type.to_owned());
+        }
+
+        let _permit = acquire_turn(self.scheduler.as_ref()).await;
+        match &mut self.part_hasher {
+            Some(part_hasher) => part_hasher.update(data)?,
+            None => self.hasher.update(data),
+        }
+        self.request()
             .get_mut()
             .await?
             .ok_or(UploadError::UploadAlreadyTerminated)?
@@ -117,18 +253,30 @@ where
             .await?;
 
         self.next_request_offset += data.len() as u64;
+        if let Some(write_through) = &mut self.write_through
+            && !write_through.push(data)
+        {
+            debug!(key = self.key, "object will not be put to the write-through cache");
+            self.write_through = None;
+        }
         Ok(data.len())
     }
 
-    pub async fn complete(self) -> Result<PutObjectResult, UploadError<Client::ClientError>> {
+    pub async fn complete(mut self) -> Result<PutObjectResult, UploadError<Client::ClientError>> {
+        self.request();
         let size = self.size();
         let checksum = self.hasher.finalize();
+        let part_checksums = self.part_hasher.map(PartHasher::finalize).transpose()?;
         let result = self
             .request
+            .expect("the request is started")
             .into_inner()
             .await?
             .ok_or(UploadError::UploadAlreadyTerminated)?
-            .review_and_complete(move |review| verify_checksums(review, size, checksum))
+            .review_and_complete(move |review| match part_checksums {
+                Some(part_checksums) => verify_part_checksums(review, size, part_checksums),
+                None => verify_checksums(review, size, checksum),
+            })
             .await?;
         if let Err(err) = self
             .sse
@@ -142,6 +290,9 @@ where
             // 2. the reported error is severe as the object was already uploaded to S3.
             std::process::exit(1);
         }
+        if let Some(write_through) = self.write_through {
+            write_through.commit(self.key, result.etag.clone()).await;
+        }
         Ok(result)
     }
 }
@@ -198,20 +349,132 @@ fn verify_checksums(review: UploadReview, expected_size: u64, expected_checksum:
     true
 }
 
+/// Checksums of the parts of an upload, computed as it is written, for the algorithms whose checksums of the parts
+/// can't be combined into the checksum of the whole upload.
+#[derive(Debug)]
+struct PartHasher {
+    checksum_algorithm: Option<ChecksumAlgorithm>,
+    part_size: usize,
+    hasher: ChecksumHasher,
+    /// Size of the data of the current part hashed so far.
+    hashed_size: usize,
+    /// Base64-encoded checksums of the previous parts.
+    checksums: Vec<String>,
+}
+
+impl PartHasher {
+    fn new(checksum_algorithm: ChecksumAlgorithm, part_size: usize) -> Result<Self, ChecksumHasherError> {
+        let checksum_algorithm = Some(checksum_algorithm);
+        let hasher = ChecksumHasher::new(&checksum_algorithm)?;
+        Ok(Self {
+            checksum_algorithm,
+            part_size,
+            hasher,
+            hashed_size: 0,
+            checksums: Vec::new(),
+        })
+    }
+
+    fn update(&mut self, mut data: &[u8]) -> Result<(), ChecksumHasherError> {
+        while !data.is_empty() {
+            let len = data.len().min(self.part_size - self.hashed_size);
+            self.hasher.update(&data[..len])?;
+            self.hashed_size += len;
+            data = &data[len..];
+            if self.hashed_size == self.part_size {
+                self.finish_part()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_part(&mut self) -> Result<(), ChecksumHasherError> {
+        let hasher = std::mem::replace(&mut self.hasher, ChecksumHasher::new(&self.checksum_algorithm)?);
+        self.checksums.extend(hasher.finalize()?.and_then(checksum_to_base64));
+        self.hashed_size = 0;
+        Ok(())
+    }
+
+    /// The base64-encoded checksums of the parts, including the last one.
+    fn finalize(mut self) -> Result<Vec<String>, ChecksumHasherError> {
+        if self.hashed_size > 0 {
+            self.finish_part()?;
+        }
+        Ok(self.checksums)
+    }
+}
+
+/// The base64-encoded value of a checksum, as in upload reviews.
+fn checksum_to_base64(checksum: UploadChecksum) -> Option<String> {
+    let checksum = Checksum::from(Some(checksum));
+    checksum
+        .checksum_crc64nvme
+        .or(checksum.checksum_crc32)
+        .or(checksum.checksum_crc32c)
+        .or(checksum.checksum_sha1)
+        .or(checksum.checksum_sha256)
+}
+
+/// Verify the checksum of each uploaded part against the checksums of the parts written, see [PartHasher]. Empty
+/// parts, such as the only part of an empty upload, are not checked.
+fn verify_part_checksums(review: UploadReview, expected_size: u64, expected_checksums: Vec<String>) -> bool {
+    let mut uploaded_size = 0u64;
+    let mut expected_checksums = expected_checksums.into_iter();
+    for (i, part) in review.parts.iter().enumerate() {
+        uploaded_size += part.size;
+        if part.size == 0 {
+            continue;
+        }
+
+        let Some(checksum) = &part.checksum else {
+            error!(part_number = i + 1, "missing part checksum");
+            return false;
+        };
+        let Some(expected_checksum) = expected_checksums.next() else {
+            error!(part_number = i + 1, "more parts were uploaded than written");
+            return false;
+        };
+        if *checksum != expected_checksum {
+            error!(
+                part_number = i + 1,
+                checksum, expected_checksum, "Checksum of uploaded part differs from expected checksum"
+            );
+            return false;
+        }
+    }
+
+    if uploaded_size != expected_size {
+        error!(
+            uploaded_size,
+            expected_size, "Total uploaded size differs from expected size"
+        );
+        return false;
+    }
+
+    if expected_checksums.next().is_some() {
+        error!("fewer parts were uploaded than written");
+        return false;
+    }
+
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use crate::data_cache::{DataCache, InMemoryDataCache};
     use crate::fs::SseCorruptedError;
     use crate::mem_limiter::{MINIMUM_MEM_LIMIT, MemoryLimiter};
     use crate::memory::PagedPool;
+    use crate::object::ObjectId;
     use crate::sync::Arc;
-    use crate::upload::{Uploader, UploaderConfig};
+    use crate::upload::{ContentTypes, Uploader, UploaderConfig, WriteThroughCache};
 
     use futures::executor::ThreadPool;
     use mountpoint_s3_client::failure_client::{CountdownFailureConfig, countdown_failure_client};
     use mountpoint_s3_client::mock_client::{MockClient, MockClientError};
-    use mountpoint_s3_client::types::ChecksumAlgorithm;
+    use mountpoint_s3_client::types::{ChecksumAlgorithm, ChecksumMode, HeadObjectParams};
     use test_case::test_case;
 
     use super::*;
@@ -262,6 +525,178 @@ mod tests {
         assert!(!client.is_upload_in_progress(key));
     }
 
+    #[test_case(ChecksumAlgorithm::Crc32c; "crc32c")]
+    #[test_case(ChecksumAlgorithm::Crc32; "crc32")]
+    #[test_case(ChecksumAlgorithm::Sha1; "sha1")]
+    #[test_case(ChecksumAlgorithm::Sha256; "sha256")]
+    #[tokio::test]
+    async fn checksum_algorithm_test(checksum_algorithm: ChecksumAlgorithm) {
+        let bucket = "bucket";
+        let key = "hello";
+
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        let buffer_size = client.write_part_size();
+        let pool = PagedPool::new_with_candidate_sizes([buffer_size]);
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let mem_limiter = MemoryLimiter::new(pool.clone(), MINIMUM_MEM_LIMIT);
+        let uploader = Uploader::new(
+            client.clone(),
+            runtime,
+            pool,
+            mem_limiter.into(),
+            UploaderConfig::new(buffer_size).default_checksum_algorithm(Some(checksum_algorithm.clone())),
+        );
+
+        let data = vec![0xaa; 100];
+        let mut request = uploader.start_atomic_upload(bucket.to_owned(), key.to_owned()).unwrap();
+        let mut offset = 0;
+        for chunk in data.chunks(20) {
+            offset += request.write(offset, chunk).await.unwrap() as i64;
+        }
+        request.complete().await.unwrap();
+
+        let head = client
+            .head_object(
+                bucket,
+                key,
+                &HeadObjectParams::new().checksum_mode(Some(ChecksumMode::Enabled)),
+            )
+            .await
+            .expect("object should exist");
+        assert_eq!(head.checksum.algorithms(), vec![checksum_algorithm]);
+    }
+
+    #[tokio::test]
+    async fn object_metadata_test() {
+        let bucket = "bucket";
+        let key = "hello";
+
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        let uploader = new_uploader_for_test(client.clone(), None, ServerSideEncryption::default(), true);
+        let object_metadata = ObjectMetadata::from([("source".to_owned(), "pipeline".to_owned())]);
+        let mut request = uploader
+            .start_atomic_upload_with_metadata(bucket.to_owned(), key.to_owned(), object_metadata.clone())
+            .unwrap();
+        assert_eq!(request.object_metadata(), &object_metadata);
+
+        _ = request.write(0, b"data").await.unwrap();
+        request.complete().await.unwrap();
+
+        let head = client
+            .head_object(bucket, key, &HeadObjectParams::new())
+            .await
+            .expect("object should exist");
+        assert_eq!(head.object_metadata, object_metadata);
+    }
+
+    #[test_case("data.csv", b"a,b,c\n", Some("text/csv"); "extension")]
+    #[test_case("data.CSV", b"\x89PNG\r\n\x1a\n", Some("text/csv"); "extension before content")]
+    #[test_case("image", b"\x89PNG\r\n\x1a\n", Some("image/png"); "sniffed")]
+    #[test_case("data", b"a,b,c\n", None; "unknown")]
+    #[tokio::test]
+    async fn content_type_test(key: &str, data: &[u8], expected: Option<&str>) {
+        let bucket = "bucket";
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        let buffer_size = client.write_part_size();
+        let pool = PagedPool::new_with_candidate_sizes([buffer_size]);
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let mem_limiter = MemoryLimiter::new(pool.clone(), MINIMUM_MEM_LIMIT);
+        let uploader = Uploader::new(
+            client.clone(),
+            runtime,
+            pool,
+            mem_limiter.into(),
+            UploaderConfig::new(buffer_size).content_types(Some(ContentTypes::new([], true))),
+        );
+
+        let mut request = uploader.start_atomic_upload(bucket.to_owned(), key.to_owned()).unwrap();
+        _ = request.write(0, data).await.unwrap();
+        request.complete().await.unwrap();
+
+        let head = client
+            .head_object(bucket, key, &HeadObjectParams::new())
+            .await
+            .expect("object should exist");
+        assert_eq!(head.content_type.as_deref(), expected);
+    }
+
+    #[tokio::test]
+    async fn write_through_test() {
+        let bucket = "bucket";
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        let cache = Arc::new(InMemoryDataCache::new(8));
+        let buffer_size = client.write_part_size();
+        let pool = PagedPool::new_with_candidate_sizes([buffer_size]);
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let mem_limiter = MemoryLimiter::new(pool.clone(), MINIMUM_MEM_LIMIT);
+        let uploader = Uploader::new(
+            client.clone(),
+            runtime,
+            pool,
+            mem_limiter.into(),
+            UploaderConfig::new(buffer_size).write_through_cache(Some(WriteThroughCache::new(cache.clone(), 1024))),
+        );
+
+        let data = b"Hello, world!";
+        let mut request = uploader.start_atomic_upload(bucket.to_owned(), key.to_owned()).unwrap();
+        let mut offset = 0;
+        for chunk in data.chunks(5) {
+            offset += request.write(offset, chunk).await.unwrap() as i64;
+        }
+        let result = request.complete().await.unwrap();
+
+        let object = client.head_object(bucket, key, &Default::default()).await.unwrap();
+        assert_eq!(
+            object.etag, result.etag,
+            "blocks are cached under the ETag of the new object"
+        );
+        let object_id = ObjectId::new(key.to_owned(), result.etag);
+        assert_eq!(cache.block_count(&object_id), 2);
+        let block = cache.get_block(&object_id, 1, 8, data.len()).await.unwrap().unwrap();
+        assert_eq!(block.into_bytes().unwrap(), &data[8..]);
+    }
+
+    #[tokio::test]
+    async fn scheduled_uploads_test() {
+        let bucket = "bucket";
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(32).build());
+        let buffer_size = client.write_part_size();
+        let pool = PagedPool::new_with_candidate_sizes([buffer_size]);
+        let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+        let mem_limiter = MemoryLimiter::new(pool.clone(), MINIMUM_MEM_LIMIT);
+        let uploader = Uploader::new(
+            client.clone(),
+            runtime,
+            pool,
+            mem_limiter.into(),
+            UploaderConfig::new(buffer_size)
+                .max_concurrent_uploads(Some(1))
+                .max_concurrent_parts_per_upload(Some(1)),
+        );
+
+        // Uploads interleaving their writes each take a turn per write.
+        let data = b"Hello, world!";
+        let mut first = uploader
+            .start_atomic_upload(bucket.to_owned(), "first".to_owned())
+            .unwrap();
+        let mut second = uploader
+            .start_atomic_upload(bucket.to_owned(), "second".to_owned())
+            .unwrap();
+        let mut offset = 0;
+        for chunk in data.chunks(5) {
+            first.write(offset, chunk).await.unwrap();
+            offset += second.write(offset, chunk).await.unwrap() as i64;
+        }
+        first.complete().await.unwrap();
+        second.complete().await.unwrap();
+
+        for key in ["first", "second"] {
+            let object = client.head_object(bucket, key, &Default::default()).await.unwrap();
+            assert_eq!(object.size, data.len() as u64);
+        }
+    }
+
     #[tokio::test]
     async fn write_order_test() {
         let bucket = "bucket";
@@ -400,6 +835,42 @@ mod tests {
         assert!(!client.is_upload_in_progress(key));
     }
 
+    #[tokio::test]
+    async fn size_hint_test() {
+        const PART_SIZE: usize = 32;
+
+        let bucket = "bucket";
+        let key = "hello";
+
+        let client = Arc::new(MockClient::config().bucket(bucket).part_size(PART_SIZE).build());
+        let uploader = new_uploader_for_test(client.clone(), None, ServerSideEncryption::default(), true);
+        let mut request = uploader.start_atomic_upload(bucket.to_owned(), key.to_owned()).unwrap();
+
+        // Hints that fit in parts of the write part size keep it.
+        assert!(request.set_size_hint(1024));
+        assert_eq!(request.maximum_upload_size, PART_SIZE * MAX_S3_MULTIPART_UPLOAD_PARTS);
+        assert!(
+            !client.is_upload_in_progress(key),
+            "the upload should only start on write"
+        );
+
+        let size = 2 * PART_SIZE * MAX_S3_MULTIPART_UPLOAD_PARTS;
+        assert!(request.set_size_hint(size as u64));
+        assert_eq!(
+            request.maximum_upload_size,
+            PART_SIZE_ALIGNMENT * MAX_S3_MULTIPART_UPLOAD_PARTS
+        );
+
+        let data = vec![0xaa; size];
+        request.write(0, &data).await.expect("object should fit");
+        assert!(
+            !request.set_size_hint(u64::MAX),
+            "hints should be ignored once written to"
+        );
+        request.complete().await.unwrap();
+        assert!(client.contains_key(key));
+    }
+
     #[test_case(Some("aws:kmr"), Some("some_key_alias"))]
     #[test_case(Some("aws:kms"), Some("some_key_ali`s"))]
     #[test_case(None, Some("some_key_alias"))]
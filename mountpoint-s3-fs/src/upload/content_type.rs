@@ -0,0 +1,230 @@
+//! Choosing the `Content-Type` of new objects.
+//!
+//! S3 stores objects uploaded without a content type as `binary/octet-stream`, which web browsers, CDNs and analytics
+//! tools reading them don't know how to handle. With [crate::S3FilesystemConfig::content_types], the content type of a
+//! new object is looked up by the extension of its key, such as `text/csv` for `data.csv`, in a built-in table that
+//! [ContentTypeMapping]s can extend or override. Objects whose extension is unknown can also have their content type
+//! recognized from the magic bytes at the start of their content.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Content types of the extensions of common file formats.
+const DEFAULT_CONTENT_TYPES: &[(&str, &str)] = &[
+    ("avif", "image/avif"),
+    ("bz2", "application/x-bzip2"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("jsonl", "application/jsonl"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ndjson", "application/x-ndjson"),
+    ("parquet", "application/vnd.apache.parquet"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tsv", "text/tab-separated-values"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xml", "application/xml"),
+    ("xz", "application/x-xz"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("zip", "application/zip"),
+    ("zst", "application/zstd"),
+];
+
+/// Content types recognized by the bytes at the start of an object, and the offset these bytes are at.
+const MAGIC_BYTES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"PAR1", "application/vnd.apache.parquet"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x00asm", "application/wasm"),
+    (0, b"<?xml", "application/xml"),
+];
+
+/// Number of bytes at the start of an object its content type is recognized from, see [sniff_content_type].
+pub(super) const SNIFFED_LEN: usize = 12;
+
+/// Content type of the new objects whose key has an extension, overriding the built-in one if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentTypeMapping {
+    /// The extension, in lowercase and without its leading dot.
+    extension: String,
+    content_type: String,
+}
+
+#[derive(Error, Debug)]
+pub enum ContentTypeMappingError {
+    #[error("content type mapping must be of the form EXTENSION=CONTENT_TYPE")]
+    InvalidMapping,
+}
+
+impl ContentTypeMapping {
+    pub fn new(extension: &str, content_type: &str) -> Self {
+        Self {
+            extension: extension.trim_start_matches('.').to_lowercase(),
+            content_type: content_type.to_owned(),
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+}
+
+impl Display for ContentTypeMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.extension, self.content_type)
+    }
+}
+
+impl FromStr for ContentTypeMapping {
+    type Err = ContentTypeMappingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (extension, content_type) = s
+            .split_once('=')
+            .filter(|(extension, content_type)| {
+                !extension.trim_start_matches('.').is_empty() && !content_type.is_empty()
+            })
+            .ok_or(ContentTypeMappingError::InvalidMapping)?;
+        Ok(Self::new(extension, content_type))
+    }
+}
+
+/// How the content types of new objects are chosen, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ContentTypes {
+    /// Content types by extension, in lowercase and without their leading dot.
+    by_extension: HashMap<String, String>,
+    /// Whether objects with an unknown extension have their content type recognized from their first bytes.
+    sniff: bool,
+}
+
+impl ContentTypes {
+    /// Look up content types in the built-in table, extended or overridden by `mappings`. If `sniff` is set, objects
+    /// whose extension is unknown have their content type recognized from their first bytes.
+    pub fn new(mappings: impl IntoIterator<Item = ContentTypeMapping>, sniff: bool) -> Self {
+        let mut by_extension: HashMap<_, _> = DEFAULT_CONTENT_TYPES
+            .iter()
+            .map(|(extension, content_type)| (extension.to_string(), content_type.to_string()))
+            .collect();
+        by_extension.extend(
+            mappings
+                .into_iter()
+                .map(|mapping| (mapping.extension, mapping.content_type)),
+        );
+        Self { by_extension, sniff }
+    }
+
+    /// The content type of a new object with the given key, from its extension.
+    pub fn for_key(&self, key: &str) -> Option<&str> {
+        let name = key.rsplit('/').next().unwrap_or(key);
+        let (stem, extension) = name.rsplit_once('.')?;
+        if stem.is_empty() {
+            return None;
+        }
+        self.by_extension.get(&extension.to_lowercase()).map(String::as_str)
+    }
+
+    /// Whether objects with an unknown extension have their content type recognized from their first bytes.
+    pub fn sniffs(&self) -> bool {
+        self.sniff
+    }
+}
+
+/// The content type of an object recognized from the magic bytes at its start, if any.
+pub(super) fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    MAGIC_BYTES
+        .iter()
+        .find(|(offset, magic, _)| data.get(*offset..offset + magic.len()) == Some(*magic))
+        .map(|(_, _, content_type)| *content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("data.csv", Some("text/csv"); "extension")]
+    #[test_case("dir/IMAGE.PNG", Some("image/png"); "uppercase extension")]
+    #[test_case("dir.d/archive.tar.gz", Some("application/gzip"); "last extension")]
+    #[test_case("model.ckpt", Some("application/octet-stream"); "mapping")]
+    #[test_case("page.html", Some("application/xhtml+xml"); "overridden")]
+    #[test_case("dir.csv/README", None; "no extension")]
+    #[test_case("dir/.csv", None; "hidden file")]
+    #[test_case("data.unknown", None; "unknown extension")]
+    fn test_for_key(key: &str, expected: Option<&str>) {
+        let content_types = ContentTypes::new(
+            [
+                "ckpt=application/octet-stream".parse().unwrap(),
+                ".HTML=application/xhtml+xml".parse().unwrap(),
+            ],
+            false,
+        );
+        assert_eq!(content_types.for_key(key), expected);
+    }
+
+    #[test_case(b"\x89PNG\r\n\x1a\n\x00\x00", Some("image/png"); "png")]
+    #[test_case(b"RIFF\x10\x00\x00\x00WEBPVP8 ", Some("image/webp"); "webp")]
+    #[test_case(b"\x00\x00\x00\x18ftypmp42", Some("video/mp4"); "mp4")]
+    #[test_case(b"PAR1\x15\x04", Some("application/vnd.apache.parquet"); "parquet")]
+    #[test_case(b"PAR", None; "too short")]
+    #[test_case(b"a,b,c\n1,2,3\n", None; "unknown")]
+    fn test_sniff_content_type(data: &[u8], expected: Option<&str>) {
+        assert_eq!(sniff_content_type(data), expected);
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping: ContentTypeMapping = ".CKPT=application/octet-stream".parse().unwrap();
+        assert_eq!(mapping.extension(), "ckpt");
+        assert_eq!(mapping.content_type(), "application/octet-stream");
+        assert_eq!(mapping.to_string(), "ckpt=application/octet-stream");
+
+        "ckpt"
+            .parse::<ContentTypeMapping>()
+            .expect_err("the content type is required");
+        "ckpt="
+            .parse::<ContentTypeMapping>()
+            .expect_err("the content type can't be empty");
+        ".=text/plain"
+            .parse::<ContentTypeMapping>()
+            .expect_err("the extension can't be empty");
+    }
+}
@@ -0,0 +1,1009 @@
+//! This module implements appending to existing objects without incremental uploads.
+//!
+//! The object is replaced by a multipart upload whose first parts are server-side copies of its current content,
+//! with UploadPartCopy, followed by parts holding the appended data. Content shorter than the minimum part size can't
+//! be copied as a part which is not the last one, so it is downloaded into the first part instead.
+//!
+//! Writes can also overwrite part of the object, in which case the content after them is copied after their parts.
+//! Objects are truncated the same way, by copying only the range they retain, see [truncate_object].
+//!
+//! Ranges of other objects can be appended the same way, with [CopyAppendRequest::copy_from], including to new
+//! objects, which is how files are copied server-side.
+
+use std::fmt::Debug;
+use std::ops::Range;
+
+use futures::task::SpawnExt as _;
+use futures::{StreamExt as _, pin_mut};
+use mountpoint_s3_client::ObjectClient;
+use mountpoint_s3_client::error::{MultipartUploadError, ObjectClientError, PutObjectError};
+use mountpoint_s3_client::types::{
+    ChecksumAlgorithm, ClientBackpressureHandle as _, CompleteMultipartUploadParams, CompletedPart,
+    CreateMultipartUploadParams, ETag, GetBodyPart, GetObjectParams, GetObjectResponse as _, HeadObjectParams,
+    HeadObjectResult, ObjectMetadata, ObjectTags, PutObjectResult, PutObjectSingleParams, UploadPartCopyParams,
+    UploadPartParams,
+};
+use tracing::{debug, trace, warn};
+
+use crate::ServerSideEncryption;
+use crate::async_util::Runtime;
+
+use super::hasher::ChecksumHasher;
+use super::{UploadError, UploadScheduler, acquire_turn};
+
+/// Minimum size of the parts of a multipart upload, other than the last one.
+pub(super) const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Maximum size of a part copied with UploadPartCopy.
+const MAX_COPY_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+const MAX_S3_MULTIPART_UPLOAD_PARTS: usize = 10000;
+
+/// Size of the read window when downloading the existing content of an object too small to be copied.
+const READ_WINDOW_SIZE: u64 = MIN_PART_SIZE as u64;
+
+/// Handle for appending data to an existing S3 object, or overwriting part of it, by copying its content into a
+/// multipart upload.
+///
+/// Nothing is sent to S3 until the first write, and completing a request with no writes leaves the object as it is,
+/// or creates an empty one if there was no object, see [CopyAppendParams::initial_etag].
+/// The object is only replaced once the request completes; a request dropped before completing aborts its upload.
+/// Writes must be sequential, starting from the end of the object unless [CopyAppendParams::allow_overwrite] is set.
+pub struct CopyAppendRequest<Client: ObjectClient + Clone + Send + Sync + 'static> {
+    client: Client,
+    runtime: Runtime,
+    params: CopyAppendParams,
+    part_size: usize,
+    /// The multipart upload, started on the first write.
+    upload: Option<MultipartUpload>,
+    /// Data not uploaded yet, starting with the existing content of the object if it was too small to be copied.
+    buffer: Vec<u8>,
+    /// The offset the next write is expected at.
+    next_offset: u64,
+    written_bytes: u64,
+}
+
+/// Parameters to initialize a [CopyAppendRequest].
+pub struct CopyAppendParams {
+    pub bucket: String,
+    pub key: String,
+    /// Size of the existing object, which writes start from.
+    pub initial_size: u64,
+    /// ETag of the existing object, which fails the request if the object changed. `None` if there is no object yet,
+    /// in which case the initial size must be zero and a new object is created.
+    pub initial_etag: Option<ETag>,
+    /// Storage class of a new object. Existing objects keep theirs.
+    pub storage_class: Option<String>,
+    /// User-defined metadata of a new object. Existing objects keep theirs.
+    pub object_metadata: ObjectMetadata,
+    /// Tags of the object. Unlike its storage class and user-defined metadata, existing objects don't keep theirs.
+    pub object_tags: ObjectTags,
+    /// Content type of a new object. Existing objects keep theirs.
+    pub content_type: Option<String>,
+    pub server_side_encryption: ServerSideEncryption,
+    /// Checksum algorithm of the parts.
+    pub default_checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Allow the first write at any offset up to the size of the object, overwriting its content from there. The
+    /// content after the last write is kept.
+    pub allow_overwrite: bool,
+    /// ETag the object replaced by a new one must still have when the request completes, which fails with
+    /// [UploadError::ObjectChanged] otherwise. Only used when there is no initial ETag.
+    pub replace_if_match: Option<ETag>,
+    /// Wait for the turn of the upload before each part, see [super::UploaderConfig::max_concurrent_uploads].
+    pub scheduler: Option<UploadScheduler>,
+}
+
+/// An object whose content is copied into the upload: the object being appended to, or the source of
+/// [CopyAppendRequest::copy_from].
+struct CopySource {
+    key: String,
+    /// ETag the object must still have.
+    etag: Option<ETag>,
+}
+
+#[derive(Debug)]
+struct MultipartUpload {
+    upload_id: String,
+    parts: Vec<CompletedPart>,
+}
+
+impl<Client> CopyAppendRequest<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    pub(super) fn new(runtime: &Runtime, client: Client, params: CopyAppendParams) -> Self {
+        let part_size = client.write_part_size().max(MIN_PART_SIZE);
+        Self {
+            client,
+            runtime: runtime.clone(),
+            next_offset: params.initial_size,
+            params,
+            part_size,
+            upload: None,
+            buffer: Vec::new(),
+            written_bytes: 0,
+        }
+    }
+
+    /// The size of the object once the request completes.
+    pub fn size(&self) -> u64 {
+        self.next_offset.max(self.params.initial_size)
+    }
+
+    /// The number of bytes written to the request.
+    pub fn written_bytes(&self) -> u64 {
+        self.written_bytes
+    }
+
+    pub async fn write(&mut self, offset: u64, data: &[u8]) -> Result<usize, UploadError<Client::ClientError>> {
+        let first_overwrite = self.upload.is_none() && self.params.allow_overwrite && offset < self.next_offset;
+        if offset != self.next_offset && !first_overwrite {
+            return Err(UploadError::OutOfOrderWrite {
+                write_offset: offset,
+                expected_offset: self.next_offset,
+            });
+        }
+        let maximum_size = self.part_size.saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS);
+        if offset + data.len() as u64 > maximum_size as u64 {
+            return Err(UploadError::ObjectTooBig { maximum_size });
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        if self.upload.is_none() {
+            self.start(offset).await?;
+            self.next_offset = offset;
+            if offset < self.params.initial_size {
+                metrics::counter!("fs.overwrites_by_copy").increment(1);
+            } else {
+                metrics::counter!("fs.appends_by_copy").increment(1);
+            }
+        }
+        self.buffer.extend_from_slice(data);
+        self.next_offset += data.len() as u64;
+        self.written_bytes += data.len() as u64;
+        if self.buffer.len() >= self.part_size {
+            self.upload_buffer().await?;
+        }
+        Ok(data.len())
+    }
+
+    /// Append the given range of another object, which fails the request if it no longer has the given ETag. The
+    /// range is copied with UploadPartCopy, except for its start when it tops up the data buffered so far into a
+    /// part large enough, and ranges too small to be copied as a part which is not the last one, which are
+    /// downloaded. Like writes, copies must be sequential.
+    pub async fn copy_from(
+        &mut self,
+        offset: u64,
+        source_key: String,
+        source_etag: ETag,
+        mut range: Range<u64>,
+    ) -> Result<u64, UploadError<Client::ClientError>> {
+        if offset != self.next_offset {
+            return Err(UploadError::OutOfOrderWrite {
+                write_offset: offset,
+                expected_offset: self.next_offset,
+            });
+        }
+        let len = range.end.saturating_sub(range.start);
+        let maximum_size = self.part_size.saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS);
+        if offset + len > maximum_size as u64 {
+            return Err(UploadError::ObjectTooBig { maximum_size });
+        }
+        if len == 0 {
+            return Ok(0);
+        }
+
+        if self.upload.is_none() {
+            self.start(offset).await?;
+        }
+        let source = CopySource {
+            key: source_key,
+            etag: Some(source_etag),
+        };
+        if !self.buffer.is_empty() && self.buffer.len() < MIN_PART_SIZE {
+            let end = range.end.min(range.start + (MIN_PART_SIZE - self.buffer.len()) as u64);
+            self.download_range(&source, range.start..end).await?;
+            range.start = end;
+        }
+        if range.end - range.start >= MIN_PART_SIZE as u64 {
+            if !self.buffer.is_empty() {
+                self.upload_buffer().await?;
+            }
+            self.copy_range(&source, range).await?;
+        } else if !range.is_empty() {
+            self.download_range(&source, range).await?;
+        }
+        if self.buffer.len() >= self.part_size {
+            self.upload_buffer().await?;
+        }
+        self.next_offset += len;
+        self.written_bytes += len;
+        Ok(len)
+    }
+
+    /// Complete the upload, replacing the object. Returns `None` if nothing was written, in which case the object is
+    /// left as it is, unless there was no object, in which case an empty one is created.
+    pub async fn complete(mut self) -> Result<Option<PutObjectResult>, UploadError<Client::ClientError>> {
+        if self.upload.is_none() {
+            if self.params.initial_etag.is_some() {
+                return Ok(None);
+            }
+            let (sse_type, key_id) = self.params.server_side_encryption.clone().into_inner()?;
+            let mut put_params = PutObjectSingleParams::new()
+                .server_side_encryption(sse_type)
+                .ssekms_key_id(key_id)
+                .object_metadata(self.params.object_metadata.clone())
+                .object_tags(self.params.object_tags.clone());
+            if let Some(storage_class) = &self.params.storage_class {
+                put_params = put_params.storage_class(storage_class.clone());
+            }
+            if let Some(content_type) = &self.params.content_type {
+                put_params = put_params.content_type(content_type.clone());
+            }
+            if let Some(etag) = &self.params.replace_if_match {
+                put_params = put_params.if_match(Some(etag.clone()));
+            }
+            let result = self
+                .client
+                .put_object_single(&self.params.bucket, &self.params.key, &put_params, [])
+                .await
+                .map_err(|err| match err {
+                    ObjectClientError::ServiceError(PutObjectError::PreconditionFailed)
+                        if self.params.replace_if_match.is_some() =>
+                    {
+                        UploadError::ObjectChanged
+                    }
+                    err => err.into(),
+                })?;
+            debug!(key = self.params.key, "created empty object");
+            return Ok(Some(result));
+        }
+        // Copy the content after the data written, if it overwrote part of the object. The part holding the end of
+        // the data isn't the last one then, so it is first topped up with the start of that content if too small.
+        let mut suffix = self.next_offset..self.params.initial_size;
+        if !suffix.is_empty() && !self.buffer.is_empty() && self.buffer.len() < MIN_PART_SIZE {
+            let end = suffix
+                .end
+                .min(suffix.start + (MIN_PART_SIZE - self.buffer.len()) as u64);
+            self.download_range(&self.own_source(), suffix.start..end).await?;
+            suffix.start = end;
+        }
+        if !self.buffer.is_empty() {
+            self.upload_buffer().await?;
+        }
+        if !suffix.is_empty() {
+            self.copy_range(&self.own_source(), suffix).await?;
+        }
+        let upload = self.upload.as_ref().expect("upload should be started");
+        let complete_params = CompleteMultipartUploadParams::new().if_match(self.params.replace_if_match.clone());
+        let result = self
+            .client
+            .complete_multipart_upload(
+                &self.params.bucket,
+                &self.params.key,
+                &upload.upload_id,
+                &upload.parts,
+                &complete_params,
+            )
+            .await
+            .map_err(|err| match err {
+                ObjectClientError::ServiceError(MultipartUploadError::PreconditionFailed)
+                    if self.params.replace_if_match.is_some() =>
+                {
+                    UploadError::ObjectChanged
+                }
+                err => err.into(),
+            })?;
+        debug!(
+            key = self.params.key,
+            parts = upload.parts.len(),
+            "append by copy completed"
+        );
+        self.upload = None;
+        Ok(Some(result))
+    }
+
+    /// The object being appended to, as a source of copies.
+    fn own_source(&self) -> CopySource {
+        CopySource {
+            key: self.params.key.clone(),
+            etag: self.params.initial_etag.clone(),
+        }
+    }
+
+    /// Start the multipart upload, and copy the existing content of the object up to `prefix_size` into it.
+    async fn start(&mut self, prefix_size: u64) -> Result<(), UploadError<Client::ClientError>> {
+        let bucket = &self.params.bucket;
+        let key = &self.params.key;
+        // The new object keeps the storage class, the user-defined metadata and the content type of the existing one.
+        let (object_metadata, storage_class, content_type) = match &self.params.initial_etag {
+            Some(etag) => {
+                let head = head_object_if_match(&self.client, bucket, key, etag).await?;
+                (head.object_metadata, head.storage_class, head.content_type)
+            }
+            None => (
+                self.params.object_metadata.clone(),
+                self.params.storage_class.clone(),
+                self.params.content_type.clone(),
+            ),
+        };
+
+        let (sse_type, key_id) = self.params.server_side_encryption.clone().into_inner()?;
+        let mut create_params = CreateMultipartUploadParams::new()
+            .checksum_algorithm(self.params.default_checksum_algorithm.clone())
+            .server_side_encryption(sse_type)
+            .ssekms_key_id(key_id)
+            .object_metadata(object_metadata)
+            .object_tags(self.params.object_tags.clone());
+        if let Some(storage_class) = storage_class {
+            create_params = create_params.storage_class(storage_class);
+        }
+        if let Some(content_type) = content_type {
+            create_params = create_params.content_type(content_type);
+        }
+        let upload_id = self
+            .client
+            .create_multipart_upload(bucket, key, &create_params)
+            .await?
+            .upload_id;
+        trace!(key, upload_id, "started append by copy");
+        self.upload = Some(MultipartUpload {
+            upload_id,
+            parts: Vec::new(),
+        });
+
+        if prefix_size >= MIN_PART_SIZE as u64 {
+            self.copy_range(&self.own_source(), 0..prefix_size).await?;
+        } else if prefix_size > 0 {
+            self.download_range(&self.own_source(), 0..prefix_size).await?;
+        }
+        Ok(())
+    }
+
+    /// Copy the given range of an object as the next parts of the upload. The range is split into parts of equal
+    /// sizes, so that they are all large enough if the range is.
+    async fn copy_range(
+        &mut self,
+        source: &CopySource,
+        range: Range<u64>,
+    ) -> Result<(), UploadError<Client::ClientError>> {
+        let upload = self.upload.as_mut().expect("upload should be started");
+        let part_count = (range.end - range.start).div_ceil(MAX_COPY_PART_SIZE);
+        let copy_part_size = (range.end - range.start).div_ceil(part_count);
+        let mut start = range.start;
+        while start < range.end {
+            let end = (start + copy_part_size).min(range.end);
+            let part_number = upload.parts.len() + 1;
+            let copy_params = UploadPartCopyParams::new(source.key.clone())
+                .source_range(Some(start..end))
+                .source_if_match(source.etag.clone());
+            let _permit = acquire_turn(self.params.scheduler.as_ref()).await;
+            let result = self
+                .client
+                .upload_part_copy(
+                    &self.params.bucket,
+                    &self.params.key,
+                    &upload.upload_id,
+                    part_number,
+                    &copy_params,
+                )
+                .await?;
+            upload.parts.push(result.into_completed_part(part_number));
+            start = end;
+        }
+        metrics::counter!("fs.appends_by_copy.copied_bytes").increment(range.end - range.start);
+        Ok(())
+    }
+
+    /// Download the given range of an object into the buffer.
+    async fn download_range(
+        &mut self,
+        source: &CopySource,
+        range: Range<u64>,
+    ) -> Result<(), UploadError<Client::ClientError>> {
+        let len = range.end - range.start;
+        let get_params = GetObjectParams::new()
+            .range(Some(range.clone()))
+            .if_match(source.etag.clone());
+        let mut response = self
+            .client
+            .get_object(&self.params.bucket, &source.key, &get_params)
+            .await?;
+        let mut backpressure_handle = response.backpressure_handle().cloned();
+        if let Some(handle) = backpressure_handle.as_mut() {
+            handle.ensure_read_window(range.start + READ_WINDOW_SIZE);
+        }
+        pin_mut!(response);
+        while let Some(part) = response.next().await {
+            let GetBodyPart { offset, data } = part?;
+            self.buffer.extend_from_slice(&data);
+            if let Some(handle) = backpressure_handle.as_mut() {
+                handle.ensure_read_window(offset + data.len() as u64 + READ_WINDOW_SIZE);
+            }
+        }
+        metrics::counter!("fs.appends_by_copy.downloaded_bytes").increment(len);
+        Ok(())
+    }
+
+    /// Upload the buffered data as the next part of the upload.
+    async fn upload_buffer(&mut self) -> Result<(), UploadError<Client::ClientError>> {
+        let upload = self.upload.as_mut().expect("upload should be started");
+        let part_number = upload.parts.len() + 1;
+        let buffer = std::mem::take(&mut self.buffer);
+        let checksum = ChecksumHasher::checksum(&self.params.default_checksum_algorithm, &buffer)?;
+        let part_params = UploadPartParams::new().checksum(checksum);
+        let _permit = acquire_turn(self.params.scheduler.as_ref()).await;
+        let result = self
+            .client
+            .upload_part(
+                &self.params.bucket,
+                &self.params.key,
+                &upload.upload_id,
+                part_number,
+                &part_params,
+                buffer,
+            )
+            .await?;
+        upload.parts.push(result.into_completed_part(part_number));
+        Ok(())
+    }
+}
+
+/// Truncate the existing object described by the given parameters, which must have its ETag, to `size`, or extend it
+/// with zeros, keeping its storage class and user-defined metadata.
+///
+/// Objects truncated to zero are replaced with an empty object. Otherwise the range they retain is copied into a
+/// multipart upload like for appends, followed by the zeros they are extended with.
+pub(super) async fn truncate_object<Client>(
+    runtime: &Runtime,
+    client: Client,
+    params: CopyAppendParams,
+    size: u64,
+) -> Result<PutObjectResult, UploadError<Client::ClientError>>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    if size == 0 {
+        let etag = params.initial_etag.as_ref().expect("truncated objects should exist");
+        let head = head_object_if_match(&client, &params.bucket, &params.key, etag).await?;
+        let (sse_type, key_id) = params.server_side_encryption.clone().into_inner()?;
+        let mut put_params = PutObjectSingleParams::new()
+            .if_match(Some(etag.clone()))
+            .server_side_encryption(sse_type)
+            .ssekms_key_id(key_id)
+            .object_metadata(head.object_metadata);
+        if let Some(storage_class) = head.storage_class {
+            put_params = put_params.storage_class(storage_class);
+        }
+        if let Some(content_type) = head.content_type {
+            put_params = put_params.content_type(content_type);
+        }
+        let result = client
+            .put_object_single(&params.bucket, &params.key, &put_params, [])
+            .await?;
+        debug!(key = params.key, "truncated object to zero");
+        return Ok(result);
+    }
+
+    let initial_size = params.initial_size;
+    let key = params.key.clone();
+    let mut request = CopyAppendRequest::new(
+        runtime,
+        client,
+        CopyAppendParams {
+            initial_size: initial_size.min(size),
+            ..params
+        },
+    );
+    request.start(request.params.initial_size).await?;
+    let zeros = vec![0u8; request.part_size];
+    let mut offset = request.size();
+    while offset < size {
+        let len = (size - offset).min(zeros.len() as u64) as usize;
+        offset += request.write(offset, &zeros[..len]).await? as u64;
+    }
+    let result = request.complete().await?.expect("upload should be started");
+    debug!(key, initial_size, size, "truncated object");
+    Ok(result)
+}
+
+/// HeadObject, failing if the object's ETag is no longer the given one.
+async fn head_object_if_match<Client: ObjectClient>(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    etag: &ETag,
+) -> Result<HeadObjectResult, UploadError<Client::ClientError>> {
+    let head = client.head_object(bucket, key, &HeadObjectParams::new()).await?;
+    if head.etag != *etag {
+        return Err(ObjectClientError::ServiceError(MultipartUploadError::PreconditionFailed).into());
+    }
+    Ok(head)
+}
+
+impl<Client> Drop for CopyAppendRequest<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        let Some(upload) = self.upload.take() else {
+            return;
+        };
+        let client = self.client.clone();
+        let bucket = self.params.bucket.clone();
+        let key = self.params.key.clone();
+        let abort = async move {
+            if let Err(error) = client.abort_multipart_upload(&bucket, &key, &upload.upload_id).await {
+                warn!(
+                    key,
+                    upload_id = upload.upload_id,
+                    ?error,
+                    "failed to abort the upload of an append"
+                );
+            }
+        };
+        if let Err(error) = self.runtime.spawn(abort) {
+            warn!(key = self.params.key, ?error, "failed to abort the upload of an append");
+        }
+    }
+}
+
+impl<Client> Debug for CopyAppendRequest<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CopyAppendRequest")
+            .field("bucket", &self.params.bucket)
+            .field("key", &self.params.key)
+            .field("upload", &self.upload)
+            .field("next_offset", &self.next_offset)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use futures::executor::ThreadPool;
+    use mountpoint_s3_client::mock_client::{MockClient, MockObject, Operation};
+    use mountpoint_s3_client::types::GetObjectParams;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::sync::Arc;
+
+    fn new_runtime() -> Runtime {
+        Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap())
+    }
+
+    fn new_params(key: &str, object: &MockObject) -> CopyAppendParams {
+        CopyAppendParams {
+            bucket: "bucket".to_owned(),
+            key: key.to_owned(),
+            initial_size: object.len() as u64,
+            initial_etag: Some(object.etag()),
+            storage_class: None,
+            object_metadata: Default::default(),
+            object_tags: Default::default(),
+            content_type: None,
+            server_side_encryption: Default::default(),
+            default_checksum_algorithm: Some(ChecksumAlgorithm::Crc32c),
+            allow_overwrite: false,
+            replace_if_match: None,
+            scheduler: None,
+        }
+    }
+
+    fn new_request(client: &Arc<MockClient>, key: &str, object: &MockObject) -> CopyAppendRequest<Arc<MockClient>> {
+        CopyAppendRequest::new(&new_runtime(), client.clone(), new_params(key, object))
+    }
+
+    #[test_case(0, 0; "empty object")]
+    #[test_case(1024, 0; "small object")]
+    #[test_case(MIN_PART_SIZE + 1, 1; "copied object")]
+    #[tokio::test]
+    async fn test_append(initial_size: usize, expected_copies: u64) {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let mut object = MockObject::ramp(0xaa, initial_size, ETag::for_tests());
+        object.set_storage_class(Some("STANDARD_IA".to_owned()));
+        object.set_object_metadata(HashMap::from([("mode".to_owned(), "644".to_owned())]));
+        object.set_content_type(Some("text/csv".to_owned()));
+        client.add_object(key, object.clone());
+        let copies = client.new_counter(Operation::UploadPartCopy);
+
+        let mut request = new_request(&client, key, &object);
+        let mut expected = object.read(0, initial_size).to_vec();
+        let mut offset = initial_size as u64;
+        for data in [vec![0x01; 100], vec![0x02; MIN_PART_SIZE], vec![0x03; 10]] {
+            offset += request.write(offset, &data).await.expect("write should succeed") as u64;
+            expected.extend_from_slice(&data);
+        }
+        assert_eq!(request.size(), offset);
+        assert_eq!(request.written_bytes(), offset - initial_size as u64);
+        // The object is only replaced once the request completes.
+        assert_eq!(
+            client
+                .head_object("bucket", key, &HeadObjectParams::new())
+                .await
+                .unwrap()
+                .size,
+            initial_size as u64
+        );
+
+        let result = request.complete().await.expect("complete should succeed");
+        assert!(result.is_some());
+        assert_eq!(copies.count(), expected_copies);
+        assert_eq!(client.multipart_upload_count(), 0);
+
+        let head = client
+            .head_object("bucket", key, &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.storage_class.as_deref(), Some("STANDARD_IA"));
+        assert_eq!(head.object_metadata.get("mode").map(String::as_str), Some("644"));
+        assert_eq!(head.content_type.as_deref(), Some("text/csv"));
+        let response = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        let actual = response.collect().await.expect("failed to collect body");
+        assert_eq!(&actual[..], &expected[..]);
+    }
+
+    #[tokio::test]
+    async fn test_append_nothing() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+        let creates = client.new_counter(Operation::CreateMultipartUpload);
+
+        let request = new_request(&client, key, &object);
+        assert!(request.complete().await.expect("complete should succeed").is_none());
+        assert_eq!(creates.count(), 0);
+        assert_eq!(
+            client
+                .head_object("bucket", key, &HeadObjectParams::new())
+                .await
+                .unwrap()
+                .etag,
+            object.etag()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_out_of_order() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+
+        let mut request = new_request(&client, key, &object);
+        let result = request.write(0, b"hello").await;
+        assert!(matches!(
+            result,
+            Err(UploadError::OutOfOrderWrite {
+                write_offset: 0,
+                expected_offset: 11
+            })
+        ));
+    }
+
+    #[test_case(1024, 100, 10, 0, 0; "small object")]
+    #[test_case(1024, 1000, 100, 0, 0; "small object extended")]
+    #[test_case(3 * MIN_PART_SIZE, 0, 10, 0, 1; "start of copied object")]
+    #[test_case(3 * MIN_PART_SIZE, MIN_PART_SIZE, 10, 1, 1; "middle of copied object")]
+    #[test_case(3 * MIN_PART_SIZE, 2 * MIN_PART_SIZE, MIN_PART_SIZE + 10, 1, 0; "end of copied object")]
+    #[test_case(3 * MIN_PART_SIZE, MIN_PART_SIZE, 2 * MIN_PART_SIZE - 10, 1, 1; "small suffix")]
+    #[tokio::test]
+    async fn test_overwrite(
+        initial_size: usize,
+        offset: usize,
+        len: usize,
+        expected_prefix_copies: u64,
+        expected_suffix_copies: u64,
+    ) {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::ramp(0xaa, initial_size, ETag::for_tests());
+        client.add_object(key, object.clone());
+        let copies = client.new_counter(Operation::UploadPartCopy);
+
+        let params = CopyAppendParams {
+            allow_overwrite: true,
+            ..new_params(key, &object)
+        };
+        let mut request = CopyAppendRequest::new(&new_runtime(), client.clone(), params);
+        let mut expected = object.read(0, initial_size).to_vec();
+        let mut offset = offset;
+        // Write in two halves to check the writes after the first one must follow it.
+        for data in [vec![0x01; len / 2], vec![0x02; len - len / 2]] {
+            offset += request.write(offset as u64, &data).await.expect("write should succeed");
+            expected.resize(expected.len().max(offset), 0);
+            expected[offset - data.len()..offset].copy_from_slice(&data);
+        }
+        let result = request.write(0, b"!").await;
+        assert!(matches!(result, Err(UploadError::OutOfOrderWrite { .. })));
+        assert_eq!(request.size(), expected.len() as u64);
+        assert_eq!(request.written_bytes(), len as u64);
+
+        request.complete().await.expect("complete should succeed");
+        assert_eq!(copies.count(), expected_prefix_copies + expected_suffix_copies);
+        assert_eq!(client.multipart_upload_count(), 0);
+        let get = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), &expected[..]);
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_past_end() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+
+        let params = CopyAppendParams {
+            allow_overwrite: true,
+            ..new_params(key, &object)
+        };
+        let mut request = CopyAppendRequest::new(&new_runtime(), client.clone(), params);
+        let result = request.write(12, b"!").await;
+        assert!(matches!(
+            result,
+            Err(UploadError::OutOfOrderWrite {
+                write_offset: 12,
+                expected_offset: 11
+            })
+        ));
+        request.write(6, b"there").await.unwrap();
+        request.complete().await.unwrap();
+        let get = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), b"hello there");
+    }
+
+    #[tokio::test]
+    async fn test_append_to_changed_object() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+
+        let mut request = new_request(&client, key, &object);
+        client.add_object(key, MockObject::from(b"goodbye world"));
+        let result = request.write(11, b"!").await;
+        assert!(matches!(
+            result,
+            Err(UploadError::MultipartUploadFailed(ObjectClientError::ServiceError(
+                MultipartUploadError::PreconditionFailed
+            )))
+        ));
+        assert_eq!(client.multipart_upload_count(), 0);
+    }
+
+    #[test_case(1024, 0, 0; "to zero")]
+    #[test_case(1024, 10, 0; "small object shrunk")]
+    #[test_case(1024, 2000, 0; "small object extended")]
+    #[test_case(MIN_PART_SIZE + 100, MIN_PART_SIZE + 1, 1; "copied object shrunk")]
+    #[test_case(MIN_PART_SIZE + 1, MIN_PART_SIZE + 3000, 1; "copied object extended")]
+    #[tokio::test]
+    async fn test_truncate(initial_size: usize, size: usize, expected_copies: u64) {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let mut object = MockObject::ramp(0xaa, initial_size, ETag::for_tests());
+        object.set_storage_class(Some("STANDARD_IA".to_owned()));
+        object.set_object_metadata(HashMap::from([("mode".to_owned(), "644".to_owned())]));
+        client.add_object(key, object.clone());
+        let copies = client.new_counter(Operation::UploadPartCopy);
+
+        truncate_object(&new_runtime(), client.clone(), new_params(key, &object), size as u64)
+            .await
+            .expect("truncate should succeed");
+        assert_eq!(copies.count(), expected_copies);
+        assert_eq!(client.multipart_upload_count(), 0);
+
+        let mut expected = object.read(0, initial_size.min(size)).to_vec();
+        expected.resize(size, 0);
+        let head = client
+            .head_object("bucket", key, &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, size as u64);
+        assert_eq!(head.storage_class.as_deref(), Some("STANDARD_IA"));
+        assert_eq!(head.object_metadata.get("mode").map(String::as_str), Some("644"));
+        let get = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), &expected[..]);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_changed_object() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+        let params = new_params(key, &object);
+        client.add_object(key, MockObject::from(b"goodbye world"));
+
+        let result = truncate_object(&new_runtime(), client.clone(), params, 0).await;
+        assert!(matches!(
+            result,
+            Err(UploadError::MultipartUploadFailed(ObjectClientError::ServiceError(
+                MultipartUploadError::PreconditionFailed
+            )))
+        ));
+        let head = client
+            .head_object("bucket", key, &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, 13);
+    }
+
+    fn new_object_params(key: &str) -> CopyAppendParams {
+        CopyAppendParams {
+            bucket: "bucket".to_owned(),
+            key: key.to_owned(),
+            initial_size: 0,
+            initial_etag: None,
+            storage_class: Some("STANDARD_IA".to_owned()),
+            object_metadata: HashMap::from([("mode".to_owned(), "644".to_owned())]),
+            object_tags: Default::default(),
+            content_type: Some("text/plain".to_owned()),
+            server_side_encryption: Default::default(),
+            default_checksum_algorithm: Some(ChecksumAlgorithm::Crc32c),
+            allow_overwrite: false,
+            replace_if_match: None,
+            scheduler: None,
+        }
+    }
+
+    #[test_case(true; "new object")]
+    #[test_case(false; "existing object")]
+    #[tokio::test]
+    async fn test_copy_from(new_object: bool) {
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let source = MockObject::ramp(0xaa, 3 * MIN_PART_SIZE, ETag::for_tests());
+        client.add_object("source", source.clone());
+        let copies = client.new_counter(Operation::UploadPartCopy);
+
+        let (mut request, mut expected) = if new_object {
+            let request = CopyAppendRequest::new(&new_runtime(), client.clone(), new_object_params("copy"));
+            (request, Vec::new())
+        } else {
+            let object = MockObject::from(b"hello world");
+            client.add_object("copy", object.clone());
+            (new_request(&client, "copy", &object), b"hello world".to_vec())
+        };
+        let mut offset = expected.len() as u64;
+        let copy_ranges = [0..MIN_PART_SIZE as u64, MIN_PART_SIZE as u64..3 * MIN_PART_SIZE as u64];
+        for (i, range) in copy_ranges.into_iter().enumerate() {
+            offset += request
+                .copy_from(offset, "source".to_owned(), source.etag(), range.clone())
+                .await
+                .expect("copy should succeed");
+            expected.extend_from_slice(&source.read(range.start, (range.end - range.start) as usize));
+            if i == 0 {
+                offset += request.write(offset, b"hello").await.expect("write should succeed") as u64;
+                expected.extend_from_slice(b"hello");
+            }
+        }
+        assert_eq!(request.size(), offset);
+
+        request.complete().await.expect("complete should succeed");
+        // The first range is downloaded when it follows the small content of the existing object, while the start of
+        // the second one tops up the data written into a part large enough.
+        assert_eq!(copies.count(), if new_object { 2 } else { 1 });
+        assert_eq!(client.multipart_upload_count(), 0);
+        let head = client
+            .head_object("bucket", "copy", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        if new_object {
+            assert_eq!(head.storage_class.as_deref(), Some("STANDARD_IA"));
+            assert_eq!(head.object_metadata.get("mode").map(String::as_str), Some("644"));
+            assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+        }
+        let response = client
+            .get_object("bucket", "copy", &GetObjectParams::new())
+            .await
+            .unwrap();
+        let actual = response.collect().await.expect("failed to collect body");
+        assert_eq!(&actual[..], &expected[..]);
+    }
+
+    #[tokio::test]
+    async fn test_copy_from_changed_object() {
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let source = MockObject::from(b"hello world");
+        client.add_object("source", source.clone());
+        client.add_object("source", MockObject::from(b"goodbye"));
+
+        let mut request = CopyAppendRequest::new(&new_runtime(), client.clone(), new_object_params("copy"));
+        request
+            .copy_from(0, "source".to_owned(), source.etag(), 0..11)
+            .await
+            .expect_err("copy should fail");
+    }
+
+    #[tokio::test]
+    async fn test_new_object_nothing_written() {
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let request = CopyAppendRequest::new(&new_runtime(), client.clone(), new_object_params("copy"));
+        assert!(request.complete().await.expect("complete should succeed").is_some());
+        let head = client
+            .head_object("bucket", "copy", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, 0);
+        assert_eq!(head.object_metadata.get("mode").map(String::as_str), Some("644"));
+    }
+
+    #[test_case(0; "empty object")]
+    #[test_case(MIN_PART_SIZE + 1; "multipart upload")]
+    #[tokio::test]
+    async fn test_replace_changed_object(size: usize) {
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object("hello", object.clone());
+        let new_request = || {
+            let params = CopyAppendParams {
+                replace_if_match: Some(object.etag()),
+                ..new_object_params("hello")
+            };
+            CopyAppendRequest::new(&new_runtime(), client.clone(), params)
+        };
+        let data = vec![0xaa; size];
+
+        let mut request = new_request();
+        request.write(0, &data).await.expect("write should succeed");
+        client.add_object("hello", MockObject::from(b"goodbye"));
+        let err = request.complete().await.expect_err("complete should fail");
+        assert!(matches!(err, UploadError::ObjectChanged), "{err:?}");
+        assert_eq!(client.multipart_upload_count(), 0);
+        let head = client
+            .head_object("bucket", "hello", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, 7);
+
+        client.add_object("hello", object.clone());
+        let mut request = new_request();
+        request.write(0, &data).await.expect("write should succeed");
+        request.complete().await.expect("complete should succeed");
+        let head = client
+            .head_object("bucket", "hello", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, size as u64);
+    }
+
+    #[tokio::test]
+    async fn test_append_dropped() {
+        let key = "hello";
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(1024).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object(key, object.clone());
+
+        let mut request = new_request(&client, key, &object);
+        request.write(11, b"!").await.expect("write should succeed");
+        assert_eq!(client.multipart_upload_count(), 1);
+
+        // Dropping the request aborts its upload in the background.
+        drop(request);
+        for _ in 0..100 {
+            if client.multipart_upload_count() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(client.multipart_upload_count(), 0);
+        assert_eq!(
+            client
+                .head_object("bucket", key, &HeadObjectParams::new())
+                .await
+                .unwrap()
+                .etag,
+            object.etag()
+        );
+    }
+}
@@ -27,6 +27,16 @@ impl ChecksumHasher {
         }
     }
 
+    /// Compute the checksum of some data at once.
+    pub fn checksum(
+        checksum_algorithm: &Option<ChecksumAlgorithm>,
+        data: &[u8],
+    ) -> Result<Option<UploadChecksum>, ChecksumHasherError> {
+        let mut hasher = Self::new(checksum_algorithm)?;
+        hasher.update(data)?;
+        hasher.finalize()
+    }
+
     pub fn update(&mut self, data: &[u8]) -> Result<(), ChecksumHasherError> {
         match self {
             ChecksumHasher::None => {}
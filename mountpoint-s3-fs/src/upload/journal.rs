@@ -0,0 +1,619 @@
+//! This module implements resumable multipart uploads of recorded spool files, whose progress is kept in a journal.
+//!
+//! A spool file recorded in a recoverable [super::staged::StagingDir] which takes more than one part is uploaded with
+//! CreateMultipartUpload, UploadPart and CompleteMultipartUpload rather than with an atomic [super::UploadRequest],
+//! whose upload can't be resumed. Its journal, next to the spool file, is written ahead of the requests depending on
+//! it: the ID of the upload is journaled before any part is uploaded, and each part once it is uploaded. If Mountpoint
+//! exits before the upload completes, the next Mountpoint process using the same staging directory reads the journal
+//! back, uploads only the parts missing and completes the upload, or aborts it if it can't be completed, rather than
+//! uploading the whole file again and leaving the incomplete upload in the bucket.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write as _};
+use std::os::unix::fs::FileExt as _;
+use std::path::Path;
+
+use futures::{StreamExt as _, stream};
+use mountpoint_s3_client::ObjectClient;
+use mountpoint_s3_client::checksums::{Crc32, Crc32c, Crc64nvme, Sha1, Sha256};
+use mountpoint_s3_client::error::{MultipartUploadError, ObjectClientError};
+use mountpoint_s3_client::types::{
+    ChecksumAlgorithm, CompleteMultipartUploadParams, CompletedPart, CreateMultipartUploadParams, PutObjectResult,
+    UploadChecksum, UploadPartParams,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use super::atomic::UploadRequestParams;
+use super::copy_append::MIN_PART_SIZE;
+use super::hasher::ChecksumHasher;
+use super::{UploadError, acquire_turn};
+
+/// Number of parts uploaded at once, unless the uploads limit it, see [UploadRequestParams::max_concurrent_parts].
+const DEFAULT_CONCURRENT_PARTS: usize = 8;
+
+/// An entry of the journal of an upload, stored as one line of JSON.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JournalEntry {
+    /// The upload was created. This is always the first entry.
+    Started {
+        bucket: String,
+        key: String,
+        upload_id: String,
+        part_size: u64,
+        /// Name of the checksum algorithm of the upload, as in the S3 API, such as `CRC32C`.
+        checksum_algorithm: Option<String>,
+    },
+    /// A part was uploaded.
+    Uploaded {
+        part_number: usize,
+        etag: String,
+        checksum: Option<PartChecksum>,
+    },
+}
+
+/// The checksum of an uploaded part, which completing the upload requires.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PartChecksum {
+    Crc64nvme(u64),
+    Crc32(u32),
+    Crc32c(u32),
+    Sha1([u8; Sha1::LENGTH]),
+    Sha256([u8; Sha256::LENGTH]),
+}
+
+impl PartChecksum {
+    fn new(checksum: &UploadChecksum) -> Option<Self> {
+        match checksum {
+            UploadChecksum::Crc64nvme(checksum) => Some(Self::Crc64nvme(checksum.value())),
+            UploadChecksum::Crc32(checksum) => Some(Self::Crc32(checksum.value())),
+            UploadChecksum::Crc32c(checksum) => Some(Self::Crc32c(checksum.value())),
+            UploadChecksum::Sha1(checksum) => Some(Self::Sha1(*checksum.value())),
+            UploadChecksum::Sha256(checksum) => Some(Self::Sha256(*checksum.value())),
+            _ => None,
+        }
+    }
+
+    fn into_upload_checksum(self) -> UploadChecksum {
+        match self {
+            Self::Crc64nvme(value) => UploadChecksum::Crc64nvme(Crc64nvme::new(value)),
+            Self::Crc32(value) => UploadChecksum::Crc32(Crc32::new(value)),
+            Self::Crc32c(value) => UploadChecksum::Crc32c(Crc32c::new(value)),
+            Self::Sha1(value) => UploadChecksum::Sha1(Sha1::new(value)),
+            Self::Sha256(value) => UploadChecksum::Sha256(Sha256::new(value)),
+        }
+    }
+}
+
+/// The checksum algorithm of a name journaled with [JournalEntry::Started].
+fn parse_checksum_algorithm(name: &str) -> ChecksumAlgorithm {
+    match name {
+        "CRC64NVME" => ChecksumAlgorithm::Crc64nvme,
+        "CRC32" => ChecksumAlgorithm::Crc32,
+        "CRC32C" => ChecksumAlgorithm::Crc32c,
+        "SHA1" => ChecksumAlgorithm::Sha1,
+        "SHA256" => ChecksumAlgorithm::Sha256,
+        other => ChecksumAlgorithm::Unknown(other.to_owned()),
+    }
+}
+
+/// A multipart upload, and the parts of it uploaded so far.
+#[derive(Debug)]
+struct JournaledUpload {
+    bucket: String,
+    key: String,
+    upload_id: String,
+    part_size: u64,
+    /// Checksum algorithm of the parts, which the parts uploaded when resuming must keep using.
+    checksum_algorithm: Option<ChecksumAlgorithm>,
+    parts: BTreeMap<usize, CompletedPart>,
+    /// Length of the valid entries of the journal.
+    journal_len: u64,
+}
+
+impl JournaledUpload {
+    /// Read the upload journaled at the given path, if any. Entries are read up to the first invalid one, such as a
+    /// line left incomplete by a crash, and the parts of the entries after it are uploaded again.
+    fn read(path: &Path) -> std::io::Result<Option<Self>> {
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut journal_len = 0;
+        let mut entries = Vec::new();
+        for line in contents.split_inclusive(|byte| *byte == b'\n') {
+            let Some(line) = line.strip_suffix(b"\n") else {
+                break;
+            };
+            let Ok(entry) = serde_json::from_slice::<JournalEntry>(line) else {
+                break;
+            };
+            entries.push(entry);
+            journal_len += line.len() as u64 + 1;
+        }
+        let mut entries = entries.into_iter();
+        let Some(JournalEntry::Started {
+            bucket,
+            key,
+            upload_id,
+            part_size,
+            checksum_algorithm,
+        }) = entries.next()
+        else {
+            warn!(?path, "ignoring upload journal without a started upload");
+            return Ok(None);
+        };
+        let mut parts = BTreeMap::new();
+        for entry in entries {
+            if let JournalEntry::Uploaded {
+                part_number,
+                etag,
+                checksum,
+            } = entry
+            {
+                let part = CompletedPart {
+                    part_number,
+                    etag: etag.into(),
+                    checksum: checksum.map(PartChecksum::into_upload_checksum),
+                };
+                parts.insert(part_number, part);
+            }
+        }
+        Ok(Some(Self {
+            bucket,
+            key,
+            upload_id,
+            part_size,
+            checksum_algorithm: checksum_algorithm.as_deref().map(parse_checksum_algorithm),
+            parts,
+            journal_len,
+        }))
+    }
+
+    /// Abort the upload, discarding its parts.
+    async fn abort<Client: ObjectClient>(&self, client: &Client) {
+        match client
+            .abort_multipart_upload(&self.bucket, &self.key, &self.upload_id)
+            .await
+        {
+            Ok(_) => debug!(key = self.key, upload_id = self.upload_id, "aborted journaled upload"),
+            Err(ObjectClientError::ServiceError(MultipartUploadError::NoSuchUpload)) => {}
+            Err(error) => warn!(
+                key = self.key,
+                upload_id = self.upload_id,
+                ?error,
+                "failed to abort journaled upload"
+            ),
+        }
+    }
+}
+
+/// The journal an upload is written ahead to.
+struct Journal {
+    file: File,
+}
+
+impl Journal {
+    /// Create the journal of a new upload, replacing any previous one.
+    fn create(path: &Path, upload: &JournaledUpload) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        let mut journal = Self { file };
+        journal.append(&JournalEntry::Started {
+            bucket: upload.bucket.clone(),
+            key: upload.key.clone(),
+            upload_id: upload.upload_id.clone(),
+            part_size: upload.part_size,
+            checksum_algorithm: upload.checksum_algorithm.as_ref().map(ToString::to_string),
+        })?;
+        Ok(journal)
+    }
+
+    /// Open the journal of an upload read back from it, dropping any entry after its valid ones.
+    fn resume(path: &Path, upload: &JournaledUpload) -> std::io::Result<Self> {
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(upload.journal_len)?;
+        Ok(Self { file })
+    }
+
+    /// Append an entry, and only return once it is durable.
+    fn append(&mut self, entry: &JournalEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry).expect("journal entries can always be serialized");
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+
+    fn append_part(&mut self, part: &CompletedPart) -> std::io::Result<()> {
+        self.append(&JournalEntry::Uploaded {
+            part_number: part.part_number,
+            etag: part.etag.as_str().to_owned(),
+            checksum: part.checksum.as_ref().and_then(PartChecksum::new),
+        })
+    }
+}
+
+/// Size of the parts of the journaled uploads of a client. Spool files no larger than it are uploaded at once.
+pub(super) fn part_size(client: &impl ObjectClient) -> u64 {
+    client.write_part_size().max(MIN_PART_SIZE) as u64
+}
+
+/// Upload the first `size` bytes of a spool file with a multipart upload journaled at `journal_path`, resuming the
+/// upload already journaled there, if any. An upload which no longer exists, such as one expired by a lifecycle rule,
+/// is started again. The journal is left in place once the upload completes.
+pub(super) async fn upload_journaled<Client>(
+    client: &Client,
+    params: &UploadRequestParams,
+    file: &File,
+    size: u64,
+    journal_path: &Path,
+) -> Result<PutObjectResult, UploadError<Client::ClientError>>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    let journaled = JournaledUpload::read(journal_path).map_err(UploadError::StagingFailed)?;
+    let (mut upload, mut journal, mut resumed) = match journaled {
+        Some(upload) if upload.bucket == params.bucket && upload.key == params.key => {
+            let journal = Journal::resume(journal_path, &upload).map_err(UploadError::StagingFailed)?;
+            info!(
+                key = params.key,
+                upload_id = upload.upload_id,
+                parts = upload.parts.len(),
+                "resuming journaled upload"
+            );
+            (upload, journal, true)
+        }
+        journaled => {
+            if let Some(upload) = journaled {
+                upload.abort(client).await;
+            }
+            let (upload, journal) = start(client, params, journal_path).await?;
+            (upload, journal, false)
+        }
+    };
+    loop {
+        match upload_parts(client, params, file, size, &mut upload, &mut journal).await {
+            Err(UploadError::MultipartUploadFailed(ObjectClientError::ServiceError(
+                MultipartUploadError::NoSuchUpload,
+            ))) if resumed => {
+                warn!(
+                    key = params.key,
+                    upload_id = upload.upload_id,
+                    "journaled upload no longer exists, starting it again"
+                );
+                (upload, journal) = start(client, params, journal_path).await?;
+                resumed = false;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Abort the upload journaled at the given path, if any, and remove its journal.
+pub(super) async fn abort_journaled<Client: ObjectClient>(client: &Client, journal_path: &Path) {
+    match JournaledUpload::read(journal_path) {
+        Ok(Some(upload)) => upload.abort(client).await,
+        Ok(None) => {}
+        Err(err) => warn!(?err, ?journal_path, "failed to read upload journal"),
+    }
+    remove_journal(journal_path);
+}
+
+/// Remove the journal at the given path, if any.
+pub(super) fn remove_journal(journal_path: &Path) {
+    match std::fs::remove_file(journal_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            warn!(?err, ?journal_path, "failed to remove upload journal");
+        }
+        _ => {}
+    }
+}
+
+/// Create a new multipart upload, and its journal.
+async fn start<Client>(
+    client: &Client,
+    params: &UploadRequestParams,
+    journal_path: &Path,
+) -> Result<(JournaledUpload, Journal), UploadError<Client::ClientError>>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    let (sse_type, key_id) = params.server_side_encryption.clone().into_inner()?;
+    let mut create_params = CreateMultipartUploadParams::new()
+        .checksum_algorithm(params.default_checksum_algorithm.clone())
+        .server_side_encryption(sse_type)
+        .ssekms_key_id(key_id)
+        .object_metadata(params.object_metadata.clone())
+        .object_tags(params.object_tags.clone());
+    if let Some(storage_class) = &params.storage_class {
+        create_params = create_params.storage_class(storage_class.clone());
+    }
+    if let Some(content_type) = &params.content_type {
+        create_params = create_params.content_type(content_type.clone());
+    }
+    let upload_id = client
+        .create_multipart_upload(&params.bucket, &params.key, &create_params)
+        .await?
+        .upload_id;
+    let upload = JournaledUpload {
+        bucket: params.bucket.clone(),
+        key: params.key.clone(),
+        upload_id,
+        part_size: part_size(client),
+        checksum_algorithm: params.default_checksum_algorithm.clone(),
+        parts: BTreeMap::new(),
+        journal_len: 0,
+    };
+    debug!(
+        key = params.key,
+        upload_id = upload.upload_id,
+        "started journaled upload"
+    );
+    match Journal::create(journal_path, &upload) {
+        Ok(journal) => Ok((upload, journal)),
+        Err(err) => {
+            // Nothing could resume or abort the upload later.
+            upload.abort(client).await;
+            Err(UploadError::StagingFailed(err))
+        }
+    }
+}
+
+/// Upload the parts of the spool file not uploaded yet, journaling each of them, then complete the upload.
+async fn upload_parts<Client>(
+    client: &Client,
+    params: &UploadRequestParams,
+    file: &File,
+    size: u64,
+    upload: &mut JournaledUpload,
+    journal: &mut Journal,
+) -> Result<PutObjectResult, UploadError<Client::ClientError>>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    let part_size = upload.part_size;
+    let part_count = size.div_ceil(part_size).max(1) as usize;
+    let missing_parts: Vec<_> = (1..=part_count)
+        .filter(|part_number| !upload.parts.contains_key(part_number))
+        .collect();
+    let concurrency = params
+        .max_concurrent_parts
+        .map_or(DEFAULT_CONCURRENT_PARTS, |parts| parts as usize)
+        .max(1);
+    let upload_id = upload.upload_id.clone();
+    let checksum_algorithm = upload.checksum_algorithm.clone();
+    let upload_part = |part_number: usize| {
+        let upload_id = &upload_id;
+        let checksum_algorithm = &checksum_algorithm;
+        async move {
+            let offset = (part_number as u64 - 1) * part_size;
+            let mut buffer = vec![0u8; part_size.min(size - offset) as usize];
+            file.read_exact_at(&mut buffer, offset)
+                .map_err(UploadError::StagingFailed)?;
+            let checksum = ChecksumHasher::checksum(checksum_algorithm, &buffer)?;
+            let part_params = UploadPartParams::new().checksum(checksum);
+            let _permit = acquire_turn(params.scheduler.as_ref()).await;
+            let result = client
+                .upload_part(
+                    &params.bucket,
+                    &params.key,
+                    upload_id,
+                    part_number,
+                    &part_params,
+                    buffer,
+                )
+                .await?;
+            Ok::<_, UploadError<Client::ClientError>>(result.into_completed_part(part_number))
+        }
+    };
+    let mut parts = stream::iter(missing_parts)
+        .map(upload_part)
+        .buffer_unordered(concurrency);
+    while let Some(part) = parts.next().await {
+        let part = part?;
+        journal.append_part(&part).map_err(UploadError::StagingFailed)?;
+        upload.parts.insert(part.part_number, part);
+    }
+
+    let parts: Vec<_> = upload
+        .parts
+        .range(..=part_count)
+        .map(|(_, part)| part.clone())
+        .collect();
+    let result = client
+        .complete_multipart_upload(
+            &params.bucket,
+            &params.key,
+            &upload.upload_id,
+            &parts,
+            &CompleteMultipartUploadParams::new(),
+        )
+        .await?;
+    debug!(key = params.key, parts = parts.len(), "journaled upload completed");
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_client::mock_client::{MockClient, Operation, ramp_bytes};
+    use mountpoint_s3_client::types::{GetObjectParams, GetObjectResponse as _};
+
+    use super::*;
+    use crate::sync::Arc;
+
+    const PART_SIZE: usize = MIN_PART_SIZE;
+
+    fn new_params(key: &str) -> UploadRequestParams {
+        UploadRequestParams {
+            bucket: "bucket".to_owned(),
+            key: key.to_owned(),
+            server_side_encryption: Default::default(),
+            default_checksum_algorithm: Some(ChecksumAlgorithm::Crc32c),
+            storage_class: None,
+            object_metadata: Default::default(),
+            object_tags: Default::default(),
+            content_type: None,
+            sniff_content_type: false,
+            write_through: None,
+            scheduler: None,
+            max_concurrent_parts: None,
+        }
+    }
+
+    /// Write a spool file of the given size.
+    fn new_spool_file(dir: &Path, size: usize) -> (File, Vec<u8>) {
+        let contents = ramp_bytes(0, size);
+        let path = dir.join("mountpoint-test.spool");
+        std::fs::write(&path, &contents).unwrap();
+        (File::open(&path).unwrap(), contents)
+    }
+
+    /// Start an upload and upload its first part, as if Mountpoint had exited then.
+    async fn interrupted_upload(
+        client: &Arc<MockClient>,
+        params: &UploadRequestParams,
+        file: &File,
+        journal_path: &Path,
+    ) -> String {
+        let (upload, mut journal) = start(client, params, journal_path).await.unwrap();
+        let mut buffer = vec![0u8; PART_SIZE];
+        file.read_exact_at(&mut buffer, 0).unwrap();
+        let checksum = ChecksumHasher::checksum(&params.default_checksum_algorithm, &buffer).unwrap();
+        let part_params = UploadPartParams::new().checksum(checksum);
+        let part = client
+            .upload_part(&params.bucket, &params.key, &upload.upload_id, 1, &part_params, buffer)
+            .await
+            .unwrap()
+            .into_completed_part(1);
+        journal.append_part(&part).unwrap();
+        upload.upload_id
+    }
+
+    async fn assert_object(client: &MockClient, key: &str, expected: &[u8]) {
+        let get = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        assert!(
+            get.collect().await.unwrap().as_ref() == expected,
+            "object content should match"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(PART_SIZE).build());
+        let (file, contents) = new_spool_file(dir.path(), 2 * PART_SIZE + 100);
+        let journal_path = dir.path().join("mountpoint-test.journal");
+
+        let params = new_params("key");
+        upload_journaled(&client, &params, &file, contents.len() as u64, &journal_path)
+            .await
+            .unwrap();
+        assert_object(&client, "key", &contents).await;
+
+        let upload = JournaledUpload::read(&journal_path)
+            .unwrap()
+            .expect("journal should be kept");
+        assert_eq!(upload.key, "key");
+        assert_eq!(upload.parts.keys().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(client.multipart_upload_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(PART_SIZE).build());
+        let (file, contents) = new_spool_file(dir.path(), 2 * PART_SIZE + 100);
+        let journal_path = dir.path().join("mountpoint-test.journal");
+        let params = new_params("key");
+        interrupted_upload(&client, &params, &file, &journal_path).await;
+        // A crash can leave the last entry incomplete.
+        let mut journal = OpenOptions::new().append(true).open(&journal_path).unwrap();
+        journal.write_all(b"{\"uploaded\":{\"part_n").unwrap();
+
+        let creates = client.new_counter(Operation::CreateMultipartUpload);
+        let parts = client.new_counter(Operation::UploadPart);
+        upload_journaled(&client, &params, &file, contents.len() as u64, &journal_path)
+            .await
+            .unwrap();
+        assert_eq!(creates.count(), 0, "the upload should be resumed");
+        assert_eq!(parts.count(), 2, "only the missing parts should be uploaded");
+        assert_object(&client, "key", &contents).await;
+        assert_eq!(client.multipart_upload_count(), 0);
+
+        let upload = JournaledUpload::read(&journal_path).unwrap().unwrap();
+        assert_eq!(upload.parts.keys().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_checksum_algorithm() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(PART_SIZE).build());
+        let (file, contents) = new_spool_file(dir.path(), 2 * PART_SIZE + 100);
+        let journal_path = dir.path().join("mountpoint-test.journal");
+        let params = UploadRequestParams {
+            default_checksum_algorithm: Some(ChecksumAlgorithm::Sha256),
+            ..new_params("key")
+        };
+        interrupted_upload(&client, &params, &file, &journal_path).await;
+
+        // The upload is resumed after a remount with another algorithm.
+        upload_journaled(&client, &new_params("key"), &file, contents.len() as u64, &journal_path)
+            .await
+            .unwrap();
+        assert_object(&client, "key", &contents).await;
+
+        let upload = JournaledUpload::read(&journal_path).unwrap().unwrap();
+        assert_eq!(upload.checksum_algorithm, Some(ChecksumAlgorithm::Sha256));
+        assert_eq!(upload.parts.len(), 3);
+        assert!(
+            upload
+                .parts
+                .values()
+                .all(|part| matches!(part.checksum, Some(UploadChecksum::Sha256(_)))),
+            "all parts should keep the algorithm the upload was started with"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restart_missing_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(PART_SIZE).build());
+        let (file, contents) = new_spool_file(dir.path(), 2 * PART_SIZE + 100);
+        let journal_path = dir.path().join("mountpoint-test.journal");
+        let params = new_params("key");
+        let upload_id = interrupted_upload(&client, &params, &file, &journal_path).await;
+        client
+            .abort_multipart_upload("bucket", "key", &upload_id)
+            .await
+            .unwrap();
+
+        let creates = client.new_counter(Operation::CreateMultipartUpload);
+        upload_journaled(&client, &params, &file, contents.len() as u64, &journal_path)
+            .await
+            .unwrap();
+        assert_eq!(creates.count(), 1, "the upload should be started again");
+        assert_object(&client, "key", &contents).await;
+        assert_eq!(client.multipart_upload_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_abort() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(PART_SIZE).build());
+        let (file, _) = new_spool_file(dir.path(), 2 * PART_SIZE + 100);
+        let journal_path = dir.path().join("mountpoint-test.journal");
+        interrupted_upload(&client, &new_params("key"), &file, &journal_path).await;
+        assert_eq!(client.multipart_upload_count(), 1);
+
+        abort_journaled(&client, &journal_path).await;
+        assert_eq!(client.multipart_upload_count(), 0);
+        assert!(!journal_path.exists());
+        assert!(!client.contains_key("key"));
+
+        // Aborting without a journal is a no-op.
+        abort_journaled(&client, &journal_path).await;
+    }
+}
@@ -0,0 +1,235 @@
+//! Fair scheduling of the uploads of files written at once.
+//!
+//! The client sends the parts of every upload as soon as they are written, so that a single large upload can take all
+//! of its connections and leave the uploads of small files waiting behind its parts. An [UploadScheduler] limits how
+//! many uploads hand data to the client at once: each upload waits for a permit before each write to its request, or
+//! each part it uploads itself, and permits are granted in the order they are waited for. Since an upload waits for a
+//! single permit at a time, uploads take turns, round-robin, and a large upload goes back to the end of the queue
+//! after each of its writes.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+use crate::sync::{Arc, Mutex};
+
+/// Permits to hand data to the client, granted to uploads in turns, see
+/// [crate::upload::UploaderConfig::max_concurrent_uploads].
+#[derive(Clone)]
+pub struct UploadScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+struct SchedulerState {
+    max_concurrent_uploads: usize,
+    available: usize,
+    /// Uploads waiting for a permit, in the order they started waiting.
+    waiters: VecDeque<(u64, Waker)>,
+    /// Waiters granted a permit they have not taken yet.
+    granted: HashSet<u64>,
+    next_waiter: u64,
+}
+
+impl SchedulerState {
+    /// Give a released permit to the next waiter, if any. Return the waker to wake once the state is unlocked.
+    fn release(&mut self) -> Option<Waker> {
+        match self.waiters.pop_front() {
+            Some((waiter, waker)) => {
+                self.granted.insert(waiter);
+                Some(waker)
+            }
+            None => {
+                self.available += 1;
+                None
+            }
+        }
+    }
+}
+
+impl UploadScheduler {
+    /// Let at most `max_concurrent_uploads` uploads hand data to the client at once.
+    pub fn new(max_concurrent_uploads: usize) -> Self {
+        assert!(max_concurrent_uploads > 0, "at least one upload must be allowed");
+        Self {
+            state: Arc::new(Mutex::new(SchedulerState {
+                max_concurrent_uploads,
+                available: max_concurrent_uploads,
+                waiters: VecDeque::new(),
+                granted: HashSet::new(),
+                next_waiter: 0,
+            })),
+        }
+    }
+
+    /// Wait for the turn of an upload to hand data to the client. The returned permit should be held until the client
+    /// accepted it.
+    pub fn acquire(&self) -> Acquire {
+        Acquire {
+            scheduler: self.clone(),
+            waiter: None,
+        }
+    }
+
+    fn release(&self) {
+        let waker = self.state.lock().unwrap().release();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Debug for UploadScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("UploadScheduler")
+            .field("max_concurrent_uploads", &state.max_concurrent_uploads)
+            .field("available", &state.available)
+            .field("waiting", &state.waiters.len())
+            .finish()
+    }
+}
+
+/// Wait for the turn of an upload if uploads take turns, see [UploadScheduler::acquire].
+pub async fn acquire_turn(scheduler: Option<&UploadScheduler>) -> Option<UploadPermit> {
+    match scheduler {
+        Some(scheduler) => Some(scheduler.acquire().await),
+        None => None,
+    }
+}
+
+/// Future returned by [UploadScheduler::acquire]. Dropping it gives up its turn.
+pub struct Acquire {
+    scheduler: UploadScheduler,
+    /// The position of the upload in the queue, and when it started waiting, once it has to wait.
+    waiter: Option<(u64, Instant)>,
+}
+
+impl Future for Acquire {
+    type Output = UploadPermit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.scheduler.state.lock().unwrap();
+        match self.waiter {
+            None if state.available > 0 && state.waiters.is_empty() => {
+                state.available -= 1;
+            }
+            None => {
+                let waiter = state.next_waiter;
+                state.next_waiter += 1;
+                state.waiters.push_back((waiter, cx.waker().clone()));
+                drop(state);
+                self.waiter = Some((waiter, Instant::now()));
+                return Poll::Pending;
+            }
+            Some((waiter, since)) => {
+                if !state.granted.remove(&waiter) {
+                    if let Some((_, waker)) = state.waiters.iter_mut().find(|(w, _)| *w == waiter) {
+                        waker.clone_from(cx.waker());
+                    }
+                    return Poll::Pending;
+                }
+                drop(state);
+                self.waiter = None;
+                metrics::histogram!("fs.upload_wait_us").record(since.elapsed().as_micros() as f64);
+            }
+        }
+        Poll::Ready(UploadPermit {
+            scheduler: self.scheduler.clone(),
+        })
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        let Some((waiter, _)) = self.waiter else {
+            return;
+        };
+        let mut state = self.scheduler.state.lock().unwrap();
+        if state.granted.remove(&waiter) {
+            // Pass on the permit granted to this upload.
+            let waker = state.release();
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        } else {
+            state.waiters.retain(|(w, _)| *w != waiter);
+        }
+    }
+}
+
+/// The turn of an upload to hand data to the client, which ends when dropped.
+#[derive(Debug)]
+pub struct UploadPermit {
+    scheduler: UploadScheduler,
+}
+
+impl Drop for UploadPermit {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    fn poll(acquire: &mut Acquire) -> Option<UploadPermit> {
+        match acquire.poll_unpin(&mut Context::from_waker(noop_waker_ref())) {
+            Poll::Ready(permit) => Some(permit),
+            Poll::Pending => None,
+        }
+    }
+
+    #[test]
+    fn test_permits_granted_in_turn() {
+        let scheduler = UploadScheduler::new(2);
+        let first = poll(&mut scheduler.acquire()).unwrap();
+        let second = poll(&mut scheduler.acquire()).unwrap();
+
+        let mut third = scheduler.acquire();
+        let mut fourth = scheduler.acquire();
+        assert!(poll(&mut third).is_none());
+        assert!(poll(&mut fourth).is_none());
+
+        drop(first);
+        // A new upload can't take the turn of the ones already waiting.
+        assert!(poll(&mut scheduler.acquire()).is_none());
+        assert!(poll(&mut fourth).is_none(), "the third upload waited first");
+        let third = poll(&mut third).unwrap();
+
+        drop(second);
+        let fourth = poll(&mut fourth).unwrap();
+        drop(third);
+        drop(fourth);
+        let _first = poll(&mut scheduler.acquire()).unwrap();
+        let _second = poll(&mut scheduler.acquire()).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_waiter() {
+        let scheduler = UploadScheduler::new(1);
+        let first = poll(&mut scheduler.acquire()).unwrap();
+        let mut second = scheduler.acquire();
+        let mut third = scheduler.acquire();
+        assert!(poll(&mut second).is_none());
+        assert!(poll(&mut third).is_none());
+
+        // The permit granted to an upload that gave up its turn goes to the next one.
+        drop(first);
+        drop(second);
+        let third = poll(&mut third).unwrap();
+
+        let mut fourth = scheduler.acquire();
+        assert!(poll(&mut fourth).is_none());
+        drop(fourth);
+        drop(third);
+        let _first = poll(&mut scheduler.acquire()).unwrap();
+    }
+}
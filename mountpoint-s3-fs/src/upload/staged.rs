@@ -0,0 +1,903 @@
+//! This module implements uploads whose writes are staged in a local spool file.
+//!
+//! Staging allows writes at any offset, such as the ones rewriting a header once the rest of a file is written, which
+//! a streaming upload can't support. Nothing is sent to S3 until the request completes, when the content of the spool
+//! file is uploaded at once with an atomic [UploadRequest], or with a [CopyAppendRequest] when it replaces an object
+//! only if that object was not changed, see [StagedUploadRequest::set_if_match].
+//!
+//! In a recoverable [StagingDir], spool files are named, and recorded along with the object they are uploaded to once
+//! their file is closed. The uploads that were not completed when Mountpoint exited can then be completed by the next
+//! Mountpoint process using the same staging directory, see [recover]. Recorded spool files taking more than one part
+//! are uploaded with a journaled multipart upload, which that process resumes, see [super::journal].
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::os::unix::fs::FileExt as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use mountpoint_s3_client::ObjectClient;
+use mountpoint_s3_client::types::{ETag, ObjectMetadata, ObjectTags, PutObjectResult};
+use serde::{Deserialize, Serialize};
+use tempfile::TempPath;
+use tracing::{debug, info, warn};
+
+use crate::async_util::{Runtime, sleep};
+use crate::sync::Arc;
+use crate::sync::atomic::{AtomicU64, Ordering};
+
+use super::UploadError;
+use super::atomic::{UploadRequest, UploadRequestParams};
+use super::content_type::{SNIFFED_LEN, sniff_content_type};
+use super::copy_append::{CopyAppendParams, CopyAppendRequest};
+use super::journal::{self, abort_journaled, remove_journal, upload_journaled};
+
+const MAX_S3_MULTIPART_UPLOAD_PARTS: usize = 10000;
+
+/// How many times the upload of a spool file is attempted. Since its content is local, the upload can still be
+/// retried once the client gives up on its requests, such as during a network outage.
+const MAX_UPLOAD_ATTEMPTS: u32 = 3;
+/// The delay before the first retry of an upload, doubled for each following one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const SPOOL_PREFIX: &str = "mountpoint-";
+const SPOOL_EXTENSION: &str = "spool";
+const RECORD_EXTENSION: &str = "json";
+const JOURNAL_EXTENSION: &str = "journal";
+
+/// The directory the writes of staged uploads are spooled to.
+#[derive(Debug)]
+pub struct StagingDir {
+    path: PathBuf,
+    /// The maximum total size of the spool files, if limited.
+    max_size: Option<u64>,
+    /// The total size of the spool files of the uploads in progress.
+    size: AtomicU64,
+    /// Whether spool files are named and recorded once their file is closed, so that their upload can be recovered.
+    recoverable: bool,
+}
+
+impl StagingDir {
+    pub fn new(path: PathBuf, max_size: Option<u64>, recoverable: bool) -> Self {
+        Self {
+            path,
+            max_size,
+            size: AtomicU64::new(0),
+            recoverable,
+        }
+    }
+
+    pub fn is_recoverable(&self) -> bool {
+        self.recoverable
+    }
+
+    /// Reserve room for the spool files to grow by `len` bytes. Return `false` if it would exceed the maximum size.
+    fn reserve(&self, len: u64) -> bool {
+        self.size
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |size| {
+                let new_size = size.saturating_add(len);
+                self.max_size.is_none_or(|max| new_size <= max).then_some(new_size)
+            })
+            .is_ok()
+    }
+
+    fn release(&self, len: u64) {
+        self.size.fetch_sub(len, Ordering::SeqCst);
+    }
+}
+
+/// The object a recoverable spool file is uploaded to, recorded next to it once its file is closed.
+#[derive(Debug, Deserialize, Serialize)]
+struct SpoolRecord {
+    bucket: String,
+    key: String,
+    object_metadata: ObjectMetadata,
+    #[serde(default)]
+    if_match: Option<String>,
+    /// Tags of the object, in place of the default ones. Records written by earlier versions have none.
+    #[serde(default)]
+    object_tags: Option<ObjectTags>,
+}
+
+/// Manages the upload of an object whose content is staged in a local spool file until it completes.
+///
+/// Unless the staging directory is recoverable, the spool file is anonymous, so that it is removed as soon as the
+/// request is dropped, even if Mountpoint exits without completing it.
+pub struct StagedUploadRequest<Client: ObjectClient> {
+    client: Client,
+    runtime: Runtime,
+    params: UploadRequestParams,
+    /// ETag the object being replaced must still have when the upload completes.
+    if_match: Option<ETag>,
+    staging_dir: Arc<StagingDir>,
+    file: File,
+    /// The path of the journal of the upload of a recoverable spool file, once its file is closed. Declared first, so
+    /// that the journal is removed before the record.
+    journal_path: Option<TempPath>,
+    /// The path of the record of a recoverable spool file, once its file is closed. Declared before the path of the
+    /// spool file, so that the record is removed first.
+    record_path: Option<TempPath>,
+    /// The path of a recoverable spool file.
+    spool_path: Option<TempPath>,
+    /// The size of the object, up to the end of the furthest write. Ranges not written to read as zeros.
+    size: u64,
+    maximum_upload_size: u64,
+}
+
+impl<Client> StagedUploadRequest<Client>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    pub(super) fn new(
+        runtime: &Runtime,
+        client: Client,
+        staging_dir: Arc<StagingDir>,
+        params: UploadRequestParams,
+    ) -> Result<Self, UploadError<Client::ClientError>> {
+        // Fail on open rather than once the file is written if the SSE settings are corrupted.
+        params.server_side_encryption.clone().into_inner()?;
+        let (file, spool_path) = if staging_dir.recoverable {
+            let (file, path) = tempfile::Builder::new()
+                .prefix(SPOOL_PREFIX)
+                .suffix(&format!(".{SPOOL_EXTENSION}"))
+                .tempfile_in(&staging_dir.path)
+                .map_err(UploadError::StagingFailed)?
+                .into_parts();
+            (file, Some(path))
+        } else {
+            let file = tempfile::tempfile_in(&staging_dir.path).map_err(UploadError::StagingFailed)?;
+            (file, None)
+        };
+        let maximum_upload_size = client.write_part_size().saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS) as u64;
+        Ok(Self {
+            client,
+            runtime: runtime.clone(),
+            params,
+            if_match: None,
+            staging_dir,
+            file,
+            journal_path: None,
+            record_path: None,
+            spool_path,
+            size: 0,
+            maximum_upload_size,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The user-defined metadata the object will be uploaded with.
+    pub fn object_metadata(&self) -> &ObjectMetadata {
+        &self.params.object_metadata
+    }
+
+    /// Set the user-defined metadata the object will be uploaded with. Unlike atomic uploads, it can be changed after
+    /// the file is written to, since nothing is uploaded yet.
+    pub fn set_object_metadata(&mut self, object_metadata: ObjectMetadata) {
+        self.params.object_metadata = object_metadata;
+    }
+
+    /// The tags the object will be uploaded with.
+    pub fn object_tags(&self) -> &ObjectTags {
+        &self.params.object_tags
+    }
+
+    /// Set the tags the object will be uploaded with, which can also be changed after the file is written to.
+    pub fn set_object_tags(&mut self, object_tags: ObjectTags) {
+        self.params.object_tags = object_tags;
+    }
+
+    /// Only replace the object if it still has the given ETag when the upload completes, which fails with
+    /// [UploadError::ObjectChanged] otherwise. The parts of such uploads are uploaded one at a time.
+    pub fn set_if_match(&mut self, etag: Option<ETag>) {
+        self.if_match = etag;
+    }
+
+    pub async fn write(&mut self, offset: i64, data: &[u8]) -> Result<usize, UploadError<Client::ClientError>> {
+        let Ok(offset) = u64::try_from(offset) else {
+            return Err(UploadError::OutOfOrderWrite {
+                write_offset: offset as u64,
+                expected_offset: self.size,
+            });
+        };
+        let end = offset + data.len() as u64;
+        if end > self.maximum_upload_size {
+            return Err(UploadError::ObjectTooBig {
+                maximum_size: self.maximum_upload_size as usize,
+            });
+        }
+        let growth = end.saturating_sub(self.size);
+        if !self.staging_dir.reserve(growth) {
+            return Err(UploadError::StagingDirFull {
+                maximum_size: self.staging_dir.max_size.unwrap_or(u64::MAX),
+            });
+        }
+        if let Err(err) = self.file.write_all_at(data, offset) {
+            self.staging_dir.release(growth);
+            return Err(UploadError::StagingFailed(err));
+        }
+        self.size += growth;
+        Ok(data.len())
+    }
+
+    /// Truncate the object to the given size, or extend it with zeros, as `ftruncate` does on the file being written.
+    pub fn set_size(&mut self, size: u64) -> Result<(), UploadError<Client::ClientError>> {
+        if size > self.maximum_upload_size {
+            return Err(UploadError::ObjectTooBig {
+                maximum_size: self.maximum_upload_size as usize,
+            });
+        }
+        let growth = size.saturating_sub(self.size);
+        if !self.staging_dir.reserve(growth) {
+            return Err(UploadError::StagingDirFull {
+                maximum_size: self.staging_dir.max_size.unwrap_or(u64::MAX),
+            });
+        }
+        if let Err(err) = self.file.set_len(size) {
+            self.staging_dir.release(growth);
+            return Err(UploadError::StagingFailed(err));
+        }
+        self.staging_dir.release(self.size.saturating_sub(size));
+        self.size = size;
+        Ok(())
+    }
+
+    /// Read back up to `len` bytes written at the given offset from the spool file, before they are uploaded.
+    pub fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>, UploadError<Client::ClientError>> {
+        let end = self.size.min(offset.saturating_add(len as u64));
+        let mut buffer = vec![0u8; end.saturating_sub(offset) as usize];
+        self.file
+            .read_exact_at(&mut buffer, offset)
+            .map_err(UploadError::StagingFailed)?;
+        Ok(buffer)
+    }
+
+    /// Record the object a recoverable spool file is uploaded to, once its file is closed, so that the upload can be
+    /// recovered if Mountpoint exits before it completes. This is a no-op if the spool file is not recoverable.
+    pub fn record(&mut self) -> Result<(), UploadError<Client::ClientError>> {
+        let Some(spool_path) = &self.spool_path else {
+            return Ok(());
+        };
+        // The content of the spool file must be durable before it is recorded, or a crash could upload it partially.
+        self.file.sync_data().map_err(UploadError::StagingFailed)?;
+        let record = SpoolRecord {
+            bucket: self.params.bucket.clone(),
+            key: self.params.key.clone(),
+            object_metadata: self.params.object_metadata.clone(),
+            if_match: self.if_match.as_ref().map(|etag| etag.as_str().to_owned()),
+            object_tags: Some(self.params.object_tags.clone()),
+        };
+        let record_path =
+            TempPath::try_from_path(spool_path.with_extension(RECORD_EXTENSION)).map_err(UploadError::StagingFailed)?;
+        let contents = serde_json::to_vec(&record).expect("records can always be serialized");
+        std::fs::write(&record_path, contents).map_err(UploadError::StagingFailed)?;
+        self.record_path = Some(record_path);
+        let journal_path = TempPath::try_from_path(spool_path.with_extension(JOURNAL_EXTENSION))
+            .map_err(UploadError::StagingFailed)?;
+        self.journal_path = Some(journal_path);
+        Ok(())
+    }
+
+    /// Upload the content of the spool file, retrying if the upload fails. If it still fails once its spool file is
+    /// recorded, the spool file, its record and the journal of its upload are kept in the staging directory to be
+    /// recovered. Retries of a journaled upload resume it.
+    pub async fn complete(mut self) -> Result<PutObjectResult, UploadError<Client::ClientError>> {
+        let mut attempt = 1;
+        loop {
+            let params = UploadRequestParams {
+                bucket: self.params.bucket.clone(),
+                key: self.params.key.clone(),
+                server_side_encryption: self.params.server_side_encryption.clone(),
+                default_checksum_algorithm: self.params.default_checksum_algorithm.clone(),
+                storage_class: self.params.storage_class.clone(),
+                object_metadata: self.params.object_metadata.clone(),
+                object_tags: self.params.object_tags.clone(),
+                content_type: self.params.content_type.clone(),
+                sniff_content_type: self.params.sniff_content_type,
+                // Only the first attempt can put the object to the write-through cache.
+                write_through: self.params.write_through.take(),
+                scheduler: self.params.scheduler.clone(),
+                max_concurrent_parts: self.params.max_concurrent_parts,
+            };
+            debug!(
+                key = self.params.key,
+                size = self.size,
+                attempt,
+                "uploading staged object"
+            );
+            let if_match = self.if_match.clone();
+            match upload_spool(
+                &self.runtime,
+                self.client.clone(),
+                params,
+                if_match,
+                &self.file,
+                self.size,
+                self.journal_path.as_deref(),
+            )
+            .await
+            {
+                Ok(result) => return Ok(result),
+                Err(err @ (UploadError::PutRequestFailed(_) | UploadError::MultipartUploadFailed(_)))
+                    if attempt < MAX_UPLOAD_ATTEMPTS =>
+                {
+                    let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+                    warn!(
+                        key = self.params.key,
+                        ?err,
+                        attempt,
+                        ?delay,
+                        "staged upload failed, retrying"
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    // There is nothing to recover once the object was changed, as the upload would fail again.
+                    if !matches!(err, UploadError::ObjectChanged)
+                        && let Some(record_path) = self.record_path.take()
+                        && let Some(spool_path) = self.spool_path.take()
+                        && let Ok(record_path) = record_path.keep()
+                        && let Ok(spool_path) = spool_path.keep()
+                    {
+                        if let Some(journal_path) = self.journal_path.take() {
+                            _ = journal_path.keep();
+                        }
+                        warn!(
+                            key = self.params.key,
+                            ?spool_path,
+                            ?record_path,
+                            "staged upload failed, keeping its spool file to recover the upload when next mounting"
+                        );
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl<Client: ObjectClient> Drop for StagedUploadRequest<Client> {
+    fn drop(&mut self) {
+        self.staging_dir.release(self.size);
+    }
+}
+
+impl<Client: ObjectClient> Debug for StagedUploadRequest<Client> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StagedUploadRequest")
+            .field("bucket", &self.params.bucket)
+            .field("key", &self.params.key)
+            .field("size", &self.size)
+            .field("spool_path", &self.spool_path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Upload the first `size` bytes of a spool file with an atomic [UploadRequest], with a [CopyAppendRequest] if it
+/// only replaces the object with the given ETag, or with a multipart upload journaled at `journal_path`, if given, when
+/// it takes more than one part or its upload was already journaled.
+async fn upload_spool<Client>(
+    runtime: &Runtime,
+    client: Client,
+    mut params: UploadRequestParams,
+    if_match: Option<ETag>,
+    file: &File,
+    size: u64,
+    journal_path: Option<&Path>,
+) -> Result<PutObjectResult, UploadError<Client::ClientError>>
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    if params.sniff_content_type && params.content_type.is_none() {
+        let mut start = vec![0u8; SNIFFED_LEN.min(size as usize)];
+        file.read_exact_at(&mut start, 0).map_err(UploadError::StagingFailed)?;
+        params.content_type = sniff_content_type(&start).map(str::to_owned);
+    }
+    let chunk_size = client.write_part_size() as u64;
+    if if_match.is_none()
+        && let Some(journal_path) = journal_path
+        && (size > journal::part_size(&client) || journal_path.exists())
+    {
+        return upload_journaled(&client, &params, file, size, journal_path).await;
+    }
+    if let Some(etag) = if_match {
+        let params = CopyAppendParams {
+            bucket: params.bucket,
+            key: params.key,
+            initial_size: 0,
+            initial_etag: None,
+            storage_class: params.storage_class,
+            object_metadata: params.object_metadata,
+            object_tags: params.object_tags,
+            content_type: params.content_type,
+            server_side_encryption: params.server_side_encryption,
+            default_checksum_algorithm: params.default_checksum_algorithm,
+            allow_overwrite: false,
+            replace_if_match: Some(etag),
+            scheduler: params.scheduler,
+        };
+        let mut request = CopyAppendRequest::new(runtime, client, params);
+        let mut buffer = vec![0u8; chunk_size.min(size) as usize];
+        let mut offset = 0;
+        while offset < size {
+            let len = chunk_size.min(size - offset) as usize;
+            file.read_exact_at(&mut buffer[..len], offset)
+                .map_err(UploadError::StagingFailed)?;
+            offset += request.write(offset, &buffer[..len]).await? as u64;
+        }
+        return Ok(request.complete().await?.expect("new objects are always created"));
+    }
+    let mut request = UploadRequest::new(runtime, client, params)?;
+    let mut buffer = vec![0u8; chunk_size.min(size) as usize];
+    let mut offset = 0;
+    while offset < size {
+        let len = chunk_size.min(size - offset) as usize;
+        file.read_exact_at(&mut buffer[..len], offset)
+            .map_err(UploadError::StagingFailed)?;
+        request.write(offset as i64, &buffer[..len]).await?;
+        offset += len as u64;
+    }
+    request.complete().await
+}
+
+/// Complete the uploads of the spool files recorded in a recoverable staging directory by a previous Mountpoint
+/// process, which exited before they completed, and remove them once uploaded. Spool files that were not recorded, as
+/// their file was still being written, are removed. Journaled uploads are resumed, or aborted if their spool file is
+/// removed. Return the number of uploads recovered.
+///
+/// `params` gives the parameters to upload an object from its bucket, key and user-defined metadata.
+pub(super) async fn recover<Client>(
+    runtime: &Runtime,
+    client: &Client,
+    staging_dir: &StagingDir,
+    params: impl Fn(String, String, ObjectMetadata) -> UploadRequestParams,
+) -> usize
+where
+    Client: ObjectClient + Clone + Send + Sync + 'static,
+{
+    let entries = match std::fs::read_dir(&staging_dir.path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!(?err, path = ?staging_dir.path, "failed to list the staging directory to recover uploads");
+            return 0;
+        }
+    };
+    let mut recovered = 0;
+    for spool_path in entries.flatten().map(|entry| entry.path()) {
+        if is_journal_file(&spool_path) && !spool_path.with_extension(SPOOL_EXTENSION).exists() {
+            debug!(journal_path = ?spool_path, "aborting journaled upload without a spool file");
+            abort_journaled(client, &spool_path).await;
+            continue;
+        }
+        if !is_spool_file(&spool_path) {
+            continue;
+        }
+        let record_path = spool_path.with_extension(RECORD_EXTENSION);
+        let journal_path = spool_path.with_extension(JOURNAL_EXTENSION);
+        let record = match std::fs::read(&record_path) {
+            Ok(contents) => serde_json::from_slice::<SpoolRecord>(&contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!(?spool_path, "removing spool file of a file that was not closed");
+                remove_spool_file(&spool_path);
+                continue;
+            }
+            Err(err) => {
+                warn!(?err, ?record_path, "failed to read the record of a spool file");
+                continue;
+            }
+        };
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                warn!(?err, ?record_path, "removing spool file with an invalid record");
+                abort_journaled(client, &journal_path).await;
+                remove_spool_file(&record_path);
+                remove_spool_file(&spool_path);
+                continue;
+            }
+        };
+        let mut params = params(record.bucket.clone(), record.key.clone(), record.object_metadata);
+        if let Some(object_tags) = record.object_tags {
+            params.object_tags = object_tags;
+        }
+        let if_match = record.if_match.map(ETag::from);
+        let upload = async {
+            let file = File::open(&spool_path).map_err(UploadError::StagingFailed)?;
+            let size = file.metadata().map_err(UploadError::StagingFailed)?.len();
+            upload_spool(
+                runtime,
+                client.clone(),
+                params,
+                if_match,
+                &file,
+                size,
+                Some(&journal_path),
+            )
+            .await?;
+            Ok::<_, UploadError<Client::ClientError>>(size)
+        };
+        match upload.await {
+            Ok(size) => {
+                info!(
+                    bucket = record.bucket,
+                    key = record.key,
+                    size,
+                    "recovered staged upload"
+                );
+                remove_journal(&journal_path);
+                remove_spool_file(&record_path);
+                remove_spool_file(&spool_path);
+                recovered += 1;
+            }
+            Err(UploadError::ObjectChanged) => {
+                warn!(
+                    bucket = record.bucket,
+                    key = record.key,
+                    "removing spool file of a staged upload whose object was changed since it was opened"
+                );
+                abort_journaled(client, &journal_path).await;
+                remove_spool_file(&record_path);
+                remove_spool_file(&spool_path);
+            }
+            Err(err) => {
+                warn!(
+                    ?err,
+                    bucket = record.bucket,
+                    key = record.key,
+                    "failed to recover staged upload, it will be retried when next mounting"
+                );
+            }
+        }
+    }
+    recovered
+}
+
+fn is_spool_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == SPOOL_EXTENSION)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(SPOOL_PREFIX))
+}
+
+fn is_journal_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == JOURNAL_EXTENSION)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(SPOOL_PREFIX))
+}
+
+fn remove_spool_file(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        warn!(?err, ?path, "failed to remove file from the staging directory");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::executor::ThreadPool;
+    use mountpoint_s3_client::failure_client::{CountdownFailureConfig, countdown_failure_client};
+    use mountpoint_s3_client::mock_client::{MockClient, MockClientError, MockObject, Operation, ramp_bytes};
+    use mountpoint_s3_client::types::{ChecksumAlgorithm, GetObjectParams, GetObjectResponse as _, HeadObjectParams};
+
+    use super::*;
+
+    fn new_runtime() -> Runtime {
+        Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap())
+    }
+
+    fn new_params(bucket: String, key: String, object_metadata: ObjectMetadata) -> UploadRequestParams {
+        UploadRequestParams {
+            bucket,
+            key,
+            server_side_encryption: Default::default(),
+            default_checksum_algorithm: Some(ChecksumAlgorithm::Crc32c),
+            storage_class: None,
+            object_metadata,
+            object_tags: Default::default(),
+            content_type: None,
+            sniff_content_type: false,
+            write_through: None,
+            scheduler: None,
+            max_concurrent_parts: None,
+        }
+    }
+
+    fn new_request<Client>(client: &Client, staging_dir: &Arc<StagingDir>, key: &str) -> StagedUploadRequest<Client>
+    where
+        Client: ObjectClient + Clone + Send + Sync + 'static,
+    {
+        let params = new_params("bucket".to_owned(), key.to_owned(), Default::default());
+        StagedUploadRequest::new(&new_runtime(), client.clone(), staging_dir.clone(), params).unwrap()
+    }
+
+    fn new_staging_dir(path: &Path) -> Arc<StagingDir> {
+        Arc::new(StagingDir::new(path.to_owned(), None, false))
+    }
+
+    #[tokio::test]
+    async fn test_random_writes() {
+        let key = "hello";
+        let staging_dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+        let puts = client.new_counter(Operation::PutObject);
+
+        let mut request = new_request(&client, &new_staging_dir(staging_dir.path()), key);
+        request.write(8, b"world").await.unwrap();
+        request.write(0, b"hello").await.unwrap();
+        request.write(100, b"!").await.unwrap();
+        request.write(0, b"HE").await.unwrap();
+        assert_eq!(request.size(), 101);
+        assert_eq!(request.read(0, 16).unwrap(), b"HEllo\0\0\0world\0\0\0");
+        assert_eq!(request.read(99, 16).unwrap(), b"\0!");
+        assert!(request.read(200, 16).unwrap().is_empty());
+        request.set_object_metadata(ObjectMetadata::from([("mode".to_owned(), "644".to_owned())]));
+
+        // Nothing is uploaded until the request completes.
+        assert_eq!(puts.count(), 0);
+        assert!(
+            client
+                .head_object("bucket", key, &HeadObjectParams::new())
+                .await
+                .is_err()
+        );
+        request.complete().await.unwrap();
+        assert_eq!(puts.count(), 1);
+
+        let mut expected = [0u8; 101];
+        expected[..5].copy_from_slice(b"HEllo");
+        expected[8..13].copy_from_slice(b"world");
+        expected[100] = b'!';
+        let get = client.get_object("bucket", key, &GetObjectParams::new()).await.unwrap();
+        assert_eq!(get.get_object_metadata().get("mode").map(String::as_str), Some("644"));
+        assert_eq!(get.collect().await.unwrap().as_ref(), &expected[..]);
+    }
+
+    #[tokio::test]
+    async fn test_empty() {
+        let key = "empty";
+        let staging_dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let request = new_request(&client, &new_staging_dir(staging_dir.path()), key);
+        request.complete().await.unwrap();
+        let head = client
+            .head_object("bucket", key, &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_too_big() {
+        let staging_dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let mut request = new_request(&client, &new_staging_dir(staging_dir.path()), "big");
+        let maximum_size = 32 * MAX_S3_MULTIPART_UPLOAD_PARTS;
+        let err = request.write(maximum_size as i64, b"a").await.unwrap_err();
+        assert!(matches!(err, UploadError::ObjectTooBig { .. }));
+        let err = request.write(-1, b"a").await.unwrap_err();
+        assert!(matches!(err, UploadError::OutOfOrderWrite { .. }));
+    }
+
+    #[test]
+    fn test_spool_file_removed() {
+        let staging_dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let _request = new_request(&client, &new_staging_dir(staging_dir.path()), "hello");
+        assert_eq!(std::fs::read_dir(staging_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_staging_dir_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), Some(16), false));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let mut request = new_request(&client, &staging_dir, "a");
+        request.write(0, b"hello").await.unwrap();
+        let mut other_request = new_request(&client, &staging_dir, "b");
+        other_request.write(5, b"world").await.unwrap();
+        // Rewriting the data already staged doesn't take more room.
+        request.write(0, b"HELLO").await.unwrap();
+        let err = request.write(5, b" world").await.unwrap_err();
+        assert!(matches!(err, UploadError::StagingDirFull { maximum_size: 16 }));
+
+        // The room of a spool file is released once its upload completes.
+        other_request.complete().await.unwrap();
+        request.write(5, b" world").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), Some(16), false));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let mut request = new_request(&client, &staging_dir, "hello");
+        request.write(0, b"hello world").await.unwrap();
+        request.set_size(5).unwrap();
+        assert_eq!(request.read(0, 16).unwrap(), b"hello");
+        request.set_size(8).unwrap();
+        assert_eq!(request.read(0, 16).unwrap(), b"hello\0\0\0");
+        let err = request.set_size(17).unwrap_err();
+        assert!(matches!(err, UploadError::StagingDirFull { maximum_size: 16 }));
+        assert_eq!(request.size(), 8);
+
+        request.complete().await.unwrap();
+        let get = client
+            .get_object("bucket", "hello", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), b"hello\0\0\0");
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+        let mut put_failures = HashMap::new();
+        put_failures.insert(1, Ok((1, MockClientError("error".to_owned().into()))));
+        let failure_client = Arc::new(countdown_failure_client(
+            client.clone(),
+            CountdownFailureConfig {
+                put_failures,
+                ..Default::default()
+            },
+        ));
+
+        let mut request = new_request(&failure_client, &new_staging_dir(dir.path()), "hello");
+        request.write(0, b"hello").await.unwrap();
+        request.complete().await.unwrap();
+        let get = client
+            .get_object("bucket", "hello", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_if_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), None, true));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object("hello", object.clone());
+
+        let mut request = new_request(&client, &staging_dir, "hello");
+        request.set_if_match(Some(object.etag()));
+        request.write(0, b"goodbye").await.unwrap();
+        request.record().unwrap();
+        let changed = MockObject::from(b"changed");
+        client.add_object("hello", changed.clone());
+        let err = request.complete().await.expect_err("upload should fail");
+        assert!(matches!(err, UploadError::ObjectChanged), "{err:?}");
+        // The spool file is not kept to be recovered, as its upload would fail again.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let mut request = new_request(&client, &staging_dir, "hello");
+        request.set_if_match(Some(changed.etag()));
+        request.write(0, b"goodbye").await.unwrap();
+        request.complete().await.unwrap();
+        let get = client
+            .get_object("bucket", "hello", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(get.collect().await.unwrap().as_ref(), b"goodbye");
+    }
+
+    #[tokio::test]
+    async fn test_sniff_content_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+        let object = MockObject::from(b"hello world");
+        client.add_object("image", object.clone());
+
+        let params = UploadRequestParams {
+            sniff_content_type: true,
+            ..new_params("bucket".to_owned(), "image".to_owned(), Default::default())
+        };
+        let mut request =
+            StagedUploadRequest::new(&new_runtime(), client.clone(), new_staging_dir(dir.path()), params).unwrap();
+        request.set_if_match(Some(object.etag()));
+        // The content type is recognized from the start of the spool file, even if it was written last.
+        request.write(8, b"IHDR").await.unwrap();
+        request.write(0, b"\x89PNG\r\n\x1a\n").await.unwrap();
+        request.complete().await.unwrap();
+        let head = client
+            .head_object("bucket", "image", &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.content_type.as_deref(), Some("image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_recover() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), None, true));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        // Leave the spool file of a closed file and of a file still being written, as if Mountpoint had exited.
+        let mut request = new_request(&client, &staging_dir, "closed");
+        request.set_object_metadata(ObjectMetadata::from([("mode".to_owned(), "644".to_owned())]));
+        request.set_object_tags(ObjectTags::from([("team".to_owned(), "data".to_owned())]));
+        request.write(0, b"hello").await.unwrap();
+        request.record().unwrap();
+        std::mem::forget(request);
+        let mut request = new_request(&client, &staging_dir, "open");
+        request.write(0, b"world").await.unwrap();
+        std::mem::forget(request);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        let recovered = recover(&new_runtime(), &client, &staging_dir, new_params).await;
+        assert_eq!(recovered, 1);
+        let get = client
+            .get_object("bucket", "closed", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(get.get_object_metadata().get("mode").map(String::as_str), Some("644"));
+        assert_eq!(get.collect().await.unwrap().as_ref(), b"hello");
+        let tags = client.get_object_tagging("bucket", "closed").await.unwrap().tags;
+        assert_eq!(tags.get("team").map(String::as_str), Some("data"));
+        assert!(
+            client
+                .head_object("bucket", "open", &HeadObjectParams::new())
+                .await
+                .is_err()
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_recover_journaled() {
+        let part_size = 5 * 1024 * 1024;
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), None, true));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(part_size).build());
+
+        let contents = ramp_bytes(0, 2 * part_size + 100);
+        let mut request = new_request(&client, &staging_dir, "large");
+        request.write(0, &contents).await.unwrap();
+        request.record().unwrap();
+        std::mem::forget(request);
+
+        let creates = client.new_counter(Operation::CreateMultipartUpload);
+        let recovered = recover(&new_runtime(), &client, &staging_dir, new_params).await;
+        assert_eq!(recovered, 1);
+        assert_eq!(
+            creates.count(),
+            1,
+            "files taking several parts should be uploaded with a journal"
+        );
+        let get = client
+            .get_object("bucket", "large", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert!(get.collect().await.unwrap().as_ref() == &contents[..]);
+        assert_eq!(client.multipart_upload_count(), 0);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_spool_file_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = Arc::new(StagingDir::new(dir.path().to_owned(), None, true));
+        let client = Arc::new(MockClient::config().bucket("bucket").part_size(32).build());
+
+        let mut request = new_request(&client, &staging_dir, "hello");
+        request.write(0, b"hello").await.unwrap();
+        request.record().unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        request.complete().await.unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Populating a data cache with the objects written through the mount.
+//!
+//! Objects are often read back right after being written, for example by the next stage of a pipeline. Rather than
+//! fetching their data from S3 again, the uploader puts it to the data cache. The blocks are keyed by the ETag of the
+//! new object, which is only known once the upload completes, so the written data is held in memory until then, up to
+//! a maximum object size.
+
+use std::fmt::Debug;
+use std::mem;
+
+use bytes::{Bytes, BytesMut};
+use mountpoint_s3_client::types::ETag;
+use tracing::{debug, warn};
+
+use crate::checksums::ChecksummedBytes;
+use crate::data_cache::DataCache;
+use crate::mem_limiter::{BufferArea, MemoryLimiter};
+use crate::object::ObjectId;
+use crate::sync::Arc;
+
+/// A data cache the objects written through the mount are put to.
+#[derive(Clone)]
+pub struct WriteThroughCache {
+    cache: Arc<dyn DataCache + Send + Sync>,
+    max_object_size: usize,
+}
+
+impl WriteThroughCache {
+    /// Put the objects written through the mount to `cache`, if they're no larger than `max_object_size`.
+    pub fn new(cache: Arc<dyn DataCache + Send + Sync>, max_object_size: usize) -> Self {
+        Self { cache, max_object_size }
+    }
+}
+
+impl Debug for WriteThroughCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteThroughCache")
+            .field("max_object_size", &self.max_object_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The data written to an upload, to be put to a [WriteThroughCache] once the upload completes.
+pub struct WriteThroughBuffer {
+    cache: WriteThroughCache,
+    mem_limiter: Arc<MemoryLimiter>,
+    /// Complete blocks, in order.
+    blocks: Vec<Bytes>,
+    /// The block being filled.
+    current: BytesMut,
+    /// Total size of the data buffered, reserved on the memory limiter.
+    size: usize,
+}
+
+impl WriteThroughBuffer {
+    pub fn new(cache: WriteThroughCache, mem_limiter: Arc<MemoryLimiter>) -> Self {
+        Self {
+            cache,
+            mem_limiter,
+            blocks: Vec::new(),
+            current: BytesMut::new(),
+            size: 0,
+        }
+    }
+
+    /// Append data written to the upload. Returns `false` if the object grew too large, or there isn't enough memory
+    /// to hold the data, in which case the buffer should be dropped.
+    pub fn push(&mut self, mut data: &[u8]) -> bool {
+        if self.size + data.len() > self.cache.max_object_size
+            || !self.mem_limiter.try_reserve(BufferArea::Upload, data.len() as u64)
+        {
+            return false;
+        }
+        self.size += data.len();
+        let block_size = self.cache.cache.block_size() as usize;
+        while !data.is_empty() {
+            let len = (block_size - self.current.len()).min(data.len());
+            self.current.extend_from_slice(&data[..len]);
+            data = &data[len..];
+            if self.current.len() == block_size {
+                self.blocks.push(self.current.split().freeze());
+            }
+        }
+        true
+    }
+
+    /// Put the buffered data to the cache, as the blocks of the object `key` uploaded with the given ETag.
+    pub async fn commit(mut self, key: String, etag: ETag) {
+        if !self.current.is_empty() {
+            let block = self.current.split().freeze();
+            self.blocks.push(block);
+        }
+        let object_size = self.size;
+        let block_size = self.cache.cache.block_size();
+        let object_id = ObjectId::new(key, etag);
+        for (block_idx, block) in mem::take(&mut self.blocks).into_iter().enumerate() {
+            let block_idx = block_idx as u64;
+            let bytes = ChecksummedBytes::new(block);
+            if let Err(error) = self
+                .cache
+                .cache
+                .put_block(object_id.clone(), block_idx, block_idx * block_size, bytes, object_size)
+                .await
+            {
+                warn!(
+                    key = object_id.key(),
+                    block_idx,
+                    ?error,
+                    "unable to put uploaded block to the cache"
+                );
+                return;
+            }
+        }
+        debug!(key = object_id.key(), object_size, "put uploaded object to the cache");
+    }
+}
+
+impl Drop for WriteThroughBuffer {
+    fn drop(&mut self) {
+        self.mem_limiter.release(BufferArea::Upload, self.size as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_cache::InMemoryDataCache;
+    use crate::mem_limiter::MINIMUM_MEM_LIMIT;
+    use crate::memory::PagedPool;
+
+    fn buffer(cache: Arc<InMemoryDataCache>, max_object_size: usize) -> WriteThroughBuffer {
+        let pool = PagedPool::new_with_candidate_sizes([1024]);
+        let mem_limiter = Arc::new(MemoryLimiter::new(pool, MINIMUM_MEM_LIMIT));
+        WriteThroughBuffer::new(WriteThroughCache::new(cache, max_object_size), mem_limiter)
+    }
+
+    #[tokio::test]
+    async fn test_commit() {
+        let cache = Arc::new(InMemoryDataCache::new(4));
+        let mut buffer = buffer(cache.clone(), 1024);
+        assert!(buffer.push(b"Hello"));
+        assert!(buffer.push(b", "));
+        assert!(buffer.push(b"world"));
+        let mem_limiter = buffer.mem_limiter.clone();
+        let available_mem = mem_limiter.available_mem();
+        let etag = ETag::for_tests();
+        buffer.commit("a".to_owned(), etag.clone()).await;
+        assert_eq!(mem_limiter.available_mem(), available_mem + 12, "memory is released");
+
+        let object_id = ObjectId::new("a".to_owned(), etag);
+        assert_eq!(cache.block_count(&object_id), 3);
+        for (block_idx, expected) in [b"Hell".as_slice(), b"o, w", b"orld"].into_iter().enumerate() {
+            let block_idx = block_idx as u64;
+            let block = cache.get_block(&object_id, block_idx, block_idx * 4, 12).await.unwrap();
+            assert_eq!(block, Some(ChecksummedBytes::new(Bytes::from_static(expected))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_object_size() {
+        let cache = Arc::new(InMemoryDataCache::new(4));
+        let mut buffer = buffer(cache, 8);
+        assert!(buffer.push(b"Hello"));
+        assert!(
+            !buffer.push(b", world"),
+            "objects larger than the maximum size are not cached"
+        );
+    }
+}
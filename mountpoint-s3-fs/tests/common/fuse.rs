@@ -17,7 +17,7 @@ use mountpoint_s3_fs::manifest::{Manifest, ManifestMetablock};
 use mountpoint_s3_fs::memory::PagedPool;
 use mountpoint_s3_fs::prefetch::PrefetcherBuilder;
 use mountpoint_s3_fs::s3::{Prefix, S3Path};
-use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig, Superblock, SuperblockConfig};
+use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig};
 use nix::fcntl::{self, FdFlag};
 use tempfile::TempDir;
 
@@ -196,7 +196,7 @@ where
 {
     #[cfg(feature = "manifest")]
     if let Some(manifest) = manifest {
-        return S3Filesystem::new(
+        return S3Filesystem::with_metablock(
             client,
             prefetcher_builder,
             pool,
@@ -207,18 +207,12 @@ where
     };
 
     S3Filesystem::new(
-        client.clone(),
+        client,
         prefetcher_builder,
         pool,
         runtime,
-        Superblock::new(
-            client,
-            s3_path,
-            SuperblockConfig {
-                cache_config: filesystem_config.cache_config.clone(),
-                s3_personality: filesystem_config.s3_personality,
-            },
-        ),
+        s3_path,
+        Default::default(),
         filesystem_config,
     )
 }
@@ -25,12 +25,13 @@ use mountpoint_s3_client::config::{
     Allocator, CredentialsProvider, CredentialsProviderStaticOptions, RustLogAdapter, S3ClientAuthConfig,
 };
 use mountpoint_s3_client::mock_client::MockClient;
+use mountpoint_s3_fs::data_cache::DataCache;
 use mountpoint_s3_fs::fs::{DirectoryEntry, DirectoryReplier};
 use mountpoint_s3_fs::memory::PagedPool;
 use mountpoint_s3_fs::metrics::metrics_tracing_span_layer;
 use mountpoint_s3_fs::prefetch::Prefetcher;
 use mountpoint_s3_fs::s3::{Bucket, Prefix, S3Path};
-use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig, Superblock, SuperblockConfig};
+use mountpoint_s3_fs::{Runtime, S3Filesystem, S3FilesystemConfig};
 use std::collections::VecDeque;
 use std::future::Future;
 use std::sync::Arc;
@@ -69,15 +70,50 @@ where
 {
     let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
     let prefetcher_builder = Prefetcher::default_builder(client.clone());
-    let superblock = Superblock::new(
+    S3Filesystem::new(
+        client,
+        prefetcher_builder,
+        pool,
+        runtime,
+        S3Path::new(Bucket::new(bucket).unwrap(), prefix.clone()),
+        Default::default(),
+        config,
+    )
+}
+
+/// Make a file system whose prefetcher reads the blocks of the objects from `cache`, and writes the blocks it fetches
+/// to it.
+pub fn make_caching_test_filesystem<Cache>(
+    bucket: &str,
+    prefix: &Prefix,
+    cache: Cache,
+    config: S3FilesystemConfig,
+) -> (Arc<MockClient>, S3Filesystem<Arc<MockClient>>)
+where
+    Cache: DataCache + Send + Sync + 'static,
+{
+    let part_size = 1024 * 1024;
+    let client = Arc::new(
+        MockClient::config()
+            .bucket(bucket)
+            .part_size(part_size)
+            .enable_backpressure(true)
+            .initial_read_window_size(256 * 1024)
+            .build(),
+    );
+    let pool = PagedPool::new_with_candidate_sizes([part_size]);
+    let runtime = Runtime::new(ThreadPool::builder().pool_size(1).create().unwrap());
+    let prefetcher_builder = Prefetcher::caching_builder(cache, client.clone());
+    let fs = S3Filesystem::new(
         client.clone(),
+        prefetcher_builder,
+        pool,
+        runtime,
         S3Path::new(Bucket::new(bucket).unwrap(), prefix.clone()),
-        SuperblockConfig {
-            cache_config: config.cache_config.clone(),
-            s3_personality: config.s3_personality,
-        },
+        Default::default(),
+        config,
     );
-    S3Filesystem::new(client, prefetcher_builder, pool, runtime, superblock, config)
+    (client, fs)
 }
 
 #[track_caller]
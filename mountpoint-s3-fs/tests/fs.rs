@@ -2,12 +2,15 @@
 
 use std::collections::HashMap;
 use std::ffi::OsString;
+use std::io::Write;
 use std::ops::Add;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use fuser::FileType;
+use fuser::consts::{FOPEN_DIRECT_IO, FOPEN_KEEP_CACHE, FUSE_WRITE_CACHE};
 use mountpoint_s3_client::ObjectClient;
 #[cfg(all(feature = "s3_tests", not(feature = "s3express_tests")))]
 use mountpoint_s3_client::PutObjectRequest;
@@ -19,14 +22,24 @@ use mountpoint_s3_client::config::S3ClientConfig;
 use mountpoint_s3_client::error_metadata::ClientErrorMetadata;
 use mountpoint_s3_client::failure_client::{CountdownFailureConfig, countdown_failure_client};
 use mountpoint_s3_client::mock_client::{MockClient, MockClientError, MockObject, Operation};
-use mountpoint_s3_client::types::{ETag, GetObjectParams, PutObjectSingleParams, RestoreStatus};
+use mountpoint_s3_client::types::{ETag, GetObjectParams, HeadObjectParams, PutObjectSingleParams, RestoreStatus};
+use mountpoint_s3_fs::data_cache::{
+    CacheLimit, ChecksummedBytes, DataCache, DiskDataCache, DiskDataCacheConfig, InMemoryDataCache,
+};
 #[cfg(feature = "s3_tests")]
 use mountpoint_s3_fs::fs::error_metadata::MOUNTPOINT_ERROR_LOOKUP_NONEXISTENT;
 #[cfg(all(feature = "s3_tests", not(feature = "s3express_tests")))]
 use mountpoint_s3_fs::fs::error_metadata::{ErrorMetadata, MOUNTPOINT_ERROR_CLIENT};
-use mountpoint_s3_fs::fs::{CacheConfig, FUSE_ROOT_INODE, OpenFlags, RenameFlags, TimeToLive, ToErrno};
+use mountpoint_s3_fs::fs::{
+    ATIME_METADATA_KEY, ArchivedObjectsPolicy, CacheConfig, DIRECT_IO_XATTR, ETAG_XATTR, FUSE_ROOT_INODE,
+    INVALIDATE_XATTR, MODE_METADATA_KEY, MTIME_METADATA_KEY, OpenFlags, PIN_XATTR, PREFETCH_STATS_XATTR,
+    PRESIGNED_URL_XATTR, PinningCache, RECURSIVE_SIZE_XATTR, RESTORE_STATUS_XATTR, RenameFlags, STORAGE_CLASS_XATTR,
+    SYMLINK_TARGET_METADATA_KEY, TAG_XATTR_PREFIX, TimeToLive, ToErrno, VERSION_ID_XATTR,
+};
 use mountpoint_s3_fs::memory::PagedPool;
+use mountpoint_s3_fs::object::ObjectId;
 use mountpoint_s3_fs::s3::{Prefix, S3Personality};
+use mountpoint_s3_fs::upload::ContentTypes;
 use mountpoint_s3_fs::{S3Filesystem, S3FilesystemConfig};
 use nix::unistd::{getgid, getuid};
 use rand::rngs::SmallRng;
@@ -38,7 +51,9 @@ mod common;
 use common::creds::get_scoped_down_credentials;
 #[cfg(feature = "s3_tests")]
 use common::s3::{get_test_bucket_and_prefix, get_test_endpoint_config};
-use common::{DirectoryReply, assert_attr, make_test_filesystem, make_test_filesystem_with_client};
+use common::{
+    DirectoryReply, assert_attr, make_caching_test_filesystem, make_test_filesystem, make_test_filesystem_with_client,
+};
 #[cfg(all(feature = "s3_tests", not(feature = "s3express_tests")))]
 use common::{get_crt_client_auth_config, s3::deny_single_object_access_policy};
 
@@ -478,6 +493,1210 @@ async fn test_random_read(object_size: usize) {
     fs.releasedir(FUSE_ROOT_INODE, dir_handle, 0).await.unwrap();
 }
 
+#[test_case(true; "enabled")]
+#[test_case(false; "disabled")]
+#[tokio::test]
+async fn test_prefetch_stats_xattr(prefetch_stats_xattr: bool) {
+    let fs_config = S3FilesystemConfig {
+        prefetch_stats_xattr,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_prefetch_stats_xattr", &Default::default(), fs_config);
+    assert_eq!(fs.xattrs_supported(), prefetch_stats_xattr);
+
+    let object_size = 1024 * 1024;
+    client.add_object("file", MockObject::constant(0xaa, object_size, ETag::for_tests()));
+    let ino = fs.lookup(FUSE_ROOT_INODE, "file".as_ref()).await.unwrap().attr.ino;
+    let stats = fs.getxattr(ino, PREFETCH_STATS_XATTR.as_ref()).await.unwrap();
+    assert_eq!(
+        stats,
+        prefetch_stats_xattr.then(Vec::new),
+        "no file handles are open yet"
+    );
+
+    let fh = fs.open(ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    for offset in [0, 64 * 1024, 32 * 1024] {
+        fs.read(ino, fh, offset, 4096, 0, None).await.unwrap();
+    }
+    let stats = fs.getxattr(ino, PREFETCH_STATS_XATTR.as_ref()).await.unwrap();
+    if prefetch_stats_xattr {
+        let stats = String::from_utf8(stats.expect("attribute should exist")).unwrap();
+        let lines: Vec<_> = stats.lines().collect();
+        assert_eq!(lines.len(), 1, "a single handle is open: {stats}");
+        assert!(lines[0].starts_with(&format!("fh={fh} ")), "unexpected stats: {stats}");
+        for counter in ["bytes_read=12288", "out_of_order_reads=2"] {
+            assert!(lines[0].contains(counter), "{counter} missing from stats: {stats}");
+        }
+    } else {
+        assert_eq!(stats, None, "extended attributes are disabled");
+    }
+
+    let other = fs.getxattr(ino, "user.other".as_ref()).await.unwrap();
+    assert_eq!(other, None, "other attributes don't exist");
+
+    fs.release(ino, fh, 0, None, true).await.unwrap();
+    let stats = fs.getxattr(ino, PREFETCH_STATS_XATTR.as_ref()).await.unwrap();
+    assert_eq!(
+        stats,
+        prefetch_stats_xattr.then(Vec::new),
+        "released handles are not listed"
+    );
+}
+
+#[test_case(true; "enabled")]
+#[test_case(false; "disabled")]
+#[tokio::test]
+async fn test_invalidate_xattr(invalidate_xattr: bool) {
+    let fs_config = S3FilesystemConfig {
+        cache_config: CacheConfig::new(TimeToLive::Duration(Duration::from_secs(24 * 60 * 60))),
+        invalidate_xattr,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_invalidate_xattr", &Default::default(), fs_config);
+    assert_eq!(fs.xattrs_supported(), invalidate_xattr);
+
+    client.add_object("dir/file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr.ino;
+    let file0 = fs.lookup(dir, "file0".as_ref()).await.unwrap().attr.ino;
+    fs.lookup(dir, "file1".as_ref())
+        .await
+        .expect_err("file1 doesn't exist yet");
+
+    // Another writer replaces file0 and creates file1.
+    client.add_object("dir/file0", MockObject::constant(0xbb, 20, ETag::for_tests()));
+    client.add_object("dir/file1", MockObject::constant(0xcc, 30, ETag::for_tests()));
+
+    let err = fs
+        .setxattr(file0, INVALIDATE_XATTR.as_ref(), b"0")
+        .await
+        .expect_err("only 1 is a valid value");
+    let expected_errno = if invalidate_xattr { libc::EINVAL } else { libc::ENOTSUP };
+    assert_eq!(err.to_errno(), expected_errno);
+
+    let result = fs.setxattr(dir, INVALIDATE_XATTR.as_ref(), b"1").await;
+    if invalidate_xattr {
+        result.expect("invalidating a directory should succeed");
+        assert_eq!(fs.getattr(file0).await.unwrap().attr.size, 20);
+        assert_eq!(fs.lookup(dir, "file1".as_ref()).await.unwrap().attr.size, 30);
+    } else {
+        assert_eq!(result.expect_err("attribute can't be set").to_errno(), libc::ENOTSUP);
+        assert_eq!(fs.getattr(file0).await.unwrap().attr.size, 10);
+        fs.lookup(dir, "file1".as_ref())
+            .await
+            .expect_err("negative lookup should still be cached");
+    }
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test_case(true; "decoded")]
+#[test_case(false; "as stored")]
+#[tokio::test]
+async fn test_decode_content_encoding(decode_content_encoding: bool) {
+    let fs_config = S3FilesystemConfig {
+        decode_content_encoding,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_decode_content_encoding", &Default::default(), fs_config);
+
+    let content: Vec<u8> = (0..300 * 1024).map(|i| b"hello world\n"[i % 12]).collect();
+    let encoded = gzip(&content);
+    let mut object = MockObject::from(&encoded);
+    object.set_content_encoding(Some("gzip".to_owned()));
+    client.add_object("index.html", object);
+    // Concatenated gzip members can't be decoded, as the trailer only holds the size of the last one.
+    let mut members = gzip(b"hello ");
+    members.extend(gzip(b"world"));
+    let mut object = MockObject::from(&members);
+    object.set_content_encoding(Some("gzip".to_owned()));
+    client.add_object("members.txt", object);
+    client.add_object("plain.txt", MockObject::from(b"hello world"));
+
+    let read_all = async |name: &str, read_size: u32| {
+        let entry = fs.lookup(FUSE_ROOT_INODE, name.as_ref()).await.unwrap();
+        let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+        let mut data = Vec::new();
+        let result = loop {
+            match fs.read(entry.attr.ino, fh, data.len() as i64, read_size, 0, None).await {
+                Ok(bytes) if bytes.is_empty() => break Ok(()),
+                Ok(bytes) => data.extend_from_slice(&bytes),
+                Err(e) => break Err(e),
+            }
+        };
+        // Reading backwards decodes the object again from its start.
+        let start = fs.read(entry.attr.ino, fh, 0, 5, 0, None).await;
+        fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+        (entry.attr.size, result.map(|()| data), start.ok())
+    };
+
+    let (size, data, start) = read_all("index.html", 64 * 1024).await;
+    if decode_content_encoding {
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(data.unwrap(), content);
+        assert_eq!(start.as_deref(), Some(&b"hello"[..]));
+    } else {
+        assert_eq!(size, encoded.len() as u64);
+        assert_eq!(data.unwrap(), encoded);
+    }
+
+    let (size, data, _) = read_all("members.txt", 4096).await;
+    if decode_content_encoding {
+        assert_eq!(size, 5);
+        let err = data.expect_err("the members decode to more than the reported size");
+        assert_eq!(err.to_errno(), libc::EIO);
+    } else {
+        assert_eq!(data.unwrap(), members);
+    }
+
+    let (size, data, _) = read_all("plain.txt", 4096).await;
+    assert_eq!(size, 11);
+    assert_eq!(data.unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn test_decode_content_encoding_metadata_update() {
+    let bucket = "test_decode_content_encoding_metadata_update";
+    let fs_config = S3FilesystemConfig {
+        decode_content_encoding: true,
+        mtime_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(bucket, &Default::default(), fs_config);
+
+    let content: Vec<u8> = (0..64 * 1024).map(|i| b"hello world\n"[i % 12]).collect();
+    let mut object = MockObject::from(&gzip(&content));
+    object.set_content_encoding(Some("gzip".to_owned()));
+    client.add_object("index.html", object);
+    let attr = fs.lookup(FUSE_ROOT_INODE, "index.html".as_ref()).await.unwrap().attr;
+
+    // Setting the modification time replaces the metadata of the object, which keeps its encoding.
+    let new_mtime = (SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)).into();
+    fs.setattr(attr.ino, None, None, Some(new_mtime), None, None)
+        .await
+        .unwrap();
+    let head = client
+        .head_object(bucket, "index.html", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.content_encoding.as_deref(), Some("gzip"));
+
+    let attr = fs.getattr(attr.ino).await.unwrap().attr;
+    assert_eq!(attr.size, content.len() as u64);
+    let fh = fs.open(attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let mut data = Vec::new();
+    loop {
+        let bytes = fs
+            .read(attr.ino, fh, data.len() as i64, 16 * 1024, 0, None)
+            .await
+            .unwrap();
+        if bytes.is_empty() {
+            break;
+        }
+        data.extend_from_slice(&bytes);
+    }
+    assert_eq!(data, content);
+    fs.release(attr.ino, fh, 0, None, true).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_object_info_xattrs() {
+    let fs_config = S3FilesystemConfig {
+        object_info_xattrs: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_object_info_xattrs", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    let mut object = MockObject::constant(0xaa, 10, ETag::from_str("\"etag0\"").unwrap());
+    object.set_storage_class(Some("STANDARD_IA".to_owned()));
+    object.set_version_id(Some("version0".to_owned()));
+    client.add_object("file0", object);
+    client.add_object("file1", MockObject::constant(0xbb, 10, ETag::for_tests()));
+    let file0 = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr.ino;
+    let file1 = fs.lookup(FUSE_ROOT_INODE, "file1".as_ref()).await.unwrap().attr.ino;
+
+    let getxattr = async |ino, name: &str| fs.getxattr(ino, name.as_ref()).await.unwrap().map(String::from_utf8);
+    assert_eq!(getxattr(file0, ETAG_XATTR).await, Some(Ok("etag0".to_owned())));
+    assert_eq!(getxattr(file0, VERSION_ID_XATTR).await, Some(Ok("version0".to_owned())));
+    assert_eq!(
+        getxattr(file0, STORAGE_CLASS_XATTR).await,
+        Some(Ok("STANDARD_IA".to_owned()))
+    );
+    assert_eq!(getxattr(file1, VERSION_ID_XATTR).await, None);
+    assert_eq!(
+        getxattr(file1, STORAGE_CLASS_XATTR).await,
+        Some(Ok("STANDARD".to_owned()))
+    );
+    assert_eq!(getxattr(FUSE_ROOT_INODE, ETAG_XATTR).await, None);
+    assert_eq!(fs.listxattr(file0).await.unwrap(), Vec::<OsString>::new());
+
+    let err = fs
+        .setxattr(file0, ETAG_XATTR.as_ref(), b"etag1")
+        .await
+        .expect_err("object info attributes are read-only");
+    assert_eq!(err.to_errno(), libc::EPERM);
+
+    // Files being written have no object yet.
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let file2 = fs
+        .mknod(FUSE_ROOT_INODE, "file2".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file2, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file2, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    assert_eq!(getxattr(file2, ETAG_XATTR).await, None);
+    fs.release(file2, fh, 0, None, false).await.unwrap();
+    let head = client
+        .head_object("test_object_info_xattrs", "file2", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        getxattr(file2, ETAG_XATTR).await,
+        Some(Ok(head.etag.as_str().trim_matches('"').to_owned()))
+    );
+}
+
+#[tokio::test]
+async fn test_presigned_url_xattr() {
+    let fs_config = S3FilesystemConfig {
+        presigned_url_xattr: Some(Duration::from_secs(900)),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_presigned_url_xattr", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    client.add_object("dir/file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr.ino;
+    let file0 = fs.lookup(dir, "file0".as_ref()).await.unwrap().attr.ino;
+
+    let head_counter = client.new_counter(Operation::HeadObject);
+    let url = fs.getxattr(file0, PRESIGNED_URL_XATTR.as_ref()).await.unwrap();
+    assert_eq!(
+        url.map(String::from_utf8),
+        Some(Ok(
+            "https://test_presigned_url_xattr.s3.mock.amazonaws.com/dir/file0?X-Amz-Expires=900".to_owned()
+        ))
+    );
+    assert_eq!(fs.getxattr(dir, PRESIGNED_URL_XATTR.as_ref()).await.unwrap(), None);
+    assert_eq!(fs.listxattr(file0).await.unwrap(), Vec::<OsString>::new());
+    assert_eq!(head_counter.count(), 0, "pre-signing needs no request");
+
+    let err = fs
+        .setxattr(file0, PRESIGNED_URL_XATTR.as_ref(), b"https://example.com")
+        .await
+        .expect_err("pre-signed URLs are read-only");
+    assert_eq!(err.to_errno(), libc::EPERM);
+}
+
+#[tokio::test]
+async fn test_recursive_size_xattr() {
+    let fs_config = S3FilesystemConfig {
+        recursive_size_xattr: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_recursive_size_xattr", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    client.add_object("dir/a", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    client.add_object("dir/sub/b", MockObject::constant(0xaa, 20, ETag::for_tests()));
+    client.add_object("dir/sub/deep/c", MockObject::constant(0xaa, 30, ETag::for_tests()));
+    client.add_object("other", MockObject::constant(0xaa, 5, ETag::for_tests()));
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr.ino;
+    let sub = fs.lookup(dir, "sub".as_ref()).await.unwrap().attr.ino;
+    let file = fs.lookup(dir, "a".as_ref()).await.unwrap().attr.ino;
+
+    let list_counter = client.new_counter(Operation::ListObjectsV2);
+    let recursive_size = async |ino| {
+        let value = fs.getxattr(ino, RECURSIVE_SIZE_XATTR.as_ref()).await.unwrap();
+        value.map(|value| String::from_utf8(value).unwrap())
+    };
+    assert_eq!(recursive_size(dir).await.as_deref(), Some("60"));
+    assert_eq!(recursive_size(dir).await.as_deref(), Some("60"));
+    assert_eq!(list_counter.count(), 1, "the size should be cached");
+    assert_eq!(recursive_size(sub).await.as_deref(), Some("50"));
+    assert_eq!(recursive_size(FUSE_ROOT_INODE).await.as_deref(), Some("65"));
+    assert_eq!(list_counter.count(), 3);
+    assert_eq!(recursive_size(file).await, None, "files have no recursive size");
+    assert_eq!(fs.listxattr(dir).await.unwrap(), Vec::<OsString>::new());
+
+    let err = fs
+        .setxattr(dir, RECURSIVE_SIZE_XATTR.as_ref(), b"0")
+        .await
+        .expect_err("the recursive size is read-only");
+    assert_eq!(err.to_errno(), libc::EPERM);
+}
+
+#[tokio::test]
+async fn test_tag_xattrs() {
+    let fs_config = S3FilesystemConfig {
+        tag_xattrs: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_tag_xattrs", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    let mut object = MockObject::constant(0xaa, 10, ETag::for_tests());
+    object.set_tags(HashMap::from([("project".to_owned(), "mountpoint".to_owned())]));
+    client.add_object("file0", object);
+    let file0 = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr.ino;
+
+    let tag_xattr = |key: &str| format!("{TAG_XATTR_PREFIX}{key}");
+    let tagging_counter = client.new_counter(Operation::GetObjectTagging);
+    assert_eq!(tagging_counter.count(), 0, "tags should be fetched lazily");
+    let value = fs.getxattr(file0, tag_xattr("project").as_ref()).await.unwrap();
+    assert_eq!(value, Some(b"mountpoint".to_vec()));
+    assert_eq!(fs.getxattr(file0, tag_xattr("team").as_ref()).await.unwrap(), None);
+    assert_eq!(
+        fs.listxattr(file0).await.unwrap(),
+        vec![OsString::from(tag_xattr("project"))]
+    );
+    assert_eq!(tagging_counter.count(), 1, "tags should be cached");
+
+    fs.setxattr(file0, tag_xattr("team").as_ref(), b"storage")
+        .await
+        .unwrap();
+    assert!(fs.removexattr(file0, tag_xattr("project").as_ref()).await.unwrap());
+    assert!(!fs.removexattr(file0, tag_xattr("project").as_ref()).await.unwrap());
+    let tags = client
+        .get_object_tagging("test_tag_xattrs", "file0")
+        .await
+        .unwrap()
+        .tags;
+    assert_eq!(tags, HashMap::from([("team".to_owned(), "storage".to_owned())]));
+    let value = fs.getxattr(file0, tag_xattr("team").as_ref()).await.unwrap();
+    assert_eq!(value, Some(b"storage".to_vec()));
+
+    for i in 1..10 {
+        fs.setxattr(file0, tag_xattr(&format!("tag{i}")).as_ref(), b"")
+            .await
+            .unwrap();
+    }
+    let err = fs
+        .setxattr(file0, tag_xattr("tag10").as_ref(), b"")
+        .await
+        .expect_err("objects can have at most 10 tags");
+    assert_eq!(err.to_errno(), libc::ENOSPC);
+
+    let err = fs
+        .setxattr(FUSE_ROOT_INODE, tag_xattr("team").as_ref(), b"storage")
+        .await
+        .expect_err("directories can't be tagged");
+    assert_eq!(err.to_errno(), libc::EPERM);
+    assert_eq!(fs.listxattr(FUSE_ROOT_INODE).await.unwrap(), Vec::<OsString>::new());
+
+    // Files being created are uploaded with the tags set before they are written to.
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let file1 = fs
+        .mknod(FUSE_ROOT_INODE, "file1".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file1, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.setxattr(file1, tag_xattr("team").as_ref(), b"storage")
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.listxattr(file1).await.unwrap(),
+        vec![OsString::from(tag_xattr("team"))]
+    );
+    fs.write(file1, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    let err = fs
+        .setxattr(file1, tag_xattr("project").as_ref(), b"mountpoint")
+        .await
+        .expect_err("files already written to can't be tagged");
+    assert_eq!(err.to_errno(), libc::EBUSY);
+    fs.release(file1, fh, 0, None, false).await.unwrap();
+    let tags = client
+        .get_object_tagging("test_tag_xattrs", "file1")
+        .await
+        .unwrap()
+        .tags;
+    assert_eq!(tags, HashMap::from([("team".to_owned(), "storage".to_owned())]));
+}
+
+#[tokio::test]
+async fn test_default_object_tags() {
+    let default_tags = HashMap::from([("team".to_owned(), "data".to_owned())]);
+    let fs_config = S3FilesystemConfig {
+        object_tags: default_tags.clone(),
+        tag_xattrs: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_default_object_tags", &Default::default(), fs_config);
+    let tags = async |key: &str| {
+        client
+            .get_object_tagging("test_default_object_tags", key)
+            .await
+            .unwrap()
+            .tags
+    };
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    for (name, extra_tag) in [("file0", None), ("file1", Some("project"))] {
+        let ino = fs
+            .mknod(FUSE_ROOT_INODE, name.as_ref(), mode, 0, 0)
+            .await
+            .unwrap()
+            .attr
+            .ino;
+        let fh = fs.open(ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+        if let Some(key) = extra_tag {
+            let name = format!("{TAG_XATTR_PREFIX}{key}");
+            fs.setxattr(ino, name.as_ref(), b"mountpoint").await.unwrap();
+        }
+        fs.write(ino, fh, 0, b"hello", 0, 0, None).await.unwrap();
+        fs.release(ino, fh, 0, None, false).await.unwrap();
+    }
+
+    assert_eq!(tags("file0").await, default_tags);
+    let mut expected = default_tags.clone();
+    expected.insert("project".to_owned(), "mountpoint".to_owned());
+    assert_eq!(tags("file1").await, expected);
+}
+
+#[tokio::test]
+async fn test_content_types() {
+    let fs_config = S3FilesystemConfig {
+        content_types: Some(ContentTypes::new(
+            ["ckpt=application/x-checkpoint".parse().unwrap()],
+            true,
+        )),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_content_types", &Default::default(), fs_config);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let files: [(&str, &[u8], Option<&str>); 4] = [
+        ("data.csv", b"a,b,c\n", Some("text/csv")),
+        ("model.ckpt", b"weights", Some("application/x-checkpoint")),
+        ("image", b"\x89PNG\r\n\x1a\n", Some("image/png")),
+        ("notes", b"hello", None),
+    ];
+    for (name, contents, expected) in files {
+        let ino = fs
+            .mknod(FUSE_ROOT_INODE, name.as_ref(), mode, 0, 0)
+            .await
+            .unwrap()
+            .attr
+            .ino;
+        let fh = fs.open(ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+        fs.write(ino, fh, 0, contents, 0, 0, None).await.unwrap();
+        fs.release(ino, fh, 0, None, false).await.unwrap();
+
+        let head = client
+            .head_object("test_content_types", name, &HeadObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(head.content_type.as_deref(), expected, "content type of {name}");
+    }
+}
+
+#[tokio::test]
+async fn test_mtime_metadata() {
+    let fs_config = S3FilesystemConfig {
+        mtime_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_mtime_metadata", &Default::default(), fs_config);
+    let mtime = |seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    let object_metadata = |key: &str| {
+        let client = client.clone();
+        let key = key.to_owned();
+        async move {
+            client
+                .head_object("test_mtime_metadata", &key, &HeadObjectParams::new())
+                .await
+                .unwrap()
+                .object_metadata
+        }
+    };
+
+    let mut object = MockObject::constant(0xaa, 10, ETag::for_tests());
+    object.set_object_metadata(HashMap::from([
+        (MTIME_METADATA_KEY.to_owned(), "1700000000.5".to_owned()),
+        ("source".to_owned(), "test".to_owned()),
+    ]));
+    object.set_storage_class(Some("STANDARD_IA".to_owned()));
+    object.set_content_type(Some("text/csv".to_owned()));
+    object.set_content_encoding(Some("gzip".to_owned()));
+    object.set_cache_control(Some("max-age=3600".to_owned()));
+    object.set_content_disposition(Some("attachment".to_owned()));
+    object.set_server_side_encryption(Some("aws:kms".to_owned()), Some("some_key".to_owned()));
+    client.add_object("file0", object);
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+    assert_eq!(attr.mtime, mtime(1_700_000_000) + Duration::from_millis(500));
+
+    // Setting the modification time of a remote file copies its object with the time in its metadata.
+    let new_mtime = (mtime(1_700_000_100) + Duration::from_millis(250)).into();
+    let attr = fs
+        .setattr(attr.ino, None, None, Some(new_mtime), None, None)
+        .await
+        .unwrap()
+        .attr;
+    assert_eq!(
+        attr.mtime,
+        mtime(1_700_000_100),
+        "the time should be truncated to seconds"
+    );
+    assert_eq!(
+        object_metadata("file0").await,
+        HashMap::from([
+            (MTIME_METADATA_KEY.to_owned(), "1700000100".to_owned()),
+            ("source".to_owned(), "test".to_owned()),
+        ])
+    );
+    let head = client
+        .head_object("test_mtime_metadata", "file0", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.storage_class.as_deref(), Some("STANDARD_IA"));
+    assert_eq!(head.content_type.as_deref(), Some("text/csv"));
+    assert_eq!(head.content_encoding.as_deref(), Some("gzip"));
+    assert_eq!(head.cache_control.as_deref(), Some("max-age=3600"));
+    assert_eq!(head.content_disposition.as_deref(), Some("attachment"));
+    assert_eq!(head.sse_type.as_deref(), Some("aws:kms"));
+    assert_eq!(head.sse_kms_key_id.as_deref(), Some("some_key"));
+
+    // The modification time of a file being written is put in its metadata once it is uploaded.
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let file1 = fs
+        .mknod(FUSE_ROOT_INODE, "file1".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file1, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file1, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    let new_mtime = mtime(1_600_000_000).into();
+    fs.setattr(file1, None, None, Some(new_mtime), None, None)
+        .await
+        .unwrap();
+    assert!(!client.contains_key("file1"), "the file should not be uploaded yet");
+    fs.release(file1, fh, 0, None, false).await.unwrap();
+    assert_eq!(
+        object_metadata("file1").await,
+        HashMap::from([(MTIME_METADATA_KEY.to_owned(), "1600000000".to_owned())])
+    );
+    let attr = fs.getattr(file1).await.unwrap().attr;
+    assert_eq!(attr.mtime, mtime(1_600_000_000));
+}
+
+#[tokio::test]
+async fn test_mtime_metadata_large_object() {
+    let fs_config = S3FilesystemConfig {
+        mtime_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_mtime_metadata_large_object", &Default::default(), fs_config);
+    let size = 5 * 1024 * 1024 * 1024 + 1;
+    client.add_object("file0", MockObject::constant(0xaa, size, ETag::for_tests()));
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+
+    // Objects over 5 GiB can't be copied with a single CopyObject request.
+    let new_mtime = (SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)).into();
+    let err = fs
+        .setattr(attr.ino, None, None, Some(new_mtime), None, None)
+        .await
+        .expect_err("updating the metadata of a large object should fail");
+    assert_eq!(err.to_errno(), libc::EFBIG);
+    assert!(
+        client
+            .head_object("test_mtime_metadata_large_object", "file0", &HeadObjectParams::new())
+            .await
+            .unwrap()
+            .object_metadata
+            .is_empty()
+    );
+}
+
+#[test_case(None; "in memory")]
+#[test_case(Some(Duration::ZERO); "flushed")]
+#[tokio::test]
+async fn test_atime(atime_flush_interval: Option<Duration>) {
+    let bucket = "test_atime";
+    let fs_config = || S3FilesystemConfig {
+        atime: true,
+        atime_flush_interval,
+        mtime_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(bucket, &Default::default(), fs_config());
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut object = MockObject::constant(0xaa, 10, ETag::for_tests());
+    object.set_last_modified(mtime.into());
+    object.set_content_type(Some("text/plain".to_owned()));
+    object.set_cache_control(Some("max-age=60".to_owned()));
+    object.set_server_side_encryption(Some("AES256".to_owned()), None);
+    client.add_object("file0", object);
+
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+    assert_eq!(
+        attr.atime, mtime,
+        "files that were not read report the time of their object"
+    );
+    let before_read = SystemTime::now() - Duration::from_secs(1);
+    let fh = fs.open(attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    fs.read(attr.ino, fh, 0, 10, 0, None).await.unwrap();
+    let atime = fs.getattr(attr.ino).await.unwrap().attr.atime;
+    assert!(atime >= before_read);
+    fs.release(attr.ino, fh, 0, None, false).await.unwrap();
+
+    let head = client
+        .head_object(bucket, "file0", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    let object_metadata = head.object_metadata;
+    if atime_flush_interval.is_none() {
+        assert!(object_metadata.is_empty(), "access times are only kept in memory");
+        return;
+    }
+    assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(head.cache_control.as_deref(), Some("max-age=60"));
+    assert_eq!(head.sse_type.as_deref(), Some("AES256"));
+    let flushed_atime = object_metadata
+        .get(ATIME_METADATA_KEY)
+        .expect("access time should be stored");
+    assert_eq!(
+        object_metadata.get(MTIME_METADATA_KEY).map(String::as_str),
+        Some("1700000000")
+    );
+    let attr = fs.getattr(attr.ino).await.unwrap().attr;
+    assert_eq!(attr.mtime, mtime, "storing the access time keeps the modification time");
+
+    // Another mount reports the stored access time.
+    let pool = PagedPool::new_with_candidate_sizes([1024 * 1024]);
+    let fs = make_test_filesystem_with_client(client.clone(), pool, bucket, &Default::default(), fs_config());
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+    let seconds = attr.atime.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(&seconds.to_string(), flushed_atime);
+}
+#[tokio::test]
+async fn test_mtime_metadata_disabled() {
+    let (client, fs) = make_test_filesystem("test_mtime_metadata_disabled", &Default::default(), Default::default());
+    client.add_object("file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+
+    let new_mtime = (SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)).into();
+    let err = fs
+        .setattr(attr.ino, None, None, Some(new_mtime), None, None)
+        .await
+        .expect_err("setting the modification time of an uploaded file should fail");
+    assert_eq!(err.to_errno(), libc::EPERM);
+    assert!(
+        client
+            .head_object("test_mtime_metadata_disabled", "file0", &HeadObjectParams::new())
+            .await
+            .unwrap()
+            .object_metadata
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_mode_metadata() {
+    let fs_config = S3FilesystemConfig {
+        mode_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_mode_metadata", &Default::default(), fs_config);
+    let object_metadata = |key: &str| {
+        let client = client.clone();
+        let key = key.to_owned();
+        async move {
+            client
+                .head_object("test_mode_metadata", &key, &HeadObjectParams::new())
+                .await
+                .unwrap()
+                .object_metadata
+        }
+    };
+
+    // Objects without the metadata have the permissions of the mount.
+    client.add_object("file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    let mut object = MockObject::constant(0xaa, 10, ETag::for_tests());
+    object.set_object_metadata(HashMap::from([(MODE_METADATA_KEY.to_owned(), "33216".to_owned())]));
+    object.set_cache_control(Some("no-cache".to_owned()));
+    object.set_content_disposition(Some("inline".to_owned()));
+    object.set_server_side_encryption(Some("aws:kms".to_owned()), Some("some_key".to_owned()));
+    client.add_object("file1", object);
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+    assert_eq!(attr.perm, 0o644);
+    let attr = fs.lookup(FUSE_ROOT_INODE, "file1".as_ref()).await.unwrap().attr;
+    assert_eq!(attr.perm, 0o700);
+
+    // Changing the permissions of a remote file copies its object with them in its metadata.
+    let attr = fs
+        .setattr(attr.ino, Some(libc::S_IFREG | 0o640), None, None, None, None)
+        .await
+        .unwrap()
+        .attr;
+    assert_eq!(attr.perm, 0o640);
+    assert_eq!(
+        object_metadata("file1").await,
+        HashMap::from([(MODE_METADATA_KEY.to_owned(), "33184".to_owned())])
+    );
+    let head = client
+        .head_object("test_mode_metadata", "file1", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.cache_control.as_deref(), Some("no-cache"));
+    assert_eq!(head.content_disposition.as_deref(), Some("inline"));
+    assert_eq!(head.sse_type.as_deref(), Some("aws:kms"));
+    assert_eq!(head.sse_kms_key_id.as_deref(), Some("some_key"));
+
+    // New files are uploaded with the mode they were created with, less the umask.
+    let mode = libc::S_IFREG | 0o777;
+    let attr = fs
+        .mknod(FUSE_ROOT_INODE, "file2".as_ref(), mode, 0o022, 0)
+        .await
+        .unwrap()
+        .attr;
+    assert_eq!(attr.perm, 0o755);
+    let fh = fs.open(attr.ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(attr.ino, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    fs.release(attr.ino, fh, 0, None, false).await.unwrap();
+    assert_eq!(
+        object_metadata("file2").await,
+        HashMap::from([(MODE_METADATA_KEY.to_owned(), "33261".to_owned())])
+    );
+
+    // Permissions changed while a file is written are put in its metadata once it is uploaded.
+    let file3 = fs
+        .mknod(FUSE_ROOT_INODE, "file3".as_ref(), mode, 0o022, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file3, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file3, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    let attr = fs
+        .setattr(file3, Some(0o600), None, None, None, None)
+        .await
+        .unwrap()
+        .attr;
+    assert_eq!(attr.perm, 0o600);
+    fs.release(file3, fh, 0, None, false).await.unwrap();
+    assert_eq!(
+        object_metadata("file3").await,
+        HashMap::from([(MODE_METADATA_KEY.to_owned(), "33152".to_owned())])
+    );
+    assert_eq!(fs.getattr(file3).await.unwrap().attr.perm, 0o600);
+}
+
+#[tokio::test]
+async fn test_symlinks() {
+    let fs_config = S3FilesystemConfig {
+        symlinks: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_symlinks", &Default::default(), fs_config);
+
+    let mut object = MockObject::from_bytes(b"file0", ETag::for_tests());
+    object.set_object_metadata(HashMap::from([(
+        SYMLINK_TARGET_METADATA_KEY.to_owned(),
+        "file0".to_owned(),
+    )]));
+    client.add_object("link0", object);
+    client.add_object("file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+
+    let attr = fs.lookup(FUSE_ROOT_INODE, "link0".as_ref()).await.unwrap().attr;
+    assert_eq!(attr.kind, FileType::Symlink);
+    assert_eq!(attr.perm, 0o777);
+    assert_eq!(attr.size, 5);
+    assert_eq!(fs.readlink(attr.ino).await.unwrap(), "file0");
+    let file0 = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr;
+    assert_eq!(file0.kind, FileType::RegularFile);
+    let err = fs
+        .readlink(file0.ino)
+        .await
+        .expect_err("regular files are not symlinks");
+    assert_eq!(err.to_errno(), libc::EINVAL);
+
+    let entry = fs
+        .symlink(FUSE_ROOT_INODE, "link1".as_ref(), Path::new("dir/file1"))
+        .await
+        .unwrap();
+    assert_eq!(entry.attr.kind, FileType::Symlink);
+    assert_eq!(entry.attr.size, 9);
+    assert_eq!(fs.readlink(entry.attr.ino).await.unwrap(), "dir/file1");
+    let head = client
+        .head_object("test_symlinks", "link1", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        head.object_metadata
+            .get(SYMLINK_TARGET_METADATA_KEY)
+            .map(String::as_str),
+        Some("dir/file1")
+    );
+    assert_eq!(head.size, 9, "the object should hold the target");
+
+    let err = fs
+        .symlink(FUSE_ROOT_INODE, "file0".as_ref(), Path::new("link0"))
+        .await
+        .expect_err("names of existing files can't be reused");
+    assert_eq!(err.to_errno(), libc::EEXIST);
+
+    // Symlinks are only supported when enabled.
+    let (client, fs) = make_test_filesystem("test_symlinks", &Default::default(), Default::default());
+    let mut object = MockObject::from_bytes(b"file0", ETag::for_tests());
+    object.set_object_metadata(HashMap::from([(
+        SYMLINK_TARGET_METADATA_KEY.to_owned(),
+        "file0".to_owned(),
+    )]));
+    client.add_object("link0", object);
+    let attr = fs.lookup(FUSE_ROOT_INODE, "link0".as_ref()).await.unwrap().attr;
+    assert_eq!(attr.kind, FileType::RegularFile);
+    let err = fs
+        .symlink(FUSE_ROOT_INODE, "link1".as_ref(), Path::new("file0"))
+        .await
+        .expect_err("symlinks are not supported");
+    assert_eq!(err.to_errno(), libc::EPERM);
+}
+
+#[tokio::test]
+async fn test_directory_markers() {
+    let fs_config = S3FilesystemConfig {
+        directory_markers: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_directory_markers", &Default::default(), fs_config);
+
+    let entry = fs
+        .mkdir(FUSE_ROOT_INODE, "dir".as_ref(), libc::S_IFDIR, 0)
+        .await
+        .unwrap();
+    assert_eq!(entry.attr.kind, FileType::Directory);
+    assert!(client.contains_key("dir/"), "mkdir should create the marker");
+    let lookup = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+    assert_eq!(lookup.attr.ino, entry.attr.ino);
+
+    fs.rmdir(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+    assert!(!client.contains_key("dir/"), "rmdir should delete the marker");
+    let err = fs
+        .lookup(FUSE_ROOT_INODE, "dir".as_ref())
+        .await
+        .expect_err("the directory was removed");
+    assert_eq!(err.to_errno(), libc::ENOENT);
+
+    // Directories with objects under them are not empty, whether they have a marker or not.
+    client.add_object("full/", MockObject::constant(0, 0, ETag::for_tests()));
+    client.add_object("full/file", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    client.add_object("implicit/sub/file", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    for name in ["full", "implicit"] {
+        fs.lookup(FUSE_ROOT_INODE, name.as_ref()).await.unwrap();
+        let err = fs
+            .rmdir(FUSE_ROOT_INODE, name.as_ref())
+            .await
+            .expect_err("the directory is not empty");
+        assert_eq!(err.to_errno(), libc::ENOTEMPTY);
+    }
+    assert!(client.contains_key("full/"));
+
+    // Without the option, remote directories can't be removed.
+    let (client, fs) = make_test_filesystem("test_directory_markers", &Default::default(), Default::default());
+    client.add_object("dir/", MockObject::constant(0, 0, ETag::for_tests()));
+    fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+    let err = fs
+        .rmdir(FUSE_ROOT_INODE, "dir".as_ref())
+        .await
+        .expect_err("remote directories can't be removed");
+    assert_eq!(err.to_errno(), libc::EPERM);
+}
+
+#[tokio::test]
+async fn test_escape_names() {
+    let fs_config = S3FilesystemConfig {
+        escape_names: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_escape_names", &Default::default(), fs_config);
+    client.add_object("dir/../file.txt", MockObject::from_bytes(b"hello", ETag::for_tests()));
+
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap();
+    let dots = fs.lookup(dir.attr.ino, "%2E%2E".as_ref()).await.unwrap();
+    assert_eq!(dots.attr.kind, FileType::Directory);
+    let entry = fs.lookup(dots.attr.ino, "file.txt".as_ref()).await.unwrap();
+    let fh = fs.open(entry.attr.ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let data = fs.read(entry.attr.ino, fh, 0, 1024, 0, None).await.unwrap();
+    assert_eq!(&data[..], b"hello");
+    fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+
+    let entry = fs
+        .mknod(dir.attr.ino, "100%25".as_ref(), libc::S_IFREG | libc::S_IRWXU, 0, 0)
+        .await
+        .unwrap();
+    let fh = fs.open(entry.attr.ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(entry.attr.ino, fh, 0, b"world", 0, 0, None).await.unwrap();
+    fs.release(entry.attr.ino, fh, 0, None, true).await.unwrap();
+    assert!(
+        client.contains_key("dir/100%"),
+        "the name of the file should be unescaped"
+    );
+
+    let err = fs
+        .mknod(dir.attr.ino, "50%".as_ref(), libc::S_IFREG | libc::S_IRWXU, 0, 0)
+        .await
+        .expect_err("the names of new files must be escaped");
+    assert_eq!(err.to_errno(), libc::EINVAL);
+}
+
+#[tokio::test]
+async fn test_user_metadata_xattrs() {
+    let fs_config = S3FilesystemConfig {
+        user_metadata_xattrs: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_user_metadata_xattrs", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    let mut object = MockObject::constant(0xaa, 10, ETag::for_tests());
+    object.set_object_metadata(HashMap::from([
+        ("source".to_owned(), "pipeline".to_owned()),
+        ("batch".to_owned(), "42".to_owned()),
+    ]));
+    client.add_object("file0", object);
+    let file0 = fs.lookup(FUSE_ROOT_INODE, "file0".as_ref()).await.unwrap().attr.ino;
+
+    let value = fs.getxattr(file0, "user.s3.meta.source".as_ref()).await.unwrap();
+    assert_eq!(value.as_deref(), Some(&b"pipeline"[..]));
+    let value = fs.getxattr(file0, "user.s3.meta.missing".as_ref()).await.unwrap();
+    assert_eq!(value, None);
+    let names = fs.listxattr(file0).await.unwrap();
+    assert_eq!(names, vec!["user.s3.meta.batch", "user.s3.meta.source"]);
+    assert_eq!(fs.listxattr(FUSE_ROOT_INODE).await.unwrap(), Vec::<OsString>::new());
+
+    let err = fs
+        .setxattr(file0, "user.s3.meta.source".as_ref(), b"other")
+        .await
+        .expect_err("metadata of existing objects can't be set");
+    assert_eq!(err.to_errno(), libc::EPERM);
+
+    // Set metadata on a new file before writing it.
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let file1 = fs
+        .mknod(FUSE_ROOT_INODE, "file1".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file1, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.setxattr(file1, "user.s3.meta.source".as_ref(), b"mountpoint")
+        .await
+        .unwrap();
+    fs.setxattr(file1, "user.s3.meta.owner".as_ref(), b"team")
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.listxattr(file1).await.unwrap(),
+        vec!["user.s3.meta.owner", "user.s3.meta.source"]
+    );
+    fs.write(file1, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    let err = fs
+        .setxattr(file1, "user.s3.meta.late".as_ref(), b"value")
+        .await
+        .expect_err("metadata can't be set once data is written");
+    assert_eq!(err.to_errno(), libc::EBUSY);
+    fs.release(file1, fh, 0, None, false).await.unwrap();
+
+    let head = client
+        .head_object("test_user_metadata_xattrs", "file1", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        head.object_metadata,
+        HashMap::from([
+            ("source".to_owned(), "mountpoint".to_owned()),
+            ("owner".to_owned(), "team".to_owned()),
+        ])
+    );
+    let value = fs.getxattr(file1, "user.s3.meta.owner".as_ref()).await.unwrap();
+    assert_eq!(value.as_deref(), Some(&b"team"[..]));
+}
+
+#[test_case(true; "overlayfs lower")]
+#[test_case(false; "default")]
+#[tokio::test]
+async fn test_overlayfs_lower(overlayfs_lower: bool) {
+    let fs_config = S3FilesystemConfig {
+        overlayfs_lower,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_overlayfs_lower", &Default::default(), fs_config);
+    assert_eq!(fs.xattrs_supported(), overlayfs_lower);
+    client.add_object("dir/file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    let head_object_counter = client.new_counter(Operation::HeadObject);
+
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr;
+    assert_eq!(dir.nlink, if overlayfs_lower { 1 } else { 2 });
+    let file0 = fs.lookup(dir.ino, "file0".as_ref()).await.unwrap().attr;
+    assert_eq!(file0.nlink, 1);
+
+    // Directory entries report the type of the files and directories.
+    let dir_handle = fs.opendir(FUSE_ROOT_INODE, 0).await.unwrap().fh;
+    let mut reply = DirectoryReply::default();
+    fs.readdir(FUSE_ROOT_INODE, dir_handle, 0, &mut reply).await.unwrap();
+    let entries: Vec<_> = reply
+        .entries
+        .iter()
+        .map(|entry| (entry.name.clone(), entry.attr.kind))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (".".into(), FileType::Directory),
+            ("..".into(), FileType::Directory),
+            ("dir".into(), FileType::Directory),
+        ]
+    );
+
+    if overlayfs_lower {
+        let head_objects = head_object_counter.count();
+        for name in [
+            "trusted.overlay.opaque",
+            "user.overlay.opaque",
+            "trusted.overlay.metacopy",
+        ] {
+            for ino in [dir.ino, file0.ino] {
+                assert_eq!(fs.getxattr(ino, name.as_ref()).await.unwrap(), None);
+            }
+        }
+        assert_eq!(
+            head_object_counter.count(),
+            head_objects,
+            "no request for overlayfs xattrs"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_pin_xattr() {
+    let block_size = 64 * 1024;
+    let cache_directory = tempfile::tempdir().unwrap();
+    let cache = Arc::new(DiskDataCache::new(
+        DiskDataCacheConfig {
+            cache_directory: cache_directory.path().to_path_buf(),
+            block_size,
+            limit: CacheLimit::TotalSize {
+                max_size: 8 * block_size as usize,
+            },
+            eviction_policy: Default::default(),
+            shared: false,
+            persistent: false,
+            admission_policy: Default::default(),
+            pinning: None,
+            encryption_key: None,
+            compression_level: None,
+            io_backend: Default::default(),
+            account: None,
+        },
+        PagedPool::new_with_candidate_sizes([block_size as usize]),
+    ));
+    let fs_config = S3FilesystemConfig {
+        pinning_cache: Some(PinningCache::new(cache.clone())),
+        ..Default::default()
+    };
+    let (client, fs) = make_caching_test_filesystem("test_pin_xattr", &Default::default(), cache.clone(), fs_config);
+    assert!(fs.xattrs_supported());
+
+    let object_size = 4 * block_size as usize;
+    client.add_object("file", MockObject::constant(0xaa, object_size, ETag::for_tests()));
+    client.add_object("dir/file", MockObject::constant(0xaa, object_size, ETag::for_tests()));
+    let ino = fs.lookup(FUSE_ROOT_INODE, "file".as_ref()).await.unwrap().attr.ino;
+    let dir_ino = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr.ino;
+    assert_eq!(fs.getxattr(ino, PIN_XATTR.as_ref()).await.unwrap(), None);
+
+    let err = fs.setxattr(ino, PIN_XATTR.as_ref(), b"yes").await.unwrap_err();
+    assert_eq!(err.to_errno(), libc::EINVAL);
+    let err = fs.setxattr(ino, "user.other".as_ref(), b"1").await.unwrap_err();
+    assert_eq!(err.to_errno(), libc::ENOTSUP);
+    let err = fs.setxattr(dir_ino, PIN_XATTR.as_ref(), b"1").await.unwrap_err();
+    assert_eq!(err.to_errno(), libc::EINVAL, "directories can't be pinned");
+
+    fs.setxattr(ino, PIN_XATTR.as_ref(), b"1").await.unwrap();
+    assert_eq!(fs.getxattr(ino, PIN_XATTR.as_ref()).await.unwrap(), Some(b"1".to_vec()));
+    let pinned_blocks = || {
+        cache
+            .list_blocks()
+            .into_iter()
+            .filter(|block| block.object_id.as_ref().is_some_and(|id| id.key() == "file"))
+            .count()
+    };
+    for _ in 0..100 {
+        if pinned_blocks() == 4 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(pinned_blocks(), 4, "the content of pinned files is fetched");
+
+    // Fill the cache with the blocks of other objects.
+    let data = ChecksummedBytes::new(vec![0xbb; block_size as usize].into());
+    for index in 0..8 {
+        let object_id = ObjectId::new(format!("other/{index}"), ETag::for_tests());
+        cache
+            .put_block(object_id, 0, 0, data.clone(), block_size as usize)
+            .await
+            .unwrap();
+    }
+    assert_eq!(pinned_blocks(), 4, "the blocks of pinned files are not evicted");
+
+    assert!(fs.removexattr(ino, PIN_XATTR.as_ref()).await.unwrap());
+    assert!(!fs.removexattr(ino, PIN_XATTR.as_ref()).await.unwrap());
+    assert_eq!(fs.getxattr(ino, PIN_XATTR.as_ref()).await.unwrap(), None);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_direct_io_bypasses_cache() {
+    let block_size = 1024 * 1024;
+    let cache = Arc::new(InMemoryDataCache::new(block_size));
+    let (client, fs) = make_caching_test_filesystem(
+        "test_direct_io_bypasses_cache",
+        &Default::default(),
+        cache.clone(),
+        Default::default(),
+    );
+
+    let object_size = 2 * block_size as usize;
+    let etag = ETag::for_tests();
+    client.add_object("file", MockObject::constant(0xaa, object_size, etag.clone()));
+    let object_id = ObjectId::new("file".to_owned(), etag);
+    let get_object_counter = client.new_counter(Operation::GetObject);
+    let ino = fs.lookup(FUSE_ROOT_INODE, "file".as_ref()).await.unwrap().attr.ino;
+
+    let read_file = async |flags: OpenFlags| {
+        let fh = fs.open(ino, flags, 0).await.unwrap().fh;
+        let bytes = fs.read(ino, fh, 0, object_size as u32, 0, None).await.unwrap();
+        assert_eq!(bytes.len(), object_size);
+        assert!(bytes.iter().all(|&b| b == 0xaa));
+        fs.release(ino, fh, 0, None, false).await.unwrap();
+    };
+
+    // Reads of handles opened with O_DIRECT don't populate the cache.
+    read_file(OpenFlags::O_DIRECT).await;
+    assert_eq!(cache.block_count(&object_id), 0);
+    assert!(get_object_counter.count() > 0);
+
+    read_file(OpenFlags::empty()).await;
+    assert_eq!(cache.block_count(&object_id), 2);
+
+    // Nor are they served from it.
+    let requests = get_object_counter.count();
+    read_file(OpenFlags::O_DIRECT).await;
+    assert!(get_object_counter.count() > requests);
+    let requests = get_object_counter.count();
+    read_file(OpenFlags::empty()).await;
+    assert_eq!(get_object_counter.count(), requests);
+}
+
 #[test_case(""; "unprefixed")]
 #[test_case("test_prefix/"; "prefixed")]
 #[tokio::test]
@@ -528,96 +1747,630 @@ async fn test_implicit_directory_shadow(prefix: &str) {
 
     // TODO test removing the directory, removing the file
 
-    fs.releasedir(dir_ino, dir_handle, 0).await.unwrap();
+    fs.releasedir(dir_ino, dir_handle, 0).await.unwrap();
+}
+
+#[test_case(1024; "small")]
+#[test_case(50 * 1024; "large")]
+#[tokio::test]
+async fn test_sequential_write(write_size: usize) {
+    const BUCKET_NAME: &str = "test_sequential_write";
+    const OBJECT_SIZE: usize = 50 * 1024;
+
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
+
+    let mut rng = SmallRng::seed_from_u64(0x12345678 + OBJECT_SIZE as u64);
+    let mut body = vec![0u8; OBJECT_SIZE];
+    rng.fill(&mut body[..]);
+
+    client.add_object("dir1/file1.bin", MockObject::constant(0xa1, 15, ETag::for_tests()));
+
+    // Find the dir1 directory
+    let entry = fs.lookup(FUSE_ROOT_INODE, "dir1".as_ref()).await.unwrap();
+    assert_eq!(entry.attr.kind, FileType::Directory);
+    let dir_ino = entry.attr.ino;
+
+    // Write the object into that directory
+    let mode = libc::S_IFREG | libc::S_IRWXU; // regular file + 0700 permissions
+    let dentry = fs.mknod(dir_ino, "file2.bin".as_ref(), mode, 0, 0).await.unwrap();
+    assert_eq!(dentry.attr.size, 0);
+    let file_ino = dentry.attr.ino;
+
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+
+    let mut offset = 0;
+    for data in body.chunks(write_size) {
+        let written = fs.write(file_ino, fh, offset, data, 0, 0, None).await.unwrap();
+        assert_eq!(written as usize, data.len());
+        offset += written as i64;
+    }
+
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    // Check that the object made it to S3 as we expected
+    let get = client
+        .get_object(BUCKET_NAME, "dir1/file2.bin", &GetObjectParams::new())
+        .await
+        .unwrap();
+    let actual = get.collect().await.unwrap();
+    assert_eq!(&actual[..], &body[..]);
+
+    // And now check that we can read it out of the file system too. We need to emulate the kernel's
+    // handling of ESTALE, which will retry after re-looking-up.
+    let stat = match fs.getattr(file_ino).await {
+        Ok(stat) => stat,
+        Err(e) if e.to_errno() == libc::ESTALE => {
+            let entry = fs.lookup(dir_ino, "file2.bin".as_ref()).await.unwrap();
+            assert_ne!(entry.attr.ino, file_ino);
+            fs.getattr(entry.attr.ino).await.unwrap()
+        }
+        Err(e) => panic!("unexpected getattr failure: {e:?}"),
+    };
+    assert_eq!(stat.attr.size, body.len() as u64);
+
+    let dentry = fs.lookup(dir_ino, "file2.bin".as_ref()).await.unwrap();
+    let size = dentry.attr.size as usize;
+    assert_eq!(size, body.len());
+    let file_ino = dentry.attr.ino;
+
+    // First let's check that we can't write it again
+    let result = fs
+        .open(file_ino, OpenFlags::O_WRONLY, 0)
+        .await
+        .expect_err("file should not be overwritable")
+        .to_errno();
+    assert_eq!(result, libc::EPERM);
+
+    // But read-only should work
+    let fh = fs.open(file_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+
+    let mut offset = 0;
+    while offset < size {
+        let length = 1024.min(size - offset);
+        let bytes_read = fs
+            .read(file_ino, fh, offset as i64, length as u32, 0, None)
+            .await
+            .expect("fs read should succeed");
+        assert_eq!(bytes_read.len(), length);
+        assert_eq!(&bytes_read[..], &body[offset..offset + length]);
+        offset += length;
+    }
+
+    fs.release(file_ino, fh, 0, None, true).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_truncate_existing_object() {
+    const BUCKET_NAME: &str = "test_truncate_existing_object";
+
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
+    client.add_object("file.txt", MockObject::from(b"hello world"));
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let err = fs
+        .setattr(entry.attr.ino, None, None, None, Some(5), None)
+        .await
+        .expect_err("truncating requires overwrites to be allowed")
+        .to_errno();
+    assert_eq!(err, libc::EPERM);
+
+    let config = S3FilesystemConfig {
+        allow_overwrite: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    client.add_object("file.txt", MockObject::from(b"hello world"));
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let file_ino = entry.attr.ino;
+
+    for (size, expected) in [(5, &b"hello"[..]), (8, &b"hello\0\0\0"[..]), (0, &b""[..])] {
+        let attr = fs.setattr(file_ino, None, None, None, Some(size), None).await.unwrap();
+        assert_eq!(attr.attr.size, size);
+        let get = client
+            .get_object(BUCKET_NAME, "file.txt", &GetObjectParams::new())
+            .await
+            .unwrap();
+        assert_eq!(&get.collect().await.unwrap()[..], expected);
+    }
+
+    // Opening in truncate mode and closing without writing also replaces the object with an empty one.
+    client.add_object("other.txt", MockObject::from(b"hello world"));
+    let entry = fs.lookup(FUSE_ROOT_INODE, "other.txt".as_ref()).await.unwrap();
+    let fh = fs
+        .open(entry.attr.ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
+        .await
+        .unwrap()
+        .fh;
+    fs.release(entry.attr.ino, fh, 0, None, false).await.unwrap();
+    let head = client
+        .head_object(BUCKET_NAME, "other.txt", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.size, 0);
+}
+
+#[tokio::test]
+async fn test_writeback_cache() {
+    const BUCKET_NAME: &str = "test_writeback_cache";
+    let mtime = (SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)).into();
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_staging_dir: Some(staging_dir.path().to_owned()),
+        writeback_cache: true,
+        mtime_metadata: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    let copy_counter = client.new_counter(Operation::CopyObject);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let file_ino = fs
+        .mknod(FUSE_ROOT_INODE, "file.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let opened = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap();
+    assert_eq!(
+        opened.flags & FOPEN_DIRECT_IO,
+        0,
+        "staged writes go through the page cache"
+    );
+    let fh = opened.fh;
+
+    // The kernel writes back pages in any order, rewrites the pages it wrote back partially, and reads them back.
+    fs.write(file_ino, fh, 4, b"5678", FUSE_WRITE_CACHE, 0, None)
+        .await
+        .unwrap();
+    fs.write(file_ino, fh, 0, b"1234", FUSE_WRITE_CACHE, 0, None)
+        .await
+        .unwrap();
+    fs.write(file_ino, fh, 0, b"12345678ab", FUSE_WRITE_CACHE, 0, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        &fs.read(file_ino, fh, 0, 4096, 0, None).await.unwrap()[..],
+        b"12345678ab"
+    );
+    // It then sets the times it keeps for the file, which are uploaded with it.
+    fs.setattr(file_ino, None, None, Some(mtime), None, None).await.unwrap();
+    fs.flush(file_ino, fh, 0, 0).await.unwrap();
+
+    let head = client
+        .head_object(BUCKET_NAME, "file.txt", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.size, 10);
+    assert_eq!(
+        head.object_metadata.get(MTIME_METADATA_KEY).map(String::as_str),
+        Some("1700000000")
+    );
+    fs.release(file_ino, fh, 0, None, true).await.unwrap();
+    assert_eq!(copy_counter.count(), 0, "the time is not set by copying the object");
+
+    // Files not staged are written with direct I/O, and the kernel setting the times of uploaded files is ignored.
+    let config = S3FilesystemConfig {
+        writeback_cache: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    let file_ino = fs
+        .mknod(FUSE_ROOT_INODE, "file.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let opened = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap();
+    assert_eq!(opened.flags & FOPEN_DIRECT_IO, FOPEN_DIRECT_IO);
+    fs.write(file_ino, opened.fh, 0, b"hello", 0, 0, None).await.unwrap();
+    fs.release(file_ino, opened.fh, 0, None, true).await.unwrap();
+    fs.setattr(file_ino, None, None, Some(mtime), None, None).await.unwrap();
+    assert_eq!(client.new_counter(Operation::CopyObject).count(), 0);
+}
+
+#[tokio::test]
+async fn test_staged_random_write() {
+    const BUCKET_NAME: &str = "test_staged_random_write";
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_staging_dir: Some(staging_dir.path().to_owned()),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.zip".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+
+    // Write the body, then come back to fill in the header.
+    fs.write(file_ino, fh, 4, b"body", 0, 0, None).await.unwrap();
+    fs.write(file_ino, fh, 12, b"tail", 0, 0, None).await.unwrap();
+    fs.write(file_ino, fh, 0, b"head", 0, 0, None).await.unwrap();
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 16);
+    assert!(
+        client
+            .head_object(BUCKET_NAME, "file.zip", &HeadObjectParams::new())
+            .await
+            .is_err()
+    );
+
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    let get = client
+        .get_object(BUCKET_NAME, "file.zip", &GetObjectParams::new())
+        .await
+        .unwrap();
+    let actual = get.collect().await.unwrap();
+    assert_eq!(&actual[..], b"headbody\0\0\0\0tail");
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 16);
+}
+
+#[tokio::test]
+async fn test_deferred_staged_upload() {
+    const BUCKET_NAME: &str = "test_deferred_staged_upload";
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_staging_dir: Some(staging_dir.path().to_owned()),
+        write_staging_max_size: Some(16),
+        defer_staged_uploads: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file_ino, fh, 0, b"hello world", 0, 0, None).await.unwrap();
+
+    // The staging directory can't hold another file as large.
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "other.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let other_ino = dentry.attr.ino;
+    let other_fh = fs.open(other_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    let err = fs
+        .write(other_ino, other_fh, 0, b"hello world", 0, 0, None)
+        .await
+        .expect_err("the staging directory should be full");
+    assert_eq!(err.to_errno(), libc::ENOSPC);
+    fs.release(other_ino, other_fh, 0, None, false).await.unwrap();
+
+    // Closing the file only records its spool file, and the upload completes on release.
+    fs.flush(file_ino, fh, 0, 0).await.unwrap();
+    assert!(
+        client
+            .head_object(BUCKET_NAME, "file.txt", &HeadObjectParams::new())
+            .await
+            .is_err()
+    );
+    assert_eq!(std::fs::read_dir(staging_dir.path()).unwrap().count(), 2);
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    let get = client
+        .get_object(BUCKET_NAME, "file.txt", &GetObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(&get.collect().await.unwrap()[..], b"hello world");
+    assert_eq!(std::fs::read_dir(staging_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_shared_mmap_write() {
+    const BUCKET_NAME: &str = "test_shared_mmap_write";
+    const PAGE_SIZE: usize = 4096;
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_staging_dir: Some(staging_dir.path().to_owned()),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let fh = fs.open(file_ino, OpenFlags::O_RDWR, 0).await.unwrap().fh;
+
+    // Applications size the file before mapping it.
+    let attr = fs
+        .setattr(file_ino, None, None, None, Some(2 * PAGE_SIZE as u64), None)
+        .await
+        .unwrap();
+    assert_eq!(attr.attr.size, 2 * PAGE_SIZE as u64);
+    let page = fs.read(file_ino, fh, 0, PAGE_SIZE as u32, 0, None).await.unwrap();
+    assert_eq!(&page[..], &[0u8; PAGE_SIZE][..]);
+
+    // The kernel writes back the dirty pages before the file is closed, and on unmap.
+    let mut second_page = vec![0u8; PAGE_SIZE];
+    second_page[..5].copy_from_slice(b"world");
+    fs.write(file_ino, fh, PAGE_SIZE as i64, &second_page, FUSE_WRITE_CACHE, 0, None)
+        .await
+        .unwrap();
+    fs.flush(file_ino, fh, 0, 0).await.unwrap();
+    assert!(
+        client
+            .head_object(BUCKET_NAME, "file.bin", &HeadObjectParams::new())
+            .await
+            .is_err(),
+        "the upload of a mapped file waits for it to be unmapped"
+    );
+    let mut first_page = vec![0u8; PAGE_SIZE];
+    first_page[..5].copy_from_slice(b"hello");
+    fs.write(file_ino, fh, 0, &first_page, FUSE_WRITE_CACHE, 0, None)
+        .await
+        .unwrap();
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    let get = client
+        .get_object(BUCKET_NAME, "file.bin", &GetObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(&get.collect().await.unwrap()[..], [first_page, second_page].concat());
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 2 * PAGE_SIZE as u64);
+}
+
+#[tokio::test]
+async fn test_read_while_writing() {
+    const BUCKET_NAME: &str = "test_read_while_writing";
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_staging_dir: Some(staging_dir.path().to_owned()),
+        ..Default::default()
+    };
+    let (_client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let write_fh = fs.open(file_ino, OpenFlags::O_RDWR, 0).await.unwrap().fh;
+    fs.write(file_ino, write_fh, 0, b"hello world", 0, 0, None)
+        .await
+        .unwrap();
+
+    // The data can be read back from the write handle and from other handles before it is uploaded.
+    let read = fs.read(file_ino, write_fh, 6, 32, 0, None).await.unwrap();
+    assert_eq!(&read[..], b"world");
+    let read_fh = fs.open(file_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let read = fs.read(file_ino, read_fh, 0, 5, 0, None).await.unwrap();
+    assert_eq!(&read[..], b"hello");
+    fs.write(file_ino, write_fh, 0, b"HELLO", 0, 0, None).await.unwrap();
+    let read = fs.read(file_ino, read_fh, 0, 32, 0, None).await.unwrap();
+    assert_eq!(&read[..], b"HELLO world");
+
+    // Once uploaded, the file is read from its object.
+    fs.release(file_ino, write_fh, 0, None, false).await.unwrap();
+    let read = fs.read(file_ino, read_fh, 0, 32, 0, None).await.unwrap();
+    assert_eq!(&read[..], b"HELLO world");
+    fs.release(file_ino, read_fh, 0, None, false).await.unwrap();
 }
 
-#[test_case(1024; "small")]
-#[test_case(50 * 1024; "large")]
 #[tokio::test]
-async fn test_sequential_write(write_size: usize) {
-    const BUCKET_NAME: &str = "test_sequential_write";
-    const OBJECT_SIZE: usize = 50 * 1024;
+async fn test_read_while_writing_not_staged() {
+    const BUCKET_NAME: &str = "test_read_while_writing_not_staged";
 
-    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
+    let (_client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
 
-    let mut rng = SmallRng::seed_from_u64(0x12345678 + OBJECT_SIZE as u64);
-    let mut body = vec![0u8; OBJECT_SIZE];
-    rng.fill(&mut body[..]);
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.txt".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let write_fh = fs.open(file_ino, OpenFlags::O_RDWR, 0).await.unwrap().fh;
+    fs.write(file_ino, write_fh, 0, b"hello world", 0, 0, None)
+        .await
+        .unwrap();
 
-    client.add_object("dir1/file1.bin", MockObject::constant(0xa1, 15, ETag::for_tests()));
+    // Streamed uploads don't keep the data written, so it can't be read back.
+    let err = fs.read(file_ino, write_fh, 0, 32, 0, None).await.unwrap_err();
+    assert_eq!(err.to_errno(), libc::EBADF);
+    let err = fs.open(file_ino, OpenFlags::empty(), 0).await.unwrap_err();
+    assert_eq!(err.to_errno(), libc::EPERM);
+    fs.release(file_ino, write_fh, 0, None, false).await.unwrap();
+}
 
-    // Find the dir1 directory
-    let entry = fs.lookup(FUSE_ROOT_INODE, "dir1".as_ref()).await.unwrap();
-    assert_eq!(entry.attr.kind, FileType::Directory);
-    let dir_ino = entry.attr.ino;
+#[tokio::test]
+async fn test_partial_overwrite_by_copy() {
+    const BUCKET_NAME: &str = "test_partial_overwrite_by_copy";
 
-    // Write the object into that directory
-    let mode = libc::S_IFREG | libc::S_IRWXU; // regular file + 0700 permissions
-    let dentry = fs.mknod(dir_ino, "file2.bin".as_ref(), mode, 0, 0).await.unwrap();
-    assert_eq!(dentry.attr.size, 0);
-    let file_ino = dentry.attr.ino;
+    let config = S3FilesystemConfig {
+        allow_overwrite: true,
+        append_by_copy: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    client.add_object("file.txt", MockObject::from(b"hello world"));
 
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let file_ino = entry.attr.ino;
     let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
-
-    let mut offset = 0;
-    for data in body.chunks(write_size) {
-        let written = fs.write(file_ino, fh, offset, data, 0, 0, None).await.unwrap();
-        assert_eq!(written as usize, data.len());
-        offset += written as i64;
-    }
-
+    fs.write(file_ino, fh, 6, b"there", 0, 0, None).await.unwrap();
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 11);
+    fs.write(file_ino, fh, 11, b"!", 0, 0, None).await.unwrap();
     fs.release(file_ino, fh, 0, None, false).await.unwrap();
 
-    // Check that the object made it to S3 as we expected
     let get = client
-        .get_object(BUCKET_NAME, "dir1/file2.bin", &GetObjectParams::new())
+        .get_object(BUCKET_NAME, "file.txt", &GetObjectParams::new())
         .await
         .unwrap();
     let actual = get.collect().await.unwrap();
-    assert_eq!(&actual[..], &body[..]);
+    assert_eq!(&actual[..], b"hello there!");
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 12);
+}
 
-    // And now check that we can read it out of the file system too. We need to emulate the kernel's
-    // handling of ESTALE, which will retry after re-looking-up.
-    let stat = match fs.getattr(file_ino).await {
-        Ok(stat) => stat,
-        Err(e) if e.to_errno() == libc::ESTALE => {
-            let entry = fs.lookup(dir_ino, "file2.bin".as_ref()).await.unwrap();
-            assert_ne!(entry.attr.ino, file_ino);
-            fs.getattr(entry.attr.ino).await.unwrap()
-        }
-        Err(e) => panic!("unexpected getattr failure: {e:?}"),
+#[tokio::test]
+async fn test_append_by_copy() {
+    const BUCKET_NAME: &str = "test_append_by_copy";
+
+    let config = S3FilesystemConfig {
+        append_by_copy: true,
+        ..Default::default()
     };
-    assert_eq!(stat.attr.size, body.len() as u64);
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    client.add_object("file.txt", MockObject::from(b"hello"));
 
-    let dentry = fs.lookup(dir_ino, "file2.bin".as_ref()).await.unwrap();
-    let size = dentry.attr.size as usize;
-    assert_eq!(size, body.len());
-    let file_ino = dentry.attr.ino;
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let file_ino = entry.attr.ino;
+    let fh = fs
+        .open(file_ino, OpenFlags::O_WRONLY | OpenFlags::O_APPEND, 0)
+        .await
+        .unwrap()
+        .fh;
+    let err = fs
+        .write(file_ino, fh, 0, b"goodbye", 0, 0, None)
+        .await
+        .expect_err("writes before the end of the file should fail")
+        .to_errno();
+    assert_eq!(err, libc::EINVAL);
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    assert_eq!(client.multipart_upload_count(), 0);
 
-    // First let's check that we can't write it again
-    let result = fs
-        .open(file_ino, OpenFlags::O_WRONLY, 0)
+    let fh = fs
+        .open(file_ino, OpenFlags::O_RDWR | OpenFlags::O_APPEND, 0)
+        .await
+        .unwrap()
+        .fh;
+    let written = fs.write(file_ino, fh, 5, b" world", 0, 0, None).await.unwrap();
+    assert_eq!(written, 6);
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    let get = client
+        .get_object(BUCKET_NAME, "file.txt", &GetObjectParams::new())
+        .await
+        .unwrap();
+    let actual = get.collect().await.unwrap();
+    assert_eq!(&actual[..], b"hello world");
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 11);
+
+    // Truncating still requires overwrites to be allowed.
+    let err = fs
+        .open(file_ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
         .await
         .expect_err("file should not be overwritable")
         .to_errno();
-    assert_eq!(result, libc::EPERM);
+    assert_eq!(err, libc::EPERM);
+}
 
-    // But read-only should work
-    let fh = fs.open(file_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+#[test_case(false; "streamed")]
+#[test_case(true; "staged")]
+#[tokio::test]
+async fn test_write_after_fsync(staged: bool) {
+    const BUCKET_NAME: &str = "test_write_after_fsync";
 
-    let mut offset = 0;
-    while offset < size {
-        let length = 1024.min(size - offset);
-        let bytes_read = fs
-            .read(file_ino, fh, offset as i64, length as u32, 0, None)
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        write_after_fsync: true,
+        write_staging_dir: staged.then(|| staging_dir.path().to_owned()),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    let get_object = async |client: &MockClient| {
+        let get = client
+            .get_object(BUCKET_NAME, "file.log", &GetObjectParams::new())
             .await
-            .expect("fs read should succeed");
-        assert_eq!(bytes_read.len(), length);
-        assert_eq!(&bytes_read[..], &body[offset..offset + length]);
-        offset += length;
-    }
+            .unwrap();
+        get.collect().await.unwrap()
+    };
 
-    fs.release(file_ino, fh, 0, None, true).await.unwrap();
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.log".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+
+    // Each fsync makes the data written so far visible, and writing can continue after it.
+    fs.write(file_ino, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    fs.fsync(file_ino, fh, false).await.unwrap();
+    assert_eq!(&get_object(&client).await[..], b"hello");
+    fs.write(file_ino, fh, 5, b" world", 0, 0, None).await.unwrap();
+    fs.fsync(file_ino, fh, false).await.unwrap();
+    assert_eq!(&get_object(&client).await[..], b"hello world");
+    fs.write(file_ino, fh, 11, b"!", 0, 0, None).await.unwrap();
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+
+    assert_eq!(&get_object(&client).await[..], b"hello world!");
+    let stat = fs.getattr(file_ino).await.unwrap();
+    assert_eq!(stat.attr.size, 12);
+}
+
+#[test_case(false; "streamed")]
+#[test_case(true; "staged")]
+#[tokio::test]
+async fn test_conditional_overwrite(staged: bool) {
+    const BUCKET_NAME: &str = "test_conditional_overwrite";
+
+    let staging_dir = tempfile::tempdir().unwrap();
+    let config = S3FilesystemConfig {
+        allow_overwrite: true,
+        conditional_overwrite: true,
+        write_staging_dir: staged.then(|| staging_dir.path().to_owned()),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), config);
+    client.add_object("file.txt", MockObject::from(b"hello world"));
+    let get_object = async |client: &MockClient| {
+        let get = client
+            .get_object(BUCKET_NAME, "file.txt", &GetObjectParams::new())
+            .await
+            .unwrap();
+        get.collect().await.unwrap()
+    };
+
+    // Another writer replaces the object while the file is being overwritten, so the overwrite fails.
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let file_ino = entry.attr.ino;
+    let fh = fs
+        .open(file_ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
+        .await
+        .unwrap()
+        .fh;
+    fs.write(file_ino, fh, 0, b"first", 0, 0, None).await.unwrap();
+    client.add_object("file.txt", MockObject::from(b"second"));
+    let err = fs
+        .flush(file_ino, fh, 0, 0)
+        .await
+        .expect_err("the object was changed since the file was opened");
+    assert_eq!(err.to_errno(), libc::ESTALE);
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    assert_eq!(&get_object(&client).await[..], b"second");
+
+    // Overwriting the object no one else changed succeeds.
+    let entry = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap();
+    let file_ino = entry.attr.ino;
+    let fh = fs
+        .open(file_ino, OpenFlags::O_WRONLY | OpenFlags::O_TRUNC, 0)
+        .await
+        .unwrap()
+        .fh;
+    fs.write(file_ino, fh, 0, b"third", 0, 0, None).await.unwrap();
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    assert_eq!(&get_object(&client).await[..], b"third");
 }
 
 #[test_case(-27; "earlier offset")]
@@ -780,6 +2533,143 @@ async fn test_upload_aborted_on_write_failure() {
         .expect("release succeeds (no op)");
 }
 
+#[tokio::test]
+async fn test_write_quota() {
+    const BUCKET_NAME: &str = "test_write_quota";
+
+    let fs_config = S3FilesystemConfig {
+        max_file_size: Some(100),
+        max_write_bytes: Some(150),
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), fs_config);
+    let mode = libc::S_IFREG | libc::S_IRWXU; // regular file + 0700 permissions
+
+    let file_ino = fs
+        .mknod(FUSE_ROOT_INODE, "a.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file_ino, fh, 0, &[0xaa; 80], 0, 0, None).await.unwrap();
+    let err = fs
+        .write(file_ino, fh, 80, &[0xaa; 30], 0, 0, None)
+        .await
+        .expect_err("the file would exceed the maximum file size");
+    assert_eq!(err.to_errno(), libc::EFBIG);
+    let err = fs
+        .setattr(file_ino, None, None, None, Some(101), None)
+        .await
+        .expect_err("the file can't be extended beyond the maximum file size");
+    assert_eq!(err.to_errno(), libc::EFBIG);
+    // The upload is not aborted, so that the file can be written up to the maximum size.
+    fs.write(file_ino, fh, 80, &[0xaa; 20], 0, 0, None).await.unwrap();
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    let head = client
+        .head_object(BUCKET_NAME, "a.bin", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.size, 100);
+
+    let file_ino = fs
+        .mknod(FUSE_ROOT_INODE, "b.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(file_ino, fh, 0, &[0xbb; 50], 0, 0, None).await.unwrap();
+    let err = fs
+        .write(file_ino, fh, 50, &[0xbb; 1], 0, 0, None)
+        .await
+        .expect_err("150 bytes were already written through the mount");
+    assert_eq!(err.to_errno(), libc::EDQUOT);
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    let head = client
+        .head_object(BUCKET_NAME, "b.bin", &HeadObjectParams::new())
+        .await
+        .unwrap();
+    assert_eq!(head.size, 50);
+}
+
+#[tokio::test]
+async fn test_keep_page_cache() {
+    const BUCKET_NAME: &str = "test_keep_page_cache";
+
+    let fs_config = S3FilesystemConfig {
+        keep_page_cache: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), fs_config);
+    client.add_object(
+        "file.txt",
+        MockObject::constant(0xa1, 15, ETag::from_str("test_etag_1").unwrap()),
+    );
+
+    let open_and_release = async || {
+        let ino = fs.lookup(FUSE_ROOT_INODE, "file.txt".as_ref()).await.unwrap().attr.ino;
+        let opened = fs.open(ino, OpenFlags::empty(), 0).await.unwrap();
+        fs.release(ino, opened.fh, 0, None, false).await.unwrap();
+        opened.flags & FOPEN_KEEP_CACHE != 0
+    };
+    assert!(!open_and_release().await, "nothing is cached on the first open");
+    assert!(open_and_release().await, "the object is unchanged");
+
+    client.add_object(
+        "file.txt",
+        MockObject::constant(0xa2, 15, ETag::from_str("test_etag_2").unwrap()),
+    );
+    assert!(!open_and_release().await, "the object was replaced");
+    assert!(open_and_release().await);
+}
+
+#[tokio::test]
+async fn test_direct_io_xattr() {
+    let fs_config = S3FilesystemConfig {
+        direct_io_xattr: true,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_direct_io_xattr", &Default::default(), fs_config);
+    assert!(fs.xattrs_supported());
+    client.add_object("dir/file0", MockObject::constant(0xaa, 10, ETag::for_tests()));
+    client.add_object("file1", MockObject::constant(0xbb, 10, ETag::for_tests()));
+
+    let dir = fs.lookup(FUSE_ROOT_INODE, "dir".as_ref()).await.unwrap().attr.ino;
+    let file0 = fs.lookup(dir, "file0".as_ref()).await.unwrap().attr.ino;
+    let file1 = fs.lookup(FUSE_ROOT_INODE, "file1".as_ref()).await.unwrap().attr.ino;
+    let direct_io = async |ino| {
+        let opened = fs.open(ino, OpenFlags::empty(), 0).await.unwrap();
+        fs.release(ino, opened.fh, 0, None, false).await.unwrap();
+        opened.flags & FOPEN_DIRECT_IO != 0
+    };
+    assert!(!direct_io(file0).await);
+
+    let err = fs
+        .setxattr(file1, DIRECT_IO_XATTR.as_ref(), b"yes")
+        .await
+        .expect_err("only 1 and 0 are valid values");
+    assert_eq!(err.to_errno(), libc::EINVAL);
+
+    fs.setxattr(file1, DIRECT_IO_XATTR.as_ref(), b"1").await.unwrap();
+    assert_eq!(
+        fs.getxattr(file1, DIRECT_IO_XATTR.as_ref()).await.unwrap(),
+        Some(b"1".to_vec())
+    );
+    assert!(direct_io(file1).await);
+    assert!(!direct_io(file0).await, "only set on file1");
+    fs.setxattr(file1, DIRECT_IO_XATTR.as_ref(), b"0").await.unwrap();
+    assert_eq!(fs.getxattr(file1, DIRECT_IO_XATTR.as_ref()).await.unwrap(), None);
+    assert!(!direct_io(file1).await);
+
+    fs.setxattr(dir, DIRECT_IO_XATTR.as_ref(), b"1").await.unwrap();
+    assert!(direct_io(file0).await, "set on the directory of file0");
+    assert_eq!(fs.getxattr(file0, DIRECT_IO_XATTR.as_ref()).await.unwrap(), None);
+    assert!(fs.removexattr(dir, DIRECT_IO_XATTR.as_ref()).await.unwrap());
+    assert!(!fs.removexattr(dir, DIRECT_IO_XATTR.as_ref()).await.unwrap());
+    assert!(!direct_io(file0).await);
+}
+
 #[tokio::test]
 async fn test_upload_aborted_on_fsync_failure() {
     const BUCKET_NAME: &str = "test_upload_aborted_on_fsync_failure";
@@ -1263,6 +3153,90 @@ async fn test_flexible_retrieval_objects() {
     }
 }
 
+#[test_case(ArchivedObjectsPolicy::Unreadable; "unreadable")]
+#[test_case(ArchivedObjectsPolicy::Hidden; "hidden")]
+#[test_case(ArchivedObjectsPolicy::FailReads; "fail reads")]
+#[test_case(ArchivedObjectsPolicy::RestoreStatus; "restore status")]
+#[tokio::test]
+async fn test_archived_objects(policy: ArchivedObjectsPolicy) {
+    let config = S3FilesystemConfig {
+        archived_objects: policy,
+        ..Default::default()
+    };
+    let (client, fs) = make_test_filesystem("test_archived_objects", &Default::default(), config);
+
+    let mut archived = MockObject::from(b"hello world");
+    archived.set_storage_class(Some("GLACIER".to_owned()));
+    client.add_object("archived", archived);
+    let mut restoring = MockObject::from(b"hello world");
+    restoring.set_storage_class(Some("DEEP_ARCHIVE".to_owned()));
+    restoring.set_restored(Some(RestoreStatus::InProgress));
+    client.add_object("restoring", restoring);
+    let mut restored = MockObject::from(b"hello world");
+    restored.set_storage_class(Some("GLACIER".to_owned()));
+    restored.set_restored(Some(RestoreStatus::Restored {
+        expiry: SystemTime::now().add(Duration::from_secs(3600)),
+    }));
+    client.add_object("restored", restored);
+
+    let dir_handle = fs.opendir(FUSE_ROOT_INODE, 0).await.unwrap().fh;
+    let mut reply = Default::default();
+    fs.readdirplus(FUSE_ROOT_INODE, dir_handle, 0, &mut reply)
+        .await
+        .unwrap();
+    fs.releasedir(FUSE_ROOT_INODE, dir_handle, 0).await.unwrap();
+    let names: Vec<_> = reply.entries.iter().skip(2).map(|entry| entry.name.clone()).collect();
+    if policy == ArchivedObjectsPolicy::Hidden {
+        assert_eq!(names, ["restored"]);
+        let err = fs.lookup(FUSE_ROOT_INODE, "archived".as_ref()).await.unwrap_err();
+        assert_eq!(err.to_errno(), libc::ENOENT);
+    } else {
+        assert_eq!(names, ["archived", "restored", "restoring"]);
+    }
+
+    for name in ["archived", "restoring", "restored"] {
+        let Ok(lookup) = fs.lookup(FUSE_ROOT_INODE, name.as_ref()).await else {
+            assert_eq!(policy, ArchivedObjectsPolicy::Hidden);
+            continue;
+        };
+        let readable = name == "restored";
+        let has_permissions = readable || policy != ArchivedObjectsPolicy::Unreadable;
+        assert_eq!(lookup.attr.perm != 0, has_permissions, "{name}");
+
+        match fs.open(lookup.attr.ino, OpenFlags::empty(), 0).await {
+            Ok(open) => {
+                assert!(readable, "{name} should not be readable");
+                fs.release(lookup.attr.ino, open.fh, 0, None, true).await.unwrap();
+            }
+            Err(err) => {
+                assert!(!readable, "{name} should be readable");
+                let expected_errno = if policy == ArchivedObjectsPolicy::Unreadable {
+                    libc::EACCES
+                } else {
+                    libc::ENODATA
+                };
+                assert_eq!(err.to_errno(), expected_errno);
+            }
+        }
+
+        let restore_status = fs
+            .getxattr(lookup.attr.ino, RESTORE_STATUS_XATTR.as_ref())
+            .await
+            .unwrap();
+        let restore_status = restore_status.map(|value| String::from_utf8(value).unwrap());
+        if policy == ArchivedObjectsPolicy::RestoreStatus {
+            let restore_status = restore_status.expect("archived objects should have a restore status");
+            match name {
+                "archived" => assert_eq!(restore_status, "archived"),
+                "restoring" => assert_eq!(restore_status, "restoring"),
+                _ => assert!(restore_status.starts_with("restored until "), "{restore_status}"),
+            }
+        } else {
+            assert_eq!(restore_status, None);
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_readdir_rewind_ordered() {
     let (client, fs) = make_test_filesystem("test_readdir_rewind", &Default::default(), Default::default());
@@ -1702,3 +3676,144 @@ async fn test_rename_support_is_cached() {
     assert_eq!(err.to_errno(), libc::ENOSYS, "rename should again fail with ENOSYS");
     assert_eq!(counter.count(), 1, "The second failed rename should have been cached");
 }
+
+#[tokio::test]
+async fn test_fallocate() {
+    const BUCKET_NAME: &str = "test_fallocate";
+
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let dentry = fs
+        .mknod(FUSE_ROOT_INODE, "file.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap();
+    let file_ino = dentry.attr.ino;
+    let fh = fs.open(file_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+
+    // Preallocating is only a hint of the final size, which doesn't change the size of the file.
+    fs.fallocate(file_ino, fh, 0, 1 << 40, libc::FALLOC_FL_KEEP_SIZE)
+        .await
+        .expect("fallocate should succeed");
+    fs.fallocate(file_ino, fh, 0, 1 << 40, 0)
+        .await
+        .expect("posix_fallocate should succeed");
+    assert_eq!(fs.getattr(file_ino).await.unwrap().attr.size, 0);
+    let err = fs
+        .fallocate(
+            file_ino,
+            fh,
+            0,
+            4096,
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+        )
+        .await
+        .expect_err("punching holes is not supported");
+    assert_eq!(err.to_errno(), libc::EOPNOTSUPP);
+
+    fs.write(file_ino, fh, 0, b"hello", 0, 0, None).await.unwrap();
+    fs.release(file_ino, fh, 0, None, false).await.unwrap();
+    assert!(client.contains_key("file.bin"));
+
+    let fh = fs.open(file_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let err = fs
+        .fallocate(file_ino, fh, 0, 4096, 0)
+        .await
+        .expect_err("read handles can't be preallocated");
+    assert_eq!(err.to_errno(), libc::EBADF);
+}
+
+#[tokio::test]
+async fn test_copy_file_range() {
+    const BUCKET_NAME: &str = "test_copy_file_range";
+    const CHUNK_SIZE: u64 = 6 * 1024 * 1024;
+
+    let (client, fs) = make_test_filesystem(BUCKET_NAME, &Default::default(), Default::default());
+    let source = MockObject::ramp(0xaa, 2 * CHUNK_SIZE as usize + 10, ETag::for_tests());
+    client.add_object("source.bin", source.clone());
+    let copies = client.new_counter(Operation::UploadPartCopy);
+
+    let source_ino = fs
+        .lookup(FUSE_ROOT_INODE, "source.bin".as_ref())
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let source_fh = fs.open(source_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let mode = libc::S_IFREG | libc::S_IRWXU;
+    let copy_ino = fs
+        .mknod(FUSE_ROOT_INODE, "copy.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let copy_fh = fs.open(copy_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+
+    // Copy the file in chunks, as the kernel splits large copies, until the end of the source.
+    let mut offset = 0;
+    loop {
+        let len = fs
+            .copy_file_range(
+                source_ino,
+                source_fh,
+                offset as i64,
+                copy_ino,
+                copy_fh,
+                offset as i64,
+                CHUNK_SIZE,
+                0,
+            )
+            .await
+            .expect("copy should succeed");
+        if len == 0 {
+            break;
+        }
+        offset += len as u64;
+    }
+    assert_eq!(offset, source.len() as u64);
+    assert_eq!(fs.getattr(copy_ino).await.unwrap().attr.size, offset);
+    fs.write(copy_ino, copy_fh, offset as i64, b"!", 0, 0, None)
+        .await
+        .unwrap();
+    assert!(
+        !client.contains_key("copy.bin"),
+        "the copy should only be uploaded on close"
+    );
+    fs.release(copy_ino, copy_fh, 0, None, false).await.unwrap();
+
+    assert_eq!(copies.count(), 2);
+    let fh = fs.open(copy_ino, OpenFlags::empty(), 0).await.unwrap().fh;
+    let mut body = Vec::new();
+    loop {
+        let data = fs
+            .read(copy_ino, fh, body.len() as i64, 1024 * 1024, 0, None)
+            .await
+            .unwrap();
+        if data.is_empty() {
+            break;
+        }
+        body.extend_from_slice(&data);
+    }
+    fs.release(copy_ino, fh, 0, None, false).await.unwrap();
+    let mut expected = source.read(0, source.len()).to_vec();
+    expected.push(b'!');
+    assert_eq!(&body[..], &expected[..]);
+
+    // Files already written to can't be copied to server-side.
+    let other_ino = fs
+        .mknod(FUSE_ROOT_INODE, "other.bin".as_ref(), mode, 0, 0)
+        .await
+        .unwrap()
+        .attr
+        .ino;
+    let other_fh = fs.open(other_ino, OpenFlags::O_WRONLY, 0).await.unwrap().fh;
+    fs.write(other_ino, other_fh, 0, b"hello", 0, 0, None).await.unwrap();
+    let err = fs
+        .copy_file_range(source_ino, source_fh, 0, other_ino, other_fh, 5, 1024, 0)
+        .await
+        .expect_err("copy should not be supported");
+    assert_eq!(err.to_errno(), libc::EOPNOTSUPP);
+    fs.write(other_ino, other_fh, 5, b" world", 0, 0, None).await.unwrap();
+    fs.release(other_ino, other_fh, 0, None, false).await.unwrap();
+    fs.release(source_ino, source_fh, 0, None, false).await.unwrap();
+    assert!(client.contains_key("other.bin"));
+}
@@ -144,6 +144,15 @@ fn disk_cache_write_read(key_suffix: &str, key_size: usize, object_size: usize)
         cache_directory: cache_dir.path().to_path_buf(),
         block_size: CACHE_BLOCK_SIZE,
         limit: Default::default(),
+        eviction_policy: Default::default(),
+        shared: false,
+        persistent: false,
+        admission_policy: Default::default(),
+        pinning: None,
+        encryption_key: None,
+        compression_level: None,
+        io_backend: Default::default(),
+        account: None,
     };
     let pool = PagedPool::new_with_candidate_sizes([CACHE_BLOCK_SIZE as usize, CLIENT_PART_SIZE]);
     let cache = DiskDataCache::new(cache_config, pool.clone());
@@ -198,6 +207,15 @@ async fn disk_cache_read_empty() {
         cache_directory: cache_dir.path().to_path_buf(),
         block_size: CACHE_BLOCK_SIZE,
         limit: Default::default(),
+        eviction_policy: Default::default(),
+        shared: false,
+        persistent: false,
+        admission_policy: Default::default(),
+        pinning: None,
+        encryption_key: None,
+        compression_level: None,
+        io_backend: Default::default(),
+        account: None,
     };
     let pool = PagedPool::new_with_candidate_sizes([CACHE_BLOCK_SIZE as usize]);
     let cache = DiskDataCache::new(cache_config, pool);
@@ -495,6 +513,15 @@ fn available_space_cache_limit_test_mock() {
             limit: CacheLimit::AvailableSpace {
                 min_ratio: DEFAULT_CACHE_MIN_AVAILABLE_RATIO,
             },
+            eviction_policy: Default::default(),
+            shared: false,
+            persistent: false,
+            admission_policy: Default::default(),
+            pinning: None,
+            encryption_key: None,
+            compression_level: None,
+            io_backend: Default::default(),
+            account: None,
         };
         CacheTestWrapper::new(DiskDataCache::new(cache_config, pool))
     })("available_space_cache_limit_test", TestSessionConfig::default());
@@ -241,6 +241,15 @@ rusty_fork_test! {
                 cache_directory: cache_dir.path().to_path_buf(),
                 block_size,
                 limit: mountpoint_s3_fs::data_cache::CacheLimit::TotalSize { max_size: 2 * 1024 * 1024 },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             };
             DiskDataCache::new(cache_config, pool)
         })("test_cache_metrics", TestSessionConfig::default());
@@ -287,6 +296,15 @@ rusty_fork_test! {
                 cache_directory: cache_dir.path().to_path_buf(),
                 block_size,
                 limit: mountpoint_s3_fs::data_cache::CacheLimit::TotalSize { max_size: 2 * 1024 * 1024 },
+                eviction_policy: Default::default(),
+                shared: false,
+                persistent: false,
+                admission_policy: Default::default(),
+                pinning: None,
+                encryption_key: None,
+                compression_level: None,
+                io_backend: Default::default(),
+                account: None,
             };
             let disk_cache = Arc::new(DiskDataCache::new(disk_config, pool.clone()));
 
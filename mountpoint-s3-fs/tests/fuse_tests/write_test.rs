@@ -1418,10 +1418,12 @@ fn concurrent_open_for_write_test(max_files: usize) {
 #[derive(Clone, Copy)]
 enum UploadChecksumsMode {
     Enabled,
+    Sha256,
     Disabled,
 }
 
 const CHECKSUMS_ENABLED: UploadChecksumsMode = UploadChecksumsMode::Enabled;
+const CHECKSUMS_SHA256: UploadChecksumsMode = UploadChecksumsMode::Sha256;
 const CHECKSUMS_DISABLED: UploadChecksumsMode = UploadChecksumsMode::Disabled;
 
 fn write_checksums_test(
@@ -1434,7 +1436,11 @@ fn write_checksums_test(
 
     let config = TestSessionConfig {
         filesystem_config: S3FilesystemConfig {
-            use_upload_checksums: matches!(checksums_mode, UploadChecksumsMode::Enabled),
+            upload_checksum_algorithm: match checksums_mode {
+                UploadChecksumsMode::Enabled => Some(ChecksumAlgorithm::Crc32c),
+                UploadChecksumsMode::Sha256 => Some(ChecksumAlgorithm::Sha256),
+                UploadChecksumsMode::Disabled => None,
+            },
             ..Default::default()
         }
         .upload_mode(upload_mode),
@@ -1479,6 +1485,16 @@ fn write_checksums_test(
                 });
             assert!(object_crc32c || parts_crc32c, "crc32c is used for trailing checksums");
         }
+        UploadChecksumsMode::Sha256 => {
+            let object_sha256 = object_checksum.is_some_and(|checksum| checksum.checksum_sha256.is_some());
+            let parts_sha256 = !part_checksums.is_empty()
+                && part_checksums.iter().all(|checksum| {
+                    checksum
+                        .as_ref()
+                        .is_some_and(|checksum| checksum.checksum_sha256.is_some())
+                });
+            assert!(object_sha256 || parts_sha256, "sha256 is used for trailing checksums");
+        }
         UploadChecksumsMode::Disabled => {
             // If no checksum was sent with the PutObject request, S3 automatically uses CRC-64NVME.
             // We'll ignore it for the test below.
@@ -1504,7 +1520,7 @@ fn write_checksums_test(
 }
 
 #[cfg(feature = "s3_tests")]
-#[test_matrix([CHECKSUMS_ENABLED, CHECKSUMS_DISABLED])]
+#[test_matrix([CHECKSUMS_ENABLED, CHECKSUMS_SHA256, CHECKSUMS_DISABLED])]
 fn write_checksums_test_s3(checksums_mode: UploadChecksumsMode) {
     write_checksums_test(fuse::s3_session::new, checksums_mode, ATOMIC_UPLOAD);
 }
@@ -1515,7 +1531,7 @@ fn write_checksums_test_s3_incremental_upload(checksums_mode: UploadChecksumsMod
     write_checksums_test(fuse::s3_session::new, checksums_mode, INCREMENTAL_UPLOAD);
 }
 
-#[test_matrix([CHECKSUMS_ENABLED, CHECKSUMS_DISABLED], [ATOMIC_UPLOAD, INCREMENTAL_UPLOAD])]
+#[test_matrix([CHECKSUMS_ENABLED, CHECKSUMS_SHA256, CHECKSUMS_DISABLED], [ATOMIC_UPLOAD, INCREMENTAL_UPLOAD])]
 fn write_checksums_test_mock(checksums_mode: UploadChecksumsMode, upload_mode: UploadMode) {
     write_checksums_test(fuse::mock_session::new, checksums_mode, upload_mode);
 }
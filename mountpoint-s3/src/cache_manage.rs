@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context as _;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use mountpoint_s3_fs::data_cache::{
+    CacheEncryptionKey, CacheLimit, CachedBlockInfo, DiskDataCache, DiskDataCacheConfig, ManagedCacheDir,
+};
+use mountpoint_s3_fs::memory::PagedPool;
+
+use crate::build_info;
+use crate::run::env_unstable_cache_key;
+
+/// Arguments of the `mount-s3 cache ls|stats|evict` commands, which inspect and manage the local data cache of mounts
+/// outside of them.
+#[derive(Parser, Debug)]
+#[clap(
+    name = "mount-s3 cache",
+    about = "Inspect and manage the local disk cache of mounts",
+    version = build_info::FULL_VERSION,
+    after_help = "\
+Pass the same `--cache` directory as the mounts using it. The cache doesn't record the bucket objects were read from, \
+so keys are listed and matched as they are in their bucket, including the prefix of the mount. Only caches kept with \
+`--shared-cache` or `--persist-cache`, or the cache of a mount still running, can be inspected, as mounts otherwise \
+clean up their cache directory when they exit.",
+)]
+pub struct CacheManageArgs {
+    #[clap(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List the cached objects, with the number and total size of their cached blocks, and the age of the most
+    /// recently written one
+    Ls {
+        #[clap(flatten)]
+        cache: CacheDirArgs,
+
+        #[clap(
+            long,
+            help = "Only list the objects whose key starts with this prefix",
+            value_name = "PREFIX"
+        )]
+        prefix: Option<String>,
+    },
+    /// Report the number and total size of the cached blocks and objects, for each block size
+    Stats {
+        #[clap(flatten)]
+        cache: CacheDirArgs,
+    },
+    /// Remove the blocks of the given objects from the cache
+    #[clap(group(ArgGroup::new("targets").required(true).multiple(true).args(["keys", "prefixes"])))]
+    Evict {
+        #[clap(flatten)]
+        cache: CacheDirArgs,
+
+        #[clap(long = "key", help = "Evict the object with this key", value_name = "KEY")]
+        keys: Vec<String>,
+
+        #[clap(
+            long = "prefix",
+            help = "Evict the objects whose key starts with this prefix",
+            value_name = "PREFIX"
+        )]
+        prefixes: Vec<String>,
+    },
+}
+
+/// The cache directory inspected by a [CacheCommand].
+#[derive(Args, Debug)]
+pub struct CacheDirArgs {
+    #[clap(long, help = "Local disk cache directory of the mounts", value_name = "DIRECTORY")]
+    pub cache: PathBuf,
+
+    #[clap(
+        long,
+        help = "Key file the blocks of the cache are encrypted with. Without it, the objects of encrypted blocks are \
+                unknown",
+        value_name = "PATH"
+    )]
+    pub cache_encryption_key_file: Option<PathBuf>,
+}
+
+impl CacheManageArgs {
+    /// Whether the command line runs one of the `mount-s3 cache ls|stats|evict` commands.
+    pub fn is_cache_manage_command(args: &[impl AsRef<std::ffi::OsStr>]) -> bool {
+        args.len() > 2
+            && args[1].as_ref() == "cache"
+            && ["ls", "stats", "evict"].map(AsRef::as_ref).contains(&args[2].as_ref())
+    }
+}
+
+impl CacheDirArgs {
+    /// Open the caches in the directory, one for each block size written to it.
+    fn open_caches(&self) -> anyhow::Result<Vec<(u64, DiskDataCache)>> {
+        let encryption_key = match &self.cache_encryption_key_file {
+            Some(path) => Some(
+                CacheEncryptionKey::from_file(path)
+                    .with_context(|| format!("invalid cache encryption key file {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let cache_directory = ManagedCacheDir::managed_path(&self.cache, env_unstable_cache_key().as_deref());
+        let block_sizes = DiskDataCache::block_sizes_in(&cache_directory)
+            .with_context(|| format!("failed to read cache directory {}", cache_directory.display()))?;
+        Ok(Self::open_caches_in(&cache_directory, &block_sizes, encryption_key))
+    }
+
+    /// Open the caches of the given block sizes in `cache_directory`.
+    fn open_caches_in(
+        cache_directory: &Path,
+        block_sizes: &[u64],
+        encryption_key: Option<CacheEncryptionKey>,
+    ) -> Vec<(u64, DiskDataCache)> {
+        block_sizes
+            .iter()
+            .map(|&block_size| {
+                let config = DiskDataCacheConfig {
+                    cache_directory: cache_directory.to_path_buf(),
+                    block_size,
+                    // Without a limit, the cache doesn't track the usage of the blocks, which the mounts do.
+                    limit: CacheLimit::Unbounded,
+                    eviction_policy: Default::default(),
+                    shared: false,
+                    persistent: false,
+                    admission_policy: Default::default(),
+                    pinning: None,
+                    encryption_key: encryption_key.clone(),
+                    // Compressed blocks are read either way.
+                    compression_level: None,
+                    io_backend: Default::default(),
+                    account: None,
+                };
+                let pool = PagedPool::new_with_candidate_sizes([block_size as usize]);
+                (block_size, DiskDataCache::new(config, pool))
+            })
+            .collect()
+    }
+}
+
+/// The cached blocks of an object.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CachedObject {
+    blocks: usize,
+    size: u64,
+    /// When the most recently written block was written.
+    last_written: Option<SystemTime>,
+}
+
+/// The cached objects, by key and ETag, and the number of blocks whose object is unknown.
+fn group_by_object<'a>(
+    blocks: impl IntoIterator<Item = &'a CachedBlockInfo>,
+) -> (BTreeMap<(String, String), CachedObject>, usize) {
+    let mut objects: BTreeMap<_, CachedObject> = BTreeMap::new();
+    let mut unknown = 0;
+    for block in blocks {
+        let Some(object_id) = &block.object_id else {
+            unknown += 1;
+            continue;
+        };
+        let key = (object_id.key().to_owned(), object_id.etag().as_str().to_owned());
+        let object = objects.entry(key).or_default();
+        object.blocks += 1;
+        object.size += block.size;
+        object.last_written = object.last_written.max(Some(block.modified));
+    }
+    (objects, unknown)
+}
+
+/// Whether the block belongs to one of the objects to evict.
+fn should_evict(block: &CachedBlockInfo, keys: &[String], prefixes: &[String]) -> bool {
+    let Some(object_id) = &block.object_id else {
+        return false;
+    };
+    let key = object_id.key();
+    keys.iter().any(|k| k == key) || prefixes.iter().any(|prefix| key.starts_with(prefix.as_str()))
+}
+
+/// Format the time elapsed since `time` with its largest unit, like `3h`.
+fn format_age(time: Option<SystemTime>, now: SystemTime) -> String {
+    let Some(time) = time else {
+        return "-".to_owned();
+    };
+    let secs = now.duration_since(time).unwrap_or(Duration::ZERO).as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Run a `mount-s3 cache ls|stats|evict` command.
+pub fn manage_cache(args: CacheManageArgs) -> anyhow::Result<()> {
+    let now = SystemTime::now();
+    match args.command {
+        CacheCommand::Ls { cache, prefix } => {
+            let caches = cache.open_caches()?;
+            let blocks: Vec<_> = caches.iter().flat_map(|(_, cache)| cache.list_blocks()).collect();
+            let (objects, unknown) = group_by_object(&blocks);
+            println!("{:>14} {:>8} {:>6}  {:<34} KEY", "SIZE", "BLOCKS", "AGE", "ETAG");
+            for ((key, etag), object) in &objects {
+                if prefix.as_ref().is_some_and(|prefix| !key.starts_with(prefix.as_str())) {
+                    continue;
+                }
+                println!(
+                    "{:>14} {:>8} {:>6}  {:<34} {}",
+                    object.size,
+                    object.blocks,
+                    format_age(object.last_written, now),
+                    etag,
+                    key
+                );
+            }
+            if unknown > 0 {
+                println!("{unknown} blocks could not be read, they may be encrypted or written by another version");
+            }
+        }
+        CacheCommand::Stats { cache } => {
+            let caches = cache.open_caches()?;
+            if caches.is_empty() {
+                println!("The cache is empty");
+            }
+            for (block_size, cache) in &caches {
+                let blocks = cache.list_blocks();
+                let (objects, unknown) = group_by_object(&blocks);
+                let size: u64 = blocks.iter().map(|block| block.size).sum();
+                let oldest = blocks.iter().map(|block| block.modified).min();
+                println!("Blocks of {block_size} bytes:");
+                println!("  objects: {}", objects.len());
+                println!("  blocks: {}", blocks.len());
+                println!("  size: {size} bytes");
+                println!("  unreadable blocks: {unknown}");
+                println!("  oldest block: {}", format_age(oldest, now));
+            }
+        }
+        CacheCommand::Evict { cache, keys, prefixes } => {
+            let caches = cache.open_caches()?;
+            let mut evicted = Vec::new();
+            for (_, cache) in &caches {
+                for block in cache.list_blocks() {
+                    if should_evict(&block, &keys, &prefixes) {
+                        cache.evict_block(&block);
+                        evicted.push(block);
+                    }
+                }
+            }
+            let (objects, _) = group_by_object(&evicted);
+            let size: u64 = evicted.iter().map(|block| block.size).sum();
+            println!(
+                "Evicted {} blocks ({size} bytes) of {} objects",
+                evicted.len(),
+                objects.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use mountpoint_s3_client::types::ETag;
+    use mountpoint_s3_fs::data_cache::{ChecksummedBytes, DataCache};
+    use mountpoint_s3_fs::object::ObjectId;
+
+    use super::*;
+
+    #[test]
+    fn test_is_cache_manage_command() {
+        assert!(CacheManageArgs::is_cache_manage_command(&[
+            "mount-s3", "cache", "ls", "--cache", "/tmp"
+        ]));
+        assert!(CacheManageArgs::is_cache_manage_command(&[
+            "mount-s3", "cache", "evict"
+        ]));
+        assert!(!CacheManageArgs::is_cache_manage_command(&[
+            "mount-s3", "cache", "warm", "bucket"
+        ]));
+        assert!(!CacheManageArgs::is_cache_manage_command(&[
+            "mount-s3", "bucket", "dir"
+        ]));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = CacheManageArgs::try_parse_from(["mount-s3 cache", "ls", "--cache", "/tmp/cache", "--prefix", "a/"])
+            .unwrap();
+        let CacheCommand::Ls { cache, prefix } = args.command else {
+            panic!("unexpected command: {:?}", args.command);
+        };
+        assert_eq!(cache.cache, PathBuf::from("/tmp/cache"));
+        assert_eq!(prefix.as_deref(), Some("a/"));
+
+        let args = CacheManageArgs::try_parse_from([
+            "mount-s3 cache",
+            "evict",
+            "--cache",
+            "/tmp/cache",
+            "--key",
+            "a",
+            "--key",
+            "b",
+            "--prefix",
+            "c/",
+        ])
+        .unwrap();
+        let CacheCommand::Evict { keys, prefixes, .. } = args.command else {
+            panic!("unexpected command: {:?}", args.command);
+        };
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(prefixes, ["c/"]);
+
+        let missing_targets = CacheManageArgs::try_parse_from(["mount-s3 cache", "evict", "--cache", "/tmp/cache"]);
+        assert!(missing_targets.is_err(), "evict requires keys or prefixes");
+        let missing_cache = CacheManageArgs::try_parse_from(["mount-s3 cache", "stats"]);
+        assert!(missing_cache.is_err());
+    }
+
+    #[test]
+    fn test_list_and_evict() {
+        let parent = tempfile::tempdir().unwrap();
+        let cache_dir_args = CacheDirArgs {
+            cache: parent.path().to_path_buf(),
+            cache_encryption_key_file: None,
+        };
+        assert!(cache_dir_args.open_caches().unwrap().is_empty());
+
+        let cache_directory = ManagedCacheDir::managed_path(parent.path(), env_unstable_cache_key().as_deref());
+        for block_size in [4, 8] {
+            let (_, cache) = &CacheDirArgs::open_caches_in(&cache_directory, &[block_size], None)[0];
+            for key in ["a/1", "a/2", "b"] {
+                let object_id = ObjectId::new(key.to_owned(), ETag::for_tests());
+                let data = ChecksummedBytes::new("Foo".into());
+                block_on(cache.put_block(object_id, 0, 0, data, 3)).unwrap();
+            }
+        }
+
+        let caches = cache_dir_args.open_caches().unwrap();
+        assert_eq!(
+            caches.iter().map(|(block_size, _)| *block_size).collect::<Vec<_>>(),
+            [4, 8]
+        );
+        let blocks: Vec<_> = caches.iter().flat_map(|(_, cache)| cache.list_blocks()).collect();
+        let (objects, unknown) = group_by_object(&blocks);
+        assert_eq!(unknown, 0);
+        assert_eq!(
+            objects.keys().map(|(key, _)| key.as_str()).collect::<Vec<_>>(),
+            ["a/1", "a/2", "b"]
+        );
+        assert!(objects.values().all(|object| object.blocks == 2));
+
+        let keys = ["b".to_owned()];
+        let prefixes = ["a/".to_owned()];
+        assert_eq!(blocks.iter().filter(|block| should_evict(block, &keys, &[])).count(), 2);
+        assert_eq!(
+            blocks
+                .iter()
+                .filter(|block| should_evict(block, &[], &prefixes))
+                .count(),
+            4
+        );
+        assert_eq!(
+            blocks
+                .iter()
+                .filter(|block| should_evict(block, &["a".to_owned()], &[]))
+                .count(),
+            0
+        );
+
+        manage_cache(CacheManageArgs {
+            command: CacheCommand::Evict {
+                cache: cache_dir_args,
+                keys: Vec::new(),
+                prefixes: prefixes.to_vec(),
+            },
+        })
+        .unwrap();
+        let cache_dir_args = CacheDirArgs {
+            cache: parent.path().to_path_buf(),
+            cache_encryption_key_file: None,
+        };
+        let caches = cache_dir_args.open_caches().unwrap();
+        let blocks: Vec<_> = caches.iter().flat_map(|(_, cache)| cache.list_blocks()).collect();
+        let (objects, _) = group_by_object(&blocks);
+        assert_eq!(objects.keys().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = SystemTime::now();
+        assert_eq!(format_age(None, now), "-");
+        assert_eq!(format_age(Some(now), now), "0s");
+        assert_eq!(format_age(Some(now - Duration::from_secs(90)), now), "1m");
+        assert_eq!(format_age(Some(now - Duration::from_secs(7200)), now), "2h");
+        assert_eq!(format_age(Some(now - Duration::from_secs(3 * 86400)), now), "3d");
+        assert_eq!(format_age(Some(now + Duration::from_secs(10)), now), "0s");
+    }
+}
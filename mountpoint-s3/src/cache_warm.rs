@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use anyhow::{Context as _, anyhow};
+use clap::{Parser, value_parser};
+use futures::executor::block_on;
+use mountpoint_s3_fs::data_cache::{AdmissionPolicy, CacheWarmer, CacheWarmerConfig, ManagedCacheDir, WarmTarget};
+use mountpoint_s3_fs::logging::init_logging;
+
+use crate::build_info;
+use crate::cli::CliArgs;
+use crate::run::{ClientBuilder, create_memory_pool, env_unstable_cache_key};
+
+/// Arguments of the `mount-s3 cache warm` command, which writes objects to the data cache of a mount ahead of a
+/// workload reading them.
+#[derive(Parser, Debug)]
+#[clap(
+    name = "mount-s3 cache warm",
+    about = "Pre-populate the data cache of a mount with the objects listed in a manifest",
+    version = build_info::FULL_VERSION,
+    after_help = "\
+Pass the same bucket, directory and options as the mount whose cache to warm. A local cache must be either shared \
+with `--shared-cache`, with the mount started before the cache is warmed, or kept with `--persist-cache`, with the \
+mount started after the cache is warmed.",
+)]
+pub struct CacheWarmArgs {
+    #[clap(
+        long,
+        help = "File listing the keys to warm, one per line, relative to the mounted prefix. Lines ending with '/' \
+                are prefixes, whose objects are all warmed",
+        value_name = "FILE"
+    )]
+    pub manifest: PathBuf,
+
+    #[clap(
+        long,
+        help = "Also look up the warmed objects through the mount point, to populate the metadata cache of the mount"
+    )]
+    pub warm_metadata: bool,
+
+    #[clap(
+        long,
+        help = "Number of objects to warm at the same time",
+        default_value = "16",
+        value_name = "N",
+        value_parser = value_parser!(u64).range(1..),
+    )]
+    pub warm_concurrency: u64,
+
+    #[clap(flatten)]
+    pub mount_args: CliArgs,
+}
+
+impl CacheWarmArgs {
+    /// Whether the command line runs the `mount-s3 cache warm` command.
+    pub fn is_cache_warm_command(args: &[impl AsRef<std::ffi::OsStr>]) -> bool {
+        args.len() > 2 && args[1].as_ref() == "cache" && args[2].as_ref() == "warm"
+    }
+}
+
+/// Warm the data cache configured by `args` with the objects of its manifest.
+pub fn warm_cache(client_builder: impl ClientBuilder, args: CacheWarmArgs) -> anyhow::Result<()> {
+    let mut logging_config = args.mount_args.make_logging_config();
+    logging_config.log_to_stdout = true;
+    let _logging = init_logging(logging_config).context("failed to initialize logging")?;
+    tracing::info!("mount-s3 {}", build_info::FULL_VERSION);
+    tracing::debug!("{:?}", args);
+
+    let manifest = File::open(&args.manifest).with_context(|| format!("failed to open {:?}", args.manifest))?;
+    let targets = WarmTarget::parse_manifest(BufReader::new(manifest))
+        .with_context(|| format!("failed to read {:?}", args.manifest))?;
+
+    let mount_args = &args.mount_args;
+    let sse = mount_args.server_side_encryption()?;
+    let client_config = mount_args.client_config(build_info::FULL_VERSION);
+    let pool = create_memory_pool(mount_args, &client_config);
+    let s3_path = mount_args.s3_path()?;
+    let (client, runtime, s3_personality) =
+        client_builder.build(client_config, pool.clone(), &s3_path, mount_args.personality())?;
+
+    let filesystem_config = mount_args.filesystem_config(sse.clone(), s3_personality);
+    let mut data_cache_config = mount_args.data_cache_config(sse)?;
+    if data_cache_config.disk_cache_config.is_none() && data_cache_config.express_cache_config.is_none() {
+        return Err(anyhow!("no data cache is configured, set `--cache` or `--cache-xz`"));
+    }
+    // Keep the cache directory locked until all the blocks are written to it.
+    let _managed_cache_dir = match &mut data_cache_config.disk_cache_config {
+        Some(disk_cache_config) => {
+            let cache_key = env_unstable_cache_key();
+            let managed_cache_dir = if disk_cache_config.shared {
+                ManagedCacheDir::new_shared_from_parent_with_cache_key(
+                    &disk_cache_config.cache_directory,
+                    cache_key.as_deref(),
+                    false,
+                )
+            } else if disk_cache_config.persistent {
+                ManagedCacheDir::new_from_parent_with_cache_key(
+                    &disk_cache_config.cache_directory,
+                    cache_key.as_deref(),
+                    false,
+                )
+            } else {
+                return Err(anyhow!(
+                    "warming a local cache requires `--shared-cache` or `--persist-cache`, as mounts otherwise clean \
+                     up their cache directory"
+                ));
+            }
+            .context("failed to create cache directory")?;
+            disk_cache_config.cache_directory = managed_cache_dir.as_path_buf();
+            // The blocks are only read once, but written for the mount to read them.
+            disk_cache_config.admission_policy = AdmissionPolicy::All;
+            Some(managed_cache_dir)
+        }
+        None => None,
+    };
+
+    let config = CacheWarmerConfig {
+        concurrency: args.warm_concurrency as usize,
+        prefetcher_config: filesystem_config.prefetcher_config,
+        mem_limit: filesystem_config.mem_limit,
+        metadata_mount_point: args.warm_metadata.then(|| mount_args.mount_point.clone()),
+    };
+    let warmer = CacheWarmer::new(client, s3_path, data_cache_config, runtime, pool, config)?;
+    let stats = block_on(warmer.warm(&targets));
+    println!(
+        "Warmed {} objects ({} bytes) in the cache of {}",
+        stats.objects,
+        stats.bytes,
+        mount_args.bucket_description()?
+    );
+    if stats.failures > 0 {
+        return Err(anyhow!("failed to warm {} objects or prefixes", stats.failures));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cache_warm_command() {
+        assert!(CacheWarmArgs::is_cache_warm_command(&[
+            "mount-s3", "cache", "warm", "bucket"
+        ]));
+        assert!(!CacheWarmArgs::is_cache_warm_command(&["mount-s3", "bucket", "dir"]));
+        assert!(!CacheWarmArgs::is_cache_warm_command(&["mount-s3", "cache"]));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = CacheWarmArgs::try_parse_from([
+            "mount-s3 cache warm",
+            "--manifest",
+            "keys.txt",
+            "--warm-metadata",
+            "bucket",
+            "dir",
+            "--cache",
+            "/tmp/cache",
+            "--shared-cache",
+        ])
+        .unwrap();
+        assert_eq!(args.manifest, PathBuf::from("keys.txt"));
+        assert!(args.warm_metadata);
+        assert_eq!(args.warm_concurrency, 16);
+        assert!(args.mount_args.shared_cache);
+
+        let missing_manifest = CacheWarmArgs::try_parse_from(["mount-s3 cache warm", "bucket", "dir"]);
+        assert!(missing_manifest.is_err());
+    }
+}
@@ -1,21 +1,44 @@
 use std::fmt::Debug;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::{Context as _, anyhow};
 use clap::{ArgGroup, Parser, ValueEnum, value_parser};
+use futures::executor::block_on;
+use mountpoint_s3_client::ObjectClient;
+#[cfg(feature = "manifest")]
+use mountpoint_s3_client::checksums::crc32c_from_base64;
 use mountpoint_s3_client::config::{AWSCRT_LOG_TARGET, AddressingStyle, S3ClientAuthConfig};
 use mountpoint_s3_client::instance_info::InstanceInfo;
+use mountpoint_s3_client::types::{ChecksumAlgorithm, MAX_PRESIGNED_URL_EXPIRATION, ObjectTags};
 use mountpoint_s3_client::user_agent::UserAgent;
-use mountpoint_s3_fs::data_cache::{CacheLimit, DataCacheConfig, DiskDataCacheConfig, ExpressDataCacheConfig};
-use mountpoint_s3_fs::fs::{CacheConfig, ServerSideEncryption, TimeToLive};
+#[cfg(feature = "manifest")]
+use mountpoint_s3_fs::checksums::Crc32cBase64;
+use mountpoint_s3_fs::data_cache::{
+    AdmissionPolicy, CacheAccount, CacheEncryptionKey, CacheLimit, DEFAULT_CACHE_COMPRESSION_LEVEL, DataCacheConfig,
+    DiskDataCacheConfig, DiskIoBackend, EvictionPolicy, ExpressDataCacheConfig, MemoryCacheConfig, PeerCacheConfig,
+    PinningConfig, PrefixMetricsConfig, WriteThroughConfig,
+};
+use mountpoint_s3_fs::fs::{
+    ArchivedObjectsPolicy, CacheConfig, KeyFilter, MetadataTtlRule, PrefixOverrides, ServerSideEncryption,
+    ShadowingPolicy, StatfsCapacity, StorageClassRule, StorageClassRules, TimeToLive,
+};
 use mountpoint_s3_fs::fuse::config::{FuseOptions, FuseSessionConfig, MountPoint};
 use mountpoint_s3_fs::logging::{LoggingConfig, prepare_log_file_name};
+#[cfg(feature = "manifest")]
+use mountpoint_s3_fs::manifest::{ChannelConfig, Manifest, ManifestMetablock, ingest_manifest};
 use mountpoint_s3_fs::mem_limiter::MINIMUM_MEM_LIMIT;
 use mountpoint_s3_fs::s3::config::{ClientConfig, PartConfig, TargetThroughputSetting};
-use mountpoint_s3_fs::s3::{Bucket, Prefix, S3Path, S3PathError, S3Personality};
-use mountpoint_s3_fs::{S3FilesystemConfig, autoconfigure, metrics};
+use mountpoint_s3_fs::s3::{Bucket, KeyDelimiter, KeyMapping, Prefix, S3Path, S3PathError, S3Personality};
+use mountpoint_s3_fs::upload::{ContentTypeMapping, ContentTypes};
+use mountpoint_s3_fs::{
+    DirectoryRenameConfig, InodeTable, InventoryConfig, ReaddirReadahead, RecursiveDeleteConfig, Runtime,
+    S3FilesystemConfig, Snapshot, TrashConfig, autoconfigure, metrics,
+};
 use sysinfo::{RefreshKind, System};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 
 use crate::build_info;
 
@@ -41,6 +64,12 @@ Arguments:
           fstab style options. Comma separated list of CLI options, with backslash escapes for commas, backslashes, and double quotes.
           Use of `--` to prefix arguments is not allowed.";
 
+/// Maximum number of parts of a multipart upload, which limits the size of the objects uploaded with a given part size.
+const MAX_S3_MULTIPART_UPLOAD_PARTS: u64 = 10000;
+
+/// Maximum number of tags of an object.
+const MAX_OBJECT_TAGS: usize = 10;
+
 #[derive(Parser, Debug)]
 #[clap(
     name = "mount-s3",
@@ -80,6 +109,26 @@ Learn more in Mountpoint's configuration documentation (CONFIGURATION.md).\
     )]
     pub prefix: Option<Prefix>,
 
+    #[clap(
+        long,
+        help = "Character separating the components of keys, which the directory hierarchy is built from \
+                [default: /]. With another delimiter, objects whose key includes a '/' are not accessible",
+        value_name = "CHAR",
+        conflicts_with_all = ["inventory", "snapshot_time"],
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub key_delimiter: Option<KeyDelimiter>,
+
+    #[clap(
+        long,
+        help = "Percent-encode the components of keys that can't be file names, such as '.', '..', the empty \
+                component of 'a//b' and '%' itself, so that every object is accessible. The prefix is given in \
+                this escaped form, and the names of new files must be escaped",
+        conflicts_with_all = ["inventory", "snapshot_time"],
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub escape_names: bool,
+
     #[clap(
         long,
         help = "AWS region of the bucket [default: auto-detect region]",
@@ -129,6 +178,51 @@ Learn more in Mountpoint's configuration documentation (CONFIGURATION.md).\
     #[clap(long, help = "Set the storage class for new objects", help_heading = BUCKET_OPTIONS_HEADER)]
     pub storage_class: Option<String>,
 
+    #[clap(
+        long,
+        help = "Set the storage class for new objects with keys matching a pattern, overriding --storage-class. \
+                Can be specified multiple times, the first matching rule applies",
+        value_name = "PATTERN=STORAGE_CLASS",
+        help_heading = BUCKET_OPTIONS_HEADER,
+    )]
+    pub storage_class_rule: Vec<StorageClassRule>,
+
+    #[clap(
+        long,
+        help = "Tag the objects created through the mount with the given tag. Can be specified multiple times, \
+                for up to 10 tags",
+        value_name = "KEY=VALUE",
+        value_parser = parse_object_tag,
+        help_heading = BUCKET_OPTIONS_HEADER,
+    )]
+    pub object_tag: Vec<(String, String)>,
+
+    #[clap(
+        long,
+        help = "Set the content type of new objects from the extension of their key, such as text/csv for .csv \
+                files, rather than binary/octet-stream",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub detect_content_type: bool,
+
+    #[clap(
+        long,
+        help = "Set the content type of new objects with the given extension, overriding the built-in one. Can be \
+                specified multiple times",
+        value_name = "EXTENSION=CONTENT_TYPE",
+        requires = "detect_content_type",
+        help_heading = BUCKET_OPTIONS_HEADER,
+    )]
+    pub content_type_mapping: Vec<ContentTypeMapping>,
+
+    #[clap(
+        long,
+        help = "Recognize the content type of new objects with an unknown extension from their first bytes",
+        requires = "detect_content_type",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub sniff_content_type: bool,
+
     #[clap(
         long,
         help = "Allow delete operations on file system",
@@ -138,793 +232,3809 @@ Learn more in Mountpoint's configuration documentation (CONFIGURATION.md).\
 
     #[clap(
         long,
-        help = "Allow overwrite operations on file system",
+        help = "Allow removing non-empty directories by deleting every object under their prefix. These removals are \
+                not atomic",
+        requires = "allow_delete",
         help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub allow_overwrite: bool,
+    pub allow_recursive_delete: bool,
 
     #[clap(
         long,
-        help = "Enable incremental uploads and support for appending to existing objects",
-        help_heading = MOUNT_OPTIONS_HEADER,
+        help = "Maximum number of objects under a directory that can be removed [default: 10000]",
+        value_name = "COUNT",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "allow_recursive_delete",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub incremental_upload: bool,
+    pub max_recursive_delete_objects: Option<u64>,
 
-    #[clap(long, help = "Automatically unmount on exit", help_heading = MOUNT_OPTIONS_HEADER)]
-    pub auto_unmount: bool,
-
-    #[clap(long, help = "Allow root user to access file system", help_heading = MOUNT_OPTIONS_HEADER)]
-    pub allow_root: bool,
+    #[clap(
+        long,
+        help = "Delete the objects of files removed concurrently together, with DeleteObjects requests of up to 1000 keys",
+        requires = "allow_delete",
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub batch_deletes: bool,
 
     #[clap(
         long,
-        help = "Allow other users, including root, to access file system",
-        help_heading = MOUNT_OPTIONS_HEADER,
-        conflicts_with = "allow_root"
+        help = "Copy the objects of removed files under the given prefix of the bucket, ending in '/', before \
+                deleting them, at a key starting with the time of the removal",
+        value_name = "PREFIX",
+        value_parser = parse_trash_prefix,
+        requires = "allow_delete",
+        conflicts_with = "allow_recursive_delete",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub allow_other: bool,
+    pub trash_prefix: Option<Prefix>,
 
     #[clap(
         long,
-        help = "Maximum throughput in Gbps [default: auto-detected on EC2 instances, 10 Gbps elsewhere]",
-        value_name = "N",
+        help = "Purge the objects that have been in the trash for longer than the given number of seconds \
+                [default: keep them]",
+        value_name = "SECONDS",
         value_parser = value_parser!(u64).range(1..),
-        help_heading = CLIENT_OPTIONS_HEADER
+        requires = "trash_prefix",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub maximum_throughput_gbps: Option<u64>,
+    pub trash_retention: Option<u64>,
 
     #[clap(
         long,
-        help = "Maximum number of FUSE daemon threads",
-        value_name = "N",
-        default_value = "16",
+        help = "Capacity in MiB reported by statfs [default: unlimited]",
+        value_name = "MiB",
         value_parser = value_parser!(u64).range(1..),
-        help_heading = CLIENT_OPTIONS_HEADER
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub max_threads: u64,
+    pub statfs_capacity: Option<u64>,
 
-    // This config is still unstable
-    #[cfg(feature = "mem_limiter")]
     #[clap(
         long,
-        help = "Maximum memory usage target [default: 95% of total system memory with a minimum of 512 MiB]",
+        help = "Free space in MiB reported by statfs, up to --statfs-capacity [default: same as --statfs-capacity]",
         value_name = "MiB",
-        value_parser = value_parser!(u64).range(512..),
-        help_heading = CLIENT_OPTIONS_HEADER
+        value_parser = value_parser!(u64),
+        requires = "statfs_capacity",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub max_memory_target: Option<u64>,
+    pub statfs_free: Option<u64>,
 
     #[clap(
         long,
-        help = "Part size for multi-part GET and PUT in bytes",
-        default_value = "8388608",
-        value_name = "SIZE",
-        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
-        help_heading = CLIENT_OPTIONS_HEADER
+        help = "Report the capacity and free space of the cache directory's filesystem in statfs",
+        requires = "cache",
+        conflicts_with = "statfs_capacity",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub part_size: u64,
+    pub statfs_from_cache: bool,
 
     #[clap(
         long,
-        help = "Part size for GET in bytes [default: 8388608]",
-        value_name = "SIZE",
-        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
-        help_heading = CLIENT_OPTIONS_HEADER,
-        conflicts_with = "part_size",
+        help = "Allow overwrite operations on file system",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub read_part_size: Option<u64>,
+    pub allow_overwrite: bool,
 
     #[clap(
         long,
-        help = "Part size for multi-part PUT in bytes [default: 8388608]",
-        value_name = "SIZE",
-        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
-        help_heading = CLIENT_OPTIONS_HEADER,
-        conflicts_with = "part_size",
+        help = "Allow renaming files in buckets that don't support RenameObject, such as general purpose buckets, by \
+                copying the object to its new key and deleting the old one. These renames are not atomic",
+        help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub write_part_size: Option<u64>,
+    pub allow_rename: bool,
 
     #[clap(
         long,
-        help = "Owner UID [default: current user's UID]",
-        value_parser = value_parser!(u32).range(1..),
+        help = "Allow renaming directories by renaming every object under their prefix, copying and deleting them \
+                in buckets that don't support RenameObject along with --allow-rename. These renames are not atomic",
         help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub uid: Option<u32>,
+    pub allow_directory_rename: bool,
 
     #[clap(
         long,
-        help = "Owner GID [default: current user's GID]",
-        value_parser = value_parser!(u32).range(1..),
+        help = "Maximum number of objects under a directory that can be renamed [default: 1000]",
+        value_name = "COUNT",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "allow_directory_rename",
         help_heading = MOUNT_OPTIONS_HEADER
     )]
-    pub gid: Option<u32>,
+    pub max_directory_rename_objects: Option<u64>,
 
     #[clap(
         long,
-        help = "Directory permissions [default: 0755]",
-        value_parser = parse_perm_bits,
-        help_heading = MOUNT_OPTIONS_HEADER
+        help = "Enable incremental uploads and support for appending to existing objects",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub dir_mode: Option<u16>,
+    pub incremental_upload: bool,
 
     #[clap(
         long,
-        help = "File permissions [default: 0644]",
-        value_parser = parse_perm_bits,
-        help_heading = MOUNT_OPTIONS_HEADER
+        help = "Allow appending to existing files without incremental uploads, by copying their object into a \
+                multipart upload that replaces it once the file is closed. Along with --allow-overwrite, also allow \
+                overwriting part of existing files opened without O_TRUNC",
+        conflicts_with = "incremental_upload",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub file_mode: Option<u16>,
+    pub allow_append: bool,
 
-    #[clap(short, long, help = "Run as foreground process")]
-    pub foreground: bool,
+    #[clap(
+        long,
+        help = "Allow writing to new and overwritten files after fsync, which completes their upload. The following \
+                writes append to the object just uploaded by copying it, as with --allow-append",
+        conflicts_with = "incremental_upload",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub allow_write_after_fsync: bool,
 
     #[clap(
         long,
-        help = "Account ID of the expected bucket owner. \
-            If the bucket is owned by a different account, S3 requests fail with an access denied error.",
-        help_heading = BUCKET_OPTIONS_HEADER,
-        value_name = "AWS_ACCOUNT_ID"
+        help = "Only replace the object of a file overwritten with O_TRUNC if no other writer changed it since the \
+                file was opened, failing close with ESTALE otherwise",
+        requires = "allow_overwrite",
+        conflicts_with = "incremental_upload",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub expected_bucket_owner: Option<String>,
+    pub conditional_overwrite: bool,
 
     #[clap(
-        short,
         long,
-        help = "Write log files to a directory [default: logs written to syslog]",
-        help_heading = LOGGING_OPTIONS_HEADER,
+        help = "Stage writes to new and overwritten files in the given directory, so that they can be made at any \
+                offset and read back before they are uploaded, and upload each file at once when it is closed",
         value_name = "DIRECTORY",
+        conflicts_with = "incremental_upload",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub log_directory: Option<PathBuf>,
+    pub write_staging_dir: Option<PathBuf>,
 
-    #[clap(long, help = "Enable logging of summarized performance metrics", help_heading = LOGGING_OPTIONS_HEADER)]
-    pub log_metrics: bool,
+    #[clap(
+        long,
+        help = "Maximum total size in MiB of the files staged in the --write-staging-dir directory. Writes that \
+                would exceed it fail with ENOSPC",
+        value_name = "MiB",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "write_staging_dir",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub write_staging_max_size: Option<u64>,
 
     #[clap(
         long,
-        help = "OTLP endpoint for publishing metrics",
-        help_heading = LOGGING_OPTIONS_HEADER,
-        value_name = "ENDPOINT"
+        help = "Maximum size in bytes files can be written to. Writes that would exceed it fail with EFBIG \
+                [default: the largest object that can be uploaded with --write-part-size]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub otlp_endpoint: Option<String>,
+    pub max_file_size: Option<u64>,
 
     #[clap(
         long,
-        help = "OTLP metrics export interval in seconds [default: 60]",
-        help_heading = LOGGING_OPTIONS_HEADER,
-        value_name = "SECONDS",
+        help = "Maximum number of bytes that can be written to files until the bucket is unmounted. Writes that \
+                would exceed it fail with EDQUOT [default: unlimited]",
+        value_name = "BYTES",
         value_parser = value_parser!(u64).range(1..),
-        requires = "otlp_endpoint"
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub otlp_export_interval: Option<u64>,
+    pub max_write_bytes_per_mount: Option<u64>,
 
-    #[clap(short, long, help = "Enable debug logging for Mountpoint", help_heading = LOGGING_OPTIONS_HEADER)]
-    pub debug: bool,
+    #[clap(
+        long,
+        help = "Maximum number of files uploading data at once. Files being written take turns, so that a large \
+                upload can't delay the uploads of small files [default: unlimited]",
+        value_name = "N",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub max_concurrent_uploads: Option<u64>,
 
-    #[clap(long, help = "Enable debug logging for AWS Common Runtime", help_heading = LOGGING_OPTIONS_HEADER)]
-    pub debug_crt: bool,
+    #[clap(
+        long,
+        help = "Maximum number of parts of a file uploaded at once [default: limited by the client throughput]",
+        value_name = "N",
+        value_parser = value_parser!(u32).range(1..),
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub max_concurrent_parts_per_upload: Option<u32>,
 
     #[clap(
         long,
-        help = "Disable all logging. You will still see stdout messages.",
-        help_heading = LOGGING_OPTIONS_HEADER,
-        conflicts_with_all(["log_directory", "debug", "debug_crt", "log_metrics"])
+        help = "Upload the files staged in the --write-staging-dir directory in the background once they are closed, \
+                rather than before close returns. The files closed but not uploaded when Mountpoint exits are \
+                uploaded when it is next mounted with the same directory",
+        requires = "write_staging_dir",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub no_log: bool,
+    pub defer_uploads: bool,
 
     #[clap(
         long,
-        help = "Enable caching of object content to the given directory and set metadata TTL to 60 seconds",
-        help_heading = CACHING_OPTIONS_HEADER,
-        value_name = "DIRECTORY",
-        group = "cache_group",
+        help = "Let the kernel cache the writes to the files staged in the --write-staging-dir directory, and write \
+                them back in larger batches",
+        requires = "write_staging_dir",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub cache: Option<PathBuf>,
+    pub writeback_cache: bool,
 
     #[clap(
         long,
-        help = "Time-to-live (TTL) for cached metadata in seconds [default: minimal, or 60 seconds if --cache is set]",
-        value_name = "SECONDS|indefinite|minimal",
-        help_heading = CACHING_OPTIONS_HEADER,
+        help = "Expose the user-defined metadata of objects as 'user.s3.meta.*' extended attributes, \
+                which can also be set on new files before writing to them to upload them with the object",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub metadata_ttl: Option<TimeToLive>,
+    pub user_metadata_xattrs: bool,
 
     #[clap(
         long,
-        help = "Time-to-live (TTL) for cached negative entries in seconds [default: same as metadata TTL]",
-        value_name = "SECONDS|indefinite|minimal",
-        help_heading = CACHING_OPTIONS_HEADER,
+        help = "Expose the ETag, version ID and storage class of objects as the read-only 'user.s3.etag', \
+                'user.s3.version_id' and 'user.s3.storage_class' extended attributes",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub negative_metadata_ttl: Option<TimeToLive>,
+    pub object_info_xattrs: bool,
 
     #[clap(
         long,
-        help = "Maximum size of the cache directory in MiB [default: preserve 5% of available space]",
-        value_name = "MiB",
-        value_parser = value_parser!(u64),
-        help_heading = CACHING_OPTIONS_HEADER,
-        requires = "cache",
+        help = "Expose URLs to get the objects of files, pre-signed with the credentials of the mount to be valid for \
+                the given number of seconds, as the read-only 'user.s3.presigned_url' extended attribute [max: 604800]",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..=MAX_PRESIGNED_URL_EXPIRATION.as_secs()),
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub max_cache_size: Option<u64>,
+    pub presigned_url_xattr: Option<u64>,
 
-    #[cfg(feature = "block_size")]
     #[clap(
         long,
-        help = "Size of a cache block in KiB [Default: 1024 (1 MiB)]",
-        help_heading = CACHING_OPTIONS_HEADER,
-        value_name = "KiB",
-        requires = "cache_group",
+        help = "Expose the total size of the objects under each directory, listed with ListObjectsV2, as the \
+                read-only 'user.s3.recursive_size' extended attribute",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub cache_block_size: Option<u64>,
+    pub recursive_size_xattr: bool,
 
     #[clap(
         long,
-        help = "Enable caching of object content to the specified bucket on S3 Express One Zone (same region only)",
-        help_heading = CACHING_OPTIONS_HEADER,
-        value_name = "BUCKET",
-        group = "cache_group",
-        value_parser = parse_bucket_name_or_s3_uri,
+        help = "Expose the tags of objects as 'user.s3.tag.*' extended attributes, which update the tags of \
+                the objects when set or removed",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub cache_xz: Option<BucketNameOrS3Uri>,
+    pub tag_xattrs: bool,
 
     #[clap(
         long,
-        help = "Configure a string to be prepended to the 'User-Agent' HTTP request header for all S3 requests",
-        value_name = "PREFIX",
-        help_heading = ADVANCED_OPTIONS_HEADER,
+        help = "Support symbolic links, stored as objects holding their target in their '--symlink-target' \
+                user-defined metadata, the same convention as goofys",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub user_agent_prefix: Option<String>,
+    pub symlinks: bool,
 
     #[clap(
         long,
-        help = "Server-side encryption algorithm to use when uploading new objects",
-        help_heading = BUCKET_OPTIONS_HEADER,
-        value_parser = clap::builder::PossibleValuesParser::new(["aws:kms", "aws:kms:dsse", "AES256"]))]
-    pub sse: Option<String>,
+        help = "Serve the objects stored with 'Content-Encoding: gzip' decoded, reporting the size of their decoded \
+                content. Files listed from their directory are looked up again to find their content encoding",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub decode_content_encoding: bool,
 
     #[clap(
         long,
-        help = "AWS Key Management Service (KMS) key ARN to use with KMS server-side encryption when uploading new objects. Key ID, Alias and Alias ARN are all not supported.",
-        help_heading = BUCKET_OPTIONS_HEADER,
-        requires = "sse",
-        value_parser = parse_kms_key_arn,
-        value_name = "AWS_KMS_KEY_ARN",
+        help = "Show the objects with a '.gz' or '.zst' extension as read-only files named without it, such as \
+                'data.csv' for 'data.csv.gz', which are decompressed as they are read",
+        conflicts_with_all = ["inventory", "snapshot_time"],
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub sse_kms_key_id: Option<String>,
+    pub decompress_files: bool,
 
     #[clap(
         long,
-        help = "Checksum algorithm to use for S3 uploads [default: crc32c]",
-        help_heading = BUCKET_OPTIONS_HEADER,
-        value_name = "ALGORITHM",
+        help = "Keep the modification times of files in the 'mtime' user-defined metadata of their objects, \
+                the same convention as s3fs and rclone. Setting the modification time of a file copies its object",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub upload_checksums: Option<UploadChecksums>,
+    pub mtime_metadata: bool,
 
     #[clap(
         long,
-        help = "One or more network interfaces for Mountpoint to use when accessing S3. Requires Linux 5.7+ or running as root. This feature is a work-in-progress.",
-        help_heading = CLIENT_OPTIONS_HEADER,
-        value_name = "NETWORK_INTERFACE",
+        help = "Keep the permissions of files in the 'mode' user-defined metadata of their objects, the same \
+                convention as s3fs, rather than using --file-mode for all files. Changing the permissions of a \
+                file copies its object",
+        help_heading = MOUNT_OPTIONS_HEADER,
     )]
-    pub bind: Option<Vec<String>>,
+    pub mode_metadata: bool,
 
-    #[clap(skip)]
-    pub is_fstab: bool,
-}
+    #[clap(
+        long,
+        help = "Track the times files are last read through the mount and report them as their access times",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub atime: bool,
 
-#[derive(Debug, Clone, Copy)]
-pub enum BucketType {
-    GeneralPurpose,
+    #[clap(
+        long,
+        help = "Also keep the access times of files in the 'atime' user-defined metadata of their objects, at most \
+                once per the given number of seconds per file, when the file is closed. Storing the access time of a \
+                file copies its object",
+        value_name = "SECONDS",
+        requires_all = ["atime", "mtime_metadata"],
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub atime_flush_interval: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Create an empty marker object with the key of each new directory, ending in '/', so that empty \
+                directories persist and are shown by other tools. Removing an empty directory deletes its marker",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub directory_markers: bool,
+
+    #[clap(
+        long,
+        help = "How to show a file and a directory with the same name, such as the keys 'foo' and 'foo/bar': show \
+                the directory, show the file, or show both with the file renamed to 'foo.shadowed'. Shadowed keys \
+                are counted in the 'fs.shadowed_keys' metric [default: directory]",
+        value_name = "POLICY",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub shadowing: Option<Shadowing>,
+
+    #[clap(
+        long,
+        help = "How to show objects in the GLACIER and DEEP_ARCHIVE storage classes that haven't been restored: \
+                with no permissions, hidden, with their usual permissions but failing reads with ENODATA, or like \
+                the latter with their restore status in the 'user.s3.restore_status' extended attribute \
+                [default: unreadable]",
+        value_name = "POLICY",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub archived_objects: Option<ArchivedObjects>,
+
+    #[clap(
+        long,
+        help = "Look up names case-insensitively when no file or directory has exactly the given name, listing the \
+                directory in S3. Files and directories keep the names of their keys, and when several of them \
+                match, the first one in byte order is used",
+        conflicts_with_all = ["inventory", "snapshot_time"],
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub case_insensitive: bool,
+
+    #[clap(
+        long,
+        help = "Only show the files whose name or key, relative to the mounted prefix, matches this pattern, such as \
+                '*.parquet'. Patterns with no '/' match names, and others match keys; '*' matches any characters \
+                other than '/', and '**' any characters. Can be repeated",
+        value_name = "PATTERN",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub include: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Hide the files and directories whose name or key, relative to the mounted prefix, matches this \
+                pattern, such as '*.tmp'. The names and keys of directories end with '/'. Takes precedence over \
+                --include. Can be repeated",
+        value_name = "PATTERN",
+        help_heading = MOUNT_OPTIONS_HEADER,
+    )]
+    pub exclude: Vec<String>,
+
+    #[clap(long, help = "Automatically unmount on exit", help_heading = MOUNT_OPTIONS_HEADER)]
+    pub auto_unmount: bool,
+
+    #[clap(long, help = "Allow root user to access file system", help_heading = MOUNT_OPTIONS_HEADER)]
+    pub allow_root: bool,
+
+    #[clap(
+        long,
+        help = "Allow other users, including root, to access file system",
+        help_heading = MOUNT_OPTIONS_HEADER,
+        conflicts_with = "allow_root"
+    )]
+    pub allow_other: bool,
+
+    #[clap(
+        long,
+        help = "Maximum throughput in Gbps [default: auto-detected on EC2 instances, 10 Gbps elsewhere]",
+        value_name = "N",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CLIENT_OPTIONS_HEADER
+    )]
+    pub maximum_throughput_gbps: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Maximum number of FUSE daemon threads",
+        value_name = "N",
+        default_value = "16",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CLIENT_OPTIONS_HEADER
+    )]
+    pub max_threads: u64,
+
+    #[clap(
+        long,
+        help = "Maximum size in bytes of the reads and writes the kernel sends to Mountpoint in a single request. \
+                Kernels cap it to their own limit, 1 MiB unless the max_pages_limit parameter of the fuse module is \
+                raised [default: 16777216]",
+        value_name = "BYTES",
+        value_parser = value_parser!(u32).range(4096..=16 * 1024 * 1024),
+        help_heading = CLIENT_OPTIONS_HEADER
+    )]
+    pub max_fuse_request_size: Option<u32>,
+
+    // This config is still unstable
+    #[cfg(feature = "mem_limiter")]
+    #[clap(
+        long,
+        help = "Maximum memory usage target [default: 95% of total system memory with a minimum of 512 MiB]",
+        value_name = "MiB",
+        value_parser = value_parser!(u64).range(512..),
+        help_heading = CLIENT_OPTIONS_HEADER
+    )]
+    pub max_memory_target: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Part size for multi-part GET and PUT in bytes",
+        default_value = "8388608",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER
+    )]
+    pub part_size: u64,
+
+    #[clap(
+        long,
+        help = "Part size for GET in bytes [default: 8388608]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+        conflicts_with = "part_size",
+    )]
+    pub read_part_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Part size for multi-part PUT in bytes [default: 8388608]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+        conflicts_with = "part_size",
+    )]
+    pub write_part_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Maximum part size for GET in bytes. When set, the part size of each GET adapts between the read part size and this maximum, based on the observed latency and throughput",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub max_read_part_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Size of the first request made when reading a file, in bytes. Set to 0 to start reading with a full part. [default: 1179648]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(0..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub initial_read_window: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Maximum amount of data to prefetch ahead of sequential reads for each open file, in bytes [default: 2147483648]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub max_read_window: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Factor by which the prefetch window grows while reads remain sequential [default: 2]",
+        value_name = "N",
+        value_parser = value_parser!(u64).range(2..usize::MAX as u64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub read_window_growth_factor: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Maximum amount of memory for the prefetched data of all open files, in bytes. When it is exhausted, \
+                the read window of each open file is scaled down to a fair share of it [default: no limit]",
+        value_name = "SIZE",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub prefetch_memory_budget: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Prefetch Parquet files according to their layout, fetching their footer first and bounding requests \
+                to the column chunk being read",
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub format_aware_prefetch: bool,
+
+    #[clap(
+        long,
+        help = "Duplicate the GET requests still waiting for their first byte after this percentile of recent \
+                first-byte latencies, between 0 and 100, and continue with whichever returns data first \
+                [default: requests are never duplicated]",
+        value_name = "PERCENTILE",
+        value_parser = parse_percentile,
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub hedge_percentile: Option<f64>,
+
+    #[clap(
+        long,
+        help = "Number of directory listing pages to fetch ahead of directory reads for each open directory \
+                [default: no pages fetched ahead]",
+        value_name = "PAGES",
+        value_parser = value_parser!(u64).range(1..=64),
+        help_heading = CLIENT_OPTIONS_HEADER,
+    )]
+    pub readdir_readahead: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Owner UID [default: current user's UID]",
+        value_parser = value_parser!(u32).range(1..),
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub uid: Option<u32>,
+
+    #[clap(
+        long,
+        help = "Owner GID [default: current user's GID]",
+        value_parser = value_parser!(u32).range(1..),
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub gid: Option<u32>,
+
+    #[clap(
+        long,
+        help = "Directory permissions [default: 0755]",
+        value_parser = parse_perm_bits,
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub dir_mode: Option<u16>,
+
+    #[clap(
+        long,
+        help = "File permissions [default: 0644]",
+        value_parser = parse_perm_bits,
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub file_mode: Option<u16>,
+
+    #[clap(
+        long,
+        help = "Keep the inode numbers of files and directories across mounts in the given file",
+        value_name = "FILE",
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub inode_table: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Serve as the read-only lower layer of an overlayfs mount, keeping inode numbers stable until unmounted \
+                and reporting no opaque directories or whiteouts",
+        requires = "read_only",
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub overlayfs_lower: bool,
+
+    #[clap(
+        long,
+        help = "Override the metadata TTL, read window, data cache, storage class or read-only options for the \
+                files and directories under some prefixes, as described by the given JSON file",
+        value_name = "FILE",
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub prefix_overrides: Option<PathBuf>,
+
+    #[cfg(feature = "manifest")]
+    #[clap(
+        long,
+        help = "Serve the file system from the given CSV manifest of the objects to mount, without listing the bucket",
+        value_name = "FILE",
+        requires = "metadata_manifest_checksum",
+        conflicts_with_all = [
+            "inode_table",
+            "readdir_readahead",
+            "key_delimiter",
+            "escape_names",
+            "case_insensitive",
+            "decompress_files",
+            "include",
+            "exclude",
+        ],
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub metadata_manifest: Option<PathBuf>,
+
+    #[cfg(feature = "manifest")]
+    #[clap(
+        long,
+        help = "Base64-encoded CRC32C checksum of the manifest",
+        value_name = "CHECKSUM",
+        value_parser = parse_crc32c_base64,
+        requires = "metadata_manifest",
+        help_heading = MOUNT_OPTIONS_HEADER
+    )]
+    pub metadata_manifest_checksum: Option<Crc32cBase64>,
+
+    #[clap(short, long, help = "Run as foreground process")]
+    pub foreground: bool,
+
+    #[clap(
+        long,
+        help = "Account ID of the expected bucket owner. \
+            If the bucket is owned by a different account, S3 requests fail with an access denied error.",
+        help_heading = BUCKET_OPTIONS_HEADER,
+        value_name = "AWS_ACCOUNT_ID"
+    )]
+    pub expected_bucket_owner: Option<String>,
+
+    #[clap(
+        long,
+        help = "List directories from the latest S3 Inventory report delivered to the given S3 URI, \
+                ending with the name of the bucket and the ID of the inventory configuration",
+        value_name = "S3_URI",
+        value_parser = S3Path::parse_s3_uri,
+        requires = "read_only",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub inventory: Option<S3Path>,
+
+    #[clap(
+        long,
+        help = "Interval in seconds between checks for a new S3 Inventory report [default: 3600]",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "inventory",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub inventory_refresh_interval: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Mount the bucket as it was at the given time, in RFC 3339 format, from the versions of its objects",
+        value_name = "TIME",
+        value_parser = parse_snapshot_time,
+        requires = "read_only",
+        conflicts_with = "inventory",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub snapshot_time: Option<OffsetDateTime>,
+
+    #[clap(
+        long,
+        help = "Serve the versions of each object in a read-only '<name>.versions' directory next to it, as files \
+                named by their version ID. Objects under a prefix ending in '.versions/' are not accessible",
+        conflicts_with = "snapshot_time",
+        help_heading = BUCKET_OPTIONS_HEADER
+    )]
+    pub version_directories: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Write log files to a directory [default: logs written to syslog]",
+        help_heading = LOGGING_OPTIONS_HEADER,
+        value_name = "DIRECTORY",
+    )]
+    pub log_directory: Option<PathBuf>,
+
+    #[clap(long, help = "Enable logging of summarized performance metrics", help_heading = LOGGING_OPTIONS_HEADER)]
+    pub log_metrics: bool,
+
+    #[clap(
+        long,
+        help = "OTLP endpoint for publishing metrics",
+        help_heading = LOGGING_OPTIONS_HEADER,
+        value_name = "ENDPOINT"
+    )]
+    pub otlp_endpoint: Option<String>,
+
+    #[clap(
+        long,
+        help = "OTLP metrics export interval in seconds [default: 60]",
+        help_heading = LOGGING_OPTIONS_HEADER,
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "otlp_endpoint"
+    )]
+    pub otlp_export_interval: Option<u64>,
+
+    #[clap(short, long, help = "Enable debug logging for Mountpoint", help_heading = LOGGING_OPTIONS_HEADER)]
+    pub debug: bool,
+
+    #[clap(long, help = "Enable debug logging for AWS Common Runtime", help_heading = LOGGING_OPTIONS_HEADER)]
+    pub debug_crt: bool,
+
+    #[clap(
+        long,
+        help = "Expose prefetching statistics of the file handles reading a file in its 'user.mountpoint.prefetch_stats' extended attribute",
+        help_heading = LOGGING_OPTIONS_HEADER
+    )]
+    pub prefetch_stats_xattr: bool,
+
+    #[clap(
+        long,
+        help = "Disable all logging. You will still see stdout messages.",
+        help_heading = LOGGING_OPTIONS_HEADER,
+        conflicts_with_all(["log_directory", "debug", "debug_crt", "log_metrics"])
+    )]
+    pub no_log: bool,
+
+    #[clap(
+        long,
+        help = "Enable caching of object content to the given directory and set metadata TTL to 60 seconds",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "DIRECTORY",
+        group = "cache_group",
+    )]
+    pub cache: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Time-to-live (TTL) for cached metadata in seconds [default: minimal, or 60 seconds if --cache is set]",
+        value_name = "SECONDS|indefinite|minimal",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub metadata_ttl: Option<TimeToLive>,
+
+    #[clap(
+        long,
+        help = "Time-to-live (TTL) for cached negative entries in seconds [default: same as metadata TTL]",
+        value_name = "SECONDS|indefinite|minimal",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub negative_metadata_ttl: Option<TimeToLive>,
+
+    #[clap(
+        long,
+        help = "Time-to-live (TTL) for cached metadata of the files and directories matching a pattern, \
+                overriding --metadata-ttl. Can be specified multiple times, the first matching rule applies",
+        value_name = "PATTERN=TTL",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub metadata_ttl_rule: Vec<MetadataTtlRule>,
+
+    #[clap(
+        long,
+        help = "Expire the cached metadata of a file, or of a directory and everything under it, when the \
+                'user.mountpoint.invalidate' extended attribute is set to 1 on it",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub invalidate_xattr: bool,
+
+    #[clap(
+        long,
+        help = "Keep the content of files cached by the kernel when they are opened again, as long as their objects \
+                still have the same ETag",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub keep_page_cache: bool,
+
+    #[clap(
+        long,
+        help = "Open a file with direct I/O, bypassing the kernel page cache, when the 'user.mountpoint.direct_io' \
+                extended attribute is set to 1 on it or on a directory above it",
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub direct_io_xattr: bool,
+
+    #[clap(
+        long,
+        help = "Keep showing the files written by this mount in directory listings and lookups for this many \
+                seconds after their upload, even if S3 doesn't return them yet",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+    )]
+    pub recent_writes_window: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Maximum size of the cache directory in MiB [default: preserve 5% of available space]",
+        value_name = "MiB",
+        value_parser = value_parser!(u64),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub max_cache_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Evict cached content once less than this percentage of the space of the cache directory's \
+                filesystem is available, until the high watermark is available again",
+        value_name = "PERCENT",
+        value_parser = value_parser!(u64).range(0..100),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires_all = ["cache", "cache_high_watermark"],
+    )]
+    pub cache_low_watermark: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Percentage of the space of the cache directory's filesystem to make available when evicting \
+                cached content past the low watermark",
+        value_name = "PERCENT",
+        value_parser = value_parser!(u64).range(1..=100),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_low_watermark",
+    )]
+    pub cache_high_watermark: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Size in MiB of a memory tier in front of the cache directory, holding its most recently read blocks",
+        value_name = "MiB",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub memory_cache_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Policy picking the blocks to evict from the local disk cache [default: lru]",
+        value_name = "POLICY",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_eviction_policy: Option<CacheEvictionPolicy>,
+
+    #[clap(
+        long,
+        help = "Time after which blocks expire from the local disk cache, in seconds. Requires --cache-eviction-policy ttl",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_eviction_ttl: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Only write blocks to the local disk cache once they are read twice within this many seconds, \
+                so that data read only once doesn't evict data read repeatedly",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_admission_window: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Evict the blocks of objects under this prefix, relative to the mounted prefix, only after the other \
+                blocks of the local disk cache. Can be repeated",
+        value_name = "PREFIX",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_pin_prefix: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Maximum percentage of the local disk cache used by pinned blocks, past which they are evicted like \
+                the other blocks [default: 50]",
+        value_name = "PERCENT",
+        value_parser = value_parser!(u64).range(1..=100),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_pin_prefix",
+    )]
+    pub cache_pin_max_percent: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Let applications pin files in the local disk cache by setting their 'user.mountpoint.pin' extended \
+                attribute to 1. The content of pinned files is fetched in the background, and is not evicted until \
+                the attribute is removed",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_pin_xattr: bool,
+
+    #[clap(
+        long,
+        help = "Encrypt the blocks of the local disk cache with a random key generated at mount time",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_encryption: bool,
+
+    #[clap(
+        long,
+        help = "Encrypt the blocks of the local disk cache with the 256-bit key in this file, \
+                as 32 raw bytes or 64 hexadecimal digits",
+        value_name = "PATH",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_encryption_key_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Compress the blocks of the local disk cache with zstd, to fit more data in the cache at the cost of CPU time",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_compression: bool,
+
+    #[clap(
+        long,
+        help = "zstd compression level of the blocks of the local disk cache, from 1 (fastest) to 22 (smallest) \
+                [default: 3]",
+        value_name = "LEVEL",
+        value_parser = value_parser!(i32).range(1..=22),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_compression",
+    )]
+    pub cache_compression_level: Option<i32>,
+
+    #[clap(
+        long,
+        help = "Read and write the blocks of the local disk cache asynchronously with io_uring, \
+                falling back to synchronous I/O where io_uring isn't available",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_io_uring: bool,
+
+    #[clap(
+        long,
+        help = "Bypass the page cache with O_DIRECT when reading and writing the blocks of the local disk cache",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_io_uring",
+    )]
+    pub cache_direct_io: bool,
+
+    #[clap(
+        long,
+        help = "Share the local disk cache with the mounts at these addresses, fetching blocks from each other \
+                before falling back to S3 [experimental]",
+        value_name = "ADDRESS,...",
+        value_delimiter = ',',
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+        requires = "cache_peer_address",
+    )]
+    pub cache_peers: Vec<SocketAddr>,
+
+    #[clap(
+        long,
+        help = "Address to serve the local disk cache to the peers on, which should be one of the --cache-peers",
+        value_name = "ADDRESS",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_peers",
+    )]
+    pub cache_peer_address: Option<SocketAddr>,
+
+    #[clap(
+        long,
+        help = "Verify the checksums of all the blocks of the local disk cache every this many seconds, \
+                removing the corrupted ones",
+        value_name = "SECONDS",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_scrub_interval: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Allow other Mountpoint processes to use the same cache directory at the same time, sharing the cached content",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub shared_cache: bool,
+
+    #[clap(
+        long,
+        help = "Name of the account the blocks written by this mount are charged to in the shared cache directory \
+                [default: the mounted bucket and prefix]",
+        value_name = "NAME",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "shared_cache",
+    )]
+    pub cache_account: Option<String>,
+
+    #[clap(
+        long,
+        help = "Maximum size of the blocks charged to the account of this mount in the shared cache directory, \
+                beyond which its own blocks are evicted first",
+        value_name = "MiB",
+        value_parser = value_parser!(u64).range(1..),
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "shared_cache",
+    )]
+    pub cache_quota: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Keep the content of the cache directory when Mountpoint exits, to read it again after a remount",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub persist_cache: bool,
+
+    #[clap(
+        long,
+        help = "Size of a cache block in KiB. Smaller blocks waste less cache space on small objects, \
+                larger blocks need fewer requests for large objects [default: 1024 (1 MiB)]",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "KiB",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "cache_group",
+    )]
+    pub cache_block_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Enable caching of object content to the specified bucket on S3 Express One Zone (same region only)",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "BUCKET",
+        group = "cache_group",
+        value_parser = parse_bucket_name_or_s3_uri,
+    )]
+    pub cache_xz: Option<BucketNameOrS3Uri>,
+
+    #[clap(
+        long,
+        help = "Maximum size in KiB of the objects cached in the S3 Express One Zone bucket [default: 1024 (1 MiB)]",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "KiB",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "cache_xz",
+    )]
+    pub cache_xz_max_object_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Report cache hits and misses for each prefix of this many directories below the mounted prefix, \
+                in the `cache.prefix_*` metrics",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "N",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "cache_group",
+    )]
+    pub cache_metrics_prefix_depth: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Put the objects written through the mount to the local disk cache once uploaded, \
+                so that reading them back doesn't download them again",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache",
+    )]
+    pub cache_write_through: bool,
+
+    #[clap(
+        long,
+        help = "Put the objects written through the mount to the S3 Express One Zone bucket cache as well once uploaded",
+        help_heading = CACHING_OPTIONS_HEADER,
+        requires = "cache_xz",
+    )]
+    pub cache_write_through_shared: bool,
+
+    #[clap(
+        long,
+        help = "Maximum size in MiB of the objects written through the mount that are cached, \
+                their data being held in memory until uploaded [default: 64]",
+        help_heading = CACHING_OPTIONS_HEADER,
+        value_name = "MiB",
+        value_parser = value_parser!(u64).range(1..),
+        requires = "cache_group",
+    )]
+    pub cache_write_through_max_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Configure a string to be prepended to the 'User-Agent' HTTP request header for all S3 requests",
+        value_name = "PREFIX",
+        help_heading = ADVANCED_OPTIONS_HEADER,
+    )]
+    pub user_agent_prefix: Option<String>,
+
+    #[clap(
+        long,
+        help = "Server-side encryption algorithm to use when uploading new objects",
+        help_heading = BUCKET_OPTIONS_HEADER,
+        value_parser = clap::builder::PossibleValuesParser::new(["aws:kms", "aws:kms:dsse", "AES256"]))]
+    pub sse: Option<String>,
+
+    #[clap(
+        long,
+        help = "AWS Key Management Service (KMS) key ARN to use with KMS server-side encryption when uploading new objects. Key ID, Alias and Alias ARN are all not supported.",
+        help_heading = BUCKET_OPTIONS_HEADER,
+        requires = "sse",
+        value_parser = parse_kms_key_arn,
+        value_name = "AWS_KMS_KEY_ARN",
+    )]
+    pub sse_kms_key_id: Option<String>,
+
+    #[clap(
+        long,
+        help = "Checksum algorithm to use for S3 uploads [default: crc32c]",
+        help_heading = BUCKET_OPTIONS_HEADER,
+        value_name = "ALGORITHM",
+    )]
+    pub upload_checksums: Option<UploadChecksums>,
+
+    #[clap(
+        long,
+        help = "One or more network interfaces for Mountpoint to use when accessing S3. Requires Linux 5.7+ or running as root. This feature is a work-in-progress.",
+        help_heading = CLIENT_OPTIONS_HEADER,
+        value_name = "NETWORK_INTERFACE",
+    )]
+    pub bind: Option<Vec<String>>,
+
+    #[clap(skip)]
+    pub is_fstab: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BucketType {
+    GeneralPurpose,
+    Directory,
+}
+
+impl BucketType {
+    pub fn to_personality(self) -> S3Personality {
+        match self {
+            Self::GeneralPurpose => S3Personality::Standard,
+            Self::Directory => S3Personality::ExpressOneZone,
+        }
+    }
+}
+
+impl ValueEnum for BucketType {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::GeneralPurpose, Self::Directory]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::GeneralPurpose => Some(clap::builder::PossibleValue::new("general-purpose")),
+            Self::Directory => Some(clap::builder::PossibleValue::new("directory")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UploadChecksums {
+    Crc32c,
+    Crc32,
+    Sha1,
+    Sha256,
+    Off,
+}
+
+impl ValueEnum for UploadChecksums {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Crc32c, Self::Crc32, Self::Sha1, Self::Sha256, Self::Off]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Crc32c => Some(clap::builder::PossibleValue::new("crc32c")),
+            Self::Crc32 => Some(clap::builder::PossibleValue::new("crc32")),
+            Self::Sha1 => Some(clap::builder::PossibleValue::new("sha1")),
+            Self::Sha256 => Some(clap::builder::PossibleValue::new("sha256")),
+            Self::Off => Some(clap::builder::PossibleValue::new("off")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Shadowing {
     Directory,
+    File,
+    SuffixFile,
+}
+
+impl ValueEnum for Shadowing {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Directory, Self::File, Self::SuffixFile]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Directory => Some(clap::builder::PossibleValue::new("directory")),
+            Self::File => Some(clap::builder::PossibleValue::new("file")),
+            Self::SuffixFile => Some(clap::builder::PossibleValue::new("suffix-file")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArchivedObjects {
+    Unreadable,
+    Hidden,
+    FailReads,
+    RestoreStatus,
+}
+
+impl ValueEnum for ArchivedObjects {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Unreadable, Self::Hidden, Self::FailReads, Self::RestoreStatus]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Unreadable => Some(clap::builder::PossibleValue::new("unreadable")),
+            Self::Hidden => Some(clap::builder::PossibleValue::new("hidden")),
+            Self::FailReads => Some(clap::builder::PossibleValue::new("fail-reads")),
+            Self::RestoreStatus => Some(clap::builder::PossibleValue::new("restore-status")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CacheEvictionPolicy {
+    Lru,
+    Lfu,
+    Fifo,
+    Ttl,
+}
+
+impl ValueEnum for CacheEvictionPolicy {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Lru, Self::Lfu, Self::Fifo, Self::Ttl]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Lru => Some(clap::builder::PossibleValue::new("lru")),
+            Self::Lfu => Some(clap::builder::PossibleValue::new("lfu")),
+            Self::Fifo => Some(clap::builder::PossibleValue::new("fifo")),
+            Self::Ttl => Some(clap::builder::PossibleValue::new("ttl")),
+        }
+    }
+}
+
+impl CliArgs {
+    fn addressing_style(&self) -> AddressingStyle {
+        if self.force_path_style {
+            AddressingStyle::Path
+        } else {
+            AddressingStyle::Automatic
+        }
+    }
+
+    pub fn s3_path(&self) -> anyhow::Result<S3Path> {
+        let prefix = self.prefix.as_ref().cloned().unwrap_or_default();
+        let s3path = match self.bucket_name.clone() {
+            BucketNameOrS3Uri::S3Uri(s3uri) => {
+                if prefix.as_str().is_empty() {
+                    s3uri
+                } else {
+                    return Err(anyhow!("explicit prefix option not allowed with S3 URI"));
+                }
+            }
+            BucketNameOrS3Uri::BucketName(bucket_name) => S3Path::new(bucket_name, prefix),
+        };
+        let key_mapping = KeyMapping::new(self.key_delimiter.unwrap_or_default(), self.escape_names);
+        if !key_mapping.is_escaped_path(s3path.prefix.as_str()) {
+            return Err(anyhow!(
+                "the prefix must be given in its escaped form with --escape-names"
+            ));
+        }
+        Ok(s3path)
+    }
+
+    fn mem_limit(&self) -> u64 {
+        let mut mem_limit = MINIMUM_MEM_LIMIT;
+
+        let sys = System::new_with_specifics(RefreshKind::everything());
+        let default_mem_target = (sys.total_memory() as f64 * 0.95) as u64;
+        mem_limit = mem_limit.max(default_mem_target);
+
+        #[cfg(feature = "mem_limiter")]
+        if let Some(max_mem_target) = self.max_memory_target {
+            mem_limit = max_mem_target * 1024 * 1024;
+        }
+
+        mem_limit
+    }
+
+    fn upload_checksum_algorithm(&self, s3_personality: S3Personality) -> Option<ChecksumAlgorithm> {
+        // Written in this awkward way to force us to update it if we add new checksum types
+        match self.upload_checksums {
+            Some(UploadChecksums::Crc32c) => Some(ChecksumAlgorithm::Crc32c),
+            Some(UploadChecksums::Crc32) => Some(ChecksumAlgorithm::Crc32),
+            Some(UploadChecksums::Sha1) => Some(ChecksumAlgorithm::Sha1),
+            Some(UploadChecksums::Sha256) => Some(ChecksumAlgorithm::Sha256),
+            Some(UploadChecksums::Off) => None,
+            None => {
+                // Default to CRC32C if supported
+                if s3_personality.supports_additional_checksums() {
+                    Some(ChecksumAlgorithm::Crc32c)
+                } else {
+                    tracing::info!("disabling upload checksums because target S3 personality does not support them");
+                    None
+                }
+            }
+        }
+    }
+
+    /// Configure fetching directory listing pages ahead of directory reads on `runtime`, if enabled.
+    pub fn readdir_readahead(&self, runtime: &Runtime) -> Option<ReaddirReadahead> {
+        let max_pages = self.readdir_readahead? as usize;
+        Some(ReaddirReadahead::new(runtime.clone(), max_pages))
+    }
+
+    /// How to resolve the collisions between files and directories with the same name.
+    pub fn archived_objects(&self) -> ArchivedObjectsPolicy {
+        match self.archived_objects {
+            None | Some(ArchivedObjects::Unreadable) => ArchivedObjectsPolicy::Unreadable,
+            Some(ArchivedObjects::Hidden) => ArchivedObjectsPolicy::Hidden,
+            Some(ArchivedObjects::FailReads) => ArchivedObjectsPolicy::FailReads,
+            Some(ArchivedObjects::RestoreStatus) => ArchivedObjectsPolicy::RestoreStatus,
+        }
+    }
+
+    pub fn recent_writes_window(&self) -> Duration {
+        self.recent_writes_window.map_or(Duration::ZERO, Duration::from_secs)
+    }
+
+    pub fn directory_rename(&self) -> Option<DirectoryRenameConfig> {
+        if !self.allow_directory_rename {
+            return None;
+        }
+        let mut config = DirectoryRenameConfig::default();
+        if let Some(max_objects) = self.max_directory_rename_objects {
+            config.max_objects = max_objects as usize;
+        }
+        Some(config)
+    }
+
+    pub fn statfs_capacity(&self) -> StatfsCapacity {
+        if let Some(cache) = self.cache.as_ref().filter(|_| self.statfs_from_cache) {
+            return StatfsCapacity::Directory(cache.clone());
+        }
+        match self.statfs_capacity {
+            Some(capacity) => {
+                let free = self.statfs_free.unwrap_or(capacity).min(capacity);
+                StatfsCapacity::Fixed {
+                    total: capacity * 1024 * 1024,
+                    free: free * 1024 * 1024,
+                }
+            }
+            None => StatfsCapacity::Unlimited,
+        }
+    }
+
+    /// The tags of the objects created through the mount.
+    pub fn object_tags(&self) -> anyhow::Result<ObjectTags> {
+        let mut object_tags = ObjectTags::new();
+        for (key, value) in &self.object_tag {
+            if object_tags.insert(key.clone(), value.clone()).is_some() {
+                return Err(anyhow!("--object-tag {key} is specified more than once"));
+            }
+        }
+        if object_tags.len() > MAX_OBJECT_TAGS {
+            return Err(anyhow!("objects can have at most {MAX_OBJECT_TAGS} tags"));
+        }
+        Ok(object_tags)
+    }
+
+    /// How the content types of new objects are chosen, if they are set.
+    pub fn content_types(&self) -> Option<ContentTypes> {
+        self.detect_content_type
+            .then(|| ContentTypes::new(self.content_type_mapping.clone(), self.sniff_content_type))
+    }
+
+    /// The maximum size of files, which can't be larger than the largest object that can be uploaded with the write
+    /// part size.
+    pub fn max_file_size(&self) -> anyhow::Result<Option<u64>> {
+        let Some(max_file_size) = self.max_file_size else {
+            return Ok(None);
+        };
+        let max_upload_size = self
+            .write_part_size
+            .unwrap_or(self.part_size)
+            .saturating_mul(MAX_S3_MULTIPART_UPLOAD_PARTS);
+        if max_file_size > max_upload_size {
+            return Err(anyhow!(
+                "--max-file-size {max_file_size} is larger than the largest object that can be uploaded with a write \
+                 part size of {} bytes, {max_upload_size} bytes; increase --write-part-size",
+                self.write_part_size.unwrap_or(self.part_size)
+            ));
+        }
+        Ok(Some(max_file_size))
+    }
+
+    pub fn recursive_delete(&self) -> Option<RecursiveDeleteConfig> {
+        if !self.allow_recursive_delete {
+            return None;
+        }
+        let mut config = RecursiveDeleteConfig::default();
+        if let Some(max_objects) = self.max_recursive_delete_objects {
+            config.max_objects = max_objects as usize;
+        }
+        Some(config)
+    }
+
+    /// Configure copying the objects of removed files to a trash prefix, purged on `runtime`, if enabled.
+    pub fn trash(&self, runtime: &Runtime) -> Option<TrashConfig> {
+        let prefix = self.trash_prefix.clone()?;
+        let retention = self.trash_retention.map(Duration::from_secs);
+        Some(TrashConfig::new(prefix, retention, runtime.clone()))
+    }
+
+    pub fn key_filter(&self) -> KeyFilter {
+        let filter = self
+            .include
+            .iter()
+            .fold(KeyFilter::default(), |filter, pattern| filter.include(pattern));
+        self.exclude
+            .iter()
+            .fold(filter, |filter, pattern| filter.exclude(pattern))
+    }
+
+    pub fn shadowing(&self) -> ShadowingPolicy {
+        match self.shadowing {
+            None | Some(Shadowing::Directory) => ShadowingPolicy::DirectoryWins,
+            Some(Shadowing::File) => ShadowingPolicy::FileWins,
+            Some(Shadowing::SuffixFile) => ShadowingPolicy::SuffixFile,
+        }
+    }
+
+    /// Configure listing directories from S3 Inventory reports on `runtime`, if enabled.
+    pub fn inventory(&self, runtime: &Runtime) -> Option<InventoryConfig> {
+        let location = self.inventory.clone()?;
+        let refresh_interval = Duration::from_secs(self.inventory_refresh_interval.unwrap_or(3600));
+        Some(InventoryConfig::new(location, refresh_interval, runtime.clone()))
+    }
+
+    /// Load the snapshot of `s3_path` to mount with `client`, if configured.
+    pub fn snapshot(&self, client: &impl ObjectClient, s3_path: &S3Path) -> anyhow::Result<Option<Snapshot>> {
+        let Some(time) = self.snapshot_time else {
+            return Ok(None);
+        };
+        let snapshot = block_on(Snapshot::load(client, s3_path, time))
+            .with_context(|| format!("failed to load the snapshot of the bucket at {time}"))?;
+        Ok(Some(snapshot))
+    }
+
+    /// Open the table keeping the inode numbers of `s3_path` across mounts, if configured.
+    pub fn inode_table(&self, s3_path: &S3Path) -> anyhow::Result<Option<InodeTable>> {
+        let Some(path) = &self.inode_table else {
+            // Overlayfs expects the inode numbers of its lower layers to be stable, at least while it is mounted, and
+            // access times are tracked by inode number.
+            return Ok((self.overlayfs_lower || self.atime).then(InodeTable::in_memory));
+        };
+        let inode_table = InodeTable::open(path, s3_path)
+            .with_context(|| format!("failed to open inode table {}", path.display()))?;
+        Ok(Some(inode_table))
+    }
+
+    /// Load the options overridden under prefixes of `s3_path`, if configured.
+    pub fn prefix_overrides(&self, s3_path: &S3Path) -> anyhow::Result<PrefixOverrides> {
+        let Some(path) = &self.prefix_overrides else {
+            return Ok(Default::default());
+        };
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read prefix overrides {}", path.display()))?;
+        PrefixOverrides::from_json(&s3_path.prefix, &json)
+            .with_context(|| format!("failed to parse prefix overrides {}", path.display()))
+    }
+
+    /// Rules choosing the storage class of new objects under `s3_path` by their key.
+    pub fn storage_class_rules(&self, s3_path: &S3Path) -> StorageClassRules {
+        StorageClassRules::new(&s3_path.prefix, self.storage_class_rule.clone())
+    }
+
+    /// Build the metadata store of the manifest to serve `s3_path` from, if configured. The returned directory holds
+    /// the store, and must be kept until the file system is unmounted.
+    #[cfg(feature = "manifest")]
+    pub fn metadata_manifest(
+        &self,
+        s3_path: &S3Path,
+    ) -> anyhow::Result<Option<(ManifestMetablock, tempfile::TempDir)>> {
+        let (Some(manifest_path), Some(manifest_checksum)) = (&self.metadata_manifest, self.metadata_manifest_checksum)
+        else {
+            return Ok(None);
+        };
+        let channel = ChannelConfig {
+            directory_name: String::new(),
+            bucket_name: s3_path.bucket.to_string(),
+            prefix: s3_path.prefix.to_string(),
+            manifest_path: manifest_path.clone(),
+            manifest_checksum,
+        };
+        let store_dir = tempfile::tempdir().context("failed to create the manifest metadata store")?;
+        let db_path = store_dir.path().join("metadata.db");
+        ingest_manifest(&[channel], &db_path)
+            .with_context(|| format!("failed to ingest manifest {}", manifest_path.display()))?;
+        let manifest = Manifest::new(&db_path).context("failed to open the manifest metadata store")?;
+        let metablock = ManifestMetablock::new(manifest).context("failed to load the manifest metadata store")?;
+        Ok(Some((metablock, store_dir)))
+    }
+
+    pub fn filesystem_config(&self, sse: ServerSideEncryption, s3_personality: S3Personality) -> S3FilesystemConfig {
+        let mut filesystem_config = S3FilesystemConfig::default();
+        if let Some(uid) = self.uid {
+            filesystem_config.uid = uid;
+        }
+        if let Some(gid) = self.gid {
+            filesystem_config.gid = gid;
+        }
+        if let Some(dir_mode) = self.dir_mode {
+            filesystem_config.dir_mode = dir_mode;
+        }
+        if let Some(file_mode) = self.file_mode {
+            filesystem_config.file_mode = file_mode;
+        }
+        filesystem_config.storage_class = self.storage_class.clone();
+        filesystem_config.allow_delete = self.allow_delete;
+        filesystem_config.allow_overwrite = self.allow_overwrite;
+        filesystem_config.incremental_upload = self.incremental_upload;
+        filesystem_config.append_by_copy = self.allow_append;
+        filesystem_config.write_after_fsync = self.allow_write_after_fsync;
+        filesystem_config.conditional_overwrite = self.conditional_overwrite;
+        filesystem_config.write_staging_dir = self.write_staging_dir.clone();
+        filesystem_config.write_staging_max_size = self.write_staging_max_size.map(|mib| mib * 1024 * 1024);
+        filesystem_config.max_write_bytes = self.max_write_bytes_per_mount;
+        filesystem_config.max_concurrent_uploads = self.max_concurrent_uploads.map(|n| n as usize);
+        filesystem_config.max_concurrent_parts_per_upload = self.max_concurrent_parts_per_upload;
+        filesystem_config.defer_staged_uploads = self.defer_uploads;
+        filesystem_config.writeback_cache = self.writeback_cache;
+        filesystem_config.max_fuse_request_size = self.max_fuse_request_size;
+        filesystem_config.statfs_capacity = self.statfs_capacity();
+        filesystem_config.s3_personality = s3_personality;
+        filesystem_config.server_side_encryption = sse;
+        filesystem_config.cache_config = self.cache_config();
+        filesystem_config.mem_limit = self.mem_limit();
+        filesystem_config.upload_checksum_algorithm = self.upload_checksum_algorithm(s3_personality);
+        filesystem_config.prefetch_stats_xattr = self.prefetch_stats_xattr;
+        filesystem_config.invalidate_xattr = self.invalidate_xattr;
+        filesystem_config.keep_page_cache = self.keep_page_cache;
+        filesystem_config.direct_io_xattr = self.direct_io_xattr;
+        filesystem_config.user_metadata_xattrs = self.user_metadata_xattrs;
+        filesystem_config.object_info_xattrs = self.object_info_xattrs;
+        filesystem_config.presigned_url_xattr = self.presigned_url_xattr.map(Duration::from_secs);
+        filesystem_config.recursive_size_xattr = self.recursive_size_xattr;
+        filesystem_config.tag_xattrs = self.tag_xattrs;
+        filesystem_config.symlinks = self.symlinks;
+        filesystem_config.decode_content_encoding = self.decode_content_encoding;
+        filesystem_config.mtime_metadata = self.mtime_metadata;
+        filesystem_config.mode_metadata = self.mode_metadata;
+        filesystem_config.atime = self.atime;
+        filesystem_config.atime_flush_interval = self.atime_flush_interval.map(Duration::from_secs);
+        filesystem_config.version_directories = self.version_directories;
+        filesystem_config.key_delimiter = self.key_delimiter.unwrap_or_default();
+        filesystem_config.directory_markers = self.directory_markers;
+        filesystem_config.escape_names = self.escape_names;
+        filesystem_config.overlayfs_lower = self.overlayfs_lower;
+        filesystem_config.archived_objects = self.archived_objects();
+        filesystem_config.prefetcher_config.max_read_part_size = self.max_read_part_size.map(|size| size as usize);
+        if let Some(initial_read_window) = self.initial_read_window {
+            filesystem_config.prefetcher_config.initial_request_size = initial_read_window as usize;
+        }
+        if let Some(max_read_window) = self.max_read_window {
+            filesystem_config.prefetcher_config.max_read_window_size = max_read_window as usize;
+        }
+        if let Some(growth_factor) = self.read_window_growth_factor {
+            filesystem_config.prefetcher_config.sequential_prefetch_multiplier = growth_factor as usize;
+        }
+        filesystem_config.prefetcher_config.memory_budget = self.prefetch_memory_budget;
+        filesystem_config.prefetcher_config.format_aware = self.format_aware_prefetch;
+        filesystem_config.prefetcher_config.hedge_percentile = self.hedge_percentile;
+        filesystem_config
+    }
+
+    pub fn cache_block_size_in_bytes(&self) -> u64 {
+        if let Some(kib) = self.cache_block_size {
+            return kib * 1024;
+        }
+        1024 * 1024 // 1 MiB block size - default for disk cache and for express cache
+    }
+
+    fn cache_config(&self) -> CacheConfig {
+        let mut metadata_cache_ttl = self.metadata_ttl.unwrap_or_else(|| {
+            if self.cache.is_some() || self.cache_xz.is_some() {
+                // When the data cache is enabled, use 1min as metadata-ttl.
+                TimeToLive::Duration(Duration::from_secs(60))
+            } else {
+                TimeToLive::Minimal
+            }
+        });
+        if matches!(metadata_cache_ttl, TimeToLive::Duration(Duration::ZERO)) {
+            const ZERO_TTL_WARNING: &str = "The '--metadata-ttl 0' setting is no longer supported, is now interpreted as 'minimal', and will be removed in a future release. Use '--metadata-ttl minimal' instead";
+            tracing::warn!("{}", ZERO_TTL_WARNING);
+            if !self.foreground {
+                // Ensure warning is visible even when not redirecting logs to stdout.
+                use owo_colors::{OwoColorize, Stream::Stderr, Style};
+                eprintln!(
+                    "{}: {}",
+                    "warning".if_supports_color(Stderr, |text| text.style(Style::new().yellow().bold())),
+                    ZERO_TTL_WARNING
+                );
+            }
+            metadata_cache_ttl = TimeToLive::Minimal;
+        }
+        tracing::trace!("using metadata TTL setting {metadata_cache_ttl:?}");
+        let mut cache_config = CacheConfig::new(metadata_cache_ttl);
+        if let Some(negative_cache_ttl) = self.negative_metadata_ttl {
+            cache_config = cache_config.with_negative_metadata_ttl(negative_cache_ttl);
+        }
+        cache_config.with_ttl_rules(self.metadata_ttl_rule.clone())
+    }
+
+    fn cache_express_bucket_name(&self) -> Option<S3Path> {
+        let bucket_name = self.cache_xz.to_owned()?;
+        let s3path = match bucket_name {
+            BucketNameOrS3Uri::BucketName(bucket_name) => S3Path::new(bucket_name, Prefix::empty()),
+            BucketNameOrS3Uri::S3Uri(s3_uri) => s3_uri,
+        };
+        Some(s3path)
+    }
+
+    fn express_data_cache_config(&self, sse: ServerSideEncryption) -> anyhow::Result<Option<ExpressDataCacheConfig>> {
+        match self.cache_express_bucket_name() {
+            Some(express_path) => {
+                if !express_path.prefix.as_str().is_empty() {
+                    return Err(anyhow!("cache-xz argument must not contain a prefix"));
+                }
+                let bucket_name = &self.s3_path()?.bucket;
+                let express_bucket_name = express_path.bucket.as_str();
+                let mut config = ExpressDataCacheConfig::new(express_bucket_name, bucket_name)
+                    .block_size(self.cache_block_size_in_bytes())
+                    .sse(sse);
+                if let Some(kib) = self.cache_xz_max_object_size {
+                    config = config.max_object_size((kib * 1024) as usize);
+                }
+                Ok(Some(config))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn disk_data_cache_config(&self) -> anyhow::Result<Option<DiskDataCacheConfig>> {
+        let Some(path) = self.cache.as_ref() else {
+            return Ok(None);
+        };
+        let max_size = match self.max_cache_size {
+            // Fallback to no data cache.
+            Some(0) => return Ok(None),
+            Some(max_size_in_mib) => Some((max_size_in_mib * 1024 * 1024) as usize),
+            None => None,
+        };
+        let cache_limit = match (self.cache_low_watermark, self.cache_high_watermark, max_size) {
+            (Some(low), Some(high), _) if high <= low => {
+                return Err(anyhow!(
+                    "--cache-high-watermark must be greater than --cache-low-watermark"
+                ));
+            }
+            (Some(low), Some(high), max_size) => CacheLimit::Watermarks {
+                low_ratio: low as f64 / 100.0,
+                high_ratio: high as f64 / 100.0,
+                max_size,
+            },
+            (_, _, Some(max_size)) => CacheLimit::TotalSize { max_size },
+            (_, _, None) => CacheLimit::default(),
+        };
+        let cache_config = DiskDataCacheConfig {
+            cache_directory: path.clone(),
+            block_size: self.cache_block_size_in_bytes(),
+            limit: cache_limit,
+            eviction_policy: self.cache_eviction_policy()?,
+            shared: self.shared_cache,
+            persistent: self.persist_cache,
+            admission_policy: match self.cache_admission_window {
+                Some(window) => AdmissionPolicy::SecondRead {
+                    window: Duration::from_secs(window),
+                },
+                None => AdmissionPolicy::All,
+            },
+            pinning: self.cache_pinning()?,
+            encryption_key: self.cache_encryption_key()?,
+            compression_level: self
+                .cache_compression
+                .then(|| self.cache_compression_level.unwrap_or(DEFAULT_CACHE_COMPRESSION_LEVEL)),
+            io_backend: if self.cache_io_uring {
+                DiskIoBackend::IoUring {
+                    direct: self.cache_direct_io,
+                }
+            } else {
+                DiskIoBackend::Sync
+            },
+            account: self.cache_account()?,
+        };
+        Ok(Some(cache_config))
+    }
+
+    /// The account of this mount in a shared cache directory, named after the mounted bucket and prefix by default.
+    fn cache_account(&self) -> anyhow::Result<Option<CacheAccount>> {
+        if !self.shared_cache {
+            return Ok(None);
+        }
+        let name = match &self.cache_account {
+            Some(name) => name.clone(),
+            None => {
+                let s3_path = self.s3_path()?;
+                format!("{}/{}", s3_path.bucket, s3_path.prefix.as_str())
+                    .trim_end_matches('/')
+                    .to_owned()
+            }
+        };
+        Ok(Some(CacheAccount {
+            name,
+            quota: self.cache_quota.map(|size_in_mib| (size_in_mib * 1024 * 1024) as usize),
+        }))
+    }
+
+    fn cache_pinning(&self) -> anyhow::Result<Option<PinningConfig>> {
+        if self.cache_pin_prefix.is_empty() {
+            return Ok(None);
+        }
+        let mounted_prefix = self.s3_path()?.prefix;
+        let prefixes = self
+            .cache_pin_prefix
+            .iter()
+            .map(|prefix| format!("{}{prefix}", mounted_prefix.as_str()))
+            .collect();
+        Ok(Some(PinningConfig {
+            prefixes,
+            max_ratio: self.cache_pin_max_percent.unwrap_or(50) as f64 / 100.0,
+        }))
+    }
+
+    fn cache_encryption_key(&self) -> anyhow::Result<Option<CacheEncryptionKey>> {
+        if let Some(path) = &self.cache_encryption_key_file {
+            let key = CacheEncryptionKey::from_file(path)
+                .with_context(|| format!("invalid cache encryption key file {}", path.display()))?;
+            return Ok(Some(key));
+        }
+        if !self.cache_encryption {
+            return Ok(None);
+        }
+        if self.shared_cache || self.persist_cache {
+            return Err(anyhow!(
+                "--cache-encryption generates a new key at every mount, which can't read a shared or persisted cache; \
+                 use --cache-encryption-key-file instead"
+            ));
+        }
+        Ok(Some(CacheEncryptionKey::ephemeral()))
+    }
+
+    fn cache_eviction_policy(&self) -> anyhow::Result<EvictionPolicy> {
+        let policy = self.cache_eviction_policy.unwrap_or(CacheEvictionPolicy::Lru);
+        match (policy, self.cache_eviction_ttl) {
+            (CacheEvictionPolicy::Ttl, Some(ttl)) => Ok(EvictionPolicy::Ttl {
+                ttl: Duration::from_secs(ttl),
+            }),
+            (CacheEvictionPolicy::Ttl, None) => {
+                Err(anyhow!("--cache-eviction-policy ttl requires --cache-eviction-ttl"))
+            }
+            (_, Some(_)) => Err(anyhow!("--cache-eviction-ttl requires --cache-eviction-policy ttl")),
+            (CacheEvictionPolicy::Lru, None) => Ok(EvictionPolicy::Lru),
+            (CacheEvictionPolicy::Lfu, None) => Ok(EvictionPolicy::Lfu),
+            (CacheEvictionPolicy::Fifo, None) => Ok(EvictionPolicy::Fifo),
+        }
+    }
+
+    pub fn data_cache_config(&self, sse: ServerSideEncryption) -> anyhow::Result<DataCacheConfig> {
+        let disk_cache_config = self.disk_data_cache_config()?;
+        let express_cache_config = self.express_data_cache_config(sse)?;
+        match (&disk_cache_config, &express_cache_config) {
+            (None, Some(_)) => {
+                tracing::trace!("using S3 Express One Zone bucket as a cache for object content");
+            }
+            (Some(_), None) => {
+                tracing::trace!("using local disk as a cache for object content");
+            }
+            (Some(_), Some(_)) => {
+                tracing::trace!("using both local disk and S3 Express One Zone bucket as a cache for object content");
+            }
+            _ => {
+                tracing::trace!("using no cache");
+            }
+        }
+        let memory_cache_config = self.memory_cache_size.map(|size_in_mib| MemoryCacheConfig {
+            max_size: (size_in_mib * 1024 * 1024) as usize,
+        });
+        let prefix_metrics_config = match self.cache_metrics_prefix_depth {
+            Some(depth) => Some(PrefixMetricsConfig {
+                depth: depth as usize,
+                mounted_prefix: self.s3_path()?.prefix.as_str().to_owned(),
+            }),
+            None => None,
+        };
+        let scrub_interval = self.cache_scrub_interval.map(Duration::from_secs);
+        let write_through_config = (self.cache_write_through || self.cache_write_through_shared).then(|| {
+            let max_size_in_mib = self.cache_write_through_max_size.unwrap_or(64);
+            WriteThroughConfig {
+                max_object_size: (max_size_in_mib * 1024 * 1024) as usize,
+                shared: self.cache_write_through_shared,
+            }
+        });
+        let peer_cache_config = self.cache_peer_address.map(|address| {
+            PeerCacheConfig::new(address, self.cache_peers.clone()).block_size(self.cache_block_size_in_bytes())
+        });
+        Ok(DataCacheConfig {
+            disk_cache_config,
+            memory_cache_config,
+            scrub_interval,
+            express_cache_config,
+            peer_cache_config,
+            prefix_metrics_config,
+            write_through_config,
+            pin_xattr: self.cache_pin_xattr,
+        })
+    }
+
+    /// The server-side encryption configuration.
+    ///
+    /// Disallow specifying `--sse-kms-key-id` when `--sse=AES256` as this is not allowed by the S3 API.
+    pub fn server_side_encryption(&self) -> anyhow::Result<ServerSideEncryption> {
+        if self.sse_kms_key_id.is_some() && self.sse.as_deref() == Some("AES256") {
+            return Err(anyhow!("--sse-kms-key-id can not be used with --sse AES256"));
+        }
+        Ok(ServerSideEncryption::new(self.sse.clone(), self.sse_kms_key_id.clone()))
+    }
+
+    /// Generates a logging configuration based on the CLI arguments.
+    ///
+    /// This includes random string generation which can change with each invocation,
+    /// so once created the [LoggingConfig] should be cloned if another owned copy is required.
+    pub fn make_logging_config(&self) -> LoggingConfig {
+        let default_filter = if self.no_log {
+            String::from("off")
+        } else {
+            let mut filter = if self.debug {
+                String::from("debug")
+            } else {
+                format!("warn,{MOUNTPOINT_LOG_TARGET}=info")
+            };
+            let crt_verbosity = if self.debug_crt { "debug" } else { "off" };
+            filter.push_str(&format!(",{AWSCRT_LOG_TARGET}={crt_verbosity}"));
+            // Only turn off metrics if neither --log-metrics nor --debug is set.
+            // In debug mode, we want to see all available information including metrics.
+            if !self.log_metrics && !self.debug {
+                filter.push_str(&format!(",{}=off", metrics::TARGET_NAME));
+            }
+            filter
+        };
+
+        let log_file = self.log_directory.as_ref().map(|dir| prepare_log_file_name(dir));
+
+        LoggingConfig {
+            log_file,
+            log_to_stdout: self.foreground,
+            default_filter,
+        }
+    }
+
+    /// Human-readable description of the bucket being mounted
+    pub fn bucket_description(&self) -> anyhow::Result<String> {
+        let s3_path = self.s3_path()?;
+        Ok(s3_path.bucket_description())
+    }
+
+    fn clone_fd_from_env(&self) -> bool {
+        std::env::var("UNSTABLE_MOUNTPOINT_CLONE_FUSE_FD")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false)
+    }
+
+    pub fn fuse_session_config(&self) -> anyhow::Result<FuseSessionConfig> {
+        let mount_point = MountPoint::new(&self.mount_point).context("Failed to create mount point")?;
+        let fuse_options = FuseOptions {
+            read_only: self.read_only,
+            auto_unmount: self.auto_unmount,
+            allow_root: self.allow_root,
+            allow_other: self.allow_other,
+            clone_fd: self.clone_fd_from_env(),
+            max_read: self.max_fuse_request_size,
+        };
+        FuseSessionConfig::new(mount_point, fuse_options, self.max_threads as usize)
+    }
+
+    fn user_agent(&self, instance_info: &InstanceInfo, version: &str) -> UserAgent {
+        let user_agent_prefix = if let Some(custom_prefix) = &self.user_agent_prefix {
+            format!("{custom_prefix} mountpoint-s3/{version}")
+        } else {
+            format!("mountpoint-s3/{version}")
+        };
+        let mut user_agent = UserAgent::new_with_instance_info(Some(user_agent_prefix), instance_info);
+        if self.read_only {
+            user_agent.value("mp-readonly");
+        }
+        if self.is_fstab {
+            user_agent.value("mp-fstab");
+        }
+        match (&self.cache, self.cache_express_bucket_name()) {
+            (None, None) => (),
+            (None, Some(_)) => {
+                user_agent.key_value("mp-cache", "shared");
+            }
+            (Some(_), None) => {
+                user_agent.key_value("mp-cache", "local");
+            }
+            (Some(_), Some(_)) => {
+                user_agent.key_values("mp-cache", &["shared", "local"]);
+            }
+        }
+        if let Some(ttl) = self.metadata_ttl {
+            user_agent.key_value("mp-cache-ttl", &ttl.to_string());
+        }
+        if let Some(interfaces) = &self.bind {
+            user_agent.key_value("mp-nw-interfaces", &interfaces.len().to_string());
+        }
+        if self.otlp_endpoint.is_some() {
+            user_agent.value("mp-otlp");
+        }
+        user_agent
+    }
+
+    fn throughput_target_gbps(&self, instance_info: &InstanceInfo) -> TargetThroughputSetting {
+        let throughput_target_gbps = self.maximum_throughput_gbps.map(|t| TargetThroughputSetting::User { gbps: t as f64 }).unwrap_or_else(|| {
+            match autoconfigure::network_throughput(instance_info) {
+                Ok(throughput) => TargetThroughputSetting::Instance { gbps: throughput },
+                Err(e) => {
+                    tracing::warn!(
+                        "failed to detect network throughput. Using {} gbps as throughput. \
+                        Use --maximum-throughput-gbps CLI flag to configure a target throughput appropriate for the instance. Detection failed due to: {:?}",
+                        TargetThroughputSetting::DEFAULT_TARGET_THROUGHPUT_GBPS,
+                        e,
+                        );
+                    TargetThroughputSetting::Default
+                }
+            }
+        });
+        tracing::info!("target network throughput {} Gbps", throughput_target_gbps.value());
+        throughput_target_gbps
+    }
+
+    pub fn personality(&self) -> Option<S3Personality> {
+        self.bucket_type.map(|bucket_type| bucket_type.to_personality())
+    }
+
+    fn part_config(&self) -> PartConfig {
+        PartConfig::with_read_write_sizes(
+            self.read_part_size.unwrap_or(self.part_size) as usize,
+            self.write_part_size.unwrap_or(self.part_size) as usize,
+        )
+    }
+
+    fn auth_config(&self) -> S3ClientAuthConfig {
+        if self.no_sign_request {
+            S3ClientAuthConfig::NoSigning
+        } else if let Some(profile_name) = self.profile.clone() {
+            S3ClientAuthConfig::Profile(profile_name)
+        } else {
+            S3ClientAuthConfig::Default
+        }
+    }
+
+    pub fn client_config(&self, version: &str) -> ClientConfig {
+        let instance_info = InstanceInfo::new();
+        let user_agent = self.user_agent(&instance_info, version);
+        let throughput_target = self.throughput_target_gbps(&instance_info);
+        let region = autoconfigure::get_region(&instance_info, self.region.clone());
+
+        ClientConfig {
+            region,
+            endpoint_url: self.endpoint_url.clone(),
+            addressing_style: self.addressing_style(),
+            dual_stack: self.dual_stack,
+            transfer_acceleration: self.transfer_acceleration,
+            auth_config: self.auth_config(),
+            requester_pays: self.requester_pays,
+            expected_bucket_owner: self.expected_bucket_owner.clone(),
+            throughput_target,
+            bind: self.bind.clone(),
+            part_config: self.part_config(),
+            user_agent,
+        }
+    }
+}
+
+fn parse_perm_bits(perm_bit_str: &str) -> Result<u16, anyhow::Error> {
+    let perm = u16::from_str_radix(perm_bit_str, 8).map_err(|_| anyhow!("must be a valid octal number"))?;
+    if perm > 0o777 {
+        Err(anyhow!("only user/group/other permissions are supported"))
+    } else {
+        Ok(perm)
+    }
+}
+
+fn parse_percentile(percentile: &str) -> anyhow::Result<f64> {
+    let percentile: f64 = percentile.parse().map_err(|_| anyhow!("must be a number"))?;
+    if (0.0..=100.0).contains(&percentile) {
+        Ok(percentile)
+    } else {
+        Err(anyhow!("must be between 0 and 100"))
+    }
+}
+
+fn parse_snapshot_time(time: &str) -> anyhow::Result<OffsetDateTime> {
+    OffsetDateTime::parse(time, &Rfc3339).context("must be a time in RFC 3339 format, such as 2024-01-02T03:04:05Z")
+}
+
+/// Validate a kms-key-id CLI parameter. Currently, Mountpoint only supports KMS Key ARNs.
+#[cfg(feature = "manifest")]
+fn parse_crc32c_base64(checksum: &str) -> anyhow::Result<Crc32cBase64> {
+    let checksum = crc32c_from_base64(checksum).context("must be a base64-encoded CRC32C checksum")?;
+    Ok(Crc32cBase64::new(checksum.value()))
+}
+
+fn parse_object_tag(tag: &str) -> anyhow::Result<(String, String)> {
+    match tag.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(anyhow!("must be of the form KEY=VALUE")),
+    }
+}
+
+fn parse_trash_prefix(prefix: &str) -> anyhow::Result<Prefix> {
+    let prefix = Prefix::new(prefix)?;
+    if prefix.as_str().is_empty() {
+        return Err(anyhow!("must not be empty"));
+    }
+    Ok(prefix)
+}
+
+fn parse_kms_key_arn(kms_key_arn: &str) -> anyhow::Result<String> {
+    if kms_key_arn.starts_with("arn:") && kms_key_arn.contains(":key") {
+        Ok(kms_key_arn.to_owned())
+    } else {
+        Err(anyhow!(
+            "KMS Key ARN is only accepted as a key identifier, Key Alias ARN is not accepted"
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BucketNameOrS3Uri {
+    BucketName(Bucket),
+    S3Uri(S3Path),
+}
+
+fn parse_bucket_name_or_s3_uri(bucket_name_or_uri: &str) -> Result<BucketNameOrS3Uri, S3PathError> {
+    if bucket_name_or_uri.starts_with("s3://") {
+        Ok(BucketNameOrS3Uri::S3Uri(S3Path::parse_s3_uri(bucket_name_or_uri)?))
+    } else {
+        Ok(BucketNameOrS3Uri::BucketName(Bucket::new(
+            bucket_name_or_uri.to_owned(),
+        )?))
+    }
 }
 
-impl BucketType {
-    pub fn to_personality(self) -> S3Personality {
-        match self {
-            Self::GeneralPurpose => S3Personality::Standard,
-            Self::Directory => S3Personality::ExpressOneZone,
-        }
+#[cfg(test)]
+mod tests {
+    use mountpoint_s3_fs::metablock::InodeKind;
+
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("s3://bucket--eun1-az1--x-s3", true; "s3:// example bucket")]
+    #[test_case("s3://bucket--eun1-az1--x-s3/", true; "s3:// example bucket with empty prefix")]
+    #[test_case("bucket--eun1-az1--x-s3", true; "raw example bucket")]
+    #[test_case("bucket--eun1-az1--x-s3/", false; "raw example bucket with /")]
+    #[test_case("s3://bucket--eun1-az1--x-s3/prefix", false; "s3:// example bucket with invalid prefix")]
+    #[test_case("s3://bucket--eun1-az1--x-s3/prefix/", false; "s3:// example bucket with prefix (prefixes not allowed)")]
+    fn validate_s3_express_cache_bucket_name(bucket_name: &str, valid: bool) {
+        fn _parse_cli_args(bucket_name: &str) -> Option<()> {
+            let cli_args =
+                CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--cache-xz", bucket_name]).ok()?;
+            let sse = cli_args.server_side_encryption().unwrap();
+            let _express_data_cache_config = cli_args.express_data_cache_config(sse).ok()??;
+            Some(())
+        }
+        assert_eq!(
+            valid,
+            _parse_cli_args(bucket_name).is_some(),
+            "bucket_name: {bucket_name}, expected: {valid}"
+        );
+    }
+
+    #[test_case("s3://bucket", Ok(("bucket".to_string(), "".to_string())); "s3:// example bucket")]
+    #[test_case("s3://bucket/", Ok(("bucket".to_string(), "".to_string())); "s3:// example bucket with empty prefix")]
+    #[test_case("bucket", Ok(("bucket".to_string(), "".to_string())); "raw example bucket")]
+    #[test_case("arn:aws:s3::00000000:accesspoint/s3-bucket-test.mrap", Ok(("arn:aws:s3::00000000:accesspoint/s3-bucket-test.mrap".to_string(), "".to_string())); "ARN example")]
+    #[test_case("arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name", Ok(("arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name".to_string(), "".to_string())); "S3 outpost accesspoint ARN")]
+    #[test_case("s3://arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name", Err("the bucket must have a valid name (only letters, numbers, . and -). ARNs are not supported in s3:// URIs".to_string()); "S3 URI with ARN without trailing slash")]
+    #[test_case("s3://arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name/", Err("the bucket must have a valid name (only letters, numbers, . and -). ARNs are not supported in s3:// URIs".to_string()); "S3 URI with ARN")]
+    #[test_case("s3://bucket/prefix/", Ok(("bucket".to_string(), "prefix/".to_string())); "s3:// example bucket with prefix")]
+    #[test_case("s3://bucket/prefix", Err("invalid bucket prefix".to_string()); "s3:// example bucket with prefix with missing delimeter")]
+    fn validate_bucket_path(bucket_name: &str, expected_path: Result<(String, String), String>) {
+        fn get_err(bucket_name: &str) -> Result<(String, String), anyhow::Error> {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", bucket_name, "test/location"])?;
+            cli_args
+                .s3_path()
+                .map(|path| (path.bucket.to_string(), path.prefix.as_str().to_string()))
+        }
+        let s3_path = get_err(bucket_name);
+        match (s3_path, expected_path) {
+            (Ok(s3_path), Ok(expected_path)) => {
+                assert_eq!(
+                    expected_path, s3_path,
+                    "bucket_name: {s3_path:?}, expected: {expected_path:?}",
+                );
+            }
+            (Err(err), Err(should_contain)) => {
+                assert!(
+                    format!("{err:#}").contains(&should_contain),
+                    "Actual error `{err:#}` does not contain string {should_contain:?}"
+                )
+            }
+            (s3_path, expected_path) => {
+                panic!("bucket_name: {s3_path:?}, expected: {expected_path:?}");
+            }
+        }
+    }
+
+    // https://docs.aws.amazon.com/kms/latest/developerguide/concepts.html#key-id
+    #[test_case("arn:aws:kms:eu-west-1:151381207180:key/dabe1478-fe48-47ca-b6f8-ca044b643a82", true; "KMS Key ARN")]
+    #[test_case("arn:aws:kms:us-west-2:111122223333:key/mrk-1234abcd12ab34cd56ef1234567890ab", true; "Multi-region KMS Key ARN")]
+    #[test_case("", false; "empty")]
+    #[test_case("1234abcd-12ab-34cd-56ef-1234567890ab", false; "KMS Key ID")]
+    #[test_case("mrk-1234abcd12ab34cd56ef1234567890ab", false; "Multi-region KMS Key ID")]
+    #[test_case("alias/ExampleAlias", false; "KMS Key alias name")]
+    #[test_case("arn:aws:kms:us-west-2:111122223333:alias/ExampleAlias", false; "KMS Key alias ARN")]
+    fn test_parse_kms_key_arn(kms_key_id: &str, valid: bool) {
+        let parsed = parse_kms_key_arn(kms_key_id);
+        if valid {
+            assert_eq!(parsed.expect("valid kms key identifier"), kms_key_id);
+        } else {
+            parsed.expect_err("invalid kms key identifier");
+        }
+    }
+
+    #[test]
+    fn test_read_window_options() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--initial-read-window",
+            "0",
+            "--max-read-window",
+            "67108864",
+            "--read-window-growth-factor",
+            "4",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.prefetcher_config.initial_request_size, 0);
+        assert_eq!(config.prefetcher_config.max_read_window_size, 64 * 1024 * 1024);
+        assert_eq!(config.prefetcher_config.sequential_prefetch_multiplier, 4);
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        let default_config = S3FilesystemConfig::default();
+        assert_eq!(
+            config.prefetcher_config.initial_request_size,
+            default_config.prefetcher_config.initial_request_size
+        );
+        assert_eq!(
+            config.prefetcher_config.sequential_prefetch_multiplier,
+            default_config.prefetcher_config.sequential_prefetch_multiplier
+        );
+
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--read-window-growth-factor",
+            "1",
+        ])
+        .expect_err("growth factor must be at least 2");
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--max-read-window", "0"])
+            .expect_err("max read window must be positive");
+    }
+
+    #[test]
+    fn test_cache_eviction_policy() {
+        let policy = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(
+                ["mount-s3", "bucket", "test/location", "--cache", "/tmp/cache"]
+                    .iter()
+                    .chain(args),
+            )
+            .unwrap();
+            cli_args
+                .data_cache_config(ServerSideEncryption::default())
+                .map(|config| config.disk_cache_config.unwrap().eviction_policy)
+        };
+        assert_eq!(policy(&[]).unwrap(), EvictionPolicy::Lru);
+        assert_eq!(
+            policy(&["--cache-eviction-policy", "lfu"]).unwrap(),
+            EvictionPolicy::Lfu
+        );
+        assert_eq!(
+            policy(&["--cache-eviction-policy", "fifo"]).unwrap(),
+            EvictionPolicy::Fifo
+        );
+        assert_eq!(
+            policy(&["--cache-eviction-policy", "ttl", "--cache-eviction-ttl", "60"]).unwrap(),
+            EvictionPolicy::Ttl {
+                ttl: Duration::from_secs(60)
+            }
+        );
+        policy(&["--cache-eviction-policy", "ttl"]).expect_err("the ttl policy requires a TTL");
+        policy(&["--cache-eviction-ttl", "60"]).expect_err("a TTL requires the ttl policy");
+    }
+
+    #[test]
+    fn test_cache_admission_window() {
+        let policy = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .disk_cache_config
+                    .map(|config| config.admission_policy),
+            )
+        };
+        assert_eq!(policy(&["--cache", "/tmp/cache"]).unwrap(), Some(AdmissionPolicy::All));
+        assert_eq!(
+            policy(&["--cache", "/tmp/cache", "--cache-admission-window", "600"]).unwrap(),
+            Some(AdmissionPolicy::SecondRead {
+                window: Duration::from_secs(600)
+            })
+        );
+        policy(&["--cache-admission-window", "600"]).expect_err("the window requires a cache directory");
+    }
+
+    #[test]
+    fn test_cache_pin_prefix() {
+        let pinning = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .disk_cache_config
+                    .and_then(|config| config.pinning),
+            )
+        };
+        assert_eq!(pinning(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            pinning(&[
+                "--cache",
+                "/tmp/cache",
+                "--prefix",
+                "data/",
+                "--cache-pin-prefix",
+                "reference/",
+                "--cache-pin-prefix",
+                "lookup/",
+            ])
+            .unwrap(),
+            Some(PinningConfig {
+                prefixes: vec!["data/reference/".to_owned(), "data/lookup/".to_owned()],
+                max_ratio: 0.5,
+            })
+        );
+        assert_eq!(
+            pinning(&[
+                "--cache",
+                "/tmp/cache",
+                "--cache-pin-prefix",
+                "reference/",
+                "--cache-pin-max-percent",
+                "20",
+            ])
+            .unwrap()
+            .map(|pinning| pinning.max_ratio),
+            Some(0.2)
+        );
+        pinning(&["--cache-pin-prefix", "reference/"]).expect_err("pinning requires a cache directory");
+        pinning(&["--cache", "/tmp/cache", "--cache-pin-max-percent", "20"])
+            .expect_err("the percentage requires a pinned prefix");
+    }
+
+    #[test]
+    fn test_cache_pin_xattr() {
+        let pin_xattr = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.pin_xattr)
+        };
+        assert!(!pin_xattr(&["--cache", "/tmp/cache"]).unwrap());
+        assert!(pin_xattr(&["--cache", "/tmp/cache", "--cache-pin-xattr"]).unwrap());
+        pin_xattr(&["--cache-pin-xattr"]).expect_err("pinning requires a cache directory");
+    }
+
+    #[test]
+    fn test_readdir_readahead() {
+        let runtime = Runtime::new(futures::executor::ThreadPool::builder().pool_size(1).create().unwrap());
+        let readdir_readahead = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, anyhow::Error>(cli_args.readdir_readahead(&runtime).is_some())
+        };
+        assert!(!readdir_readahead(&[]).unwrap());
+        assert!(readdir_readahead(&["--readdir-readahead", "2"]).unwrap());
+        readdir_readahead(&["--readdir-readahead", "0"]).expect_err("at least one page is fetched ahead");
+    }
+
+    #[test]
+    fn test_inventory() {
+        let runtime = Runtime::new(futures::executor::ThreadPool::builder().pool_size(1).create().unwrap());
+        let inventory = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, anyhow::Error>(cli_args.inventory(&runtime).is_some())
+        };
+        assert!(!inventory(&["--read-only"]).unwrap());
+        assert!(
+            inventory(&[
+                "--read-only",
+                "--inventory",
+                "s3://inventory-bucket/bucket/config/",
+                "--inventory-refresh-interval",
+                "600"
+            ])
+            .unwrap()
+        );
+        inventory(&["--inventory", "s3://inventory-bucket/bucket/config/"]).expect_err("the mount must be read-only");
+        inventory(&["--read-only", "--inventory", "inventory-bucket"]).expect_err("the location is an S3 URI");
+    }
+
+    #[test]
+    fn test_snapshot_time() {
+        let snapshot_time = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, anyhow::Error>(cli_args.snapshot_time)
+        };
+        assert_eq!(snapshot_time(&["--read-only"]).unwrap(), None);
+        assert_eq!(
+            snapshot_time(&["--read-only", "--snapshot-time", "2024-01-02T03:04:05+01:00"]).unwrap(),
+            Some(OffsetDateTime::parse("2024-01-02T02:04:05Z", &Rfc3339).unwrap())
+        );
+        snapshot_time(&["--snapshot-time", "2024-01-02T03:04:05Z"]).expect_err("the mount must be read-only");
+        snapshot_time(&["--read-only", "--snapshot-time", "2024-01-02"])
+            .expect_err("the time includes the time of day");
+        snapshot_time(&[
+            "--read-only",
+            "--snapshot-time",
+            "2024-01-02T03:04:05Z",
+            "--inventory",
+            "s3://inventory-bucket/bucket/config/",
+        ])
+        .expect_err("snapshots are not listed from inventory reports");
+    }
+
+    #[test]
+    fn test_directory_markers() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.directory_markers);
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--directory-markers"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.directory_markers);
+    }
+
+    #[test]
+    fn test_version_directories() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.version_directories);
+
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--version-directories"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.version_directories);
+
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--read-only",
+            "--version-directories",
+            "--snapshot-time",
+            "2024-01-02T03:04:05Z",
+        ])
+        .expect_err("snapshots only show one version of each object");
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let shadowing = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(cli_args.shadowing())
+        };
+        assert_eq!(shadowing(&[]).unwrap(), ShadowingPolicy::DirectoryWins);
+        assert_eq!(
+            shadowing(&["--shadowing", "directory"]).unwrap(),
+            ShadowingPolicy::DirectoryWins
+        );
+        assert_eq!(shadowing(&["--shadowing", "file"]).unwrap(), ShadowingPolicy::FileWins);
+        assert_eq!(
+            shadowing(&["--shadowing", "suffix-file"]).unwrap(),
+            ShadowingPolicy::SuffixFile
+        );
+        shadowing(&["--shadowing", "both"]).expect_err("invalid policy");
+    }
+
+    #[test]
+    fn test_archived_objects() {
+        let archived_objects = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+            Ok::<_, clap::Error>(config.archived_objects)
+        };
+        assert_eq!(archived_objects(&[]).unwrap(), ArchivedObjectsPolicy::Unreadable);
+        assert_eq!(
+            archived_objects(&["--archived-objects", "hidden"]).unwrap(),
+            ArchivedObjectsPolicy::Hidden
+        );
+        assert_eq!(
+            archived_objects(&["--archived-objects", "fail-reads"]).unwrap(),
+            ArchivedObjectsPolicy::FailReads
+        );
+        assert_eq!(
+            archived_objects(&["--archived-objects", "restore-status"]).unwrap(),
+            ArchivedObjectsPolicy::RestoreStatus
+        );
+        archived_objects(&["--archived-objects", "visible"]).expect_err("invalid policy");
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--case-insensitive"]).unwrap();
+        assert!(cli_args.case_insensitive);
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--case-insensitive",
+            "--snapshot-time",
+            "2024-01-01T00:00:00Z",
+        ])
+        .expect_err("case-insensitive lookups can't be combined with snapshots");
+    }
+
+    #[test]
+    fn test_decompress_files() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(!cli_args.decompress_files);
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--decompress-files"]).unwrap();
+        assert!(cli_args.decompress_files);
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--decompress-files",
+            "--snapshot-time",
+            "2024-01-01T00:00:00Z",
+        ])
+        .expect_err("decompressed files can't be combined with snapshots");
+    }
+
+    #[test]
+    fn test_recent_writes_window() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert_eq!(cli_args.recent_writes_window(), Duration::ZERO);
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--recent-writes-window", "30"]).unwrap();
+        assert_eq!(cli_args.recent_writes_window(), Duration::from_secs(30));
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--recent-writes-window", "0"])
+            .expect_err("the window can't be zero");
+    }
+
+    #[test]
+    fn test_directory_rename() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(cli_args.directory_rename().is_none());
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--allow-directory-rename"]).unwrap();
+        assert_eq!(cli_args.directory_rename().unwrap().max_objects, 1000);
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-directory-rename",
+            "--max-directory-rename-objects",
+            "50000",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.directory_rename().unwrap().max_objects, 50000);
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-directory-rename-objects",
+            "50000",
+        ])
+        .expect_err("the limit requires directory renames");
+    }
+
+    #[test]
+    fn test_statfs_capacity() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert_eq!(cli_args.statfs_capacity(), StatfsCapacity::Unlimited);
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--statfs-capacity",
+            "1024",
+            "--statfs-free",
+            "256",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli_args.statfs_capacity(),
+            StatfsCapacity::Fixed {
+                total: 1024 * 1024 * 1024,
+                free: 256 * 1024 * 1024,
+            }
+        );
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--statfs-capacity", "1024"]).unwrap();
+        assert_eq!(
+            cli_args.statfs_capacity(),
+            StatfsCapacity::Fixed {
+                total: 1024 * 1024 * 1024,
+                free: 1024 * 1024 * 1024,
+            }
+        );
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--cache",
+            "/tmp/cache",
+            "--statfs-from-cache",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli_args.statfs_capacity(),
+            StatfsCapacity::Directory("/tmp/cache".into())
+        );
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--statfs-from-cache"])
+            .expect_err("statfs from the cache requires a cache");
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--statfs-free", "256"])
+            .expect_err("the free space requires a capacity");
+    }
+
+    #[test]
+    fn test_recursive_delete() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--allow-delete"]).unwrap();
+        assert!(cli_args.recursive_delete().is_none());
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-delete",
+            "--allow-recursive-delete",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.recursive_delete().unwrap().max_objects, 10000);
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-delete",
+            "--allow-recursive-delete",
+            "--max-recursive-delete-objects",
+            "100",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.recursive_delete().unwrap().max_objects, 100);
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--allow-recursive-delete"])
+            .expect_err("recursive deletes require deletes");
     }
-}
 
-impl ValueEnum for BucketType {
-    fn value_variants<'a>() -> &'a [Self] {
-        &[Self::GeneralPurpose, Self::Directory]
+    #[test]
+    fn test_trash() {
+        let runtime = Runtime::new(futures::executor::ThreadPool::builder().pool_size(1).create().unwrap());
+        let trash = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, anyhow::Error>(cli_args.trash(&runtime).is_some())
+        };
+        assert!(!trash(&["--allow-delete"]).unwrap());
+        assert!(trash(&["--allow-delete", "--trash-prefix", ".trash/"]).unwrap());
+        assert!(
+            trash(&[
+                "--allow-delete",
+                "--trash-prefix",
+                ".trash/",
+                "--trash-retention",
+                "86400"
+            ])
+            .unwrap()
+        );
+        trash(&["--trash-prefix", ".trash/"]).expect_err("the trash requires deletes");
+        trash(&["--allow-delete", "--trash-prefix", ".trash"]).expect_err("the prefix must end in '/'");
+        trash(&["--allow-delete", "--trash-prefix", ""]).expect_err("the prefix must not be empty");
+        trash(&["--allow-delete", "--trash-retention", "86400"]).expect_err("the retention requires a trash");
+        trash(&[
+            "--allow-delete",
+            "--allow-recursive-delete",
+            "--trash-prefix",
+            ".trash/",
+        ])
+        .expect_err("recursive deletes don't copy objects to the trash");
     }
 
-    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
-        match self {
-            Self::GeneralPurpose => Some(clap::builder::PossibleValue::new("general-purpose")),
-            Self::Directory => Some(clap::builder::PossibleValue::new("directory")),
-        }
+    #[test]
+    fn test_allow_append() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--allow-append"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.append_by_copy);
+        assert!(!config.incremental_upload);
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-append",
+            "--incremental-upload",
+        ])
+        .expect_err("incremental uploads already support appends");
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub enum UploadChecksums {
-    Crc32c,
-    Off,
-}
+    #[test]
+    fn test_allow_write_after_fsync() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.write_after_fsync);
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--allow-write-after-fsync"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.write_after_fsync);
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-write-after-fsync",
+            "--incremental-upload",
+        ])
+        .expect_err("incremental uploads already allow writes after fsync");
+    }
 
-impl ValueEnum for UploadChecksums {
-    fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Crc32c, Self::Off]
+    #[test]
+    fn test_conditional_overwrite() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.conditional_overwrite);
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--allow-overwrite",
+            "--conditional-overwrite",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.conditional_overwrite);
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--conditional-overwrite"])
+            .expect_err("conditional overwrites require overwrites");
     }
 
-    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
-        match self {
-            Self::Crc32c => Some(clap::builder::PossibleValue::new("crc32c")),
-            Self::Off => Some(clap::builder::PossibleValue::new("off")),
-        }
+    #[test]
+    fn test_write_staging_dir() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.write_staging_dir.is_none());
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--write-staging-dir",
+            "/tmp/spool",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.write_staging_dir, Some(PathBuf::from("/tmp/spool")));
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--write-staging-dir",
+            "/tmp/spool",
+            "--incremental-upload",
+        ])
+        .expect_err("incremental uploads can't be staged");
     }
-}
 
-impl CliArgs {
-    fn addressing_style(&self) -> AddressingStyle {
-        if self.force_path_style {
-            AddressingStyle::Path
-        } else {
-            AddressingStyle::Automatic
-        }
+    #[test]
+    fn test_write_quota() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert_eq!(cli_args.max_file_size().unwrap(), None);
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_write_bytes, None);
+
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-file-size",
+            "1073741824",
+            "--max-write-bytes-per-mount",
+            "10737418240",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.max_file_size().unwrap(), Some(1024 * 1024 * 1024));
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_write_bytes, Some(10 * 1024 * 1024 * 1024));
+
+        // 10000 parts of 8 MiB
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--max-file-size", "83886080001"]).unwrap();
+        cli_args
+            .max_file_size()
+            .expect_err("files can't be larger than the largest upload");
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-file-size",
+            "83886080001",
+            "--write-part-size",
+            "16777216",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.max_file_size().unwrap(), Some(83886080001));
     }
 
-    pub fn s3_path(&self) -> anyhow::Result<S3Path> {
-        let prefix = self.prefix.as_ref().cloned().unwrap_or_default();
-        let s3path = match self.bucket_name.clone() {
-            BucketNameOrS3Uri::S3Uri(s3uri) => {
-                if prefix.as_str().is_empty() {
-                    s3uri
-                } else {
-                    return Err(anyhow!("explicit prefix option not allowed with S3 URI"));
-                }
-            }
-            BucketNameOrS3Uri::BucketName(bucket_name) => S3Path::new(bucket_name, prefix),
-        };
-        Ok(s3path)
+    #[test]
+    fn test_keep_page_cache() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.keep_page_cache);
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--keep-page-cache"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.keep_page_cache);
     }
 
-    fn mem_limit(&self) -> u64 {
-        let mut mem_limit = MINIMUM_MEM_LIMIT;
+    #[test]
+    fn test_direct_io_xattr() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.direct_io_xattr);
 
-        let sys = System::new_with_specifics(RefreshKind::everything());
-        let default_mem_target = (sys.total_memory() as f64 * 0.95) as u64;
-        mem_limit = mem_limit.max(default_mem_target);
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--direct-io-xattr"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.direct_io_xattr);
+    }
 
-        #[cfg(feature = "mem_limiter")]
-        if let Some(max_mem_target) = self.max_memory_target {
-            mem_limit = max_mem_target * 1024 * 1024;
-        }
+    #[test]
+    fn test_decode_content_encoding() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.decode_content_encoding);
 
-        mem_limit
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--decode-content-encoding"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.decode_content_encoding);
     }
 
-    fn should_use_upload_checksum(&self, s3_personality: S3Personality) -> bool {
-        // Written in this awkward way to force us to update it if we add new checksum types
-        match self.upload_checksums {
-            Some(UploadChecksums::Crc32c) => true,
-            Some(UploadChecksums::Off) => false,
-            None => {
-                // Default to true if supported
-                if s3_personality.supports_additional_checksums() {
-                    true
-                } else {
-                    tracing::info!("disabling upload checksums because target S3 personality does not support them");
-                    false
-                }
-            }
+    #[test]
+    fn test_presigned_url_xattr() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.presigned_url_xattr, None);
+
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--presigned-url-xattr", "3600"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.presigned_url_xattr, Some(Duration::from_secs(3600)));
+
+        for expiry in ["0", "604801"] {
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--presigned-url-xattr", expiry])
+                .expect_err("pre-signed URLs expire after 1 second to 7 days");
         }
     }
 
-    pub fn filesystem_config(&self, sse: ServerSideEncryption, s3_personality: S3Personality) -> S3FilesystemConfig {
-        let mut filesystem_config = S3FilesystemConfig::default();
-        if let Some(uid) = self.uid {
-            filesystem_config.uid = uid;
-        }
-        if let Some(gid) = self.gid {
-            filesystem_config.gid = gid;
-        }
-        if let Some(dir_mode) = self.dir_mode {
-            filesystem_config.dir_mode = dir_mode;
-        }
-        if let Some(file_mode) = self.file_mode {
-            filesystem_config.file_mode = file_mode;
-        }
-        filesystem_config.storage_class = self.storage_class.clone();
-        filesystem_config.allow_delete = self.allow_delete;
-        filesystem_config.allow_overwrite = self.allow_overwrite;
-        filesystem_config.incremental_upload = self.incremental_upload;
-        filesystem_config.s3_personality = s3_personality;
-        filesystem_config.server_side_encryption = sse;
-        filesystem_config.cache_config = self.cache_config();
-        filesystem_config.mem_limit = self.mem_limit();
-        filesystem_config.use_upload_checksums = self.should_use_upload_checksum(s3_personality);
-        filesystem_config
+    #[test]
+    fn test_recursive_size_xattr() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.recursive_size_xattr);
+
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--recursive-size-xattr"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.recursive_size_xattr);
     }
 
-    pub fn cache_block_size_in_bytes(&self) -> u64 {
-        #[cfg(feature = "block_size")]
-        if let Some(kib) = self.cache_block_size {
-            return kib * 1024;
+    #[test]
+    fn test_upload_checksums() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.upload_checksum_algorithm, Some(ChecksumAlgorithm::Crc32c));
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Outposts);
+        assert_eq!(config.upload_checksum_algorithm, None);
+
+        for (value, expected) in [
+            ("crc32", Some(ChecksumAlgorithm::Crc32)),
+            ("sha1", Some(ChecksumAlgorithm::Sha1)),
+            ("sha256", Some(ChecksumAlgorithm::Sha256)),
+            ("off", None),
+        ] {
+            let cli_args =
+                CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--upload-checksums", value]).unwrap();
+            let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+            assert_eq!(config.upload_checksum_algorithm, expected);
         }
-        1024 * 1024 // 1 MiB block size - default for disk cache and for express cache
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--upload-checksums", "md5"])
+            .expect_err("only the algorithms of additional checksums are supported");
     }
 
-    fn cache_config(&self) -> CacheConfig {
-        let mut metadata_cache_ttl = self.metadata_ttl.unwrap_or_else(|| {
-            if self.cache.is_some() || self.cache_xz.is_some() {
-                // When the data cache is enabled, use 1min as metadata-ttl.
-                TimeToLive::Duration(Duration::from_secs(60))
-            } else {
-                TimeToLive::Minimal
-            }
-        });
-        if matches!(metadata_cache_ttl, TimeToLive::Duration(Duration::ZERO)) {
-            const ZERO_TTL_WARNING: &str = "The '--metadata-ttl 0' setting is no longer supported, is now interpreted as 'minimal', and will be removed in a future release. Use '--metadata-ttl minimal' instead";
-            tracing::warn!("{}", ZERO_TTL_WARNING);
-            if !self.foreground {
-                // Ensure warning is visible even when not redirecting logs to stdout.
-                use owo_colors::{OwoColorize, Stream::Stderr, Style};
-                eprintln!(
-                    "{}: {}",
-                    "warning".if_supports_color(Stderr, |text| text.style(Style::new().yellow().bold())),
-                    ZERO_TTL_WARNING
-                );
-            }
-            metadata_cache_ttl = TimeToLive::Minimal;
-        }
-        tracing::trace!("using metadata TTL setting {metadata_cache_ttl:?}");
-        let mut cache_config = CacheConfig::new(metadata_cache_ttl);
-        if let Some(negative_cache_ttl) = self.negative_metadata_ttl {
-            cache_config = cache_config.with_negative_metadata_ttl(negative_cache_ttl);
-        }
-        cache_config
+    #[test]
+    fn test_upload_concurrency() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_concurrent_uploads, None);
+        assert_eq!(config.max_concurrent_parts_per_upload, None);
+
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-concurrent-uploads",
+            "4",
+            "--max-concurrent-parts-per-upload",
+            "2",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_concurrent_uploads, Some(4));
+        assert_eq!(config.max_concurrent_parts_per_upload, Some(2));
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--max-concurrent-uploads", "0"])
+            .expect_err("at least one upload must be allowed");
     }
 
-    fn cache_express_bucket_name(&self) -> Option<S3Path> {
-        let bucket_name = self.cache_xz.to_owned()?;
-        let s3path = match bucket_name {
-            BucketNameOrS3Uri::BucketName(bucket_name) => S3Path::new(bucket_name, Prefix::empty()),
-            BucketNameOrS3Uri::S3Uri(s3_uri) => s3_uri,
+    #[test]
+    fn test_deferred_uploads() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--write-staging-dir",
+            "/tmp/spool",
+            "--write-staging-max-size",
+            "1024",
+            "--defer-uploads",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.write_staging_max_size, Some(1024 * 1024 * 1024));
+        assert!(config.defer_staged_uploads);
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--defer-uploads"])
+            .expect_err("only staged uploads can be deferred");
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--write-staging-max-size",
+            "1024",
+        ])
+        .expect_err("the limit requires a staging directory");
+    }
+
+    #[test]
+    fn test_writeback_cache() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--write-staging-dir",
+            "/tmp/spool",
+            "--writeback-cache",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.writeback_cache);
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--writeback-cache"])
+            .expect_err("only staged writes are cached");
+    }
+
+    #[test]
+    fn test_key_filter() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--include",
+            "*.parquet",
+            "--exclude",
+            "tmp/",
+            "--exclude",
+            "*_$folder$",
+        ])
+        .unwrap();
+        let filter = cli_args.key_filter();
+        assert!(filter.is_visible("year=2024/data.parquet"));
+        assert!(!filter.is_visible("year=2024/_SUCCESS"));
+        assert!(!filter.is_visible("year=2024/tmp/"));
+        assert!(filter.is_visible("year=2024/"));
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(cli_args.key_filter().is_empty());
+    }
+
+    #[test]
+    fn test_escape_names() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--escape-names"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.escape_names);
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--prefix", "100%25/"]).unwrap();
+        assert_eq!(cli_args.s3_path().unwrap().prefix.as_str(), "100%25/");
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--escape-names",
+            "--prefix",
+            "100%25/",
+        ])
+        .unwrap();
+        assert_eq!(cli_args.s3_path().unwrap().prefix.as_str(), "100%25/");
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--escape-names",
+            "--prefix",
+            "100%/",
+        ])
+        .unwrap();
+        cli_args.s3_path().expect_err("the prefix must be escaped");
+    }
+
+    #[test]
+    fn test_key_delimiter() {
+        let key_delimiter = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+            Ok::<_, anyhow::Error>(config.key_delimiter)
         };
-        Some(s3path)
+        assert!(key_delimiter(&[]).unwrap().is_default());
+        assert_eq!(
+            key_delimiter(&["--key-delimiter", "|"]).unwrap(),
+            KeyDelimiter::new("|").unwrap()
+        );
+        key_delimiter(&["--key-delimiter", ""]).expect_err("the delimiter is a character");
+        key_delimiter(&["--key-delimiter", "||"]).expect_err("the delimiter is a single character");
+        key_delimiter(&[
+            "--read-only",
+            "--key-delimiter",
+            "|",
+            "--inventory",
+            "s3://inventory-bucket/bucket/config/",
+        ])
+        .expect_err("inventory reports are split at '/'");
     }
 
-    fn express_data_cache_config(&self, sse: ServerSideEncryption) -> anyhow::Result<Option<ExpressDataCacheConfig>> {
-        match self.cache_express_bucket_name() {
-            Some(express_path) => {
-                if !express_path.prefix.as_str().is_empty() {
-                    return Err(anyhow!("cache-xz argument must not contain a prefix"));
-                }
-                let bucket_name = &self.s3_path()?.bucket;
-                let express_bucket_name = express_path.bucket.as_str();
-                let config = ExpressDataCacheConfig::new(express_bucket_name, bucket_name)
-                    .block_size(self.cache_block_size_in_bytes())
-                    .sse(sse);
-                Ok(Some(config))
-            }
-            None => Ok(None),
-        }
+    #[test]
+    fn test_overlayfs_lower() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--read-only",
+            "--overlayfs-lower",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.overlayfs_lower);
+        let s3_path = cli_args.s3_path().unwrap();
+        assert!(
+            cli_args.inode_table(&s3_path).unwrap().is_some(),
+            "inode numbers are kept stable"
+        );
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--overlayfs-lower"])
+            .expect_err("lower layers are read-only");
     }
 
-    fn disk_data_cache_config(&self) -> Option<DiskDataCacheConfig> {
-        let path = self.cache.as_ref()?;
-        let cache_limit = match self.max_cache_size {
-            // Fallback to no data cache.
-            Some(0) => return None,
-            Some(max_size_in_mib) => CacheLimit::TotalSize {
-                max_size: (max_size_in_mib * 1024 * 1024) as usize,
-            },
-            None => CacheLimit::default(),
+    #[test]
+    fn test_atime() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--atime"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.atime);
+        assert_eq!(config.atime_flush_interval, None);
+        let s3_path = cli_args.s3_path().unwrap();
+        assert!(
+            cli_args.inode_table(&s3_path).unwrap().is_some(),
+            "inode numbers are kept stable"
+        );
+
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--atime",
+            "--mtime-metadata",
+            "--atime-flush-interval",
+            "3600",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.atime_flush_interval, Some(Duration::from_secs(3600)));
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--atime-flush-interval", "3600"])
+            .expect_err("requires --atime");
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--atime",
+            "--atime-flush-interval",
+            "3600",
+        ])
+        .expect_err("flushing access times would lose modification times without --mtime-metadata");
+    }
+
+    #[test]
+    fn test_inode_table() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("inodes");
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--inode-table",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let s3_path = cli_args.s3_path().unwrap();
+        let inode_table = cli_args.inode_table(&s3_path).unwrap();
+        assert!(inode_table.is_some());
+        cli_args
+            .inode_table(&s3_path)
+            .expect_err("the inode table is used by another mount");
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(cli_args.inode_table(&s3_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prefix_overrides() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("overrides.json");
+        let prefix_overrides = |json: &str| {
+            std::fs::write(&path, json).unwrap();
+            let cli_args = CliArgs::try_parse_from([
+                "mount-s3",
+                "bucket",
+                "test/location",
+                "--prefix",
+                "prefix/",
+                "--prefix-overrides",
+                path.to_str().unwrap(),
+            ])
+            .unwrap();
+            cli_args.prefix_overrides(&cli_args.s3_path().unwrap())
         };
-        let cache_config = DiskDataCacheConfig {
-            cache_directory: path.clone(),
-            block_size: self.cache_block_size_in_bytes(),
-            limit: cache_limit,
+        let overrides = prefix_overrides(r#"{ "archive/": { "read-only": true } }"#).unwrap();
+        assert!(overrides.is_read_only("archive/a.txt"));
+        assert!(overrides.for_object_key("prefix/archive/a.txt").is_some());
+        prefix_overrides(r#"{ "archive": { "read-only": true } }"#).expect_err("prefixes end in '/'");
+        prefix_overrides("[]").expect_err("overrides are a JSON object");
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(
+            cli_args
+                .prefix_overrides(&cli_args.s3_path().unwrap())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[cfg(feature = "manifest")]
+    #[test]
+    fn test_metadata_manifest() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("manifest.csv");
+        std::fs::write(&path, r#""key1","""etag1""",1024,kr9zjg=="#).unwrap();
+        let manifest = |checksum: &str| {
+            let cli_args = CliArgs::try_parse_from([
+                "mount-s3",
+                "bucket",
+                "test/location",
+                "--metadata-manifest",
+                path.to_str().unwrap(),
+                "--metadata-manifest-checksum",
+                checksum,
+            ])?;
+            Ok::<_, anyhow::Error>(cli_args.metadata_manifest(&cli_args.s3_path()?)?.is_some())
         };
-        Some(cache_config)
+        assert!(manifest("kr9zjg==").unwrap());
+        manifest("AAAAAA==").expect_err("the checksum of the manifest does not match");
+        manifest("not a checksum").expect_err("the checksum must be base64-encoded");
+
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--metadata-manifest",
+            path.to_str().unwrap(),
+        ])
+        .expect_err("the checksum of the manifest is required");
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(
+            cli_args
+                .metadata_manifest(&cli_args.s3_path().unwrap())
+                .unwrap()
+                .is_none()
+        );
     }
 
-    pub fn data_cache_config(&self, sse: ServerSideEncryption) -> anyhow::Result<DataCacheConfig> {
-        let disk_cache_config = self.disk_data_cache_config();
-        let express_cache_config = self.express_data_cache_config(sse)?;
-        match (&disk_cache_config, &express_cache_config) {
-            (None, Some(_)) => {
-                tracing::trace!("using S3 Express One Zone bucket as a cache for object content");
-            }
-            (Some(_), None) => {
-                tracing::trace!("using local disk as a cache for object content");
-            }
-            (Some(_), Some(_)) => {
-                tracing::trace!("using both local disk and S3 Express One Zone bucket as a cache for object content");
-            }
-            _ => {
-                tracing::trace!("using no cache");
-            }
-        }
-        Ok(DataCacheConfig {
-            disk_cache_config,
-            express_cache_config,
-        })
+    #[test]
+    fn test_metadata_ttl_rule() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--metadata-ttl-rule",
+            "static-data/**=1h",
+            "--metadata-ttl-rule",
+            "*.json=indefinite",
+        ])
+        .unwrap();
+        let cache_config = cli_args.cache_config();
+        assert_eq!(
+            cache_config.ttl_for(InodeKind::File, "static-data/a"),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            cache_config.ttl_for(InodeKind::File, "a.json"),
+            TimeToLive::INDEFINITE_DURATION
+        );
+        assert_eq!(cache_config.ttl_for(InodeKind::File, "a.bin"), cache_config.file_ttl);
+        assert!(cache_config.serve_lookup_from_cache_for("static-data"));
+        assert!(!cache_config.serve_lookup_from_cache_for("other-data"));
+
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--metadata-ttl-rule",
+            "static-data/**",
+        ])
+        .expect_err("rules require a TTL");
     }
 
-    /// The server-side encryption configuration.
-    ///
-    /// Disallow specifying `--sse-kms-key-id` when `--sse=AES256` as this is not allowed by the S3 API.
-    pub fn server_side_encryption(&self) -> anyhow::Result<ServerSideEncryption> {
-        if self.sse_kms_key_id.is_some() && self.sse.as_deref() == Some("AES256") {
-            return Err(anyhow!("--sse-kms-key-id can not be used with --sse AES256"));
+    #[test]
+    fn test_object_tags() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--object-tag",
+            "team=data",
+            "--object-tag",
+            "expires=",
+        ])
+        .unwrap();
+        let expected = ObjectTags::from([
+            ("team".to_owned(), "data".to_owned()),
+            ("expires".to_owned(), String::new()),
+        ]);
+        assert_eq!(cli_args.object_tags().unwrap(), expected);
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--object-tag", "team"])
+            .expect_err("tags require a value");
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--object-tag",
+            "team=data",
+            "--object-tag",
+            "team=storage",
+        ])
+        .unwrap();
+        cli_args.object_tags().expect_err("tags can't be repeated");
+        let mut args = vec!["mount-s3".to_owned(), "bucket".to_owned(), "test/location".to_owned()];
+        for i in 0..11 {
+            args.push("--object-tag".to_owned());
+            args.push(format!("tag{i}=value"));
         }
-        Ok(ServerSideEncryption::new(self.sse.clone(), self.sse_kms_key_id.clone()))
+        let cli_args = CliArgs::try_parse_from(args).unwrap();
+        cli_args.object_tags().expect_err("objects can have at most 10 tags");
     }
 
-    /// Generates a logging configuration based on the CLI arguments.
-    ///
-    /// This includes random string generation which can change with each invocation,
-    /// so once created the [LoggingConfig] should be cloned if another owned copy is required.
-    pub fn make_logging_config(&self) -> LoggingConfig {
-        let default_filter = if self.no_log {
-            String::from("off")
-        } else {
-            let mut filter = if self.debug {
-                String::from("debug")
-            } else {
-                format!("warn,{MOUNTPOINT_LOG_TARGET}=info")
-            };
-            let crt_verbosity = if self.debug_crt { "debug" } else { "off" };
-            filter.push_str(&format!(",{AWSCRT_LOG_TARGET}={crt_verbosity}"));
-            // Only turn off metrics if neither --log-metrics nor --debug is set.
-            // In debug mode, we want to see all available information including metrics.
-            if !self.log_metrics && !self.debug {
-                filter.push_str(&format!(",{}=off", metrics::TARGET_NAME));
-            }
-            filter
-        };
+    #[test]
+    fn test_content_types() {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        assert!(cli_args.content_types().is_none());
+
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--detect-content-type",
+            "--content-type-mapping",
+            ".ckpt=application/x-checkpoint",
+            "--sniff-content-type",
+        ])
+        .unwrap();
+        let content_types = cli_args.content_types().unwrap();
+        assert_eq!(content_types.for_key("data/a.csv"), Some("text/csv"));
+        assert_eq!(content_types.for_key("data/a.ckpt"), Some("application/x-checkpoint"));
+        assert!(content_types.sniffs());
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--sniff-content-type"])
+            .expect_err("sniffing requires --detect-content-type");
+        CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--detect-content-type",
+            "--content-type-mapping",
+            "ckpt",
+        ])
+        .expect_err("mappings require a content type");
+    }
 
-        let log_file = self.log_directory.as_ref().map(|dir| prepare_log_file_name(dir));
+    #[test]
+    fn test_storage_class_rule() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--prefix",
+            "data/",
+            "--storage-class",
+            "STANDARD",
+            "--storage-class-rule",
+            "logs/**=STANDARD_IA",
+            "--storage-class-rule",
+            "*.ckpt=INTELLIGENT_TIERING",
+        ])
+        .unwrap();
+        let rules = cli_args.storage_class_rules(&cli_args.s3_path().unwrap());
+        assert_eq!(rules.for_object_key("data/logs/a.log"), Some("STANDARD_IA"));
+        assert_eq!(rules.for_object_key("data/a.ckpt"), Some("INTELLIGENT_TIERING"));
+        assert_eq!(rules.for_object_key("data/a.bin"), None);
+        assert_eq!(rules.for_object_key("logs/a.log"), None);
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--storage-class-rule", "logs/**"])
+            .expect_err("rules require a storage class");
+    }
 
-        LoggingConfig {
-            log_file,
-            log_to_stdout: self.foreground,
-            default_filter,
-        }
+    #[test]
+    fn test_cache_watermarks() {
+        let cache_limit = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(format!("{:?}", data_cache_config.disk_cache_config.unwrap().limit))
+        };
+        let watermarks = ["--cache-low-watermark", "10", "--cache-high-watermark", "20"];
+        assert_eq!(
+            cache_limit(&[&["--cache", "/tmp/cache"], watermarks.as_slice()].concat()).unwrap(),
+            "Watermarks { low_ratio: 0.1, high_ratio: 0.2, max_size: None }"
+        );
+        assert_eq!(
+            cache_limit(
+                &[
+                    &["--cache", "/tmp/cache", "--max-cache-size", "1"],
+                    watermarks.as_slice()
+                ]
+                .concat()
+            )
+            .unwrap(),
+            "Watermarks { low_ratio: 0.1, high_ratio: 0.2, max_size: Some(1048576) }"
+        );
+        cache_limit(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-low-watermark",
+            "20",
+            "--cache-high-watermark",
+            "10",
+        ])
+        .expect_err("the high watermark must be greater than the low one");
+        cache_limit(&["--cache", "/tmp/cache", "--cache-low-watermark", "10"])
+            .expect_err("the low watermark requires a high watermark");
+        cache_limit(&["--cache", "/tmp/cache", "--cache-high-watermark", "10"])
+            .expect_err("the high watermark requires a low watermark");
+        cache_limit(&watermarks).expect_err("watermarks require a cache directory");
+    }
+
+    #[test]
+    fn test_cache_compression() {
+        let compression_level = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.disk_cache_config.unwrap().compression_level)
+        };
+        assert_eq!(compression_level(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            compression_level(&["--cache", "/tmp/cache", "--cache-compression"]).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            compression_level(&[
+                "--cache",
+                "/tmp/cache",
+                "--cache-compression",
+                "--cache-compression-level",
+                "9"
+            ])
+            .unwrap(),
+            Some(9)
+        );
+        compression_level(&["--cache", "/tmp/cache", "--cache-compression-level", "9"])
+            .expect_err("the compression level requires compression");
+        compression_level(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-compression",
+            "--cache-compression-level",
+            "23",
+        ])
+        .expect_err("the compression level must be supported by zstd");
     }
 
-    /// Human-readable description of the bucket being mounted
-    pub fn bucket_description(&self) -> anyhow::Result<String> {
-        let s3_path = self.s3_path()?;
-        Ok(s3_path.bucket_description())
+    #[test]
+    fn test_cache_io_backend() {
+        let io_backend = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.disk_cache_config.unwrap().io_backend)
+        };
+        assert_eq!(io_backend(&["--cache", "/tmp/cache"]).unwrap(), DiskIoBackend::Sync);
+        assert_eq!(
+            io_backend(&["--cache", "/tmp/cache", "--cache-io-uring"]).unwrap(),
+            DiskIoBackend::IoUring { direct: false }
+        );
+        assert_eq!(
+            io_backend(&["--cache", "/tmp/cache", "--cache-io-uring", "--cache-direct-io"]).unwrap(),
+            DiskIoBackend::IoUring { direct: true }
+        );
+        io_backend(&["--cache-io-uring"]).expect_err("io_uring requires a cache directory");
+        io_backend(&["--cache", "/tmp/cache", "--cache-direct-io"]).expect_err("direct I/O requires io_uring");
     }
 
-    fn clone_fd_from_env(&self) -> bool {
-        std::env::var("UNSTABLE_MOUNTPOINT_CLONE_FUSE_FD")
-            .map(|v| v.to_lowercase() == "true")
-            .unwrap_or(false)
+    #[test]
+    fn test_cache_account() {
+        let account = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.disk_cache_config.unwrap().account)
+        };
+        assert_eq!(account(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            account(&["--cache", "/tmp/cache", "--shared-cache"]).unwrap(),
+            Some(CacheAccount {
+                name: "bucket".to_owned(),
+                quota: None,
+            })
+        );
+        assert_eq!(
+            account(&["--cache", "/tmp/cache", "--shared-cache", "--prefix", "data/"]).unwrap(),
+            Some(CacheAccount {
+                name: "bucket/data".to_owned(),
+                quota: None,
+            })
+        );
+        assert_eq!(
+            account(&[
+                "--cache",
+                "/tmp/cache",
+                "--shared-cache",
+                "--cache-account",
+                "training",
+                "--cache-quota",
+                "1024",
+            ])
+            .unwrap(),
+            Some(CacheAccount {
+                name: "training".to_owned(),
+                quota: Some(1024 * 1024 * 1024),
+            })
+        );
+        account(&["--cache", "/tmp/cache", "--cache-quota", "1024"]).expect_err("quotas require a shared cache");
+        account(&["--cache", "/tmp/cache", "--cache-account", "training"])
+            .expect_err("accounts require a shared cache");
+        account(&["--cache", "/tmp/cache", "--shared-cache", "--cache-quota", "0"]).expect_err("quotas can't be 0");
     }
 
-    pub fn fuse_session_config(&self) -> anyhow::Result<FuseSessionConfig> {
-        let mount_point = MountPoint::new(&self.mount_point).context("Failed to create mount point")?;
-        let fuse_options = FuseOptions {
-            read_only: self.read_only,
-            auto_unmount: self.auto_unmount,
-            allow_root: self.allow_root,
-            allow_other: self.allow_other,
-            clone_fd: self.clone_fd_from_env(),
+    #[test]
+    fn test_cache_peers() {
+        let peer_cache_config = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.peer_cache_config)
         };
-        FuseSessionConfig::new(mount_point, fuse_options, self.max_threads as usize)
+        assert!(peer_cache_config(&["--cache", "/tmp/cache"]).unwrap().is_none());
+        let config = peer_cache_config(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-block-size",
+            "64",
+            "--cache-peers",
+            "10.0.0.1:7000,10.0.0.2:7000",
+            "--cache-peer-address",
+            "10.0.0.1:7000",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.address, "10.0.0.1:7000".parse().unwrap());
+        assert_eq!(
+            config.peers,
+            ["10.0.0.1:7000".parse().unwrap(), "10.0.0.2:7000".parse().unwrap()]
+        );
+        assert_eq!(config.block_size, 64 * 1024);
+        peer_cache_config(&["--cache", "/tmp/cache", "--cache-peers", "10.0.0.1:7000"])
+            .expect_err("peers require the address of this mount");
+        peer_cache_config(&[
+            "--cache-peers",
+            "10.0.0.1:7000",
+            "--cache-peer-address",
+            "10.0.0.1:7000",
+        ])
+        .expect_err("peers require a cache directory");
+        peer_cache_config(&["--cache", "/tmp/cache", "--cache-peer-address", "10.0.0.1:7000"])
+            .expect_err("the address of this mount requires peers");
+        peer_cache_config(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-peers",
+            "host",
+            "--cache-peer-address",
+            "host",
+        ])
+        .expect_err("peers are IP addresses and ports");
     }
 
-    fn user_agent(&self, instance_info: &InstanceInfo, version: &str) -> UserAgent {
-        let user_agent_prefix = if let Some(custom_prefix) = &self.user_agent_prefix {
-            format!("{custom_prefix} mountpoint-s3/{version}")
-        } else {
-            format!("mountpoint-s3/{version}")
+    #[test]
+    fn test_cache_encryption() {
+        let encryption_key = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            let data_cache_config = cli_args.data_cache_config(ServerSideEncryption::default())?;
+            Ok::<_, anyhow::Error>(data_cache_config.disk_cache_config.unwrap().encryption_key)
         };
-        let mut user_agent = UserAgent::new_with_instance_info(Some(user_agent_prefix), instance_info);
-        if self.read_only {
-            user_agent.value("mp-readonly");
-        }
-        if self.is_fstab {
-            user_agent.value("mp-fstab");
-        }
-        match (&self.cache, self.cache_express_bucket_name()) {
-            (None, None) => (),
-            (None, Some(_)) => {
-                user_agent.key_value("mp-cache", "shared");
-            }
-            (Some(_), None) => {
-                user_agent.key_value("mp-cache", "local");
-            }
-            (Some(_), Some(_)) => {
-                user_agent.key_values("mp-cache", &["shared", "local"]);
-            }
-        }
-        if let Some(ttl) = self.metadata_ttl {
-            user_agent.key_value("mp-cache-ttl", &ttl.to_string());
-        }
-        if let Some(interfaces) = &self.bind {
-            user_agent.key_value("mp-nw-interfaces", &interfaces.len().to_string());
-        }
-        if self.otlp_endpoint.is_some() {
-            user_agent.value("mp-otlp");
-        }
-        user_agent
+        assert_eq!(encryption_key(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert!(
+            encryption_key(&["--cache", "/tmp/cache", "--cache-encryption"])
+                .unwrap()
+                .is_some()
+        );
+
+        let key_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(key_file.path(), "01".repeat(32)).unwrap();
+        let key_path = key_file.path().to_str().unwrap();
+        assert_eq!(
+            encryption_key(&["--cache", "/tmp/cache", "--cache-encryption-key-file", key_path]).unwrap(),
+            Some(CacheEncryptionKey::from_file(key_path).unwrap())
+        );
+        encryption_key(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-encryption-key-file",
+            "/nonexistent/key",
+        ])
+        .expect_err("the key file must exist");
+        encryption_key(&["--cache", "/tmp/cache", "--cache-encryption", "--persist-cache"])
+            .expect_err("ephemeral keys can't read a persisted cache");
+        encryption_key(&[
+            "--cache",
+            "/tmp/cache",
+            "--cache-encryption-key-file",
+            key_path,
+            "--persist-cache",
+        ])
+        .unwrap();
+        encryption_key(&["--cache-encryption"]).expect_err("encryption requires a cache directory");
     }
 
-    fn throughput_target_gbps(&self, instance_info: &InstanceInfo) -> TargetThroughputSetting {
-        let throughput_target_gbps = self.maximum_throughput_gbps.map(|t| TargetThroughputSetting::User { gbps: t as f64 }).unwrap_or_else(|| {
-            match autoconfigure::network_throughput(instance_info) {
-                Ok(throughput) => TargetThroughputSetting::Instance { gbps: throughput },
-                Err(e) => {
-                    tracing::warn!(
-                        "failed to detect network throughput. Using {} gbps as throughput. \
-                        Use --maximum-throughput-gbps CLI flag to configure a target throughput appropriate for the instance. Detection failed due to: {:?}",
-                        TargetThroughputSetting::DEFAULT_TARGET_THROUGHPUT_GBPS,
-                        e,
-                        );
-                    TargetThroughputSetting::Default
-                }
-            }
-        });
-        tracing::info!("target network throughput {} Gbps", throughput_target_gbps.value());
-        throughput_target_gbps
+    #[test]
+    fn test_cache_scrub_interval() {
+        let scrub_interval = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .scrub_interval,
+            )
+        };
+        assert_eq!(scrub_interval(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            scrub_interval(&["--cache", "/tmp/cache", "--cache-scrub-interval", "3600"]).unwrap(),
+            Some(Duration::from_secs(3600))
+        );
+        scrub_interval(&["--cache-scrub-interval", "3600"]).expect_err("scrubbing requires a cache directory");
     }
 
-    pub fn personality(&self) -> Option<S3Personality> {
-        self.bucket_type.map(|bucket_type| bucket_type.to_personality())
+    #[test]
+    fn test_cache_write_through() {
+        let write_through = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .write_through_config,
+            )
+        };
+        assert_eq!(write_through(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            write_through(&["--cache", "/tmp/cache", "--cache-write-through"]).unwrap(),
+            Some(WriteThroughConfig {
+                max_object_size: 64 * 1024 * 1024,
+                shared: false,
+            })
+        );
+        assert_eq!(
+            write_through(&[
+                "--cache-xz",
+                "amzn-s3-demo-bucket--usw2-az1--x-s3",
+                "--cache-write-through-shared"
+            ])
+            .unwrap(),
+            Some(WriteThroughConfig {
+                max_object_size: 64 * 1024 * 1024,
+                shared: true,
+            })
+        );
+        assert_eq!(
+            write_through(&[
+                "--cache",
+                "/tmp/cache",
+                "--cache-write-through",
+                "--cache-write-through-max-size",
+                "8"
+            ])
+            .unwrap(),
+            Some(WriteThroughConfig {
+                max_object_size: 8 * 1024 * 1024,
+                shared: false,
+            })
+        );
+        write_through(&["--cache-write-through"]).expect_err("write-through caching requires a cache directory");
+        write_through(&["--cache", "/tmp/cache", "--cache-write-through-shared"])
+            .expect_err("write-through caching to the shared cache requires a shared cache");
     }
 
-    fn part_config(&self) -> PartConfig {
-        PartConfig::with_read_write_sizes(
-            self.read_part_size.unwrap_or(self.part_size) as usize,
-            self.write_part_size.unwrap_or(self.part_size) as usize,
-        )
+    #[test]
+    fn test_shared_cache() {
+        let shared = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .disk_cache_config
+                    .map(|config| config.shared),
+            )
+        };
+        assert_eq!(shared(&["--cache", "/tmp/cache"]).unwrap(), Some(false));
+        assert_eq!(
+            shared(&["--cache", "/tmp/cache", "--shared-cache"]).unwrap(),
+            Some(true)
+        );
+        shared(&["--shared-cache"]).expect_err("sharing requires a cache directory");
     }
 
-    fn auth_config(&self) -> S3ClientAuthConfig {
-        if self.no_sign_request {
-            S3ClientAuthConfig::NoSigning
-        } else if let Some(profile_name) = self.profile.clone() {
-            S3ClientAuthConfig::Profile(profile_name)
-        } else {
-            S3ClientAuthConfig::Default
-        }
+    #[test]
+    fn test_cache_xz_max_object_size() {
+        let max_object_size = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .express_cache_config
+                    .map(|config| config.max_object_size),
+            )
+        };
+        let express_bucket = "amzn-s3-demo-bucket--usw2-az1--x-s3";
+        assert_eq!(
+            max_object_size(&["--cache-xz", express_bucket]).unwrap(),
+            Some(1024 * 1024)
+        );
+        assert_eq!(
+            max_object_size(&["--cache-xz", express_bucket, "--cache-xz-max-object-size", "8192"]).unwrap(),
+            Some(8 * 1024 * 1024)
+        );
+        max_object_size(&["--cache-xz-max-object-size", "8192"]).expect_err("the size requires an express cache");
     }
 
-    pub fn client_config(&self, version: &str) -> ClientConfig {
-        let instance_info = InstanceInfo::new();
-        let user_agent = self.user_agent(&instance_info, version);
-        let throughput_target = self.throughput_target_gbps(&instance_info);
-        let region = autoconfigure::get_region(&instance_info, self.region.clone());
+    #[test]
+    fn test_cache_block_size() {
+        let block_size = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .disk_cache_config
+                    .map(|config| config.block_size),
+            )
+        };
+        assert_eq!(block_size(&["--cache", "/tmp/cache"]).unwrap(), Some(1024 * 1024));
+        assert_eq!(
+            block_size(&["--cache", "/tmp/cache", "--cache-block-size", "64"]).unwrap(),
+            Some(64 * 1024)
+        );
+        block_size(&["--cache-block-size", "64"]).expect_err("the block size requires a cache");
+        block_size(&["--cache", "/tmp/cache", "--cache-block-size", "0"]).expect_err("the block size can't be 0");
+    }
 
-        ClientConfig {
-            region,
-            endpoint_url: self.endpoint_url.clone(),
-            addressing_style: self.addressing_style(),
-            dual_stack: self.dual_stack,
-            transfer_acceleration: self.transfer_acceleration,
-            auth_config: self.auth_config(),
-            requester_pays: self.requester_pays,
-            expected_bucket_owner: self.expected_bucket_owner.clone(),
-            throughput_target,
-            bind: self.bind.clone(),
-            part_config: self.part_config(),
-            user_agent,
-        }
+    #[test]
+    fn test_cache_metrics_prefix_depth() {
+        let prefix_metrics_config = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .prefix_metrics_config,
+            )
+        };
+        assert_eq!(prefix_metrics_config(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            prefix_metrics_config(&["--cache", "/tmp/cache", "--cache-metrics-prefix-depth", "2"]).unwrap(),
+            Some(PrefixMetricsConfig {
+                depth: 2,
+                mounted_prefix: String::new(),
+            })
+        );
+        assert_eq!(
+            prefix_metrics_config(&[
+                "--cache",
+                "/tmp/cache",
+                "--cache-metrics-prefix-depth",
+                "1",
+                "--prefix",
+                "datasets/"
+            ])
+            .unwrap(),
+            Some(PrefixMetricsConfig {
+                depth: 1,
+                mounted_prefix: "datasets/".to_owned(),
+            })
+        );
+        prefix_metrics_config(&["--cache-metrics-prefix-depth", "1"]).expect_err("the metrics require a cache");
     }
-}
 
-fn parse_perm_bits(perm_bit_str: &str) -> Result<u16, anyhow::Error> {
-    let perm = u16::from_str_radix(perm_bit_str, 8).map_err(|_| anyhow!("must be a valid octal number"))?;
-    if perm > 0o777 {
-        Err(anyhow!("only user/group/other permissions are supported"))
-    } else {
-        Ok(perm)
+    #[test]
+    fn test_memory_cache_size() {
+        let memory_cache_config = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .memory_cache_config,
+            )
+        };
+        assert_eq!(memory_cache_config(&["--cache", "/tmp/cache"]).unwrap(), None);
+        assert_eq!(
+            memory_cache_config(&["--cache", "/tmp/cache", "--memory-cache-size", "64"]).unwrap(),
+            Some(MemoryCacheConfig {
+                max_size: 64 * 1024 * 1024
+            })
+        );
+        memory_cache_config(&["--memory-cache-size", "64"]).expect_err("the memory tier requires a cache directory");
+        memory_cache_config(&["--cache", "/tmp/cache", "--memory-cache-size", "0"]).expect_err("the size can't be 0");
     }
-}
 
-/// Validate a kms-key-id CLI parameter. Currently, Mountpoint only supports KMS Key ARNs.
-fn parse_kms_key_arn(kms_key_arn: &str) -> anyhow::Result<String> {
-    if kms_key_arn.starts_with("arn:") && kms_key_arn.contains(":key") {
-        Ok(kms_key_arn.to_owned())
-    } else {
-        Err(anyhow!(
-            "KMS Key ARN is only accepted as a key identifier, Key Alias ARN is not accepted"
-        ))
+    #[test]
+    fn test_persist_cache() {
+        let persistent = |args: &[&str]| {
+            let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"].iter().chain(args))?;
+            Ok::<_, clap::Error>(
+                cli_args
+                    .data_cache_config(ServerSideEncryption::default())
+                    .unwrap()
+                    .disk_cache_config
+                    .map(|config| config.persistent),
+            )
+        };
+        assert_eq!(persistent(&["--cache", "/tmp/cache"]).unwrap(), Some(false));
+        assert_eq!(
+            persistent(&["--cache", "/tmp/cache", "--persist-cache"]).unwrap(),
+            Some(true)
+        );
+        persistent(&["--persist-cache"]).expect_err("persistence requires a cache directory");
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum BucketNameOrS3Uri {
-    BucketName(Bucket),
-    S3Uri(S3Path),
-}
+    #[test]
+    fn test_max_read_part_size() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-read-part-size",
+            "67108864",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.prefetcher_config.max_read_part_size, Some(64 * 1024 * 1024));
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.prefetcher_config.max_read_part_size, None);
+    }
 
-fn parse_bucket_name_or_s3_uri(bucket_name_or_uri: &str) -> Result<BucketNameOrS3Uri, S3PathError> {
-    if bucket_name_or_uri.starts_with("s3://") {
-        Ok(BucketNameOrS3Uri::S3Uri(S3Path::parse_s3_uri(bucket_name_or_uri)?))
-    } else {
-        Ok(BucketNameOrS3Uri::BucketName(Bucket::new(
-            bucket_name_or_uri.to_owned(),
-        )?))
+    #[test]
+    fn test_prefetch_memory_budget() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--prefetch-memory-budget",
+            "1073741824",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.prefetcher_config.memory_budget, Some(1024 * 1024 * 1024));
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.prefetcher_config.memory_budget, None);
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--prefetch-memory-budget", "0"])
+            .expect_err("the memory budget can't be zero");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test_case::test_case;
+    #[test]
+    fn test_format_aware_prefetch() {
+        let cli_args =
+            CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--format-aware-prefetch"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(config.prefetcher_config.format_aware);
 
-    #[test_case("s3://bucket--eun1-az1--x-s3", true; "s3:// example bucket")]
-    #[test_case("s3://bucket--eun1-az1--x-s3/", true; "s3:// example bucket with empty prefix")]
-    #[test_case("bucket--eun1-az1--x-s3", true; "raw example bucket")]
-    #[test_case("bucket--eun1-az1--x-s3/", false; "raw example bucket with /")]
-    #[test_case("s3://bucket--eun1-az1--x-s3/prefix", false; "s3:// example bucket with invalid prefix")]
-    #[test_case("s3://bucket--eun1-az1--x-s3/prefix/", false; "s3:// example bucket with prefix (prefixes not allowed)")]
-    fn validate_s3_express_cache_bucket_name(bucket_name: &str, valid: bool) {
-        fn _parse_cli_args(bucket_name: &str) -> Option<()> {
-            let cli_args =
-                CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--cache-xz", bucket_name]).ok()?;
-            let sse = cli_args.server_side_encryption().unwrap();
-            let _express_data_cache_config = cli_args.express_data_cache_config(sse).ok()??;
-            Some(())
-        }
-        assert_eq!(
-            valid,
-            _parse_cli_args(bucket_name).is_some(),
-            "bucket_name: {bucket_name}, expected: {valid}"
-        );
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert!(!config.prefetcher_config.format_aware);
     }
 
-    #[test_case("s3://bucket", Ok(("bucket".to_string(), "".to_string())); "s3:// example bucket")]
-    #[test_case("s3://bucket/", Ok(("bucket".to_string(), "".to_string())); "s3:// example bucket with empty prefix")]
-    #[test_case("bucket", Ok(("bucket".to_string(), "".to_string())); "raw example bucket")]
-    #[test_case("arn:aws:s3::00000000:accesspoint/s3-bucket-test.mrap", Ok(("arn:aws:s3::00000000:accesspoint/s3-bucket-test.mrap".to_string(), "".to_string())); "ARN example")]
-    #[test_case("arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name", Ok(("arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name".to_string(), "".to_string())); "S3 outpost accesspoint ARN")]
-    #[test_case("s3://arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name", Err("the bucket must have a valid name (only letters, numbers, . and -). ARNs are not supported in s3:// URIs".to_string()); "S3 URI with ARN without trailing slash")]
-    #[test_case("s3://arn:aws:s3-outposts:us-east-1:555555555555:outpost/outpost-id/accesspoint/accesspoint-name/", Err("the bucket must have a valid name (only letters, numbers, . and -). ARNs are not supported in s3:// URIs".to_string()); "S3 URI with ARN")]
-    #[test_case("s3://bucket/prefix/", Ok(("bucket".to_string(), "prefix/".to_string())); "s3:// example bucket with prefix")]
-    #[test_case("s3://bucket/prefix", Err("invalid bucket prefix".to_string()); "s3:// example bucket with prefix with missing delimeter")]
-    fn validate_bucket_path(bucket_name: &str, expected_path: Result<(String, String), String>) {
-        fn get_err(bucket_name: &str) -> Result<(String, String), anyhow::Error> {
-            let cli_args = CliArgs::try_parse_from(["mount-s3", bucket_name, "test/location"])?;
-            cli_args
-                .s3_path()
-                .map(|path| (path.bucket.to_string(), path.prefix.as_str().to_string()))
-        }
-        let s3_path = get_err(bucket_name);
-        match (s3_path, expected_path) {
-            (Ok(s3_path), Ok(expected_path)) => {
-                assert_eq!(
-                    expected_path, s3_path,
-                    "bucket_name: {s3_path:?}, expected: {expected_path:?}",
-                );
+    #[test_case("99", Some(99.0); "integer")]
+    #[test_case("99.9", Some(99.9); "fraction")]
+    #[test_case("0", Some(0.0); "zero")]
+    #[test_case("100", Some(100.0); "hundred")]
+    #[test_case("100.1", None; "above 100")]
+    #[test_case("-1", None; "negative")]
+    #[test_case("NaN", None; "not a number")]
+    fn test_hedge_percentile(value: &str, expected: Option<f64>) {
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--hedge-percentile", value]);
+        match expected {
+            Some(percentile) => {
+                let config = cli_args
+                    .unwrap()
+                    .filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+                assert_eq!(config.prefetcher_config.hedge_percentile, Some(percentile));
             }
-            (Err(err), Err(should_contain)) => {
-                assert!(
-                    format!("{err:#}").contains(&should_contain),
-                    "Actual error `{err:#}` does not contain string {should_contain:?}"
-                )
-            }
-            (s3_path, expected_path) => {
-                panic!("bucket_name: {s3_path:?}, expected: {expected_path:?}");
+            None => {
+                cli_args.expect_err("the percentile should be rejected");
             }
         }
     }
 
-    // https://docs.aws.amazon.com/kms/latest/developerguide/concepts.html#key-id
-    #[test_case("arn:aws:kms:eu-west-1:151381207180:key/dabe1478-fe48-47ca-b6f8-ca044b643a82", true; "KMS Key ARN")]
-    #[test_case("arn:aws:kms:us-west-2:111122223333:key/mrk-1234abcd12ab34cd56ef1234567890ab", true; "Multi-region KMS Key ARN")]
-    #[test_case("", false; "empty")]
-    #[test_case("1234abcd-12ab-34cd-56ef-1234567890ab", false; "KMS Key ID")]
-    #[test_case("mrk-1234abcd12ab34cd56ef1234567890ab", false; "Multi-region KMS Key ID")]
-    #[test_case("alias/ExampleAlias", false; "KMS Key alias name")]
-    #[test_case("arn:aws:kms:us-west-2:111122223333:alias/ExampleAlias", false; "KMS Key alias ARN")]
-    fn test_parse_kms_key_arn(kms_key_id: &str, valid: bool) {
-        let parsed = parse_kms_key_arn(kms_key_id);
-        if valid {
-            assert_eq!(parsed.expect("valid kms key identifier"), kms_key_id);
-        } else {
-            parsed.expect_err("invalid kms key identifier");
-        }
+    #[test]
+    fn test_max_fuse_request_size() {
+        let cli_args = CliArgs::try_parse_from([
+            "mount-s3",
+            "bucket",
+            "test/location",
+            "--max-fuse-request-size",
+            "4194304",
+        ])
+        .unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_fuse_request_size, Some(4 * 1024 * 1024));
+
+        let cli_args = CliArgs::try_parse_from(["mount-s3", "bucket", "test/location"]).unwrap();
+        let config = cli_args.filesystem_config(ServerSideEncryption::default(), S3Personality::Standard);
+        assert_eq!(config.max_fuse_request_size, None);
+
+        CliArgs::try_parse_from(["mount-s3", "bucket", "test/location", "--max-fuse-request-size", "1024"])
+            .expect_err("requests are at least a page");
     }
 }
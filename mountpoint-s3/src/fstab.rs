@@ -59,8 +59,9 @@ impl FsTabCliArgs {
         // "rw" can be automatically added by systemd, which is our default, so we ignore it
         // "nodev" and "nosuid" are the default behaviour for Mountpoint, and aren't normally allowed as CLI arguments.
         // Ignore them to allow users to add them as arguments to avoid systemd's default behaviour of passing in "dev" and "suid"
+        // "noatime" is the default behaviour for Mountpoint, and "atime" enables tracking access times with `--atime`
         !([
-            "auto", "noauto", "user", "nouser", "users", "_netdev", "nofail", "rw", "nodev", "nosuid",
+            "auto", "noauto", "user", "nouser", "users", "_netdev", "nofail", "rw", "nodev", "nosuid", "noatime",
         ]
         .contains(&option)
             || option.starts_with("x-"))
@@ -184,6 +185,8 @@ mod tests {
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "ro"].to_vec(),                    true,  Ok("".to_string()))]
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "rw"].to_vec(),                    true,  Ok("".to_string()))]
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "uid=2,gid=4,debug"].to_vec(),     true,  Ok("".to_string()))]
+    #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "noatime"].to_vec(),               true,  Ok("".to_string()))]
+    #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "atime"].to_vec(),                 true,  Ok("".to_string()))]
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "prefix=foo/bar\\,baz/"].to_vec(), true,  Ok("foo/bar,baz/".to_string()))]
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "--uid=2"].to_vec(),               false, Ok("".to_string()))]
     #[test_case(["_", "demo_s3_bucket",                                                "/mnt/test", "-o", "ro,rw"].to_vec(),                 false, Ok("".to_string()))]
@@ -1,4 +1,6 @@
 mod build_info;
+mod cache_manage;
+mod cache_warm;
 mod cli;
 mod fstab;
 mod run;
@@ -6,6 +8,8 @@ mod run;
 use clap::Parser;
 use std::env;
 
+pub use cache_manage::{CacheManageArgs, manage_cache};
+pub use cache_warm::{CacheWarmArgs, warm_cache};
 pub use cli::CliArgs;
 pub use fstab::FsTabCliArgs;
 pub use run::{create_s3_client, run};
@@ -24,3 +28,26 @@ pub fn parse_cli_args(log_fstab: bool) -> CliArgs {
 
     cli_args.unwrap_or_else(|err| err.exit())
 }
+
+/// Parse the arguments of the `mount-s3 cache ls|stats|evict` commands, or `None` if the command line runs another
+/// command.
+pub fn parse_cache_manage_args() -> Option<CacheManageArgs> {
+    let args: Vec<_> = env::args_os().collect();
+    if !CacheManageArgs::is_cache_manage_command(&args) {
+        return None;
+    }
+    // Parse the arguments after `cache`, as if they were passed to a `mount-s3 cache` program.
+    let args = std::iter::once("mount-s3 cache".into()).chain(args.into_iter().skip(2));
+    Some(CacheManageArgs::try_parse_from(args).unwrap_or_else(|err| err.exit()))
+}
+
+/// Parse the arguments of the `mount-s3 cache warm` command, or `None` if the command line runs another command.
+pub fn parse_cache_warm_args() -> Option<CacheWarmArgs> {
+    let args: Vec<_> = env::args_os().collect();
+    if !CacheWarmArgs::is_cache_warm_command(&args) {
+        return None;
+    }
+    // Parse the arguments after `cache warm`, as if they were passed to a `mount-s3 cache warm` program.
+    let args = std::iter::once("mount-s3 cache warm".into()).chain(args.into_iter().skip(3));
+    Some(CacheWarmArgs::try_parse_from(args).unwrap_or_else(|err| err.exit()))
+}
@@ -1,6 +1,12 @@
-use mountpoint_s3::{create_s3_client, parse_cli_args};
+use mountpoint_s3::{create_s3_client, parse_cache_manage_args, parse_cache_warm_args, parse_cli_args};
 
 fn main() -> anyhow::Result<()> {
+    if let Some(cache_warm_args) = parse_cache_warm_args() {
+        return mountpoint_s3::warm_cache(create_s3_client, cache_warm_args);
+    }
+    if let Some(cache_manage_args) = parse_cache_manage_args() {
+        return mountpoint_s3::manage_cache(cache_manage_args);
+    }
     let cli_args = parse_cli_args(true);
     mountpoint_s3::run(create_s3_client, cli_args)
 }
@@ -12,9 +12,10 @@ use mountpoint_s3_fs::fuse::session::FuseSession;
 use mountpoint_s3_fs::logging::init_logging;
 use mountpoint_s3_fs::memory::PagedPool;
 use mountpoint_s3_fs::metrics::MetricsConfig;
+use mountpoint_s3_fs::prefetch::candidate_part_sizes;
 use mountpoint_s3_fs::s3::config::ClientConfig;
 use mountpoint_s3_fs::s3::{S3Path, S3Personality};
-use mountpoint_s3_fs::{MountpointConfig, Runtime, Superblock, SuperblockConfig, metrics};
+use mountpoint_s3_fs::{MountpointConfig, Runtime, SuperblockConfig, metrics};
 use nix::sys::signal::Signal;
 use nix::unistd::ForkResult;
 
@@ -196,15 +197,7 @@ fn mount(args: CliArgs, client_builder: impl ClientBuilder) -> anyhow::Result<Fu
 
     let client_config = args.client_config(build_info::FULL_VERSION);
 
-    // Set up a paged memory pool
-    let pool = PagedPool::new_with_candidate_sizes([
-        args.cache_block_size_in_bytes() as usize,
-        client_config.part_config.read_size_bytes,
-        client_config.part_config.write_size_bytes,
-    ]);
-    // Schedule trimming of empty memory pages every minutes. We should consider
-    // event-based triggers and/or a configurable interval in the future.
-    pool.schedule_trim(Duration::from_secs(60));
+    let pool = create_memory_pool(&args, &client_config);
 
     let s3_path = args.s3_path()?;
     let (client, runtime, s3_personality) =
@@ -213,7 +206,18 @@ fn mount(args: CliArgs, client_builder: impl ClientBuilder) -> anyhow::Result<Fu
     let bucket_description = args.bucket_description()?;
     tracing::debug!("using S3 personality {s3_personality:?} for {bucket_description}");
 
-    let filesystem_config = args.filesystem_config(sse.clone(), s3_personality);
+    let mut filesystem_config = args.filesystem_config(sse.clone(), s3_personality);
+    let prefix_overrides = args.prefix_overrides(&s3_path)?;
+    // The metadata TTLs of the prefixes take precedence over the rules of the mount.
+    filesystem_config
+        .cache_config
+        .ttl_rules
+        .splice(0..0, prefix_overrides.ttl_rules());
+    filesystem_config.prefix_overrides = prefix_overrides;
+    filesystem_config.storage_class_rules = args.storage_class_rules(&s3_path);
+    filesystem_config.max_file_size = args.max_file_size()?;
+    filesystem_config.object_tags = args.object_tags()?;
+    filesystem_config.content_types = args.content_types();
     let mut data_cache_config = args.data_cache_config(sse)?;
 
     let managed_cache_dir = setup_disk_cache_directory(&mut data_cache_config)?;
@@ -221,17 +225,45 @@ fn mount(args: CliArgs, client_builder: impl ClientBuilder) -> anyhow::Result<Fu
     tracing::debug!(?fuse_session_config, "creating fuse session");
     let mount_point_path = format!("{}", fuse_session_config.mount_point());
 
-    let superblock = Superblock::new(
-        client.clone(),
-        s3_path,
-        SuperblockConfig {
-            cache_config: filesystem_config.cache_config.clone(),
-            s3_personality: filesystem_config.s3_personality,
-        },
-    );
+    #[cfg(feature = "manifest")]
+    if let Some((metablock, store_dir)) = args.metadata_manifest(&s3_path)? {
+        let mut fuse_session = MountpointConfig::new(fuse_session_config, filesystem_config, data_cache_config)
+            .create_fuse_session_with_metablock(metablock, client, runtime, pool)?;
+        tracing::info!(
+            "successfully mounted {} from manifest at {}",
+            bucket_description,
+            mount_point_path
+        );
+
+        fuse_session.run_on_close(Box::new(move || {
+            drop(managed_cache_dir);
+            drop(store_dir);
+        }));
+        return Ok(fuse_session);
+    }
+
+    let inode_table = args.inode_table(&s3_path)?;
+    let snapshot = args.snapshot(&client, &s3_path)?;
+    let superblock_config = SuperblockConfig {
+        shadowing: args.shadowing(),
+        case_insensitive: args.case_insensitive,
+        decompress_files: args.decompress_files,
+        key_filter: args.key_filter(),
+        recent_writes_window: args.recent_writes_window(),
+        rename_by_copy: args.allow_rename,
+        directory_rename: args.directory_rename(),
+        recursive_delete: args.recursive_delete(),
+        batch_deletes: args.batch_deletes,
+        trash: args.trash(&runtime),
+        inode_table,
+        readdir_readahead: args.readdir_readahead(&runtime),
+        inventory: args.inventory(&runtime),
+        snapshot,
+        ..Default::default()
+    };
 
     let mut fuse_session = MountpointConfig::new(fuse_session_config, filesystem_config, data_cache_config)
-        .create_fuse_session(superblock, client, runtime, pool)?;
+        .create_fuse_session(s3_path, superblock_config, client, runtime, pool)?;
     tracing::info!("successfully mounted {} at {}", bucket_description, mount_point_path);
 
     if let Some(managed_cache_dir) = managed_cache_dir {
@@ -242,6 +274,29 @@ fn mount(args: CliArgs, client_builder: impl ClientBuilder) -> anyhow::Result<Fu
     Ok(fuse_session)
 }
 
+/// Set up a paged memory pool, for the buffer sizes used by the client and the cache.
+pub(crate) fn create_memory_pool(args: &CliArgs, client_config: &ClientConfig) -> PagedPool {
+    let mut buffer_sizes = vec![
+        args.cache_block_size_in_bytes() as usize,
+        client_config.part_config.read_size_bytes,
+        client_config.part_config.write_size_bytes,
+    ];
+    if let Some(max_read_part_size) = args.max_read_part_size {
+        // GET requests may use any of the part sizes the prefetcher adapts between.
+        let read_part_size = client_config.part_config.read_size_bytes;
+        buffer_sizes.extend(
+            candidate_part_sizes(read_part_size, max_read_part_size as usize)
+                .into_iter()
+                .skip(1),
+        );
+    }
+    let pool = PagedPool::new_with_candidate_sizes(buffer_sizes);
+    // Schedule trimming of empty memory pages every minutes. We should consider
+    // event-based triggers and/or a configurable interval in the future.
+    pool.schedule_trim(Duration::from_secs(60));
+    pool
+}
+
 /// Builder for [ObjectClient] implementations.
 pub trait ClientBuilder {
     type Client: ObjectClient + Clone + Send + Sync + 'static;
@@ -297,11 +352,21 @@ fn setup_disk_cache_directory(cache_config: &mut DataCacheConfig) -> anyhow::Res
         return Ok(None);
     };
     let cache_key = env_unstable_cache_key();
-    let managed_cache_dir = ManagedCacheDir::new_from_parent_with_cache_key(
-        &disk_cache_config.cache_directory,
-        cache_key.as_deref(),
-        should_cleanup_cache_dir(),
-    )
+    // The content of a persistent cache is validated as it is read, rather than removed.
+    let should_cleanup = !disk_cache_config.persistent && should_cleanup_cache_dir();
+    let managed_cache_dir = if disk_cache_config.shared {
+        ManagedCacheDir::new_shared_from_parent_with_cache_key(
+            &disk_cache_config.cache_directory,
+            cache_key.as_deref(),
+            should_cleanup,
+        )
+    } else {
+        ManagedCacheDir::new_from_parent_with_cache_key(
+            &disk_cache_config.cache_directory,
+            cache_key.as_deref(),
+            should_cleanup,
+        )
+    }
     .context("failed to create cache directory")?;
     disk_cache_config.cache_directory = managed_cache_dir.as_path_buf();
     Ok(Some(managed_cache_dir))
@@ -321,7 +386,7 @@ fn should_cleanup_cache_dir() -> bool {
     !disable_cleanup
 }
 
-fn env_unstable_cache_key() -> Option<OsString> {
+pub(crate) fn env_unstable_cache_key() -> Option<OsString> {
     env::var_os("UNSTABLE_MOUNTPOINT_CACHE_KEY")
 }
 